@@ -0,0 +1,204 @@
+//! Python bindings for image2
+//!
+//! Images are exposed as 32-bit float RGB, which is the common denominator for most research
+//! code. Pixel data can be viewed as a numpy array without copying using `Image.array`
+//!
+//! The bindings are only compiled with the `python` feature enabled, which is set by
+//! `pyproject.toml` when building with maturin
+
+#![cfg(feature = "python")]
+
+use image2::{filter, Image, Kernel, Pipeline, Rgb};
+use numpy::{PyArray3, PyReadonlyArray3};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+
+fn to_py_err(err: image2::Error) -> PyErr {
+    PyIOError::new_err(err.to_string())
+}
+
+/// Python wrapper around `Image<f32, Rgb>`
+#[pyclass(name = "Image")]
+pub struct PyImage {
+    inner: Image<f32, Rgb>,
+}
+
+#[pymethods]
+impl PyImage {
+    /// Create a new, black image
+    #[new]
+    fn new(width: usize, height: usize) -> Self {
+        PyImage {
+            inner: Image::new((width, height)),
+        }
+    }
+
+    /// Read an image from disk
+    #[staticmethod]
+    fn open(path: &str) -> PyResult<Self> {
+        let inner = Image::open(path).map_err(to_py_err)?;
+        Ok(PyImage { inner })
+    }
+
+    /// Copy a `(height, width, 3)` float32 numpy array into a new image
+    #[staticmethod]
+    fn from_numpy(array: PyReadonlyArray3<f32>) -> PyResult<Self> {
+        let shape = array.shape();
+        if shape[2] != 3 {
+            return Err(PyValueError::new_err("expected an array with 3 channels"));
+        }
+
+        let data = array.as_array().iter().copied().collect::<Vec<_>>();
+        let inner = Image::new_with_data((shape[1], shape[0]), data).map_err(to_py_err)?;
+        Ok(PyImage { inner })
+    }
+
+    /// Write an image to disk
+    fn save(&self, path: &str) -> PyResult<()> {
+        self.inner.save(path).map_err(to_py_err)
+    }
+
+    /// Image width
+    #[getter]
+    fn width(&self) -> usize {
+        self.inner.width()
+    }
+
+    /// Image height
+    #[getter]
+    fn height(&self) -> usize {
+        self.inner.height()
+    }
+
+    /// Returns (height, width, channels), matching numpy ordering
+    #[getter]
+    fn shape(&self) -> (usize, usize, usize) {
+        let (w, h, c) = self.inner.shape();
+        (h, w, c)
+    }
+
+    /// Zero-copy numpy view of the pixel data, the view keeps the image alive
+    #[getter]
+    fn array<'py>(slf: &'py PyCell<Self>) -> PyResult<&'py PyArray3<f32>> {
+        let image = slf.borrow();
+        let (w, h, c) = image.inner.shape();
+        let view = ndarray::ArrayView3::from_shape((h, w, c), image.inner.data())
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        // Only hand out a borrowed view when the buffer is exactly a packed, C-ordered
+        // `(height, width, channels)` float32 array, otherwise fall back to a copy
+        if !view.is_standard_layout() || view.len() != image.inner.data().len() {
+            return Ok(PyArray3::from_array(slf.py(), &view));
+        }
+
+        // Safety: the layout was checked above and the array borrows `slf`, so the underlying
+        // buffer outlives the view
+        Ok(unsafe { PyArray3::borrow_from_array(&view, slf) })
+    }
+
+    /// Resize an image
+    fn resize(&self, width: usize, height: usize) -> Self {
+        PyImage {
+            inner: self.inner.resize((width, height)),
+        }
+    }
+}
+
+/// Python wrapper around `Pipeline<f32, Rgb>`, each method appends a filter and returns the
+/// pipeline so calls can be chained
+#[pyclass(name = "Pipeline", unsendable)]
+pub struct PyPipeline {
+    inner: Pipeline<f32, Rgb>,
+    len: usize,
+}
+
+impl PyPipeline {
+    fn push(&mut self, f: impl 'static + image2::Filter<f32, Rgb>) {
+        self.inner.push(f);
+        self.len += 1;
+    }
+}
+
+#[pymethods]
+impl PyPipeline {
+    /// Create a new, empty pipeline
+    #[new]
+    fn new() -> Self {
+        PyPipeline {
+            inner: Pipeline::new(),
+            len: 0,
+        }
+    }
+
+    /// Adjust brightness
+    fn brightness(mut slf: PyRefMut<Self>, amount: f64) -> PyRefMut<Self> {
+        slf.push(filter::brightness(amount));
+        slf
+    }
+
+    /// Adjust contrast
+    fn contrast(mut slf: PyRefMut<Self>, amount: f64) -> PyRefMut<Self> {
+        slf.push(filter::contrast(amount));
+        slf
+    }
+
+    /// Adjust exposure by the given number of stops
+    fn exposure(mut slf: PyRefMut<Self>, stops: f64) -> PyRefMut<Self> {
+        slf.push(filter::exposure(stops));
+        slf
+    }
+
+    /// Adjust saturation
+    fn saturation(mut slf: PyRefMut<Self>, amount: f64) -> PyRefMut<Self> {
+        slf.push(filter::saturation(amount));
+        slf
+    }
+
+    /// Invert the image
+    fn invert(mut slf: PyRefMut<Self>) -> PyRefMut<Self> {
+        slf.push(filter::invert());
+        slf
+    }
+
+    /// Clamp values between 0 and 1
+    fn clamp(mut slf: PyRefMut<Self>) -> PyRefMut<Self> {
+        slf.push(filter::clamp());
+        slf
+    }
+
+    /// Gaussian blur with an `n`x`n` kernel
+    fn gaussian_blur(mut slf: PyRefMut<Self>, n: usize, std: f64) -> PyResult<PyRefMut<Self>> {
+        if n % 2 == 0 {
+            return Err(PyValueError::new_err("kernel size must be odd"));
+        }
+        slf.push(Kernel::gaussian(n, std));
+        Ok(slf)
+    }
+
+    /// Sobel edge detection
+    fn sobel(mut slf: PyRefMut<Self>) -> PyRefMut<Self> {
+        slf.push(Kernel::sobel());
+        slf
+    }
+
+    /// Run the pipeline, returning a new image
+    fn execute(&self, image: &PyImage) -> PyImage {
+        if self.len == 0 {
+            return PyImage {
+                inner: image.inner.clone(),
+            };
+        }
+
+        let mut dest = image.inner.new_like();
+        self.inner.execute(&[&image.inner], &mut dest);
+        PyImage { inner: dest }
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "image2")]
+fn image2_py(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyImage>()?;
+    m.add_class::<PyPipeline>()?;
+    Ok(())
+}
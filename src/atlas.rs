@@ -0,0 +1,308 @@
+use crate::*;
+
+/// Algorithm used to place images on a page
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Packing {
+    /// Keep track of the top edge of the placed images and put each image as low as possible,
+    /// this works well for images with similar heights
+    #[default]
+    Skyline,
+
+    /// Split the free space into rectangles after each placement and put each image in the
+    /// rectangle it fits best, this works well for images with mixed sizes
+    Guillotine,
+}
+
+/// Location of an image in an atlas
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Placement {
+    /// Index of the page containing the image
+    pub page: usize,
+
+    /// Region of the page covered by the image, this doesn't include the bleed
+    pub region: Region,
+}
+
+impl Placement {
+    /// Normalized texture coordinates of the image as `[u0, v0, u1, v1]`
+    pub fn uv(&self, page_size: Size) -> [f64; 4] {
+        let (w, h) = (page_size.width as f64, page_size.height as f64);
+        [
+            self.region.min_x() as f64 / w,
+            self.region.min_y() as f64 / h,
+            self.region.max_x() as f64 / w,
+            self.region.max_y() as f64 / h,
+        ]
+    }
+}
+
+/// Packed atlas pages, see `AtlasBuilder`
+#[derive(Clone)]
+pub struct Atlas<T: Type, C: Color> {
+    /// Atlas pages, each one is the size passed to `AtlasBuilder::new`
+    pub pages: Vec<Image<T, C>>,
+
+    /// Placement of each input image, in the same order as the input
+    pub placements: Vec<Placement>,
+}
+
+impl<T: Type, C: Color> Atlas<T, C> {
+    /// Normalized texture coordinates of the image at `index`, see `Placement::uv`
+    pub fn uv(&self, index: usize) -> [f64; 4] {
+        let placement = &self.placements[index];
+        placement.uv(self.pages[placement.page].size())
+    }
+}
+
+/// Skyline packer, the skyline is stored as `(x, y, width)` segments from left to right
+struct Skyline {
+    size: Size,
+    segments: Vec<(usize, usize, usize)>,
+}
+
+impl Skyline {
+    fn insert(&mut self, w: usize, h: usize) -> Option<(usize, usize)> {
+        let mut best: Option<(usize, usize, usize)> = None;
+        for i in 0..self.segments.len() {
+            let x = self.segments[i].0;
+            if x + w > self.size.width {
+                break;
+            }
+
+            // The image rests on the highest segment below it
+            let mut y = 0;
+            let mut covered = 0;
+            for segment in &self.segments[i..] {
+                if covered >= w {
+                    break;
+                }
+                y = y.max(segment.1);
+                covered += segment.2;
+            }
+
+            if y + h <= self.size.height && best.is_none_or(|b| (y + h, x) < (b.0, b.1)) {
+                best = Some((y + h, x, y));
+            }
+        }
+
+        let (top, x, y) = best?;
+        let mut segments = Vec::with_capacity(self.segments.len() + 2);
+        for &(sx, sy, sw) in &self.segments {
+            if sx < x {
+                segments.push((sx, sy, sw.min(x - sx)));
+            }
+        }
+        segments.push((x, top, w));
+        for &(sx, sy, sw) in &self.segments {
+            let start = sx.max(x + w);
+            if sx + sw > start {
+                segments.push((start, sy, sx + sw - start));
+            }
+        }
+        segments.dedup_by(|next, prev| {
+            if next.1 == prev.1 {
+                prev.2 += next.2;
+                true
+            } else {
+                false
+            }
+        });
+        self.segments = segments;
+        Some((x, y))
+    }
+}
+
+/// Guillotine packer, free space is stored as `(x, y, width, height)` rectangles
+struct Guillotine {
+    free: Vec<(usize, usize, usize, usize)>,
+}
+
+impl Guillotine {
+    fn insert(&mut self, w: usize, h: usize) -> Option<(usize, usize)> {
+        // Best short side fit
+        let index = (0..self.free.len())
+            .filter(|i| self.free[*i].2 >= w && self.free[*i].3 >= h)
+            .min_by_key(|i| {
+                let (dw, dh) = (self.free[*i].2 - w, self.free[*i].3 - h);
+                (dw.min(dh), dw.max(dh))
+            })?;
+
+        // Split along the shorter leftover axis so the larger leftover stays in one piece
+        let (x, y, fw, fh) = self.free.swap_remove(index);
+        let (dw, dh) = (fw - w, fh - h);
+        let (right, bottom) = if dw < dh {
+            ((x + w, y, dw, h), (x, y + h, fw, dh))
+        } else {
+            ((x + w, y, dw, fh), (x, y + h, w, dh))
+        };
+        self.free
+            .extend([right, bottom].into_iter().filter(|r| r.2 > 0 && r.3 > 0));
+        Some((x, y))
+    }
+}
+
+enum Page {
+    Skyline(Skyline),
+    Guillotine(Guillotine),
+}
+
+impl Page {
+    fn new(packing: Packing, size: Size) -> Page {
+        match packing {
+            Packing::Skyline => Page::Skyline(Skyline {
+                size,
+                segments: vec![(0, 0, size.width)],
+            }),
+            Packing::Guillotine => Page::Guillotine(Guillotine {
+                free: vec![(0, 0, size.width, size.height)],
+            }),
+        }
+    }
+
+    fn insert(&mut self, w: usize, h: usize) -> Option<(usize, usize)> {
+        match self {
+            Page::Skyline(p) => p.insert(w, h),
+            Page::Guillotine(p) => p.insert(w, h),
+        }
+    }
+}
+
+/// Packs images into one or more atlas pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AtlasBuilder {
+    /// Size of each page
+    pub page_size: Size,
+
+    /// Minimum number of empty pixels between neighboring images, including their bleed
+    pub padding: usize,
+
+    /// Number of pixels the edges of each image are extended by, this avoids sampling
+    /// neighboring images or empty space when filtering
+    pub bleed: usize,
+
+    /// Packing algorithm
+    pub packing: Packing,
+}
+
+impl AtlasBuilder {
+    /// Create a new `AtlasBuilder` with the given page size
+    pub fn new(page_size: impl Into<Size>) -> AtlasBuilder {
+        AtlasBuilder {
+            page_size: page_size.into(),
+            padding: 0,
+            bleed: 0,
+            packing: Packing::default(),
+        }
+    }
+
+    /// Set padding
+    pub fn with_padding(mut self, padding: usize) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Set bleed
+    pub fn with_bleed(mut self, bleed: usize) -> Self {
+        self.bleed = bleed;
+        self
+    }
+
+    /// Set packing algorithm
+    pub fn with_packing(mut self, packing: Packing) -> Self {
+        self.packing = packing;
+        self
+    }
+
+    /// Compute placements for images with the given sizes without copying any image data, new
+    /// pages are added as needed. Returns an error when an image doesn't fit on a page
+    pub fn pack(&self, sizes: &[Size]) -> Result<Vec<Placement>, Error> {
+        // Padding is only needed between images so the packer can use the padding of the last
+        // row and column of images outside of the page
+        let bounds = Size::new(
+            self.page_size.width + self.padding,
+            self.page_size.height + self.padding,
+        );
+        let extra = 2 * self.bleed + self.padding;
+
+        // Place large images first
+        let mut order: Vec<usize> = (0..sizes.len()).collect();
+        order.sort_by_key(|i| std::cmp::Reverse((sizes[*i].height, sizes[*i].width)));
+
+        let mut pages: Vec<Page> = Vec::new();
+        let mut placements = vec![
+            Placement {
+                page: 0,
+                region: Region::default(),
+            };
+            sizes.len()
+        ];
+        for i in order {
+            let size = sizes[i];
+            if size.width == 0 || size.height == 0 {
+                continue;
+            }
+
+            let (w, h) = (size.width + extra, size.height + extra);
+            if w > bounds.width || h > bounds.height {
+                return Err(Error::Message(format!(
+                    "image {} ({}x{}) doesn't fit on a {}x{} atlas page",
+                    i, size.width, size.height, self.page_size.width, self.page_size.height
+                )));
+            }
+
+            let found = pages
+                .iter_mut()
+                .enumerate()
+                .find_map(|(page, p)| p.insert(w, h).map(|pt| (page, pt)));
+            let (page, (x, y)) = match found {
+                Some(found) => found,
+                None => {
+                    let mut p = Page::new(self.packing, bounds);
+                    let pt = p.insert(w, h).expect("image fits on an empty page");
+                    pages.push(p);
+                    (pages.len() - 1, pt)
+                }
+            };
+
+            placements[i] = Placement {
+                page,
+                region: Region::new(Point::new(x + self.bleed, y + self.bleed), size),
+            };
+        }
+        Ok(placements)
+    }
+
+    /// Pack `images` into atlas pages, the area around each image is filled by repeating its
+    /// edge pixels `bleed` times and the remaining space is left empty
+    pub fn build<T: Type, C: Color>(&self, images: &[&Image<T, C>]) -> Result<Atlas<T, C>, Error> {
+        let sizes: Vec<Size> = images.iter().map(|image| image.size()).collect();
+        let placements = self.pack(&sizes)?;
+
+        let num_pages = placements.iter().map(|p| p.page + 1).max().unwrap_or(0);
+        let mut pages: Vec<Image<T, C>> =
+            (0..num_pages).map(|_| Image::new(self.page_size)).collect();
+        for (image, placement) in images.iter().zip(&placements) {
+            let (w, h) = (image.width(), image.height());
+            if w == 0 || h == 0 {
+                continue;
+            }
+
+            let page = &mut pages[placement.page];
+            let x = placement.region.min_x() - self.bleed;
+            let y = placement.region.min_y() - self.bleed;
+            for dy in 0..h + 2 * self.bleed {
+                let sy = dy.saturating_sub(self.bleed).min(h - 1);
+                for dx in 0..w + 2 * self.bleed {
+                    let sx = dx.saturating_sub(self.bleed).min(w - 1);
+                    page.get_mut((x + dx, y + dy))
+                        .copy_from_slice(image.get((sx, sy)).as_slice());
+                }
+            }
+        }
+
+        Ok(Atlas { pages, placements })
+    }
+}
@@ -0,0 +1,556 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::*;
+
+/// A single processing step, steps are written as `name` or `name(arg, ...)` and separated by
+/// `|` in the text form of a `Recipe`. Each step names one of the filters in `filter`, or a
+/// convolution kernel from `kernel::presets`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Step {
+    /// `brightness(amt)`
+    Brightness(f64),
+
+    /// `exposure(stops)`
+    Exposure(f64),
+
+    /// `contrast(amt)`
+    Contrast(f64),
+
+    /// `saturation(amt)`
+    Saturation(f64),
+
+    /// `hue_rotate(degrees)`
+    HueRotate(f64),
+
+    /// `gamma_lin(gamma)`
+    GammaLin(f64),
+
+    /// `gamma_log(gamma)`
+    GammaLog(f64),
+
+    /// `srgb_lin`
+    SrgbLin,
+
+    /// `srgb_log`
+    SrgbLog,
+
+    /// `invert`
+    Invert,
+
+    /// `clamp`
+    Clamp,
+
+    /// `gaussian_blur(sigma)`
+    GaussianBlur(f64),
+
+    /// `box_blur(radius)`
+    BoxBlur(usize),
+
+    /// `kernel(name)`, a kernel registered in `kernel::presets`
+    Kernel(String),
+
+    /// `resize(width, height)`
+    Resize(usize, usize),
+}
+
+impl Step {
+    /// Append the filter for this step to `pipeline`, returns an error for unknown kernels.
+    /// `resize` changes the image size so it can't be part of a pipeline, see `Recipe::apply`
+    pub fn push_to<T: Type, C: Color>(&self, pipeline: &mut Pipeline<T, C>) -> Result<(), Error> {
+        match *self {
+            Step::Brightness(x) => pipeline.push(filter::Brightness(x)),
+            Step::Exposure(x) => pipeline.push(filter::Exposure(x)),
+            Step::Contrast(x) => pipeline.push(filter::Contrast(x)),
+            Step::Saturation(x) => pipeline.push(filter::Saturation(x, filter::HueModel::Hsv)),
+            Step::HueRotate(x) => pipeline.push(filter::HueRotate(x, filter::HueModel::Hsv)),
+            Step::GammaLin(x) => pipeline.push(filter::GammaLin(x)),
+            Step::GammaLog(x) => pipeline.push(filter::GammaLog(x)),
+            Step::SrgbLin => pipeline.push(filter::SrgbLin),
+            Step::SrgbLog => pipeline.push(filter::SrgbLog),
+            Step::Invert => pipeline.push(filter::Invert),
+            Step::Clamp => pipeline.push(filter::Clamp),
+            Step::GaussianBlur(x) => pipeline.push(filter::GaussianBlur(x)),
+            Step::BoxBlur(x) => pipeline.push(filter::BoxBlur::new(x)),
+            Step::Kernel(ref name) => pipeline.push(kernel_preset(name)?),
+            Step::Resize(..) => {
+                return Err(Error::Message("resize can't be part of a pipeline".into()))
+            }
+        }
+        Ok(())
+    }
+
+    fn parse(s: &str) -> Result<Step, Error> {
+        let s = s.trim();
+        let (name, args) = match s.find('(') {
+            Some(i) => {
+                let args = s[i + 1..]
+                    .strip_suffix(')')
+                    .ok_or_else(|| Error::Message(format!("missing ')' in step: {s}")))?;
+                (s[..i].trim(), args.split(',').map(str::trim).collect())
+            }
+            None => (s, Vec::new()),
+        };
+
+        let float = |i: usize| -> Result<f64, Error> {
+            args.get(i)
+                .and_then(|x| x.parse().ok())
+                .ok_or_else(|| Error::Message(format!("invalid argument {i} for step: {s}")))
+        };
+        let int = |i: usize| -> Result<usize, Error> {
+            args.get(i)
+                .and_then(|x| x.parse().ok())
+                .ok_or_else(|| Error::Message(format!("invalid argument {i} for step: {s}")))
+        };
+
+        let (step, nargs) = match name {
+            "brightness" => (Step::Brightness(float(0)?), 1),
+            "exposure" => (Step::Exposure(float(0)?), 1),
+            "contrast" => (Step::Contrast(float(0)?), 1),
+            "saturation" => (Step::Saturation(float(0)?), 1),
+            "hue_rotate" => (Step::HueRotate(float(0)?), 1),
+            "gamma_lin" => (Step::GammaLin(float(0)?), 1),
+            "gamma_log" => (Step::GammaLog(float(0)?), 1),
+            "srgb_lin" => (Step::SrgbLin, 0),
+            "srgb_log" => (Step::SrgbLog, 0),
+            "invert" => (Step::Invert, 0),
+            "clamp" => (Step::Clamp, 0),
+            "gaussian_blur" => (Step::GaussianBlur(float(0)?), 1),
+            "box_blur" => (Step::BoxBlur(int(0)?), 1),
+            "kernel" => {
+                let name = args.first().copied().unwrap_or_default();
+                kernel_preset(name)?;
+                (Step::Kernel(name.to_string()), 1)
+            }
+            "resize" => (Step::Resize(int(0)?, int(1)?), 2),
+            _ => return Err(Error::Message(format!("unknown step: {name}"))),
+        };
+
+        if args.len() != nargs {
+            return Err(Error::Message(format!(
+                "step {name} expects {nargs} arguments, got {}",
+                args.len()
+            )));
+        }
+        Ok(step)
+    }
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Brightness(x) => write!(f, "brightness({x})"),
+            Step::Exposure(x) => write!(f, "exposure({x})"),
+            Step::Contrast(x) => write!(f, "contrast({x})"),
+            Step::Saturation(x) => write!(f, "saturation({x})"),
+            Step::HueRotate(x) => write!(f, "hue_rotate({x})"),
+            Step::GammaLin(x) => write!(f, "gamma_lin({x})"),
+            Step::GammaLog(x) => write!(f, "gamma_log({x})"),
+            Step::SrgbLin => write!(f, "srgb_lin"),
+            Step::SrgbLog => write!(f, "srgb_log"),
+            Step::Invert => write!(f, "invert"),
+            Step::Clamp => write!(f, "clamp"),
+            Step::GaussianBlur(x) => write!(f, "gaussian_blur({x})"),
+            Step::BoxBlur(x) => write!(f, "box_blur({x})"),
+            Step::Kernel(name) => write!(f, "kernel({name})"),
+            Step::Resize(w, h) => write!(f, "resize({w}, {h})"),
+        }
+    }
+}
+
+fn kernel_preset(name: &str) -> Result<Kernel, Error> {
+    kernel::presets::get(name).ok_or_else(|| Error::Message(format!("unknown kernel: {name}")))
+}
+
+/// Serializable list of processing steps, applied in order. The text form is a list of steps
+/// separated by `|`, for example `resize(640, 480) | gaussian_blur(1.5) | invert`
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Recipe {
+    /// Steps, in order of execution
+    pub steps: Vec<Step>,
+}
+
+impl Recipe {
+    /// Create a new, empty recipe
+    pub fn new() -> Self {
+        Recipe::default()
+    }
+
+    /// Append a step to a recipe
+    pub fn then(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Apply every step to an image, returning a new image. Consecutive steps are run as a single
+    /// `Pipeline` so point-wise steps are fused, `resize` steps run between pipelines
+    pub fn apply<T: Type, C: Color>(&self, image: &Image<T, C>) -> Result<Image<T, C>, Error> {
+        let mut image = image.clone();
+        for steps in self
+            .steps
+            .split_inclusive(|x| matches!(x, Step::Resize(..)))
+        {
+            let (resize, steps) = match steps.split_last() {
+                Some((Step::Resize(w, h), steps)) => (Some(Size::new(*w, *h)), steps),
+                _ => (None, steps),
+            };
+            if !steps.is_empty() {
+                let mut pipeline = Pipeline::new();
+                for step in steps {
+                    step.push_to(&mut pipeline)?;
+                }
+                image = image.run(pipeline, None);
+            }
+            if let Some(size) = resize {
+                image = image.run_geometry(filter::resize(image.size(), size));
+            }
+        }
+        Ok(image)
+    }
+}
+
+impl std::str::FromStr for Recipe {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let steps = s
+            .split('|')
+            .filter(|x| !x.trim().is_empty())
+            .map(Step::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Recipe { steps })
+    }
+}
+
+impl std::fmt::Display for Recipe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                write!(f, " | ")?;
+            }
+            write!(f, "{step}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns true when `name` matches `pattern`, `*` matches any number of characters and `?`
+/// matches a single character
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut i, mut j) = (0, 0);
+    let mut star = None;
+    while j < n.len() {
+        if i < p.len() && (p[i] == '?' || p[i] == n[j]) {
+            i += 1;
+            j += 1;
+        } else if i < p.len() && p[i] == '*' {
+            star = Some((i, j));
+            i += 1;
+        } else if let Some((si, sj)) = star {
+            i = si + 1;
+            j = sj + 1;
+            star = Some((si, sj + 1));
+        } else {
+            return false;
+        }
+    }
+    p[i..].iter().all(|c| *c == '*')
+}
+
+/// List files matching a glob pattern, wildcards are supported in any path component but `**`
+/// is treated as `*`. Results are sorted
+pub fn glob(pattern: impl AsRef<Path>) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = vec![PathBuf::new()];
+    for component in pattern.as_ref().components() {
+        let part = component.as_os_str().to_string_lossy();
+        if !part.contains(['*', '?']) {
+            paths.iter_mut().for_each(|p| p.push(component));
+            continue;
+        }
+
+        let mut next = Vec::new();
+        for dir in paths {
+            let path = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                &dir
+            };
+            if path.exists() && !path.is_dir() {
+                continue;
+            }
+
+            // A missing directory doesn't match anything, other errors are returned
+            let entries = match std::fs::read_dir(path) {
+                Ok(entries) => entries,
+                Err(source) if source.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(source) => return Err(Error::IO(source).with_path(path)),
+            };
+            for entry in entries {
+                let entry = entry.map_err(|source| Error::IO(source).with_path(path))?;
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                if !name.starts_with('.') && glob_match(&part, &name) {
+                    next.push(dir.join(&*name));
+                }
+            }
+        }
+        paths = next;
+    }
+
+    let mut paths: Vec<PathBuf> = paths.into_iter().filter(|p| p.is_file()).collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Build an output path from `template`, the following placeholders are replaced:
+///
+/// - `{dir}`: input directory
+/// - `{name}`: input file name
+/// - `{stem}`: input file name without extension
+/// - `{ext}`: input file extension
+/// - `{index}`: position of the input in the list of files
+pub fn output_path(template: &str, input: impl AsRef<Path>, index: usize) -> PathBuf {
+    let input = input.as_ref();
+    let lossy = |x: Option<&std::ffi::OsStr>| {
+        x.map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let dir = input
+        .parent()
+        .map(|x| x.to_string_lossy())
+        .filter(|x| !x.is_empty())
+        .unwrap_or(".".into());
+    PathBuf::from(
+        template
+            .replace("{dir}", &dir)
+            .replace("{name}", &lossy(input.file_name()))
+            .replace("{stem}", &lossy(input.file_stem()))
+            .replace("{ext}", &lossy(input.extension()))
+            .replace("{index}", &index.to_string()),
+    )
+}
+
+/// Result of processing a single file
+#[derive(Debug)]
+pub enum Outcome {
+    /// The file was processed and written to the output path
+    Processed(PathBuf),
+
+    /// The output already existed
+    Skipped(PathBuf),
+
+    /// Processing failed
+    Failed(Error),
+}
+
+/// Summary of a batch run
+#[derive(Debug)]
+pub struct Report {
+    /// Input paths and their outcomes, in the same order as the matched files
+    pub files: Vec<(PathBuf, Outcome)>,
+
+    /// Total processing time
+    pub elapsed: Duration,
+}
+
+impl Report {
+    /// Number of files that were processed
+    pub fn processed(&self) -> usize {
+        self.count(|x| matches!(x, Outcome::Processed(_)))
+    }
+
+    /// Number of files that were skipped because the output already existed
+    pub fn skipped(&self) -> usize {
+        self.count(|x| matches!(x, Outcome::Skipped(_)))
+    }
+
+    /// Number of files that failed
+    pub fn failed(&self) -> usize {
+        self.count(|x| matches!(x, Outcome::Failed(_)))
+    }
+
+    /// Iterate over the inputs that failed along with their errors
+    pub fn errors(&self) -> impl Iterator<Item = (&Path, &Error)> {
+        self.files.iter().filter_map(|(path, x)| match x {
+            Outcome::Failed(e) => Some((path.as_path(), e)),
+            _ => None,
+        })
+    }
+
+    /// Returns true when no files failed
+    pub fn is_ok(&self) -> bool {
+        self.failed() == 0
+    }
+
+    fn count(&self, f: impl Fn(&Outcome) -> bool) -> usize {
+        self.files.iter().filter(|(_, x)| f(x)).count()
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} processed, {} skipped, {} failed in {:.2}s",
+            self.processed(),
+            self.skipped(),
+            self.failed(),
+            self.elapsed.as_secs_f64()
+        )?;
+        for (path, e) in self.errors() {
+            write!(f, "\n  {}: {e}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Process every file matching a glob pattern in parallel. Errors, including panics, are
+/// collected per file instead of stopping the batch, files are distributed across threads using
+/// rayon's work stealing scheduler so slow files don't hold up the rest
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Batch {
+    /// Input glob pattern, see `glob`
+    pub input: String,
+
+    /// Output path template, see `output_path`
+    pub output: String,
+
+    /// Steps applied to each image
+    pub recipe: Recipe,
+
+    /// Number of threads to use, `None` uses the current rayon thread pool
+    pub threads: Option<usize>,
+
+    /// When true, inputs with an existing output file are skipped
+    pub skip_existing: bool,
+}
+
+impl Batch {
+    /// Create a new batch
+    pub fn new(input: impl Into<String>, output: impl Into<String>, recipe: Recipe) -> Batch {
+        Batch {
+            input: input.into(),
+            output: output.into(),
+            recipe,
+            threads: None,
+            skip_existing: false,
+        }
+    }
+
+    /// Build batch with a fixed number of threads
+    pub fn with_threads(mut self, threads: usize) -> Batch {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Build batch with skipping of existing outputs enabled or disabled
+    pub fn with_skip_existing(mut self, skip_existing: bool) -> Batch {
+        self.skip_existing = skip_existing;
+        self
+    }
+
+    /// List input files and their output paths, returns an error when two inputs would be
+    /// written to the same output path
+    pub fn files(&self) -> Result<Vec<(PathBuf, PathBuf)>, Error> {
+        let files: Vec<_> = glob(&self.input)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, input)| {
+                let output = output_path(&self.output, &input, i);
+                (input, output)
+            })
+            .collect();
+
+        let mut outputs = std::collections::HashMap::new();
+        for (input, output) in &files {
+            if let Some(other) = outputs.insert(output, input) {
+                return Err(Error::Message(format!(
+                    "{} and {} are both written to {}",
+                    other.display(),
+                    input.display(),
+                    output.display()
+                )));
+            }
+        }
+        Ok(files)
+    }
+
+    /// Open each file as `Image<T, C>`, apply the recipe and save the result
+    pub fn run<T: Type, C: Color>(&self) -> Result<Report, Error> {
+        self.run_with(|input, output| {
+            let image = Image::<T, C>::open(input)?;
+            self.recipe.apply(&image)?.save(output)
+        })
+    }
+
+    /// Call `f` with each input and output path, this can be used to run custom processing using
+    /// the batch's file matching, error collection and scheduling. Output directories are created
+    /// before `f` is called. An error is only returned when listing the input files fails or when
+    /// two inputs map to the same output path
+    pub fn run_with(
+        &self,
+        f: impl Sync + Send + Fn(&Path, &Path) -> Result<(), Error>,
+    ) -> Result<Report, Error> {
+        let start = Instant::now();
+        let files = self.files()?;
+        let process = |(input, output): (PathBuf, PathBuf)| {
+            let outcome = self.process(&input, output, &f);
+            (input, outcome)
+        };
+
+        let options = match self.threads {
+            Some(n) => ExecutionOptions::new().with_threads(n)?,
+            None => ExecutionOptions::new(),
+        };
+
+        #[cfg(feature = "parallel")]
+        let files = options.install(|| files.into_par_iter().map(process).collect());
+
+        #[cfg(not(feature = "parallel"))]
+        let files = options.install(|| files.into_iter().map(process).collect());
+
+        Ok(Report {
+            files,
+            elapsed: start.elapsed(),
+        })
+    }
+
+    fn process(
+        &self,
+        input: &Path,
+        output: PathBuf,
+        f: &(impl Sync + Fn(&Path, &Path) -> Result<(), Error>),
+    ) -> Outcome {
+        if self.skip_existing && output.exists() {
+            return Outcome::Skipped(output);
+        }
+
+        if let Some(parent) = output.parent().filter(|x| !x.as_os_str().is_empty()) {
+            if let Err(source) = std::fs::create_dir_all(parent) {
+                return Outcome::Failed(Error::IO(source).with_path(parent));
+            }
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(input, &output)));
+        match result {
+            Ok(Ok(())) => Outcome::Processed(output),
+            Ok(Err(e)) => Outcome::Failed(e),
+            Err(panic) => {
+                let msg = panic
+                    .downcast_ref::<&str>()
+                    .map(|x| x.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".into());
+                Outcome::Failed(Error::Message(format!("panicked: {msg}")))
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant};
+
+use crate::*;
+
+/// Timing results for a single image size, see `bench_filter`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    /// Input image size
+    pub size: Size,
+
+    /// Number of times the filter was evaluated
+    pub iterations: usize,
+
+    /// Mean time per evaluation
+    pub mean: Duration,
+
+    /// Fastest evaluation
+    pub min: Duration,
+
+    /// Throughput in megapixels per second, based on the number of output pixels and `mean`
+    pub mpix_per_sec: f64,
+}
+
+/// Allocate the input and output images for benchmarking `filter` at `size`
+fn setup<T: Type, C: Color, U: Type, D: Color>(
+    filter: &impl Filter<T, C, U, D>,
+    size: Size,
+) -> (Image<T, C>, Image<U, D>) {
+    let input: Image<T, C> = testing::random_image(size, 0);
+    let mut dest = Image::new(size);
+    let output_size = filter.output_size(&Input::new(&[&input]), &mut dest);
+    if output_size != size {
+        dest = Image::new(output_size);
+    }
+    (input, dest)
+}
+
+/// Measure the throughput of `filter` for each of the given input sizes, each size is run for at
+/// least `min_time` and at least 3 times
+pub fn bench_filter_for<T: Type, C: Color, U: Type, D: Color>(
+    filter: &impl Filter<T, C, U, D>,
+    sizes: &[Size],
+    min_time: Duration,
+) -> Vec<BenchResult> {
+    sizes
+        .iter()
+        .map(|&size| {
+            let (input, mut dest) = setup(filter, size);
+
+            // Warm up caches and the thread pool
+            filter.eval(&[&input], &mut dest);
+
+            let mut iterations = 0;
+            let mut min = Duration::MAX;
+            let start = Instant::now();
+            while iterations < 3 || start.elapsed() < min_time {
+                let t = Instant::now();
+                filter.eval(&[&input], &mut dest);
+                min = min.min(t.elapsed());
+                iterations += 1;
+            }
+
+            let mean = start.elapsed() / iterations as u32;
+            let pixels = (dest.width() * dest.height()) as f64;
+            BenchResult {
+                size,
+                iterations,
+                mean,
+                min,
+                mpix_per_sec: pixels / mean.as_secs_f64().max(f64::EPSILON) / 1e6,
+            }
+        })
+        .collect()
+}
+
+/// Measure the throughput of `filter` for each of the given input sizes using random input
+/// images, each size is run for at least half a second
+pub fn bench_filter<T: Type, C: Color, U: Type, D: Color>(
+    filter: &impl Filter<T, C, U, D>,
+    sizes: &[Size],
+) -> Vec<BenchResult> {
+    bench_filter_for(filter, sizes, Duration::from_millis(500))
+}
+
+/// Register a criterion benchmark group named `name` with one benchmark per input size,
+/// throughput is reported in output pixels
+///
+/// ```rust,ignore
+/// use criterion::{criterion_group, criterion_main, Criterion};
+/// use image2::*;
+///
+/// fn invert(c: &mut Criterion) {
+///     let sizes = [Size::new(512, 512), Size::new(2048, 2048)];
+///     bench::criterion_filter::<f32, Rgb, f32, Rgb>(c, "invert", &filter::Invert, &sizes);
+/// }
+///
+/// criterion_group!(benches, invert);
+/// criterion_main!(benches);
+/// ```
+#[cfg(feature = "bench")]
+pub fn criterion_filter<T: Type, C: Color, U: Type, D: Color>(
+    c: &mut criterion::Criterion,
+    name: &str,
+    filter: &impl Filter<T, C, U, D>,
+    sizes: &[Size],
+) {
+    let mut group = c.benchmark_group(name);
+    for &size in sizes {
+        let (input, mut dest) = setup(filter, size);
+        let pixels = (dest.width() * dest.height()) as u64;
+        group.throughput(criterion::Throughput::Elements(pixels));
+        group.bench_with_input(
+            criterion::BenchmarkId::from_parameter(format!("{}x{}", size.width, size.height)),
+            &input,
+            |b, input| b.iter(|| filter.eval(&[input], &mut dest)),
+        );
+    }
+    group.finish();
+}
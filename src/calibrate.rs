@@ -0,0 +1,295 @@
+use crate::*;
+
+/// Calibrated images are stored as normalized `f32`, values may be negative or greater than `1`
+/// after calibration and are only clamped when converted back to an integer type
+pub type Calibrated<C> = Image<f32, C>;
+
+fn check_size<T: Type, U: Type, C: Color>(
+    image: &Image<T, C>,
+    frame: &Image<U, C>,
+) -> Result<(), Error> {
+    if image.size() != frame.size() {
+        return Err(Error::ShapeMismatch {
+            expected: image.shape(),
+            got: frame.shape(),
+        });
+    }
+    Ok(())
+}
+
+/// Median of `values`, the two middle values are averaged for even counts
+fn median(values: &mut [f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let len = values.len();
+    let (lower, m, _) = values.select_nth_unstable_by(len / 2, f64::total_cmp);
+    if len % 2 == 1 {
+        return *m;
+    }
+
+    // After selection every value below `mid` is less than or equal to the upper middle value
+    let below = lower.iter().copied().max_by(f64::total_cmp).unwrap_or(*m);
+    (below + *m) / 2.0
+}
+
+/// Combine a sequence of calibration frames into a master frame by taking the per-pixel median,
+/// which rejects outliers such as cosmic ray hits
+pub fn combine_median<T: Type, C: Color>(frames: &[Image<T, C>]) -> Result<Calibrated<C>, Error> {
+    let first = match frames.first() {
+        Some(first) => first,
+        None => return Err(Error::Message("no frames to combine".into())),
+    };
+    for frame in frames {
+        check_size(first, frame)?;
+    }
+
+    let mut dest = Image::new(first.size());
+    let mut values = vec![0.0; frames.len()];
+    for (i, x) in dest.data_mut().iter_mut().enumerate() {
+        for (v, frame) in values.iter_mut().zip(frames) {
+            *v = frame.data()[i].to_norm();
+        }
+        *x = median(&mut values) as f32;
+    }
+    Ok(dest)
+}
+
+/// Subtract the readout offset measured by a zero-length exposure
+pub fn subtract_bias<T: Type, U: Type, C: Color>(
+    image: &Image<T, C>,
+    bias: &Image<U, C>,
+) -> Result<Calibrated<C>, Error> {
+    apply_dark_frame(image, bias, 1.0)
+}
+
+/// Subtract a dark frame, scaled by `scale`. When the dark frame was taken with a different
+/// exposure time than the image, `scale` should be the ratio of the exposure times and the bias
+/// should be subtracted from the dark frame first
+pub fn apply_dark_frame<T: Type, U: Type, C: Color>(
+    image: &Image<T, C>,
+    dark: &Image<U, C>,
+    scale: f64,
+) -> Result<Calibrated<C>, Error> {
+    check_size(image, dark)?;
+    let mut dest = Image::new(image.size());
+    dest.data_mut()
+        .iter_mut()
+        .zip(image.data().iter().zip(dark.data()))
+        .for_each(|(d, (x, dark))| *d = (x.to_norm() - dark.to_norm() * scale) as f32);
+    Ok(dest)
+}
+
+/// Divide by a flat field to correct vignetting and uneven pixel sensitivity, the flat is
+/// normalized by the mean of each channel so overall brightness is preserved. The flat should
+/// already be bias or dark subtracted, pixels where the flat is zero or negative are left as-is
+pub fn apply_flat_field<T: Type, U: Type, C: Color>(
+    image: &Image<T, C>,
+    flat: &Image<U, C>,
+) -> Result<Calibrated<C>, Error> {
+    check_size(image, flat)?;
+    let mut mean = vec![0.0; C::CHANNELS];
+    for (i, x) in flat.data().iter().enumerate() {
+        mean[i % C::CHANNELS] += x.to_norm();
+    }
+    let n = (flat.width() * flat.height()).max(1) as f64;
+    mean.iter_mut().for_each(|m| *m /= n);
+
+    let mut dest = Image::new(image.size());
+    for (i, d) in dest.data_mut().iter_mut().enumerate() {
+        let x = image.data()[i].to_norm();
+        let f = flat.data()[i].to_norm();
+        let c = i % C::CHANNELS;
+        *d = if f > 0.0 && C::ALPHA != Some(c) {
+            (x * mean[c] / f) as f32
+        } else {
+            x as f32
+        };
+    }
+    Ok(dest)
+}
+
+/// Find hot pixels in a dark frame, a pixel is hot when any channel exceeds the median of that
+/// channel by more than `sigma` standard deviations, estimated using the median absolute deviation
+pub fn detect_hot_pixels<T: Type, C: Color>(dark: &Image<T, C>, sigma: f64) -> Vec<Point> {
+    let limits: Vec<f64> = (0..C::CHANNELS)
+        .map(|c| {
+            let mut values: Vec<f64> = dark
+                .data()
+                .iter()
+                .skip(c)
+                .step_by(C::CHANNELS)
+                .map(Type::to_norm)
+                .collect();
+            let m = median(&mut values);
+            values.iter_mut().for_each(|x| *x = (*x - m).abs());
+            let std = median(&mut values) * 1.4826;
+            m + sigma * std.max(f64::EPSILON)
+        })
+        .collect();
+
+    let mut hot = Vec::new();
+    dark.each_pixel(|pt, px| {
+        if (0..C::CHANNELS).any(|c| C::ALPHA != Some(c) && px[c] > limits[c]) {
+            hot.push(pt);
+        }
+    });
+    hot
+}
+
+/// Replace each of the given pixels with the per-channel median of its neighbors, neighbors that
+/// are also listed are skipped
+pub fn remove_hot_pixels<T: Type, C: Color>(image: &mut Image<T, C>, pixels: &[Point]) {
+    let (w, h) = (image.width(), image.height());
+    let mut hot = vec![false; w * h];
+    for pt in pixels {
+        if image.in_bounds(*pt) {
+            hot[pt.y * w + pt.x] = true;
+        }
+    }
+
+    let src = image.clone();
+    let mut values = Vec::with_capacity(8);
+    for pt in pixels {
+        if !image.in_bounds(*pt) {
+            continue;
+        }
+
+        let neighbors: Vec<Point> = (-1isize..=1)
+            .flat_map(|dy| (-1isize..=1).map(move |dx| (dx, dy)))
+            .filter(|d| *d != (0, 0))
+            .map(|(dx, dy)| (pt.x as isize + dx, pt.y as isize + dy))
+            .filter(|(x, y)| *x >= 0 && *y >= 0 && *x < w as isize && *y < h as isize)
+            .map(|(x, y)| Point::new(x as usize, y as usize))
+            .filter(|p| !hot[p.y * w + p.x])
+            .collect();
+        if neighbors.is_empty() {
+            continue;
+        }
+
+        let mut px = src.get_pixel(*pt);
+        for c in 0..C::CHANNELS {
+            values.clear();
+            values.extend(neighbors.iter().map(|p| src.get_f(*p, c)));
+            px[c] = median(&mut values);
+        }
+        image.set_pixel(*pt, &px);
+    }
+}
+
+/// Calibration frames applied to each image in a sequence, see `Calibration::apply`
+pub struct Calibration<C: Color> {
+    /// Master bias frame
+    pub bias: Option<Calibrated<C>>,
+
+    /// Master dark frame and its exposure time, the bias is subtracted from the dark frame
+    /// before it is scaled
+    pub dark: Option<(Calibrated<C>, f64)>,
+
+    /// Master flat field, the bias is subtracted from the flat before it is applied
+    pub flat: Option<Calibrated<C>>,
+
+    /// Hot pixels, replaced after the other corrections
+    pub hot_pixels: Vec<Point>,
+}
+
+impl<C: Color> std::fmt::Debug for Calibration<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Calibration")
+            .field("bias", &self.bias.is_some())
+            .field("dark", &self.dark.as_ref().map(|(_, exposure)| exposure))
+            .field("flat", &self.flat.is_some())
+            .field("hot_pixels", &self.hot_pixels.len())
+            .finish()
+    }
+}
+
+impl<C: Color> Default for Calibration<C> {
+    fn default() -> Self {
+        Calibration::new()
+    }
+}
+
+impl<C: Color> Calibration<C> {
+    /// Create a new `Calibration` without any calibration frames
+    pub fn new() -> Calibration<C> {
+        Calibration {
+            bias: None,
+            dark: None,
+            flat: None,
+            hot_pixels: Vec::new(),
+        }
+    }
+
+    /// Set master bias frame
+    pub fn with_bias<T: Type>(mut self, bias: &Image<T, C>) -> Calibration<C> {
+        self.bias = Some(bias.convert());
+        self
+    }
+
+    /// Set master dark frame, taken with the given exposure time
+    pub fn with_dark<T: Type>(mut self, dark: &Image<T, C>, exposure: f64) -> Calibration<C> {
+        self.dark = Some((dark.convert(), exposure));
+        self
+    }
+
+    /// Set master flat field
+    pub fn with_flat<T: Type>(mut self, flat: &Image<T, C>) -> Calibration<C> {
+        self.flat = Some(flat.convert());
+        self
+    }
+
+    /// Set hot pixels, see `detect_hot_pixels`
+    pub fn with_hot_pixels(mut self, hot_pixels: Vec<Point>) -> Calibration<C> {
+        self.hot_pixels = hot_pixels;
+        self
+    }
+
+    /// Calibrate a single image taken with the given exposure time
+    pub fn apply<T: Type>(
+        &self,
+        image: &Image<T, C>,
+        exposure: f64,
+    ) -> Result<Calibrated<C>, Error> {
+        let mut dest: Calibrated<C> = image.convert();
+        if let Some(bias) = &self.bias {
+            dest = subtract_bias(&dest, bias)?;
+        }
+
+        if let Some((dark, dark_exposure)) = &self.dark {
+            let scale = if *dark_exposure > 0.0 {
+                exposure / dark_exposure
+            } else {
+                1.0
+            };
+            let dark = match &self.bias {
+                Some(bias) => subtract_bias(dark, bias)?,
+                None => dark.clone(),
+            };
+            dest = apply_dark_frame(&dest, &dark, scale)?;
+        }
+
+        if let Some(flat) = &self.flat {
+            let flat = match &self.bias {
+                Some(bias) => subtract_bias(flat, bias)?,
+                None => flat.clone(),
+            };
+            dest = apply_flat_field(&dest, &flat)?;
+        }
+
+        remove_hot_pixels(&mut dest, &self.hot_pixels);
+        Ok(dest)
+    }
+
+    /// Calibrate a sequence of images that were all taken with the same exposure time
+    pub fn apply_all<T: Type>(
+        &self,
+        images: &[Image<T, C>],
+        exposure: f64,
+    ) -> Result<Vec<Calibrated<C>>, Error> {
+        images
+            .iter()
+            .map(|image| self.apply(image, exposure))
+            .collect()
+    }
+}
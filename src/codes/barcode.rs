@@ -0,0 +1,486 @@
+use super::{Binary, Code, Format};
+
+/// Bar and space widths of EAN L-code digits, the G-code is reversed and the R-code starts with a
+/// bar
+const EAN_DIGITS: [[u8; 4]; 10] = [
+    [3, 2, 1, 1],
+    [2, 2, 2, 1],
+    [2, 1, 2, 2],
+    [1, 4, 1, 1],
+    [1, 1, 3, 2],
+    [1, 2, 3, 1],
+    [1, 1, 1, 4],
+    [1, 3, 1, 2],
+    [1, 2, 1, 3],
+    [3, 1, 1, 2],
+];
+
+/// L/G parity of the left half encoding the first digit of an EAN-13 code, `true` is G
+const EAN_PARITY: [[bool; 6]; 10] = {
+    const L: bool = false;
+    const G: bool = true;
+    [
+        [L, L, L, L, L, L],
+        [L, L, G, L, G, G],
+        [L, L, G, G, L, G],
+        [L, L, G, G, G, L],
+        [L, G, L, L, G, G],
+        [L, G, G, L, L, G],
+        [L, G, G, G, L, L],
+        [L, G, L, G, L, G],
+        [L, G, L, G, G, L],
+        [L, G, G, L, G, L],
+    ]
+};
+
+/// Bar and space widths of Code 128 symbols, indexed by value
+const CODE128: [[u8; 6]; 106] = [
+    [2, 1, 2, 2, 2, 2],
+    [2, 2, 2, 1, 2, 2],
+    [2, 2, 2, 2, 2, 1],
+    [1, 2, 1, 2, 2, 3],
+    [1, 2, 1, 3, 2, 2],
+    [1, 3, 1, 2, 2, 2],
+    [1, 2, 2, 2, 1, 3],
+    [1, 2, 2, 3, 1, 2],
+    [1, 3, 2, 2, 1, 2],
+    [2, 2, 1, 2, 1, 3],
+    [2, 2, 1, 3, 1, 2],
+    [2, 3, 1, 2, 1, 2],
+    [1, 1, 2, 2, 3, 2],
+    [1, 2, 2, 1, 3, 2],
+    [1, 2, 2, 2, 3, 1],
+    [1, 1, 3, 2, 2, 2],
+    [1, 2, 3, 1, 2, 2],
+    [1, 2, 3, 2, 2, 1],
+    [2, 2, 3, 2, 1, 1],
+    [2, 2, 1, 1, 3, 2],
+    [2, 2, 1, 2, 3, 1],
+    [2, 1, 3, 2, 1, 2],
+    [2, 2, 3, 1, 1, 2],
+    [3, 1, 2, 1, 3, 1],
+    [3, 1, 1, 2, 2, 2],
+    [3, 2, 1, 1, 2, 2],
+    [3, 2, 1, 2, 2, 1],
+    [3, 1, 2, 2, 1, 2],
+    [3, 2, 2, 1, 1, 2],
+    [3, 2, 2, 2, 1, 1],
+    [2, 1, 2, 1, 2, 3],
+    [2, 1, 2, 3, 2, 1],
+    [2, 3, 2, 1, 2, 1],
+    [1, 1, 1, 3, 2, 3],
+    [1, 3, 1, 1, 2, 3],
+    [1, 3, 1, 3, 2, 1],
+    [1, 1, 2, 3, 1, 3],
+    [1, 3, 2, 1, 1, 3],
+    [1, 3, 2, 3, 1, 1],
+    [2, 1, 1, 3, 1, 3],
+    [2, 3, 1, 1, 1, 3],
+    [2, 3, 1, 3, 1, 1],
+    [1, 1, 2, 1, 3, 3],
+    [1, 1, 2, 3, 3, 1],
+    [1, 3, 2, 1, 3, 1],
+    [1, 1, 3, 1, 2, 3],
+    [1, 1, 3, 3, 2, 1],
+    [1, 3, 3, 1, 2, 1],
+    [3, 1, 3, 1, 2, 1],
+    [2, 1, 1, 3, 3, 1],
+    [2, 3, 1, 1, 3, 1],
+    [2, 1, 3, 1, 1, 3],
+    [2, 1, 3, 3, 1, 1],
+    [2, 1, 3, 1, 3, 1],
+    [3, 1, 1, 1, 2, 3],
+    [3, 1, 1, 3, 2, 1],
+    [3, 3, 1, 1, 2, 1],
+    [3, 1, 2, 1, 1, 3],
+    [3, 1, 2, 3, 1, 1],
+    [3, 3, 2, 1, 1, 1],
+    [3, 1, 4, 1, 1, 1],
+    [2, 2, 1, 4, 1, 1],
+    [4, 3, 1, 1, 1, 1],
+    [1, 1, 1, 2, 2, 4],
+    [1, 1, 1, 4, 2, 2],
+    [1, 2, 1, 1, 2, 4],
+    [1, 2, 1, 4, 2, 1],
+    [1, 4, 1, 1, 2, 2],
+    [1, 4, 1, 2, 2, 1],
+    [1, 1, 2, 2, 1, 4],
+    [1, 1, 2, 4, 1, 2],
+    [1, 2, 2, 1, 1, 4],
+    [1, 2, 2, 4, 1, 1],
+    [1, 4, 2, 1, 1, 2],
+    [1, 4, 2, 2, 1, 1],
+    [2, 4, 1, 2, 1, 1],
+    [2, 2, 1, 1, 1, 4],
+    [4, 1, 3, 1, 1, 1],
+    [2, 4, 1, 1, 1, 2],
+    [1, 3, 4, 1, 1, 1],
+    [1, 1, 1, 2, 4, 2],
+    [1, 2, 1, 1, 4, 2],
+    [1, 2, 1, 2, 4, 1],
+    [1, 1, 4, 2, 1, 2],
+    [1, 2, 4, 1, 1, 2],
+    [1, 2, 4, 2, 1, 1],
+    [4, 1, 1, 2, 1, 2],
+    [4, 2, 1, 1, 1, 2],
+    [4, 2, 1, 2, 1, 1],
+    [2, 1, 2, 1, 4, 1],
+    [2, 1, 4, 1, 2, 1],
+    [4, 1, 2, 1, 2, 1],
+    [1, 1, 1, 1, 4, 3],
+    [1, 1, 1, 3, 4, 1],
+    [1, 3, 1, 1, 4, 1],
+    [1, 1, 4, 1, 1, 3],
+    [1, 1, 4, 3, 1, 1],
+    [4, 1, 1, 1, 1, 3],
+    [4, 1, 1, 3, 1, 1],
+    [1, 1, 3, 1, 4, 1],
+    [1, 1, 4, 1, 3, 1],
+    [3, 1, 1, 1, 4, 1],
+    [4, 1, 1, 1, 3, 1],
+    [2, 1, 1, 4, 1, 2],
+    [2, 1, 1, 2, 1, 4],
+    [2, 1, 1, 2, 3, 2],
+];
+
+/// Code 128 stop pattern, including the final bar
+const CODE128_STOP: [u8; 7] = [2, 3, 3, 1, 1, 1, 2];
+
+/// Maximum average deviation per element, in modules, when matching a pattern
+const MAX_DEVIATION: f64 = 0.4;
+
+/// Run lengths as `(start, length, dark)`
+type Run = (usize, usize, bool);
+
+/// Deviation between measured run lengths and a pattern, after scaling the runs to the pattern
+/// width
+fn deviation(runs: &[Run], pattern: &[u8]) -> f64 {
+    let total: usize = runs.iter().map(|r| r.1).sum();
+    let modules: u8 = pattern.iter().sum();
+    let scale = modules as f64 / total as f64;
+    runs.iter()
+        .zip(pattern)
+        .map(|(r, p)| (r.1 as f64 * scale - *p as f64).abs())
+        .sum::<f64>()
+        / pattern.len() as f64
+}
+
+/// Index of the best matching pattern
+fn best_match<const N: usize>(runs: &[Run], patterns: &[[u8; N]]) -> Option<(usize, f64)> {
+    patterns
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (i, deviation(runs, p)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .filter(|x| x.1 < MAX_DEVIATION)
+}
+
+/// Check for a light quiet zone of at least `modules` modules before `runs[i]`
+fn quiet_zone(runs: &[Run], i: usize, module: f64, modules: f64) -> bool {
+    i == 0 || (!runs[i - 1].2 && runs[i - 1].1 as f64 >= module * modules)
+}
+
+/// Horizontal extent of a range of runs
+fn extent(runs: &[Run]) -> (usize, usize) {
+    let start = runs.iter().map(|r| r.0).min().unwrap_or(0);
+    let end = runs.iter().map(|r| r.0 + r.1).max().unwrap_or(0);
+    (start, end)
+}
+
+/// Decode an EAN-13 code starting with the guard at `runs[0]`
+fn decode_ean13(runs: &[Run]) -> Option<Vec<u8>> {
+    if runs.len() < 59 {
+        return None;
+    }
+
+    let guard = [1, 1, 1];
+    let middle = [1, 1, 1, 1, 1];
+    if deviation(&runs[..3], &guard) >= MAX_DEVIATION
+        || deviation(&runs[27..32], &middle) >= MAX_DEVIATION
+        || deviation(&runs[56..59], &guard) >= MAX_DEVIATION
+    {
+        return None;
+    }
+
+    let g_digits = EAN_DIGITS.map(|mut d| {
+        d.reverse();
+        d
+    });
+    let mut digits = [0u8; 13];
+    let mut parity = [false; 6];
+    for i in 0..6 {
+        let runs = &runs[3 + i * 4..7 + i * 4];
+        let l = best_match(runs, &EAN_DIGITS);
+        let g = best_match(runs, &g_digits);
+        let (digit, is_g) = match (l, g) {
+            (Some(l), Some(g)) if g.1 < l.1 => (g.0, true),
+            (Some(l), _) => (l.0, false),
+            (None, Some(g)) => (g.0, true),
+            (None, None) => return None,
+        };
+        digits[i + 1] = digit as u8;
+        parity[i] = is_g;
+    }
+    for i in 0..6 {
+        let (digit, _) = best_match(&runs[32 + i * 4..36 + i * 4], &EAN_DIGITS)?;
+        digits[i + 7] = digit as u8;
+    }
+
+    digits[0] = EAN_PARITY.iter().position(|p| *p == parity)? as u8;
+
+    let sum: u32 = digits[..12]
+        .iter()
+        .enumerate()
+        .map(|(i, d)| *d as u32 * if i % 2 == 0 { 1 } else { 3 })
+        .sum();
+    if (10 - sum % 10) % 10 != digits[12] as u32 {
+        return None;
+    }
+
+    Some(digits.iter().map(|d| b'0' + d).collect())
+}
+
+/// Code 128 code sets
+#[derive(Clone, Copy, PartialEq)]
+enum CodeSet {
+    A,
+    B,
+    C,
+}
+
+/// Decode a Code 128 code starting with the start symbol at `runs[0]`, returns the text and the
+/// number of runs used
+fn decode_code128(runs: &[Run]) -> Option<(Vec<u8>, usize)> {
+    let start = runs.get(..6).and_then(|r| best_match(r, &CODE128))?.0;
+    let mut set = match start {
+        103 => CodeSet::A,
+        104 => CodeSet::B,
+        105 => CodeSet::C,
+        _ => return None,
+    };
+
+    // Read symbols until the stop pattern
+    let mut values = Vec::new();
+    let mut i = 6;
+    loop {
+        let stop = runs
+            .get(i..i + 7)
+            .map(|r| deviation(r, &CODE128_STOP))
+            .unwrap_or(f64::INFINITY);
+        let symbol = runs.get(i..i + 6).and_then(|r| best_match(r, &CODE128));
+        match symbol {
+            Some((v, d)) if d <= stop => {
+                values.push(v);
+                i += 6;
+            }
+            _ if stop < MAX_DEVIATION => break,
+            _ => return None,
+        }
+    }
+
+    let checksum = values.pop()?;
+    let sum = values
+        .iter()
+        .enumerate()
+        .fold(start, |acc, (i, v)| acc + v * (i + 1));
+    if sum % 103 != checksum {
+        return None;
+    }
+
+    let mut text = Vec::new();
+    let mut shift = false;
+    for v in values {
+        let current = match (shift, set) {
+            (true, CodeSet::A) => CodeSet::B,
+            (true, CodeSet::B) => CodeSet::A,
+            (_, s) => s,
+        };
+        shift = false;
+        match (current, v) {
+            (CodeSet::C, 0..=99) => text.extend(format!("{:02}", v).bytes()),
+            (CodeSet::A, 0..=63) | (CodeSet::B, 0..=95) => text.push(v as u8 + 32),
+            (CodeSet::A, 64..=95) => text.push(v as u8 - 64),
+            (CodeSet::A | CodeSet::B, 98) => shift = true,
+            (CodeSet::A | CodeSet::B, 99) => set = CodeSet::C,
+            (CodeSet::A | CodeSet::C, 100) => set = CodeSet::B,
+            (CodeSet::B | CodeSet::C, 101) => set = CodeSet::A,
+            // FNC1 to FNC4 carry no text
+            (_, 96 | 97 | 100 | 101 | 102) => (),
+            _ => return None,
+        }
+    }
+
+    Some((text, i + 7))
+}
+
+/// Decode all barcodes found in a single row, returns `(format, payload, start, end)`
+fn scan_row(runs: &[Run]) -> Vec<(Format, Vec<u8>, usize, usize)> {
+    let mut dest = Vec::new();
+    let mut i = 0;
+    while i < runs.len() {
+        if !runs[i].2 {
+            i += 1;
+            continue;
+        }
+
+        if let Some(payload) = runs.get(i..).and_then(decode_ean13) {
+            let (start, end) = extent(&runs[i..i + 59]);
+            if quiet_zone(runs, i, (end - start) as f64 / 95.0, 5.0) {
+                dest.push((Format::Ean13, payload, start, end));
+                i += 59;
+                continue;
+            }
+        }
+
+        if let Some((payload, n)) = decode_code128(&runs[i..]) {
+            let (start, end) = extent(&runs[i..i + n]);
+            let module = (runs[i..i + 6].iter().map(|r| r.1).sum::<usize>()) as f64 / 11.0;
+            if quiet_zone(runs, i, module, 5.0) {
+                dest.push((Format::Code128, payload, start, end));
+                i += n;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+    dest
+}
+
+/// Detection merged across consecutive rows
+struct Found {
+    format: Format,
+    payload: Vec<u8>,
+    x: (usize, usize),
+    y: (usize, usize),
+    rows: usize,
+}
+
+pub(crate) fn detect(bin: &Binary) -> Vec<Code> {
+    let mut found: Vec<Found> = Vec::new();
+    for y in 0..bin.height {
+        let mut runs = bin.row_runs(y);
+        let mut codes = scan_row(&runs);
+        runs.reverse();
+        codes.extend(scan_row(&runs));
+
+        for (format, payload, start, end) in codes {
+            let existing = found.iter_mut().find(|f| {
+                f.format == format
+                    && f.payload == payload
+                    && start < f.x.1
+                    && end > f.x.0
+                    && y <= f.y.1 + 2
+            });
+            match existing {
+                Some(f) => {
+                    f.x = (f.x.0.min(start), f.x.1.max(end));
+                    f.y.1 = y;
+                    f.rows += 1;
+                }
+                None => found.push(Found {
+                    format,
+                    payload,
+                    x: (start, end),
+                    y: (y, y),
+                    rows: 1,
+                }),
+            }
+        }
+    }
+
+    found
+        .into_iter()
+        .filter(|f| f.rows >= 2)
+        .map(|f| {
+            let (x0, x1) = (f.x.0 as f64, f.x.1 as f64);
+            let (y0, y1) = (f.y.0 as f64, (f.y.1 + 1) as f64);
+            Code {
+                format: f.format,
+                payload: f.payload,
+                corners: [[x0, y0], [x1, y0], [x1, y1], [x0, y1]],
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    /// Render bar widths, starting with a bar, as an image with a quiet zone
+    fn render(widths: &[u8], scale: usize, flip: bool) -> Image<u8, Gray> {
+        let mut modules = vec![false; 10];
+        for (i, w) in widths.iter().enumerate() {
+            modules.extend(vec![i % 2 == 0; *w as usize]);
+        }
+        modules.extend([false; 10]);
+        if flip {
+            modules.reverse();
+        }
+
+        let mut image = Image::<u8, Gray>::new((modules.len() * scale, 24));
+        image.for_each(|pt, mut px| {
+            let dark = pt.y >= 4 && pt.y < 20 && modules[pt.x / scale];
+            px[0] = if dark { 0 } else { 255 };
+        });
+        image
+    }
+
+    fn ean13(code: &str) -> Vec<u8> {
+        let digits: Vec<usize> = code.bytes().map(|b| (b - b'0') as usize).collect();
+        let mut widths = vec![1, 1, 1];
+        for (i, d) in digits[1..7].iter().enumerate() {
+            let mut w = EAN_DIGITS[*d];
+            if EAN_PARITY[digits[0]][i] {
+                w.reverse();
+            }
+            widths.extend(w);
+        }
+        widths.extend([1, 1, 1, 1, 1]);
+        for d in &digits[7..] {
+            widths.extend(EAN_DIGITS[*d]);
+        }
+        widths.extend([1, 1, 1]);
+        widths
+    }
+
+    fn code128(text: &str) -> Vec<u8> {
+        let mut values = vec![104];
+        values.extend(text.bytes().map(|b| (b - 32) as usize));
+        let checksum = values
+            .iter()
+            .enumerate()
+            .skip(1)
+            .fold(104, |acc, (i, v)| acc + v * i)
+            % 103;
+        values.push(checksum);
+        let mut widths: Vec<u8> = values.iter().flat_map(|v| CODE128[*v]).collect();
+        widths.extend(CODE128_STOP);
+        widths
+    }
+
+    #[test]
+    fn test_barcodes() {
+        for flip in [false, true] {
+            let image = render(&ean13("4006381333931"), 2, flip);
+            let found = codes::detect_barcodes(&image);
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].format, codes::Format::Ean13);
+            assert_eq!(found[0].text(), Some("4006381333931"));
+            assert_eq!(found[0].corners[0], [20.0, 4.0]);
+            assert_eq!(found[0].corners[2], [210.0, 20.0]);
+
+            let image = render(&code128("Hello-128"), 3, flip);
+            let found = codes::detect(&image);
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].format, codes::Format::Code128);
+            assert_eq!(found[0].text(), Some("Hello-128"));
+        }
+
+        // Bad checksum
+        let image = render(&ean13("4006381333932"), 2, false);
+        assert!(codes::detect_barcodes(&image).is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+use crate::*;
+
+mod barcode;
+mod qr;
+mod reed_solomon;
+
+/// Symbology of a decoded code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// QR code
+    Qr,
+
+    /// EAN-13 barcode
+    Ean13,
+
+    /// Code 128 barcode
+    Code128,
+}
+
+/// Decoded code
+#[derive(Debug, Clone, PartialEq)]
+pub struct Code {
+    /// Symbology
+    pub format: Format,
+
+    /// Decoded payload, for barcodes this is the ASCII text of the code
+    pub payload: Vec<u8>,
+
+    /// Corners of the code in image coordinates, clockwise starting at the top-left corner of
+    /// the symbol
+    pub corners: [[f64; 2]; 4],
+}
+
+impl Code {
+    /// Payload as text, if it is valid UTF-8
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.payload).ok()
+    }
+}
+
+/// Binarized image, `true` is dark
+pub(crate) struct Binary {
+    width: usize,
+    height: usize,
+    data: Vec<bool>,
+}
+
+impl Binary {
+    /// Threshold `image` using Otsu's method
+    fn new<T: Type>(image: &Image<T, Gray>) -> Binary {
+        let values: Vec<f64> = image.data().iter().map(Type::to_norm).collect();
+        let mut histogram = [0usize; 256];
+        for v in &values {
+            histogram[(v.clamp(0.0, 1.0) * 255.0).round() as usize] += 1;
+        }
+
+        let total = values.len() as f64;
+        let sum: f64 = histogram
+            .iter()
+            .enumerate()
+            .map(|(i, n)| i as f64 * *n as f64)
+            .sum();
+        let (mut sum_b, mut weight_b) = (0.0, 0.0);
+        let mut best = (0, 0.0);
+        for (i, n) in histogram.iter().enumerate() {
+            weight_b += *n as f64;
+            if weight_b == 0.0 {
+                continue;
+            }
+            let weight_f = total - weight_b;
+            if weight_f == 0.0 {
+                break;
+            }
+            sum_b += i as f64 * *n as f64;
+            let mean_b = sum_b / weight_b;
+            let mean_f = (sum - sum_b) / weight_f;
+            let between = weight_b * weight_f * (mean_b - mean_f).powi(2);
+            if between > best.1 {
+                best = (i, between);
+            }
+        }
+
+        let threshold = best.0 as f64 / 255.0;
+        Binary {
+            width: image.width(),
+            height: image.height(),
+            data: values.iter().map(|v| *v <= threshold).collect(),
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.data[y * self.width + x]
+    }
+
+    /// Nearest pixel at a floating point position, positions outside of the image are light
+    fn sample(&self, x: f64, y: f64) -> bool {
+        if x < 0.0 || y < 0.0 {
+            return false;
+        }
+        let (x, y) = (x as usize, y as usize);
+        x < self.width && y < self.height && self.get(x, y)
+    }
+
+    /// Run lengths of row `y` as `(start, length, dark)`
+    fn row_runs(&self, y: usize) -> Vec<(usize, usize, bool)> {
+        let mut runs: Vec<(usize, usize, bool)> = Vec::new();
+        for x in 0..self.width {
+            let dark = self.get(x, y);
+            match runs.last_mut() {
+                Some(run) if run.2 == dark => run.1 += 1,
+                _ => runs.push((x, 1, dark)),
+            }
+        }
+        runs
+    }
+}
+
+/// Find and decode all supported codes in `image`
+pub fn detect<T: Type>(image: &Image<T, Gray>) -> Vec<Code> {
+    let binary = Binary::new(image);
+    let mut codes = qr::detect(&binary);
+    codes.extend(barcode::detect(&binary));
+    codes
+}
+
+/// Find and decode QR codes in `image`
+pub fn detect_qr<T: Type>(image: &Image<T, Gray>) -> Vec<Code> {
+    qr::detect(&Binary::new(image))
+}
+
+/// Find and decode EAN-13 and Code 128 barcodes in `image`
+pub fn detect_barcodes<T: Type>(image: &Image<T, Gray>) -> Vec<Code> {
+    barcode::detect(&Binary::new(image))
+}
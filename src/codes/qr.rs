@@ -0,0 +1,842 @@
+use super::reed_solomon::Gf;
+use super::{Binary, Code, Format};
+use crate::fit::{Homography, Model};
+
+/// Error correction codewords per block, indexed by error correction level (L, M, Q, H) and
+/// version
+const ECC_CODEWORDS_PER_BLOCK: [[u8; 41]; 4] = [
+    [
+        0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28,
+        30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28,
+        28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+    ],
+    [
+        0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30,
+        30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24,
+        30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+];
+
+/// Number of error correction blocks, indexed by error correction level (L, M, Q, H) and version
+const ECC_BLOCKS: [[u8; 41]; 4] = [
+    [
+        0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13,
+        14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25,
+    ],
+    [
+        0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21,
+        23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49,
+    ],
+    [
+        0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29,
+        34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68,
+    ],
+    [
+        0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32,
+        35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81,
+    ],
+];
+
+const ALPHANUMERIC: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Size of a symbol in modules
+fn dimension(version: usize) -> usize {
+    version * 4 + 17
+}
+
+/// Number of codewords available for data and error correction
+fn raw_codewords(version: usize) -> usize {
+    let mut n = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let align = version / 7 + 2;
+        n -= (25 * align - 10) * align - 55;
+        if version >= 7 {
+            n -= 36;
+        }
+    }
+    n / 8
+}
+
+/// Center coordinates of alignment patterns along each axis
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+
+    let count = version / 7 + 2;
+    let step = if version == 32 {
+        26
+    } else {
+        (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2
+    };
+    let last = dimension(version) - 7;
+    let mut dest = vec![6];
+    dest.extend((1..count).map(|i| last - (count - 1 - i) * step));
+    dest
+}
+
+/// Alignment pattern centers, skipping the ones that overlap finder patterns
+fn alignment_centers(version: usize) -> Vec<(usize, usize)> {
+    let positions = alignment_positions(version);
+    let last = positions.len().saturating_sub(1);
+    let mut dest = Vec::new();
+    for (i, y) in positions.iter().enumerate() {
+        for (j, x) in positions.iter().enumerate() {
+            let corner = (i == 0 && (j == 0 || j == last)) || (i == last && j == 0);
+            if !corner {
+                dest.push((*x, *y));
+            }
+        }
+    }
+    dest
+}
+
+/// Modules used by function patterns, indexed as `[y][x]`
+fn function_modules(version: usize) -> Vec<Vec<bool>> {
+    let n = dimension(version);
+    let mut dest = vec![vec![false; n]; n];
+    let mut fill = |x0: usize, y0: usize, w: usize, h: usize| {
+        for row in dest.iter_mut().skip(y0).take(h) {
+            for m in row.iter_mut().skip(x0).take(w) {
+                *m = true;
+            }
+        }
+    };
+
+    // Finder patterns with separators and format information
+    fill(0, 0, 9, 9);
+    fill(n - 8, 0, 8, 9);
+    fill(0, n - 8, 9, 8);
+
+    // Timing patterns
+    fill(6, 0, 1, n);
+    fill(0, 6, n, 1);
+
+    for (x, y) in alignment_centers(version) {
+        fill(x - 2, y - 2, 5, 5);
+    }
+
+    if version >= 7 {
+        fill(n - 11, 0, 3, 6);
+        fill(0, n - 11, 6, 3);
+    }
+
+    dest
+}
+
+/// Module positions of data bits in placement order
+fn codeword_positions(version: usize) -> Vec<(usize, usize)> {
+    let n = dimension(version);
+    let function = function_modules(version);
+    let mut dest = Vec::new();
+    let mut right = n as isize - 1;
+    while right >= 1 {
+        if right == 6 {
+            right = 5;
+        }
+        for vert in 0..n {
+            for j in 0..2 {
+                let x = (right - j) as usize;
+                let upward = (right + 1) & 2 == 0;
+                let y = if upward { n - 1 - vert } else { vert };
+                if !function[y][x] {
+                    dest.push((x, y));
+                }
+            }
+        }
+        right -= 2;
+    }
+    dest
+}
+
+fn mask_bit(mask: u8, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y) % 2 == 0,
+        1 => y % 2 == 0,
+        2 => x % 3 == 0,
+        3 => (x + y) % 3 == 0,
+        4 => (x / 3 + y / 2) % 2 == 0,
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3) % 2 == 0,
+        _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+    }
+}
+
+/// Format information codeword for 5 bits of data
+fn format_bits(data: u32) -> u32 {
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    ((data << 10) | rem) ^ 0x5412
+}
+
+/// Positions of the 15 format bits, both copies, as `(x, y)`
+fn format_positions(n: usize) -> [[(usize, usize); 15]; 2] {
+    let mut a = [(0, 0); 15];
+    let mut b = [(0, 0); 15];
+    for (i, p) in a.iter_mut().enumerate() {
+        *p = match i {
+            0..=5 => (8, i),
+            6 => (8, 7),
+            7 => (8, 8),
+            8 => (7, 8),
+            _ => (14 - i, 8),
+        };
+    }
+    for (i, p) in b.iter_mut().enumerate() {
+        *p = if i < 8 {
+            (n - 1 - i, 8)
+        } else {
+            (8, n - 15 + i)
+        };
+    }
+    [a, b]
+}
+
+/// Error correction level index into the tables and mask from format information
+fn read_format(grid: &[Vec<bool>]) -> Option<(usize, u8)> {
+    let n = grid.len();
+    let mut best = (usize::MAX, 0);
+    for positions in format_positions(n) {
+        let read = positions
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, (x, y))| acc | ((grid[*y][*x] as u32) << i));
+        for data in 0..32 {
+            let d = (format_bits(data) ^ read).count_ones() as usize;
+            if d < best.0 {
+                best = (d, data);
+            }
+        }
+    }
+
+    if best.0 > 3 {
+        return None;
+    }
+
+    let level = match best.1 >> 3 {
+        1 => 0,
+        0 => 1,
+        3 => 2,
+        _ => 3,
+    };
+    Some((level, (best.1 & 7) as u8))
+}
+
+/// Block lengths as `(data, ecc)` for each block
+fn blocks(version: usize, level: usize) -> Vec<(usize, usize)> {
+    let count = ECC_BLOCKS[level][version] as usize;
+    let ecc = ECC_CODEWORDS_PER_BLOCK[level][version] as usize;
+    let raw = raw_codewords(version);
+    let short = count - raw % count;
+    let short_len = raw / count;
+    (0..count)
+        .map(|i| (short_len - ecc + usize::from(i >= short), ecc))
+        .collect()
+}
+
+/// Split interleaved codewords into blocks of data followed by error correction codewords
+fn deinterleave(codewords: &[u8], layout: &[(usize, usize)]) -> Vec<Vec<u8>> {
+    let mut dest: Vec<Vec<u8>> = layout.iter().map(|(d, e)| vec![0; d + e]).collect();
+    let max_data = layout.iter().map(|x| x.0).max().unwrap_or(0);
+    let mut index = 0;
+    for i in 0..max_data {
+        for (block, (d, _)) in dest.iter_mut().zip(layout) {
+            if i < *d {
+                block[i] = codewords[index];
+                index += 1;
+            }
+        }
+    }
+    let ecc = layout.first().map(|x| x.1).unwrap_or(0);
+    for i in 0..ecc {
+        for (block, (d, _)) in dest.iter_mut().zip(layout) {
+            block[d + i] = codewords[index];
+            index += 1;
+        }
+    }
+    dest
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn read(&mut self, n: usize) -> Option<u32> {
+        if self.pos + n > self.data.len() * 8 {
+            return None;
+        }
+        let mut dest = 0;
+        for _ in 0..n {
+            let bit = (self.data[self.pos / 8] >> (7 - self.pos % 8)) & 1;
+            dest = (dest << 1) | bit as u32;
+            self.pos += 1;
+        }
+        Some(dest)
+    }
+}
+
+/// Decode the data segments of a corrected bit stream
+fn decode_segments(data: &[u8], version: usize) -> Option<Vec<u8>> {
+    let mut reader = BitReader { data, pos: 0 };
+    let mut dest = Vec::new();
+    let group = if version <= 9 {
+        0
+    } else if version <= 26 {
+        1
+    } else {
+        2
+    };
+
+    while let Some(mode) = reader.read(4) {
+        match mode {
+            0 => break,
+            1 => {
+                let mut count = reader.read([10, 12, 14][group])? as usize;
+                while count >= 3 {
+                    dest.extend(format!("{:03}", reader.read(10)?).bytes());
+                    count -= 3;
+                }
+                if count == 2 {
+                    dest.extend(format!("{:02}", reader.read(7)?).bytes());
+                } else if count == 1 {
+                    dest.extend(format!("{}", reader.read(4)?).bytes());
+                }
+            }
+            2 => {
+                let mut count = reader.read([9, 11, 13][group])? as usize;
+                while count >= 2 {
+                    let v = reader.read(11)? as usize;
+                    dest.push(*ALPHANUMERIC.get(v / 45)?);
+                    dest.push(*ALPHANUMERIC.get(v % 45)?);
+                    count -= 2;
+                }
+                if count == 1 {
+                    dest.push(*ALPHANUMERIC.get(reader.read(6)? as usize)?);
+                }
+            }
+            4 => {
+                let count = reader.read([8, 16, 16][group])?;
+                for _ in 0..count {
+                    dest.push(reader.read(8)? as u8);
+                }
+            }
+            7 => {
+                // ECI designators are skipped, the payload is returned as raw bytes
+                let first = reader.read(8)?;
+                if first & 0x80 != 0 {
+                    reader.read(if first & 0x40 == 0 { 8 } else { 16 })?;
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    Some(dest)
+}
+
+/// Decode a sampled module grid
+fn decode_grid(grid: &[Vec<bool>], version: usize, gf: &Gf) -> Option<Vec<u8>> {
+    let (level, mask) = read_format(grid)?;
+    let mut codewords = vec![0u8; raw_codewords(version)];
+    for (i, (x, y)) in codeword_positions(version)
+        .into_iter()
+        .take(codewords.len() * 8)
+        .enumerate()
+    {
+        let bit = grid[y][x] ^ mask_bit(mask, x, y);
+        codewords[i / 8] |= (bit as u8) << (7 - i % 8);
+    }
+
+    let layout = blocks(version, level);
+    let mut data = Vec::new();
+    for (mut block, (d, e)) in deinterleave(&codewords, &layout).into_iter().zip(&layout) {
+        if !gf.correct(&mut block, *e) {
+            return None;
+        }
+        data.extend_from_slice(&block[..*d]);
+    }
+
+    decode_segments(&data, version)
+}
+
+/// Finder pattern candidate
+#[derive(Debug, Clone, Copy)]
+struct Finder {
+    x: f64,
+    y: f64,
+    module: f64,
+    count: usize,
+}
+
+/// Check run lengths for the 1:1:3:1:1 finder pattern ratio
+fn finder_ratio(runs: &[usize; 5]) -> bool {
+    let total: usize = runs.iter().sum();
+    if total < 7 {
+        return false;
+    }
+    let module = total as f64 / 7.0;
+    let v = module / 2.0;
+    runs.iter()
+        .zip([1.0, 1.0, 3.0, 1.0, 1.0])
+        .all(|(r, n)| (*r as f64 - module * n).abs() < v * n)
+}
+
+/// Measure the finder pattern through `(x, y)` along the given direction, returns the offset of
+/// the center from `(x, y)` and the total width
+fn cross_check(bin: &Binary, x: usize, y: usize, dx: isize, dy: isize) -> Option<(f64, usize)> {
+    if !bin.get(x, y) {
+        return None;
+    }
+
+    let inside = |i: isize| {
+        let (px, py) = (x as isize + dx * i, y as isize + dy * i);
+        px >= 0 && py >= 0 && (px as usize) < bin.width && (py as usize) < bin.height
+    };
+    let at = |i: isize| {
+        bin.get(
+            (x as isize + dx * i) as usize,
+            (y as isize + dy * i) as usize,
+        )
+    };
+
+    // Count the center run and two runs in each direction
+    let mut runs = [0usize; 5];
+    let mut i = 0;
+    while inside(i) && at(i) {
+        runs[2] += 1;
+        i -= 1;
+    }
+    let start = i + 1;
+    for (k, dark) in [(1, false), (0, true)] {
+        while inside(i) && at(i) == dark {
+            runs[k] += 1;
+            i -= 1;
+        }
+    }
+    let mut i = 1;
+    while inside(i) && at(i) {
+        runs[2] += 1;
+        i += 1;
+    }
+    let end = i - 1;
+    for (k, dark) in [(3, false), (4, true)] {
+        while inside(i) && at(i) == dark {
+            runs[k] += 1;
+            i += 1;
+        }
+    }
+
+    if runs.contains(&0) || !finder_ratio(&runs) {
+        return None;
+    }
+
+    Some(((start + end) as f64 / 2.0 + 0.5, runs.iter().sum()))
+}
+
+fn find_finders(bin: &Binary) -> Vec<Finder> {
+    let mut finders: Vec<Finder> = Vec::new();
+    for y in 0..bin.height {
+        let runs = bin.row_runs(y);
+        for w in runs.windows(5) {
+            if !w[0].2 || !w[2].2 || !w[4].2 {
+                continue;
+            }
+            let lengths = [w[0].1, w[1].1, w[2].1, w[3].1, w[4].1];
+            if !finder_ratio(&lengths) {
+                continue;
+            }
+
+            let cx = w[2].0 + w[2].1 / 2;
+            let (cy, vertical) = match cross_check(bin, cx, y, 0, 1) {
+                Some(c) => c,
+                None => continue,
+            };
+            let cy = cy + y as f64;
+            let cy_i = cy as usize;
+            let (cx, horizontal) = match cross_check(bin, cx, cy_i, 1, 0) {
+                Some(c) => (c.0 + cx as f64, c.1),
+                None => continue,
+            };
+            let module = (vertical + horizontal) as f64 / 14.0;
+
+            match finders.iter_mut().find(|f| {
+                (f.x - cx).abs() < f.module * 2.0
+                    && (f.y - cy).abs() < f.module * 2.0
+                    && (f.module - module).abs() < f.module
+            }) {
+                Some(f) => {
+                    let n = f.count as f64;
+                    f.x = (f.x * n + cx) / (n + 1.0);
+                    f.y = (f.y * n + cy) / (n + 1.0);
+                    f.module = (f.module * n + module) / (n + 1.0);
+                    f.count += 1;
+                }
+                None => finders.push(Finder {
+                    x: cx,
+                    y: cy,
+                    module,
+                    count: 1,
+                }),
+            }
+        }
+    }
+
+    finders.retain(|f| f.count >= 2);
+    finders.sort_by_key(|f| std::cmp::Reverse(f.count));
+    finders
+}
+
+fn distance(a: &Finder, b: &Finder) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+/// Order three finders as top-left, top-right and bottom-left
+fn order(f: [Finder; 3]) -> Option<[Finder; 3]> {
+    let d01 = distance(&f[0], &f[1]);
+    let d02 = distance(&f[0], &f[2]);
+    let d12 = distance(&f[1], &f[2]);
+
+    // The top-left finder is opposite to the longest side
+    let (a, b, c) = if d12 >= d01 && d12 >= d02 {
+        (f[0], f[1], f[2])
+    } else if d02 >= d01 && d02 >= d12 {
+        (f[1], f[0], f[2])
+    } else {
+        (f[2], f[0], f[1])
+    };
+
+    // The two legs should have roughly equal length and be roughly perpendicular
+    let (ab, ac) = (distance(&a, &b), distance(&a, &c));
+    if (ab - ac).abs() > 0.2 * ab.max(ac) {
+        return None;
+    }
+    let cross = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+    if (cross / (ab * ac)).abs() < 0.8 {
+        return None;
+    }
+
+    if cross > 0.0 {
+        Some([a, b, c])
+    } else {
+        Some([a, c, b])
+    }
+}
+
+/// Affine map from module coordinates to image coordinates given by the finder centers
+fn affine(f: &[Finder; 3], n: usize) -> Homography {
+    let s = (n - 7) as f64;
+    let (ux, uy) = ((f[1].x - f[0].x) / s, (f[1].y - f[0].y) / s);
+    let (vx, vy) = ((f[2].x - f[0].x) / s, (f[2].y - f[0].y) / s);
+    let tx = f[0].x - 3.5 * (ux + vx);
+    let ty = f[0].y - 3.5 * (uy + vy);
+    Homography([[ux, vx, tx], [uy, vy, ty], [0.0, 0.0, 1.0]])
+}
+
+/// Refine the module to image mapping using the bottom-right alignment pattern
+fn refine(bin: &Binary, h: Homography, version: usize) -> Homography {
+    let centers = alignment_centers(version);
+    let (ax, ay) = match centers.iter().max_by_key(|(x, y)| x + y) {
+        Some(c) => (c.0 as f64 + 0.5, c.1 as f64 + 0.5),
+        None => return h,
+    };
+
+    let m = h.0;
+    let (ux, uy, vx, vy) = (m[0][0], m[1][0], m[0][1], m[1][1]);
+    let module = ux.hypot(uy).max(vx.hypot(vy));
+    let expected = match h.transform_point([ax, ay]) {
+        Some(p) => p,
+        None => return h,
+    };
+
+    // Score positions by how well the 5x5 alignment pattern matches
+    let score = |cx: f64, cy: f64| -> usize {
+        let mut score = 0;
+        for j in -2i32..=2 {
+            for i in -2i32..=2 {
+                let dark = i.abs().max(j.abs()) != 1;
+                let x = cx + i as f64 * ux + j as f64 * vx;
+                let y = cy + i as f64 * uy + j as f64 * vy;
+                if bin.sample(x, y) == dark {
+                    score += 1;
+                }
+            }
+        }
+        score
+    };
+
+    let radius = (module * 4.0).ceil() as isize;
+    let mut best = (0, expected);
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let (x, y) = (expected[0] + dx as f64, expected[1] + dy as f64);
+            let s = score(x, y);
+            let closer = |p: [f64; 2]| (p[0] - expected[0]).hypot(p[1] - expected[1]);
+            if s > best.0 || (s == best.0 && closer([x, y]) < closer(best.1)) {
+                best = (s, [x, y]);
+            }
+        }
+    }
+
+    if best.0 < 23 {
+        return h;
+    }
+
+    let n = dimension(version) as f64;
+    let points = [[3.5, 3.5], [n - 3.5, 3.5], [3.5, n - 3.5]];
+    let mut data: Vec<([f64; 2], [f64; 2])> = points
+        .iter()
+        .filter_map(|p| h.transform_point(*p).map(|q| (*p, q)))
+        .collect();
+    data.push(([ax, ay], best.1));
+    Homography::estimate(&data).unwrap_or(h)
+}
+
+fn sample_grid(bin: &Binary, h: &Homography, n: usize) -> Vec<Vec<bool>> {
+    (0..n)
+        .map(|y| {
+            (0..n)
+                .map(
+                    |x| match h.transform_point([x as f64 + 0.5, y as f64 + 0.5]) {
+                        Some(p) => bin.sample(p[0], p[1]),
+                        None => false,
+                    },
+                )
+                .collect()
+        })
+        .collect()
+}
+
+fn decode(bin: &Binary, finders: [Finder; 3], gf: &Gf) -> Option<Code> {
+    let module = finders.iter().map(|f| f.module).sum::<f64>() / 3.0;
+    let legs = (distance(&finders[0], &finders[1]) + distance(&finders[0], &finders[2])) / 2.0;
+    let estimate = ((legs / module + 7.0 - 17.0) / 4.0).round() as isize;
+
+    for version in [
+        estimate,
+        estimate - 1,
+        estimate + 1,
+        estimate - 2,
+        estimate + 2,
+    ] {
+        if !(1..=40).contains(&version) {
+            continue;
+        }
+        let version = version as usize;
+        let n = dimension(version);
+        let h = refine(bin, affine(&finders, n), version);
+        let grid = sample_grid(bin, &h, n);
+        if let Some(payload) = decode_grid(&grid, version, gf) {
+            let nf = n as f64;
+            let corner = |x: f64, y: f64| h.transform_point([x, y]).unwrap_or([x, y]);
+            return Some(Code {
+                format: Format::Qr,
+                payload,
+                corners: [
+                    corner(0.0, 0.0),
+                    corner(nf, 0.0),
+                    corner(nf, nf),
+                    corner(0.0, nf),
+                ],
+            });
+        }
+    }
+
+    None
+}
+
+pub(crate) fn detect(bin: &Binary) -> Vec<Code> {
+    let gf = Gf::new();
+    let mut finders = find_finders(bin);
+    finders.truncate(16);
+
+    let mut codes = Vec::new();
+    let mut used = vec![false; finders.len()];
+    for i in 0..finders.len() {
+        for j in i + 1..finders.len() {
+            for k in j + 1..finders.len() {
+                if used[i] || used[j] || used[k] {
+                    continue;
+                }
+                let (a, b, c) = (finders[i], finders[j], finders[k]);
+                let sizes = [a.module, b.module, c.module];
+                let max = sizes.iter().cloned().fold(0.0, f64::max);
+                let min = sizes.iter().cloned().fold(f64::INFINITY, f64::min);
+                if min < max * 0.6 {
+                    continue;
+                }
+
+                if let Some(code) = order([a, b, c]).and_then(|f| decode(bin, f, &gf)) {
+                    used[i] = true;
+                    used[j] = true;
+                    used[k] = true;
+                    codes.push(code);
+                }
+            }
+        }
+    }
+    codes
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::*;
+
+    /// Encode `text` in byte mode, used to generate test images
+    pub(crate) fn encode(text: &[u8], version: usize, level: usize, mask: u8) -> Vec<Vec<bool>> {
+        let gf = Gf::new();
+        let n = dimension(version);
+        let layout = blocks(version, level);
+        let capacity: usize = layout.iter().map(|x| x.0).sum();
+
+        let mut bits = Vec::new();
+        let mut push = |value: u32, n: usize| {
+            for i in (0..n).rev() {
+                bits.push((value >> i) & 1 == 1);
+            }
+        };
+        push(4, 4);
+        push(text.len() as u32, if version <= 9 { 8 } else { 16 });
+        for b in text {
+            push(*b as u32, 8);
+        }
+        let terminator = (capacity * 8 - bits.len()).min(4);
+        bits.resize(bits.len() + terminator, false);
+        while bits.len() % 8 != 0 {
+            bits.push(false);
+        }
+        let mut data: Vec<u8> = bits
+            .chunks(8)
+            .map(|c| c.iter().fold(0, |acc, b| (acc << 1) | *b as u8))
+            .collect();
+        let mut pad = [0xec, 0x11].iter().cycle();
+        while data.len() < capacity {
+            data.push(*pad.next().unwrap());
+        }
+
+        // Split into blocks and interleave
+        let mut blocks = Vec::new();
+        let mut offset = 0;
+        for (d, e) in &layout {
+            let block = data[offset..offset + d].to_vec();
+            offset += d;
+            let ecc = gf.encode(&block, *e);
+            blocks.push((block, ecc));
+        }
+        let mut codewords = Vec::new();
+        let max = layout.iter().map(|x| x.0).max().unwrap();
+        for i in 0..max {
+            for (block, _) in &blocks {
+                if i < block.len() {
+                    codewords.push(block[i]);
+                }
+            }
+        }
+        for i in 0..layout[0].1 {
+            for (_, ecc) in &blocks {
+                codewords.push(ecc[i]);
+            }
+        }
+
+        let mut grid = vec![vec![false; n]; n];
+        for (i, row) in grid.iter_mut().enumerate() {
+            row[6] = i % 2 == 0;
+        }
+        for (i, m) in grid[6].iter_mut().enumerate() {
+            *m = i % 2 == 0;
+        }
+        for (cx, cy) in [(3, 3), (n - 4, 3), (3, n - 4)] {
+            for dy in -4isize..=4 {
+                for dx in -4isize..=4 {
+                    let (x, y) = (cx as isize + dx, cy as isize + dy);
+                    if x >= 0 && y >= 0 && (x as usize) < n && (y as usize) < n {
+                        let d = dx.abs().max(dy.abs());
+                        grid[y as usize][x as usize] = d != 2 && d != 4;
+                    }
+                }
+            }
+        }
+        for (cx, cy) in alignment_centers(version) {
+            for dy in -2isize..=2 {
+                for dx in -2isize..=2 {
+                    grid[(cy as isize + dy) as usize][(cx as isize + dx) as usize] =
+                        dx.abs().max(dy.abs()) != 1;
+                }
+            }
+        }
+        let level_bits = [1, 0, 3, 2][level];
+        let format = format_bits((level_bits << 3) | mask as u32);
+        for positions in format_positions(n) {
+            for (i, (x, y)) in positions.iter().enumerate() {
+                grid[*y][*x] = (format >> i) & 1 == 1;
+            }
+        }
+        grid[n - 8][8] = true;
+
+        for (i, (x, y)) in codeword_positions(version).into_iter().enumerate() {
+            let bit = i < codewords.len() * 8 && (codewords[i / 8] >> (7 - i % 8)) & 1 == 1;
+            grid[y][x] = bit ^ mask_bit(mask, x, y);
+        }
+
+        grid
+    }
+
+    /// Render a module grid with a quiet zone, rotated by `angle` degrees
+    pub(crate) fn render(grid: &[Vec<bool>], scale: f64, angle: f64) -> Image<u8, Gray> {
+        let n = grid.len() as f64;
+        let size = ((n + 8.0) * scale * 1.5) as usize;
+        let c = size as f64 / 2.0;
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let mut image = Image::<u8, Gray>::new((size, size));
+        image.for_each(|pt, mut px| {
+            let (x, y) = (pt.x as f64 + 0.5 - c, pt.y as f64 + 0.5 - c);
+            let (u, v) = (x * cos + y * sin, -x * sin + y * cos);
+            let (mx, my) = (u / scale + n / 2.0, v / scale + n / 2.0);
+            let dark = mx >= 0.0 && my >= 0.0 && mx < n && my < n && grid[my as usize][mx as usize];
+            px[0] = if dark { 0 } else { 255 };
+        });
+        image
+    }
+
+    #[test]
+    fn test_qr_tables() {
+        assert_eq!(alignment_positions(7), vec![6, 22, 38]);
+        assert_eq!(alignment_positions(40), vec![6, 30, 58, 86, 114, 142, 170]);
+        assert_eq!(raw_codewords(1), 26);
+        assert_eq!(blocks(1, 1), vec![(16, 10)]);
+        assert_eq!(blocks(5, 2), vec![(15, 18), (15, 18), (16, 18), (16, 18)]);
+        for version in 1..=40 {
+            let n = codeword_positions(version).len();
+            assert_eq!(n / 8, raw_codewords(version));
+        }
+    }
+
+    #[test]
+    fn test_qr_decode() {
+        let text = b"https://github.com/zshipko/image2-rs";
+        for (version, level, mask, angle) in [(3, 1, 2, 0.0), (4, 0, 5, 15.0), (7, 2, 0, -30.0)] {
+            let mut grid = encode(text, version, level, mask);
+
+            // Flip a few modules to exercise error correction
+            let n = grid.len();
+            grid[n / 2][n / 2 + 3] ^= true;
+            grid[n - 2][n - 5] ^= true;
+
+            let image = render(&grid, 4.0, angle);
+            let codes = codes::detect_qr(&image);
+            assert_eq!(codes.len(), 1, "version {}", version);
+            assert_eq!(codes[0].format, codes::Format::Qr);
+            assert_eq!(codes[0].payload, text);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+/// GF(256) arithmetic using the QR code polynomial `x^8 + x^4 + x^3 + x^2 + 1`
+pub(crate) struct Gf {
+    exp: [u8; 512],
+    log: [u8; 256],
+}
+
+impl Gf {
+    pub fn new() -> Gf {
+        let mut exp = [0; 512];
+        let mut log = [0; 256];
+        let mut x = 1u16;
+        for (i, e) in exp.iter_mut().take(255).enumerate() {
+            *e = x as u8;
+            log[x as usize] = i as u8;
+            x <<= 1;
+            if x & 0x100 != 0 {
+                x ^= 0x11d;
+            }
+        }
+        for i in 255..512 {
+            exp[i] = exp[i - 255];
+        }
+        Gf { exp, log }
+    }
+
+    pub fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+    }
+
+    pub fn div(&self, a: u8, b: u8) -> u8 {
+        if a == 0 {
+            return 0;
+        }
+        self.exp[self.log[a as usize] as usize + 255 - self.log[b as usize] as usize]
+    }
+
+    /// `alpha^n`
+    pub fn pow(&self, n: usize) -> u8 {
+        self.exp[n % 255]
+    }
+
+    /// Evaluate a polynomial with the lowest degree coefficient first
+    fn eval(&self, poly: &[u8], x: u8) -> u8 {
+        poly.iter().rev().fold(0, |acc, c| self.mul(acc, x) ^ c)
+    }
+
+    /// Generator polynomial `(x - a^0)(x - a^1)...(x - a^(n - 1))`, highest degree first without
+    /// the leading `1`
+    #[cfg(test)]
+    pub fn generator(&self, n: usize) -> Vec<u8> {
+        let mut g = vec![0; n];
+        g[n - 1] = 1;
+        let mut root = 1;
+        for _ in 0..n {
+            for j in 0..n {
+                g[j] = self.mul(g[j], root);
+                if j + 1 < n {
+                    g[j] ^= g[j + 1];
+                }
+            }
+            root = self.mul(root, 2);
+        }
+        g
+    }
+
+    /// Compute `n` error correction codewords for `data`
+    #[cfg(test)]
+    pub fn encode(&self, data: &[u8], n: usize) -> Vec<u8> {
+        let g = self.generator(n);
+        let mut rem = vec![0; n];
+        for b in data {
+            let factor = b ^ rem[0];
+            rem.remove(0);
+            rem.push(0);
+            for (r, g) in rem.iter_mut().zip(&g) {
+                *r ^= self.mul(*g, factor);
+            }
+        }
+        rem
+    }
+
+    /// Correct errors in a block of data followed by `n` error correction codewords in place,
+    /// returns `false` if the block can't be corrected
+    pub fn correct(&self, block: &mut [u8], n: usize) -> bool {
+        let len = block.len();
+
+        // Syndromes, the first codeword is the highest degree coefficient
+        let syndromes: Vec<u8> = (0..n)
+            .map(|j| {
+                let x = self.pow(j);
+                block.iter().fold(0, |acc, c| self.mul(acc, x) ^ c)
+            })
+            .collect();
+        if syndromes.iter().all(|s| *s == 0) {
+            return true;
+        }
+
+        // Berlekamp-Massey, polynomials are stored lowest degree first
+        let mut sigma = vec![1u8];
+        let mut prev = vec![1u8];
+        let mut l = 0;
+        let mut m = 1;
+        let mut b = 1u8;
+        for i in 0..n {
+            let mut d = syndromes[i];
+            for j in 1..=l.min(sigma.len() - 1) {
+                d ^= self.mul(sigma[j], syndromes[i - j]);
+            }
+
+            if d == 0 {
+                m += 1;
+                continue;
+            }
+
+            let coef = self.div(d, b);
+            let mut next = sigma.clone();
+            if next.len() < prev.len() + m {
+                next.resize(prev.len() + m, 0);
+            }
+            for (j, p) in prev.iter().enumerate() {
+                next[j + m] ^= self.mul(coef, *p);
+            }
+
+            if 2 * l <= i {
+                l = i + 1 - l;
+                prev = sigma;
+                b = d;
+                m = 1;
+            } else {
+                m += 1;
+            }
+            sigma = next;
+        }
+
+        while sigma.len() > 1 && sigma[sigma.len() - 1] == 0 {
+            sigma.pop();
+        }
+        let errors = sigma.len() - 1;
+        if errors == 0 || 2 * errors > n {
+            return false;
+        }
+
+        // Chien search, byte `i` corresponds to the location `a^(len - 1 - i)`
+        let mut positions = Vec::with_capacity(errors);
+        for i in 0..len {
+            let x_inv = self.pow(255 - (len - 1 - i) % 255);
+            if self.eval(&sigma, x_inv) == 0 {
+                positions.push(i);
+            }
+        }
+        if positions.len() != errors {
+            return false;
+        }
+
+        // Forney, error evaluator `omega = S(x) sigma(x) mod x^n`
+        let mut omega = vec![0u8; n];
+        for (i, s) in syndromes.iter().enumerate() {
+            for (j, c) in sigma.iter().enumerate() {
+                if i + j < n {
+                    omega[i + j] ^= self.mul(*s, *c);
+                }
+            }
+        }
+        let derivative: Vec<u8> = sigma
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, c)| if i % 2 == 1 { *c } else { 0 })
+            .collect();
+
+        for i in positions {
+            let x = self.pow(len - 1 - i);
+            let x_inv = self.div(1, x);
+            let denom = self.eval(&derivative, x_inv);
+            if denom == 0 {
+                return false;
+            }
+            let magnitude = self.mul(x, self.div(self.eval(&omega, x_inv), denom));
+            block[i] ^= magnitude;
+        }
+
+        syndromes_zero(self, block, n)
+    }
+}
+
+fn syndromes_zero(gf: &Gf, block: &[u8], n: usize) -> bool {
+    (0..n).all(|j| {
+        let x = gf.pow(j);
+        block.iter().fold(0, |acc, c| gf.mul(acc, x) ^ c) == 0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reed_solomon() {
+        let gf = Gf::new();
+        let data: Vec<u8> = (0..20).map(|i| (i * 37 + 11) as u8).collect();
+        let ecc = gf.encode(&data, 10);
+        let mut block = [data.clone(), ecc].concat();
+        assert!(gf.correct(&mut block.clone(), 10));
+
+        block[0] ^= 0x55;
+        block[7] ^= 0x01;
+        block[19] ^= 0xff;
+        block[25] ^= 0x80;
+        block[29] ^= 0x10;
+        assert!(gf.correct(&mut block, 10));
+        assert_eq!(&block[..20], &data[..]);
+
+        for i in 0..6 {
+            block[i * 3] ^= 0x21;
+        }
+        assert!(!gf.correct(&mut block, 10));
+    }
+}
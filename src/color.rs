@@ -18,6 +18,12 @@ pub trait Color:
     /// Index of alpha channel
     const ALPHA: Option<Channel> = None;
 
+    /// Colorspace of the stored values, `None` when the color isn't RGB based
+    const COLORSPACE: Option<Colorspace> = None;
+
+    /// True when the first three channels are stored as blue, green, red
+    const BGR_ORDER: bool = false;
+
     /// Convert from Self -> Rgb
     fn to_rgb(src: &Pixel<Self>, dest: &mut Pixel<Rgb>);
 
@@ -30,6 +36,412 @@ pub trait Color:
     }
 }
 
+/// Exact piecewise sRGB EOTF, converts an encoded sRGB value to linear. Negative values are
+/// mirrored so extended-range values round-trip
+pub fn srgb_to_linear(x: f64) -> f64 {
+    let a = x.abs();
+    let y = if a <= 0.04045 {
+        a / 12.92
+    } else {
+        ((a + 0.055) / 1.055).powf(2.4)
+    };
+    y.copysign(x)
+}
+
+/// Exact piecewise sRGB OETF, converts a linear value to encoded sRGB. Negative values are
+/// mirrored so extended-range values round-trip
+pub fn linear_to_srgb(x: f64) -> f64 {
+    let a = x.abs();
+    let y = if a <= 0.0031308 {
+        a * 12.92
+    } else {
+        1.055 * a.powf(1.0 / 2.4) - 0.055
+    };
+    y.copysign(x)
+}
+
+/// Luminance, in cd/m², that maps to a linear value of 1.0 when encoding or decoding PQ. This is
+/// the HDR reference white from ITU-R BT.2408
+pub const PQ_REFERENCE_WHITE: f64 = 203.0;
+
+const PQ_M1: f64 = 2610.0 / 16384.0;
+const PQ_M2: f64 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f64 = 3424.0 / 4096.0;
+const PQ_C2: f64 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f64 = 2392.0 / 4096.0 * 32.0;
+
+/// SMPTE ST 2084 (PQ) EOTF, converts an encoded value to linear light relative to
+/// `PQ_REFERENCE_WHITE`
+pub fn pq_to_linear(x: f64) -> f64 {
+    let p = x.clamp(0.0, 1.0).powf(1.0 / PQ_M2);
+    let y = ((p - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * p)).powf(1.0 / PQ_M1);
+    y * 10000.0 / PQ_REFERENCE_WHITE
+}
+
+/// SMPTE ST 2084 (PQ) inverse EOTF, converts linear light relative to `PQ_REFERENCE_WHITE` to an
+/// encoded value
+pub fn linear_to_pq(x: f64) -> f64 {
+    let y = (x * PQ_REFERENCE_WHITE / 10000.0)
+        .clamp(0.0, 1.0)
+        .powf(PQ_M1);
+    ((PQ_C1 + PQ_C2 * y) / (1.0 + PQ_C3 * y)).powf(PQ_M2)
+}
+
+const HLG_A: f64 = 0.17883277;
+const HLG_B: f64 = 0.28466892;
+const HLG_C: f64 = 0.55991073;
+
+/// ITU-R BT.2100 HLG inverse OETF, converts an encoded value to linear scene light in `[0, 1]`
+pub fn hlg_to_linear(x: f64) -> f64 {
+    let x = x.max(0.0);
+    if x <= 0.5 {
+        x * x / 3.0
+    } else {
+        (((x - HLG_C) / HLG_A).exp() + HLG_B) / 12.0
+    }
+}
+
+/// ITU-R BT.2100 HLG OETF, converts linear scene light in `[0, 1]` to an encoded value
+pub fn linear_to_hlg(x: f64) -> f64 {
+    let x = x.max(0.0);
+    if x <= 1.0 / 12.0 {
+        (3.0 * x).sqrt()
+    } else {
+        HLG_A * (12.0 * x - HLG_B).ln() + HLG_C
+    }
+}
+
+/// Linear Rec.709 -> Display P3 primaries, both D65
+const RGB_TO_P3: [[f64; 3]; 3] = [
+    [0.8224621, 0.1775380, 0.0000000],
+    [0.0331941, 0.9668058, 0.0000000],
+    [0.0170827, 0.0723974, 0.9105199],
+];
+
+const P3_TO_RGB: [[f64; 3]; 3] = [
+    [1.2249401, -0.2249404, 0.0000000],
+    [-0.0420569, 1.0420571, 0.0000000],
+    [-0.0196376, -0.0786361, 1.0982735],
+];
+
+/// Linear Rec.709 -> Rec.2020 primaries, both D65
+const RGB_TO_REC2020: [[f64; 3]; 3] = [
+    [0.6274040, 0.3292820, 0.0433136],
+    [0.0690970, 0.9195400, 0.0113612],
+    [0.0163916, 0.0880132, 0.8955950],
+];
+
+const REC2020_TO_RGB: [[f64; 3]; 3] = [
+    [1.6604910, -0.5876411, -0.0728499],
+    [-0.1245505, 1.1328999, -0.0083494],
+    [-0.0181508, -0.1005789, 1.1187297],
+];
+
+fn transform<A: Color, B: Color>(m: &[[f64; 3]; 3], src: &Pixel<A>, mut dest: &mut Pixel<B>) {
+    let (r, g, b) = (src[0], src[1], src[2]);
+    for (i, row) in m.iter().enumerate() {
+        dest[i] = row[0] * r + row[1] * g + row[2] * b;
+    }
+}
+
+/// Method used to bring colors that fall outside of the destination gamut back into range when
+/// converting between colors, see `Pixel::convert_mapped` and `filter::GamutMap`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GamutMapping {
+    /// Clamp each channel independently, this preserves in-gamut colors exactly but can shift hue
+    #[default]
+    Clip,
+
+    /// Desaturate out-of-gamut colors towards a gray of the same luminance until they fit, this
+    /// preserves hue and lightness
+    Compress,
+
+    /// Reduce Oklab chroma while preserving Oklab lightness and hue until the color fits
+    Perceptual,
+
+    /// Desaturate negative values towards a gray of the same luminance, then compress values
+    /// above the knee, in `[0, 1)`, with a smooth shoulder that approaches but never reaches 1.
+    /// Every channel is scaled by the same amount so hue is preserved
+    Rolloff(f64),
+
+    /// Replace out-of-gamut colors with the given linear `Rgb` color, used to preview which
+    /// areas of an image would be affected
+    Highlight([f64; 3]),
+}
+
+impl GamutMapping {
+    /// Convert linear `Rgb` to a space where valid values are in `[0, 1]` using `to_space`,
+    /// values outside of that range are brought back in using the mapping method
+    pub(crate) fn map_into<D: Color>(
+        &self,
+        rgb: &Pixel<Rgb>,
+        to_space: impl Fn(&Pixel<Rgb>) -> Pixel<D>,
+    ) -> Pixel<D> {
+        let fits = |px: &Pixel<Rgb>| {
+            to_space(px)
+                .iter()
+                .all(|x| (-1e-9..=1.0 + 1e-9).contains(x))
+        };
+        if fits(rgb) {
+            return to_space(rgb).clamped();
+        }
+
+        let mapped = match *self {
+            GamutMapping::Clip => rgb.clone(),
+            GamutMapping::Compress => desaturate(rgb, fits),
+            GamutMapping::Perceptual => {
+                let mut lab: Pixel<OkLab> = rgb.convert();
+                if lab[0] >= 1.0 {
+                    let mut white = Pixel::new();
+                    white.fill(1.0);
+                    return to_space(&white).clamped();
+                }
+                if lab[0] <= 0.0 {
+                    return to_space(&Pixel::new()).clamped();
+                }
+
+                let (a, b) = (lab[1], lab[2]);
+                let t = search(|t| {
+                    lab[1] = a * t;
+                    lab[2] = b * t;
+                    fits(&lab.convert())
+                });
+                lab[1] = a * t;
+                lab[2] = b * t;
+                lab.convert()
+            }
+            GamutMapping::Rolloff(knee) => {
+                let knee = knee.clamp(0.0, 1.0 - 1e-6);
+                let mut px = desaturate(rgb, |px| to_space(px).iter().all(|x| *x >= -1e-9));
+                let max = to_space(&px).iter().cloned().fold(0.0, f64::max);
+                if max > knee {
+                    let range = 1.0 - knee;
+                    let shoulder = knee + range * ((max - knee) / range).tanh();
+                    px.map(|x| x * shoulder / max);
+                }
+                px
+            }
+            GamutMapping::Highlight(color) => Pixel::from_slice(color),
+        };
+
+        // Clip any remaining error, luminance outside of the destination range can't be fixed by
+        // desaturating
+        to_space(&mapped).clamped()
+    }
+}
+
+/// Largest value of `t` in `[0, 1]` where `f(t)` is true, assuming `f(0)` is true
+fn search(mut f: impl FnMut(f64) -> bool) -> f64 {
+    let (mut lo, mut hi) = (0.0, 1.0);
+    for _ in 0..24 {
+        let t = (lo + hi) / 2.0;
+        if f(t) {
+            lo = t;
+        } else {
+            hi = t;
+        }
+    }
+    lo
+}
+
+/// Mix linear `Rgb` with a gray of the same luminance as little as possible so that `fits` is
+/// true
+fn desaturate(rgb: &Pixel<Rgb>, fits: impl Fn(&Pixel<Rgb>) -> bool) -> Pixel<Rgb> {
+    if fits(rgb) {
+        return rgb.clone();
+    }
+    let y = rgb[0] * 0.2126 + rgb[1] * 0.7152 + rgb[2] * 0.0722;
+    let mix = |t: f64| {
+        let mut px = rgb.clone();
+        px.map(|x| y + t * (x - y));
+        px
+    };
+    mix(search(|t| fits(&mix(t))))
+}
+
+/// RGB primaries used as a gamut mapping target, all use a D65 white point
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Gamut {
+    /// ITU-R BT.709 primaries, shared by `Rgb` and `Srgb`
+    #[default]
+    Rec709,
+
+    /// Display P3 primaries
+    DisplayP3,
+
+    /// ITU-R BT.2020 primaries
+    Rec2020,
+}
+
+impl Gamut {
+    /// Convert linear `Rgb` to linear values using the gamut's primaries
+    pub fn from_rgb(&self, rgb: &Pixel<Rgb>) -> Pixel<Rgb> {
+        let mut dest = Pixel::new();
+        match self {
+            Gamut::Rec709 => {
+                dest.copy_from(rgb);
+            }
+            Gamut::DisplayP3 => transform(&RGB_TO_P3, rgb, &mut dest),
+            Gamut::Rec2020 => transform(&RGB_TO_REC2020, rgb, &mut dest),
+        }
+        dest
+    }
+
+    /// Convert linear values using the gamut's primaries to linear `Rgb`
+    pub fn to_rgb(&self, px: &Pixel<Rgb>) -> Pixel<Rgb> {
+        let mut dest = Pixel::new();
+        match self {
+            Gamut::Rec709 => {
+                dest.copy_from(px);
+            }
+            Gamut::DisplayP3 => transform(&P3_TO_RGB, px, &mut dest),
+            Gamut::Rec2020 => transform(&REC2020_TO_RGB, px, &mut dest),
+        }
+        dest
+    }
+
+    /// Returns true when linear `Rgb` falls inside of the gamut, allowing for the rounding error
+    /// of the conversion matrices
+    pub fn contains(&self, rgb: &Pixel<Rgb>) -> bool {
+        self.from_rgb(rgb)
+            .iter()
+            .all(|x| (-1e-5..=1.0 + 1e-5).contains(x))
+    }
+}
+
+/// Transfer function used to encode color values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransferFunction {
+    /// Values are linear light
+    #[default]
+    Linear,
+
+    /// Piecewise sRGB transfer function
+    Srgb,
+
+    /// SMPTE ST 2084 (PQ), see `pq_to_linear`
+    Pq,
+
+    /// ITU-R BT.2100 HLG, see `hlg_to_linear`
+    Hlg,
+}
+
+impl TransferFunction {
+    /// Convert an encoded value to linear light
+    pub fn to_linear(&self, x: f64) -> f64 {
+        match self {
+            TransferFunction::Linear => x,
+            TransferFunction::Srgb => srgb_to_linear(x),
+            TransferFunction::Pq => pq_to_linear(x),
+            TransferFunction::Hlg => hlg_to_linear(x),
+        }
+    }
+
+    /// Encode a linear light value
+    pub fn from_linear(&self, x: f64) -> f64 {
+        match self {
+            TransferFunction::Linear => x,
+            TransferFunction::Srgb => linear_to_srgb(x),
+            TransferFunction::Pq => linear_to_pq(x),
+            TransferFunction::Hlg => linear_to_hlg(x),
+        }
+    }
+}
+
+/// Describes how the values stored in an image should be interpreted, see `Image::colorspace`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Colorspace {
+    /// Color primaries
+    pub primaries: Gamut,
+
+    /// Transfer function
+    pub transfer: TransferFunction,
+}
+
+impl Colorspace {
+    /// Linear Rec.709, used by `Rgb`
+    pub const LINEAR_SRGB: Colorspace = Colorspace::new(Gamut::Rec709, TransferFunction::Linear);
+
+    /// sRGB, used by `Srgb`
+    pub const SRGB: Colorspace = Colorspace::new(Gamut::Rec709, TransferFunction::Srgb);
+
+    /// Display P3, used by `DisplayP3`
+    pub const DISPLAY_P3: Colorspace = Colorspace::new(Gamut::DisplayP3, TransferFunction::Srgb);
+
+    /// Linear Rec.2020, used by `Rec2020`
+    pub const REC2020: Colorspace = Colorspace::new(Gamut::Rec2020, TransferFunction::Linear);
+
+    /// Rec.2100 PQ, used by `Rec2100Pq`
+    pub const REC2100_PQ: Colorspace = Colorspace::new(Gamut::Rec2020, TransferFunction::Pq);
+
+    /// Rec.2100 HLG, used by `Rec2100Hlg`
+    pub const REC2100_HLG: Colorspace = Colorspace::new(Gamut::Rec2020, TransferFunction::Hlg);
+
+    /// Create a new `Colorspace`
+    pub const fn new(primaries: Gamut, transfer: TransferFunction) -> Colorspace {
+        Colorspace {
+            primaries,
+            transfer,
+        }
+    }
+
+    /// Parse a colorspace name, this accepts the names used by OpenImageIO and OpenColorIO such
+    /// as `sRGB`, `Linear` or `lin_rec2020`
+    pub fn from_name(name: impl AsRef<str>) -> Option<Colorspace> {
+        match name.as_ref().to_ascii_lowercase().as_str() {
+            "linear" | "scene_linear" | "lin_rec709" | "lin_srgb" | "linear rec.709 (srgb)" => {
+                Some(Colorspace::LINEAR_SRGB)
+            }
+            "srgb" | "srgb_rec709_scene" | "srgb_texture" | "srgb - texture" => {
+                Some(Colorspace::SRGB)
+            }
+            "displayp3" | "display p3" | "srgb_p3d65" => Some(Colorspace::DISPLAY_P3),
+            "rec2020" | "lin_rec2020" | "linear rec.2020" => Some(Colorspace::REC2020),
+            "rec2100pq" | "rec2100-pq" => Some(Colorspace::REC2100_PQ),
+            "rec2100hlg" | "rec2100-hlg" => Some(Colorspace::REC2100_HLG),
+            _ => None,
+        }
+    }
+
+    /// Convert `px` from `self` to `dest` in-place, the alpha channel is left unchanged and
+    /// primaries are only converted when there are at least three color channels
+    pub fn convert_pixel<C: Color>(&self, dest: &Colorspace, mut px: &mut Pixel<C>) {
+        if self == dest {
+            return;
+        }
+
+        let channels: Vec<Channel> = (0..C::CHANNELS).filter(|c| C::ALPHA != Some(*c)).collect();
+        for c in &channels {
+            px[*c] = self.transfer.to_linear(px[*c]);
+        }
+
+        if self.primaries != dest.primaries && channels.len() >= 3 {
+            // BGR colors store the same values as RGB in reverse order
+            let rgb_index = if C::NAME.starts_with("bgr") {
+                [channels[2], channels[1], channels[0]]
+            } else {
+                [channels[0], channels[1], channels[2]]
+            };
+            let mut rgb = Pixel::<Rgb>::new();
+            for (i, c) in rgb_index.iter().enumerate() {
+                rgb[i] = px[*c];
+            }
+            let rgb = dest.primaries.from_rgb(&self.primaries.to_rgb(&rgb));
+            for (i, c) in rgb_index.iter().enumerate() {
+                px[*c] = rgb[i];
+            }
+        }
+
+        for c in &channels {
+            px[*c] = dest.transfer.from_linear(px[*c]);
+        }
+    }
+}
+
 macro_rules! color {
     ($t:ident, $doc:expr) => {
         #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -45,13 +457,14 @@ color!(Gray, "Single-channel grayscale");
 impl Color for Gray {
     const NAME: &'static str = "gray";
     const CHANNELS: Channel = 1;
+    const COLORSPACE: Option<Colorspace> = Some(Colorspace::LINEAR_SRGB);
 
     fn to_rgb(src: &Pixel<Self>, pixel: &mut Pixel<Rgb>) {
         pixel.fill(src[0]);
     }
 
     fn from_rgb(src: &Pixel<Rgb>, mut dest: &mut Pixel<Self>) {
-        dest[0] = src[0] * 0.21 + src[1] * 0.72 + src[2] * 0.7;
+        dest[0] = src[0] * 0.21 + src[1] * 0.72 + src[2] * 0.07;
     }
 }
 
@@ -59,6 +472,7 @@ color!(Rgb, "Three-channel red, green, blue");
 impl Color for Rgb {
     const NAME: &'static str = "rgb";
     const CHANNELS: Channel = 3;
+    const COLORSPACE: Option<Colorspace> = Some(Colorspace::LINEAR_SRGB);
 
     fn to_rgb(rgb: &Pixel<Self>, pixel: &mut Pixel<Rgb>) {
         pixel.copy_from(rgb);
@@ -69,19 +483,41 @@ impl Color for Rgb {
     }
 }
 
-color!(Srgb, "Log space, three-channel red, green, blue");
+color!(
+    LinearRgb,
+    "Three-channel red, green, blue with linear transfer, this is an alias for `Rgb`"
+);
+impl Color for LinearRgb {
+    const NAME: &'static str = "rgb";
+    const CHANNELS: Channel = 3;
+    const COLORSPACE: Option<Colorspace> = Some(Colorspace::LINEAR_SRGB);
+
+    fn to_rgb(rgb: &Pixel<Self>, pixel: &mut Pixel<Rgb>) {
+        pixel.copy_from_slice(rgb);
+    }
+
+    fn from_rgb(rgb: &Pixel<Rgb>, pixel: &mut Pixel<Self>) {
+        pixel.copy_from_slice(rgb);
+    }
+}
+
+color!(
+    Srgb,
+    "Three-channel red, green, blue encoded using the piecewise sRGB transfer function"
+);
 impl Color for Srgb {
     const NAME: &'static str = "rgb";
     const CHANNELS: Channel = 3;
+    const COLORSPACE: Option<Colorspace> = Some(Colorspace::SRGB);
 
     fn to_rgb(rgb: &Pixel<Self>, pixel: &mut Pixel<Rgb>) {
         pixel.copy_from_slice(rgb);
-        pixel.gamma_lin();
+        pixel.srgb_to_linear();
     }
 
     fn from_rgb(rgb: &Pixel<Rgb>, pixel: &mut Pixel<Self>) {
         pixel.copy_from_slice(rgb);
-        pixel.gamma_log();
+        pixel.linear_to_srgb();
     }
 }
 
@@ -90,6 +526,7 @@ impl Color for Rgba {
     const NAME: &'static str = "rgba";
     const CHANNELS: Channel = 4;
     const ALPHA: Option<Channel> = Some(3);
+    const COLORSPACE: Option<Colorspace> = Some(Colorspace::LINEAR_SRGB);
 
     fn to_rgb(pixel: &Pixel<Self>, mut rgb: &mut Pixel<Rgb>) {
         rgb[0] = pixel[0] * pixel[3];
@@ -107,18 +544,19 @@ impl Color for Rgba {
 
 color!(
     Srgba,
-    "Log space, four-channel red, green, blue with alpha channel"
+    "Four-channel red, green, blue with alpha channel encoded using the piecewise sRGB transfer function"
 );
 impl Color for Srgba {
     const NAME: &'static str = "rgba";
     const CHANNELS: Channel = 4;
     const ALPHA: Option<Channel> = Some(3);
+    const COLORSPACE: Option<Colorspace> = Some(Colorspace::SRGB);
 
     fn to_rgb(pixel: &Pixel<Self>, mut rgb: &mut Pixel<Rgb>) {
         rgb[0] = pixel[0] * pixel[3];
         rgb[1] = pixel[1] * pixel[3];
         rgb[2] = pixel[2] * pixel[3];
-        rgb.gamma_lin();
+        rgb.srgb_to_linear();
     }
 
     fn from_rgb(rgb: &Pixel<Rgb>, mut pixel: &mut Pixel<Self>) {
@@ -126,72 +564,207 @@ impl Color for Srgba {
         pixel[1] = rgb[1];
         pixel[2] = rgb[2];
         pixel[3] = 1.0;
-        pixel.gamma_log();
+        pixel[0] = linear_to_srgb(pixel[0]);
+        pixel[1] = linear_to_srgb(pixel[1]);
+        pixel[2] = linear_to_srgb(pixel[2]);
+    }
+}
+
+color!(Bgr, "Three-channel blue, green, red");
+impl Color for Bgr {
+    const NAME: &'static str = "bgr";
+    const CHANNELS: Channel = 3;
+    const COLORSPACE: Option<Colorspace> = Some(Colorspace::LINEAR_SRGB);
+    const BGR_ORDER: bool = true;
+
+    fn to_rgb(pixel: &Pixel<Self>, mut rgb: &mut Pixel<Rgb>) {
+        rgb[0] = pixel[2];
+        rgb[1] = pixel[1];
+        rgb[2] = pixel[0];
+    }
+
+    fn from_rgb(rgb: &Pixel<Rgb>, mut pixel: &mut Pixel<Self>) {
+        pixel[0] = rgb[2];
+        pixel[1] = rgb[1];
+        pixel[2] = rgb[0];
+    }
+}
+
+color!(Bgra, "Four-channel blue, green, red with alpha channel");
+impl Color for Bgra {
+    const NAME: &'static str = "bgra";
+    const CHANNELS: Channel = 4;
+    const ALPHA: Option<Channel> = Some(3);
+    const COLORSPACE: Option<Colorspace> = Some(Colorspace::LINEAR_SRGB);
+    const BGR_ORDER: bool = true;
+
+    fn to_rgb(pixel: &Pixel<Self>, mut rgb: &mut Pixel<Rgb>) {
+        rgb[0] = pixel[2] * pixel[3];
+        rgb[1] = pixel[1] * pixel[3];
+        rgb[2] = pixel[0] * pixel[3];
+    }
+
+    fn from_rgb(rgb: &Pixel<Rgb>, mut pixel: &mut Pixel<Self>) {
+        pixel[0] = rgb[2];
+        pixel[1] = rgb[1];
+        pixel[2] = rgb[0];
+        pixel[3] = 1.0;
     }
 }
 
-color!(Xyz, "Three-channel CIE-XYZ");
+color!(Xyz, "Three-channel CIE-XYZ, D65 white point");
 impl Color for Xyz {
     const NAME: &'static str = "xyz";
     const CHANNELS: Channel = 3;
 
     fn from_rgb(rgb: &Pixel<Rgb>, mut pixel: &mut Pixel<Self>) {
-        let mut r = rgb[0];
-        let mut g = rgb[1];
-        let mut b = rgb[2];
+        let (r, g, b) = (rgb[0], rgb[1], rgb[2]);
+        pixel[0] = r * 0.4124564 + g * 0.3575761 + b * 0.1804375;
+        pixel[1] = r * 0.2126729 + g * 0.7151522 + b * 0.0721750;
+        pixel[2] = r * 0.0193339 + g * 0.1191920 + b * 0.9503041;
+    }
 
-        if r > 0.04045 {
-            r = ((r + 0.055) / 1.055).powf(2.4)
-        } else {
-            r /= 12.92
-        }
+    fn to_rgb(px: &Pixel<Xyz>, mut rgb: &mut Pixel<Rgb>) {
+        let (x, y, z) = (px[0], px[1], px[2]);
+        rgb[0] = x * 3.2404542 + y * -1.5371385 + z * -0.4985314;
+        rgb[1] = x * -0.9692660 + y * 1.8760108 + z * 0.0415560;
+        rgb[2] = x * 0.0556434 + y * -0.2040259 + z * 1.0572252;
+    }
+}
 
-        if g > 0.04045 {
-            g = ((g + 0.055) / 1.055).powf(2.4);
-        } else {
-            g /= 12.92
-        }
+color!(
+    DisplayP3,
+    "Three-channel Display P3, D65 white point encoded using the sRGB transfer function"
+);
+impl Color for DisplayP3 {
+    const NAME: &'static str = "rgb";
+    const CHANNELS: Channel = 3;
+    const COLORSPACE: Option<Colorspace> = Some(Colorspace::DISPLAY_P3);
 
-        if b > 0.04045 {
-            b = ((b + 0.055) / 1.055).powf(2.4)
-        } else {
-            b /= 12.92
-        }
+    fn to_rgb(px: &Pixel<Self>, rgb: &mut Pixel<Rgb>) {
+        let mut tmp = px.clone();
+        tmp.srgb_to_linear();
+        transform(&P3_TO_RGB, &tmp, rgb);
+    }
 
-        pixel[0] = r * 0.4124 + g * 0.3576 + b * 0.1805;
-        pixel[1] = r * 0.2126 + g * 0.7152 + b * 0.0722;
-        pixel[2] = r * 0.0193 + g * 0.1192 + b * 0.9505;
+    fn from_rgb(rgb: &Pixel<Rgb>, px: &mut Pixel<Self>) {
+        transform(&RGB_TO_P3, rgb, px);
+        px.linear_to_srgb();
     }
+}
 
-    fn to_rgb(px: &Pixel<Xyz>, mut rgb: &mut Pixel<Rgb>) {
-        let x = px[0];
-        let y = px[1];
-        let z = px[2];
-
-        rgb[0] = {
-            let var_r = x * 3.2406 + y * -1.5372 + z * -0.4986;
-            if var_r > 0.0031308 {
-                1.055 * (var_r.powf(1.0 / 2.4)) - 0.055
-            } else {
-                12.92 * var_r
-            }
-        };
-        rgb[1] = {
-            let var_g = x * -0.9689 + y * 1.8758 + z * 0.0415;
-            if var_g > 0.0031308 {
-                1.055 * (var_g.powf(1. / 2.4)) - 0.055
-            } else {
-                12.92 * var_g
-            }
-        };
-        rgb[2] = {
-            let var_b = x * 0.0557 + y * -0.2040 + z * 1.0570;
-            if var_b > 0.0031308 {
-                1.055 * (var_b.powf(1. / 2.4)) - 0.055
-            } else {
-                12.92 * var_b
-            }
-        };
+color!(
+    Rec2020,
+    "Three-channel linear ITU-R BT.2020, D65 white point"
+);
+impl Color for Rec2020 {
+    const NAME: &'static str = "rgb";
+    const CHANNELS: Channel = 3;
+    const COLORSPACE: Option<Colorspace> = Some(Colorspace::REC2020);
+
+    fn to_rgb(px: &Pixel<Self>, rgb: &mut Pixel<Rgb>) {
+        transform(&REC2020_TO_RGB, px, rgb);
+    }
+
+    fn from_rgb(rgb: &Pixel<Rgb>, px: &mut Pixel<Self>) {
+        transform(&RGB_TO_REC2020, rgb, px);
+    }
+}
+
+color!(
+    Rec2100Pq,
+    "Three-channel ITU-R BT.2100 using BT.2020 primaries and the PQ transfer function, a linear value of 1.0 is `PQ_REFERENCE_WHITE`"
+);
+impl Color for Rec2100Pq {
+    const NAME: &'static str = "rgb";
+    const CHANNELS: Channel = 3;
+    const COLORSPACE: Option<Colorspace> = Some(Colorspace::REC2100_PQ);
+
+    fn to_rgb(px: &Pixel<Self>, rgb: &mut Pixel<Rgb>) {
+        let mut tmp = px.clone();
+        tmp.map(pq_to_linear);
+        transform(&REC2020_TO_RGB, &tmp, rgb);
+    }
+
+    fn from_rgb(rgb: &Pixel<Rgb>, px: &mut Pixel<Self>) {
+        transform(&RGB_TO_REC2020, rgb, px);
+        px.map(linear_to_pq);
+    }
+}
+
+color!(
+    Rec2100Hlg,
+    "Three-channel ITU-R BT.2100 using BT.2020 primaries and the HLG transfer function, scene light is not passed through the OOTF"
+);
+impl Color for Rec2100Hlg {
+    const NAME: &'static str = "rgb";
+    const CHANNELS: Channel = 3;
+    const COLORSPACE: Option<Colorspace> = Some(Colorspace::REC2100_HLG);
+
+    fn to_rgb(px: &Pixel<Self>, rgb: &mut Pixel<Rgb>) {
+        let mut tmp = px.clone();
+        tmp.map(hlg_to_linear);
+        transform(&REC2020_TO_RGB, &tmp, rgb);
+    }
+
+    fn from_rgb(rgb: &Pixel<Rgb>, px: &mut Pixel<Self>) {
+        transform(&RGB_TO_REC2020, rgb, px);
+        px.map(linear_to_hlg);
+    }
+}
+
+color!(
+    OkLab,
+    "Three-channel Oklab lightness, green-red and blue-yellow, `a` and `b` are signed so floating point storage is recommended"
+);
+impl Color for OkLab {
+    const NAME: &'static str = "oklab";
+    const CHANNELS: Channel = 3;
+
+    fn from_rgb(rgb: &Pixel<Rgb>, mut pixel: &mut Pixel<Self>) {
+        let (r, g, b) = (rgb[0], rgb[1], rgb[2]);
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+        pixel[0] = 0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s;
+        pixel[1] = 1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s;
+        pixel[2] = 0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s;
+    }
+
+    fn to_rgb(px: &Pixel<Self>, mut rgb: &mut Pixel<Rgb>) {
+        let (l, a, b) = (px[0], px[1], px[2]);
+        let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+        let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+        let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+        rgb[0] = 4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_;
+        rgb[1] = -1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_;
+        rgb[2] = -0.0041960863 * l_ - 0.7034186147 * m_ + 1.7076147010 * s_;
+    }
+}
+
+color!(
+    OkLch,
+    "Three-channel Oklab lightness, chroma and hue, hue is stored in the range `[0, 1)`"
+);
+impl Color for OkLch {
+    const NAME: &'static str = "oklch";
+    const CHANNELS: Channel = 3;
+
+    fn from_rgb(rgb: &Pixel<Rgb>, mut pixel: &mut Pixel<Self>) {
+        let lab: Pixel<OkLab> = rgb.convert();
+        let h = lab[2].atan2(lab[1]) / std::f64::consts::TAU;
+        pixel[0] = lab[0];
+        pixel[1] = lab[1].hypot(lab[2]);
+        pixel[2] = if h < 0.0 { h + 1.0 } else { h };
+    }
+
+    fn to_rgb(px: &Pixel<Self>, rgb: &mut Pixel<Rgb>) {
+        let mut lab = Pixel::<OkLab>::new();
+        let h = px[2] * std::f64::consts::TAU;
+        lab[0] = px[0];
+        lab[1] = px[1] * h.cos();
+        lab[2] = px[1] * h.sin();
+        OkLab::to_rgb(&lab, rgb);
     }
 }
 
@@ -1,3 +1,4 @@
+use crate::kernel::EdgeStrategy;
 use crate::*;
 
 /// Wraps image data slices, tagging them with a Color type
@@ -171,3 +172,94 @@ impl<'a, T: 'a + Type, C: 'a + Color> IntoIterator for DataMut<'a, T, C> {
         self.0.iter_mut()
     }
 }
+
+/// Read-only view of the pixels surrounding a single pixel, see `Image::map_neighborhood`. Offsets
+/// are relative to the center pixel and coordinates outside of the image are mapped using the
+/// edge strategy
+pub struct Neighborhood<'a, T: Type, C: Color> {
+    image: &'a Image<T, C>,
+    center: Point,
+    radius: usize,
+    edge: EdgeStrategy,
+}
+
+impl<'a, T: Type, C: Color> std::fmt::Debug for Neighborhood<'a, T, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Neighborhood")
+            .field("center", &self.center)
+            .field("radius", &self.radius)
+            .field("edge", &self.edge)
+            .finish()
+    }
+}
+
+impl<'a, T: Type, C: Color> Neighborhood<'a, T, C> {
+    pub(crate) fn new(
+        image: &'a Image<T, C>,
+        center: Point,
+        radius: usize,
+        edge: EdgeStrategy,
+    ) -> Self {
+        Neighborhood {
+            image,
+            center,
+            radius,
+            edge,
+        }
+    }
+
+    /// Position of the center pixel in the image
+    pub fn center(&self) -> Point {
+        self.center
+    }
+
+    /// Window radius, offsets from `-radius` to `radius` are part of the neighborhood
+    pub fn radius(&self) -> usize {
+        self.radius
+    }
+
+    /// Image the neighborhood belongs to
+    pub fn image(&self) -> &'a Image<T, C> {
+        self.image
+    }
+
+    /// Get the data at an offset from the center, returns `None` when the offset is outside of the
+    /// image and the edge strategy is `EdgeStrategy::Constant`
+    pub fn get(&self, dx: isize, dy: isize) -> Option<Data<'a, T, C>> {
+        let x = self
+            .edge
+            .map_point(self.center.x as isize + dx, self.image.width() as isize - 1)?;
+        let y = self.edge.map_point(
+            self.center.y as isize + dy,
+            self.image.height() as isize - 1,
+        )?;
+        Some(self.image.get((x, y)))
+    }
+
+    /// Get a normalized channel value at an offset from the center, pixels outside of the image
+    /// with `EdgeStrategy::Constant` are `0`
+    pub fn get_f(&self, dx: isize, dy: isize, c: Channel) -> f64 {
+        self.get(dx, dy)
+            .map(|data| data[c].to_norm())
+            .unwrap_or(0.0)
+    }
+
+    /// Get the pixel at an offset from the center
+    pub fn get_pixel(&self, dx: isize, dy: isize) -> Pixel<C> {
+        self.get(dx, dy)
+            .map(|data| data.to_pixel())
+            .unwrap_or_default()
+    }
+
+    /// Iterate over every `(dx, dy)` offset in the window in row-major order
+    pub fn offsets(&self) -> impl Iterator<Item = (isize, isize)> {
+        let r = self.radius as isize;
+        (-r..=r).flat_map(move |dy| (-r..=r).map(move |dx| (dx, dy)))
+    }
+
+    /// Iterate over every pixel in the window along with its offset
+    pub fn pixels(&self) -> impl Iterator<Item = ((isize, isize), Pixel<C>)> + '_ {
+        self.offsets()
+            .map(move |(dx, dy)| ((dx, dy), self.get_pixel(dx, dy)))
+    }
+}
@@ -0,0 +1,174 @@
+use crate::fft::{fft2, Complex};
+use crate::*;
+
+/// Padded FFT layout used to move image channels into the frequency domain
+struct Spectrum {
+    width: usize,
+    height: usize,
+    padded_width: usize,
+    padded_height: usize,
+}
+
+impl Spectrum {
+    fn new(size: Size, psf: &Kernel) -> Spectrum {
+        let padded_width = (size.width + psf.cols()).next_power_of_two();
+        let padded_height = (size.height + psf.rows()).next_power_of_two();
+        Spectrum {
+            width: size.width,
+            height: size.height,
+            padded_width,
+            padded_height,
+        }
+    }
+
+    /// Map a padded coordinate back into the image, the padding is filled with the nearest edge
+    /// so the periodic boundary of the FFT doesn't introduce hard edges
+    fn source_index(i: usize, n: usize, padded: usize) -> usize {
+        if i < n {
+            i
+        } else if i - n < (padded - n) / 2 {
+            n - 1
+        } else {
+            0
+        }
+    }
+
+    fn load<T: Type, C: Color>(&self, image: &Image<T, C>, c: Channel) -> Vec<f64> {
+        let mut dest = Vec::with_capacity(self.padded_width * self.padded_height);
+        for y in 0..self.padded_height {
+            let sy = Self::source_index(y, self.height, self.padded_height);
+            for x in 0..self.padded_width {
+                let sx = Self::source_index(x, self.width, self.padded_width);
+                dest.push(image.get_f((sx, sy), c));
+            }
+        }
+        dest
+    }
+
+    fn store<T: Type, C: Color>(&self, data: &[f64], image: &mut Image<T, C>, c: Channel) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                image.set_f((x, y), c, data[y * self.padded_width + x]);
+            }
+        }
+    }
+
+    /// Transfer function of `psf`, the kernel center is moved to the origin. Kernels are applied
+    /// as a correlation, so the kernel is flipped to match the blur produced by `Image::run`
+    fn transfer(&self, psf: &Kernel) -> Vec<Complex> {
+        let (w, h) = (self.padded_width, self.padded_height);
+        let mut data = vec![Complex::default(); w * h];
+        let (r2, c2) = (psf.rows() / 2, psf.cols() / 2);
+        for j in 0..psf.rows() {
+            for i in 0..psf.cols() {
+                let x = (c2 + w - i) % w;
+                let y = (r2 + h - j) % h;
+                data[y * w + x].re += psf.get(j, i);
+            }
+        }
+        fft2(&mut data, w, h, false);
+        data
+    }
+
+    fn forward(&self, data: &[f64]) -> Vec<Complex> {
+        let mut data = data.iter().map(|x| Complex::new(*x, 0.0)).collect();
+        fft2(&mut data, self.padded_width, self.padded_height, false);
+        data
+    }
+
+    fn inverse(&self, mut data: Vec<Complex>) -> Vec<f64> {
+        fft2(&mut data, self.padded_width, self.padded_height, true);
+        data.into_iter().map(|x| x.re).collect()
+    }
+
+    /// Convolve `data` with the PSF, or correlate when `conj` is true
+    fn convolve(&self, data: &[f64], h: &[Complex], conj: bool) -> Vec<f64> {
+        let mut f = self.forward(data);
+        f.iter_mut().zip(h).for_each(|(f, h)| {
+            *f = *f * if conj { h.conj() } else { *h };
+        });
+        self.inverse(f)
+    }
+}
+
+/// Richardson–Lucy deconvolution, `psf` is the point spread function that blurred `image`
+pub fn richardson_lucy<T: Type, C: Color>(
+    image: &Image<T, C>,
+    psf: &Kernel,
+    iterations: usize,
+) -> Image<T, C> {
+    let spectrum = Spectrum::new(image.size(), psf);
+    let h = spectrum.transfer(psf);
+    let mut dest = image.new_like();
+
+    for c in 0..C::CHANNELS {
+        let observed = spectrum.load(image, c);
+        let mut estimate = observed.clone();
+        for _ in 0..iterations {
+            let blurred = spectrum.convolve(&estimate, &h, false);
+            let ratio: Vec<f64> = observed
+                .iter()
+                .zip(blurred)
+                .map(|(o, b)| if b.abs() > 1e-12 { o / b } else { 0.0 })
+                .collect();
+            let correction = spectrum.convolve(&ratio, &h, true);
+            estimate
+                .iter_mut()
+                .zip(correction)
+                .for_each(|(e, c)| *e = (*e * c).max(0.0));
+        }
+        spectrum.store(&estimate, &mut dest, c);
+    }
+
+    dest
+}
+
+/// Wiener deconvolution, `noise` is the noise-to-signal power ratio used to regularize the
+/// inverse filter
+pub fn wiener<T: Type, C: Color>(image: &Image<T, C>, psf: &Kernel, noise: f64) -> Image<T, C> {
+    let spectrum = Spectrum::new(image.size(), psf);
+    let h = spectrum.transfer(psf);
+    let mut dest = image.new_like();
+
+    for c in 0..C::CHANNELS {
+        let mut f = spectrum.forward(&spectrum.load(image, c));
+        f.iter_mut().zip(&h).for_each(|(f, h)| {
+            *f = (*f * h.conj()).scale(1.0 / (h.norm_sqr() + noise));
+        });
+        spectrum.store(&spectrum.inverse(f), &mut dest, c);
+    }
+
+    dest
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_deconv() {
+        // Linear deconvolution can't restore frequencies removed by the PSF, so the test image is
+        // made of soft blobs instead of single pixels to avoid measuring ringing
+        let mut points = Image::<f32, Gray>::new((32, 32));
+        points.set_f((16, 16), 0, 1.0);
+        points.set_f((8, 20), 0, 0.5);
+        let image: Image<f32, Gray> = points.run(Kernel::gaussian_2d(1.5, 1.5, 0.0), None);
+
+        let psf = Kernel::gaussian_5x5();
+        let blurred = image.run(psf.clone(), None);
+        let err = |a: &Image<f32, Gray>| {
+            a.data()
+                .iter()
+                .zip(image.data())
+                .map(|(a, b)| (a - b).abs())
+                .sum::<f32>()
+        };
+
+        let rl = deconv::richardson_lucy(&blurred, &psf, 50);
+        assert!(err(&rl) < err(&blurred));
+        assert!(rl.get_f((16, 16), 0) > blurred.get_f((16, 16), 0));
+
+        let w = deconv::wiener(&blurred, &psf, 1e-3);
+        assert!(err(&w) < err(&blurred));
+    }
+}
@@ -0,0 +1,210 @@
+use crate::*;
+
+/// Single sample of a deep pixel
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DeepSample {
+    /// Premultiplied red, green, blue and alpha
+    pub color: [f32; 4],
+
+    /// Distance to the front of the sample
+    pub depth: f32,
+
+    /// Distance to the back of the sample, equal to `depth` for point samples
+    pub depth_back: f32,
+}
+
+impl DeepSample {
+    /// Create a new point sample
+    pub fn new(color: [f32; 4], depth: f32) -> DeepSample {
+        DeepSample {
+            color,
+            depth,
+            depth_back: depth,
+        }
+    }
+}
+
+/// Image with a variable number of depth-sorted samples per pixel, as stored in OpenEXR deep
+/// files
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeepImage {
+    size: Size,
+
+    /// Start index into `samples` for each pixel, with a final entry equal to `samples.len()`
+    offsets: Vec<usize>,
+    samples: Vec<DeepSample>,
+}
+
+impl DeepImage {
+    /// Create a new deep image with no samples
+    pub fn new(size: impl Into<Size>) -> DeepImage {
+        let size = size.into();
+        DeepImage {
+            size,
+            offsets: vec![0; size.width * size.height + 1],
+            samples: Vec::new(),
+        }
+    }
+
+    /// Create a new deep image using `f` to generate the samples of each pixel, samples are
+    /// sorted by depth
+    pub fn from_fn(
+        size: impl Into<Size>,
+        mut f: impl FnMut(Point) -> Vec<DeepSample>,
+    ) -> DeepImage {
+        let size = size.into();
+        let mut offsets = Vec::with_capacity(size.width * size.height + 1);
+        let mut samples = Vec::new();
+        for y in 0..size.height {
+            for x in 0..size.width {
+                offsets.push(samples.len());
+                let mut px = f(Point::new(x, y));
+                sort_samples(&mut px);
+                samples.extend(px);
+            }
+        }
+        offsets.push(samples.len());
+        DeepImage {
+            size,
+            offsets,
+            samples,
+        }
+    }
+
+    /// Read deep image from disk, this requires the `oiio` feature
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<DeepImage, Error> {
+        let path = path.as_ref();
+        io::read_deep(path).map_err(|e| e.with_path(path))
+    }
+
+    /// Image size
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Image width
+    pub fn width(&self) -> usize {
+        self.size.width
+    }
+
+    /// Image height
+    pub fn height(&self) -> usize {
+        self.size.height
+    }
+
+    /// Total number of samples
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
+    }
+
+    fn index(&self, pt: Point) -> usize {
+        pt.y * self.size.width + pt.x
+    }
+
+    /// Samples at `pt` sorted from front to back
+    pub fn samples(&self, pt: impl Into<Point>) -> &[DeepSample] {
+        let i = self.index(pt.into());
+        &self.samples[self.offsets[i]..self.offsets[i + 1]]
+    }
+
+    /// Composite the samples at `pt` front to back, returns premultiplied RGBA
+    pub fn flatten_pixel(&self, pt: impl Into<Point>) -> [f32; 4] {
+        let mut dest = [0.0f32; 4];
+        for sample in self.samples(pt) {
+            let remaining = 1.0 - dest[3];
+            if remaining <= 0.0 {
+                break;
+            }
+            for (d, s) in dest.iter_mut().zip(sample.color) {
+                *d += s * remaining;
+            }
+        }
+        dest
+    }
+
+    /// Composite all samples into a flat image
+    pub fn flatten<T: Type>(&self) -> Image<T, Rgba> {
+        let mut dest = Image::new(self.size);
+        dest.each_pixel_mut(|pt, mut px| {
+            let [r, g, b, a] = self.flatten_pixel(pt);
+            let unpremultiply = |x: f32| if a > 0.0 { (x / a) as f64 } else { 0.0 };
+            px[0] = unpremultiply(r);
+            px[1] = unpremultiply(g);
+            px[2] = unpremultiply(b);
+            px[3] = a as f64;
+        });
+        dest
+    }
+
+    /// Merge the samples of two deep images, unlike compositing flat images this handles
+    /// interleaved depths correctly
+    pub fn composite(&self, other: &DeepImage) -> Result<DeepImage, Error> {
+        if self.size != other.size {
+            return Err(Error::InvalidDimensions(other.width(), other.height(), 4));
+        }
+
+        Ok(DeepImage::from_fn(self.size, |pt| {
+            let mut px = self.samples(pt).to_vec();
+            px.extend_from_slice(other.samples(pt));
+            px
+        }))
+    }
+
+    /// Remove samples further away than `depth`
+    pub fn clip(&self, depth: f32) -> DeepImage {
+        DeepImage::from_fn(self.size, |pt| {
+            self.samples(pt)
+                .iter()
+                .filter(|s| s.depth <= depth)
+                .copied()
+                .collect()
+        })
+    }
+}
+
+fn sort_samples(samples: &mut [DeepSample]) {
+    samples.sort_by(|a, b| {
+        a.depth
+            .total_cmp(&b.depth)
+            .then(a.depth_back.total_cmp(&b.depth_back))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_deep_image() {
+        let red = DeepImage::from_fn((2, 1), |pt| {
+            vec![DeepSample::new(
+                [0.5, 0.0, 0.0, 0.5],
+                2.0 + pt.x as f32 * 2.0,
+            )]
+        });
+        let blue = DeepImage::from_fn((2, 1), |_| {
+            vec![
+                DeepSample::new([0.0, 0.0, 1.0, 1.0], 3.0),
+                DeepSample::new([0.0, 0.0, 0.0, 0.0], 1.0),
+            ]
+        });
+        assert_eq!(blue.samples((0, 0))[0].depth, 1.0);
+
+        // Red is in front of blue in the first pixel and behind it in the second
+        let merged = red.composite(&blue).unwrap();
+        assert_eq!(merged.sample_count(), 6);
+        assert_eq!(merged.flatten_pixel((0, 0)), [0.5, 0.0, 0.5, 1.0]);
+        assert_eq!(merged.flatten_pixel((1, 0)), [0.0, 0.0, 1.0, 1.0]);
+
+        let flat: Image<f32, Rgba> = red.flatten();
+        assert_eq!(flat.get_f((0, 0), 0), 1.0);
+        assert_eq!(flat.get_f((0, 0), 3), 0.5);
+
+        assert_eq!(merged.clip(2.5).flatten_pixel((1, 0)), [0.0; 4]);
+        assert!(red.composite(&DeepImage::new((1, 1))).is_err());
+
+        // Without an I/O backend reading returns an error instead of panicking
+        #[cfg(all(not(feature = "magick"), not(feature = "oiio")))]
+        assert!(DeepImage::open("images/deep.exr").is_err());
+    }
+}
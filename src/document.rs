@@ -0,0 +1,294 @@
+use crate::*;
+
+/// Separable blend modes, see the W3C Compositing and Blending specification
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BlendMode {
+    /// Source replaces the backdrop
+    #[default]
+    Normal,
+
+    /// Product of source and backdrop
+    Multiply,
+
+    /// Inverse of the product of the inverted source and backdrop
+    Screen,
+
+    /// Multiply or screen depending on the backdrop
+    Overlay,
+
+    /// Minimum of source and backdrop
+    Darken,
+
+    /// Maximum of source and backdrop
+    Lighten,
+
+    /// Sum of source and backdrop
+    Add,
+
+    /// Absolute difference between source and backdrop
+    Difference,
+}
+
+impl BlendMode {
+    /// Blend a single normalized channel of `source` with `backdrop`
+    pub fn blend(&self, backdrop: f64, source: f64) -> f64 {
+        let (b, s) = (backdrop, source);
+        match self {
+            BlendMode::Normal => s,
+            BlendMode::Multiply => b * s,
+            BlendMode::Screen => b + s - b * s,
+            BlendMode::Overlay => {
+                if b <= 0.5 {
+                    2.0 * b * s
+                } else {
+                    let b = 2.0 * b - 1.0;
+                    b + s - b * s
+                }
+            }
+            BlendMode::Darken => b.min(s),
+            BlendMode::Lighten => b.max(s),
+            BlendMode::Add => b + s,
+            BlendMode::Difference => (b - s).abs(),
+        }
+    }
+}
+
+/// Image layer
+pub struct Layer {
+    /// Layer name
+    pub name: String,
+
+    /// Layer contents, with straight (not premultiplied) alpha
+    pub image: Image<f32, Rgba>,
+
+    /// Position of the top-left corner of the layer in the document
+    pub offset: (isize, isize),
+
+    /// Opacity between `0` and `1`
+    pub opacity: f64,
+
+    /// Blend mode used when compositing onto the layers below
+    pub blend_mode: BlendMode,
+
+    /// Layer mask, in layer coordinates
+    pub mask: Option<Mask>,
+
+    /// Hidden layers are skipped when flattening
+    pub visible: bool,
+}
+
+impl std::fmt::Debug for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Layer")
+            .field("name", &self.name)
+            .field("size", &self.image.size())
+            .field("offset", &self.offset)
+            .field("opacity", &self.opacity)
+            .field("blend_mode", &self.blend_mode)
+            .field("mask", &self.mask)
+            .field("visible", &self.visible)
+            .finish()
+    }
+}
+
+impl Layer {
+    /// Create a new layer from an image
+    pub fn new<T: Type, C: Color>(name: impl Into<String>, image: &Image<T, C>) -> Layer {
+        Layer {
+            name: name.into(),
+            image: image.convert(),
+            offset: (0, 0),
+            opacity: 1.0,
+            blend_mode: BlendMode::Normal,
+            mask: None,
+            visible: true,
+        }
+    }
+
+    /// Set offset
+    pub fn with_offset(mut self, x: isize, y: isize) -> Layer {
+        self.offset = (x, y);
+        self
+    }
+
+    /// Set opacity
+    pub fn with_opacity(mut self, opacity: f64) -> Layer {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Set blend mode
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Layer {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Set layer mask
+    pub fn with_mask(mut self, mask: Mask) -> Layer {
+        self.mask = Some(mask);
+        self
+    }
+}
+
+/// Group of layers, children are composited together before the result is blended onto the
+/// layers below the group
+#[derive(Debug)]
+pub struct Group {
+    /// Group name
+    pub name: String,
+
+    /// Child nodes, from bottom to top
+    pub children: Vec<Node>,
+
+    /// Opacity between `0` and `1`
+    pub opacity: f64,
+
+    /// Blend mode used when compositing onto the layers below
+    pub blend_mode: BlendMode,
+
+    /// Hidden groups are skipped when flattening
+    pub visible: bool,
+}
+
+impl Group {
+    /// Create a new, empty group
+    pub fn new(name: impl Into<String>) -> Group {
+        Group {
+            name: name.into(),
+            children: Vec::new(),
+            opacity: 1.0,
+            blend_mode: BlendMode::Normal,
+            visible: true,
+        }
+    }
+
+    /// Add a layer or group on top of the existing children
+    pub fn push(&mut self, node: impl Into<Node>) -> &mut Self {
+        self.children.push(node.into());
+        self
+    }
+}
+
+/// Entry in the layer tree
+#[derive(Debug)]
+pub enum Node {
+    /// Single layer
+    Layer(Box<Layer>),
+
+    /// Layer group
+    Group(Group),
+}
+
+impl From<Layer> for Node {
+    fn from(layer: Layer) -> Node {
+        Node::Layer(Box::new(layer))
+    }
+}
+
+impl From<Group> for Node {
+    fn from(group: Group) -> Node {
+        Node::Group(group)
+    }
+}
+
+/// Straight alpha RGBA compositing buffer
+type Canvas = Vec<[f64; 4]>;
+
+/// Layered document
+#[derive(Debug)]
+pub struct Document {
+    /// Document size
+    pub size: Size,
+
+    /// Layers and groups, from bottom to top
+    pub layers: Vec<Node>,
+}
+
+impl Document {
+    /// Create a new, empty document
+    pub fn new(size: impl Into<Size>) -> Document {
+        Document {
+            size: size.into(),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Add a layer or group on top of the existing layers
+    pub fn push(&mut self, node: impl Into<Node>) -> &mut Self {
+        self.layers.push(node.into());
+        self
+    }
+
+    /// Composite all visible layers, areas not covered by any layer are transparent
+    pub fn flatten(&self) -> Image<f32, Rgba> {
+        let canvas = self.composite(&self.layers);
+        let mut dest = Image::new(self.size);
+        dest.each_pixel_mut(|pt, mut px| {
+            let value = canvas[pt.y * self.size.width + pt.x];
+            for (c, x) in value.iter().enumerate() {
+                px[c] = *x;
+            }
+        });
+        dest
+    }
+
+    fn composite(&self, nodes: &[Node]) -> Canvas {
+        let mut canvas = vec![[0.0; 4]; self.size.width * self.size.height];
+        for node in nodes {
+            match node {
+                Node::Layer(layer) if layer.visible => self.composite_layer(&mut canvas, layer),
+                Node::Group(group) if group.visible => {
+                    let source = self.composite(&group.children);
+                    for (backdrop, source) in canvas.iter_mut().zip(source) {
+                        blend(backdrop, source, group.opacity, group.blend_mode);
+                    }
+                }
+                _ => (),
+            }
+        }
+        canvas
+    }
+
+    fn composite_layer(&self, canvas: &mut Canvas, layer: &Layer) {
+        let (w, h) = (self.size.width as isize, self.size.height as isize);
+        layer.image.each_pixel(|pt, px| {
+            let (x, y) = (
+                pt.x as isize + layer.offset.0,
+                pt.y as isize + layer.offset.1,
+            );
+            if x < 0 || y < 0 || x >= w || y >= h {
+                return;
+            }
+
+            let coverage = layer.mask.as_ref().map_or(1.0, |mask| mask.get(pt));
+            let source = [px[0], px[1], px[2], px[3]];
+            blend(
+                &mut canvas[(y * w + x) as usize],
+                source,
+                layer.opacity * coverage,
+                layer.blend_mode,
+            );
+        });
+    }
+}
+
+/// Composite `source` over `backdrop`, both using straight alpha
+fn blend(backdrop: &mut [f64; 4], source: [f64; 4], opacity: f64, mode: BlendMode) {
+    let source_alpha = (source[3] * opacity).clamp(0.0, 1.0);
+    let backdrop_alpha = backdrop[3];
+    let alpha = source_alpha + backdrop_alpha * (1.0 - source_alpha);
+    if alpha <= 0.0 {
+        *backdrop = [0.0; 4];
+        return;
+    }
+
+    for c in 0..3 {
+        let blended = mode.blend(backdrop[c], source[c]);
+        let mixed = (1.0 - backdrop_alpha) * source[c] + backdrop_alpha * blended;
+        let premultiplied =
+            source_alpha * mixed + (1.0 - source_alpha) * backdrop_alpha * backdrop[c];
+        backdrop[c] = premultiplied / alpha;
+    }
+    backdrop[3] = alpha;
+}
@@ -29,6 +29,29 @@ pub enum Error {
     #[error("Multiple images not supported in image: {0}")]
     MultipleImagesNotSupported(String),
 
+    /// An allocation failed or would exceed the memory budget, sizes are in bytes
+    #[error("Out of memory: requested {requested} bytes, {available} available")]
+    OutOfMemory {
+        /// Size of the failed allocation
+        requested: usize,
+
+        /// Bytes left in the budget, `0` when the system allocator failed
+        available: usize,
+    },
+
+    /// A file exceeds one of the limits in `io::DecodeOptions`, it was rejected before decoding
+    #[error("Decode limit exceeded: {limit} is {value}, the maximum is {max}")]
+    LimitExceeded {
+        /// Name of the limit: `width`, `height`, `bytes` or `frames`
+        limit: &'static str,
+
+        /// Value found in the file
+        value: usize,
+
+        /// Configured maximum
+        max: usize,
+    },
+
     /// Invalid image data type
     #[error("Invalid data type")]
     InvalidType,
@@ -52,7 +75,137 @@ pub enum Error {
     #[error("GLFW init: {0}")]
     GLFWInit(#[from] glfw::InitError),
 
+    /// A thread pool could not be created, see `ExecutionOptions::with_threads`
+    #[cfg(feature = "parallel")]
+    #[error("Thread pool: {0}")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+
     /// Wraps `std::io::Error`
     #[error("I/O: {0}")]
     IO(#[from] std::io::Error),
+
+    /// A file exists but couldn't be decoded
+    #[error("Unable to decode {} as {format}: {reason}", path.display())]
+    Decode {
+        /// File path
+        path: std::path::PathBuf,
+
+        /// File format, usually the file extension
+        format: String,
+
+        /// Message from the decoder
+        reason: String,
+    },
+
+    /// An image can't be represented using the requested color
+    #[error("Unsupported color: requested {requested}, found {found}")]
+    UnsupportedColor {
+        /// Requested color or channel count
+        requested: String,
+
+        /// Color or channel count of the source
+        found: String,
+    },
+
+    /// Data can't be reinterpreted as the requested type
+    #[error("Invalid cast: {0:?}")]
+    Cast(bytemuck::PodCastError),
+
+    /// Image shapes don't match, shapes are `(width, height, channels)`
+    #[error("Shape mismatch: expected {expected:?}, got {got:?}")]
+    ShapeMismatch {
+        /// Expected shape
+        expected: (usize, usize, usize),
+
+        /// Actual shape
+        got: (usize, usize, usize),
+    },
+
+    /// Filter inputs are tagged with different colorspaces, see `ColorspaceMismatch::Error`
+    #[error("Colorspace mismatch: expected {expected:?}, found {found:?}")]
+    ColorspaceMismatch {
+        /// Colorspace of the first tagged input
+        expected: crate::Colorspace,
+
+        /// Colorspace of the first input that doesn't match
+        found: crate::Colorspace,
+    },
+
+    /// Error message reported by OpenImageIO
+    #[error("OpenImageIO: {0}")]
+    Oiio(String),
+
+    /// A strict conversion would have lost data, see `Image::try_convert`
+    #[error("Lossy conversion: {0}")]
+    LossyConversion(ConversionLoss),
+
+    /// Adds the path of the file being processed to another error
+    #[error("{}: {source}", path.display())]
+    WithPath {
+        /// File path
+        path: std::path::PathBuf,
+
+        /// Underlying error
+        #[source]
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Attach a file path to an error, errors that already include a path are returned unchanged
+    pub fn with_path(self, path: impl AsRef<std::path::Path>) -> Error {
+        if self.path().is_some() {
+            return self;
+        }
+
+        Error::WithPath {
+            path: path.as_ref().to_path_buf(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Path of the file associated with the error, if available
+    pub fn path(&self) -> Option<&std::path::Path> {
+        match self {
+            Error::Decode { path, .. } | Error::WithPath { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+/// Describes data lost by a conversion, see `Image::try_convert` and `Pixel::convert_checked`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConversionLoss {
+    /// Number of values that were outside of the range of the destination type and clamped
+    pub clipped: usize,
+
+    /// Number of pixels with a non-opaque alpha value when the destination color has no alpha
+    /// channel
+    pub alpha_dropped: usize,
+
+    /// Set when the destination type has less precision than the source type
+    pub reduced_bit_depth: bool,
+}
+
+impl ConversionLoss {
+    /// Returns true when no data is lost
+    pub fn is_lossless(&self) -> bool {
+        self.clipped == 0 && self.alpha_dropped == 0 && !self.reduced_bit_depth
+    }
+}
+
+impl std::fmt::Display for ConversionLoss {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if self.clipped > 0 {
+            parts.push(format!("{} values clipped", self.clipped));
+        }
+        if self.alpha_dropped > 0 {
+            parts.push(format!("alpha dropped from {} pixels", self.alpha_dropped));
+        }
+        if self.reduced_bit_depth {
+            parts.push("bit depth reduced".to_string());
+        }
+        write!(f, "{}", parts.join(", "))
+    }
 }
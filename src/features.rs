@@ -0,0 +1,167 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::*;
+
+/// Number of orientation bins in each HOG cell histogram, covering unsigned angles in `[0, 180)`
+pub const HOG_BINS: usize = 9;
+
+/// Apply `f` to each index in `0..n`, in parallel when the `parallel` feature is enabled
+#[cfg(feature = "parallel")]
+fn map_indices<R: Send>(n: usize, f: impl Sync + Send + Fn(usize) -> R) -> Vec<R> {
+    (0..n).into_par_iter().map(f).collect()
+}
+
+/// Apply `f` to each index in `0..n`, in parallel when the `parallel` feature is enabled
+#[cfg(not(feature = "parallel"))]
+fn map_indices<R: Send>(n: usize, f: impl Sync + Send + Fn(usize) -> R) -> Vec<R> {
+    (0..n).map(f).collect()
+}
+
+/// Bilinear sample of a grayscale image, points outside of the image are clamped to the edge
+fn sample(image: &Image<f32, Gray>, x: f64, y: f64) -> f64 {
+    let x = x.clamp(0.0, (image.width() - 1) as f64);
+    let y = y.clamp(0.0, (image.height() - 1) as f64);
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = (
+        (x0 + 1).min(image.width() - 1),
+        (y0 + 1).min(image.height() - 1),
+    );
+    let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+    let get = |x, y| image.get((x, y))[0] as f64;
+    let top = get(x0, y0) * (1.0 - fx) + get(x1, y0) * fx;
+    let bottom = get(x0, y1) * (1.0 - fx) + get(x1, y1) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+/// Local binary pattern codes of the luminance of `image`. For each pixel `points` neighbors
+/// are sampled evenly on a circle of `radius` pixels, bit `i` of the code is set when neighbor
+/// `i` is at least as bright as the center. `points` must be between 1 and 32
+pub fn lbp<T: Type, C: Color>(image: &Image<T, C>, radius: f64, points: usize) -> Image<u32, Gray> {
+    assert!(
+        (1..=32).contains(&points),
+        "LBP supports between 1 and 32 points"
+    );
+
+    let gray: Image<f32, Gray> = image.convert();
+    let offsets: Vec<(f64, f64)> = (0..points)
+        .map(|i| {
+            let angle = 2.0 * std::f64::consts::PI * i as f64 / points as f64;
+            // Round away tiny errors so axis-aligned neighbors land exactly on pixels
+            let round = |x: f64| (x * 1e9).round() / 1e9;
+            (round(radius * angle.cos()), round(-radius * angle.sin()))
+        })
+        .collect();
+
+    let mut dest = Image::new(image.size());
+    dest.for_each(|pt, mut data| {
+        let center = gray.get(pt)[0] as f64;
+        let (x, y) = (pt.x as f64, pt.y as f64);
+        data[0] = offsets
+            .iter()
+            .enumerate()
+            .filter(|(_, (dx, dy))| sample(&gray, x + dx, y + dy) >= center)
+            .fold(0u32, |code, (i, _)| code | (1 << i));
+    });
+    dest
+}
+
+/// Histogram of oriented gradients descriptor, see `hog`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hog {
+    /// Number of blocks in each direction
+    pub blocks: Size,
+
+    /// Block size in cells
+    pub block_size: usize,
+
+    /// Concatenated block descriptors in row-major order, each block contains
+    /// `block_size * block_size * HOG_BINS` values
+    pub descriptor: Vec<f64>,
+}
+
+impl Hog {
+    /// Number of values in each block
+    pub fn block_len(&self) -> usize {
+        self.block_size * self.block_size * HOG_BINS
+    }
+
+    /// Get the normalized histograms for the block at `(x, y)`
+    pub fn block(&self, x: usize, y: usize) -> &[f64] {
+        let len = self.block_len();
+        let i = (y * self.blocks.width + x) * len;
+        &self.descriptor[i..i + len]
+    }
+}
+
+/// Histogram of oriented gradients of the luminance of `image`. Gradient magnitudes are
+/// accumulated into `HOG_BINS` orientation bins for each `cell`x`cell` pixel region, then
+/// overlapping blocks of `block`x`block` cells, with a stride of one cell, are normalized using
+/// L2-Hys. Images smaller than a block produce an empty descriptor
+pub fn hog<T: Type, C: Color>(image: &Image<T, C>, cell: usize, block: usize) -> Hog {
+    assert!(
+        cell > 0 && block > 0,
+        "cell and block size must be non-zero"
+    );
+
+    let gray: Image<f32, Gray> = image.convert();
+    let (width, height) = (image.width(), image.height());
+    let cells = Size::new(width / cell, height / cell);
+    let get = |x: usize, y: usize| gray.get((x, y))[0] as f64;
+
+    let histograms = map_indices(cells.width * cells.height, |i| {
+        let (cx, cy) = (i % cells.width, i / cells.width);
+        let mut hist = [0.0; HOG_BINS];
+        for y in cy * cell..(cy + 1) * cell {
+            for x in cx * cell..(cx + 1) * cell {
+                let gx = get((x + 1).min(width - 1), y) - get(x.saturating_sub(1), y);
+                let gy = get(x, (y + 1).min(height - 1)) - get(x, y.saturating_sub(1));
+                let magnitude = gx.hypot(gy);
+                if magnitude == 0.0 {
+                    continue;
+                }
+
+                // Split each vote between the two nearest bin centers
+                let angle = gy.atan2(gx).to_degrees().rem_euclid(180.0);
+                let bin = angle / (180.0 / HOG_BINS as f64) - 0.5;
+                let lower = bin.floor();
+                let frac = bin - lower;
+                let lower = (lower as isize).rem_euclid(HOG_BINS as isize) as usize;
+                hist[lower] += magnitude * (1.0 - frac);
+                hist[(lower + 1) % HOG_BINS] += magnitude * frac;
+            }
+        }
+        hist
+    });
+
+    let blocks = Size::new(
+        (cells.width + 1).saturating_sub(block),
+        (cells.height + 1).saturating_sub(block),
+    );
+    let descriptor = map_indices(blocks.width * blocks.height, |i| {
+        let (bx, by) = (i % blocks.width, i / blocks.width);
+        let mut values = Vec::with_capacity(block * block * HOG_BINS);
+        for y in by..by + block {
+            for x in bx..bx + block {
+                values.extend_from_slice(&histograms[y * cells.width + x]);
+            }
+        }
+
+        // L2-Hys: normalize, clip large values and normalize again
+        let normalize = |values: &mut Vec<f64>| {
+            let norm = (values.iter().map(|x| x * x).sum::<f64>() + 1e-12).sqrt();
+            values.iter_mut().for_each(|x| *x /= norm);
+        };
+        normalize(&mut values);
+        values.iter_mut().for_each(|x| *x = x.min(0.2));
+        normalize(&mut values);
+        values
+    })
+    .concat();
+
+    Hog {
+        blocks,
+        block_size: block,
+        descriptor,
+    }
+}
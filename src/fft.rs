@@ -0,0 +1,144 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Complex number
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    pub fn conj(self) -> Complex {
+        Complex::new(self.re, -self.im)
+    }
+
+    pub fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn scale(self, x: f64) -> Complex {
+        Complex::new(self.re * x, self.im * x)
+    }
+}
+
+impl std::ops::Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl std::ops::Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl std::ops::Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+/// In-place FFT, `data.len()` must be a power of two. The inverse transform is scaled by `1/n`
+pub(crate) fn fft(data: &mut [Complex], inverse: bool) {
+    let n = data.len();
+    assert!(n.is_power_of_two());
+
+    // Bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * std::f64::consts::PI / len as f64;
+        let w = Complex::new(angle.cos(), angle.sin());
+        for chunk in data.chunks_mut(len) {
+            let mut wk = Complex::new(1.0, 0.0);
+            for k in 0..len / 2 {
+                let a = chunk[k];
+                let b = chunk[k + len / 2] * wk;
+                chunk[k] = a + b;
+                chunk[k + len / 2] = a - b;
+                wk = wk * w;
+            }
+        }
+        len <<= 1;
+    }
+
+    if inverse {
+        let scale = 1.0 / n as f64;
+        data.iter_mut().for_each(|x| *x = x.scale(scale));
+    }
+}
+
+fn transpose(data: &[Complex], width: usize, height: usize) -> Vec<Complex> {
+    let mut dest = vec![Complex::default(); data.len()];
+    for y in 0..height {
+        for x in 0..width {
+            dest[x * height + y] = data[y * width + x];
+        }
+    }
+    dest
+}
+
+#[cfg(feature = "parallel")]
+fn fft_rows(data: &mut [Complex], width: usize, inverse: bool) {
+    data.par_chunks_mut(width).for_each(|row| fft(row, inverse));
+}
+
+#[cfg(not(feature = "parallel"))]
+fn fft_rows(data: &mut [Complex], width: usize, inverse: bool) {
+    data.chunks_mut(width).for_each(|row| fft(row, inverse));
+}
+
+/// 2-dimensional FFT over a row-major `width` x `height` buffer, both dimensions must be powers
+/// of two
+pub(crate) fn fft2(data: &mut Vec<Complex>, width: usize, height: usize, inverse: bool) {
+    fft_rows(data, width, inverse);
+    let mut t = transpose(data, width, height);
+    fft_rows(&mut t, height, inverse);
+    *data = transpose(&t, height, width);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fft_roundtrip() {
+        let input: Vec<Complex> = (0..64)
+            .map(|i| Complex::new((i as f64 * 0.3).sin(), 0.0))
+            .collect();
+        let mut data = input.clone();
+        fft2(&mut data, 8, 8, false);
+        fft2(&mut data, 8, 8, true);
+        for (a, b) in input.iter().zip(data) {
+            assert!((a.re - b.re).abs() < 1e-9 && b.im.abs() < 1e-9);
+        }
+    }
+}
@@ -29,9 +29,9 @@ pub struct AsyncPipeline<'a, T: 'a + Type, C: 'a + Color, U: 'a + Type, D: 'a +
     /// Intermediary image
     pub tmpconv: std::cell::UnsafeCell<Image<T, C>>,
 
-    pub(crate) image_schedule_filters: Vec<usize>,
-    pub(crate) j: usize,
-    pub(crate) index: usize,
+    pub(crate) filters: Vec<&'a dyn Filter<T, C, U, D>>,
+    pub(crate) plan: Plan,
+    pub(crate) stage: usize,
 }
 
 impl<'a, T: Type, C: Color, U: Unpin + Type, D: Unpin + Color> AsyncPipeline<'a, T, C, U, D> {
@@ -51,19 +51,20 @@ impl<'a, T: Type, C: Color, U: Unpin + Type, D: Unpin + Color> std::future::Futu
         ctx: &mut std::task::Context,
     ) -> std::task::Poll<Self::Output> {
         let p = std::pin::Pin::get_mut(self);
-        let pipeline = &p.pipeline;
-        let j = p.j;
-        let image_schedule_filters = &p.image_schedule_filters;
-        let index = p.index;
-        let input = &mut p.input;
-        let output = &mut p.output;
+        if p.filters.is_empty() {
+            p.input.images[0].convert_to(p.output);
+            return std::task::Poll::Ready(());
+        }
 
-        pipeline.loop_inner(input, output, &p.tmpconv, j, index, image_schedule_filters);
+        let stage = match p.plan.stages.get(p.stage) {
+            Some(stage) => stage.clone(),
+            None => return std::task::Poll::Ready(()),
+        };
 
-        if p.index != p.pipeline.filters.len() - 1 {
-            p.j += 1;
-            p.index = p.image_schedule_filters[p.j];
+        Pipeline::run_stage(&p.filters, &stage, &mut p.input, p.output, &p.tmpconv);
 
+        p.stage += 1;
+        if p.stage < p.plan.stages.len() {
             ctx.waker().wake_by_ref();
             return std::task::Poll::Pending;
         }
@@ -11,394 +11,3574 @@ pub fn convert<T: Type, C: Color, U: Type, D: Color>() -> impl Filter<T, C, U, D
 }
 
 impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Convert<D> {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[])
+    }
+
     fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
         input.get_pixel(pt, None).convert_to_data(dest);
     }
 }
 
-#[derive(Debug, Default)]
+/// Convert between colors, bringing out-of-gamut values into range, see `convert_mapped`
+#[derive(Clone, Copy, Default, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct Saturation(pub f64);
+struct ConvertMapped<T: Color>(GamutMapping, std::marker::PhantomData<T>);
 
-/// Adjust saturation
-pub fn saturation<T: Type, C: Color, U: Type, D: Color>(amt: f64) -> impl Filter<T, C, U, D> {
-    Saturation(amt)
+/// Create new color conversion filter using the given gamut mapping
+pub fn convert_mapped<T: Type, C: Color, U: Type, D: Color>(
+    mapping: GamutMapping,
+) -> impl Filter<T, C, U, D> {
+    ConvertMapped(mapping, std::marker::PhantomData)
 }
 
-impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Saturation {
-    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
-        let px = input.get_pixel(pt, None);
-        let mut tmp: Pixel<Hsv> = px.convert();
-        tmp[1] *= self.0;
-        tmp.convert_to_data(data);
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for ConvertMapped<D> {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        input
+            .get_pixel(pt, None)
+            .convert_mapped::<D>(self.0)
+            .copy_to_slice(dest);
     }
 }
 
-#[derive(Debug)]
+/// Bring colors into the gamut of `target_space`, this can be used to proof wide gamut images
+/// for narrower displays, see `gamut_map`
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct Brightness(f64);
+pub struct GamutMap {
+    /// Target gamut
+    pub target_space: Gamut,
 
-/// Adjust image brightness
-pub fn brightness<T: Type, C: Color, U: Type, D: Color>(amt: f64) -> impl Filter<T, C, U, D> {
-    Brightness(amt)
+    /// Mapping method
+    pub method: GamutMapping,
 }
 
-impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Brightness {
-    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
-        let mut px = input.get_pixel(pt, None);
-        px *= self.0;
-        px.convert_to_data(data);
+/// Create a new gamut mapping filter
+pub fn gamut_map<T: Type, C: Color, U: Type, D: Color>(
+    target_space: Gamut,
+    method: GamutMapping,
+) -> impl Filter<T, C, U, D> {
+    GamutMap {
+        target_space,
+        method,
     }
 }
 
-#[derive(Debug)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct Exposure(f64);
+impl GamutMap {
+    /// Map a linear `Rgb` color
+    pub fn map(&self, rgb: &Pixel<Rgb>) -> Pixel<Rgb> {
+        let gamut = self.target_space;
+        if gamut.contains(rgb) {
+            return rgb.clone();
+        }
+        gamut.to_rgb(&self.method.map_into(rgb, |px| gamut.from_rgb(px)))
+    }
+}
 
-/// Adjust image exposure, the argument is the number of stops to increase or decrease exposure by
-pub fn exposure<T: Type, C: Color, U: Type, D: Color>(stops: f64) -> impl Filter<T, C, U, D> {
-    Exposure(stops)
+impl<C: Color> PointFilter<C> for GamutMap {
+    fn apply(&self, px: &mut Pixel<C>) {
+        self.map(&px.convert()).convert_to(px);
+    }
 }
 
-impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Exposure {
-    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
-        let mut px = input.get_pixel(pt, None);
-        px *= 2f64.powf(self.0);
-        px.convert_to_data(data);
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for GamutMap {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let px: Pixel<Rgb> = input.get_pixel(pt, None).convert();
+        self.map(&px).convert_to_data(dest);
     }
 }
 
-#[derive(Debug)]
+/// Color model used to adjust hue and saturation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct Contrast(pub f64);
-
-/// Adjust image contrast
-pub fn contrast<T: Type, C: Color, U: Type, D: Color>(amt: f64) -> impl Filter<T, C, U, D> {
-    Contrast(amt)
-}
+pub enum HueModel {
+    /// HSV, fast but hues shift visibly as saturation changes
+    #[default]
+    Hsv,
 
-impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Contrast {
-    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
-        let mut px = input.get_pixel(pt, None);
-        px.map(|x| (self.0 * (x - 0.5)) + 0.5);
-        px.convert_to_data(data);
-    }
+    /// OkLch, perceptually uniform so hue and lightness are preserved
+    OkLch,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct Crop(Region);
+/// Saturation adjustment, see `saturation`
+pub struct Saturation(pub f64, pub HueModel);
 
-/// Crop an image
-pub fn crop<T: Type, C: Color, U: Type, D: Color>(r: Region) -> impl Filter<T, C, U, D> {
-    Crop(r)
+/// Adjust saturation
+pub fn saturation<T: Type, C: Color, U: Type, D: Color>(amt: f64) -> impl Filter<T, C, U, D> {
+    Saturation(amt, HueModel::Hsv)
 }
 
-impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Crop {
-    fn output_size(&self, _input: &Input<T, C>, _dest: &mut Image<U, D>) -> Size {
-        self.0.size
-    }
+/// Adjust saturation using the given color model
+pub fn saturation_with<T: Type, C: Color, U: Type, D: Color>(
+    amt: f64,
+    model: HueModel,
+) -> impl Filter<T, C, U, D> {
+    Saturation(amt, model)
+}
 
-    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
-        if pt.x > self.0.origin.x + self.0.size.width || pt.y > self.0.origin.y + self.0.size.height
-        {
-            return;
+impl<C: Color> PointFilter<C> for Saturation {
+    fn apply(&self, px: &mut Pixel<C>) {
+        match self.1 {
+            HueModel::Hsv => {
+                let mut tmp: Pixel<Hsv> = px.convert();
+                tmp[1] *= self.0;
+                tmp.convert_to(px);
+            }
+            HueModel::OkLch => {
+                let mut tmp: Pixel<OkLch> = px.convert();
+                tmp[1] *= self.0;
+                tmp.convert_to(px);
+            }
         }
-
-        let x = pt.x + self.0.origin.x;
-        let y = pt.y + self.0.origin.y;
-        let px = input.get_pixel((x, y), None);
-        px.copy_to_slice(dest);
     }
 }
 
-#[derive(Debug)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct Invert;
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Saturation {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[self.0, f64::from(self.1 as u8)])
+    }
 
-/// Invert an image
-pub fn invert<T: Type, C: Color, U: Type, D: Color>() -> impl Filter<T, C, U, D> {
-    Invert
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
+        let px = input.get_pixel(pt, None);
+        match self.1 {
+            HueModel::Hsv => {
+                let mut tmp: Pixel<Hsv> = px.convert();
+                tmp[1] *= self.0;
+                tmp.convert_to_data(data);
+            }
+            HueModel::OkLch => {
+                let mut tmp: Pixel<OkLch> = px.convert();
+                tmp[1] *= self.0;
+                tmp.convert_to_data(data);
+            }
+        }
+    }
 }
 
-impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Invert {
-    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
-        let mut px = input.get_pixel(pt, None);
-        px.map(|x| 1.0 - x);
-        px.copy_to_slice(dest);
+/// Call `f` with the lightness, chroma and hue of `px` using `model`, hue is in the range `[0, 1)`
+fn adjust_hue<C: Color>(
+    px: &mut Pixel<C>,
+    model: HueModel,
+    f: impl Fn(&mut f64, &mut f64, &mut f64),
+) {
+    match model {
+        HueModel::Hsv => {
+            let mut tmp: Pixel<Hsv> = px.convert();
+            let (mut h, mut s, mut v) = (tmp[0], tmp[1], tmp[2]);
+            f(&mut v, &mut s, &mut h);
+            tmp[0] = h.rem_euclid(1.0);
+            tmp[1] = s.clamp(0.0, 1.0);
+            tmp[2] = v;
+            tmp.convert_to(px);
+        }
+        HueModel::OkLch => {
+            let mut tmp: Pixel<OkLch> = px.convert();
+            let (mut l, mut c, mut h) = (tmp[0], tmp[1], tmp[2]);
+            f(&mut l, &mut c, &mut h);
+            tmp[0] = l;
+            tmp[1] = c.max(0.0);
+            tmp[2] = h.rem_euclid(1.0);
+            tmp.convert_to(px);
+        }
     }
 }
 
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct Blend;
+/// Hue rotation by the given number of degrees, see `hue_rotate`
+pub struct HueRotate(pub f64, pub HueModel);
 
-/// Average two images
-pub fn blend<T: Type, C: Color, U: Type, D: Color>() -> impl Filter<T, C, U, D> {
-    Blend
+/// Rotate hue by `degrees`
+pub fn hue_rotate<T: Type, C: Color, U: Type, D: Color>(degrees: f64) -> impl Filter<T, C, U, D> {
+    HueRotate(degrees, HueModel::Hsv)
 }
 
-impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Blend {
-    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
-        let a = input.get_pixel(pt, None);
-        let b = input.get_pixel(pt, Some(1));
-        ((a + &b) / 2.).copy_to_slice(dest);
-    }
+/// Rotate hue by `degrees` using the given color model
+pub fn hue_rotate_with<T: Type, C: Color, U: Type, D: Color>(
+    degrees: f64,
+    model: HueModel,
+) -> impl Filter<T, C, U, D> {
+    HueRotate(degrees, model)
 }
 
-#[derive(Debug)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct GammaLog(f64);
-
-/// Convert to log gamma
-pub fn gamma_log<T: Type, C: Color, U: Type, D: Color>(
-    gamma: Option<f64>,
-) -> impl Filter<T, C, U, D> {
-    GammaLog(gamma.unwrap_or(2.2))
+impl<C: Color> PointFilter<C> for HueRotate {
+    fn apply(&self, px: &mut Pixel<C>) {
+        adjust_hue(px, self.1, |_, _, h| *h += self.0 / 360.0);
+    }
 }
 
-impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for GammaLog {
-    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for HueRotate {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[self.0, f64::from(self.1 as u8)])
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
         let mut px = input.get_pixel(pt, None);
-        px.map(|x| x.powf(1.0 / self.0));
-        px.copy_to_slice(dest);
+        self.apply(&mut px);
+        px.convert_to_data(data);
     }
 }
 
-#[derive(Debug)]
+/// Adjust saturation and lightness of pixels within a range of hues, see `selective_color`
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct GammaLin(f64);
+pub struct SelectiveColor {
+    /// Center of the target hue range, in degrees
+    pub hue: f64,
 
-/// Convert to linear gamma
-pub fn gamma_lin<T: Type, C: Color, U: Type, D: Color>(
-    gamma: Option<f64>,
-) -> impl Filter<T, C, U, D> {
-    GammaLin(gamma.unwrap_or(2.2))
-}
+    /// Half-width of the target hue range, in degrees
+    pub range: f64,
 
-impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for GammaLin {
-    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
-        let mut px = input.get_pixel(pt, None);
-        px.map(|x| x.powf(self.0));
-        px.copy_to_slice(dest);
-    }
-}
+    /// Width of the soft transition outside of `range`, in degrees
+    pub falloff: f64,
 
-/// Conditional filter
-struct If<
-    F: Fn(Point, &Input<T, C>) -> bool,
-    G: Filter<T, C, U, D>,
-    H: Filter<T, C, U, D>,
-    T: Type,
-    C: Color,
-    U: Type,
-    D: Color,
-> {
-    cond: F,
-    then: G,
-    else_: H,
-    _t: std::marker::PhantomData<(T, C, U, D)>,
+    /// Saturation multiplier
+    pub saturation: f64,
+
+    /// Amount added to lightness
+    pub lightness: f64,
+
+    /// Color model used to measure hue
+    pub model: HueModel,
 }
 
-/// Create new conditional filter
-pub fn if_then_else<
-    F: Sync + Fn(Point, &Input<T, C>) -> bool,
-    G: Filter<T, C, U, D>,
-    H: Filter<T, C, U, D>,
-    T: Type,
-    C: Color,
-    U: Type,
-    D: Color,
->(
-    cond: F,
-    then: G,
-    else_: H,
-) -> impl Filter<T, C, U, D> {
-    If {
-        cond,
-        then,
-        else_,
-        _t: std::marker::PhantomData,
+impl SelectiveColor {
+    /// Target hues within `range` degrees of `hue`, without adjusting anything
+    pub fn new(hue: f64, range: f64) -> SelectiveColor {
+        SelectiveColor {
+            hue,
+            range,
+            falloff: 15.0,
+            saturation: 1.0,
+            lightness: 0.0,
+            model: HueModel::OkLch,
+        }
     }
-}
 
-impl<
-        F: Fn(Point, &Input<T, C>) -> bool,
-        G: Filter<T, C, U, D>,
-        H: Filter<T, C, U, D>,
-        T: Type,
-        C: Color,
-        U: Type,
-        D: Color,
-    > std::fmt::Debug for If<F, G, H, T, C, U, D>
-{
-    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
-        fmt.debug_struct("If")
-            .field("cond", &"Function")
-            .field("then", &self.then)
-            .field("else", &self.else_)
-            .finish()
+    /// Set falloff width
+    pub fn with_falloff(mut self, falloff: f64) -> Self {
+        self.falloff = falloff;
+        self
     }
-}
 
-impl<
-        F: Sync + Fn(Point, &Input<T, C>) -> bool,
-        G: Filter<T, C, U, D>,
-        H: Filter<T, C, U, D>,
-        T: Type,
-        C: Color,
-        U: Type,
-        D: Color,
-    > Filter<T, C, U, D> for If<F, G, H, T, C, U, D>
-{
-    fn schedule(&self) -> Schedule {
-        if self.then.schedule() == Schedule::Image || self.else_.schedule() == Schedule::Image {
-            return Schedule::Image;
-        }
+    /// Set saturation multiplier
+    pub fn with_saturation(mut self, saturation: f64) -> Self {
+        self.saturation = saturation;
+        self
+    }
 
-        Schedule::Pixel
+    /// Set lightness offset
+    pub fn with_lightness(mut self, lightness: f64) -> Self {
+        self.lightness = lightness;
+        self
     }
 
-    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
-        if (self.cond)(pt, input) {
-            self.then.compute_at(pt, input, dest)
+    /// Set color model
+    pub fn with_model(mut self, model: HueModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Amount of the adjustment applied to a pixel with the given hue in degrees, `1` inside of
+    /// the range, falling smoothly to `0` at `range + falloff`
+    pub fn weight(&self, hue: f64) -> f64 {
+        let d = (hue - self.hue).rem_euclid(360.0);
+        let d = d.min(360.0 - d) - self.range;
+        if d <= 0.0 {
+            1.0
+        } else if d >= self.falloff {
+            0.0
         } else {
-            self.else_.compute_at(pt, input, dest)
+            let t = 1.0 - d / self.falloff;
+            t * t * (3.0 - 2.0 * t)
         }
     }
 }
 
-#[derive(Debug)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct Clamp;
+/// Adjust saturation and lightness of pixels within a range of hues
+pub fn selective_color<T: Type, C: Color, U: Type, D: Color>(
+    selective: SelectiveColor,
+) -> impl Filter<T, C, U, D> {
+    selective
+}
 
-/// Clamp pixel values
-pub fn clamp<T: Type, C: Color, U: Type, D: Color>() -> impl Filter<T, C, U, D> {
-    Clamp
+impl<C: Color> PointFilter<C> for SelectiveColor {
+    fn apply(&self, px: &mut Pixel<C>) {
+        // Near-neutral pixels don't have a meaningful hue, fade the adjustment out for them
+        let neutral = match self.model {
+            HueModel::Hsv => 0.1,
+            HueModel::OkLch => 0.02,
+        };
+        adjust_hue(px, self.model, |l, c, h| {
+            let w = self.weight(*h * 360.0) * (*c / neutral).min(1.0);
+            *c *= 1.0 + w * (self.saturation - 1.0);
+            *l += w * self.lightness;
+        });
+    }
 }
 
-impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Clamp {
-    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
-        input.get_pixel(pt, None).clamped().copy_to_slice(dest)
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for SelectiveColor {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(&mut px);
+        px.convert_to_data(data);
     }
 }
 
-#[derive(Debug)]
+/// Map luminance through a color gradient, see `gradient_map`
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct Normalize {
-    min: f64,
-    max: f64,
-    new_min: f64,
-    new_max: f64,
+pub struct GradientMap {
+    /// Gradient stops as `(position, color)` pairs sorted by position, colors are sRGB encoded
+    pub stops: Vec<(f64, [f64; 3])>,
 }
 
-/// Normalize image data
-pub fn normalize<T: Type, C: Color, U: Type, D: Color>(
-    min: f64,
-    max: f64,
-    new_min: f64,
-    new_max: f64,
-) -> impl Filter<T, C, U, D> {
-    Normalize {
-        min,
-        max,
-        new_min,
-        new_max,
+impl GradientMap {
+    /// Create a new gradient from `(position, color)` stops, colors are sRGB encoded
+    pub fn new(stops: impl Into<Vec<(f64, [f64; 3])>>) -> GradientMap {
+        let mut stops = stops.into();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        GradientMap { stops }
     }
-}
 
-impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Normalize {
-    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
-        input
-            .get_pixel(pt, None)
-            .map(|x| {
-                (x - self.min) * ((self.new_max - self.new_min) / (self.max - self.min))
-                    + self.new_min
+    /// Evenly spaced stops from `0xRRGGBB` colors
+    fn from_hex(colors: &[u32]) -> GradientMap {
+        let n = (colors.len() - 1).max(1) as f64;
+        let stops = colors
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let channel = |shift: u32| ((c >> shift) & 0xff) as f64 / 255.0;
+                (i as f64 / n, [channel(16), channel(8), channel(0)])
             })
-            .copy_to_slice(dest)
+            .collect::<Vec<_>>();
+        GradientMap { stops }
     }
-}
 
-#[derive(Debug)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct Noop;
+    /// Two-color gradient from `shadows` to `highlights`
+    pub fn duotone(shadows: [f64; 3], highlights: [f64; 3]) -> GradientMap {
+        GradientMap::new(vec![(0.0, shadows), (1.0, highlights)])
+    }
 
-/// Filter that does nothing
-pub fn noop<T: Type, C: Color, U: Type, D: Color>() -> impl Filter<T, C, U, D> {
-    Noop
-}
+    /// Matplotlib viridis colormap
+    pub fn viridis() -> GradientMap {
+        GradientMap::from_hex(&[
+            0x440154, 0x482878, 0x3e4989, 0x31688e, 0x26828e, 0x1f9e89, 0x35b779, 0x6ece58,
+            0xb5de2b, 0xfde725,
+        ])
+    }
 
-impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Noop {
-    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
-        input.get_pixel(pt, None).copy_to_slice(dest)
+    /// Matplotlib inferno colormap
+    pub fn inferno() -> GradientMap {
+        GradientMap::from_hex(&[
+            0x000004, 0x1b0c41, 0x4a0c6b, 0x781c6d, 0xa52c60, 0xcf4446, 0xed6925, 0xfb9b06,
+            0xf7d13d, 0xfcffa4,
+        ])
     }
-}
+
+    /// Google turbo colormap
+    pub fn turbo() -> GradientMap {
+        GradientMap::from_hex(&[
+            0x30123b, 0x4662d7, 0x36aaf9, 0x1ae4b6, 0x72fe5e, 0xc7ef34, 0xfabb39, 0xf66b19,
+            0xca2a04, 0x7a0403,
+        ])
+    }
+
+    /// Get the sRGB encoded color at position `t`, positions outside of the stops are clamped
+    pub fn color_at(&self, t: f64) -> [f64; 3] {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return [t; 3],
+        };
+
+        if t <= first.0 {
+            return first.1;
+        }
+
+        for w in self.stops.windows(2) {
+            let ((p0, c0), (p1, c1)) = (w[0], w[1]);
+            if t <= p1 {
+                let f = if p1 > p0 { (t - p0) / (p1 - p0) } else { 1.0 };
+                return [
+                    c0[0] + (c1[0] - c0[0]) * f,
+                    c0[1] + (c1[1] - c0[1]) * f,
+                    c0[2] + (c1[2] - c0[2]) * f,
+                ];
+            }
+        }
+
+        last.1
+    }
+
+    fn map<C: Color>(&self, px: &Pixel<C>) -> Pixel<Srgb> {
+        let gray: Pixel<Gray> = px.convert();
+        let mut dest = Pixel::new();
+        dest.copy_from_slice(self.color_at(gray[0]));
+        dest
+    }
+}
+
+/// Map luminance through a color gradient, useful for false-color visualization of
+/// single-channel data
+pub fn gradient_map<T: Type, C: Color, U: Type, D: Color>(
+    map: GradientMap,
+) -> impl Filter<T, C, U, D> {
+    map
+}
+
+impl<C: Color> PointFilter<C> for GradientMap {
+    fn apply(&self, px: &mut Pixel<C>) {
+        self.map(px).convert_to(px);
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for GradientMap {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
+        let px = input.get_pixel(pt, None);
+        self.map(&px).convert_to_data(data);
+    }
+}
+
+/// Chroma key, see `chroma_key`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChromaKey {
+    /// Background color to remove, sRGB encoded
+    pub key_color: [f64; 3],
+
+    /// Pixels closer than `tolerance` to the key color are fully transparent, distance is
+    /// measured on the OkLab chroma plane so shading on the background doesn't matter
+    pub tolerance: f64,
+
+    /// Width of the transition from transparent to opaque beyond `tolerance`
+    pub softness: f64,
+
+    /// Amount of key color removed from the remaining pixels, `0` disables spill removal
+    pub spill_suppression: f64,
+}
+
+impl ChromaKey {
+    /// Create a new chroma key with default settings for `key_color`
+    pub fn new(key_color: [f64; 3]) -> ChromaKey {
+        ChromaKey {
+            key_color,
+            tolerance: 0.08,
+            softness: 0.08,
+            spill_suppression: 1.0,
+        }
+    }
+
+    /// Typical green screen
+    pub fn green_screen() -> ChromaKey {
+        ChromaKey::new([0.0, 0.69, 0.25])
+    }
+
+    /// Typical blue screen
+    pub fn blue_screen() -> ChromaKey {
+        ChromaKey::new([0.0, 0.28, 0.73])
+    }
+
+    fn key(&self) -> (f64, f64) {
+        let mut key = Pixel::<Srgb>::new();
+        key.copy_from_slice(self.key_color);
+        let key: Pixel<OkLab> = key.convert();
+        (key[1], key[2])
+    }
+
+    /// Compute the keyed pixel, color is not premultiplied
+    pub fn key_pixel<C: Color>(&self, px: &Pixel<C>) -> Pixel<Rgba> {
+        let (ka, kb) = self.key();
+        let mut lab: Pixel<OkLab> = px.convert();
+
+        let d = (lab[1] - ka).hypot(lab[2] - kb);
+        let alpha = if d <= self.tolerance {
+            0.0
+        } else if d >= self.tolerance + self.softness {
+            1.0
+        } else {
+            (d - self.tolerance) / self.softness
+        };
+
+        // Remove the component of the pixel chroma that points towards the key color
+        let norm = ka.hypot(kb);
+        if norm > 0.0 {
+            let (ua, ub) = (ka / norm, kb / norm);
+            let projection = lab[1] * ua + lab[2] * ub;
+            if projection > 0.0 {
+                let amt = projection * self.spill_suppression.clamp(0.0, 1.0);
+                lab[1] -= amt * ua;
+                lab[2] -= amt * ub;
+            }
+        }
+
+        let mut dest: Pixel<Rgba> = lab.convert();
+        dest.clamp();
+        dest[3] = alpha * px.alpha().unwrap_or(1.0);
+        dest
+    }
+}
+
+/// Remove a solid background color, producing an alpha matte and removing color spill from the
+/// foreground
+pub fn chroma_key<T: Type, C: Color, U: Type>(key: ChromaKey) -> impl Filter<T, C, U, Rgba> {
+    key
+}
+
+impl<T: Type, C: Color, U: Type> Filter<T, C, U, Rgba> for ChromaKey {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, Rgba>) {
+        let px = input.get_pixel(pt, None);
+        self.key_pixel(&px).copy_to_slice(data);
+    }
+}
+
+/// Halftone screen pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Screen {
+    /// Clustered round dots that join into a checkerboard at 50% coverage
+    #[default]
+    Dot,
+
+    /// Parallel lines
+    Line,
+
+    /// Stochastic screen using a blue noise mask, each mask texel covers one screen cell
+    BlueNoise,
+}
+
+/// Width of the clustered-dot and line threshold matrices
+const SCREEN_SIZE: usize = 16;
+
+/// Width of the blue noise threshold matrix
+const BLUE_NOISE_SIZE: usize = 64;
+
+/// Convert values to thresholds in `(0, 1)` by rank, so coverage is proportional to the input
+fn rank_thresholds(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|a, b| values[*a].total_cmp(&values[*b]));
+    let mut dest = vec![0.0; values.len()];
+    for (rank, i) in order.into_iter().enumerate() {
+        dest[i] = (rank as f64 + 0.5) / values.len() as f64;
+    }
+    dest
+}
+
+/// Euclidean dot spot function sampled over one cell
+fn dot_matrix() -> Vec<f64> {
+    let n = SCREEN_SIZE;
+    let values: Vec<f64> = (0..n * n)
+        .map(|i| {
+            let x = ((i % n) as f64 + 0.5) / n as f64 * 2.0 - 1.0;
+            let y = ((i / n) as f64 + 0.5) / n as f64 * 2.0 - 1.0;
+            if x.abs() + y.abs() <= 1.0 {
+                x * x + y * y
+            } else {
+                2.0 - (1.0 - x.abs()).powi(2) - (1.0 - y.abs()).powi(2)
+            }
+        })
+        .collect();
+    rank_thresholds(&values)
+}
+
+/// Distance from the line center, the threshold only depends on the row
+fn line_matrix() -> Vec<f64> {
+    let n = SCREEN_SIZE;
+    (0..n * n)
+        .map(|i| (((i / n) as f64 + 0.5) / n as f64 * 2.0 - 1.0).abs())
+        .collect()
+}
+
+/// Blue noise threshold matrix generated using the void-and-cluster method
+fn blue_noise_matrix() -> Vec<f64> {
+    let n = BLUE_NOISE_SIZE;
+    let len = n * n;
+
+    // Gaussian energy filter on a torus
+    let sigma = 1.5;
+    let kernel: Vec<f64> = (0..len)
+        .map(|i| {
+            let dx = (i % n).min(n - i % n) as f64;
+            let dy = (i / n).min(n - i / n) as f64;
+            (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let offset = |a: usize, b: usize| {
+        let dx = (b % n + n - a % n) % n;
+        let dy = (b / n + n - a / n) % n;
+        dy * n + dx
+    };
+    let update = |energy: &mut [f64], p: usize, sign: f64| {
+        for (q, e) in energy.iter_mut().enumerate() {
+            *e += sign * kernel[offset(p, q)];
+        }
+    };
+    let tightest_cluster = |pattern: &[bool], energy: &[f64]| {
+        (0..len)
+            .filter(|i| pattern[*i])
+            .max_by(|a, b| energy[*a].total_cmp(&energy[*b]))
+            .unwrap_or(0)
+    };
+    let largest_void = |pattern: &[bool], energy: &[f64]| {
+        (0..len)
+            .filter(|i| !pattern[*i])
+            .min_by(|a, b| energy[*a].total_cmp(&energy[*b]))
+            .unwrap_or(0)
+    };
+
+    // Deterministic initial pattern with 10% of the pixels set
+    let mut pattern = vec![false; len];
+    let mut energy = vec![0.0; len];
+    let mut seed = 0x2545f491u64;
+    let mut ones = 0;
+    while ones < len / 10 {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let p = (seed >> 33) as usize % len;
+        if !pattern[p] {
+            pattern[p] = true;
+            update(&mut energy, p, 1.0);
+            ones += 1;
+        }
+    }
+
+    // Move pixels from the tightest cluster to the largest void until the pattern is stable
+    for _ in 0..len {
+        let cluster = tightest_cluster(&pattern, &energy);
+        pattern[cluster] = false;
+        update(&mut energy, cluster, -1.0);
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        update(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    // Rank the initial pixels by removing clusters, then fill voids for the rest
+    let mut rank = vec![0; len];
+    let (mut removed, mut removed_energy) = (pattern.clone(), energy.clone());
+    for r in (0..ones).rev() {
+        let cluster = tightest_cluster(&removed, &removed_energy);
+        removed[cluster] = false;
+        update(&mut removed_energy, cluster, -1.0);
+        rank[cluster] = r;
+    }
+    for r in ones..len {
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        update(&mut energy, void, 1.0);
+        rank[void] = r;
+    }
+
+    rank.into_iter()
+        .map(|r| (r as f64 + 0.5) / len as f64)
+        .collect()
+}
+
+impl Screen {
+    /// Square threshold matrix for one screen cell, or one texel per cell for `BlueNoise`
+    fn matrix(self) -> &'static [f64] {
+        static DOT: std::sync::OnceLock<Vec<f64>> = std::sync::OnceLock::new();
+        static LINE: std::sync::OnceLock<Vec<f64>> = std::sync::OnceLock::new();
+        static BLUE_NOISE: std::sync::OnceLock<Vec<f64>> = std::sync::OnceLock::new();
+        match self {
+            Screen::Dot => DOT.get_or_init(dot_matrix),
+            Screen::Line => LINE.get_or_init(line_matrix),
+            Screen::BlueNoise => BLUE_NOISE.get_or_init(blue_noise_matrix),
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Screen::Dot | Screen::Line => SCREEN_SIZE,
+            Screen::BlueNoise => BLUE_NOISE_SIZE,
+        }
+    }
+}
+
+/// Halftone screening, see `halftone`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Halftone {
+    /// Screen pattern
+    pub screen: Screen,
+
+    /// Output resolution in dots per inch, a screen cell is `dpi / lpi` pixels wide
+    pub dpi: f64,
+
+    /// Screen frequency in lines per inch for each channel, the last value is used for any
+    /// remaining channels
+    pub lpi: Vec<f64>,
+
+    /// Screen angle in degrees for each channel, the last value is used for any remaining
+    /// channels
+    pub angles: Vec<f64>,
+}
+
+impl Halftone {
+    /// Create a new halftone screen with the same frequency for every channel at 45 degrees
+    pub fn new(screen: Screen, dpi: f64, lpi: f64) -> Halftone {
+        Halftone {
+            screen,
+            dpi,
+            lpi: vec![lpi],
+            angles: vec![45.0],
+        }
+    }
+
+    /// Traditional process color angles, 15, 75 and 0 degrees for cyan, magenta and yellow, for
+    /// use with RGB images
+    pub fn cmy(screen: Screen, dpi: f64, lpi: f64) -> Halftone {
+        Halftone::new(screen, dpi, lpi).with_angles(&[15.0, 75.0, 0.0])
+    }
+
+    /// Set screen frequency for each channel
+    pub fn with_lpi(mut self, lpi: &[f64]) -> Self {
+        self.lpi = lpi.to_vec();
+        self
+    }
+
+    /// Set screen angle for each channel
+    pub fn with_angles(mut self, angles: &[f64]) -> Self {
+        self.angles = angles.to_vec();
+        self
+    }
+
+    /// Threshold for channel `c` at `pt`, a channel value below the threshold is printed
+    pub fn threshold(&self, pt: Point, c: Channel) -> f64 {
+        let get = |v: &[f64], default: f64| v.get(c).or(v.last()).copied().unwrap_or(default);
+        let lpi = get(&self.lpi, 1.0);
+        let cell = if lpi > 0.0 { self.dpi / lpi } else { 1.0 }.max(1.0);
+        let (sin, cos) = get(&self.angles, 0.0).to_radians().sin_cos();
+
+        let (x, y) = (pt.x as f64 + 0.5, pt.y as f64 + 0.5);
+        let u = (x * cos + y * sin) / cell;
+        let v = (y * cos - x * sin) / cell;
+
+        let n = self.screen.size();
+        let (i, j) = match self.screen {
+            Screen::BlueNoise => (u.floor(), v.floor()),
+            Screen::Dot | Screen::Line => (
+                (u.rem_euclid(1.0) * n as f64),
+                (v.rem_euclid(1.0) * n as f64),
+            ),
+        };
+        let i = (i as i64).rem_euclid(n as i64) as usize;
+        let j = (j as i64).rem_euclid(n as i64) as usize;
+        self.screen.matrix()[j * n + i]
+    }
+}
+
+/// Simulate print screening, each channel is reduced to ink (`0`) or paper (`1`) using a rotated
+/// threshold matrix, the alpha channel is not screened
+pub fn halftone<T: Type, C: Color, U: Type, D: Color>(
+    halftone: Halftone,
+) -> impl Filter<T, C, U, D> {
+    halftone
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Halftone {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        for c in 0..C::CHANNELS {
+            if C::ALPHA == Some(c) {
+                continue;
+            }
+            let coverage = 1.0 - px[c].clamp(0.0, 1.0);
+            px[c] = if self.threshold(pt, c) < coverage {
+                0.0
+            } else {
+                1.0
+            };
+        }
+        px.convert_to_data(data);
+    }
+}
+
+/// Film grain synthesis, see `film_grain` and `FilmGrain::estimate`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FilmGrain {
+    /// Grain size in pixels, this is the standard deviation of the blur used to correlate
+    /// neighboring grains. `0` produces per-pixel noise
+    pub size: f64,
+
+    /// Standard deviation of the grain in midtones, grain fades out towards black and white
+    pub intensity: f64,
+
+    /// Amount of independent grain in each channel, `0` produces monochrome grain and `1` fully
+    /// independent channels
+    pub chroma: f64,
+
+    /// Random seed, the same seed always produces the same grain
+    pub seed: u64,
+}
+
+/// Add film grain to an image, the alpha channel is left unchanged
+pub fn film_grain<T: Type, C: Color, U: Type, D: Color>(
+    grain: FilmGrain,
+) -> impl Filter<T, C, U, D> {
+    grain
+}
+
+/// Hash a pixel position into a uniform value in `(0, 1)`
+fn grain_hash(x: i64, y: i64, stream: u64, seed: u64) -> f64 {
+    let mut z = seed
+        ^ (x as u64).wrapping_mul(0x9e3779b97f4a7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2ae3d27d4eb4f)
+        ^ stream.wrapping_mul(0x165667b19e3779f9);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^= z >> 31;
+    ((z >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+}
+
+impl FilmGrain {
+    /// Create new film grain with a random seed of `0`
+    pub fn new(size: f64, intensity: f64, chroma: f64) -> FilmGrain {
+        FilmGrain {
+            size,
+            intensity,
+            chroma,
+            seed: 0,
+        }
+    }
+
+    /// Set random seed
+    pub fn with_seed(mut self, seed: u64) -> FilmGrain {
+        self.seed = seed;
+        self
+    }
+
+    /// Gaussian white noise with unit variance
+    fn white(&self, x: i64, y: i64, stream: u64) -> f64 {
+        let u = grain_hash(x, y, stream * 2, self.seed);
+        let v = grain_hash(x, y, stream * 2 + 1, self.seed);
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+
+    /// Correlated noise with unit variance
+    fn noise(&self, pt: Point, stream: u64) -> f64 {
+        let (x, y) = (pt.x as i64, pt.y as i64);
+        if self.size <= 0.0 {
+            return self.white(x, y, stream);
+        }
+
+        let r = (self.size * 2.0).ceil() as i64;
+        let (mut sum, mut norm) = (0.0, 0.0);
+        for j in -r..=r {
+            for i in -r..=r {
+                let w = (-((i * i + j * j) as f64) / (2.0 * self.size * self.size)).exp();
+                sum += w * self.white(x + i, y + j, stream);
+                norm += w * w;
+            }
+        }
+        sum / norm.sqrt()
+    }
+
+    /// Grain value of channel `c` at `pt` before luminance weighting
+    pub fn grain(&self, pt: Point, c: Channel) -> f64 {
+        let chroma = self.chroma.clamp(0.0, 1.0);
+        let mono = if chroma < 1.0 { self.noise(pt, 0) } else { 0.0 };
+        let color = if chroma > 0.0 {
+            self.noise(pt, c as u64 + 1)
+        } else {
+            0.0
+        };
+        let norm = ((1.0 - chroma).powi(2) + chroma * chroma).sqrt();
+        ((1.0 - chroma) * mono + chroma * color) / norm
+    }
+
+    /// Grain amplitude relative to `intensity` for a given luminance, this peaks at `1` for
+    /// midtones
+    fn response(luminance: f64) -> f64 {
+        let l = luminance.clamp(0.0, 1.0);
+        2.0 * (l * (1.0 - l)).sqrt()
+    }
+
+    /// Estimate grain parameters from a sample, the sample should be mostly flat such as a grain
+    /// plate or an out-of-focus area. The result can be used to add matching grain to
+    /// composited elements
+    pub fn estimate<T: Type, C: Color>(image: &Image<T, C>) -> FilmGrain {
+        let stats = GrainStats::measure(image);
+
+        // Measure the same statistics on synthetic grain to calibrate size and intensity
+        let reference = 0.1;
+        let mut best = (f64::INFINITY, 0.0, 1.0);
+        for size in (0..=8).map(|i| i as f64 * 0.5) {
+            let grain = FilmGrain::new(size, reference, 0.0);
+            let mut plate = Image::<f32, Gray>::new((64, 64));
+            plate.each_pixel_mut(|pt, mut px| {
+                px[0] = 0.5 + reference * grain.grain(pt, 0);
+            });
+            let synth = GrainStats::measure(&plate);
+            let dist = (synth.correlation - stats.correlation).abs();
+            if dist < best.0 {
+                best = (dist, size, synth.deviation);
+            }
+        }
+
+        let t = (1.0 / stats.channel_correlation.clamp(1e-6, 1.0) - 1.0).sqrt();
+        FilmGrain::new(
+            best.1,
+            stats.deviation / best.2.max(f64::EPSILON) * reference,
+            t / (1.0 + t),
+        )
+    }
+}
+
+/// High-pass grain statistics used by `FilmGrain::estimate`
+struct GrainStats {
+    /// Standard deviation of the residual normalized by the luminance response
+    deviation: f64,
+
+    /// Correlation between horizontally adjacent residuals
+    correlation: f64,
+
+    /// Mean correlation between the residuals of different channels
+    channel_correlation: f64,
+}
+
+impl GrainStats {
+    fn measure<T: Type, C: Color>(image: &Image<T, C>) -> GrainStats {
+        let (w, h) = (image.width(), image.height());
+        let channels: Vec<Channel> = (0..C::CHANNELS).filter(|c| C::ALPHA != Some(*c)).collect();
+
+        // Residual after subtracting a 5x5 local mean, divided by the luminance response
+        let mut residual = vec![vec![0.0; w * h]; channels.len()];
+        let mut valid = vec![false; w * h];
+        for y in 2..h.saturating_sub(2) {
+            for x in 2..w.saturating_sub(2) {
+                let mut mean: Pixel<C> = Pixel::new();
+                for j in y - 2..=y + 2 {
+                    for i in x - 2..=x + 2 {
+                        let px = image.get_pixel((i, j));
+                        for c in 0..C::CHANNELS {
+                            mean[c] += px[c] / 25.0;
+                        }
+                    }
+                }
+                let response = FilmGrain::response(mean.convert::<Gray>()[0]);
+                if response < 0.2 {
+                    continue;
+                }
+                let px = image.get_pixel((x, y));
+                for (k, c) in channels.iter().enumerate() {
+                    residual[k][y * w + x] = (px[*c] - mean[*c]) / response;
+                }
+                valid[y * w + x] = true;
+            }
+        }
+
+        let (mut var, mut cov, mut n, mut pairs) = (0.0, 0.0, 0usize, 0usize);
+        for r in &residual {
+            for i in (0..w * h).filter(|i| valid[*i]) {
+                var += r[i] * r[i];
+                n += 1;
+                if i % w + 1 < w && valid[i + 1] {
+                    cov += r[i] * r[i + 1];
+                    pairs += 1;
+                }
+            }
+        }
+        let deviation = (var / n.max(1) as f64).sqrt();
+        let variance = (var / n.max(1) as f64).max(f64::EPSILON);
+        let correlation = cov / pairs.max(1) as f64 / variance;
+
+        let (mut channel_cov, mut count) = (0.0, 0usize);
+        for a in 0..residual.len() {
+            for b in a + 1..residual.len() {
+                let (mut ab, mut aa, mut bb) = (0.0, 0.0, 0.0);
+                for i in (0..w * h).filter(|i| valid[*i]) {
+                    ab += residual[a][i] * residual[b][i];
+                    aa += residual[a][i] * residual[a][i];
+                    bb += residual[b][i] * residual[b][i];
+                }
+                channel_cov += ab / (aa * bb).sqrt().max(f64::EPSILON);
+                count += 1;
+            }
+        }
+
+        GrainStats {
+            deviation,
+            correlation,
+            channel_correlation: if count == 0 {
+                1.0
+            } else {
+                channel_cov / count as f64
+            },
+        }
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for FilmGrain {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        let amplitude = self.intensity * Self::response(px.convert::<Gray>()[0]);
+        for c in 0..C::CHANNELS {
+            if C::ALPHA == Some(c) {
+                continue;
+            }
+            px[c] += amplitude * self.grain(pt, c);
+        }
+        px.convert_to_data(data);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Linear window/level transform used to display medical images, see `window_level`
+pub struct WindowLevel {
+    /// Center of the window, in rescaled units
+    pub center: f64,
+
+    /// Width of the window, in rescaled units
+    pub width: f64,
+
+    /// Multiplier applied to stored values before windowing
+    pub slope: f64,
+
+    /// Offset added to stored values after `slope`
+    pub intercept: f64,
+}
+
+/// Windows are compared bitwise so they can be part of `Meta`, which implements `Eq`
+impl PartialEq for WindowLevel {
+    fn eq(&self, other: &WindowLevel) -> bool {
+        self.center.to_bits() == other.center.to_bits()
+            && self.width.to_bits() == other.width.to_bits()
+            && self.slope.to_bits() == other.slope.to_bits()
+            && self.intercept.to_bits() == other.intercept.to_bits()
+    }
+}
+
+impl Eq for WindowLevel {}
+
+impl WindowLevel {
+    /// Create a new window with no rescaling
+    pub fn new(center: f64, width: f64) -> WindowLevel {
+        WindowLevel {
+            center,
+            width,
+            slope: 1.0,
+            intercept: 0.0,
+        }
+    }
+
+    /// Set the rescale slope and intercept, for CT these map stored values to Hounsfield units
+    pub fn with_rescale(mut self, slope: f64, intercept: f64) -> WindowLevel {
+        self.slope = slope;
+        self.intercept = intercept;
+        self
+    }
+
+    /// Map a stored value to the display range `0..=1`, this is the DICOM `LINEAR_EXACT` function
+    pub fn map(&self, stored: f64) -> f64 {
+        let x = stored * self.slope + self.intercept;
+        ((x - self.center) / self.width.max(f64::EPSILON) + 0.5).clamp(0.0, 1.0)
+    }
+}
+
+/// Map stored values to the display range using a window/level, input values are interpreted in
+/// the range of the input type, e.g. `0..=65535` for `u16`
+pub fn window_level<T: Type, C: Color, U: Type, D: Color>(
+    window: WindowLevel,
+) -> impl Filter<T, C, U, D> {
+    window
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for WindowLevel {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[self.center, self.width, self.slope, self.intercept])
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        for c in 0..C::CHANNELS {
+            if C::ALPHA == Some(c) {
+                continue;
+            }
+            px[c] = self.map(px[c] * (T::MAX - T::MIN) + T::MIN);
+        }
+        px.convert_to_data(data);
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Brightness adjustment, see `brightness`
+pub struct Brightness(pub f64);
+
+/// Adjust image brightness
+pub fn brightness<T: Type, C: Color, U: Type, D: Color>(amt: f64) -> impl Filter<T, C, U, D> {
+    Brightness(amt)
+}
+
+impl<C: Color> PointFilter<C> for Brightness {
+    fn apply(&self, px: &mut Pixel<C>) {
+        *px *= self.0;
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Brightness {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[self.0])
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(&mut px);
+        px.convert_to_data(data);
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Exposure adjustment in stops, see `exposure`
+pub struct Exposure(pub f64);
+
+/// Adjust image exposure, the argument is the number of stops to increase or decrease exposure by
+pub fn exposure<T: Type, C: Color, U: Type, D: Color>(stops: f64) -> impl Filter<T, C, U, D> {
+    Exposure(stops)
+}
+
+impl<C: Color> PointFilter<C> for Exposure {
+    fn apply(&self, px: &mut Pixel<C>) {
+        *px *= 2f64.powf(self.0);
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Exposure {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[self.0])
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(&mut px);
+        px.convert_to_data(data);
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Contrast adjustment, see `contrast`
+pub struct Contrast(pub f64);
+
+/// Adjust image contrast
+pub fn contrast<T: Type, C: Color, U: Type, D: Color>(amt: f64) -> impl Filter<T, C, U, D> {
+    Contrast(amt)
+}
+
+impl<C: Color> PointFilter<C> for Contrast {
+    fn apply(&self, px: &mut Pixel<C>) {
+        px.map(|x| (self.0 * (x - 0.5)) + 0.5);
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Contrast {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[self.0])
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(&mut px);
+        px.convert_to_data(data);
+    }
+}
+
+/// Per-channel 1-D lookup tables, see `lut1d`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lut1d {
+    /// Output values for evenly spaced normalized inputs from `0` to `1`, one table per channel.
+    /// A single table is applied to every channel, channels without a table are left unchanged
+    pub tables: Vec<Vec<f64>>,
+}
+
+impl Lut1d {
+    /// Create a new `Lut1d` from per-channel tables
+    pub fn new(tables: Vec<Vec<f64>>) -> Lut1d {
+        Lut1d { tables }
+    }
+
+    /// Bake a point-wise filter into a table with `resolution` entries per channel. Each entry is
+    /// computed by applying `filter` to a pixel with every channel set to the same value, so the
+    /// result is only exact for filters that treat channels independently
+    pub fn from_filter<C: Color>(filter: &impl PointFilter<C>, resolution: usize) -> Lut1d {
+        let resolution = resolution.max(2);
+        let mut tables = vec![Vec::with_capacity(resolution); C::CHANNELS];
+        let mut px = Pixel::<C>::new();
+        for i in 0..resolution {
+            let x = i as f64 / (resolution - 1) as f64;
+            px.map(|_| x);
+            filter.apply(&mut px);
+            for (c, table) in tables.iter_mut().enumerate() {
+                table.push(px[c]);
+            }
+        }
+        Lut1d { tables }
+    }
+
+    /// Look up a normalized value for channel `c` using linear interpolation, inputs outside of
+    /// `0` to `1` are clamped
+    pub fn lookup(&self, c: Channel, x: f64) -> f64 {
+        let table = match self.tables.get(c) {
+            Some(table) => table,
+            None if self.tables.len() == 1 => &self.tables[0],
+            None => return x,
+        };
+        match table.len() {
+            0 => x,
+            1 => table[0],
+            n => {
+                let f = x.clamp(0.0, 1.0) * (n - 1) as f64;
+                let i = (f as usize).min(n - 2);
+                let t = f - i as f64;
+                table[i] + (table[i + 1] - table[i]) * t
+            }
+        }
+    }
+}
+
+/// Apply per-channel 1-D lookup tables, this is a fast way to apply tone curves or to reuse
+/// point-wise filters baked using `Lut1d::from_filter`
+pub fn lut1d<T: Type, C: Color, U: Type, D: Color>(lut: Lut1d) -> impl Filter<T, C, U, D> {
+    lut
+}
+
+impl<C: Color> PointFilter<C> for Lut1d {
+    fn apply(&self, px: &mut Pixel<C>) {
+        px.iter_mut()
+            .enumerate()
+            .for_each(|(c, x)| *x = self.lookup(c, *x));
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Lut1d {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(&mut px);
+        px.convert_to_data(data);
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Crop(Region);
+
+/// Crop an image
+pub fn crop<T: Type, C: Color, U: Type, D: Color>(r: Region) -> impl GeometryFilter<T, C, U, D> {
+    Crop(r)
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> GeometryFilter<T, C, U, D> for Crop {
+    fn output_size(&self, _input_size: Size) -> Size {
+        self.0.size
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Crop {
+    fn output_size(&self, _input: &Input<T, C>, _dest: &mut Image<U, D>) -> Size {
+        self.0.size
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        if pt.x > self.0.origin.x + self.0.size.width || pt.y > self.0.origin.y + self.0.size.height
+        {
+            return;
+        }
+
+        let x = pt.x + self.0.origin.x;
+        let y = pt.y + self.0.origin.y;
+        let px = input.get_pixel((x, y), None);
+        px.copy_to_slice(dest);
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Inverts pixel values, see `invert`
+pub struct Invert;
+
+/// Invert an image
+pub fn invert<T: Type, C: Color, U: Type, D: Color>() -> impl Filter<T, C, U, D> {
+    Invert
+}
+
+impl<C: Color> PointFilter<C> for Invert {
+    fn apply(&self, px: &mut Pixel<C>) {
+        px.map(|x| 1.0 - x);
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Invert {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[])
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(&mut px);
+        px.copy_to_slice(dest);
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Blend;
+
+/// Average two images
+pub fn blend<T: Type, C: Color, U: Type, D: Color>() -> impl Filter<T, C, U, D> {
+    Blend
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Blend {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let a = input.get_pixel(pt, None);
+        let b = input.get_pixel(pt, Some(1));
+        ((a + &b) / 2.).copy_to_slice(dest);
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Converts to log gamma, see `gamma_log`
+pub struct GammaLog(pub f64);
+
+/// Convert to log gamma
+pub fn gamma_log<T: Type, C: Color, U: Type, D: Color>(
+    gamma: Option<f64>,
+) -> impl Filter<T, C, U, D> {
+    GammaLog(gamma.unwrap_or(2.2))
+}
+
+impl<C: Color> PointFilter<C> for GammaLog {
+    fn apply(&self, px: &mut Pixel<C>) {
+        px.map(|x| x.powf(1.0 / self.0));
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for GammaLog {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[self.0])
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(&mut px);
+        px.copy_to_slice(dest);
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Converts to linear gamma, see `gamma_lin`
+pub struct GammaLin(pub f64);
+
+/// Convert to linear gamma
+pub fn gamma_lin<T: Type, C: Color, U: Type, D: Color>(
+    gamma: Option<f64>,
+) -> impl Filter<T, C, U, D> {
+    GammaLin(gamma.unwrap_or(2.2))
+}
+
+impl<C: Color> PointFilter<C> for GammaLin {
+    fn apply(&self, px: &mut Pixel<C>) {
+        px.map(|x| x.powf(self.0));
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for GammaLin {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[self.0])
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(&mut px);
+        px.copy_to_slice(dest);
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Converts from sRGB to linear using the exact piecewise transfer function, see `srgb_lin`
+pub struct SrgbLin;
+
+/// Convert from sRGB to linear, unlike `gamma_lin` this handles the linear segment near black
+pub fn srgb_lin<T: Type, C: Color, U: Type, D: Color>() -> impl Filter<T, C, U, D> {
+    SrgbLin
+}
+
+impl<C: Color> PointFilter<C> for SrgbLin {
+    fn apply(&self, px: &mut Pixel<C>) {
+        px.srgb_to_linear();
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for SrgbLin {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[])
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(&mut px);
+        px.copy_to_slice(dest);
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Converts from linear to sRGB using the exact piecewise transfer function, see `srgb_log`
+pub struct SrgbLog;
+
+/// Convert from linear to sRGB, unlike `gamma_log` this handles the linear segment near black
+pub fn srgb_log<T: Type, C: Color, U: Type, D: Color>() -> impl Filter<T, C, U, D> {
+    SrgbLog
+}
+
+impl<C: Color> PointFilter<C> for SrgbLog {
+    fn apply(&self, px: &mut Pixel<C>) {
+        px.linear_to_srgb();
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for SrgbLog {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[])
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(&mut px);
+        px.copy_to_slice(dest);
+    }
+}
+
+/// Conditional filter
+struct If<
+    F: Fn(Point, &Input<T, C>) -> bool,
+    G: Filter<T, C, U, D>,
+    H: Filter<T, C, U, D>,
+    T: Type,
+    C: Color,
+    U: Type,
+    D: Color,
+> {
+    cond: F,
+    then: G,
+    else_: H,
+    _t: std::marker::PhantomData<(T, C, U, D)>,
+}
+
+/// Create new conditional filter
+pub fn if_then_else<
+    F: Sync + Fn(Point, &Input<T, C>) -> bool,
+    G: Filter<T, C, U, D>,
+    H: Filter<T, C, U, D>,
+    T: Type,
+    C: Color,
+    U: Type,
+    D: Color,
+>(
+    cond: F,
+    then: G,
+    else_: H,
+) -> impl Filter<T, C, U, D> {
+    If {
+        cond,
+        then,
+        else_,
+        _t: std::marker::PhantomData,
+    }
+}
+
+impl<
+        F: Fn(Point, &Input<T, C>) -> bool,
+        G: Filter<T, C, U, D>,
+        H: Filter<T, C, U, D>,
+        T: Type,
+        C: Color,
+        U: Type,
+        D: Color,
+    > std::fmt::Debug for If<F, G, H, T, C, U, D>
+{
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("If")
+            .field("cond", &"Function")
+            .field("then", &self.then)
+            .field("else", &self.else_)
+            .finish()
+    }
+}
+
+impl<
+        F: Sync + Fn(Point, &Input<T, C>) -> bool,
+        G: Filter<T, C, U, D>,
+        H: Filter<T, C, U, D>,
+        T: Type,
+        C: Color,
+        U: Type,
+        D: Color,
+    > Filter<T, C, U, D> for If<F, G, H, T, C, U, D>
+{
+    fn schedule(&self) -> Schedule {
+        if self.then.schedule() == Schedule::Image || self.else_.schedule() == Schedule::Image {
+            return Schedule::Image;
+        }
+
+        Schedule::Pixel
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        if (self.cond)(pt, input) {
+            self.then.compute_at(pt, input, dest)
+        } else {
+            self.else_.compute_at(pt, input, dest)
+        }
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Clamps pixel values between 0 and 1, see `clamp`
+pub struct Clamp;
+
+/// Clamp pixel values
+pub fn clamp<T: Type, C: Color, U: Type, D: Color>() -> impl Filter<T, C, U, D> {
+    Clamp
+}
+
+impl<C: Color> PointFilter<C> for Clamp {
+    fn apply(&self, px: &mut Pixel<C>) {
+        px.clamp();
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Clamp {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[])
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        input.get_pixel(pt, None).clamped().copy_to_slice(dest)
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Maps values from one range to another, see `normalize`
+pub struct Normalize {
+    /// Input minimum
+    pub min: f64,
+
+    /// Input maximum
+    pub max: f64,
+
+    /// Output minimum
+    pub new_min: f64,
+
+    /// Output maximum
+    pub new_max: f64,
+}
+
+/// Normalize image data
+pub fn normalize<T: Type, C: Color, U: Type, D: Color>(
+    min: f64,
+    max: f64,
+    new_min: f64,
+    new_max: f64,
+) -> impl Filter<T, C, U, D> {
+    Normalize {
+        min,
+        max,
+        new_min,
+        new_max,
+    }
+}
+
+impl<C: Color> PointFilter<C> for Normalize {
+    fn apply(&self, px: &mut Pixel<C>) {
+        px.map(|x| {
+            (x - self.min) * ((self.new_max - self.new_min) / (self.max - self.min)) + self.new_min
+        });
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Normalize {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[self.min, self.max, self.new_min, self.new_max])
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(&mut px);
+        px.copy_to_slice(dest)
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Noop;
+
+/// Filter that does nothing
+pub fn noop<T: Type, C: Color, U: Type, D: Color>() -> impl Filter<T, C, U, D> {
+    Noop
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Noop {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[])
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        input.get_pixel(pt, None).copy_to_slice(dest)
+    }
+}
 
 #[inline]
 /// Build rotation `Transform` using the specified degrees and center point
 pub fn rotate<T: Type, C: Color, U: Type, D: Color>(
     deg: f64,
-    center: Point,
+    center: Point,
+) -> impl Filter<T, C, U, D> {
+    rotation(deg, center)
+}
+
+#[inline]
+/// Build rotation filter using the specified degrees and center point, use `Transfer::Srgb` to
+/// resample sRGB encoded images in linear light
+pub fn rotate_with_transfer<T: Type, C: Color, U: Type, D: Color>(
+    deg: f64,
+    center: Point,
+    transfer: transform::Transfer,
+) -> impl Filter<T, C, U, D> {
+    transform::Resample::new(rotation(deg, center)).with_transfer(transfer)
+}
+
+fn rotation(deg: f64, center: Point) -> Transform {
+    let center = center.to_tuple();
+    Transform::rotation(euclid::Angle::degrees(-deg))
+        .pre_translate(euclid::Vector2D::new(
+            -(center.0 as f64),
+            -(center.1 as f64),
+        ))
+        .then_translate(euclid::Vector2D::new(center.0 as f64, center.1 as f64))
+}
+
+#[inline]
+/// Build scale `Transform`
+pub fn scale<T: Type, C: Color, U: Type, D: Color>(
+    x: f64,
+    y: f64,
+) -> impl GeometryFilter<T, C, U, D> {
+    Transform::scale(1.0 / x, 1.0 / y)
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Resize {
+    from: Size,
+    to: Size,
+    transfer: transform::Transfer,
+}
+
+impl Resize {
+    fn transform(&self) -> Transform {
+        Transform::scale(
+            self.from.width as f64 / self.to.width as f64,
+            self.from.height as f64 / self.to.height as f64,
+        )
+    }
+}
+
+#[inline]
+/// Build resize transform
+pub fn resize<T: Type, C: Color, U: Type, D: Color>(
+    from: Size,
+    to: Size,
+) -> impl GeometryFilter<T, C, U, D> {
+    resize_with_transfer(from, to, transform::Transfer::Linear)
+}
+
+#[inline]
+/// Build resize transform, use `Transfer::Srgb` to resample sRGB encoded images in linear light
+pub fn resize_with_transfer<T: Type, C: Color, U: Type, D: Color>(
+    from: Size,
+    to: Size,
+    transfer: transform::Transfer,
+) -> impl GeometryFilter<T, C, U, D> {
+    Resize { from, to, transfer }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Resize {
+    fn output_size(&self, _input: &Input<T, C>, _dest: &mut Image<U, D>) -> Size {
+        self.to
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        transform::sample(&self.transform(), self.transfer, pt, input).convert_to_data(dest)
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> GeometryFilter<T, C, U, D> for Resize {
+    fn output_size(&self, _input_size: Size) -> Size {
+        self.to
+    }
+}
+
+/// 90 degree rotation
+pub fn rotate90<T: Type, C: Color, U: Type, D: Color>(
+    from: Size,
+    to: Size,
+) -> impl Filter<T, C, U, D> {
+    let dwidth = to.width as f64;
+    let height = from.height as f64;
+    rotate(
+        90.,
+        Point::new((dwidth / 2.) as usize, (height / 2.) as usize),
+    )
+}
+
+/// 180 degree rotation
+pub fn rotate180<T: Type, C: Color, U: Type, D: Color>(src: Size) -> impl Filter<T, C, U, D> {
+    let dwidth = src.width as f64;
+    let height = src.height as f64;
+    rotate(
+        180.,
+        Point::new((dwidth / 2.) as usize, (height / 2.) as usize),
+    )
+}
+
+/// 270 degree rotation
+pub fn rotate270<T: Type, C: Color, U: Type, D: Color>(
+    from: Size,
+    to: Size,
+) -> impl Filter<T, C, U, D> {
+    let width = to.height as f64;
+    let dheight = from.width as f64;
+    rotate(
+        270.,
+        Point::new((width / 2.) as usize, (dheight / 2.) as usize),
+    )
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct RotateExpand(f64);
+
+/// Rotate an image around its center by `deg` degrees clockwise, the output is expanded to fit the
+/// whole rotated image and uncovered areas are set to zero
+pub fn rotate_expand<T: Type, C: Color, U: Type, D: Color>(
+    deg: f64,
+) -> impl GeometryFilter<T, C, U, D> {
+    RotateExpand(deg)
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for RotateExpand {
+    fn output_size(&self, input: &Input<T, C>, _dest: &mut Image<U, D>) -> Size {
+        GeometryFilter::<T, C, U, D>::output_size(self, input.images()[0].size())
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let src = input.images()[0].size();
+        let size = GeometryFilter::<T, C, U, D>::output_size(self, src);
+        let (sin, cos) = self.0.to_radians().sin_cos();
+
+        // Rotate the destination pixel center back into the input image
+        let x = pt.x as f64 + 0.5 - size.width as f64 / 2.0;
+        let y = pt.y as f64 + 0.5 - size.height as f64 / 2.0;
+        let sx = x * cos + y * sin + src.width as f64 / 2.0;
+        let sy = -x * sin + y * cos + src.height as f64 / 2.0;
+
+        let px = if sx < 0.0 || sy < 0.0 {
+            Pixel::new()
+        } else {
+            input.get_pixel((sx as usize, sy as usize), None)
+        };
+        px.convert_to_data(dest);
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> GeometryFilter<T, C, U, D> for RotateExpand {
+    fn output_size(&self, input_size: Size) -> Size {
+        let (sin, cos) = self.0.to_radians().sin_cos();
+        let (w, h) = (input_size.width as f64, input_size.height as f64);
+
+        // Round away tiny errors so that multiples of 90 degrees keep exact sizes
+        let fit = |x: f64| (x - 1e-9).ceil().max(1.0) as usize;
+        Size::new(
+            fit(w * cos.abs() + h * sin.abs()),
+            fit(w * sin.abs() + h * cos.abs()),
+        )
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Pad {
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+}
+
+/// Add a border of zero-valued pixels around an image
+pub fn pad<T: Type, C: Color, U: Type, D: Color>(
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+) -> impl GeometryFilter<T, C, U, D> {
+    Pad {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Pad {
+    fn output_size(&self, input: &Input<T, C>, _dest: &mut Image<U, D>) -> Size {
+        GeometryFilter::<T, C, U, D>::output_size(self, input.images()[0].size())
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let px = if pt.x < self.left || pt.y < self.top {
+            Pixel::new()
+        } else {
+            input.get_pixel((pt.x - self.left, pt.y - self.top), None)
+        };
+        px.convert_to_data(dest);
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> GeometryFilter<T, C, U, D> for Pad {
+    fn output_size(&self, input_size: Size) -> Size {
+        Size::new(
+            input_size.width + self.left + self.right,
+            input_size.height + self.top + self.bottom,
+        )
+    }
+}
+
+/// Summed-area table over a rectangle of an image, used for constant time box filtering
+struct Integral {
+    x: usize,
+    y: usize,
+    width: usize,
+    data: Vec<f64>,
+}
+
+impl Integral {
+    /// Build table for the `width`x`height` rectangle starting at `(x, y)`, `f` is called with
+    /// offsets relative to the rectangle
+    fn new(x: usize, y: usize, width: usize, height: usize, f: impl Fn(usize) -> f64) -> Integral {
+        let stride = width + 1;
+        let mut data = vec![0.0; stride * (height + 1)];
+        for j in 0..height {
+            let mut row = 0.0;
+            for i in 0..width {
+                row += f(j * width + i);
+                data[(j + 1) * stride + i + 1] = data[j * stride + i + 1] + row;
+            }
+        }
+        Integral { x, y, width, data }
+    }
+
+    /// Mean over the absolute rectangle `[x0, x1)`x`[y0, y1)`
+    fn mean(&self, (x0, y0, x1, y1): (usize, usize, usize, usize)) -> f64 {
+        let stride = self.width + 1;
+        let (x0, x1) = (x0 - self.x, x1 - self.x);
+        let (y0, y1) = (y0 - self.y, y1 - self.y);
+        let sum =
+            self.data[y1 * stride + x1] - self.data[y0 * stride + x1] - self.data[y1 * stride + x0]
+                + self.data[y0 * stride + x0];
+        sum / ((x1 - x0) * (y1 - y0)) as f64
+    }
+}
+
+/// Guided filter from He et al., "Guided Image Filtering". Smooths the input while preserving
+/// edges present in the guide, window statistics are computed using summed-area tables so the
+/// cost doesn't depend on `radius`
+///
+/// The guide is the luminance of the input image with index `guide`, using `0` makes the image
+/// guide itself. Alpha channels are left unchanged
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Guided {
+    /// Index of the input image used as guide
+    pub guide: usize,
+
+    /// Window radius
+    pub radius: usize,
+
+    /// Regularization, edges with a variance smaller than `eps` are smoothed
+    pub eps: f64,
+}
+
+/// Self-guided edge-preserving smoothing, see `Guided`
+pub fn guided<T: Type, C: Color, U: Type, D: Color>(
+    radius: usize,
+    eps: f64,
+) -> impl Filter<T, C, U, D> {
+    Guided {
+        guide: 0,
+        radius,
+        eps,
+    }
+}
+
+impl Guided {
+    /// Window of `radius` around `(x, y)` clamped to `size`
+    fn window(&self, x: usize, y: usize, size: Size) -> (usize, usize, usize, usize) {
+        let r = self.radius;
+        (
+            x.saturating_sub(r),
+            y.saturating_sub(r),
+            (x + r + 1).min(size.width),
+            (y + r + 1).min(size.height),
+        )
+    }
+
+    /// Filter the `width`x`height` rectangle starting at `(x, y)`, returning pixels in row order
+    fn filter_region<T: Type, C: Color>(
+        &self,
+        input: &Input<T, C>,
+        (x, y, width, height): (usize, usize, usize, usize),
+    ) -> Vec<Pixel<C>> {
+        let images = input.images();
+        let size = images[0].size();
+        let guide = images[self.guide.min(images.len() - 1)];
+
+        // Coefficients are needed for every window touching the output, and their statistics for
+        // every window touching those
+        let r = self.radius;
+        let (cx0, cy0) = (x.saturating_sub(r), y.saturating_sub(r));
+        let (cx1, cy1) = (
+            (x + width + r).min(size.width),
+            (y + height + r).min(size.height),
+        );
+        let (px0, py0) = (x.saturating_sub(2 * r), y.saturating_sub(2 * r));
+        let (px1, py1) = (
+            (x + width + 2 * r).min(size.width),
+            (y + height + 2 * r).min(size.height),
+        );
+        let (pw, ph) = (px1 - px0, py1 - py0);
+
+        let point = |i: usize, w: usize, x0: usize, y0: usize| (x0 + i % w, y0 + i / w);
+        let g: Vec<f64> = (0..pw * ph)
+            .map(|i| guide.get_pixel(point(i, pw, px0, py0)).convert::<Gray>()[0])
+            .collect();
+        let p: Vec<Pixel<C>> = (0..pw * ph)
+            .map(|i| input.get_pixel(point(i, pw, px0, py0), None))
+            .collect();
+        let mean_g = Integral::new(px0, py0, pw, ph, |i| g[i]);
+        let mean_gg = Integral::new(px0, py0, pw, ph, |i| g[i] * g[i]);
+
+        let (cw, ch) = (cx1 - cx0, cy1 - cy0);
+        let mut a = vec![Pixel::<C>::new(); cw * ch];
+        let mut b = vec![Pixel::<C>::new(); cw * ch];
+        for c in (0..C::CHANNELS).filter(|c| C::ALPHA != Some(*c)) {
+            let mean_p = Integral::new(px0, py0, pw, ph, |i| p[i][c]);
+            let mean_gp = Integral::new(px0, py0, pw, ph, |i| g[i] * p[i][c]);
+            for i in 0..cw * ch {
+                let (kx, ky) = point(i, cw, cx0, cy0);
+                let window = self.window(kx, ky, size);
+                let (mg, mp) = (mean_g.mean(window), mean_p.mean(window));
+                let var = mean_gg.mean(window) - mg * mg;
+                a[i][c] = (mean_gp.mean(window) - mg * mp) / (var + self.eps);
+                b[i][c] = mp - a[i][c] * mg;
+            }
+        }
+
+        let mut dest = Vec::with_capacity(width * height);
+        let mut mean_a = Vec::with_capacity(C::CHANNELS);
+        let mut mean_b = Vec::with_capacity(C::CHANNELS);
+        for c in 0..C::CHANNELS {
+            mean_a.push(Integral::new(cx0, cy0, cw, ch, |i| a[i][c]));
+            mean_b.push(Integral::new(cx0, cy0, cw, ch, |i| b[i][c]));
+        }
+        for i in 0..width * height {
+            let (ox, oy) = point(i, width, x, y);
+            let window = self.window(ox, oy, size);
+            let j = (oy - py0) * pw + ox - px0;
+            let mut px = p[j].clone();
+            for c in (0..C::CHANNELS).filter(|c| C::ALPHA != Some(*c)) {
+                px[c] = mean_a[c].mean(window) * g[j] + mean_b[c].mean(window);
+            }
+            dest.push(px);
+        }
+        dest
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Guided {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        self.filter_region(input, (pt.x, pt.y, 1, 1))[0].convert_to_data(dest);
+    }
+
+    fn eval(&self, input: &[&Image<T, C>], output: &mut Image<U, D>) {
+        let input = Input::new(input);
+        let size = input.images()[0].size();
+        let pixels = self.filter_region(&input, (0, 0, size.width, size.height));
+        output.for_each(|pt, mut data| {
+            if pt.x < size.width && pt.y < size.height {
+                pixels[pt.y * size.width + pt.x].convert_to_data(&mut data);
+            }
+        });
+    }
+}
+
+/// Gaussian blur with standard deviation `sigma` in pixels, the kernel radius is picked
+/// automatically and the blur is applied as two separable passes. When `sigma` is larger than
+/// `GaussianBlur::DOWNSAMPLE_SIGMA` the image is blurred at a lower resolution and upsampled,
+/// which keeps the cost roughly constant for very large blurs
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GaussianBlur(pub f64);
+
+/// Blur using a Gaussian with standard deviation `sigma`, see `GaussianBlur`
+pub fn gaussian_blur<T: Type, C: Color, U: Type, D: Color>(sigma: f64) -> impl Filter<T, C, U, D> {
+    GaussianBlur(sigma)
+}
+
+impl GaussianBlur {
+    /// Largest `sigma` blurred at full resolution
+    pub const DOWNSAMPLE_SIGMA: f64 = 8.0;
+
+    /// Kernel radius covering three standard deviations
+    pub fn radius(&self) -> usize {
+        (3.0 * self.0.max(0.0)).ceil() as usize
+    }
+
+    /// Normalized kernel weights for offsets `-radius..=radius`
+    fn weights(sigma: f64) -> Vec<f64> {
+        let r = (3.0 * sigma).ceil() as isize;
+        let weights: Vec<f64> = (-r..=r)
+            .map(|i| (-((i * i) as f64) / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let total: f64 = weights.iter().sum();
+        weights.into_iter().map(|w| w / total).collect()
+    }
+
+    /// Blur interleaved `channels` values in place, edges are extended
+    fn blur(data: &mut [f64], (width, height, channels): (usize, usize, usize), sigma: f64) {
+        if sigma <= 0.0 || width == 0 || height == 0 {
+            return;
+        }
+
+        let weights = GaussianBlur::weights(sigma);
+        let r = (weights.len() / 2) as isize;
+        let mut tmp = vec![0.0; data.len()];
+        let pass = |src: &[f64], dest: &mut [f64], horizontal: bool| {
+            let (len, stride) = if horizontal {
+                (width as isize, channels)
+            } else {
+                (height as isize, width * channels)
+            };
+            for y in 0..height {
+                for x in 0..width {
+                    let p = if horizontal { x } else { y } as isize;
+                    let base = (y * width + x) * channels - p as usize * stride;
+                    for c in 0..channels {
+                        let mut sum = 0.0;
+                        for (k, w) in (-r..=r).zip(&weights) {
+                            let i = (p + k).clamp(0, len - 1) as usize;
+                            sum += src[base + i * stride + c] * w;
+                        }
+                        dest[(y * width + x) * channels + c] = sum;
+                    }
+                }
+            }
+        };
+        pass(data, &mut tmp, true);
+        pass(&tmp, data, false);
+    }
+
+    /// Blur a normalized image buffer
+    fn blur_image(
+        &self,
+        data: &[f64],
+        (width, height, channels): (usize, usize, usize),
+    ) -> Vec<f64> {
+        let sigma = self.0.max(0.0);
+        if sigma <= GaussianBlur::DOWNSAMPLE_SIGMA {
+            let mut dest = data.to_vec();
+            GaussianBlur::blur(&mut dest, (width, height, channels), sigma);
+            return dest;
+        }
+
+        // Box downsampling and bilinear upsampling both add some blur, which is subtracted from
+        // the blur applied at the lower resolution
+        let factor = ((sigma / (GaussianBlur::DOWNSAMPLE_SIGMA / 2.0)) as usize)
+            .next_power_of_two()
+            .max(2);
+        let f = factor as f64;
+        let small_sigma = (sigma * sigma - (f * f - 1.0) / 12.0 - f * f / 6.0)
+            .max(0.0)
+            .sqrt()
+            / f;
+        let (sw, sh) = (width.div_ceil(factor), height.div_ceil(factor));
+        let mut small = vec![0.0; sw * sh * channels];
+        let mut count = vec![0usize; sw * sh];
+        for y in 0..height {
+            for x in 0..width {
+                let i = (y / factor) * sw + x / factor;
+                for c in 0..channels {
+                    small[i * channels + c] += data[(y * width + x) * channels + c];
+                }
+                count[i] += 1;
+            }
+        }
+        for (i, n) in count.iter().enumerate() {
+            for c in 0..channels {
+                small[i * channels + c] /= *n as f64;
+            }
+        }
+        GaussianBlur::blur(&mut small, (sw, sh, channels), small_sigma);
+
+        let mut dest = vec![0.0; data.len()];
+        let pos = |p: usize, n: usize| {
+            let v = ((p as f64 + 0.5) / f - 0.5).clamp(0.0, (n - 1) as f64);
+            let i0 = v.floor() as usize;
+            (i0, (i0 + 1).min(n - 1), v - i0 as f64)
+        };
+        for y in 0..height {
+            let (y0, y1, fy) = pos(y, sh);
+            for x in 0..width {
+                let (x0, x1, fx) = pos(x, sw);
+                for c in 0..channels {
+                    let at = |x: usize, y: usize| small[(y * sw + x) * channels + c];
+                    let top = at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx;
+                    let bottom = at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx;
+                    dest[(y * width + x) * channels + c] = top * (1.0 - fy) + bottom * fy;
+                }
+            }
+        }
+        dest
+    }
+}
+
+/// Apply the separable kernel `weights`, centered on `pt`, to the first input image. Edges are
+/// extended
+fn separable_at<T: Type, C: Color>(pt: Point, input: &Input<T, C>, weights: &[f64]) -> Pixel<C> {
+    let image = input.images()[0];
+    let r = (weights.len() / 2) as isize;
+    let (w, h) = (image.width() as isize, image.height() as isize);
+    let mut px = Pixel::<C>::new();
+    for (dy, wy) in (-r..=r).zip(weights) {
+        let y = (pt.y as isize + dy).clamp(0, h - 1) as usize;
+        for (dx, wx) in (-r..=r).zip(weights) {
+            let x = (pt.x as isize + dx).clamp(0, w - 1) as usize;
+            let src = input.get_pixel((x, y), None);
+            for c in 0..C::CHANNELS {
+                px[c] += src[c] * wx * wy;
+            }
+        }
+    }
+    px
+}
+
+/// Evaluate a filter over the normalized values of the first input image, `f` receives the
+/// values and `(width, height, channels)`
+fn eval_buffer<T: Type, C: Color, U: Type, D: Color>(
+    input: &[&Image<T, C>],
+    output: &mut Image<U, D>,
+    f: impl FnOnce(&[f64], (usize, usize, usize)) -> Vec<f64>,
+) {
+    let image = input[0];
+    let data: Vec<f64> = image.data().iter().map(Type::to_norm).collect();
+    let shape = (image.width(), image.height(), C::CHANNELS);
+    let result = f(&data, shape);
+    output.for_each(|pt, mut data| {
+        if pt.x < shape.0 && pt.y < shape.1 {
+            let i = (pt.y * shape.0 + pt.x) * C::CHANNELS;
+            Pixel::<C>::from(result[i..i + C::CHANNELS].to_vec()).convert_to_data(&mut data);
+        }
+    });
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for GaussianBlur {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[self.0])
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let sigma = self.0.max(0.0);
+        if sigma == 0.0 {
+            input.get_pixel(pt, None).convert_to_data(dest);
+            return;
+        }
+
+        separable_at(pt, input, &GaussianBlur::weights(sigma)).convert_to_data(dest);
+    }
+
+    fn eval(&self, input: &[&Image<T, C>], output: &mut Image<U, D>) {
+        eval_buffer(input, output, |data, shape| self.blur_image(data, shape));
+    }
+}
+
+/// Moving average over `[p + lo, p + hi]` along one axis, edges are extended. Uses a running
+/// sum so the cost doesn't depend on the window size
+fn running_mean(
+    data: &mut [f64],
+    tmp: &mut [f64],
+    (width, height, channels): (usize, usize, usize),
+    (lo, hi): (isize, isize),
+    horizontal: bool,
+) {
+    let (len, stride, lines) = if horizontal {
+        (width, channels, height)
+    } else {
+        (height, width * channels, width)
+    };
+    if len == 0 {
+        return;
+    }
+
+    let n = (hi - lo + 1) as f64;
+    let clamp = |i: isize| i.clamp(0, len as isize - 1) as usize;
+    for line in 0..lines {
+        let base = if horizontal {
+            line * width * channels
+        } else {
+            line * channels
+        };
+        for c in 0..channels {
+            let at = |i: usize| base + i * stride + c;
+            let mut sum: f64 = (lo..=hi).map(|i| data[at(clamp(i))]).sum();
+            for p in 0..len as isize {
+                tmp[at(p as usize)] = sum / n;
+                sum += data[at(clamp(p + hi + 1))] - data[at(clamp(p + lo))];
+            }
+        }
+    }
+    data.copy_from_slice(tmp);
+}
+
+/// Stack blur, this approximates a Gaussian blur using a triangular kernel that extends `radius`
+/// pixels in each direction. The cost per pixel doesn't depend on the radius, which makes it
+/// suitable for real-time previews
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StackBlur(pub usize);
+
+/// Fast blur using a triangular kernel, see `StackBlur`
+pub fn stack_blur<T: Type, C: Color, U: Type, D: Color>(radius: usize) -> impl Filter<T, C, U, D> {
+    StackBlur(radius)
+}
+
+impl StackBlur {
+    /// Triangular weights for offsets `-radius..=radius`
+    fn weights(&self) -> Vec<f64> {
+        let r = self.0 as isize;
+        let total = ((r + 1) * (r + 1)) as f64;
+        (-r..=r).map(|i| (r + 1 - i.abs()) as f64 / total).collect()
+    }
+
+    fn blur(&self, data: &[f64], shape: (usize, usize, usize)) -> Vec<f64> {
+        let mut dest = data.to_vec();
+        let mut tmp = vec![0.0; data.len()];
+        let r = self.0 as isize;
+
+        // Two boxes of width `radius + 1`, offset in opposite directions, combine into a centered
+        // triangle
+        for horizontal in [true, false] {
+            running_mean(&mut dest, &mut tmp, shape, (0, r), horizontal);
+            running_mean(&mut dest, &mut tmp, shape, (-r, 0), horizontal);
+        }
+        dest
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for StackBlur {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        separable_at(pt, input, &self.weights()).convert_to_data(dest);
+    }
+
+    fn eval(&self, input: &[&Image<T, C>], output: &mut Image<U, D>) {
+        eval_buffer(input, output, |data, shape| self.blur(data, shape));
+    }
+}
+
+/// Box blur over a `2 * radius + 1` window, repeated `passes` times. Three passes are close to a
+/// Gaussian with a standard deviation of `sqrt(passes * radius * (radius + 1) / 3)`, the cost per
+/// pixel doesn't depend on the radius
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoxBlur {
+    /// Window radius
+    pub radius: usize,
+
+    /// Number of passes
+    pub passes: usize,
+}
+
+/// Fast approximate Gaussian blur using three box blur passes, see `BoxBlur`
+pub fn box_blur<T: Type, C: Color, U: Type, D: Color>(radius: usize) -> impl Filter<T, C, U, D> {
+    BoxBlur::new(radius)
+}
+
+impl BoxBlur {
+    /// Create a new `BoxBlur` using three passes
+    pub fn new(radius: usize) -> BoxBlur {
+        BoxBlur { radius, passes: 3 }
+    }
+
+    /// Set number of passes
+    pub fn with_passes(mut self, passes: usize) -> BoxBlur {
+        self.passes = passes;
+        self
+    }
+
+    /// Combined weights of all passes
+    fn weights(&self) -> Vec<f64> {
+        let width = 2 * self.radius + 1;
+        let mut weights = vec![1.0];
+        for _ in 0..self.passes {
+            let mut next = vec![0.0; weights.len() + width - 1];
+            for (i, w) in weights.iter().enumerate() {
+                for x in &mut next[i..i + width] {
+                    *x += w / width as f64;
+                }
+            }
+            weights = next;
+        }
+        weights
+    }
+
+    fn blur(&self, data: &[f64], shape: (usize, usize, usize)) -> Vec<f64> {
+        let mut dest = data.to_vec();
+        let mut tmp = vec![0.0; data.len()];
+        let r = self.radius as isize;
+        for horizontal in [true, false] {
+            for _ in 0..self.passes {
+                running_mean(&mut dest, &mut tmp, shape, (-r, r), horizontal);
+            }
+        }
+        dest
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for BoxBlur {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[self.radius as f64, self.passes as f64])
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        separable_at(pt, input, &self.weights()).convert_to_data(dest);
+    }
+
+    fn eval(&self, input: &[&Image<T, C>], output: &mut Image<U, D>) {
+        eval_buffer(input, output, |data, shape| self.blur(data, shape));
+    }
+}
+
+/// Determines the blur radius of each pixel for `LensBlur`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Focus {
+    /// Depth of field from a depth map stored as the input image with index `input`, the
+    /// luminance of each pixel is its normalized depth. Pixels at depth `focus` are sharp and the
+    /// blur radius grows with the distance from it, reaching the maximum at a distance of `range`
+    DepthMap {
+        /// Index of the depth map input
+        input: usize,
+
+        /// Depth in focus
+        focus: f64,
+
+        /// Depth distance with the maximum blur radius
+        range: f64,
+    },
+
+    /// Tilt-shift, a horizontal band centered on `center` is sharp and the blur radius grows
+    /// with the distance from it, reaching the maximum `falloff` away from the band. All values
+    /// are relative to the image height
+    TiltShift {
+        /// Center of the band
+        center: f64,
+
+        /// Height of the band
+        width: f64,
+
+        /// Distance from the band with the maximum blur radius
+        falloff: f64,
+    },
+}
+
+/// Aperture shape, this is the shape of out of focus highlights
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Bokeh {
+    /// Circular aperture
+    #[default]
+    Disk,
+
+    /// Regular polygon with the given number of aperture blades
+    Polygon(usize),
+}
+
+impl Bokeh {
+    /// Coverage of the offset `(dx, dy)` by the shape scaled to `radius`, edges are antialiased
+    fn coverage(&self, dx: f64, dy: f64, radius: f64) -> f64 {
+        let dist = dx.hypot(dy);
+        let edge = match *self {
+            Bokeh::Disk => radius,
+            Bokeh::Polygon(blades) => {
+                let n = blades.max(3) as f64;
+                let sector = std::f64::consts::TAU / n;
+                let theta = dy.atan2(dx).rem_euclid(sector) - sector / 2.0;
+                radius * (sector / 2.0).cos() / theta.cos()
+            }
+        };
+        (edge - dist + 0.5).clamp(0.0, 1.0)
+    }
+}
+
+/// Variable radius blur simulating a camera lens, used for depth of field and tilt-shift effects
+///
+/// Each output pixel gathers the input pixels whose blur shape covers it, weighted by the inverse
+/// of the shape area, so in-focus pixels stay sharp next to blurred ones. With a depth map, pixels
+/// behind the output pixel only contribute up to its own blur radius, which stops blurred
+/// backgrounds from bleeding over a sharp foreground. Pixels brighter than `highlight_threshold`
+/// are given more weight to produce bright bokeh highlights
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LensBlur {
+    /// Blur radius source
+    pub focus: Focus,
+
+    /// Maximum blur radius in pixels
+    pub max_radius: f64,
+
+    /// Aperture shape
+    pub bokeh: Bokeh,
+
+    /// Luminance above which pixels are boosted
+    pub highlight_threshold: f64,
+
+    /// Extra weight per unit of luminance above `highlight_threshold`
+    pub highlight_gain: f64,
+}
+
+/// Lens blur using the provided focus and maximum radius, see `LensBlur`
+pub fn lens_blur<T: Type, C: Color, U: Type, D: Color>(
+    focus: Focus,
+    max_radius: f64,
+) -> impl Filter<T, C, U, D> {
+    LensBlur::new(focus, max_radius)
+}
+
+/// Tilt-shift blur with a sharp band of `width` centered on `center`, relative to the image height
+pub fn tilt_shift<T: Type, C: Color, U: Type, D: Color>(
+    center: f64,
+    width: f64,
+    max_radius: f64,
+) -> impl Filter<T, C, U, D> {
+    LensBlur::new(
+        Focus::TiltShift {
+            center,
+            width,
+            falloff: (1.0 - width) / 2.0,
+        },
+        max_radius,
+    )
+}
+
+impl LensBlur {
+    /// Create a new `LensBlur` with a circular aperture and no highlight boost
+    pub fn new(focus: Focus, max_radius: f64) -> LensBlur {
+        LensBlur {
+            focus,
+            max_radius,
+            bokeh: Bokeh::Disk,
+            highlight_threshold: 1.0,
+            highlight_gain: 0.0,
+        }
+    }
+
+    /// Set aperture shape
+    pub fn with_bokeh(mut self, bokeh: Bokeh) -> LensBlur {
+        self.bokeh = bokeh;
+        self
+    }
+
+    /// Boost pixels with a luminance above `threshold`
+    pub fn with_highlights(mut self, threshold: f64, gain: f64) -> LensBlur {
+        self.highlight_threshold = threshold;
+        self.highlight_gain = gain;
+        self
+    }
+
+    /// Depth, if available, and blur radius at `pt`
+    fn radius_at<T: Type, C: Color>(&self, pt: Point, input: &Input<T, C>) -> (Option<f64>, f64) {
+        let max = self.max_radius.max(0.0);
+        match self.focus {
+            Focus::DepthMap {
+                input: index,
+                focus,
+                range,
+            } => {
+                let depth = input.get_pixel(pt, Some(index)).convert::<Gray>()[0];
+                let t = ((depth - focus).abs() / range.max(f64::EPSILON)).min(1.0);
+                (Some(depth), t * max)
+            }
+            Focus::TiltShift {
+                center,
+                width,
+                falloff,
+            } => {
+                let height = input.images()[0].height().max(1) as f64;
+                let y = (pt.y as f64 + 0.5) / height;
+                let d = (y - center).abs() - width / 2.0;
+                let t = (d / falloff.max(f64::EPSILON)).clamp(0.0, 1.0);
+                (None, t * max)
+            }
+        }
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for LensBlur {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let image = input.images()[0];
+        let (w, h) = (image.width() as isize, image.height() as isize);
+        let (depth, radius) = self.radius_at(pt, input);
+        let reach = self.max_radius.max(0.0).ceil() as isize;
+
+        let mut sum = Pixel::<C>::new();
+        let mut total = 0.0;
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let (x, y) = (pt.x as isize + dx, pt.y as isize + dy);
+                if x < 0 || y < 0 || x >= w || y >= h {
+                    continue;
+                }
+
+                let src = Point::new(x as usize, y as usize);
+                let (src_depth, src_radius) = self.radius_at(src, input);
+                let effective = match (depth, src_depth) {
+                    (Some(a), Some(b)) if b > a => src_radius.min(radius),
+                    _ => src_radius,
+                };
+                let coverage = if dx == 0 && dy == 0 {
+                    1.0
+                } else {
+                    self.bokeh.coverage(dx as f64, dy as f64, effective)
+                };
+                if coverage <= 0.0 {
+                    continue;
+                }
+
+                let px = input.get_pixel(src, None);
+                let luminance = px.convert::<Gray>()[0];
+                let boost =
+                    1.0 + self.highlight_gain * (luminance - self.highlight_threshold).max(0.0);
+                let area = std::f64::consts::PI * src_radius.max(0.5).powi(2);
+                let weight = coverage * boost / area;
+                for c in 0..C::CHANNELS {
+                    sum[c] += px[c] * weight;
+                }
+                total += weight;
+            }
+        }
+
+        for c in 0..C::CHANNELS {
+            sum[c] /= total;
+        }
+        sum.convert_to_data(dest);
+    }
+}
+
+/// Pixel art upscaling using the xBR edge detection rules, see `xbr`
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Xbr(pub usize);
+
+/// Upscale pixel art by an integer factor, diagonal edges are smoothed while horizontal and
+/// vertical edges stay sharp. The destination image should be `factor` times the size of the input
+pub fn xbr<T: Type, C: Color, U: Type, D: Color>(factor: usize) -> impl GeometryFilter<T, C, U, D> {
+    Xbr(factor)
+}
+
+impl Xbr {
+    /// Weighted YUV difference between two pixels
+    fn difference<C: Color>(a: &Pixel<C>, b: &Pixel<C>) -> f64 {
+        let yuv = |px: &Pixel<C>| {
+            let rgb: Pixel<Rgb> = px.convert();
+            let y = 0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2];
+            [y, 0.492 * (rgb[2] - y), 0.877 * (rgb[0] - y)]
+        };
+        let (a, b) = (yuv(a), yuv(b));
+        48.0 * (a[0] - b[0]).abs() + 7.0 * (a[1] - b[1]).abs() + 6.0 * (a[2] - b[2]).abs()
+    }
+
+    /// Replacement color for the corner of `E` pointing towards `I`, if there is an edge along
+    /// `F`-`H`, offsets are relative to `E` in the direction of the corner:
+    ///
+    /// ```text
+    ///     A  B  C  C4
+    ///     D  E  F  F4
+    ///     G  H  I  I4
+    ///        H5 I5
+    /// ```
+    fn corner<C: Color>(at: impl Fn(isize, isize) -> Pixel<C>) -> Option<Pixel<C>> {
+        let d = Self::difference;
+        let (e, f, h, i) = (at(0, 0), at(1, 0), at(0, 1), at(1, 1));
+        let (b, c, d0, g) = (at(0, -1), at(1, -1), at(-1, 0), at(-1, 1));
+        let (f4, i4, h5, i5) = (at(2, 0), at(2, 1), at(0, 2), at(1, 2));
+
+        let wd1 = d(&e, &c) + d(&e, &g) + d(&i, &f4) + d(&i, &h5) + 4.0 * d(&h, &f);
+        let wd2 = d(&h, &d0) + d(&h, &i5) + d(&f, &i4) + d(&f, &b) + 4.0 * d(&e, &i);
+        if wd1 >= wd2 {
+            return None;
+        }
+
+        Some(if d(&e, &f) <= d(&e, &h) { f } else { h })
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> GeometryFilter<T, C, U, D> for Xbr {
+    fn output_size(&self, input_size: Size) -> Size {
+        Size::new(
+            input_size.width * self.0.max(1),
+            input_size.height * self.0.max(1),
+        )
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Xbr {
+    fn output_size(&self, input: &Input<T, C>, _dest: &mut Image<U, D>) -> Size {
+        GeometryFilter::<T, C, U, D>::output_size(self, input.images()[0].size())
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let n = self.0.max(1);
+        let size = input.images()[0].size();
+        let (x, y) = ((pt.x / n) as isize, (pt.y / n) as isize);
+        let get = |dx: isize, dy: isize| {
+            let px = (x + dx).clamp(0, size.width as isize - 1) as usize;
+            let py = (y + dy).clamp(0, size.height as isize - 1) as usize;
+            input.get_pixel((px, py), None)
+        };
+
+        let mut px = get(0, 0);
+        let fx = ((pt.x % n) as f64 + 0.5) / n as f64;
+        let fy = ((pt.y % n) as f64 + 0.5) / n as f64;
+        for (sx, sy) in [(1, 1), (-1, 1), (1, -1), (-1, -1)] {
+            // The edge crosses the pixel diagonally through the midpoints of its two sides
+            let u = if sx > 0 { fx } else { 1.0 - fx };
+            let v = if sy > 0 { fy } else { 1.0 - fy };
+            let weight = ((u + v - 1.5) * n as f64 + 0.5).clamp(0.0, 1.0);
+            if weight <= 0.0 {
+                continue;
+            }
+
+            if let Some(color) = Self::corner(|dx, dy| get(dx * sx, dy * sy)) {
+                for c in 0..C::CHANNELS {
+                    px[c] = px[c] * (1.0 - weight) + color[c] * weight;
+                }
+            }
+        }
+        px.convert_to_data(dest);
+    }
+}
+
+/// Statistics computed from a whole input image before a filter is applied, cached using the
+/// address of the image data so they are only computed once per image
+#[derive(Default)]
+struct PrePass<S>(std::sync::RwLock<Option<(usize, Size, std::sync::Arc<S>)>>);
+
+// The cached statistics aren't part of the filter configuration
+impl<S> std::fmt::Debug for PrePass<S> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.write_str("PrePass")
+    }
+}
+
+impl<S> PrePass<S> {
+    fn get<T: Type, C: Color>(
+        &self,
+        image: &Image<T, C>,
+        f: impl FnOnce(&Image<T, C>) -> S,
+    ) -> std::sync::Arc<S> {
+        let key = (image.data().as_ptr() as usize, image.size());
+        if let Ok(cache) = self.0.read() {
+            if let Some((ptr, size, stats)) = cache.as_ref() {
+                if (*ptr, *size) == key {
+                    return stats.clone();
+                }
+            }
+        }
+
+        let stats = std::sync::Arc::new(f(image));
+        if let Ok(mut cache) = self.0.write() {
+            *cache = Some((key.0, key.1, stats.clone()));
+        }
+        stats
+    }
+}
+
+/// Per-channel `(low, high, gamma)` computed by `AutoLevels`
+type Levels = Vec<(f64, f64, f64)>;
+
+/// Mode used by `AutoLevels`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AutoMode {
+    /// Stretch each channel independently, this also removes some color casts
+    Levels,
+
+    /// Stretch all channels by the same amount, this preserves hue
+    Contrast,
+
+    /// Stretch each channel independently then adjust the gamma of each channel so that midtones
+    /// are neutral
+    Color,
+}
+
+/// Automatic tone adjustment, per-channel percentiles are computed in a pre-pass over the input
+/// image and used to stretch values to the full range, see `auto_levels`, `auto_contrast` and
+/// `auto_color`. The alpha channel is left unchanged
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AutoLevels {
+    /// Percent of values clipped at each end of the range, this keeps a few outliers from
+    /// limiting the adjustment
+    pub clip_percent: f64,
+
+    /// Adjustment mode
+    pub mode: AutoMode,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    levels: PrePass<Levels>,
+}
+
+impl AutoLevels {
+    /// Create a new `AutoLevels` filter
+    pub fn new(clip_percent: f64, mode: AutoMode) -> AutoLevels {
+        AutoLevels {
+            clip_percent,
+            mode,
+            levels: PrePass::default(),
+        }
+    }
+
+    /// Compute the per-channel `(low, high, gamma)` used to adjust `image`
+    pub fn levels<T: Type, C: Color>(&self, image: &Image<T, C>) -> Vec<(f64, f64, f64)> {
+        let hist = image.reduce(reduce::Histograms::new(4096));
+        let channels: Vec<Channel> = (0..C::CHANNELS).filter(|c| C::ALPHA != Some(*c)).collect();
+        let mut levels: Levels = hist
+            .iter()
+            .map(|h| {
+                (
+                    h.percentile(self.clip_percent),
+                    h.percentile(100.0 - self.clip_percent),
+                    1.0,
+                )
+            })
+            .collect();
+
+        if self.mode == AutoMode::Contrast {
+            let low = channels.iter().map(|c| levels[*c].0).fold(1.0, f64::min);
+            let high = channels.iter().map(|c| levels[*c].1).fold(0.0, f64::max);
+            for c in &channels {
+                levels[*c] = (low, high, 1.0);
+            }
+        }
+
+        if self.mode == AutoMode::Color && !channels.is_empty() {
+            // Midtone of each channel after stretching, estimated from the histogram
+            let means: Vec<f64> = channels
+                .iter()
+                .map(|c| {
+                    let (low, high, _) = levels[*c];
+                    let n = (hist[*c].len() - 1) as f64;
+                    let sum: f64 = hist[*c]
+                        .bins()
+                        .map(|(i, x)| {
+                            ((i as f64 / n - low) / (high - low).max(f64::EPSILON)).clamp(0.0, 1.0)
+                                * x as f64
+                        })
+                        .sum();
+                    sum / hist[*c].sum().max(1) as f64
+                })
+                .collect();
+            let target = means.iter().sum::<f64>() / means.len() as f64;
+            for (c, mean) in channels.iter().zip(means) {
+                if mean > 0.0 && mean < 1.0 && target > 0.0 && target < 1.0 {
+                    levels[*c].2 = target.ln() / mean.ln();
+                }
+            }
+        }
+
+        if let Some(alpha) = C::ALPHA {
+            levels[alpha] = (0.0, 1.0, 1.0);
+        }
+        levels
+    }
+}
+
+/// Stretch each channel so that `clip_percent` percent of the values are clipped at each end
+pub fn auto_levels<T: Type, C: Color, U: Type, D: Color>(
+    clip_percent: f64,
 ) -> impl Filter<T, C, U, D> {
-    let center = center.to_tuple();
-    Transform::rotation(euclid::Angle::degrees(-deg))
-        .pre_translate(euclid::Vector2D::new(
-            -(center.0 as f64),
-            -(center.1 as f64),
-        ))
-        .then_translate(euclid::Vector2D::new(center.0 as f64, center.1 as f64))
+    AutoLevels::new(clip_percent, AutoMode::Levels)
 }
 
-#[inline]
-/// Build scale `Transform`
-pub fn scale<T: Type, C: Color, U: Type, D: Color>(x: f64, y: f64) -> impl Filter<T, C, U, D> {
-    Transform::scale(1.0 / x, 1.0 / y)
+/// Stretch all channels by the same amount, preserving hue
+pub fn auto_contrast<T: Type, C: Color, U: Type, D: Color>(
+    clip_percent: f64,
+) -> impl Filter<T, C, U, D> {
+    AutoLevels::new(clip_percent, AutoMode::Contrast)
 }
 
-#[inline]
-/// Build resize transform
-pub fn resize<T: Type, C: Color, U: Type, D: Color>(
-    from: Size,
-    to: Size,
+/// Stretch each channel and neutralize midtones to remove color casts
+pub fn auto_color<T: Type, C: Color, U: Type, D: Color>(
+    clip_percent: f64,
 ) -> impl Filter<T, C, U, D> {
-    Transform::scale(
-        from.width as f64 / to.width as f64,
-        from.height as f64 / to.height as f64,
-    )
+    AutoLevels::new(clip_percent, AutoMode::Color)
 }
 
-/// 90 degree rotation
-pub fn rotate90<T: Type, C: Color, U: Type, D: Color>(
-    from: Size,
-    to: Size,
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for AutoLevels {
+    fn fingerprint(&self) -> Option<u64> {
+        ContentHasher::fingerprint::<Self>(&[self.clip_percent, f64::from(self.mode as u8)])
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let levels = self
+            .levels
+            .get(input.images()[0], |image| self.levels(image));
+        let mut px = input.get_pixel(pt, None);
+        for (c, (low, high, gamma)) in levels.iter().enumerate() {
+            if C::ALPHA == Some(c) {
+                continue;
+            }
+            let x = ((px[c] - low) / (high - low).max(f64::EPSILON)).clamp(0.0, 1.0);
+            px[c] = x.powf(*gamma);
+        }
+        px.convert_to_data(dest);
+    }
+}
+
+/// Automatic exposure for previewing HDR images, the log-average luminance of the input is
+/// computed in a pre-pass and a gain is applied so that it maps to `target_middle_gray`. Use it
+/// before a tone mapping operator to get a viewable image in one step. The alpha channel is left
+/// unchanged
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AutoExposure {
+    /// Linear value the log-average luminance is mapped to, `0.18` is the usual middle gray
+    pub target_middle_gray: f64,
+
+    /// Percent of luminance values ignored at each end of the range, this keeps specular
+    /// highlights and black borders from skewing the exposure
+    pub percentile_clip: f64,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    gain: PrePass<f64>,
+}
+
+impl Default for AutoExposure {
+    fn default() -> Self {
+        AutoExposure::new(0.18, 1.0)
+    }
+}
+
+impl AutoExposure {
+    /// Create a new `AutoExposure` filter
+    pub fn new(target_middle_gray: f64, percentile_clip: f64) -> AutoExposure {
+        AutoExposure {
+            target_middle_gray,
+            percentile_clip,
+            gain: PrePass::default(),
+        }
+    }
+
+    /// Compute the gain applied to `image`, pixels that aren't finite are ignored
+    pub fn gain<T: Type, C: Color>(&self, image: &Image<T, C>) -> f64 {
+        let mut log = Vec::with_capacity(image.width() * image.height());
+        image.each_pixel(|_, px| {
+            let rgb: Pixel<Rgb> = px.convert();
+            let l = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+            if l.is_finite() {
+                log.push((l.max(0.0) + 1e-6).log2());
+            }
+        });
+        if log.is_empty() {
+            return 1.0;
+        }
+
+        log.sort_unstable_by(f64::total_cmp);
+        let clip = (self.percentile_clip.clamp(0.0, 50.0) / 100.0 * log.len() as f64) as usize;
+        let kept = &log[clip..(log.len() - clip).max(clip + 1)];
+        let key = (kept.iter().sum::<f64>() / kept.len() as f64).exp2();
+        self.target_middle_gray / key
+    }
+}
+
+/// Scale exposure so the log-average luminance maps to `target_middle_gray`, ignoring
+/// `percentile_clip` percent of the luminance values at each end
+pub fn auto_exposure<T: Type, C: Color, U: Type, D: Color>(
+    target_middle_gray: f64,
+    percentile_clip: f64,
 ) -> impl Filter<T, C, U, D> {
-    let dwidth = to.width as f64;
-    let height = from.height as f64;
-    rotate(
-        90.,
-        Point::new((dwidth / 2.) as usize, (height / 2.) as usize),
-    )
+    AutoExposure::new(target_middle_gray, percentile_clip)
 }
 
-/// 180 degree rotation
-pub fn rotate180<T: Type, C: Color, U: Type, D: Color>(src: Size) -> impl Filter<T, C, U, D> {
-    let dwidth = src.width as f64;
-    let height = src.height as f64;
-    rotate(
-        180.,
-        Point::new((dwidth / 2.) as usize, (height / 2.) as usize),
-    )
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for AutoExposure {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let gain = *self.gain.get(input.images()[0], |image| self.gain(image));
+        let mut px = input.get_pixel(pt, None);
+        for c in (0..C::CHANNELS).filter(|c| C::ALPHA != Some(*c)) {
+            px[c] *= gain;
+        }
+        px.convert_to_data(dest);
+    }
 }
 
-/// 270 degree rotation
-pub fn rotate270<T: Type, C: Color, U: Type, D: Color>(
-    from: Size,
-    to: Size,
+/// Normalized pixel buffer with a hole mask, used by `PatchMatchFill`
+#[derive(Clone)]
+struct HoleBuffer {
+    width: usize,
+    height: usize,
+    channels: usize,
+    data: Vec<f64>,
+    hole: Vec<bool>,
+}
+
+impl HoleBuffer {
+    fn pixel(&self, i: usize) -> &[f64] {
+        &self.data[i * self.channels..][..self.channels]
+    }
+
+    /// Half resolution copy, a pixel is part of the hole if any of the pixels it covers are
+    fn downsample(&self) -> HoleBuffer {
+        let (width, height) = (self.width.div_ceil(2), self.height.div_ceil(2));
+        let mut dest = HoleBuffer {
+            width,
+            height,
+            channels: self.channels,
+            data: vec![0.0; width * height * self.channels],
+            hole: vec![false; width * height],
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                let mut n = 0;
+                for (sx, sy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let (sx, sy) = (2 * x + sx, 2 * y + sy);
+                    if sx >= self.width || sy >= self.height {
+                        continue;
+                    }
+                    let j = sy * self.width + sx;
+                    if self.hole[j] {
+                        dest.hole[i] = true;
+                    } else {
+                        for c in 0..self.channels {
+                            dest.data[i * self.channels + c] += self.data[j * self.channels + c];
+                        }
+                        n += 1;
+                    }
+                }
+                for c in 0..self.channels {
+                    dest.data[i * self.channels + c] /= n.max(1) as f64;
+                }
+            }
+        }
+        dest
+    }
+
+    /// Fill the hole from the outside in, each pixel is set to the mean of its already known
+    /// neighbors
+    fn fill_diffuse(&mut self) {
+        let mut known: Vec<bool> = self.hole.iter().map(|h| !h).collect();
+        let (w, h) = (self.width as isize, self.height as isize);
+        loop {
+            let mut next = known.clone();
+            let mut changed = false;
+            for i in (0..known.len()).filter(|i| !known[*i]) {
+                let (x, y) = ((i % self.width) as isize, (i / self.width) as isize);
+                let mut sum = vec![0.0; self.channels];
+                let mut n = 0;
+                for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if nx < 0 || ny < 0 || nx >= w || ny >= h {
+                        continue;
+                    }
+                    let j = (ny * w + nx) as usize;
+                    if known[j] {
+                        sum.iter_mut().zip(self.pixel(j)).for_each(|(s, v)| *s += v);
+                        n += 1;
+                    }
+                }
+                if n > 0 {
+                    for (c, v) in sum.iter().enumerate() {
+                        self.data[i * self.channels + c] = v / n as f64;
+                    }
+                    next[i] = true;
+                    changed = true;
+                }
+            }
+            known = next;
+            if !changed {
+                break;
+            }
+        }
+    }
+}
+
+/// Content-aware fill using PatchMatch, from Barnes et al., "PatchMatch: A Randomized
+/// Correspondence Algorithm for Structural Image Editing". Pixels where `mask` is greater than
+/// `0.5` are synthesized from patches found elsewhere in the image, coarse to fine, which makes
+/// it suitable for regions too large for diffusion-based inpainting
+///
+/// The fill is computed once for the whole input image and cached
+pub struct PatchMatchFill {
+    /// Region to fill
+    pub mask: Image<f32, Gray>,
+
+    /// Patch width and height in pixels
+    pub patch_size: usize,
+
+    /// Number of search and vote iterations at each scale
+    pub iterations: usize,
+
+    /// Random seed
+    pub seed: u64,
+
+    filled: PrePass<Vec<f64>>,
+}
+
+impl std::fmt::Debug for PatchMatchFill {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PatchMatchFill")
+            .field("mask", &self.mask.size())
+            .field("patch_size", &self.patch_size)
+            .field("iterations", &self.iterations)
+            .field("seed", &self.seed)
+            .finish()
+    }
+}
+
+/// Fill the masked region of an image using patches from the rest of the image, see
+/// `PatchMatchFill`
+pub fn patch_match_fill<T: Type, C: Color, U: Type, D: Color>(
+    mask: Image<f32, Gray>,
 ) -> impl Filter<T, C, U, D> {
-    let width = to.height as f64;
-    let dheight = from.width as f64;
-    rotate(
-        270.,
-        Point::new((width / 2.) as usize, (dheight / 2.) as usize),
-    )
+    PatchMatchFill::new(mask)
+}
+
+impl PatchMatchFill {
+    /// Create a new `PatchMatchFill` using 7x7 patches
+    pub fn new(mask: Image<f32, Gray>) -> PatchMatchFill {
+        PatchMatchFill {
+            mask,
+            patch_size: 7,
+            iterations: 5,
+            seed: 0,
+            filled: PrePass::default(),
+        }
+    }
+
+    /// Set patch size
+    pub fn with_patch_size(mut self, patch_size: usize) -> PatchMatchFill {
+        self.patch_size = patch_size;
+        self
+    }
+
+    /// Set number of iterations per scale
+    pub fn with_iterations(mut self, iterations: usize) -> PatchMatchFill {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Fill the masked region of `image`
+    pub fn fill<T: Type, C: Color>(&self, image: &Image<T, C>) -> Image<T, C> {
+        let data = self.compute(image);
+        let mut dest = image.clone();
+        dest.data_mut()
+            .iter_mut()
+            .zip(data)
+            .for_each(|(d, x)| d.set_from_norm(x));
+        dest
+    }
+
+    fn compute<T: Type, C: Color>(&self, image: &Image<T, C>) -> Vec<f64> {
+        let (width, height) = (image.width(), image.height());
+        let base = HoleBuffer {
+            width,
+            height,
+            channels: C::CHANNELS,
+            data: image.data().iter().map(Type::to_norm).collect(),
+            hole: (0..width * height)
+                .map(|i| self.mask.get_f((i % width, i / width), 0) > 0.5)
+                .collect(),
+        };
+        if !base.hole.contains(&true) {
+            return base.data;
+        }
+
+        // Coarser levels need to be large enough to hold a few patches
+        let r = self.patch_size.max(3) / 2;
+        let mut pyramid = vec![base];
+        while pyramid.len() < 6 {
+            let last = pyramid.last().unwrap();
+            if last.width.min(last.height) / 2 < 4 * (2 * r + 1) {
+                break;
+            }
+            pyramid.push(last.downsample());
+        }
+
+        let mut rng = self.seed ^ 0x853c49e6748fea9b;
+        let mut previous: Option<HoleBuffer> = None;
+        while let Some(mut level) = pyramid.pop() {
+            match &previous {
+                Some(coarse) => {
+                    for i in (0..level.hole.len()).filter(|i| level.hole[*i]) {
+                        let (x, y) = (i % level.width, i / level.width);
+                        let j = (y / 2).min(coarse.height - 1) * coarse.width
+                            + (x / 2).min(coarse.width - 1);
+                        for c in 0..level.channels {
+                            level.data[i * level.channels + c] =
+                                coarse.data[j * coarse.channels + c];
+                        }
+                    }
+                }
+                None => level.fill_diffuse(),
+            }
+            self.synthesize(&mut level, r, &mut rng);
+            previous = Some(level);
+        }
+        previous.map(|level| level.data).unwrap_or_default()
+    }
+
+    /// Expectation-maximization: find the nearest source patch for each patch overlapping the
+    /// hole using PatchMatch, then set each hole pixel to the average of the overlapping patches
+    fn synthesize(&self, buf: &mut HoleBuffer, r: usize, rng: &mut u64) {
+        let (w, h, channels) = (buf.width, buf.height, buf.channels);
+        let r = r as isize;
+        let mut random = |n: usize| {
+            *rng = rng
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (*rng >> 33) as usize % n.max(1)
+        };
+
+        // Source patches must lie entirely inside of the image and outside of the hole
+        let mut valid = vec![false; w * h];
+        let mut sources = Vec::new();
+        for y in r..h as isize - r {
+            for x in r..w as isize - r {
+                let known = (-r..=r).all(|dy| {
+                    (-r..=r).all(|dx| !buf.hole[((y + dy) * w as isize + x + dx) as usize])
+                });
+                if known {
+                    valid[(y * w as isize + x) as usize] = true;
+                    sources.push((x, y));
+                }
+            }
+        }
+        if sources.is_empty() {
+            return;
+        }
+
+        // Target patches are centered on every pixel within `r` of the hole
+        let mut targets = Vec::new();
+        let mut target_index = vec![usize::MAX; w * h];
+        for y in 0..h as isize {
+            for x in 0..w as isize {
+                let near = (-r..=r).any(|dy| {
+                    (-r..=r).any(|dx| {
+                        let (nx, ny) = (x + dx, y + dy);
+                        nx >= 0
+                            && ny >= 0
+                            && nx < w as isize
+                            && ny < h as isize
+                            && buf.hole[(ny * w as isize + nx) as usize]
+                    })
+                });
+                if near {
+                    target_index[(y * w as isize + x) as usize] = targets.len();
+                    targets.push((x, y));
+                }
+            }
+        }
+
+        let distance = |buf: &HoleBuffer, (tx, ty): (isize, isize), (sx, sy): (isize, isize)| {
+            let (mut sum, mut n) = (0.0, 0);
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    let (x, y) = (tx + dx, ty + dy);
+                    if x < 0 || y < 0 || x >= w as isize || y >= h as isize {
+                        continue;
+                    }
+                    let a = buf.pixel((y * w as isize + x) as usize);
+                    let b = buf.pixel(((sy + dy) * w as isize + sx + dx) as usize);
+                    sum += a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum::<f64>();
+                    n += 1;
+                }
+            }
+            sum / n.max(1) as f64
+        };
+
+        let mut nnf: Vec<(isize, isize)> = targets
+            .iter()
+            .map(|_| sources[random(sources.len())])
+            .collect();
+        let is_valid = |(x, y): (isize, isize)| {
+            x >= 0
+                && y >= 0
+                && x < w as isize
+                && y < h as isize
+                && valid[(y * w as isize + x) as usize]
+        };
+
+        for _ in 0..self.iterations.max(1) {
+            let mut cost: Vec<f64> = targets
+                .iter()
+                .zip(&nnf)
+                .map(|(t, s)| distance(buf, *t, *s))
+                .collect();
+
+            for pass in 0..4 {
+                let forward = pass % 2 == 0;
+                let step: isize = if forward { 1 } else { -1 };
+                for k in 0..targets.len() {
+                    let k = if forward { k } else { targets.len() - 1 - k };
+                    let (tx, ty) = targets[k];
+                    let try_source = |s: (isize, isize), best: &mut ((isize, isize), f64)| {
+                        if is_valid(s) && s != best.0 {
+                            let d = distance(buf, (tx, ty), s);
+                            if d < best.1 {
+                                *best = (s, d);
+                            }
+                        }
+                    };
+                    let mut best = (nnf[k], cost[k]);
+
+                    // Propagation from the previous neighbors in scan order
+                    for (nx, ny) in [(tx - step, ty), (tx, ty - step)] {
+                        if nx < 0 || ny < 0 || nx >= w as isize || ny >= h as isize {
+                            continue;
+                        }
+                        let j = target_index[(ny * w as isize + nx) as usize];
+                        if j != usize::MAX {
+                            let (sx, sy) = nnf[j];
+                            try_source(
+                                (sx + step * (tx - nx).abs(), sy + step * (ty - ny).abs()),
+                                &mut best,
+                            );
+                        }
+                    }
+
+                    // Random search in an exponentially shrinking window
+                    let mut radius = w.max(h) as isize;
+                    while radius >= 1 {
+                        let (sx, sy) = best.0;
+                        let rx = random(2 * radius as usize + 1) as isize - radius;
+                        let ry = random(2 * radius as usize + 1) as isize - radius;
+                        try_source((sx + rx, sy + ry), &mut best);
+                        radius /= 2;
+                    }
+
+                    nnf[k] = best.0;
+                    cost[k] = best.1;
+                }
+            }
+
+            // Vote: every patch covering a hole pixel contributes its source value
+            let mut sum = vec![0.0; w * h * channels];
+            let mut count = vec![0usize; w * h];
+            for ((tx, ty), (sx, sy)) in targets.iter().zip(&nnf) {
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        let (x, y) = (tx + dx, ty + dy);
+                        if x < 0 || y < 0 || x >= w as isize || y >= h as isize {
+                            continue;
+                        }
+                        let i = (y * w as isize + x) as usize;
+                        if !buf.hole[i] {
+                            continue;
+                        }
+                        let j = ((sy + dy) * w as isize + sx + dx) as usize;
+                        for c in 0..channels {
+                            sum[i * channels + c] += buf.data[j * channels + c];
+                        }
+                        count[i] += 1;
+                    }
+                }
+            }
+            for i in (0..w * h).filter(|i| buf.hole[*i] && count[*i] > 0) {
+                for c in 0..channels {
+                    buf.data[i * channels + c] = sum[i * channels + c] / count[i] as f64;
+                }
+            }
+        }
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for PatchMatchFill {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let image = input.images()[0];
+        let filled = self.filled.get(image, |image| self.compute(image));
+        let mut px = Pixel::<C>::new();
+        let i = (pt.y * image.width() + pt.x) * C::CHANNELS;
+        if let Some(data) = filled.get(i..i + C::CHANNELS) {
+            for (c, x) in data.iter().enumerate() {
+                px[c] = *x;
+            }
+        }
+        px.convert_to_data(dest);
+    }
+}
+
+/// Wavelet-domain denoising, each channel is decomposed using a discrete wavelet transform and
+/// the detail coefficients are shrunk towards zero using soft thresholding. This removes noise
+/// while keeping edges sharper than spatial smoothing, the alpha channel is left unchanged
+///
+/// The result is computed once for the whole input image and cached
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WaveletDenoise {
+    /// Threshold in normalized units, when `None` the noise level is estimated and a separate
+    /// threshold is chosen for each subband using BayesShrink
+    pub threshold: Option<f64>,
+
+    /// Number of decomposition levels
+    pub levels: usize,
+
+    /// Wavelet used for the decomposition
+    pub wavelet: wavelet::Wavelet,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    denoised: PrePass<Vec<f64>>,
+}
+
+/// Denoise using soft thresholding of wavelet coefficients, see `WaveletDenoise`
+pub fn wavelet_denoise<T: Type, C: Color, U: Type, D: Color>(
+    threshold: Option<f64>,
+    levels: usize,
+) -> impl Filter<T, C, U, D> {
+    WaveletDenoise::new(threshold, levels)
+}
+
+impl WaveletDenoise {
+    /// Create a new `WaveletDenoise` filter using the `Daubechies4` wavelet
+    pub fn new(threshold: Option<f64>, levels: usize) -> WaveletDenoise {
+        WaveletDenoise {
+            threshold,
+            levels,
+            wavelet: wavelet::Wavelet::Daubechies4,
+            denoised: PrePass::default(),
+        }
+    }
+
+    /// Set wavelet
+    pub fn with_wavelet(mut self, wavelet: wavelet::Wavelet) -> WaveletDenoise {
+        self.wavelet = wavelet;
+        self
+    }
+
+    /// Denoise `image`
+    pub fn denoise<T: Type, C: Color>(&self, image: &Image<T, C>) -> Image<T, C> {
+        let data = self.compute(image);
+        let mut dest = image.clone();
+        dest.data_mut()
+            .iter_mut()
+            .zip(data)
+            .for_each(|(d, x)| d.set_from_norm(x));
+        dest
+    }
+
+    fn compute<T: Type, C: Color>(&self, image: &Image<T, C>) -> Vec<f64> {
+        let mut data: Vec<f64> = image.data().iter().map(Type::to_norm).collect();
+        let channels = wavelet::forward(image, self.wavelet, self.levels);
+        for (c, mut decomposition) in channels.into_iter().enumerate() {
+            if C::ALPHA == Some(c) {
+                continue;
+            }
+
+            match self.threshold {
+                Some(threshold) => decomposition.soft_threshold(threshold),
+                None => decomposition.bayes_shrink(decomposition.estimate_noise()),
+            }
+            data.iter_mut()
+                .skip(c)
+                .step_by(C::CHANNELS)
+                .zip(wavelet::idwt2(&decomposition))
+                .for_each(|(d, x)| *d = x);
+        }
+        data
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for WaveletDenoise {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let image = input.images()[0];
+        let denoised = self.denoised.get(image, |image| self.compute(image));
+        let mut px = Pixel::<C>::new();
+        let i = (pt.y * image.width() + pt.x) * C::CHANNELS;
+        if let Some(data) = denoised.get(i..i + C::CHANNELS) {
+            for (c, x) in data.iter().enumerate() {
+                px[c] = *x;
+            }
+        }
+        px.convert_to_data(dest);
+    }
+}
+
+/// Replaces NaN and infinite values, and optionally clamps values to a fixed range. This is
+/// mostly useful for float images produced by renderers, which may contain invalid values that
+/// cause problems when encoding. The number of pixels that were modified is tracked and can be
+/// accessed using `Sanitize::fixed`
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sanitize {
+    /// Replacement for NaN values
+    pub replace_nan: f64,
+
+    /// Replacement for positive infinity, negative infinity is replaced with `-replace_inf`
+    pub replace_inf: f64,
+
+    /// Optional (min, max) range, values outside of the range are clamped. The bounds are
+    /// swapped if `min > max` and a NaN bound is ignored
+    pub clamp_range: Option<(f64, f64)>,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    fixed: std::sync::atomic::AtomicUsize,
+}
+
+impl Default for Sanitize {
+    fn default() -> Self {
+        Sanitize {
+            replace_nan: 0.0,
+            replace_inf: 1.0,
+            clamp_range: None,
+            fixed: std::sync::atomic::AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Sanitize {
+    /// Create a new `Sanitize` filter, NaN values are replaced with 0 and infinite values are
+    /// replaced with 1
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set NaN replacement value
+    pub fn with_nan(mut self, value: f64) -> Self {
+        self.replace_nan = value;
+        self
+    }
+
+    /// Set infinity replacement value
+    pub fn with_inf(mut self, value: f64) -> Self {
+        self.replace_inf = value;
+        self
+    }
+
+    /// Clamp values between `min` and `max`, the bounds are swapped if `min > max` and a NaN
+    /// bound is ignored
+    pub fn with_clamp(mut self, min: f64, max: f64) -> Self {
+        self.clamp_range = Some(Self::clamp_bounds(min, max));
+        self
+    }
+
+    fn clamp_bounds(min: f64, max: f64) -> (f64, f64) {
+        let lo = if min.is_nan() { f64::NEG_INFINITY } else { min };
+        let hi = if max.is_nan() { f64::INFINITY } else { max };
+        (lo.min(hi), lo.max(hi))
+    }
+
+    /// Number of pixels modified since the filter was created or last reset
+    pub fn fixed(&self) -> usize {
+        self.fixed.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Reset the count of modified pixels
+    pub fn reset(&self) {
+        self.fixed.store(0, std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+impl<C: Color> PointFilter<C> for Sanitize {
+    fn apply(&self, px: &mut Pixel<C>) {
+        let clamp_range = self
+            .clamp_range
+            .map(|(min, max)| Self::clamp_bounds(min, max));
+        let mut changed = false;
+        for x in px.as_mut() {
+            let y = if x.is_nan() {
+                self.replace_nan
+            } else if x.is_infinite() {
+                self.replace_inf.copysign(*x)
+            } else {
+                *x
+            };
+
+            let y = match clamp_range {
+                Some((min, max)) => y.clamp(min, max),
+                None => y,
+            };
+
+            if y.to_bits() != x.to_bits() {
+                changed = true;
+                *x = y;
+            }
+        }
+
+        if changed {
+            self.fixed
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Sanitize {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(&mut px);
+        px.copy_to_slice(dest);
+    }
 }
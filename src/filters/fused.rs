@@ -0,0 +1,49 @@
+use crate::*;
+
+/// Point-wise filters operate on a single pixel of normalized values, several point-wise filters
+/// can be combined into a single pass using `fuse!`
+pub trait PointFilter<C: Color>: std::fmt::Debug + Sync {
+    /// Apply the filter to `px` in-place
+    fn apply(&self, px: &mut Pixel<C>);
+}
+
+/// Two point-wise filters evaluated in a single pass, the input pixel is read and converted once
+/// and the output is only written after both filters are applied. Use `fuse!` to combine more
+/// than two filters
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fused<A, B>(pub A, pub B);
+
+impl<C: Color, A: PointFilter<C>, B: PointFilter<C>> PointFilter<C> for Fused<A, B> {
+    fn apply(&self, px: &mut Pixel<C>) {
+        self.0.apply(px);
+        self.1.apply(px);
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color, A: PointFilter<C>, B: PointFilter<C>> Filter<T, C, U, D>
+    for Fused<A, B>
+{
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(&mut px);
+        px.convert_to_data(dest);
+    }
+}
+
+/// Combine point-wise filters into a single `Fused` filter
+///
+/// ```rust
+/// use image2::{filter::*, fuse};
+///
+/// let f = fuse!(Brightness(1.2), Contrast(1.1), GammaLog(2.2));
+/// ```
+#[macro_export]
+macro_rules! fuse {
+    ($a:expr $(,)?) => {
+        $a
+    };
+    ($a:expr, $($rest:expr),+ $(,)?) => {
+        $crate::Fused($a, $crate::fuse!($($rest),+))
+    };
+}
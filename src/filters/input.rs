@@ -75,6 +75,20 @@ impl<'a, T: 'a + Type, C: 'a + Color> Input<'a, T, C> {
         }
     }
 
+    /// Same as `get_f`, but returns `None` when `pt` is outside of the bounds of the selected image
+    pub fn try_get_f(
+        &self,
+        pt: impl Into<Point>,
+        c: Channel,
+        image_index: Option<usize>,
+    ) -> Option<f64> {
+        let pt = pt.into();
+        if !self.images[image_index.unwrap_or_default()].in_bounds(pt) {
+            return None;
+        }
+        Some(self.get_f(pt, c, image_index))
+    }
+
     /// Create a new pixel
     pub fn new_pixel(&self) -> Pixel<C> {
         Pixel::new()
@@ -5,17 +5,31 @@ use rayon::prelude::*;
 
 mod r#async;
 mod ext;
+mod fused;
 mod input;
+mod options;
 mod pipeline;
 
 /// Image processing filters
 pub mod filter;
 
 pub use ext::*;
+pub use fused::{Fused, PointFilter};
 pub use input::Input;
+pub use options::{ColorspaceMismatch, ExecutionOptions};
 pub use pipeline::*;
 pub use r#async::*;
 
+/// Filters that determine the size of their own output, such as crop, resize and pad
+///
+/// Use `Image::run_geometry` to allocate a destination image with the correct size, since
+/// `Filter` also has an `output_size` method call this one as
+/// `GeometryFilter::output_size(&filter, size)`
+pub trait GeometryFilter<T: Type, C: Color, U: Type = T, D: Color = C>: Filter<T, C, U, D> {
+    /// Get the output size for an input image of `input_size`
+    fn output_size(&self, input_size: Size) -> Size;
+}
+
 /// Filters are used to manipulate images in a generic, composable manner
 pub trait Filter<T: Type, C: Color, U: Type = T, D: Color = C>: std::fmt::Debug + Sync {
     /// Determines whether a filter should be executed one pixel at a time, or a whole image at a time
@@ -36,6 +50,19 @@ pub trait Filter<T: Type, C: Color, U: Type = T, D: Color = C>: std::fmt::Debug
     /// - `dest`: Single pixel output buffer
     fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>);
 
+    /// Hash identifying the filter and every parameter that affects its output, used by
+    /// `Pipeline::execute_cached`. The default is `None`, which disables caching for pipelines
+    /// containing the filter, see `ContentHasher::fingerprint`
+    fn fingerprint(&self) -> Option<u64> {
+        None
+    }
+
+    /// Returns the underlying pipeline when the filter is a `Pipeline`, this is used to flatten
+    /// nested pipelines when planning execution
+    fn as_pipeline(&self) -> Option<&Pipeline<T, C, U, D>> {
+        None
+    }
+
     /// Evaluate a filter on part of an image
     fn eval_partial(&self, roi: Region, input: &[&Image<T, C>], output: &mut Image<U, D>) {
         let input = Input::new(input);
@@ -55,6 +82,43 @@ pub trait Filter<T: Type, C: Color, U: Type = T, D: Color = C>: std::fmt::Debug
         });
     }
 
+    /// Evaluate filter only where `mask` is selected, partially selected pixels are blended with
+    /// the existing contents of `output`
+    fn eval_masked(&self, mask: &Mask, input: &[&Image<T, C>], output: &mut Image<U, D>) {
+        let input = Input::new(input);
+
+        output.for_each(|pt, mut data| {
+            let coverage = mask.get(pt);
+            if coverage <= 0.0 {
+                return;
+            }
+
+            let mut buf = data.as_slice().to_vec();
+            self.compute_at(pt, &input, &mut DataMut::new(&mut buf));
+            if coverage >= 1.0 {
+                data.copy_from_slice(&buf);
+                return;
+            }
+
+            let mut px = data.to_pixel();
+            let filtered = Pixel::<D>::from_slice(&buf);
+            for c in 0..D::CHANNELS {
+                px[c] = px[c] * (1.0 - coverage) + filtered[c] * coverage;
+            }
+            px.convert_to_data(&mut data);
+        });
+    }
+
+    /// Evaluate filter using the provided `ExecutionOptions`
+    fn eval_with(
+        &self,
+        options: &ExecutionOptions,
+        input: &[&Image<T, C>],
+        output: &mut Image<U, D>,
+    ) {
+        options.install(|| self.eval(input, output))
+    }
+
     /// Evaluate filter using the same image for input and output, this will
     /// make a copy internally
     fn eval_in_place(&self, image: &mut Image<U, D>) {
@@ -65,4 +129,9 @@ pub trait Filter<T: Type, C: Color, U: Type = T, D: Color = C>: std::fmt::Debug
             self.compute_at(pt, &input, &mut data);
         });
     }
+
+    /// Evaluate filter in place using the provided `ExecutionOptions`
+    fn eval_in_place_with(&self, options: &ExecutionOptions, image: &mut Image<U, D>) {
+        options.install(|| self.eval_in_place(image))
+    }
 }
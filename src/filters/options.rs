@@ -0,0 +1,179 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::Error;
+
+/// Number of items handled by each task when `deterministic` is enabled
+const DETERMINISTIC_CHUNK_SIZE: usize = 1024;
+
+/// Behavior of `Image::apply` when the inputs of a filter are tagged with different colorspaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorspaceMismatch {
+    /// Convert every input to the colorspace of the first input
+    #[default]
+    Convert,
+
+    /// Fail with `Error::ColorspaceMismatch`, see `Image::try_apply_with`
+    Error,
+
+    /// Use the inputs as-is
+    Ignore,
+}
+
+/// Controls how filters are executed: thread count, thread pool and reduction ordering
+///
+/// Options only apply to the `*_with` entry points: `Image::apply_with`, `Image::run_with`,
+/// `Image::run_in_place_with`, `Filter::eval_with`, `Pipeline::execute_with` and
+/// `Reduce::eval_with`. `Image::apply`, `Image::run`, `Filter::eval` and the other entry points
+/// without options run on the current rayon thread pool with the default settings
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionOptions {
+    threads: Option<usize>,
+
+    /// When true, reductions are split into fixed-size chunks that are combined in order, so
+    /// floating point results are bit-identical regardless of the number of threads
+    pub deterministic: bool,
+
+    /// Handling of inputs tagged with different colorspaces
+    pub colorspace_mismatch: ColorspaceMismatch,
+
+    #[cfg(feature = "parallel")]
+    pool: Option<std::sync::Arc<rayon::ThreadPool>>,
+}
+
+impl ExecutionOptions {
+    /// Create new `ExecutionOptions` using the default thread pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of threads configured using `with_threads`, `None` uses the current rayon thread
+    /// pool or the pool passed to `with_pool`
+    pub fn threads(&self) -> Option<usize> {
+        self.threads
+    }
+
+    /// Build options with a fixed number of threads. The thread pool is created once here and
+    /// shared by clones of the returned options
+    #[cfg(feature = "parallel")]
+    pub fn with_threads(mut self, threads: usize) -> Result<Self, Error> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?;
+        self.threads = Some(threads);
+        self.pool = Some(std::sync::Arc::new(pool));
+        Ok(self)
+    }
+
+    /// Build options with a fixed number of threads
+    #[cfg(not(feature = "parallel"))]
+    pub fn with_threads(mut self, threads: usize) -> Result<Self, Error> {
+        self.threads = Some(threads);
+        Ok(self)
+    }
+
+    /// Build options with deterministic reductions enabled or disabled
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Build options with the given handling of mismatched input colorspaces
+    pub fn with_colorspace_mismatch(mut self, colorspace_mismatch: ColorspaceMismatch) -> Self {
+        self.colorspace_mismatch = colorspace_mismatch;
+        self
+    }
+
+    /// Build options using a caller-supplied thread pool, this replaces any pool created by
+    /// `with_threads`
+    #[cfg(feature = "parallel")]
+    pub fn with_pool(mut self, pool: std::sync::Arc<rayon::ThreadPool>) -> Self {
+        self.threads = Some(pool.current_num_threads());
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Run `f` using the configured thread pool
+    #[cfg(feature = "parallel")]
+    pub fn install<R: Send>(&self, f: impl Send + FnOnce() -> R) -> R {
+        match &self.pool {
+            Some(pool) => pool.install(f),
+            None => f(),
+        }
+    }
+
+    /// Run `f` using the configured thread pool
+    #[cfg(not(feature = "parallel"))]
+    pub fn install<R: Send>(&self, f: impl Send + FnOnce() -> R) -> R {
+        f()
+    }
+
+    /// Apply `map` to each index in `0..n` and combine the results using `reduce`, starting
+    /// from `identity`
+    #[cfg(feature = "parallel")]
+    pub fn map_reduce<R, M, F>(&self, n: usize, identity: R, map: M, reduce: F) -> R
+    where
+        R: Clone + Send + Sync,
+        M: Sync + Send + Fn(usize) -> R,
+        F: Sync + Send + Fn(R, R) -> R,
+    {
+        self.install(|| {
+            if !self.deterministic {
+                return (0..n)
+                    .into_par_iter()
+                    .map(&map)
+                    .reduce(|| identity.clone(), &reduce);
+            }
+
+            let chunks: Vec<R> = (0..n)
+                .into_par_iter()
+                .step_by(DETERMINISTIC_CHUNK_SIZE)
+                .map(|start| {
+                    let end = (start + DETERMINISTIC_CHUNK_SIZE).min(n);
+                    (start..end).fold(identity.clone(), |acc, i| reduce(acc, map(i)))
+                })
+                .collect();
+            chunks.into_iter().fold(identity.clone(), &reduce)
+        })
+    }
+
+    /// Apply `map` to each index in `0..n` and combine the results using `reduce`, starting
+    /// from `identity`
+    #[cfg(not(feature = "parallel"))]
+    pub fn map_reduce<R, M, F>(&self, n: usize, identity: R, map: M, reduce: F) -> R
+    where
+        R: Clone + Send + Sync,
+        M: Sync + Send + Fn(usize) -> R,
+        F: Sync + Send + Fn(R, R) -> R,
+    {
+        (0..n)
+            .step_by(DETERMINISTIC_CHUNK_SIZE)
+            .map(|start| {
+                let end = (start + DETERMINISTIC_CHUNK_SIZE).min(n);
+                (start..end).fold(identity.clone(), |acc, i| reduce(acc, map(i)))
+            })
+            .fold(identity.clone(), &reduce)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExecutionOptions;
+
+    #[test]
+    fn test_deterministic_map_reduce() {
+        let f = |i: usize| (i as f64 * 0.1).sin() * 1e-3;
+        let opts = ExecutionOptions::new().with_deterministic(true);
+        let a = opts
+            .clone()
+            .with_threads(1)
+            .unwrap()
+            .map_reduce(100_000, 0.0, f, |a, b| a + b);
+        let b = opts
+            .with_threads(4)
+            .unwrap()
+            .map_reduce(100_000, 0.0, f, |a, b| a + b);
+        assert_eq!(a.to_bits(), b.to_bits());
+    }
+}
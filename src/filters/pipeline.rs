@@ -1,5 +1,8 @@
 use crate::*;
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
 /// Used to determine if a filter can be executed and interleaved at the pixel level or if the
 /// whole filter needs to be evaulated before moving to the next filter
 #[derive(Debug, PartialEq, Clone, Copy, Eq)]
@@ -12,8 +15,78 @@ pub enum Schedule {
     Image,
 }
 
+/// A single pass over the output image, produced by `Pipeline::plan`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stage {
+    /// Range of filters, indexing into the flattened filter list, that are fused and evaluated
+    /// one pixel at a time
+    pub filters: std::ops::Range<usize>,
+
+    /// When true the output of the stage is written to a scratch buffer which is used as the
+    /// input to the next stage
+    pub materialize: bool,
+}
+
+/// Execution plan for a `Pipeline`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    /// Stages, in order of execution
+    pub stages: Vec<Stage>,
+
+    /// Number of scratch buffers that need to be allocated
+    pub scratch_buffers: usize,
+}
+
+/// Records which pipeline runs are up to date, see `Pipeline::execute_cached`. Each entry maps a
+/// key built from the pipeline fingerprint and the content hashes of the inputs to the content
+/// hash of the output that was produced, serialize the cache to keep it between runs
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PipelineCache {
+    entries: std::collections::HashMap<u64, u64>,
+}
+
+impl PipelineCache {
+    /// Create a new, empty cache
+    pub fn new() -> Self {
+        PipelineCache::default()
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true when there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all entries
+    pub fn clear(&mut self) {
+        self.entries.clear()
+    }
+}
+
+/// Buffers used by `Pipeline::compute_chain`, created once per thread and reused for every pixel
+struct ChainScratch<'a, T: Type, C: Color, D: Color> {
+    input: Input<'a, T, C>,
+    px: Pixel<D>,
+    rgb: Pixel<Rgb>,
+}
+
+impl<'a, T: Type, C: Color, D: Color> ChainScratch<'a, T, C, D> {
+    fn new(input: &Input<'a, T, C>) -> Self {
+        ChainScratch {
+            input: input.clone(),
+            px: Pixel::new(),
+            rgb: Pixel::new(),
+        }
+    }
+}
+
 /// Pipelines are used to compose several filters
-#[derive(Default)]
+#[derive(Debug, Default)]
 pub struct Pipeline<T: Type, C: Color, U: Type = T, D: Color = C> {
     pub(crate) filters: Vec<Box<dyn Filter<T, C, U, D>>>,
 }
@@ -37,59 +110,125 @@ impl<T: Type, C: Color, U: Type, D: Color> Pipeline<T, C, U, D> {
         self
     }
 
-    fn image_schedule_list(&self) -> Vec<usize> {
+    /// Returns every filter in the pipeline in execution order, nested pipelines are expanded
+    pub fn flatten(&self) -> Vec<&dyn Filter<T, C, U, D>> {
         let mut dest = Vec::new();
-        for (i, f) in self.filters.iter().enumerate() {
-            if f.schedule() == Schedule::Image {
-                dest.push(i);
+        for f in self.filters.iter() {
+            match f.as_pipeline() {
+                Some(p) => dest.extend(p.flatten()),
+                None => dest.push(f.as_ref()),
             }
         }
-        dest.push(self.filters.len() - 1);
         dest
     }
 
-    #[allow(clippy::too_many_arguments)]
-    pub(crate) fn loop_inner<'a>(
-        &self,
+    /// Build an execution plan. Point-wise filters are fused into a single stage, filters with
+    /// `Schedule::Image` read neighboring pixels so everything before them is materialized
+    /// first, and their own output is materialized for the filters that follow
+    pub fn plan(&self) -> Plan {
+        Self::plan_filters(&self.flatten())
+    }
+
+    fn plan_filters(filters: &[&dyn Filter<T, C, U, D>]) -> Plan {
+        let mut stages = Vec::new();
+        let mut start = 0;
+        for i in 0..filters.len() {
+            let last = i + 1 == filters.len();
+            let boundary = filters[i].schedule() == Schedule::Image
+                || (!last && filters[i + 1].schedule() == Schedule::Image);
+            if last || boundary {
+                stages.push(Stage {
+                    filters: start..i + 1,
+                    materialize: !last,
+                });
+                start = i + 1;
+            }
+        }
+
+        // The output image and a single scratch buffer are used as ping-pong buffers
+        let scratch_buffers = usize::from(stages.len() > 1);
+        Plan {
+            stages,
+            scratch_buffers,
+        }
+    }
+
+    /// Evaluate a chain of filters at a single point, each filter after the first receives
+    /// the output of the previous filter as its input pixel. `scratch` holds the chained `Input`
+    /// and conversion buffers so they can be reused between pixels
+    fn compute_chain<'f, 'a>(
+        filters: impl IntoIterator<Item = &'f dyn Filter<T, C, U, D>>,
+        pt: Point,
+        input: &Input<'a, T, C>,
+        scratch: &mut ChainScratch<'a, T, C, D>,
+        data: &mut DataMut<U, D>,
+    ) where
+        T: 'f,
+        C: 'f,
+        U: 'f,
+        D: 'f,
+    {
+        let mut filters = filters.into_iter();
+        let first = match filters.next() {
+            Some(f) => f,
+            None => return,
+        };
+        first.compute_at(pt, input, data);
+
+        for f in filters {
+            scratch.px.copy_from_data(&data.as_data());
+            D::to_rgb(&scratch.px, &mut scratch.rgb);
+            let (point, px) = scratch
+                .input
+                .pixel
+                .get_or_insert_with(|| (pt, Pixel::new()));
+            *point = pt;
+            C::from_rgb(&scratch.rgb, px);
+            f.compute_at(pt, &scratch.input, data);
+        }
+    }
+
+    pub(crate) fn run_stage<'a>(
+        filters: &[&dyn Filter<T, C, U, D>],
+        stage: &Stage,
         input: &mut Input<'a, T, C>,
         output: &mut Image<U, D>,
         tmpconvp: &std::cell::UnsafeCell<Image<T, C>>,
-        j: usize,
-        index: usize,
-        image_schedule_filters: &[usize],
     ) {
+        let filters = &filters[stage.filters.clone()];
         let tmpconv = unsafe { &mut *tmpconvp.get() };
-        let current_filter = &self.filters[index];
-        if current_filter.schedule() == Schedule::Image {
-            let output_size = current_filter.output_size(input, output);
-            if output_size != tmpconv.size() {
-                *tmpconv = Image::new(output_size);
+        if let Some(last) = filters.last() {
+            if stage.materialize && last.schedule() == Schedule::Image {
+                let output_size = last.output_size(input, output);
+                if output_size != tmpconv.size() {
+                    *tmpconv = Image::new(output_size);
+                }
             }
         }
-        output.iter_mut().for_each(|(pt, mut data)| {
-            let n = if j == 0 {
-                0
-            } else {
-                image_schedule_filters[j - 1] + 1
-            }..=index;
-            for f in self.filters[n].iter() {
-                match f.schedule() {
-                    Schedule::Pixel if j > 0 => {
-                        let mut px = Pixel::new();
-                        let input = input
-                            .clone()
-                            .with_pixel(pt, px.copy_from_data(&data.as_data()).convert());
-
-                        f.compute_at(pt, &input, &mut data);
-                    }
-                    Schedule::Pixel | Schedule::Image => {
-                        f.compute_at(pt, input, &mut data);
-                    }
-                }
+
+        let stage_input = &*input;
+        let compute_row = |scratch: &mut ChainScratch<'a, T, C, D>, (y, row): (usize, &mut [U])| {
+            for (x, px) in row.chunks_mut(D::CHANNELS).enumerate() {
+                let mut data = DataMut::new(px);
+                let pt = Point::new(x, y);
+                Self::compute_chain(filters.iter().copied(), pt, stage_input, scratch, &mut data);
             }
-        });
+        };
+
+        #[cfg(feature = "parallel")]
+        output
+            .par_rows_mut()
+            .for_each_init(|| ChainScratch::new(stage_input), compute_row);
 
-        if index != self.filters.len() - 1 {
+        #[cfg(not(feature = "parallel"))]
+        {
+            let mut scratch = ChainScratch::new(stage_input);
+            output
+                .rows_mut()
+                .for_each(|row| compute_row(&mut scratch, row));
+        }
+
+        if stage.materialize {
             output.convert_to(tmpconv);
 
             let tmp = tmpconvp.get();
@@ -97,23 +236,85 @@ impl<T: Type, C: Color, U: Type, D: Color> Pipeline<T, C, U, D> {
         }
     }
 
-    /// Execute the pipeline
+    /// Execute the pipeline, an empty pipeline converts the first input image into `output`
     pub fn execute(&self, input: &[&Image<T, C>], output: &mut Image<U, D>) {
+        let filters = self.flatten();
+        if filters.is_empty() {
+            input[0].convert_to(output);
+            return;
+        }
+
         let mut input = Input::new(input);
-        let image_schedule_filters = self.image_schedule_list();
-
-        let tmpconv = std::cell::UnsafeCell::new(Image::<T, C>::new(output.size()));
-
-        for (j, index) in image_schedule_filters.iter().enumerate() {
-            self.loop_inner(
-                &mut input,
-                output,
-                &tmpconv,
-                j,
-                *index,
-                &image_schedule_filters,
-            );
+        let plan = Self::plan_filters(&filters);
+
+        let tmpconv = std::cell::UnsafeCell::new(Image::<T, C>::new(if plan.scratch_buffers > 0 {
+            output.size()
+        } else {
+            Size::new(0, 0)
+        }));
+
+        for stage in plan.stages.iter() {
+            Self::run_stage(&filters, stage, &mut input, output, &tmpconv);
+        }
+    }
+
+    /// Execute the pipeline using the provided `ExecutionOptions`
+    pub fn execute_with(
+        &self,
+        options: &ExecutionOptions,
+        input: &[&Image<T, C>],
+        output: &mut Image<U, D>,
+    ) {
+        options.install(|| self.execute(input, output))
+    }
+
+    /// Hash identifying the filters in the pipeline and their parameters, `None` when any filter
+    /// doesn't implement `Filter::fingerprint`
+    pub fn fingerprint(&self) -> Option<u64> {
+        use std::hash::Hasher;
+
+        let mut hasher = ContentHasher::new();
+        for f in self.flatten() {
+            hasher.write_u64(f.fingerprint()?);
+        }
+        Some(hasher.finish())
+    }
+
+    /// Execute the pipeline unless `cache` shows that `output` already holds the result of
+    /// running this pipeline on the same inputs. Returns true when the pipeline was executed,
+    /// pipelines without a `fingerprint` are always executed and never cached
+    pub fn execute_cached(
+        &self,
+        cache: &mut PipelineCache,
+        input: &[&Image<T, C>],
+        output: &mut Image<U, D>,
+    ) -> bool {
+        use std::hash::Hasher;
+
+        let fingerprint = match self.fingerprint() {
+            Some(fingerprint) => fingerprint,
+            None => {
+                self.execute(input, output);
+                return true;
+            }
+        };
+
+        let mut hasher = ContentHasher::new();
+        hasher.write_u64(fingerprint);
+        input
+            .iter()
+            .for_each(|image| hasher.write_u64(image.content_hash()));
+        let key = hasher.finish();
+
+        if let Some(hash) = cache.entries.get(&key) {
+            if *hash == output.content_hash() {
+                return false;
+            }
         }
+
+        self.execute(input, output);
+        cache.entries.insert(key, output.content_hash());
+        true
     }
 
     /// Convert to `AsyncPipeline`
@@ -122,18 +323,50 @@ impl<T: Type, C: Color, U: Type, D: Color> Pipeline<T, C, U, D> {
         input: &'a [&'a Image<T, C>],
         output: &'a mut Image<U, D>,
     ) -> AsyncPipeline<'a, T, C, U, D> {
-        let image_schedule_filters = self.image_schedule_list();
+        let filters = self.flatten();
+        let plan = Self::plan_filters(&filters);
         let input = Input::new(input);
-        let index = image_schedule_filters[0];
-        let size = output.size();
+        let size = if plan.scratch_buffers > 0 {
+            output.size()
+        } else {
+            Size::new(0, 0)
+        };
         AsyncPipeline {
             pipeline: self,
-            image_schedule_filters,
-            j: 0,
-            index,
+            filters,
+            plan,
+            stage: 0,
             input,
             output,
             tmpconv: std::cell::UnsafeCell::new(Image::<T, C>::new(size)),
         }
     }
 }
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Pipeline<T, C, U, D> {
+    fn schedule(&self) -> Schedule {
+        if self.filters.iter().any(|f| f.schedule() == Schedule::Image) {
+            Schedule::Image
+        } else {
+            Schedule::Pixel
+        }
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut scratch = ChainScratch::new(input);
+        let filters = self.filters.iter().map(|f| f.as_ref());
+        Self::compute_chain(filters, pt, input, &mut scratch, dest);
+    }
+
+    fn eval(&self, input: &[&Image<T, C>], output: &mut Image<U, D>) {
+        self.execute(input, output)
+    }
+
+    fn fingerprint(&self) -> Option<u64> {
+        Pipeline::fingerprint(self)
+    }
+
+    fn as_pipeline(&self) -> Option<&Pipeline<T, C, U, D>> {
+        Some(self)
+    }
+}
@@ -0,0 +1,534 @@
+use crate::*;
+
+/// Pair of corresponding points, `(source, destination)`
+pub type Correspondence = ([f64; 2], [f64; 2]);
+
+/// Model that can be estimated from a minimal sample of data and used with `Ransac`
+pub trait Model<D>: Sized {
+    /// Number of data points required to estimate a model
+    const SAMPLE_SIZE: usize;
+
+    /// Least-squares estimate from at least `SAMPLE_SIZE` data points, returns `None` for
+    /// degenerate input
+    fn estimate(data: &[D]) -> Option<Self>;
+
+    /// Error of a single data point, compared against `Ransac::threshold`
+    fn residual(&self, data: &D) -> f64;
+}
+
+/// Result of a robust fit
+#[derive(Debug, Clone)]
+pub struct Fit<M> {
+    /// Estimated model, refined using all inliers
+    pub model: M,
+
+    /// Indices of the data points consistent with `model`
+    pub inliers: Vec<usize>,
+}
+
+/// Random sample consensus settings
+#[derive(Debug, Clone, Copy)]
+pub struct Ransac {
+    /// Maximum number of random samples
+    pub max_iterations: usize,
+
+    /// Data points with a residual below `threshold` are inliers
+    pub threshold: f64,
+
+    /// Stop early once a model is found with this probability of being outlier free
+    pub confidence: f64,
+
+    /// Random seed, fits are deterministic for a given seed
+    pub seed: u64,
+}
+
+impl Default for Ransac {
+    fn default() -> Self {
+        Ransac {
+            max_iterations: 1000,
+            threshold: 1.0,
+            confidence: 0.99,
+            seed: 0x9e3779b97f4a7c15,
+        }
+    }
+}
+
+impl Ransac {
+    /// Create new RANSAC settings with the given inlier threshold
+    pub fn new(threshold: f64) -> Ransac {
+        Ransac {
+            threshold,
+            ..Default::default()
+        }
+    }
+
+    /// Robustly estimate a model from `data`, returns `None` if no model could be estimated
+    pub fn fit<D: Clone, M: Model<D>>(&self, data: &[D]) -> Option<Fit<M>> {
+        let n = data.len();
+        if n < M::SAMPLE_SIZE || M::SAMPLE_SIZE == 0 {
+            return None;
+        }
+
+        let mut state = self.seed.max(1);
+        let mut random = move |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % n as u64) as usize
+        };
+
+        let inliers = |model: &M| -> Vec<usize> {
+            (0..n)
+                .filter(|i| model.residual(&data[*i]) < self.threshold)
+                .collect()
+        };
+
+        let mut best: Option<Vec<usize>> = None;
+        let mut iterations = self.max_iterations;
+        let mut i = 0;
+        let mut indices = Vec::with_capacity(M::SAMPLE_SIZE);
+        let mut sample = Vec::with_capacity(M::SAMPLE_SIZE);
+        while i < iterations {
+            i += 1;
+
+            indices.clear();
+            while indices.len() < M::SAMPLE_SIZE {
+                let j = random(n);
+                if !indices.contains(&j) {
+                    indices.push(j);
+                }
+            }
+
+            sample.clear();
+            sample.extend(indices.iter().map(|j| data[*j].clone()));
+            let model = match M::estimate(&sample) {
+                Some(model) => model,
+                None => continue,
+            };
+
+            let current = inliers(&model);
+            if best
+                .as_ref()
+                .map(|b| current.len() > b.len())
+                .unwrap_or(true)
+            {
+                // Update the number of iterations needed to reach the requested confidence
+                let w = current.len() as f64 / n as f64;
+                let p = 1.0 - w.powi(M::SAMPLE_SIZE as i32);
+                if p <= 0.0 {
+                    iterations = i;
+                } else if p < 1.0 {
+                    let needed = ((1.0 - self.confidence).ln() / p.ln()).ceil();
+                    iterations = iterations.min(needed.max(0.0) as usize);
+                }
+                best = Some(current);
+            }
+        }
+
+        // Refine using all inliers, then recompute the consensus set
+        let best = best?;
+        let subset: Vec<D> = best.iter().map(|i| data[*i].clone()).collect();
+        let model = M::estimate(&subset)?;
+        let inliers = inliers(&model);
+        Some(Fit { model, inliers })
+    }
+}
+
+/// Solve `a * x = b` using Gaussian elimination with partial pivoting
+fn solve<const N: usize>(mut a: [[f64; N]; N], mut b: [f64; N]) -> Option<[f64; N]> {
+    for col in 0..N {
+        let pivot = (col..N).max_by(|i, j| a[*i][col].abs().total_cmp(&a[*j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        for row in col + 1..N {
+            let f = a[row][col] / a[col][col];
+            let pivot_row = a[col];
+            for (x, p) in a[row].iter_mut().zip(&pivot_row).skip(col) {
+                *x -= f * p;
+            }
+            b[row] -= f * b[col];
+        }
+    }
+
+    let mut x = [0.0; N];
+    for row in (0..N).rev() {
+        let s: f64 = (row + 1..N).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - s) / a[row][row];
+    }
+    Some(x)
+}
+
+/// Eigenvector of the symmetric matrix `a` with the smallest eigenvalue, using Jacobi rotations
+fn smallest_eigenvector<const N: usize>(mut a: [[f64; N]; N]) -> [f64; N] {
+    let mut v = [[0.0; N]; N];
+    for (i, row) in v.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+
+    for _ in 0..100 {
+        let off: f64 = (0..N)
+            .flat_map(|i| (0..N).filter(move |j| *j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j] * a[i][j])
+            .sum();
+        if off < 1e-24 {
+            break;
+        }
+
+        for p in 0..N {
+            for q in p + 1..N {
+                if a[p][q].abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let t = if theta == 0.0 { 1.0 } else { t };
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                let (rp, rq) = (a[p], a[q]);
+                for (k, (apk, aqk)) in rp.iter().zip(&rq).enumerate() {
+                    a[p][k] = c * apk - s * aqk;
+                    a[q][k] = s * apk + c * aqk;
+                }
+                for row in v.iter_mut() {
+                    let (vp, vq) = (row[p], row[q]);
+                    row[p] = c * vp - s * vq;
+                    row[q] = s * vp + c * vq;
+                }
+            }
+        }
+    }
+
+    let min = (0..N)
+        .min_by(|i, j| a[*i][*i].total_cmp(&a[*j][*j]))
+        .unwrap_or(0);
+    let mut dest = [0.0; N];
+    for (i, row) in v.iter().enumerate() {
+        dest[i] = row[min];
+    }
+    dest
+}
+
+/// `a^T a` for rows of a design matrix
+fn normal_matrix<const N: usize>(rows: &[[f64; N]]) -> [[f64; N]; N] {
+    let mut dest = [[0.0; N]; N];
+    for r in rows {
+        for i in 0..N {
+            for j in 0..N {
+                dest[i][j] += r[i] * r[j];
+            }
+        }
+    }
+    dest
+}
+
+fn mul3(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut dest = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            dest[i][j] = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    dest
+}
+
+fn transpose3(a: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut dest = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            dest[i][j] = a[j][i];
+        }
+    }
+    dest
+}
+
+fn invert3(m: &[[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    if det.abs() < 1e-300 {
+        return None;
+    }
+
+    let mut dest = [[0.0; 3]; 3];
+    for (i, row) in dest.iter_mut().enumerate() {
+        for (j, x) in row.iter_mut().enumerate() {
+            let (a, b) = ((j + 1) % 3, (j + 2) % 3);
+            let (c, d) = ((i + 1) % 3, (i + 2) % 3);
+            *x = (m[a][c] * m[b][d] - m[a][d] * m[b][c]) / det;
+        }
+    }
+    Some(dest)
+}
+
+/// Similarity transform that moves the centroid of `points` to the origin with an average
+/// distance of `sqrt(2)`, improves the conditioning of the DLT
+fn normalization(points: impl Iterator<Item = [f64; 2]> + Clone) -> [[f64; 3]; 3] {
+    let n = points.clone().count().max(1) as f64;
+    let (cx, cy) = points
+        .clone()
+        .fold((0.0, 0.0), |(x, y), p| (x + p[0] / n, y + p[1] / n));
+    let d = points.map(|p| (p[0] - cx).hypot(p[1] - cy)).sum::<f64>() / n;
+    let s = if d > 0.0 { 2f64.sqrt() / d } else { 1.0 };
+    [[s, 0.0, -s * cx], [0.0, s, -s * cy], [0.0, 0.0, 1.0]]
+}
+
+fn apply3(m: &[[f64; 3]; 3], p: [f64; 2]) -> [f64; 3] {
+    [
+        m[0][0] * p[0] + m[0][1] * p[1] + m[0][2],
+        m[1][0] * p[0] + m[1][1] * p[1] + m[1][2],
+        m[2][0] * p[0] + m[2][1] * p[1] + m[2][2],
+    ]
+}
+
+impl Model<Correspondence> for Transform {
+    const SAMPLE_SIZE: usize = 3;
+
+    fn estimate(data: &[Correspondence]) -> Option<Self> {
+        if data.len() < 3 {
+            return None;
+        }
+
+        // Each output coordinate is an independent linear least-squares problem
+        let rows: Vec<[f64; 3]> = data.iter().map(|(a, _)| [a[0], a[1], 1.0]).collect();
+        let ata = normal_matrix(&rows);
+        let mut bx = [0.0; 3];
+        let mut by = [0.0; 3];
+        for (r, (_, b)) in rows.iter().zip(data) {
+            for i in 0..3 {
+                bx[i] += r[i] * b[0];
+                by[i] += r[i] * b[1];
+            }
+        }
+        let x = solve(ata, bx)?;
+        let y = solve(ata, by)?;
+        Some(Transform::new(x[0], y[0], x[1], y[1], x[2], y[2]))
+    }
+
+    fn residual(&self, (a, b): &Correspondence) -> f64 {
+        let p = self.transform_point(euclid::Point2D::new(a[0], a[1]));
+        (p.x - b[0]).hypot(p.y - b[1])
+    }
+}
+
+/// Projective transform between two planes, stored as a row-major 3x3 matrix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Homography(pub [[f64; 3]; 3]);
+
+impl Homography {
+    /// Identity transform
+    pub fn identity() -> Homography {
+        Homography([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+    }
+
+    /// Map a point, returns `None` for points mapped to infinity
+    pub fn transform_point(&self, p: [f64; 2]) -> Option<[f64; 2]> {
+        let [x, y, w] = apply3(&self.0, p);
+        (w.abs() > 1e-12).then(|| [x / w, y / w])
+    }
+
+    /// Inverse transform
+    pub fn inverse(&self) -> Option<Homography> {
+        invert3(&self.0).map(Homography)
+    }
+}
+
+impl Model<Correspondence> for Homography {
+    const SAMPLE_SIZE: usize = 4;
+
+    fn estimate(data: &[Correspondence]) -> Option<Self> {
+        if data.len() < 4 {
+            return None;
+        }
+
+        let ta = normalization(data.iter().map(|c| c.0));
+        let tb = normalization(data.iter().map(|c| c.1));
+        let mut rows = Vec::with_capacity(data.len() * 2);
+        for (a, b) in data {
+            let a = apply3(&ta, *a);
+            let b = apply3(&tb, *b);
+            let (x, y, u, v) = (a[0], a[1], b[0], b[1]);
+            rows.push([-x, -y, -1.0, 0.0, 0.0, 0.0, u * x, u * y, u]);
+            rows.push([0.0, 0.0, 0.0, -x, -y, -1.0, v * x, v * y, v]);
+        }
+
+        let h = smallest_eigenvector(normal_matrix(&rows));
+        let h = [[h[0], h[1], h[2]], [h[3], h[4], h[5]], [h[6], h[7], h[8]]];
+
+        // Undo the normalization: H = Tb^-1 * H' * Ta
+        let mut m = mul3(&mul3(&invert3(&tb)?, &h), &ta);
+        let scale = m[2][2];
+        if scale.abs() < 1e-12 {
+            return None;
+        }
+        m.iter_mut()
+            .flat_map(|r| r.iter_mut())
+            .for_each(|x| *x /= scale);
+        Some(Homography(m))
+    }
+
+    fn residual(&self, (a, b): &Correspondence) -> f64 {
+        match self.transform_point(*a) {
+            Some(p) => (p[0] - b[0]).hypot(p[1] - b[1]),
+            None => f64::INFINITY,
+        }
+    }
+}
+
+/// Fundamental matrix relating corresponding points of two views, `b^T F a = 0`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fundamental(pub [[f64; 3]; 3]);
+
+impl Fundamental {
+    /// Epipolar line in the second view for a point in the first view, as `(a, b, c)` with
+    /// `a * x + b * y + c = 0`
+    pub fn epipolar_line(&self, p: [f64; 2]) -> [f64; 3] {
+        apply3(&self.0, p)
+    }
+}
+
+impl Model<Correspondence> for Fundamental {
+    const SAMPLE_SIZE: usize = 8;
+
+    fn estimate(data: &[Correspondence]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+
+        // Normalized eight-point algorithm
+        let ta = normalization(data.iter().map(|c| c.0));
+        let tb = normalization(data.iter().map(|c| c.1));
+        let rows: Vec<[f64; 9]> = data
+            .iter()
+            .map(|(a, b)| {
+                let a = apply3(&ta, *a);
+                let b = apply3(&tb, *b);
+                let (x, y, u, v) = (a[0], a[1], b[0], b[1]);
+                [u * x, u * y, u, v * x, v * y, v, x, y, 1.0]
+            })
+            .collect();
+
+        let f = smallest_eigenvector(normal_matrix(&rows));
+        let f = [[f[0], f[1], f[2]], [f[3], f[4], f[5]], [f[6], f[7], f[8]]];
+
+        // Enforce rank 2 by removing the smallest singular value: F' = F (I - v v^T) where `v` is
+        // the right singular vector of the smallest singular value
+        let v = smallest_eigenvector(mul3(&transpose3(&f), &f));
+        let mut p = [[0.0; 3]; 3];
+        for i in 0..3 {
+            for j in 0..3 {
+                p[i][j] = if i == j { 1.0 } else { 0.0 } - v[i] * v[j];
+            }
+        }
+        let f = mul3(&f, &p);
+
+        // Undo the normalization: F = Tb^T * F' * Ta
+        let mut m = mul3(&mul3(&transpose3(&tb), &f), &ta);
+        let norm = m.iter().flatten().map(|x| x * x).sum::<f64>().sqrt();
+        if norm < 1e-300 {
+            return None;
+        }
+        m.iter_mut()
+            .flat_map(|r| r.iter_mut())
+            .for_each(|x| *x /= norm);
+        Some(Fundamental(m))
+    }
+
+    /// Sampson distance
+    fn residual(&self, (a, b): &Correspondence) -> f64 {
+        let fa = apply3(&self.0, *a);
+        let ftb = apply3(&transpose3(&self.0), *b);
+        let e = b[0] * fa[0] + b[1] * fa[1] + fa[2];
+        let d = fa[0] * fa[0] + fa[1] * fa[1] + ftb[0] * ftb[0] + ftb[1] * ftb[1];
+        if d <= 0.0 {
+            return f64::INFINITY;
+        }
+        (e * e / d).sqrt()
+    }
+}
+
+/// Least-squares affine transform from point correspondences
+pub fn affine(data: &[Correspondence]) -> Option<Transform> {
+    Transform::estimate(data)
+}
+
+/// Least-squares homography from point correspondences
+pub fn homography(data: &[Correspondence]) -> Option<Homography> {
+    Homography::estimate(data)
+}
+
+/// Least-squares fundamental matrix from point correspondences
+pub fn fundamental(data: &[Correspondence]) -> Option<Fundamental> {
+    Fundamental::estimate(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_fit() {
+        let h = fit::Homography([[1.1, 0.05, 3.0], [-0.02, 0.95, -2.0], [1e-4, -2e-4, 1.0]]);
+        let mut data: Vec<fit::Correspondence> = (0..40)
+            .map(|i| {
+                let p = [(i % 8) as f64 * 10.0, (i / 8) as f64 * 12.0];
+                (p, h.transform_point(p).unwrap())
+            })
+            .collect();
+
+        let estimate = fit::homography(&data).unwrap();
+        for i in 0..3 {
+            for j in 0..3 {
+                assert!((estimate.0[i][j] - h.0[i][j]).abs() < 1e-6);
+            }
+        }
+
+        // Outliers are rejected
+        for i in (0..40).step_by(5) {
+            data[i].1 = [500.0 - data[i].1[0], data[i].1[1] * 3.0];
+        }
+        let fit: fit::Fit<fit::Homography> = fit::Ransac::new(0.5).fit(&data).unwrap();
+        assert_eq!(fit.inliers.len(), 32);
+        assert!(fit.inliers.iter().all(|i| i % 5 != 0));
+
+        let affine = Transform::new(0.9, 0.1, -0.2, 1.2, 5.0, -3.0);
+        let data: Vec<fit::Correspondence> = (0..10)
+            .map(|i| {
+                let p = [(i * 3 % 7) as f64, (i * 5 % 11) as f64];
+                let q = affine.transform_point(euclid::Point2D::new(p[0], p[1]));
+                (p, [q.x, q.y])
+            })
+            .collect();
+        let estimate = fit::affine(&data).unwrap();
+        assert!(estimate.approx_eq(&affine));
+
+        // Points at different depths viewed from two translated cameras
+        let mut data: Vec<fit::Correspondence> = Vec::new();
+        for i in 0..20 {
+            let (x, y, z) = (
+                (i % 5) as f64 - 2.0,
+                (i / 5) as f64 - 1.5,
+                4.0 + (i % 3) as f64,
+            );
+            let a = [x / z, y / z];
+            let b = [(x - 0.5) / (z - 0.2), (y - 0.1) / (z - 0.2)];
+            data.push((a, b));
+        }
+        let f = fit::fundamental(&data).unwrap();
+        assert!(data.iter().all(|c| fit::Model::residual(&f, c) < 1e-6));
+        let m = f.0;
+        let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+        assert!(det.abs() < 1e-9);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::*;
+
+/// Gradient geometry, positions are in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GradientShape {
+    /// Varies along the line from `start` to `end`
+    Linear {
+        /// Position of the first stop
+        start: (f64, f64),
+
+        /// Position of the last stop
+        end: (f64, f64),
+    },
+
+    /// Varies with the distance from `center`, reaching the last stop at `radius`
+    Radial {
+        /// Center point
+        center: (f64, f64),
+
+        /// Distance of the last stop from the center
+        radius: f64,
+    },
+
+    /// Varies with the angle around `center`, starting at `angle` radians from the positive x
+    /// axis and continuing clockwise
+    Conic {
+        /// Center point
+        center: (f64, f64),
+
+        /// Starting angle in radians
+        angle: f64,
+    },
+}
+
+impl GradientShape {
+    /// Gradient position of the point `(x, y)`, between `0` and `1`
+    pub fn position(&self, x: f64, y: f64) -> f64 {
+        let t = match *self {
+            GradientShape::Linear { start, end } => {
+                let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+                let len = dx * dx + dy * dy;
+                if len == 0.0 {
+                    0.0
+                } else {
+                    ((x - start.0) * dx + (y - start.1) * dy) / len
+                }
+            }
+            GradientShape::Radial { center, radius } => {
+                (x - center.0).hypot(y - center.1) / radius.max(f64::EPSILON)
+            }
+            GradientShape::Conic { center, angle } => {
+                let theta = (y - center.1).atan2(x - center.0) - angle;
+                (theta / std::f64::consts::TAU).rem_euclid(1.0)
+            }
+        };
+        t.clamp(0.0, 1.0)
+    }
+}
+
+/// Gradient with any number of color stops, see `gradient`
+#[derive(Debug, Clone)]
+pub struct Gradient<C: Color> {
+    /// Gradient geometry
+    pub shape: GradientShape,
+
+    /// Color stops as `(position, color)`, sorted by position
+    pub stops: Vec<(f64, Pixel<C>)>,
+
+    /// Add noise of up to one quantization step to integer output to avoid banding
+    pub dither: bool,
+}
+
+impl<C: Color> Gradient<C> {
+    /// Create a gradient going from `from` to `to`
+    pub fn new(shape: GradientShape, from: Pixel<C>, to: Pixel<C>) -> Gradient<C> {
+        Gradient {
+            shape,
+            stops: vec![(0.0, from), (1.0, to)],
+            dither: true,
+        }
+    }
+
+    /// Create a linear gradient from `start` to `end`
+    pub fn linear(start: (f64, f64), end: (f64, f64), from: Pixel<C>, to: Pixel<C>) -> Gradient<C> {
+        Gradient::new(GradientShape::Linear { start, end }, from, to)
+    }
+
+    /// Create a radial gradient around `center`
+    pub fn radial(center: (f64, f64), radius: f64, from: Pixel<C>, to: Pixel<C>) -> Gradient<C> {
+        Gradient::new(GradientShape::Radial { center, radius }, from, to)
+    }
+
+    /// Create a conic gradient around `center`
+    pub fn conic(center: (f64, f64), angle: f64, from: Pixel<C>, to: Pixel<C>) -> Gradient<C> {
+        Gradient::new(GradientShape::Conic { center, angle }, from, to)
+    }
+
+    /// Add a color stop at `position`, between `0` and `1`
+    pub fn with_stop(mut self, position: f64, color: Pixel<C>) -> Gradient<C> {
+        let position = position.clamp(0.0, 1.0);
+        let index = self.stops.partition_point(|(p, _)| *p <= position);
+        self.stops.insert(index, (position, color));
+        self
+    }
+
+    /// Enable or disable dithering
+    pub fn with_dither(mut self, dither: bool) -> Gradient<C> {
+        self.dither = dither;
+        self
+    }
+
+    /// Color at gradient position `t`
+    pub fn color_at(&self, t: f64) -> Pixel<C> {
+        let mut px = Pixel::new();
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return px,
+        };
+        if t <= first.0 {
+            return first.1.clone();
+        }
+        if t >= last.0 {
+            return last.1.clone();
+        }
+
+        let i = self.stops.partition_point(|(p, _)| *p <= t);
+        let ((p0, a), (p1, b)) = (&self.stops[i - 1], &self.stops[i]);
+        let f = if p1 > p0 { (t - p0) / (p1 - p0) } else { 1.0 };
+        for c in 0..C::CHANNELS {
+            px[c] = a[c] + (b[c] - a[c]) * f;
+        }
+        px
+    }
+}
+
+/// Triangular noise between `-1` and `1`
+pub(crate) fn dither_noise(pt: Point, c: usize) -> f64 {
+    let hash = |mut x: u64| {
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+        x ^ (x >> 31)
+    };
+    let h = hash(((pt.y as u64) << 32 | pt.x as u64) ^ (c as u64).wrapping_mul(0x9e3779b97f4a7c15));
+    let a = (h & 0xffffffff) as f64 / u32::MAX as f64;
+    let b = (h >> 32) as f64 / u32::MAX as f64;
+    a - b
+}
+
+/// Render a gradient, each pixel is sampled at its center
+pub fn gradient<T: Type, C: Color>(spec: &Gradient<C>, size: impl Into<Size>) -> Image<T, C> {
+    let mut image = Image::new(size);
+    let step = if spec.dither && !T::is_float() {
+        1.0 / (T::MAX - T::MIN)
+    } else {
+        0.0
+    };
+    image.each_pixel_mut(|pt, mut px| {
+        let t = spec.shape.position(pt.x as f64 + 0.5, pt.y as f64 + 0.5);
+        let color = spec.color_at(t);
+        for c in 0..C::CHANNELS {
+            px[c] = if step > 0.0 && C::ALPHA != Some(c) {
+                (color[c] + dither_noise(pt, c) * step).clamp(0.0, 1.0)
+            } else {
+                color[c]
+            };
+        }
+    });
+    image
+}
@@ -1,3 +1,6 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
 use crate::*;
 
 /// Hash is used for content-based hashing
@@ -37,6 +40,137 @@ impl From<Hash> for String {
     }
 }
 
+/// Fast, non-cryptographic hasher used by `Image::content_hash`. Input is consumed 8 bytes at a
+/// time and the result only depends on the data written, so hashes are stable between runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentHasher {
+    state: u64,
+    len: u64,
+}
+
+const P0: u64 = 0xa076_1d64_78bd_642f;
+const P1: u64 = 0xe703_7ed1_a0b4_28db;
+
+/// Multiply and fold the high bits back into the low bits
+#[inline]
+fn mum(a: u64, b: u64) -> u64 {
+    let r = a as u128 * b as u128;
+    (r as u64) ^ ((r >> 64) as u64)
+}
+
+impl Default for ContentHasher {
+    fn default() -> Self {
+        ContentHasher::new()
+    }
+}
+
+impl ContentHasher {
+    /// Create a new hasher
+    pub fn new() -> ContentHasher {
+        ContentHasher { state: 0, len: 0 }
+    }
+
+    /// Fingerprint of a filter of type `F` with the given parameters, this can be used to
+    /// implement `Filter::fingerprint` for filters that are fully described by a few numbers
+    pub fn fingerprint<F: ?Sized>(params: &[f64]) -> Option<u64> {
+        use std::hash::Hasher;
+
+        let mut hasher = ContentHasher::new();
+        hasher.write(std::any::type_name::<F>().as_bytes());
+        params.iter().for_each(|x| hasher.write_u64(x.to_bits()));
+        Some(hasher.finish())
+    }
+
+    #[inline]
+    fn word(&mut self, word: u64) {
+        self.state = mum(self.state ^ word ^ P0, P1);
+    }
+}
+
+impl std::hash::Hasher for ContentHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            self.word(u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+
+        let rest = chunks.remainder();
+        if !rest.is_empty() {
+            let mut buf = [0u8; 8];
+            buf[..rest.len()].copy_from_slice(rest);
+            self.word(u64::from_le_bytes(buf) ^ ((rest.len() as u64) << 56));
+        }
+        self.len += bytes.len() as u64;
+    }
+
+    fn finish(&self) -> u64 {
+        mum(self.state ^ self.len, P1 ^ self.len.rotate_left(32))
+    }
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Fast non-cryptographic hash of the size, type, color, metadata and pixel data. Unlike the
+    /// perceptual `hash` any change to the image produces a different value, this is meant to be
+    /// used as an identity for caching, see `PipelineCache`
+    pub fn content_hash(&self) -> u64 {
+        use std::hash::Hasher;
+
+        let hash_row = |row: &[T]| {
+            let mut hasher = ContentHasher::new();
+            hasher.write(bytemuck::cast_slice(row));
+            hasher.finish()
+        };
+
+        let width_step = self.meta.width_step().max(1);
+        #[cfg(feature = "parallel")]
+        let rows: Vec<u64> = self.data().par_chunks(width_step).map(hash_row).collect();
+        #[cfg(not(feature = "parallel"))]
+        let rows: Vec<u64> = self.data().chunks(width_step).map(hash_row).collect();
+
+        let mut hasher = ContentHasher::new();
+        hasher.write_u64(self.width() as u64);
+        hasher.write_u64(self.height() as u64);
+        hasher.write(T::type_name().as_bytes());
+        hasher.write(C::NAME.as_bytes());
+        hasher.write_u64(C::CHANNELS as u64);
+
+        // Metadata is written field by field, floating point values using their bits
+        match &self.meta.colorspace {
+            Some(colorspace) => {
+                hasher.write_u8(1);
+                hasher.write_u8(colorspace.primaries as u8);
+                hasher.write_u8(colorspace.transfer as u8);
+            }
+            None => hasher.write_u8(0),
+        }
+        match &self.meta.window_level {
+            Some(w) => {
+                hasher.write_u8(1);
+                for x in [w.center, w.width, w.slope, w.intercept] {
+                    hasher.write_u64(x.to_bits());
+                }
+            }
+            None => hasher.write_u8(0),
+        }
+        match &self.meta.georeference {
+            Some(g) => {
+                hasher.write_u8(1);
+                g.transform
+                    .iter()
+                    .chain(&g.double_params)
+                    .for_each(|x| hasher.write_u64(x.to_bits()));
+                hasher.write_u64(g.double_params.len() as u64);
+                g.keys.iter().for_each(|k| hasher.write_u16(*k));
+                hasher.write_u64(g.keys.len() as u64);
+                hasher.write(g.ascii_params.as_bytes());
+            }
+            None => hasher.write_u8(0),
+        }
+        rows.iter().for_each(|row| hasher.write_u64(*row));
+        hasher.finish()
+    }
+}
+
 impl<'a, T: Type, C: 'a + Color> Image<T, C>
 where
     &'a Image<T, C>: blockhash::Image,
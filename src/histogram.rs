@@ -1,13 +1,27 @@
 use crate::*;
 
-/// Image histogram
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Image histogram, values in `range` are mapped to the nearest of `len()` evenly spaced bin
+/// centers, the first and last bin are centered on the ends of the range
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Histogram {
     total: usize,
     bins: Box<[usize]>,
+    range: (f64, f64),
+}
+
+/// Ranges are compared bitwise so histograms can be `Eq`
+impl PartialEq for Histogram {
+    fn eq(&self, other: &Self) -> bool {
+        self.total == other.total
+            && self.bins == other.bins
+            && self.range.0.to_bits() == other.range.0.to_bits()
+            && self.range.1.to_bits() == other.range.1.to_bits()
+    }
 }
 
+impl Eq for Histogram {}
+
 impl std::ops::Index<usize> for Histogram {
     type Output = usize;
 
@@ -29,18 +43,38 @@ impl AsRef<[usize]> for Histogram {
 }
 
 impl Histogram {
-    /// Create a new histogram with the given number of bins
+    /// Create a new histogram with the given number of bins covering normalized values in
+    /// `[0, 1]`
     pub fn new(nbins: usize) -> Histogram {
+        Histogram::new_with_range(nbins, 0.0, 1.0)
+    }
+
+    /// Create a new histogram with the given number of bins covering values in `[min, max]`,
+    /// this is used for floating point images with values outside of `[0, 1]`
+    pub fn new_with_range(nbins: usize, min: f64, max: f64) -> Histogram {
         Histogram {
             total: 0,
             bins: vec![0; nbins].into_boxed_slice(),
+            range: (min, max),
         }
     }
 
-    /// Join data from multiple histograms
+    /// Create a new histogram with one bin for each value of an integer type, up to 65536 bins.
+    /// Floating point types use 1024 bins
+    pub fn for_type<T: Type>() -> Histogram {
+        if T::is_float() {
+            return Histogram::new(1024);
+        }
+        let levels = (T::MAX - T::MIN + 1.0).min(65536.0);
+        Histogram::new(levels as usize)
+    }
+
+    /// Join data from multiple histograms, every histogram should have the same number of bins
+    /// and range
     pub fn join(h: impl AsRef<[Histogram]>) -> Histogram {
         let h = h.as_ref();
-        let mut hist = Histogram::new(h[0].len());
+        let (min, max) = h[0].range;
+        let mut hist = Histogram::new_with_range(h[0].len(), min, max);
 
         for i in h {
             hist.total += i.total;
@@ -52,10 +86,38 @@ impl Histogram {
         hist
     }
 
-    /// Add a value to the histogram
+    /// Value range
+    pub fn range(&self) -> (f64, f64) {
+        self.range
+    }
+
+    /// Get the index of the bin containing `value`, values outside of the range are placed in the
+    /// first or last bin
+    pub fn bin_index(&self, value: f64) -> usize {
+        let (min, max) = self.range;
+        let n = self.len().max(1) - 1;
+        let x = ((value - min) / (max - min)).clamp(0.0, 1.0) * n as f64;
+        if x.is_nan() {
+            0
+        } else {
+            x.round() as usize
+        }
+    }
+
+    /// Get the value at the center of a bin
+    pub fn bin_value(&self, index: usize) -> f64 {
+        let (min, max) = self.range;
+        min + index as f64 / (self.len().max(2) - 1) as f64 * (max - min)
+    }
+
+    /// Add a normalized value to the histogram
     pub fn add_value<T: Type>(&mut self, value: T) {
-        let x = value.to_norm() * (self.bins.len() - 1) as f64;
-        self.incr_bin(x.round() as usize)
+        self.add(value.to_norm())
+    }
+
+    /// Add a value to the histogram
+    pub fn add(&mut self, value: f64) {
+        self.incr_bin(self.bin_index(value))
     }
 
     /// Increment a bin without adding a value
@@ -124,10 +186,157 @@ impl Histogram {
         self.bins().map(|(_, x)| x as f64 / total).collect()
     }
 
+    /// Running total of the bins, the last value is the number of values in the histogram
+    pub fn cumulative(&self) -> Vec<usize> {
+        self.bins
+            .iter()
+            .scan(0, |acc, x| {
+                *acc += x;
+                Some(*acc)
+            })
+            .collect()
+    }
+
+    /// Cumulative distribution, the fraction of values in each bin or below
+    pub fn cdf(&self) -> Vec<f64> {
+        let total = self.total.max(1) as f64;
+        self.cumulative()
+            .into_iter()
+            .map(|x| x as f64 / total)
+            .collect()
+    }
+
+    /// Fraction of values in the bin containing `value` or below
+    pub fn cdf_at(&self, value: f64) -> f64 {
+        let index = self.bin_index(value);
+        self.bins[..=index].iter().sum::<usize>() as f64 / self.total.max(1) as f64
+    }
+
     /// Get sum of all values
     pub fn sum(&self) -> usize {
         self.total
     }
+
+    /// Get the value below which `percent` percent of the values fall
+    pub fn percentile(&self, percent: f64) -> f64 {
+        let target = (percent.clamp(0.0, 100.0) / 100.0 * self.total as f64).ceil() as usize;
+        let mut count = 0;
+        for (i, n) in self.bins() {
+            count += n;
+            if count >= target.max(1) {
+                return self.bin_value(i);
+            }
+        }
+        self.range.1
+    }
+
+    /// Redistribute the counts into a histogram with `nbins` bins covering the same range, each
+    /// bin is moved to the new bin containing its center
+    pub fn resample(&self, nbins: usize) -> Histogram {
+        let (min, max) = self.range;
+        let mut dest = Histogram::new_with_range(nbins, min, max);
+        for (i, n) in self.bins() {
+            let index = dest.bin_index(self.bin_value(i));
+            dest.bins[index] += n;
+        }
+        dest.total = self.total;
+        dest
+    }
+}
+
+/// Joint histogram of two values, used to measure how two channels or images relate to each other
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Histogram2d {
+    x: Histogram,
+    y: Histogram,
+    bins: Box<[usize]>,
+}
+
+impl Histogram2d {
+    /// Create a new joint histogram with `nbins` bins on each axis covering normalized values
+    pub fn new(nbins: usize) -> Histogram2d {
+        Histogram2d::new_with_range(nbins, (0.0, 1.0), (0.0, 1.0))
+    }
+
+    /// Create a new joint histogram with `nbins` bins on each axis covering the given ranges
+    pub fn new_with_range(nbins: usize, x: (f64, f64), y: (f64, f64)) -> Histogram2d {
+        Histogram2d {
+            x: Histogram::new_with_range(nbins, x.0, x.1),
+            y: Histogram::new_with_range(nbins, y.0, y.1),
+            bins: vec![0; nbins * nbins].into_boxed_slice(),
+        }
+    }
+
+    /// Number of bins on each axis
+    pub fn len(&self) -> usize {
+        self.x.len()
+    }
+
+    /// Returns true when there are zero bins
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add a pair of values
+    pub fn add(&mut self, x: f64, y: f64) {
+        let (i, j) = (self.x.bin_index(x), self.y.bin_index(y));
+        self.x.incr_bin(i);
+        self.y.incr_bin(j);
+        self.bins[j * self.len() + i] += 1;
+    }
+
+    /// Get the number of values in bin `(i, j)`, where `i` is the bin of the first value
+    pub fn bin(&self, i: usize, j: usize) -> usize {
+        self.bins[j * self.len() + i]
+    }
+
+    /// Histogram of the first value
+    pub fn marginal_x(&self) -> &Histogram {
+        &self.x
+    }
+
+    /// Histogram of the second value
+    pub fn marginal_y(&self) -> &Histogram {
+        &self.y
+    }
+
+    /// Get number of values
+    pub fn sum(&self) -> usize {
+        self.x.sum()
+    }
+
+    /// Join data from multiple histograms with the same shape
+    pub fn join(h: impl AsRef<[Histogram2d]>) -> Histogram2d {
+        let h = h.as_ref();
+        let mut dest = h[0].clone();
+        for other in &h[1..] {
+            dest.x = Histogram::join([dest.x, other.x.clone()]);
+            dest.y = Histogram::join([dest.y, other.y.clone()]);
+            dest.bins
+                .iter_mut()
+                .zip(other.bins.iter())
+                .for_each(|(a, b)| *a += b);
+        }
+        dest
+    }
+
+    /// Mutual information between the two values in bits
+    pub fn mutual_information(&self) -> f64 {
+        let total = self.sum().max(1) as f64;
+        let mut mi = 0.0;
+        for j in 0..self.len() {
+            for i in 0..self.len() {
+                let pxy = self.bin(i, j) as f64 / total;
+                if pxy > 0.0 {
+                    let px = self.x.bin(i) as f64 / total;
+                    let py = self.y.bin(j) as f64 / total;
+                    mi += pxy * (pxy / (px * py)).log2();
+                }
+            }
+        }
+        mi
+    }
 }
 
 #[cfg(test)]
@@ -147,4 +356,70 @@ mod tests {
             assert!(h.distribution().into_iter().skip(1).sum::<f64>() == 0.0);
         }
     }
+
+    #[test]
+    fn test_histogram_range() {
+        let mut h = Histogram::new_with_range(5, -1.0, 3.0);
+        for x in [-2.0, -1.0, 0.1, 1.0, 2.9, 10.0] {
+            h.add(x);
+        }
+        assert_eq!(h.as_ref(), &[2, 1, 1, 0, 2]);
+        assert_eq!(h.bin_value(4), 3.0);
+        assert_eq!(h.cumulative(), vec![2, 3, 4, 4, 6]);
+        assert_eq!(h.cdf_at(1.0), 4.0 / 6.0);
+        assert_eq!(h.percentile(50.0), 0.0);
+
+        let r = h.resample(3);
+        assert_eq!(r.sum(), 6);
+        assert_eq!(r.as_ref(), &[2, 2, 2]);
+        assert_eq!(r.range(), (-1.0, 3.0));
+
+        assert_eq!(Histogram::for_type::<u8>().len(), 256);
+        assert_eq!(Histogram::for_type::<u16>().len(), 65536);
+        assert_eq!(Histogram::for_type::<f32>().len(), 1024);
+    }
+
+    #[test]
+    fn test_histogram_alpha() {
+        let mut image = Image::<u8, Rgba>::new((4, 4));
+        image.for_each(|_, mut px| px[3] = 255);
+        let hist = image.histogram(2);
+        assert_eq!(hist.len(), 4);
+        assert_eq!(hist[0].bin(0), 16);
+        assert_eq!(hist[3].bin(1), 16);
+        assert_eq!(hist[0], Histogram::join([hist[0].clone()]));
+    }
+
+    #[test]
+    fn test_histogram_hdr() {
+        let mut image = Image::<f32, Rgb>::new((10, 10));
+        image.for_each(|pt, mut px| px[0] = pt.x as f32);
+        let hist = image.reduce(reduce::Histograms::new(10).with_range_of(&image));
+        assert_eq!(hist[0].range(), (0.0, 9.0));
+        assert!(hist[0].bins().all(|(_, n)| n == 10));
+        assert_eq!(hist[1].bin(0), 100);
+
+        let joint = image.histogram2d(0, 1, 4);
+        assert_eq!(joint.sum(), 100);
+        assert_eq!(joint.bin(3, 0), 90);
+        assert_eq!(joint.marginal_y().bin(0), 100);
+        assert_eq!(joint.mutual_information(), 0.0);
+
+        let mut gray = Image::<f32, Rgb>::new((16, 1));
+        gray.for_each(|pt, mut px| {
+            px[0] = pt.x as f32 / 15.0;
+            px[1] = px[0];
+        });
+        // Identical channels share all of their information, so the mutual information is the
+        // entropy of either channel
+        let joint = gray.histogram2d(0, 1, 4);
+        let entropy: f64 = joint
+            .marginal_x()
+            .distribution()
+            .iter()
+            .map(|p| -p * p.log2())
+            .sum();
+        assert!((joint.mutual_information() - entropy).abs() < 1e-9);
+        assert!(entropy > 1.5);
+    }
 }
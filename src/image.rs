@@ -22,7 +22,7 @@ impl<T: Type, C: Color> Clone for Image<T, C> {
     fn clone(&self) -> Self {
         Image {
             meta: self.meta.clone(),
-            data: Box::new(self.data.data().to_vec().into_boxed_slice()),
+            data: memory::track(self.data.data().to_vec().into_boxed_slice()),
         }
     }
 }
@@ -65,16 +65,48 @@ impl<T: Type, C: Color> Image<T, C> {
         })
     }
 
-    /// Create a new image
+    /// Create a new image, with the `memory-budget` feature the allocation counts towards the
+    /// budget but never fails because of it, use `Image::try_new` to respect the budget
     pub fn new(size: impl Into<Size>) -> Image<T, C> {
         let size = size.into();
         let data = vec![T::default(); size.width * size.height * C::CHANNELS];
         Image {
             meta: Meta::new(size),
-            data: Box::new(data.into_boxed_slice()),
+            data: memory::track(data.into_boxed_slice()),
         }
     }
 
+    /// Create a new image, returns `Error::OutOfMemory` instead of aborting when the allocation
+    /// fails or exceeds the memory budget
+    pub fn try_new(size: impl Into<Size>) -> Result<Image<T, C>, Error> {
+        Image::new_in(size, &memory::Global)
+    }
+
+    /// Create a new image using pixel data from `allocator`
+    pub fn new_in(
+        size: impl Into<Size>,
+        allocator: &impl memory::Allocator<T>,
+    ) -> Result<Image<T, C>, Error> {
+        let meta = Meta::new(size);
+        let len = meta
+            .width()
+            .checked_mul(meta.height())
+            .and_then(|n| n.checked_mul(C::CHANNELS))
+            .ok_or(Error::OutOfMemory {
+                requested: usize::MAX,
+                available: 0,
+            })?;
+        let data = allocator.allocate(len)?;
+        if data.data().len() < len {
+            return Err(Error::InvalidDimensions(
+                meta.width(),
+                meta.height(),
+                C::CHANNELS,
+            ));
+        }
+        Ok(Image { meta, data })
+    }
+
     /// Consume image and return inner ImageData
     pub fn into_data(self) -> Box<dyn ImageData<T>> {
         self.data
@@ -123,6 +155,33 @@ impl<T: Type, C: Color> Image<T, C> {
         Ok(self)
     }
 
+    #[cfg(feature = "mmap")]
+    /// Memory-map the pixel data of an uncompressed image file instead of decoding it, supported
+    /// files are 8-bit binary PNM (`P5` and `P6`), little-endian TIFF with uncompressed,
+    /// interleaved, contiguous strips and files created using `Image::new_mmap`. `T` and `C` must
+    /// match the file exactly since no conversion is performed
+    ///
+    /// The mapping is copy-on-write, modifying the image never changes the file and only the
+    /// modified pages use additional memory
+    pub fn open_mmap(path: impl AsRef<std::path::Path>) -> Result<Image<T, C>, Error> {
+        let path = path.as_ref();
+        Mmap::open_image(path).map_err(|e| e.with_path(path))
+    }
+
+    #[cfg(feature = "mmap")]
+    /// Memory-map headerless pixel data starting `offset` bytes into a file, see `open_mmap`
+    pub fn open_mmap_raw(
+        path: impl AsRef<std::path::Path>,
+        size: impl Into<Size>,
+        offset: u64,
+    ) -> Result<Image<T, C>, Error> {
+        let path = path.as_ref();
+        let size = size.into();
+        let len = image_data::mmap::num_values(size, C::CHANNELS)?;
+        let data = Mmap::open_copy(path, offset, len).map_err(|e| e.with_path(path))?;
+        Image::new_with_data(size, data)
+    }
+
     #[cfg(feature = "mmap")]
     /// Map an existing image to disk, returns the new mmapped image
     pub fn mmap_clone(&self, filename: impl AsRef<std::path::Path>) -> Result<Image<T, C>, Error> {
@@ -186,6 +245,82 @@ impl<T: Type, C: Color> Image<T, C> {
         self.data.buffer_mut()
     }
 
+    /// Image data as native-endian bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(self.data())
+    }
+
+    /// Image data as mutable native-endian bytes
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        bytemuck::cast_slice_mut(self.data_mut())
+    }
+
+    /// Reinterpret image data as a slice of `P` with one element per pixel, for example
+    /// `[u8; 4]` for `Image<u8, Rgba>`
+    pub fn as_pixels<P: bytemuck::Pod>(&self) -> Result<&[P], Error> {
+        self.check_pixel_size::<P>()?;
+        bytemuck::try_cast_slice(self.data()).map_err(Error::Cast)
+    }
+
+    /// Reinterpret image data as a mutable slice of `P` with one element per pixel
+    pub fn as_pixels_mut<P: bytemuck::Pod>(&mut self) -> Result<&mut [P], Error> {
+        self.check_pixel_size::<P>()?;
+        bytemuck::try_cast_slice_mut(self.data_mut()).map_err(Error::Cast)
+    }
+
+    fn check_pixel_size<P>(&self) -> Result<(), Error> {
+        if std::mem::size_of::<P>() != C::CHANNELS * std::mem::size_of::<T>() {
+            return Err(Error::Cast(bytemuck::PodCastError::SizeMismatch));
+        }
+        Ok(())
+    }
+
+    /// Create an image from native-endian bytes, `bytes` doesn't need to be aligned for `T`
+    pub fn from_bytes(size: impl Into<Size>, bytes: &[u8]) -> Result<Image<T, C>, Error> {
+        let mut image = Image::new(size);
+        if bytes.len() != image.as_bytes().len() {
+            return Err(Error::Cast(bytemuck::PodCastError::SizeMismatch));
+        }
+        image.as_bytes_mut().copy_from_slice(bytes);
+        Ok(image)
+    }
+
+    /// Create an image from little-endian bytes
+    pub fn from_le_bytes(size: impl Into<Size>, bytes: &[u8]) -> Result<Image<T, C>, Error> {
+        let mut image = Image::from_bytes(size, bytes)?;
+        if cfg!(target_endian = "big") {
+            swap_bytes::<T>(image.as_bytes_mut());
+        }
+        Ok(image)
+    }
+
+    /// Create an image from big-endian bytes
+    pub fn from_be_bytes(size: impl Into<Size>, bytes: &[u8]) -> Result<Image<T, C>, Error> {
+        let mut image = Image::from_bytes(size, bytes)?;
+        if cfg!(target_endian = "little") {
+            swap_bytes::<T>(image.as_bytes_mut());
+        }
+        Ok(image)
+    }
+
+    /// Copy image data to little-endian bytes
+    pub fn to_le_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.as_bytes().to_vec();
+        if cfg!(target_endian = "big") {
+            swap_bytes::<T>(&mut bytes);
+        }
+        bytes
+    }
+
+    /// Copy image data to big-endian bytes
+    pub fn to_be_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.as_bytes().to_vec();
+        if cfg!(target_endian = "little") {
+            swap_bytes::<T>(&mut bytes);
+        }
+        bytes
+    }
+
     /// Get data at specified index
     #[inline]
     pub fn get(&self, pt: impl Into<Point>) -> Data<T, C> {
@@ -298,13 +433,11 @@ impl<T: Type, C: Color> Image<T, C> {
     }
 
     /// Iterate over image rows
-    #[cfg(not(feature = "parallel"))]
     pub fn rows(&self) -> impl Iterator<Item = (usize, &[T])> {
         self.data.data().chunks(self.meta.width_step()).enumerate()
     }
 
     /// Iterate over mutable image rows
-    #[cfg(not(feature = "parallel"))]
     pub fn rows_mut(&mut self) -> impl Iterator<Item = (usize, &mut [T])> {
         self.data
             .data_mut()
@@ -312,24 +445,37 @@ impl<T: Type, C: Color> Image<T, C> {
             .enumerate()
     }
 
-    /// Iterate over image rows
+    /// Iterate over image rows in parallel
     #[cfg(feature = "parallel")]
-    pub fn rows(&self) -> impl ParallelIterator<Item = (usize, &[T])> {
+    pub fn par_rows(&self) -> impl ParallelIterator<Item = (usize, &[T])> {
         self.data
             .data()
             .par_chunks(self.meta.width_step())
             .enumerate()
     }
 
-    /// Iterate over mutable image rows
+    /// Iterate over mutable image rows in parallel
     #[cfg(feature = "parallel")]
-    pub fn rows_mut(&mut self) -> impl ParallelIterator<Item = (usize, &mut [T])> {
+    pub fn par_rows_mut(&mut self) -> impl ParallelIterator<Item = (usize, &mut [T])> {
         self.data
             .data_mut()
             .par_chunks_mut(self.meta.width_step())
             .enumerate()
     }
 
+    /// Iterate over image columns, each column yields the data of one pixel per row from top to
+    /// bottom
+    pub fn columns(&self) -> impl Iterator<Item = (usize, impl Iterator<Item = &[T]>)> {
+        let width_step = self.meta.width_step();
+        let data = self.data.data();
+        (0..self.width()).map(move |x| {
+            let column = data
+                .chunks(width_step)
+                .map(move |row| &row[x * C::CHANNELS..(x + 1) * C::CHANNELS]);
+            (x, column)
+        })
+    }
+
     /// Iterate over image rows
     #[cfg(not(feature = "parallel"))]
     pub fn row_range(&self, y: usize, height: usize) -> impl Iterator<Item = (usize, &[T])> {
@@ -392,12 +538,44 @@ impl<T: Type, C: Color> Image<T, C> {
 
     /// Read an image from disk
     pub fn open(path: impl AsRef<std::path::Path>) -> Result<Image<T, C>, Error> {
-        io::read(path)
+        let path = path.as_ref();
+        io::read(path).map_err(|e| e.with_path(path))
+    }
+
+    /// Read an image from disk, the file header is checked against the limits in `options` before
+    /// any pixel data is allocated. Use this when reading untrusted files
+    pub fn open_with_options(
+        path: impl AsRef<std::path::Path>,
+        options: &io::DecodeOptions,
+    ) -> Result<Image<T, C>, Error> {
+        let path = path.as_ref();
+        io::read_with_options(path, options).map_err(|e| e.with_path(path))
+    }
+
+    /// Read a single layer of an image file, use `io::layers` to list the available layers
+    pub fn open_layer(
+        path: impl AsRef<std::path::Path>,
+        layer_name: impl AsRef<str>,
+    ) -> Result<Image<T, C>, Error> {
+        let path = path.as_ref();
+        io::open_layer(path, layer_name).map_err(|e| e.with_path(path))
     }
 
     /// Write an image to disk
     pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), Error> {
-        io::write(path, self)
+        let path = path.as_ref();
+        io::write(path, self).map_err(|e| e.with_path(path))
+    }
+
+    /// Write an image to disk using the provided encoder options, see `io::supported_formats` to
+    /// check which formats are available at runtime
+    pub fn save_with_options(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        options: &io::EncodeOptions,
+    ) -> Result<(), Error> {
+        let path = path.as_ref();
+        io::write_with_options(path, self, options).map_err(|e| e.with_path(path))
     }
 
     /// Iterate over part of an image with mutable data access
@@ -468,7 +646,7 @@ impl<T: Type, C: Color> Image<T, C> {
     /// Get pixel iterator
     #[cfg(feature = "parallel")]
     pub fn iter(&self) -> impl rayon::iter::ParallelIterator<Item = (Point, Data<T, C>)> {
-        self.rows().flat_map(move |(y, row)| {
+        self.par_rows().flat_map(move |(y, row)| {
             row.par_chunks(C::CHANNELS)
                 .map(Data::new)
                 .enumerate()
@@ -492,7 +670,7 @@ impl<T: Type, C: Color> Image<T, C> {
     pub fn iter_mut(
         &mut self,
     ) -> impl rayon::iter::ParallelIterator<Item = (Point, DataMut<T, C>)> {
-        self.rows_mut().flat_map(move |(y, row)| {
+        self.par_rows_mut().flat_map(move |(y, row)| {
             row.par_chunks_mut(C::CHANNELS)
                 .map(DataMut::new)
                 .enumerate()
@@ -513,7 +691,11 @@ impl<T: Type, C: Color> Image<T, C> {
 
     /// Iterate over each pixel applying `f` to every pixel
     pub fn for_each<F: Sync + Send + Fn(Point, DataMut<T, C>)>(&mut self, f: F) {
-        self.rows_mut().for_each(|(y, row)| {
+        #[cfg(feature = "parallel")]
+        let rows = self.par_rows_mut();
+        #[cfg(not(feature = "parallel"))]
+        let rows = self.rows_mut();
+        rows.for_each(|(y, row)| {
             row.chunks_mut(C::CHANNELS)
                 .map(DataMut::new)
                 .enumerate()
@@ -521,6 +703,32 @@ impl<T: Type, C: Color> Image<T, C> {
         })
     }
 
+    /// Create a new image by applying `f` to the `(2 * radius + 1)` square window around each
+    /// pixel, pixels outside of the image are clamped to the nearest edge. Rows are processed in
+    /// parallel when the `parallel` feature is enabled
+    pub fn map_neighborhood<F: Sync + Send + Fn(&Neighborhood<T, C>) -> Pixel<C>>(
+        &self,
+        radius: usize,
+        f: F,
+    ) -> Image<T, C> {
+        self.map_neighborhood_with(radius, kernel::EdgeStrategy::Extend, f)
+    }
+
+    /// Like `map_neighborhood` with the given edge strategy
+    pub fn map_neighborhood_with<F: Sync + Send + Fn(&Neighborhood<T, C>) -> Pixel<C>>(
+        &self,
+        radius: usize,
+        edge: kernel::EdgeStrategy,
+        f: F,
+    ) -> Image<T, C> {
+        let mut dest = self.new_like();
+        dest.for_each(|pt, mut data| {
+            let window = Neighborhood::new(self, pt, radius, edge.clone());
+            f(&window).convert_to_data(&mut data);
+        });
+        dest
+    }
+
     /// Iterate over a region of pixels qpplying `f` to every pixel
     pub fn for_each_region<F: Sync + Send + Fn(Point, DataMut<T, C>)>(
         &mut self,
@@ -657,6 +865,7 @@ impl<T: Type, C: Color> Image<T, C> {
     pub fn crop(&self, roi: Region) -> Image<T, C> {
         let mut dest = Image::new(roi.size);
         dest.apply(filter::crop(roi), &[self]);
+        dest.meta.georeference = self.meta.georeference.as_ref().map(|g| g.crop(roi.origin));
         dest
     }
 
@@ -670,16 +879,192 @@ impl<T: Type, C: Color> Image<T, C> {
         });
     }
 
+    /// Copy a circular area centered at `src` to `dst`, like a clone brush. The copied area is
+    /// fully opaque up to `radius - feather` and fades out smoothly at `radius`
+    pub fn clone_stamp(
+        &mut self,
+        src: impl Into<Point>,
+        dst: impl Into<Point>,
+        radius: f64,
+        feather: f64,
+    ) {
+        self.stamp(src.into(), dst.into(), radius, feather, false)
+    }
+
+    /// Like `clone_stamp`, but the copied texture is adjusted to match the mean and contrast
+    /// of the area surrounding `dst`, which hides the seams when removing blemishes
+    pub fn heal_stamp(
+        &mut self,
+        src: impl Into<Point>,
+        dst: impl Into<Point>,
+        radius: f64,
+        feather: f64,
+    ) {
+        self.stamp(src.into(), dst.into(), radius, feather, true)
+    }
+
+    fn stamp(&mut self, src: Point, dst: Point, radius: f64, feather: f64, heal: bool) {
+        let radius = radius.max(0.0);
+        let feather = feather.clamp(0.0, radius);
+        let (w, h) = (self.width() as isize, self.height() as isize);
+        let reach = (radius * 1.5).ceil() as isize + 1;
+        let offset = (
+            src.x as isize - dst.x as isize,
+            src.y as isize - dst.y as isize,
+        );
+
+        // Brush footprint around `dst` that has a matching source pixel, read up front since
+        // source and destination may overlap
+        let mut area = Vec::new();
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let (x, y) = (dst.x as isize + dx, dst.y as isize + dy);
+                let (sx, sy) = (x + offset.0, y + offset.1);
+                if x < 0 || y < 0 || x >= w || y >= h || sx < 0 || sy < 0 || sx >= w || sy >= h {
+                    continue;
+                }
+                let d = ((dx * dx + dy * dy) as f64).sqrt();
+                area.push((
+                    Point::new(x as usize, y as usize),
+                    d,
+                    self.get_pixel((x as usize, y as usize)),
+                    self.get_pixel((sx as usize, sy as usize)),
+                ));
+            }
+        }
+
+        // Per-channel mean and standard deviation of the ring just outside of the brush
+        let mut scale = vec![1.0; C::CHANNELS];
+        let mut shift = vec![0.0; C::CHANNELS];
+        if heal {
+            let ring: Vec<_> = area
+                .iter()
+                .filter(|(_, d, _, _)| *d >= radius && *d <= radius * 1.5 + 1.0)
+                .collect();
+            if !ring.is_empty() {
+                let n = ring.len() as f64;
+                let stats = |values: &mut dyn Iterator<Item = f64>| {
+                    let values: Vec<f64> = values.collect();
+                    let mean = values.iter().sum::<f64>() / n;
+                    let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+                    (mean, var.sqrt())
+                };
+                for c in (0..C::CHANNELS).filter(|c| C::ALPHA != Some(*c)) {
+                    let (dst_mean, dst_std) = stats(&mut ring.iter().map(|(_, _, a, _)| a[c]));
+                    let (src_mean, src_std) = stats(&mut ring.iter().map(|(_, _, _, b)| b[c]));
+                    scale[c] = if src_std > 1e-6 {
+                        (dst_std / src_std).clamp(0.5, 2.0)
+                    } else {
+                        1.0
+                    };
+                    shift[c] = dst_mean - src_mean * scale[c];
+                }
+            }
+        }
+
+        for (pt, d, mut px, source) in area {
+            if d >= radius {
+                continue;
+            }
+            let t = if feather > 0.0 {
+                ((radius - d) / feather).clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+            let weight = t * t * (3.0 - 2.0 * t);
+            for c in 0..C::CHANNELS {
+                let value = source[c] * scale[c] + shift[c];
+                px[c] = px[c] * (1.0 - weight) + value * weight;
+            }
+            self.set_pixel(pt, &px);
+        }
+    }
+
+    /// Copy channels into an image of another color without any color conversion, destination
+    /// channel `i` is set to source channel `order[i]`. This is much faster than `convert` for
+    /// swizzles like RGBA to BGRA
+    pub fn reorder_channels<D: Color>(&self, order: &[Channel]) -> Image<T, D> {
+        let mut dest = Image::new(self.size());
+        self.reorder_channels_into(order, &mut dest);
+        dest
+    }
+
+    /// Same as `reorder_channels` using an existing destination image
+    pub fn reorder_channels_into<D: Color>(&self, order: &[Channel], dest: &mut Image<T, D>) {
+        assert_eq!(
+            order.len(),
+            D::CHANNELS,
+            "one source channel per destination channel"
+        );
+        assert!(
+            order.iter().all(|c| *c < C::CHANNELS),
+            "invalid source channel"
+        );
+        dest.data_mut()
+            .chunks_exact_mut(D::CHANNELS)
+            .zip(self.data().chunks_exact(C::CHANNELS))
+            .for_each(|(d, s)| {
+                for (x, c) in d.iter_mut().zip(order) {
+                    *x = s[*c];
+                }
+            });
+        dest.meta.georeference = self.meta.georeference.clone();
+    }
+
+    /// Swap the first and third channel in place, changing the color type
+    fn swap_red_blue<D: Color>(mut self) -> Image<T, D> {
+        self.data_mut()
+            .chunks_exact_mut(C::CHANNELS)
+            .for_each(|px| px.swap(0, 2));
+        let georeference = self.meta.georeference.take();
+        let mut dest = self.with_color();
+        dest.meta.georeference = georeference;
+        dest
+    }
+
     /// Apply a filter using an Image as output
     pub fn apply<U: Type, D: Color>(
         &mut self,
         filter: impl Filter<U, D, T, C>,
         input: &[&Image<U, D>],
     ) -> &mut Self {
-        filter.eval(input, self);
+        let input = match_colorspaces(input, ColorspaceMismatch::default())
+            .expect("inputs are converted by default");
+        filter.eval(&input.iter().map(|x| x.as_ref()).collect::<Vec<_>>(), self);
+        self
+    }
+
+    /// Apply a filter using an Image as output and the provided `ExecutionOptions`
+    ///
+    /// Panics when `options.colorspace_mismatch` is `ColorspaceMismatch::Error` and the inputs
+    /// are tagged with different colorspaces, use `try_apply_with` to handle that case
+    pub fn apply_with<U: Type, D: Color>(
+        &mut self,
+        options: &ExecutionOptions,
+        filter: impl Filter<U, D, T, C>,
+        input: &[&Image<U, D>],
+    ) -> &mut Self {
+        if let Err(e) = self.try_apply_with(options, filter, input) {
+            panic!("{e}");
+        }
         self
     }
 
+    /// Apply a filter using an Image as output and the provided `ExecutionOptions`, returns
+    /// `Error::ColorspaceMismatch` instead of running the filter when the inputs are tagged with
+    /// different colorspaces and `options.colorspace_mismatch` is `ColorspaceMismatch::Error`
+    pub fn try_apply_with<U: Type, D: Color>(
+        &mut self,
+        options: &ExecutionOptions,
+        filter: impl Filter<U, D, T, C>,
+        input: &[&Image<U, D>],
+    ) -> Result<&mut Self, Error> {
+        let input = match_colorspaces(input, options.colorspace_mismatch)?;
+        let input = input.iter().map(|x| x.as_ref()).collect::<Vec<_>>();
+        filter.eval_with(options, &input, self);
+        Ok(self)
+    }
+
     /// Apply an async filter using an Image as output
     pub async fn apply_async<'a, U: Type, D: Color>(
         &mut self,
@@ -697,6 +1082,17 @@ impl<T: Type, C: Color> Image<T, C> {
         self
     }
 
+    /// Run a filter using the same Image as input and output and the provided
+    /// `ExecutionOptions`
+    pub fn run_in_place_with(
+        &mut self,
+        options: &ExecutionOptions,
+        filter: impl Filter<T, C>,
+    ) -> &mut Self {
+        filter.eval_in_place_with(options, self);
+        self
+    }
+
     /// Run a filter using an Image as input
     pub fn run<U: Type, D: Color>(
         &self,
@@ -713,6 +1109,33 @@ impl<T: Type, C: Color> Image<T, C> {
         dest
     }
 
+    /// Run a filter using an Image as input and the provided `ExecutionOptions`
+    pub fn run_with<U: Type, D: Color>(
+        &self,
+        options: &ExecutionOptions,
+        filter: impl Filter<T, C, U, D>,
+        output: Option<Meta<U, D>>,
+    ) -> Image<U, D> {
+        let size = if let Some(o) = output {
+            o.size
+        } else {
+            self.size()
+        };
+        let mut dest = Image::new(size);
+        dest.apply_with(options, filter, &[self]);
+        dest
+    }
+
+    /// Run a filter that determines its own output size using an Image as input
+    pub fn run_geometry<U: Type, D: Color>(
+        &self,
+        filter: impl GeometryFilter<T, C, U, D>,
+    ) -> Image<U, D> {
+        let mut dest = Image::new(GeometryFilter::output_size(&filter, self.size()));
+        dest.apply(filter, &[self]);
+        dest
+    }
+
     /// Run an async filter using an Image as input
     pub async fn run_async<'a, U: 'a + Type, D: 'a + Color>(
         &self,
@@ -730,14 +1153,167 @@ impl<T: Type, C: Color> Image<T, C> {
         dest
     }
 
-    /// Convert image type/color
+    /// Colorspace of the stored values, this is the tag stored in `meta` when set, otherwise the
+    /// default colorspace of `C`
+    pub fn colorspace(&self) -> Option<Colorspace> {
+        self.meta.colorspace.or(C::COLORSPACE)
+    }
+
+    /// Tag the image with a colorspace without changing any values
+    pub fn with_colorspace(mut self, colorspace: Colorspace) -> Image<T, C> {
+        self.meta.colorspace = (Some(colorspace) != C::COLORSPACE).then_some(colorspace);
+        self
+    }
+
+    /// Convert values to `colorspace` and tag the result, images without a known colorspace are
+    /// returned unchanged
+    pub fn to_colorspace(&self, colorspace: Colorspace) -> Image<T, C> {
+        let src = match self.colorspace() {
+            Some(src) => src,
+            None => return self.clone(),
+        };
+
+        let mut dest = self.clone().with_colorspace(colorspace);
+        if src != colorspace {
+            dest.for_each(|_, mut data| {
+                let mut px = data.to_pixel();
+                src.convert_pixel(&colorspace, &mut px);
+                px.copy_to_slice(&mut data);
+            });
+        }
+        dest
+    }
+
+    /// Source image for a conversion to `D`, a colorspace tag that differs from the default for
+    /// `C` is applied first so the conversion starts from the correct values
+    fn conversion_source<D: Color>(&self) -> std::borrow::Cow<'_, Image<T, C>> {
+        match (self.meta.colorspace, C::COLORSPACE) {
+            (Some(tag), Some(default)) if tag != default && !same_color::<C, D>() => {
+                std::borrow::Cow::Owned(self.to_colorspace(default))
+            }
+            _ => std::borrow::Cow::Borrowed(self),
+        }
+    }
+
+    /// Colorspace tag for the result of a conversion to `D`, the tag is only kept when the color
+    /// type doesn't change
+    fn conversion_tag<D: Color>(&self) -> Option<Colorspace> {
+        if same_color::<C, D>() {
+            self.meta.colorspace
+        } else {
+            None
+        }
+    }
+
+    /// Convert image type/color, when the image is tagged with a colorspace that differs from the
+    /// default for `C` the values are interpreted using the tag
     pub fn convert<U: Type, D: Color>(&self) -> Image<U, D> {
-        self.run(filter::convert(), None)
+        let mut dest = self.conversion_source::<D>().run(filter::convert(), None);
+        dest.meta.colorspace = self.conversion_tag::<D>();
+        dest
     }
 
-    /// Convert image type/color
+    /// Convert the data type without changing the color, unlike `convert` the values are not
+    /// passed through a color conversion. Rows are converted in bulk using `f32` lanes when both
+    /// types fit, which is much faster than `convert` for `f16` images
+    pub fn convert_type<U: Type>(&self) -> Image<U, C> {
+        let mut dest = Image::new(self.size());
+        dest.meta.colorspace = self.meta.colorspace;
+
+        let width_step = self.meta.width_step();
+        let lanes = T::precision() <= f32::precision() && U::precision() <= f32::precision();
+        let convert_row = |(y, row): (usize, &mut [U])| {
+            let src = &self.data()[y * width_step..y * width_step + row.len()];
+            if lanes {
+                let mut tmp = vec![0.0f32; row.len()];
+                T::to_f32_slice(src, &mut tmp);
+                U::from_f32_slice(&tmp, row);
+            } else {
+                let mut tmp = vec![0.0f64; row.len()];
+                T::to_norm_slice(src, &mut tmp);
+                U::from_norm_slice(&tmp, row);
+            }
+        };
+
+        #[cfg(feature = "parallel")]
+        dest.par_rows_mut().for_each(convert_row);
+
+        #[cfg(not(feature = "parallel"))]
+        dest.rows_mut().for_each(convert_row);
+
+        dest
+    }
+
+    /// Convert image type/color, see `convert`
     pub fn convert_to<U: Type, D: Color>(&self, dest: &mut Image<U, D>) {
-        dest.apply(filter::convert(), &[self]);
+        dest.apply(filter::convert(), &[&self.conversion_source::<D>()]);
+        dest.meta.colorspace = self.conversion_tag::<D>();
+    }
+
+    /// Convert image type/color, when `U` is an integer type with less precision than `T`
+    /// triangular noise of up to one quantization step is added before rounding to avoid banding
+    /// in smooth gradients. The alpha channel is rounded without noise
+    pub fn convert_dithered<U: Type, D: Color>(&self) -> Image<U, D> {
+        if U::is_float() || U::precision() >= T::precision() {
+            return self.convert();
+        }
+
+        let tmp: Image<f64, D> = self.convert();
+        let step = 1.0 / (U::MAX - U::MIN);
+        let mut dest = Image::new(self.size());
+        dest.each_pixel_mut(|pt, mut px| {
+            let src = tmp.get_pixel(pt);
+            for c in 0..D::CHANNELS {
+                let noise = if D::ALPHA == Some(c) {
+                    0.0
+                } else {
+                    generate::dither_noise(pt, c)
+                };
+                px[c] = (src[c] + noise * step).clamp(0.0, 1.0);
+            }
+        });
+        dest
+    }
+
+    /// Convert image type/color, returns `Error::LossyConversion` instead of silently clipping
+    /// values, dropping a non-opaque alpha channel or converting to a type with less precision
+    pub fn try_convert<U: Type, D: Color>(&self) -> Result<Image<U, D>, Error> {
+        let mut loss = ConversionLoss {
+            reduced_bit_depth: U::precision() < T::precision(),
+            ..ConversionLoss::default()
+        };
+
+        // Floating point types keep values outside of `[0, 1]`
+        let check_range = !U::is_float();
+        let check_alpha = C::ALPHA.is_some() && D::ALPHA.is_none();
+        if check_range || check_alpha {
+            self.each_pixel(|_, px| {
+                if check_range {
+                    loss.clipped += px
+                        .convert::<D>()
+                        .iter()
+                        .filter(|x| !(0.0..=1.0).contains(*x))
+                        .count();
+                }
+                if check_alpha && px.alpha().is_some_and(|a| a < 1.0) {
+                    loss.alpha_dropped += 1;
+                }
+            });
+        }
+
+        if !loss.is_lossless() {
+            return Err(Error::LossyConversion(loss));
+        }
+        Ok(self.convert())
+    }
+
+    /// Convert image type/color, out-of-gamut colors are handled using `mapping`
+    pub fn convert_mapped<U: Type, D: Color>(&self, mapping: GamutMapping) -> Image<U, D> {
+        let mut dest = self
+            .conversion_source::<D>()
+            .run(filter::convert_mapped(mapping), None);
+        dest.meta.colorspace = self.conversion_tag::<D>();
+        dest
     }
 
     /// Convert to `ImageBuf`
@@ -794,17 +1370,15 @@ impl<T: Type, C: Color> Image<T, C> {
         Ok(dest)
     }
 
-    /// Get image histogram
+    /// Get per-channel histograms of normalized values, see `reduce::Histograms` for other
+    /// value ranges
     pub fn histogram(&self, bins: usize) -> Vec<Histogram> {
-        let mut hist = vec![Histogram::new(bins); C::CHANNELS];
-
-        self.each_pixel(|_, px| {
-            for i in 0..C::CHANNELS {
-                hist[i].add_value(px[i]);
-            }
-        });
+        self.reduce(reduce::Histograms::new(bins))
+    }
 
-        hist
+    /// Get the joint histogram of two channels
+    pub fn histogram2d(&self, a: Channel, b: Channel, bins: usize) -> Histogram2d {
+        self.reduce(reduce::JointHistogram::new(a, b, bins))
     }
 
     /// Gamma correction
@@ -826,21 +1400,196 @@ impl<T: Type, C: Color> Image<T, C> {
         self.gamma(2.2)
     }
 
+    /// Georeference of a resized copy of the image
+    fn scaled_georeference(&self, size: Size) -> Option<Georeference> {
+        self.meta.georeference.as_ref().map(|g| {
+            g.scale(
+                size.width as f64 / self.width() as f64,
+                size.height as f64 / self.height() as f64,
+            )
+        })
+    }
+
     /// Resize an image
     pub fn resize(&self, size: impl Into<Size>) -> Image<T, C> {
         let size = size.into();
-        self.run(filter::resize(self.size(), size), Some(Meta::new(size)))
+        let mut dest = self.run(filter::resize(self.size(), size), Some(Meta::new(size)));
+        dest.meta.georeference = self.scaled_georeference(size);
+        dest
+    }
+
+    /// Resize an image, use `Transfer::Srgb` to resample sRGB encoded images in linear light
+    pub fn resize_with_transfer(
+        &self,
+        size: impl Into<Size>,
+        transfer: transform::Transfer,
+    ) -> Image<T, C> {
+        let size = size.into();
+        let mut dest = self.run(
+            filter::resize_with_transfer(self.size(), size, transfer),
+            Some(Meta::new(size)),
+        );
+        dest.meta.georeference = self.scaled_georeference(size);
+        dest
     }
 
     /// Scale an image
     pub fn scale(&self, width: f64, height: f64) -> Image<T, C> {
-        self.run(
-            filter::scale(width, height),
-            Some(Meta::new((
-                (self.width() as f64 * width) as usize,
-                (self.height() as f64 * height) as usize,
-            ))),
-        )
+        let size = Size::new(
+            (self.width() as f64 * width) as usize,
+            (self.height() as f64 * height) as usize,
+        );
+        let mut dest = self.run(filter::scale(width, height), Some(Meta::new(size)));
+        dest.meta.georeference = self.scaled_georeference(size);
+        dest
+    }
+
+    /// Detect the integer scale of upscaled pixel art, this is the largest `n` such that the image
+    /// is made of `n`x`n` blocks of identical pixels aligned to the top-left corner. Returns `1`
+    /// for images that haven't been upscaled
+    pub fn detect_pixel_scale(&self) -> usize {
+        fn gcd(a: usize, b: usize) -> usize {
+            if b == 0 {
+                a
+            } else {
+                gcd(b, a % b)
+            }
+        }
+
+        let (width, height) = (self.width(), self.height());
+        let mut scale = gcd(width, height);
+        let same = |a: (usize, usize), b: (usize, usize)| self.get(a) == self.get(b);
+
+        // Every run of identical pixels must start and end on a block boundary
+        for y in 0..height {
+            let mut start = 0;
+            for x in 1..=width {
+                if x == width || !same((x, y), (x - 1, y)) {
+                    scale = gcd(scale, x - start);
+                    start = x;
+                }
+                if scale == 1 {
+                    return 1;
+                }
+            }
+        }
+        for x in 0..width {
+            let mut start = 0;
+            for y in 1..=height {
+                if y == height || !same((x, y), (x, y - 1)) {
+                    scale = gcd(scale, y - start);
+                    start = y;
+                }
+                if scale == 1 {
+                    return 1;
+                }
+            }
+        }
+
+        scale.max(1)
+    }
+
+    /// Downscale by an integer factor by taking the top-left pixel of each `factor`x`factor`
+    /// block, this is lossless for pixel art upscaled by `factor` (see `detect_pixel_scale`)
+    pub fn downscale_integer(&self, factor: usize) -> Result<Image<T, C>, Error> {
+        if factor == 0 || self.width() % factor != 0 || self.height() % factor != 0 {
+            return Err(Error::InvalidDimensions(
+                self.width(),
+                self.height(),
+                self.channels(),
+            ));
+        }
+
+        let mut dest = Image::new((self.width() / factor, self.height() / factor));
+        dest.for_each(|pt, mut px| {
+            px.copy_from_slice(self.get((pt.x * factor, pt.y * factor)));
+        });
+        dest.meta.georeference = self.scaled_georeference(dest.size());
+        Ok(dest)
+    }
+
+    /// Upscale pixel art by an integer factor using `filter::xbr`
+    pub fn upscale_xbr(&self, factor: usize) -> Image<T, C> {
+        let factor = factor.max(1);
+        let size = Size::new(self.width() * factor, self.height() * factor);
+        let mut dest = self.run(filter::xbr(factor), Some(Meta::new(size)));
+        dest.meta.georeference = self.scaled_georeference(size);
+        dest
+    }
+
+    /// Average color of each cell of a `grid.width` x `grid.height` grid, in row-major order,
+    /// intended for driving ambient (bias) lighting from screen content
+    ///
+    /// At most 16x16 evenly spaced pixels are sampled from each cell, so the cost depends on the
+    /// grid size rather than the image size, and the averages are smoothed using a 3x3 binomial
+    /// kernel across neighboring cells to soften the transitions between lights
+    pub fn ambient_color_regions(&self, grid: impl Into<Size>) -> Vec<Pixel<C>> {
+        const SAMPLES: usize = 16;
+
+        let grid = grid.into();
+        let (cols, rows) = (grid.width, grid.height);
+        let (w, h) = (self.width(), self.height());
+        if cols == 0 || rows == 0 || w == 0 || h == 0 {
+            return Vec::new();
+        }
+
+        let bounds = |i: usize, n: usize, len: usize| {
+            let start = (i * len / n).min(len - 1);
+            let end = ((i + 1) * len / n).clamp(start + 1, len);
+            (start, end, ((end - start) / SAMPLES).max(1))
+        };
+        let data = self.data();
+        let mut averages = Vec::with_capacity(cols * rows);
+        for gy in 0..rows {
+            let (y0, y1, sy) = bounds(gy, rows, h);
+            for gx in 0..cols {
+                let (x0, x1, sx) = bounds(gx, cols, w);
+                let mut sum = Pixel::<C>::new();
+                let mut count = 0usize;
+                for y in (y0..y1).step_by(sy) {
+                    let row = &data[y * w * C::CHANNELS..][..w * C::CHANNELS];
+                    for x in (x0..x1).step_by(sx) {
+                        let px = &row[x * C::CHANNELS..][..C::CHANNELS];
+                        for (c, v) in px.iter().enumerate() {
+                            sum[c] += v.to_norm();
+                        }
+                        count += 1;
+                    }
+                }
+                for c in 0..C::CHANNELS {
+                    sum[c] /= count as f64;
+                }
+                averages.push(sum);
+            }
+        }
+
+        let weight = [1.0, 2.0, 1.0];
+        let mut dest = Vec::with_capacity(cols * rows);
+        for gy in 0..rows {
+            for gx in 0..cols {
+                let mut px = Pixel::new();
+                let mut total = 0.0;
+                for (dy, wy) in weight.iter().enumerate() {
+                    for (dx, wx) in weight.iter().enumerate() {
+                        let (x, y) = ((gx + dx).checked_sub(1), (gy + dy).checked_sub(1));
+                        if let (Some(x), Some(y)) = (x, y) {
+                            if x < cols && y < rows {
+                                let avg = &averages[y * cols + x];
+                                for c in 0..C::CHANNELS {
+                                    px[c] += avg[c] * wx * wy;
+                                }
+                                total += wx * wy;
+                            }
+                        }
+                    }
+                }
+                for c in 0..C::CHANNELS {
+                    px[c] /= total;
+                }
+                dest.push(px);
+            }
+        }
+        dest
     }
 
     /// Image data
@@ -853,3 +1602,133 @@ impl<T: Type, C: Color> Image<T, C> {
         self.data.data_mut()
     }
 }
+
+impl<T: Type> Image<T, Rgb> {
+    /// Convert to BGR in place
+    pub fn into_bgr(self) -> Image<T, Bgr> {
+        self.swap_red_blue()
+    }
+}
+
+impl<T: Type> Image<T, Bgr> {
+    /// Convert to RGB in place
+    pub fn into_rgb(self) -> Image<T, Rgb> {
+        self.swap_red_blue()
+    }
+}
+
+impl<T: Type> Image<T, Rgba> {
+    /// Convert to BGRA in place, alpha is preserved
+    pub fn into_bgra(self) -> Image<T, Bgra> {
+        self.swap_red_blue()
+    }
+
+    /// Composite the image over an opaque background color, the image has straight (not
+    /// premultiplied) alpha and blending is performed in linear RGB
+    pub fn flatten_onto(&self, background: &Pixel<Rgb>) -> Image<T, Rgb> {
+        self.flatten_with(false, |_| background.clone())
+    }
+
+    /// Like `flatten_onto` for images with premultiplied alpha
+    pub fn flatten_premultiplied_onto(&self, background: &Pixel<Rgb>) -> Image<T, Rgb> {
+        self.flatten_with(true, |_| background.clone())
+    }
+
+    /// Composite the image over a light and dark gray checkerboard with square cells of
+    /// `cell_size` pixels, the usual way of previewing transparency
+    pub fn preview_checkerboard(&self, cell_size: usize) -> Image<T, Rgb> {
+        let cell_size = cell_size.max(1);
+        let mut light = Pixel::new();
+        light.fill(srgb_to_linear(1.0));
+        let mut dark = Pixel::new();
+        dark.fill(srgb_to_linear(0.8));
+        self.flatten_with(false, |pt| {
+            if (pt.x / cell_size + pt.y / cell_size) % 2 == 0 {
+                light.clone()
+            } else {
+                dark.clone()
+            }
+        })
+    }
+
+    fn flatten_with(
+        &self,
+        premultiplied: bool,
+        background: impl Sync + Fn(Point) -> Pixel<Rgb>,
+    ) -> Image<T, Rgb> {
+        let mut dest = self.new_like_with_color::<Rgb>();
+        self.each_pixel(|pt, px| {
+            let alpha = px[3].clamp(0.0, 1.0);
+            let mut out = background(pt);
+            for c in 0..3 {
+                let color = if premultiplied { px[c] } else { px[c] * alpha };
+                out[c] = color + out[c] * (1.0 - alpha);
+            }
+            dest.set_pixel(pt, &out);
+        });
+        dest
+    }
+}
+
+impl<T: Type> Image<T, Bgra> {
+    /// Convert to RGBA in place, alpha is preserved
+    pub fn into_rgba(self) -> Image<T, Rgba> {
+        self.swap_red_blue()
+    }
+}
+
+/// Reverse the byte order of each `T` in `bytes`
+fn swap_bytes<T: Type>(bytes: &mut [u8]) {
+    bytes
+        .chunks_exact_mut(std::mem::size_of::<T>())
+        .for_each(|x| x.reverse());
+}
+
+/// Returns true when `A` and `B` are the same color type
+fn same_color<A: Color, B: Color>() -> bool {
+    std::any::type_name::<A>() == std::any::type_name::<B>()
+}
+
+/// Bring filter inputs into the colorspace of the first input with a known colorspace according
+/// to `policy`, inputs without a known colorspace are used as-is
+fn match_colorspaces<'a, T: Type, C: Color>(
+    input: &[&'a Image<T, C>],
+    policy: ColorspaceMismatch,
+) -> Result<Vec<std::borrow::Cow<'a, Image<T, C>>>, Error> {
+    let unchanged = || {
+        Ok(input
+            .iter()
+            .map(|x| std::borrow::Cow::Borrowed(*x))
+            .collect())
+    };
+    let target = match input.iter().find_map(|x| x.colorspace()) {
+        Some(target) => target,
+        None => return unchanged(),
+    };
+    let mismatch = |x: &Image<T, C>| x.colorspace().is_some_and(|c| c != target);
+    let found = input
+        .iter()
+        .find_map(|x| x.colorspace().filter(|c| *c != target));
+    let found = match found {
+        Some(found) if policy != ColorspaceMismatch::Ignore => found,
+        _ => return unchanged(),
+    };
+
+    if policy == ColorspaceMismatch::Error {
+        return Err(Error::ColorspaceMismatch {
+            expected: target,
+            found,
+        });
+    }
+
+    Ok(input
+        .iter()
+        .map(|x| {
+            if mismatch(x) {
+                std::borrow::Cow::Owned(x.to_colorspace(target))
+            } else {
+                std::borrow::Cow::Borrowed(*x)
+            }
+        })
+        .collect())
+}
@@ -32,22 +32,12 @@ where
 
     /// Get byte slice
     fn buffer(&self) -> &[u8] {
-        unsafe {
-            std::slice::from_raw_parts(
-                self.as_ref().as_ptr() as *const u8,
-                self.as_ref().len() * std::mem::size_of::<T>(),
-            )
-        }
+        bytemuck::cast_slice(self.as_ref())
     }
 
     /// Get mutable byte slice
     fn buffer_mut(&mut self) -> &mut [u8] {
-        unsafe {
-            std::slice::from_raw_parts_mut(
-                self.as_mut().as_ptr() as *mut u8,
-                self.as_mut().len() * std::mem::size_of::<T>(),
-            )
-        }
+        bytemuck::cast_slice_mut(self.as_mut())
     }
 
     /// Convert from ImageData to Vec
@@ -217,10 +207,195 @@ pub mod mmap {
             filename: impl AsRef<std::path::Path>,
         ) -> Result<Image<T, C>, Error> {
             let (data, meta) = Self::load::<C>(filename)?;
+            num_values(meta.size(), C::CHANNELS)?;
             Image::new_with_data(meta.size(), data)
         }
     }
 
+    impl<T: Type> Mmap<T> {
+        /// Map `len` values starting at `offset` bytes into an existing file. The mapping is
+        /// copy-on-write, so the file is never modified
+        pub fn open_copy(
+            filename: impl AsRef<std::path::Path>,
+            offset: u64,
+            len: usize,
+        ) -> Result<Mmap<T>, Error> {
+            let file = std::fs::File::open(filename)?;
+            let num_bytes = len
+                .checked_mul(std::mem::size_of::<T>())
+                .filter(|n| offset.checked_add(*n as u64).is_some())
+                .ok_or(Error::InvalidDimensions(len, 1, 1))?;
+            if file.metadata()?.len() < offset + num_bytes as u64 {
+                return Err(Error::Message("file is too short".into()));
+            }
+            if offset % std::mem::align_of::<T>() as u64 != 0 {
+                return Err(Error::Cast(
+                    bytemuck::PodCastError::TargetAlignmentGreaterAndInputNotAligned,
+                ));
+            }
+
+            let inner = unsafe {
+                MmapOptions::new()
+                    .offset(offset)
+                    .len(num_bytes)
+                    .map_copy(&file)?
+            };
+            Ok(Self {
+                inner,
+                _t: std::marker::PhantomData,
+            })
+        }
+
+        /// Map the pixel data of an uncompressed image file, see `Image::open_mmap`
+        pub fn open_image<C: Color>(
+            filename: impl AsRef<std::path::Path>,
+        ) -> Result<Image<T, C>, Error> {
+            let path = filename.as_ref();
+            let mut header = Vec::with_capacity(4096);
+            std::fs::File::open(path)?
+                .take(1 << 16)
+                .read_to_end(&mut header)?;
+
+            let (size, offset) = if header.starts_with(b"img2") {
+                Self::read_header::<C>(header.as_slice())
+                    .map(|meta| (meta.size(), Self::header_len()))
+                    .map_err(|e| e.to_string())
+            } else if header.starts_with(b"P5") || header.starts_with(b"P6") {
+                pnm_layout::<T, C>(&header)
+            } else if header.starts_with(b"II*\0") {
+                tiff_layout::<T, C>(path)
+            } else {
+                Err("unsupported format".to_string())
+            }
+            .map_err(|reason| Error::Decode {
+                path: path.to_path_buf(),
+                format: crate::io::extension(path),
+                reason,
+            })?;
+
+            let data = Self::open_copy(path, offset, num_values(size, C::CHANNELS)?)?;
+            Image::new_with_data(size, data)
+        }
+    }
+
+    /// Number of values in an image, fails instead of overflowing for sizes read from a header
+    pub(crate) fn num_values(size: Size, channels: usize) -> Result<usize, Error> {
+        size.width
+            .checked_mul(size.height)
+            .and_then(|n| n.checked_mul(channels))
+            .ok_or(Error::InvalidDimensions(size.width, size.height, channels))
+    }
+
+    /// Size and data offset of a binary PNM file
+    fn pnm_layout<T: Type, C: Color>(header: &[u8]) -> Result<(Size, u64), String> {
+        // Magic number followed by width, height and maxval separated by whitespace and comments
+        let mut fields = Vec::with_capacity(3);
+        let mut i = 2;
+        while fields.len() < 3 {
+            match header.get(i) {
+                Some(b'#') => {
+                    while header.get(i).is_some_and(|c| *c != b'\n') {
+                        i += 1;
+                    }
+                }
+                Some(c) if c.is_ascii_whitespace() => i += 1,
+                Some(c) if c.is_ascii_digit() => {
+                    let start = i;
+                    while header.get(i).is_some_and(u8::is_ascii_digit) {
+                        i += 1;
+                    }
+                    let field = std::str::from_utf8(&header[start..i]).unwrap_or_default();
+                    fields.push(field.parse::<usize>().map_err(|e| e.to_string())?);
+                }
+                _ => return Err("invalid header".into()),
+            }
+        }
+
+        // A single whitespace character separates the header from the data
+        let offset = i as u64 + 1;
+        let channels = if header[1] == b'5' { 1 } else { 3 };
+        if channels != C::CHANNELS {
+            return Err(format!("expected {} channels", C::CHANNELS));
+        }
+        if fields[2] > 255 || T::BASE != crate::io::BaseType::UInt8 {
+            // 16-bit PNM data is big-endian and can't be used without converting it
+            return Err("only 8-bit PNM data can be mapped".into());
+        }
+        Ok((Size::new(fields[0], fields[1]), offset))
+    }
+
+    /// Size and data offset of a little-endian, uncompressed TIFF file with interleaved
+    /// channels stored in contiguous strips
+    fn tiff_layout<T: Type, C: Color>(path: &std::path::Path) -> Result<(Size, u64), String> {
+        use std::io::{Seek, SeekFrom};
+
+        // The IFD can be anywhere in the file, read only the parts that are needed
+        let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        let mut read = |offset: usize, buf: &mut [u8]| {
+            file.seek(SeekFrom::Start(offset as u64))
+                .and_then(|_| file.read_exact(buf))
+                .map_err(|_| "truncated file".to_string())
+        };
+        let mut uint_at = |i: usize, width: usize| {
+            let mut b = [0; 4];
+            read(i, &mut b[..width]).map(|_| u32::from_le_bytes(b))
+        };
+
+        // Tag values, arrays are only needed for the strip offsets and byte counts
+        let ifd = uint_at(4, 4)? as usize;
+        let mut tags = std::collections::BTreeMap::new();
+        for n in 0..uint_at(ifd, 2)? as usize {
+            let entry = ifd + 2 + n * 12;
+            let tag = uint_at(entry, 2)? as u16;
+            let width = if uint_at(entry + 2, 2)? == 3 { 2 } else { 4 };
+            let count = uint_at(entry + 4, 4)? as usize;
+            let values = if count * width <= 4 {
+                entry + 8
+            } else {
+                uint_at(entry + 8, 4)? as usize
+            };
+            let values = (0..count)
+                .map(|i| uint_at(values + i * width, width).map(|x| x as u64))
+                .collect::<Result<Vec<u64>, String>>()?;
+            tags.insert(tag, values);
+        }
+
+        let get = |tag: u16, default: Option<u64>| -> Result<u64, String> {
+            tags.get(&tag)
+                .and_then(|v| v.first().copied())
+                .or(default)
+                .ok_or_else(|| format!("missing tag {}", tag))
+        };
+        let width = get(256, None)? as usize;
+        let height = get(257, None)? as usize;
+        let bits = get(258, Some(1))?;
+        let channels = get(277, Some(1))? as usize;
+        let float = get(339, Some(1))? == 3;
+        if get(259, Some(1))? != 1 {
+            return Err("compressed data can't be mapped".into());
+        }
+        if channels > 1 && get(284, Some(1))? != 1 {
+            return Err("planar data can't be mapped".into());
+        }
+        if channels != C::CHANNELS {
+            return Err(format!("expected {} channels", C::CHANNELS));
+        }
+        if bits as usize != T::bits() || float != T::is_float() {
+            return Err("sample type doesn't match".into());
+        }
+
+        let offsets = tags.get(&273).ok_or("missing strip offsets")?;
+        let counts = tags.get(&279).ok_or("missing strip byte counts")?;
+        let contiguous = offsets
+            .windows(2)
+            .zip(counts.iter())
+            .all(|(o, count)| o[0] + count == o[1]);
+        if offsets.is_empty() || !contiguous {
+            return Err("strips aren't contiguous".into());
+        }
+        Ok((Size::new(width, height), offsets[0]))
+    }
+
     impl<T: Type> AsRef<[T]> for Mmap<T> {
         fn as_ref(&self) -> &[T] {
             unsafe {
@@ -0,0 +1,376 @@
+use crate::*;
+
+const IMPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2";
+const EXPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2.1";
+
+const UNDEFINED_LENGTH: u32 = 0xffffffff;
+
+const ITEM: (u16, u16) = (0xfffe, 0xe000);
+const ITEM_DELIMITER: (u16, u16) = (0xfffe, 0xe00d);
+const SEQUENCE_DELIMITER: (u16, u16) = (0xfffe, 0xe0dd);
+
+const TRANSFER_SYNTAX: (u16, u16) = (0x0002, 0x0010);
+const SAMPLES_PER_PIXEL: (u16, u16) = (0x0028, 0x0002);
+const ROWS: (u16, u16) = (0x0028, 0x0010);
+const COLUMNS: (u16, u16) = (0x0028, 0x0011);
+const BITS_ALLOCATED: (u16, u16) = (0x0028, 0x0100);
+const PIXEL_REPRESENTATION: (u16, u16) = (0x0028, 0x0103);
+const WINDOW_CENTER: (u16, u16) = (0x0028, 0x1050);
+const WINDOW_WIDTH: (u16, u16) = (0x0028, 0x1051);
+const RESCALE_INTERCEPT: (u16, u16) = (0x0028, 0x1052);
+const RESCALE_SLOPE: (u16, u16) = (0x0028, 0x1053);
+const PIXEL_DATA: (u16, u16) = (0x7fe0, 0x0010);
+
+/// Maximum nesting of undefined length sequences and items, deeper files are rejected
+const MAX_SEQUENCE_DEPTH: usize = 64;
+
+fn error(msg: &str) -> Error {
+    Error::CannotReadImage(format!("DICOM: {msg}"))
+}
+
+struct Element<'a> {
+    tag: (u16, u16),
+    value: Option<&'a [u8]>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    explicit: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if self.data.len() - self.pos < n {
+            return Err(error("unexpected end of file"));
+        }
+        let b = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(b)
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn is_done(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    /// Read the next element, the value is `None` when the length is undefined, in which case
+    /// the reader is positioned at the first nested item
+    fn element(&mut self) -> Result<Element<'a>, Error> {
+        let tag = (self.u16()?, self.u16()?);
+
+        // Item and delimitation tags never have a VR
+        if tag.0 == 0xfffe {
+            let len = self.u32()?;
+            let value = if len == UNDEFINED_LENGTH {
+                None
+            } else {
+                Some(self.bytes(len as usize)?)
+            };
+            return Ok(Element { tag, value });
+        }
+
+        let len = if self.explicit || tag.0 == 0x0002 {
+            let vr = self.bytes(2)?;
+            match vr {
+                b"OB" | b"OD" | b"OF" | b"OL" | b"OV" | b"OW" | b"SQ" | b"SV" | b"UC" | b"UN"
+                | b"UR" | b"UT" | b"UV" => {
+                    self.bytes(2)?;
+                    self.u32()?
+                }
+                _ => self.u16()? as u32,
+            }
+        } else {
+            self.u32()?
+        };
+
+        let value = if len == UNDEFINED_LENGTH {
+            None
+        } else {
+            Some(self.bytes(len as usize)?)
+        };
+        Ok(Element { tag, value })
+    }
+
+    /// Skip the contents of an undefined length sequence or item, nested sequences are tracked
+    /// using an explicit stack of delimiters limited to `MAX_SEQUENCE_DEPTH`
+    fn skip_undefined(&mut self, end: (u16, u16)) -> Result<(), Error> {
+        let mut ends = vec![end];
+        while let Some(&end) = ends.last() {
+            let elem = self.element()?;
+            if elem.tag == end {
+                ends.pop();
+                continue;
+            }
+            if elem.value.is_none() {
+                if ends.len() >= MAX_SEQUENCE_DEPTH {
+                    return Err(error("sequences are nested too deeply"));
+                }
+                ends.push(if elem.tag == ITEM {
+                    ITEM_DELIMITER
+                } else {
+                    SEQUENCE_DELIMITER
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn string(value: &[u8]) -> &str {
+    std::str::from_utf8(value)
+        .unwrap_or_default()
+        .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+}
+
+/// Parse the first value of a multi-valued decimal string
+fn decimal(value: &[u8]) -> Option<f64> {
+    string(value).split('\\').next()?.trim().parse().ok()
+}
+
+fn unsigned(value: &[u8]) -> Option<u16> {
+    Some(u16::from_le_bytes([*value.first()?, *value.get(1)?]))
+}
+
+/// Decode an uncompressed, little endian, single channel DICOM file
+///
+/// Signed pixel data is offset by `32768` to fit into `u16`, the rescale intercept in
+/// `meta.window_level` is adjusted to compensate. `MONOCHROME1` images are not inverted.
+pub fn decode(data: &[u8]) -> Result<Image<u16, Gray>, Error> {
+    // Files written without the optional preamble start directly with the dataset
+    let start = if data.len() >= 132 && &data[128..132] == b"DICM" {
+        132
+    } else {
+        0
+    };
+    let mut reader = Reader {
+        data,
+        pos: start,
+        explicit: start > 0,
+    };
+
+    let mut size = (0, 0);
+    let mut samples_per_pixel = 1;
+    let mut bits_allocated = 16;
+    let mut signed = false;
+    let mut center = None;
+    let mut width = None;
+    let mut slope = 1.0;
+    let mut intercept = 0.0;
+    let mut pixels = None;
+
+    while !reader.is_done() {
+        let elem = reader.element()?;
+        let value = match elem.value {
+            Some(value) => value,
+            None if elem.tag == PIXEL_DATA => {
+                return Err(error("compressed pixel data is not supported"))
+            }
+            None => {
+                let end = if elem.tag == ITEM {
+                    ITEM_DELIMITER
+                } else {
+                    SEQUENCE_DELIMITER
+                };
+                reader.skip_undefined(end)?;
+                continue;
+            }
+        };
+
+        match elem.tag {
+            TRANSFER_SYNTAX => match string(value) {
+                IMPLICIT_VR_LITTLE_ENDIAN => reader.explicit = false,
+                EXPLICIT_VR_LITTLE_ENDIAN => reader.explicit = true,
+                syntax => return Err(error(&format!("unsupported transfer syntax {syntax}"))),
+            },
+            SAMPLES_PER_PIXEL => samples_per_pixel = unsigned(value).unwrap_or(1),
+            ROWS => size.1 = unsigned(value).unwrap_or(0) as usize,
+            COLUMNS => size.0 = unsigned(value).unwrap_or(0) as usize,
+            BITS_ALLOCATED => bits_allocated = unsigned(value).unwrap_or(0),
+            PIXEL_REPRESENTATION => signed = unsigned(value) == Some(1),
+            WINDOW_CENTER => center = decimal(value),
+            WINDOW_WIDTH => width = decimal(value),
+            RESCALE_SLOPE => slope = decimal(value).unwrap_or(1.0),
+            RESCALE_INTERCEPT => intercept = decimal(value).unwrap_or(0.0),
+            PIXEL_DATA => {
+                // Only the first frame is read
+                pixels = Some(value);
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    let pixels = pixels.ok_or_else(|| error("missing pixel data"))?;
+    if samples_per_pixel != 1 {
+        return Err(Error::InvalidDimensions(
+            size.0,
+            size.1,
+            samples_per_pixel as usize,
+        ));
+    }
+
+    let bytes_per_sample = match bits_allocated {
+        8 => 1,
+        16 => 2,
+        n => return Err(error(&format!("unsupported bits allocated: {n}"))),
+    };
+    if size.0 == 0 || size.1 == 0 || pixels.len() < size.0 * size.1 * bytes_per_sample {
+        return Err(Error::InvalidDimensions(size.0, size.1, 1));
+    }
+
+    let offset: u16 = match (bits_allocated, signed) {
+        (_, false) => 0,
+        (8, true) => 128,
+        (_, true) => 32768,
+    };
+    let mut image = Image::<u16, Gray>::new(size);
+    for (i, dest) in image.data.data_mut().iter_mut().enumerate() {
+        let v = if bytes_per_sample == 1 {
+            pixels[i] as u16
+        } else {
+            u16::from_le_bytes([pixels[i * 2], pixels[i * 2 + 1]])
+        };
+        *dest = v ^ offset;
+    }
+
+    // Keep `stored * slope + intercept` equal to the original modality value
+    if offset > 0 {
+        intercept -= offset as f64 * slope;
+    }
+
+    let window = match (center, width) {
+        (Some(center), Some(width)) => filter::WindowLevel::new(center, width),
+        _ => {
+            // Default to the full range of the stored values
+            let (lo, hi) = image
+                .data
+                .data()
+                .iter()
+                .fold((u16::MAX, 0), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+            let (lo, hi) = (lo as f64 * slope + intercept, hi as f64 * slope + intercept);
+            let (lo, hi) = (lo.min(hi), lo.max(hi));
+            filter::WindowLevel::new((lo + hi) / 2.0, (hi - lo).max(1.0))
+        }
+    };
+    image.meta.window_level = Some(window.with_rescale(slope, intercept));
+    Ok(image)
+}
+
+/// Read a DICOM file, see `decode`
+pub fn read(path: impl AsRef<std::path::Path>) -> Result<Image<u16, Gray>, Error> {
+    let data = std::fs::read(path)?;
+    decode(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(out: &mut Vec<u8>, tag: (u16, u16), vr: Option<&[u8; 2]>, value: &[u8]) {
+        out.extend(tag.0.to_le_bytes());
+        out.extend(tag.1.to_le_bytes());
+        match vr {
+            Some(vr @ (b"OW" | b"SQ")) => {
+                out.extend(vr);
+                out.extend([0, 0]);
+                out.extend((value.len() as u32).to_le_bytes());
+            }
+            Some(vr) => {
+                out.extend(vr);
+                out.extend((value.len() as u16).to_le_bytes());
+            }
+            None => out.extend((value.len() as u32).to_le_bytes()),
+        }
+        out.extend(value);
+    }
+
+    fn file(explicit: bool, signed: bool, pixels: &[i16]) -> Vec<u8> {
+        let mut out = vec![0; 128];
+        out.extend(b"DICM");
+        let syntax = if explicit {
+            b"1.2.840.10008.1.2.1\0".as_slice()
+        } else {
+            b"1.2.840.10008.1.2\0".as_slice()
+        };
+        element(&mut out, TRANSFER_SYNTAX, Some(b"UI"), syntax);
+
+        let vr = |vr| if explicit { Some(vr) } else { None };
+
+        // Undefined length sequence that should be skipped
+        out.extend([0x08, 0x00, 0x15, 0x11]);
+        if explicit {
+            out.extend(b"SQ\0\0");
+        }
+        out.extend(UNDEFINED_LENGTH.to_le_bytes());
+        out.extend([0xfe, 0xff, 0x00, 0xe0]);
+        out.extend(UNDEFINED_LENGTH.to_le_bytes());
+        element(&mut out, (0x0008, 0x1150), vr(b"UI"), b"1.2\0");
+        out.extend([0xfe, 0xff, 0x0d, 0xe0, 0, 0, 0, 0]);
+        out.extend([0xfe, 0xff, 0xdd, 0xe0, 0, 0, 0, 0]);
+
+        element(&mut out, ROWS, vr(b"US"), &1u16.to_le_bytes());
+        element(&mut out, COLUMNS, vr(b"US"), &3u16.to_le_bytes());
+        element(&mut out, BITS_ALLOCATED, vr(b"US"), &16u16.to_le_bytes());
+        element(
+            &mut out,
+            PIXEL_REPRESENTATION,
+            vr(b"US"),
+            &(signed as u16).to_le_bytes(),
+        );
+        element(&mut out, WINDOW_CENTER, vr(b"DS"), b"40\\400 ");
+        element(&mut out, WINDOW_WIDTH, vr(b"DS"), b"80\\2000");
+        element(&mut out, RESCALE_INTERCEPT, vr(b"DS"), b"-1024 ");
+        element(&mut out, RESCALE_SLOPE, vr(b"DS"), b"1 ");
+
+        let data: Vec<u8> = pixels.iter().flat_map(|p| p.to_le_bytes()).collect();
+        element(&mut out, PIXEL_DATA, vr(b"OW"), &data);
+        out
+    }
+
+    #[test]
+    fn test_dicom_decode() {
+        for explicit in [true, false] {
+            let image = decode(&file(explicit, false, &[1024, 1064, 2000])).unwrap();
+            assert_eq!(image.size(), Size::new(3, 1));
+            assert_eq!(image.data.data(), &[1024, 1064, 2000]);
+
+            let window = image.meta.window_level.unwrap();
+            assert_eq!(window.center, 40.0);
+            assert_eq!(window.width, 80.0);
+            assert_eq!(window.intercept, -1024.0);
+            assert_eq!(window.map(1064.0), 0.5);
+            assert_eq!(window.map(1024.0), 0.0);
+            assert_eq!(window.map(2000.0), 1.0);
+        }
+
+        // Signed values are offset into the `u16` range without changing rescaled values
+        let image = decode(&file(true, true, &[-1000, 40, 1000])).unwrap();
+        assert_eq!(image.data.data(), &[31768, 32808, 33768]);
+        let window = image.meta.window_level.unwrap();
+        assert_eq!(window.intercept, -1024.0 - 32768.0);
+
+        assert!(matches!(
+            decode(&file(true, true, &[-1000, 40])),
+            Err(Error::InvalidDimensions(3, 1, 1))
+        ));
+        assert!(decode(&[0; 16]).is_err());
+
+        // Deeply nested sequences are rejected instead of overflowing the stack
+        let mut nested = Vec::new();
+        for _ in 0..100_000 {
+            nested.extend([0x08, 0x00, 0x15, 0x11]);
+            nested.extend(UNDEFINED_LENGTH.to_le_bytes());
+        }
+        assert!(decode(&nested).is_err());
+    }
+}
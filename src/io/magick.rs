@@ -4,7 +4,8 @@ use std::path::Path;
 use std::process::{Command, Stdio};
 use std::usize;
 
-use crate::{Color, Image, Rgb, Type};
+use super::EncodeOptions;
+use crate::{Color, DeepImage, Image, Rgb, Type};
 
 /// Magick I/O errors
 #[derive(Debug, thiserror::Error)]
@@ -55,6 +56,30 @@ fn depth<T: Type, C: Color>(cmd: &mut Command) {
     }
 }
 
+fn encode_options(cmd: &mut Command, path: &Path, options: &EncodeOptions) {
+    if let Some(quality) = options.effective_quality() {
+        cmd.arg("-quality").arg(quality.to_string());
+    }
+
+    match super::extension(path).as_str() {
+        "avif" | "heic" | "heif" => {
+            if let Some(speed) = options.speed {
+                cmd.arg("-define")
+                    .arg(format!("heic:speed={}", speed.min(9)));
+            }
+        }
+        "jxl" => {
+            if let Some(effort) = options.jxl_effort() {
+                cmd.arg("-define").arg(format!("jxl:effort={}", effort));
+            }
+        }
+        "webp" if options.lossless => {
+            cmd.args(["-define", "webp:lossless=true"]);
+        }
+        _ => (),
+    }
+}
+
 /// ImageMagick
 pub const IM: Magick = Magick {
     identify: &["identify"],
@@ -88,13 +113,31 @@ pub fn set_default(magick: Magick) {
     }
 }
 
-const ALLOWED_COLORS: &[&str] = &["rgb", "rgba", "gray", "graya", "yuv", "cmyk"];
+/// Arguments passing resource limits to a subprocess, each limit is `(name, value)`
+fn limit_args(limits: &[(&str, usize)]) -> Vec<String> {
+    limits
+        .iter()
+        .flat_map(|(name, value)| ["-limit".to_string(), name.to_string(), value.to_string()])
+        .collect()
+}
+
+const ALLOWED_COLORS: &[&str] = &["rgb", "rgba", "bgr", "bgra", "gray", "graya", "yuv", "cmyk"];
 
 impl Magick {
     /// Get size of image using identify command
     pub fn get_image_shape<P: AsRef<Path>>(&self, path: P) -> Result<(usize, usize), Error> {
+        self.image_shape(path, &[])
+    }
+
+    /// Get size of image using identify command started with the given resource limits
+    fn image_shape<P: AsRef<Path>>(
+        &self,
+        path: P,
+        limits: &[(&str, usize)],
+    ) -> Result<(usize, usize), Error> {
         let identify = Command::new(self.identify[0])
             .args(self.identify[1..].iter())
+            .args(limit_args(limits))
             .args(&["-format", "%w %h"])
             .arg(path.as_ref())
             .output();
@@ -126,19 +169,59 @@ impl Magick {
 
     /// Read image from disk using ImageMagick/GraphicsMagick
     pub fn read<P: AsRef<Path>, T: Type, C: Color>(&self, path: P) -> Result<Image<T, C>, Error> {
+        self.read_limited(path, &[])
+    }
+
+    /// Read image from disk using ImageMagick/GraphicsMagick, the limits in `options` are passed
+    /// to every subprocess using `-limit` so decoding stays bounded even when the file changes
+    /// after it was probed
+    pub fn read_with_options<P: AsRef<Path>, T: Type, C: Color>(
+        &self,
+        path: P,
+        options: &super::DecodeOptions,
+    ) -> Result<Image<T, C>, Error> {
+        // GraphicsMagick calls the pixel count limit `pixels` instead of `area`
+        let area = if self.convert[0] == "gm" {
+            "pixels"
+        } else {
+            "area"
+        };
+        let pixels = options
+            .max_width
+            .zip(options.max_height)
+            .and_then(|(w, h)| w.checked_mul(h));
+        let limits: Vec<(&str, usize)> = [
+            ("width", options.max_width),
+            ("height", options.max_height),
+            (area, pixels),
+            ("memory", options.max_bytes),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?)))
+        .collect();
+        self.read_limited(path, &limits)
+    }
+
+    fn read_limited<P: AsRef<Path>, T: Type, C: Color>(
+        &self,
+        path: P,
+        limits: &[(&str, usize)],
+    ) -> Result<Image<T, C>, Error> {
         if !ALLOWED_COLORS.contains(&C::NAME) {
-            let image: Image<T, Rgb> = self.read(path)?;
+            let image: Image<T, Rgb> = self.read_limited(path, limits)?;
             return Ok(image.convert());
         }
 
-        let (width, height) = match self.get_image_shape(&path) {
+        let (width, height) = match self.image_shape(&path, limits) {
             Ok((width, height)) => (width, height),
             Err(e) => return Err(e),
         };
 
         let kind = kind::<C>();
         let mut cmd = Command::new(self.convert[0]);
-        cmd.args(self.convert[1..].iter()).arg(path.as_ref());
+        cmd.args(self.convert[1..].iter())
+            .args(limit_args(limits))
+            .arg(path.as_ref());
         depth::<T, C>(&mut cmd);
         cmd.arg(kind);
 
@@ -166,10 +249,24 @@ impl Magick {
         &self,
         path: P,
         image: &Image<T, C>,
+    ) -> Result<(), Error> {
+        self.write_with_options(path, image, &EncodeOptions::default())
+    }
+
+    /// Write image to disk using ImageMagick/GraphicsMagick with the provided encoder options
+    pub fn write_with_options<P: AsRef<Path>, T: Type, C: Color>(
+        &self,
+        path: P,
+        image: &Image<T, C>,
+        options: &EncodeOptions,
     ) -> Result<(), Error> {
         if !ALLOWED_COLORS.contains(&C::NAME) {
             let image: Image<T, Rgb> = image.convert();
-            return self.write(path, &image);
+            return self.write_with_options(path, &image, options);
+        }
+
+        if let Some(image) = super::dither_for_output(path.as_ref(), image, options) {
+            return self.write_with_options(path, &image, options);
         }
 
         let kind = kind::<C>();
@@ -178,9 +275,9 @@ impl Magick {
         let mut cmd = Command::new(self.convert[0]);
         cmd.args(self.convert[1..].iter()).stdin(Stdio::piped());
         depth::<T, C>(&mut cmd);
-        cmd.args(&["-size", size.as_str()])
-            .arg(kind)
-            .arg(path.as_ref());
+        cmd.args(&["-size", size.as_str()]).arg(kind);
+        encode_options(&mut cmd, path.as_ref(), options);
+        cmd.arg(path.as_ref());
 
         let mut proc = match cmd.spawn() {
             Ok(c) => c,
@@ -202,6 +299,113 @@ impl Magick {
         }
     }
 
+    /// List file extensions that can be written, based on `convert -list format`
+    pub fn supported_formats(&self) -> Vec<String> {
+        let mut formats: Vec<String> = self
+            .formats()
+            .into_iter()
+            .filter(|format| format.write)
+            .map(|format| format.name)
+            .collect();
+        formats.sort();
+        formats.dedup();
+        formats
+    }
+
+    /// List formats that can be read or written, based on `convert -list format`
+    pub fn formats(&self) -> Vec<super::FormatInfo> {
+        let output = match Command::new(self.convert[0])
+            .args(self.convert[1..].iter())
+            .args(["-list", "format"])
+            .output()
+        {
+            Ok(output) => output,
+            Err(_) => return Vec::new(),
+        };
+
+        let is_mode = |s: &str| {
+            let b = s.as_bytes();
+            b.len() == 3 && matches!(b[0], b'r' | b'-') && matches!(b[1], b'w' | b'-')
+        };
+
+        // Each format is listed as `NAME [MODULE] MODE DESCRIPTION`
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let mut words = line.split_whitespace();
+                let name = words.next()?;
+                let mode = words.take(2).find(|w| is_mode(w))?.as_bytes();
+                let name = name.trim_end_matches('*').to_ascii_lowercase();
+                Some(super::FormatInfo::new(
+                    name.clone(),
+                    vec![name],
+                    mode[0] == b'r',
+                    mode[1] == b'w',
+                ))
+            })
+            .collect()
+    }
+
+    /// Read image properties using the identify command without decoding pixels, channel names
+    /// and attributes aren't reported
+    pub fn probe<P: AsRef<Path>>(&self, path: P) -> Result<super::ProbeInfo, Error> {
+        if !path.as_ref().exists() {
+            return Err(Error::FileDoesNotExist);
+        }
+
+        let identify = Command::new(self.identify[0])
+            .args(self.identify[1..].iter())
+            .args(["-format", "%w %h %z %A %r\\n"])
+            .arg(path.as_ref())
+            .output()
+            .map_err(|_| Error::UnableToExecuteCommand)?;
+
+        // Only the first frame of multi-frame images is used, each frame is printed on its own
+        // line
+        let output = String::from_utf8_lossy(&identify.stdout);
+        let frames = output.lines().filter(|l| !l.trim().is_empty()).count();
+        let fields: Vec<&str> = output
+            .lines()
+            .next()
+            .ok_or(Error::InvalidImageShape)?
+            .split_whitespace()
+            .collect();
+        if fields.len() < 4 {
+            return Err(Error::InvalidImageShape);
+        }
+
+        let parse = |s: &str| s.parse::<usize>().map_err(|_| Error::InvalidImageShape);
+        let (width, height, depth) = (parse(fields[0])?, parse(fields[1])?, parse(fields[2])?);
+        let class = fields[4..].join(" ").to_ascii_lowercase();
+        let color = if class.contains("gray") {
+            1
+        } else if class.contains("cmyk") {
+            4
+        } else {
+            3
+        };
+        let alpha = matches!(
+            fields[3].to_ascii_lowercase().as_str(),
+            "true" | "blend" | "activate" | "on" | "set"
+        );
+        let base_type = match depth {
+            0..=8 => super::BaseType::UInt8,
+            9..=16 => super::BaseType::UInt16,
+            32 => super::BaseType::Float,
+            64 => super::BaseType::Double,
+            _ => super::BaseType::Unknown,
+        };
+
+        Ok(super::ProbeInfo {
+            size: crate::Size::new(width, height),
+            base_type,
+            channels: color + alpha as usize,
+            channel_names: Vec::new(),
+            frames,
+            attributes: Default::default(),
+        })
+    }
+
     /// Encode image to an im-memory buffer using ImageMagick/GraphicsMagick
     pub fn encode<T: Type, C: Color>(
         &self,
@@ -254,6 +458,60 @@ pub fn read<P: AsRef<Path>, T: Type, C: Color>(path: P) -> Result<Image<T, C>, c
     Ok(x)
 }
 
+/// Read image from disk, the image is probed first and rejected if it exceeds the limits in
+/// `options`. The decoder is also started with the limits and the decoded image is checked again,
+/// so a file that changes after it was probed can't bypass them
+pub fn read_with_options<P: AsRef<Path>, T: Type, C: Color>(
+    path: P,
+    options: &super::DecodeOptions,
+) -> Result<Image<T, C>, crate::Error> {
+    options.check::<T, C>(&probe(&path)?)?;
+    let image: Image<T, C> = unsafe { DEFAULT.read_with_options(path, options)? };
+    options.check::<T, C>(&super::ProbeInfo {
+        size: image.size(),
+        base_type: T::BASE,
+        channels: C::CHANNELS,
+        channel_names: Vec::new(),
+        frames: 1,
+        attributes: Default::default(),
+    })?;
+    Ok(image)
+}
+
+/// List channel groups in an image file, ImageMagick doesn't expose channel names so this
+/// always returns a single unnamed layer
+pub fn layers<P: AsRef<Path>>(_path: P) -> Result<Vec<super::Layer>, crate::Error> {
+    Ok(vec![super::Layer {
+        name: String::new(),
+        channels: Vec::new(),
+    }])
+}
+
+/// Read a single layer from a multi-layer image, only the unnamed layer is supported by ImageMagick
+pub fn open_layer<P: AsRef<Path>, T: Type, C: Color>(
+    path: P,
+    layer_name: impl AsRef<str>,
+) -> Result<Image<T, C>, crate::Error> {
+    if !layer_name.as_ref().is_empty() {
+        return Err(crate::Error::Message(format!(
+            "Layer not found: {}",
+            layer_name.as_ref()
+        )));
+    }
+    read(path)
+}
+
+/// Read deep image from disk, deep images are not supported by ImageMagick so this always returns an
+/// error, use the `oiio` feature instead
+pub fn read_deep<P: AsRef<Path>>(path: P) -> Result<DeepImage, crate::Error> {
+    let path = path.as_ref();
+    Err(crate::Error::Decode {
+        path: path.to_path_buf(),
+        format: super::extension(path),
+        reason: "deep images are not supported by ImageMagick".into(),
+    })
+}
+
 /// Write image to disk
 pub fn write<P: AsRef<Path>, T: Type, C: Color>(
     path: P,
@@ -262,3 +520,42 @@ pub fn write<P: AsRef<Path>, T: Type, C: Color>(
     let x = unsafe { DEFAULT.write(path, image)? };
     Ok(x)
 }
+
+/// Write a MIP chain to disk, MIP levels are not supported by ImageMagick so this always
+/// returns an error
+pub fn write_mipmaps<P: AsRef<Path>, T: Type, C: Color>(
+    path: P,
+    _levels: &[Image<T, C>],
+) -> Result<(), crate::Error> {
+    Err(crate::Error::Message(format!(
+        "MIP levels are not supported by ImageMagick: {}",
+        path.as_ref().display()
+    )))
+}
+
+/// Write image to disk using the provided encoder options
+pub fn write_with_options<P: AsRef<Path>, T: Type, C: Color>(
+    path: P,
+    image: &Image<T, C>,
+    options: &EncodeOptions,
+) -> Result<(), crate::Error> {
+    options.check(path.as_ref())?;
+    let x = unsafe { DEFAULT.write_with_options(path, image, options)? };
+    Ok(x)
+}
+
+/// List file extensions that can be written using the default Magick implementation
+pub fn supported_formats() -> Vec<String> {
+    unsafe { DEFAULT.supported_formats() }
+}
+
+/// List formats that can be read or written using the default Magick implementation
+pub fn formats() -> Vec<super::FormatInfo> {
+    unsafe { DEFAULT.formats() }
+}
+
+/// Read image properties without decoding pixels using the default Magick implementation
+pub fn probe<P: AsRef<Path>>(path: P) -> Result<super::ProbeInfo, crate::Error> {
+    let x = unsafe { DEFAULT.probe(path)? };
+    Ok(x)
+}
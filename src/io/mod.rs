@@ -63,18 +63,391 @@ pub enum BaseType {
     Last,
 }
 
+/// Group of channels sharing a name prefix, for example the `diffuse` layer contains `diffuse.R`,
+/// `diffuse.G` and `diffuse.B`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer {
+    /// Layer name, channels without a prefix belong to the layer with an empty name
+    pub name: String,
+
+    /// Full channel names, color channels are ordered as R, G, B, A
+    pub channels: Vec<String>,
+}
+
+/// Group channel names into layers, layers are returned in the order they first appear
+#[cfg_attr(not(feature = "oiio"), allow(dead_code))]
+pub(crate) fn group_layers(names: &[String]) -> Vec<Layer> {
+    let order = |channel: &str| match channel.rsplit('.').next().unwrap_or("") {
+        "R" | "r" | "X" | "x" | "Y" | "y" => 0,
+        "G" | "g" => 1,
+        "B" | "b" => 2,
+        "A" | "a" => 3,
+        _ => 4,
+    };
+
+    let mut layers: Vec<Layer> = Vec::new();
+    for channel in names {
+        let name = channel.rsplit_once('.').map(|x| x.0).unwrap_or("");
+        match layers.iter_mut().find(|l| l.name == name) {
+            Some(layer) => layer.channels.push(channel.clone()),
+            None => layers.push(Layer {
+                name: name.to_string(),
+                channels: vec![channel.clone()],
+            }),
+        }
+    }
+
+    for layer in &mut layers {
+        layer.channels.sort_by_key(|c| order(c));
+    }
+    layers
+}
+
+/// Encoder settings used by `Image::save_with_options`, unset values use the encoder defaults
+///
+/// Options are applied to AVIF, HEIC and JPEG XL output and `quality` is also used for JPEG and
+/// WebP, other formats ignore them. `lossless` is supported for WebP and JPEG XL, the AVIF and
+/// HEIC encoders always subsample and quantize so lossless output is rejected for them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EncodeOptions {
+    /// Quality from `0` to `100`
+    pub quality: Option<u8>,
+
+    /// Encoding speed from `0` (slowest, smallest files) to `10` (fastest)
+    pub speed: Option<u8>,
+
+    /// Encode without loss, `quality` is ignored when this is set. Writing AVIF or HEIC files
+    /// with this enabled returns an error
+    pub lossless: bool,
+
+    /// Dither when images with more than 8 bits per channel are saved to a format that only
+    /// stores 8 bits, see `Image::convert_dithered`
+    pub dither: bool,
+}
+
+impl EncodeOptions {
+    /// Create new `EncodeOptions` using the encoder defaults
+    pub fn new() -> EncodeOptions {
+        EncodeOptions::default()
+    }
+
+    /// Set quality, values are clamped to `100`
+    pub fn with_quality(mut self, quality: u8) -> EncodeOptions {
+        self.quality = Some(quality.min(100));
+        self
+    }
+
+    /// Set encoding speed, values are clamped to `10`
+    pub fn with_speed(mut self, speed: u8) -> EncodeOptions {
+        self.speed = Some(speed.min(10));
+        self
+    }
+
+    /// Enable lossless encoding
+    pub fn with_lossless(mut self, lossless: bool) -> EncodeOptions {
+        self.lossless = lossless;
+        self
+    }
+
+    /// Enable dithering when quantizing to 8 bits
+    pub fn with_dither(mut self, dither: bool) -> EncodeOptions {
+        self.dither = dither;
+        self
+    }
+
+    /// Quality to pass to the encoder, `100` when lossless
+    pub fn effective_quality(&self) -> Option<u8> {
+        if self.lossless {
+            Some(100)
+        } else {
+            self.quality
+        }
+    }
+
+    /// Returns an error when the options can't be honored by the encoder used for `path`
+    pub fn check(&self, path: &std::path::Path) -> Result<(), crate::Error> {
+        let ext = extension(path);
+        if self.lossless && matches!(ext.as_str(), "avif" | "heic" | "heif") {
+            let msg = format!("lossless encoding is not supported for {ext}");
+            return Err(crate::Error::UnableToWriteImage(msg).with_path(path));
+        }
+        Ok(())
+    }
+
+    /// JPEG XL effort from `1` (fastest) to `9`
+    pub fn jxl_effort(&self) -> Option<u8> {
+        self.speed.map(|s| (9 - s.min(8)).max(1))
+    }
+}
+
+/// Lowercase file extension of `path`, used to select encoder options
+pub(crate) fn extension(path: &std::path::Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+/// File formats that only store 8 bits per channel
+const EIGHT_BIT_FORMATS: &[&str] = &["jpg", "jpeg", "jpe", "jfif", "webp", "gif", "bmp", "ico"];
+
+/// Dithered 8-bit copy of `image`, when dithering is enabled and the format at `path` can't store
+/// the precision of `T`
+#[cfg_attr(not(any(feature = "oiio", feature = "magick")), allow(dead_code))]
+pub(crate) fn dither_for_output<T: crate::Type, C: crate::Color>(
+    path: &std::path::Path,
+    image: &crate::Image<T, C>,
+    options: &EncodeOptions,
+) -> Option<crate::Image<u8, C>> {
+    use crate::Type;
+
+    if !options.dither
+        || T::precision() <= u8::precision()
+        || !EIGHT_BIT_FORMATS.contains(&extension(path).as_str())
+    {
+        return None;
+    }
+    Some(image.convert_dithered())
+}
+
+/// Capabilities of a file format supported by the active I/O backend, see `formats`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatInfo {
+    /// Format name used by the backend
+    pub name: String,
+
+    /// Lowercase file extensions
+    pub extensions: Vec<String>,
+
+    /// Files can be read
+    pub read: bool,
+
+    /// Files can be written
+    pub write: bool,
+
+    /// Data types that can be stored without conversion, empty when unknown
+    pub types: Vec<BaseType>,
+
+    /// Supported channel counts, empty when any number of channels can be stored or the limits
+    /// are unknown
+    pub channels: Vec<usize>,
+}
+
+/// Data types and channel counts of common formats, indexed by extension
+const FORMAT_CAPABILITIES: &[(&[&str], &[BaseType], &[usize])] = {
+    use BaseType::*;
+    &[
+        (&["jpg", "jpeg", "jpe", "jfif"], &[UInt8], &[1, 3, 4]),
+        (&["png"], &[UInt8, UInt16], &[1, 2, 3, 4]),
+        (&["gif", "bmp", "tga", "ico"], &[UInt8], &[1, 3, 4]),
+        (&["webp", "avif", "heic", "heif"], &[UInt8], &[3, 4]),
+        (&["pnm", "ppm", "pgm", "pbm"], &[UInt8, UInt16], &[1, 3]),
+        (&["dpx"], &[UInt8, UInt16, Float], &[1, 3, 4]),
+        (&["jp2", "j2k"], &[UInt8, UInt16], &[1, 2, 3, 4]),
+        (&["jxl"], &[UInt8, UInt16, Half, Float], &[1, 2, 3, 4]),
+        (&["hdr"], &[Float], &[3]),
+        (&["exr"], &[Half, Float, UInt32], &[]),
+        (&["fits"], &[UInt8, Int16, Int32, Float, Double], &[]),
+        (
+            &["tif", "tiff"],
+            &[
+                UInt8, Int8, UInt16, Int16, UInt32, Int32, Half, Float, Double,
+            ],
+            &[],
+        ),
+    ]
+};
+
+impl FormatInfo {
+    /// Create a new `FormatInfo`, known data types and channel counts are filled in based on the
+    /// extensions
+    pub fn new(
+        name: impl Into<String>,
+        extensions: Vec<String>,
+        read: bool,
+        write: bool,
+    ) -> FormatInfo {
+        let known = FORMAT_CAPABILITIES
+            .iter()
+            .find(|(exts, _, _)| extensions.iter().any(|e| exts.contains(&e.as_str())));
+        FormatInfo {
+            name: name.into(),
+            types: known.map(|x| x.1.to_vec()).unwrap_or_default(),
+            channels: known.map(|x| x.2.to_vec()).unwrap_or_default(),
+            extensions,
+            read,
+            write,
+        }
+    }
+
+    /// Returns true when the format is used for files with the extension of `path`
+    pub fn matches(&self, path: impl AsRef<std::path::Path>) -> bool {
+        let ext = extension(path.as_ref());
+        self.extensions.contains(&ext)
+    }
+
+    /// Returns true when images with the given data type and number of channels can be stored
+    /// without conversion, unknown limits are assumed to be supported
+    pub fn supports(&self, ty: BaseType, channels: usize) -> bool {
+        (self.types.is_empty() || self.types.contains(&ty))
+            && (self.channels.is_empty() || self.channels.contains(&channels))
+    }
+}
+
+/// Image properties read from a file header without decoding the pixel data, see `probe`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeInfo {
+    /// Image size
+    pub size: crate::Size,
+
+    /// Data type stored in the file
+    pub base_type: BaseType,
+
+    /// Number of channels
+    pub channels: usize,
+
+    /// Channel names, empty when the backend doesn't report them
+    pub channel_names: Vec<String>,
+
+    /// Number of frames or subimages, `1` for single images
+    pub frames: usize,
+
+    /// Metadata attributes converted to strings
+    pub attributes: std::collections::BTreeMap<String, String>,
+}
+
+impl ProbeInfo {
+    /// Number of bytes needed to load the image as `T`, `None` when the size overflows
+    pub fn num_bytes<T: crate::Type>(&self) -> Option<usize> {
+        self.size
+            .width
+            .checked_mul(self.size.height)?
+            .checked_mul(self.channels)?
+            .checked_mul(std::mem::size_of::<T>())
+    }
+}
+
+/// Limits checked against the file header before any pixel data is allocated, use them to
+/// reject decompression bombs and absurd dimensions when reading untrusted files. Unset limits
+/// aren't checked
+///
+/// Limits are only enforced by `Image::open_with_options`, `io::read_with_options` and
+/// `ProgressiveDecoder::with_options`. `io::dicom::read`, `io::dicom::decode`, `Image::open`
+/// and `Image::open_mmap` don't take options and read whatever size the file declares
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecodeOptions {
+    /// Maximum image width
+    pub max_width: Option<usize>,
+
+    /// Maximum image height
+    pub max_height: Option<usize>,
+
+    /// Maximum number of bytes allocated to decode the image, this includes the intermediate
+    /// buffer used when the channels in the file don't match the requested color
+    pub max_bytes: Option<usize>,
+
+    /// Maximum number of frames or subimages in the file
+    pub max_frames: Option<usize>,
+}
+
+impl DecodeOptions {
+    /// Create new `DecodeOptions` without any limits
+    pub fn new() -> DecodeOptions {
+        DecodeOptions::default()
+    }
+
+    /// Set maximum width
+    pub fn with_max_width(mut self, width: usize) -> DecodeOptions {
+        self.max_width = Some(width);
+        self
+    }
+
+    /// Set maximum height
+    pub fn with_max_height(mut self, height: usize) -> DecodeOptions {
+        self.max_height = Some(height);
+        self
+    }
+
+    /// Set maximum number of bytes
+    pub fn with_max_bytes(mut self, bytes: usize) -> DecodeOptions {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    /// Set maximum number of frames
+    pub fn with_max_frames(mut self, frames: usize) -> DecodeOptions {
+        self.max_frames = Some(frames);
+        self
+    }
+
+    /// Number of bytes needed to decode an image described by `info` as `Image<T, C>`, `None`
+    /// when the size overflows. This models the OpenImageIO backend, which reads files with a
+    /// different channel count into an intermediate `f32` buffer. ImageMagick converts channels
+    /// in its subprocess, so the estimate is an upper bound for that backend
+    pub fn decoded_bytes<T: crate::Type, C: crate::Color>(info: &ProbeInfo) -> Option<usize> {
+        let pixels = info.size.width.checked_mul(info.size.height)?;
+        let bytes = pixels
+            .checked_mul(C::CHANNELS)?
+            .checked_mul(std::mem::size_of::<T>())?;
+        if info.channels == C::CHANNELS {
+            return Some(bytes);
+        }
+
+        // Other channel counts are read as `f32` Gray, Rgb or Rgba and then converted
+        let intermediate = pixels.checked_mul(info.channels.clamp(1, 4) * 4)?;
+        bytes.checked_add(intermediate)
+    }
+
+    /// Check an image described by `info` against the limits, returns `Error::LimitExceeded` for
+    /// the first limit that isn't met
+    pub fn check<T: crate::Type, C: crate::Color>(
+        &self,
+        info: &ProbeInfo,
+    ) -> Result<(), crate::Error> {
+        let exceeded = |limit: &'static str, value: usize, max: Option<usize>| match max {
+            Some(max) if value > max => Err(crate::Error::LimitExceeded { limit, value, max }),
+            _ => Ok(()),
+        };
+        exceeded("width", info.size.width, self.max_width)?;
+        exceeded("height", info.size.height, self.max_height)?;
+        exceeded("frames", info.frames, self.max_frames)?;
+        let bytes = Self::decoded_bytes::<T, C>(info).unwrap_or(usize::MAX);
+        exceeded("bytes", bytes, self.max_bytes)
+    }
+}
+
+#[cfg(feature = "progressive")]
+mod progressive;
+
+#[cfg(feature = "progressive")]
+pub use progressive::ProgressiveDecoder;
+
+#[cfg(feature = "medical")]
+/// Uncompressed DICOM decoding
+pub mod dicom;
+
 #[cfg(all(feature = "oiio", not(feature = "docs-rs")))]
 /// OpenImageIO bindings
 pub mod oiio;
 
 #[cfg(all(feature = "oiio", not(feature = "docs-rs")))]
-pub use oiio::{read, write};
+pub use oiio::{
+    formats, layers, open_layer, probe, read, read_deep, read_with_options, supported_formats,
+    write, write_mipmaps, write_with_options,
+};
 
 #[cfg(feature = "magick")]
-pub use magick::{read, write};
+pub use magick::{
+    formats, layers, open_layer, probe, read, read_deep, read_with_options, supported_formats,
+    write, write_mipmaps, write_with_options,
+};
 
 #[cfg(all(not(feature = "magick"), not(feature = "oiio")))]
 mod stub;
 
 #[cfg(all(not(feature = "magick"), not(feature = "oiio")))]
-pub use stub::{read, write};
+pub use stub::{
+    formats, layers, open_layer, probe, read, read_deep, read_with_options, supported_formats,
+    write, write_mipmaps, write_with_options,
+};
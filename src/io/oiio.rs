@@ -71,9 +71,7 @@ impl ImageOutput {
         };
 
         if image_output.is_null() {
-            return Err(Error::UnableToWriteImage(
-                path.to_string_lossy().to_string(),
-            ));
+            return Err(Error::Oiio(last_error()).with_path(path));
         }
 
         Ok(ImageOutput {
@@ -88,6 +86,14 @@ impl ImageOutput {
     ///
     /// Note: `image` dimensions and type will take precendence over the ImageSpec
     pub fn write<T: Type, C: Color>(mut self, image: &Image<T, C>) -> Result<(), Error> {
+        let swapped;
+        let image = match bgr_order::<C>() {
+            Some(order) => {
+                swapped = image.reorder_channels::<C>(&order);
+                &swapped
+            }
+            None => image,
+        };
         let base_type = T::BASE;
         let path: &std::path::Path = self.path.as_ref();
         let path_str = std::ffi::CString::new(path.to_string_lossy().as_bytes().to_vec()).unwrap();
@@ -95,6 +101,14 @@ impl ImageOutput {
         let pixels = image.data.as_ptr();
         let (width, height, channels) = image.shape();
         let out = self.image_output;
+        if let Some(geo) = &image.meta.georeference {
+            self.spec.set_georeference(geo);
+        }
+        let names = channel_names::<C>();
+        let name_ptrs: Vec<*const std::os::raw::c_char> =
+            names.iter().map(|n| n.as_ptr()).collect();
+        let names_ptr = name_ptrs.as_ptr();
+        let alpha = C::ALPHA.map(|c| c as i32).unwrap_or(-1);
         let spec = &mut self.spec;
         unsafe {
             cpp!([out as "ImageOutput*",
@@ -104,13 +118,16 @@ impl ImageOutput {
               width as "size_t",
               height as "size_t",
               channels as "size_t",
+              names_ptr as "const char * const *",
+              alpha as "int",
               pixels as "const void*"
             ] {
                 ImageSpec outspec (*spec);
                 outspec.width = width;
                 outspec.height = height;
                 outspec.nchannels = channels;
-                outspec.channelnames.assign({"R", "G", "B", "A"});
+                outspec.channelnames.assign(names_ptr, names_ptr + channels);
+                outspec.alpha_channel = alpha;
                 outspec.set_format(TypeDesc(base_type));
                 out->open (filename, outspec);
                 out->write_image (base_type, pixels);
@@ -120,10 +137,100 @@ impl ImageOutput {
         Ok(())
     }
 
+    /// Write a MIP chain to the file, `levels` should start with the full resolution image and
+    /// each following level should be half the size of the previous one, see
+    /// `Image::generate_mipmaps`. Returns an error when the format doesn't support MIP levels
+    pub fn write_mipmaps<T: Type, C: Color>(mut self, levels: &[Image<T, C>]) -> Result<(), Error> {
+        let base_type = T::BASE;
+        let path = self.path.clone();
+        let path_str = std::ffi::CString::new(path.to_string_lossy().as_bytes().to_vec()).unwrap();
+        let filename = path_str.as_ptr();
+        let out = self.image_output;
+        if let Some(geo) = levels
+            .first()
+            .and_then(|image| image.meta.georeference.as_ref())
+        {
+            self.spec.set_georeference(geo);
+        }
+
+        let names = channel_names::<C>();
+        let name_ptrs: Vec<*const std::os::raw::c_char> =
+            names.iter().map(|n| n.as_ptr()).collect();
+        let names_ptr = name_ptrs.as_ptr();
+        let alpha = C::ALPHA.map(|c| c as i32).unwrap_or(-1);
+
+        for (index, image) in levels.iter().enumerate() {
+            let swapped;
+            let image = match bgr_order::<C>() {
+                Some(order) => {
+                    swapped = image.reorder_channels::<C>(&order);
+                    &swapped
+                }
+                None => image,
+            };
+            let pixels = image.data.as_ptr();
+            let (width, height, channels) = image.shape();
+            let spec = &mut self.spec;
+            let status = unsafe {
+                cpp!([out as "ImageOutput*",
+                  index as "size_t",
+                  filename as "const char *",
+                  base_type as "TypeDesc::BASETYPE",
+                  spec as "ImageSpec *",
+                  width as "size_t",
+                  height as "size_t",
+                  channels as "size_t",
+                  names_ptr as "const char * const *",
+                  alpha as "int",
+                  pixels as "const void*"
+                ] -> i32 as "int32_t" {
+                    if (!out->supports ("mipmap")) {
+                        return 1;
+                    }
+
+                    ImageSpec outspec (*spec);
+                    outspec.width = width;
+                    outspec.height = height;
+                    outspec.full_width = width;
+                    outspec.full_height = height;
+                    outspec.nchannels = channels;
+                    outspec.channelnames.assign(names_ptr, names_ptr + channels);
+                    outspec.alpha_channel = alpha;
+                    outspec.set_format(TypeDesc(base_type));
+                    ImageOutput::OpenMode mode = index == 0 ? ImageOutput::Create : ImageOutput::AppendMIPLevel;
+                    if (!out->open (filename, outspec, mode) || !out->write_image (base_type, pixels)) {
+                        return 2;
+                    }
+                    return 0;
+                })
+            };
+
+            match status {
+                0 => (),
+                1 => {
+                    return Err(Error::Message(format!(
+                        "MIP levels are not supported by {}",
+                        path.display()
+                    )))
+                }
+                _ => return Err(Error::Oiio(last_error()).with_path(&path)),
+            }
+        }
+        Ok(())
+    }
+
     /// Append an image to the file for formats with multi-image support
     ///
     /// Note: `image` dimensions and type will take precendence over the ImageSpec
     pub fn append<T: Type, C: Color>(&mut self, image: &Image<T, C>) -> Result<(), Error> {
+        let swapped;
+        let image = match bgr_order::<C>() {
+            Some(order) => {
+                swapped = image.reorder_channels::<C>(&order);
+                &swapped
+            }
+            None => image,
+        };
         let base_type = T::BASE;
         let path: &std::path::Path = self.path.as_ref();
         let path_str = std::ffi::CString::new(path.to_string_lossy().as_bytes().to_vec()).unwrap();
@@ -229,6 +336,73 @@ impl ImageInput {
         &self.path
     }
 
+    /// Number of subimages in the file, only headers are read
+    pub fn num_subimages(&self) -> usize {
+        let input = self.image_input;
+        let current = self.subimage as i32;
+        let miplevel = self.miplevel as i32;
+        let n = unsafe {
+            cpp!([input as "std::unique_ptr<ImageInput>",
+              current as "int",
+              miplevel as "int"
+            ] -> i32 as "int" {
+                int n = 0;
+                while (input->seek_subimage(n, 0)) {
+                    n += 1;
+                }
+                input->seek_subimage(current, miplevel);
+                return n;
+            })
+        };
+        n.max(1) as usize
+    }
+
+    /// Image properties from the file header, see `io::probe`
+    pub fn probe_info(&self) -> super::ProbeInfo {
+        let spec = self.spec();
+        let attributes = spec
+            .attrs()
+            .into_iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    Attr::Int(i) => i.to_string(),
+                    Attr::Float(f) => f.to_string(),
+                    Attr::String(s) => s.to_string(),
+                };
+                (key.to_string(), value)
+            })
+            .collect();
+
+        super::ProbeInfo {
+            size: Size::new(spec.width(), spec.height()),
+            base_type: spec.format(),
+            channels: spec.nchannels(),
+            channel_names: spec.channel_names(),
+            frames: self.num_subimages(),
+            attributes,
+        }
+    }
+
+    /// Build a `Decode` error using the last error message reported by the reader, or `fallback`
+    /// when no message is available
+    fn decode_error(&self, fallback: &str) -> Error {
+        let input = self.image_input;
+        let reason = take_string(unsafe {
+            cpp!([input as "std::unique_ptr<ImageInput>"] -> *mut u8 as "std::string*" {
+                return new std::string(input->geterror());
+            })
+        });
+        Error::Decode {
+            path: self.path.clone(),
+            format: super::extension(&self.path),
+            reason: if reason.is_empty() {
+                fallback.to_string()
+            } else {
+                reason
+            },
+        }
+    }
+
     /// Open image for reading
     pub fn open(
         path: impl AsRef<std::path::Path>,
@@ -262,7 +436,15 @@ impl ImageInput {
         };
 
         if input.is_null() {
-            return Err(Error::UnableToOpenImage(path.to_string_lossy().to_string()));
+            if !path.exists() {
+                let source = std::io::Error::from(std::io::ErrorKind::NotFound);
+                return Err(Error::IO(source).with_path(path));
+            }
+            return Err(Error::Decode {
+                path: path.to_path_buf(),
+                format: super::extension(path),
+                reason: last_error(),
+            });
         }
 
         Ok(ImageInput {
@@ -290,11 +472,10 @@ impl ImageInput {
             || spec.width() != image.width()
             || spec.height() != image.height()
         {
-            return Err(Error::InvalidDimensions(
-                spec.width(),
-                spec.height(),
-                spec.nchannels(),
-            ));
+            return Err(Error::ShapeMismatch {
+                expected: (image.width(), image.height(), C::CHANNELS),
+                got: (spec.width(), spec.height(), spec.nchannels()),
+            });
         }
 
         let res = unsafe {
@@ -310,14 +491,198 @@ impl ImageInput {
         };
 
         if !res {
-            return Err(Error::CannotReadImage(
-                self.path.to_string_lossy().to_string(),
-            ));
+            return Err(self.decode_error("read failed"));
         }
 
         Ok(())
     }
 
+    /// Channel groups in the current subimage, see `io::Layer`
+    pub fn layers(&self) -> Vec<super::Layer> {
+        super::group_layers(&self.spec.channel_names())
+    }
+
+    /// Read the channels of a single layer into a new image, an empty name selects channels
+    /// without a layer prefix. Layers with 1 (gray), 2 (gray and alpha), 3 (RGB) or 4 (RGBA)
+    /// channels are supported, other layouts return `Error::UnsupportedColor`
+    pub fn read_layer<T: Type, C: Color>(
+        &self,
+        name: impl AsRef<str>,
+    ) -> Result<Image<T, C>, Error> {
+        let name = name.as_ref();
+        let names = self.spec.channel_names();
+        let layer = super::group_layers(&names)
+            .into_iter()
+            .find(|l| l.name == name)
+            .ok_or_else(|| Error::Message(format!("Layer not found: {name}")))?;
+
+        let (width, height) = (self.spec.width(), self.spec.height());
+        let mut src = vec![0.0f32; width * height * names.len()];
+        let data = src.as_mut_ptr();
+        let input = self.image_input;
+        let index = self.subimage;
+        let miplevel = self.miplevel;
+        let channels = names.len();
+        let fmt = f32::BASE;
+        let res = unsafe {
+            cpp!([input as "std::unique_ptr<ImageInput>",
+              index as "size_t",
+              miplevel as "size_t",
+              channels as "size_t",
+              fmt as "TypeDesc::BASETYPE",
+              data as "void *"
+            ] ->  bool as "bool" {
+                return input->read_image(index, miplevel, 0, channels, fmt, data);
+            })
+        };
+
+        if !res {
+            return Err(self.decode_error("read failed"));
+        }
+
+        let indices: Vec<usize> = layer
+            .channels
+            .iter()
+            .filter_map(|c| names.iter().position(|n| n == c))
+            .collect();
+
+        // `map` lists the source channel used for each destination channel
+        let copy = |dest: &mut [f32], map: &[usize]| {
+            for (i, px) in dest.chunks_exact_mut(map.len()).enumerate() {
+                for (d, c) in px.iter_mut().zip(map) {
+                    *d = src[i * names.len() + c];
+                }
+            }
+        };
+
+        let is_alpha = |c: &str| matches!(c.rsplit('.').next(), Some("A" | "a"));
+        match indices.as_slice() {
+            &[y] => {
+                let mut image = Image::<f32, Gray>::new((width, height));
+                copy(image.data_mut(), &[y]);
+                Ok(image.convert())
+            }
+            &[y, a] if is_alpha(&layer.channels[1]) => {
+                let mut image = Image::<f32, Rgba>::new((width, height));
+                copy(image.data_mut(), &[y, y, y, a]);
+                Ok(image.convert())
+            }
+            &[r, g, b] => {
+                let mut image = Image::<f32, Rgb>::new((width, height));
+                copy(image.data_mut(), &[r, g, b]);
+                Ok(image.convert())
+            }
+            &[r, g, b, a] => {
+                let mut image = Image::<f32, Rgba>::new((width, height));
+                copy(image.data_mut(), &[r, g, b, a]);
+                Ok(image.convert())
+            }
+            _ => Err(Error::UnsupportedColor {
+                requested: C::NAME.to_string(),
+                found: format!("layer {:?} with channels {:?}", name, layer.channels),
+            }),
+        }
+    }
+
+    /// Read deep samples, returns an error if the image is not a deep image. Samples use the `R`,
+    /// `G`, `B`, `A`, `Z` and `ZBack` channels, missing color channels are set to `0` and a missing
+    /// alpha channel is treated as opaque
+    pub fn read_deep(&self) -> Result<DeepImage, Error> {
+        let input = self.image_input;
+        let index = self.subimage;
+        let miplevel = self.miplevel;
+        let deep = unsafe {
+            cpp!([input as "std::unique_ptr<ImageInput>",
+              index as "size_t",
+              miplevel as "size_t"
+            ] -> *mut u8 as "DeepData*" {
+                if (!input->spec().deep) {
+                    return nullptr;
+                }
+
+                DeepData *deep = new DeepData();
+                if (!input->read_native_deep_image(index, miplevel, *deep)) {
+                    delete deep;
+                    return nullptr;
+                }
+
+                return deep;
+            })
+        };
+
+        if deep.is_null() {
+            return Err(self.decode_error("not a deep image"));
+        }
+
+        // Channel indices for R, G, B, A, Z and ZBack, -1 if missing
+        let mut channels = [-1i32; 6];
+        let channels_ptr = channels.as_mut_ptr();
+        unsafe {
+            cpp!([input as "std::unique_ptr<ImageInput>", channels_ptr as "int*"] {
+                const ImageSpec &spec = input->spec();
+                const char *names[] = {"R", "G", "B", "A", "Z", "ZBack"};
+                for (int i = 0; i < 6; i++) {
+                    channels_ptr[i] = spec.channelindex(names[i]);
+                }
+                if (channels_ptr[3] < 0) {
+                    channels_ptr[3] = spec.alpha_channel;
+                }
+                if (channels_ptr[4] < 0) {
+                    channels_ptr[4] = spec.z_channel;
+                }
+            })
+        };
+
+        let value = |pixel: usize, channel: i32, sample: usize| -> Option<f32> {
+            if channel < 0 {
+                return None;
+            }
+            let v = unsafe {
+                cpp!([deep as "const DeepData*",
+                  pixel as "size_t",
+                  channel as "int",
+                  sample as "size_t"
+                ] -> f32 as "float" {
+                    return deep->deep_value(pixel, channel, sample);
+                })
+            };
+            Some(v)
+        };
+
+        let width = self.spec.width();
+        let image = DeepImage::from_fn((width, self.spec.height()), |pt| {
+            let pixel = pt.y * width + pt.x;
+            let count = unsafe {
+                cpp!([deep as "const DeepData*", pixel as "size_t"] -> usize as "size_t" {
+                    return deep->samples(pixel);
+                })
+            };
+
+            (0..count)
+                .map(|s| {
+                    let mut color = [0.0; 4];
+                    for (c, x) in color.iter_mut().enumerate() {
+                        *x = value(pixel, channels[c], s).unwrap_or(if c == 3 { 1.0 } else { 0.0 });
+                    }
+                    let depth = value(pixel, channels[4], s).unwrap_or(0.0);
+                    DeepSample {
+                        color,
+                        depth,
+                        depth_back: value(pixel, channels[5], s).unwrap_or(depth),
+                    }
+                })
+                .collect()
+        });
+
+        unsafe {
+            cpp!([deep as "DeepData*"] {
+                delete deep;
+            })
+        };
+
+        Ok(image)
+    }
+
     /// Read to new image
     ///
     /// Note: the `convert` method may be called if the requested color doesn't match
@@ -326,25 +691,43 @@ impl ImageInput {
 
         // `convert` is called if the channels don't match the image on disk or the color is not
         // Gray, Rgb, or Rgba
-        if C::CHANNELS != nchannels {
+        let mut image = if C::CHANNELS != nchannels {
             if nchannels == 1 {
                 let mut image = Image::<f32, Gray>::new((self.spec.width(), self.spec.height()));
                 self.read_into(&mut image)?;
-                Ok(image.convert())
+                image.convert()
             } else if nchannels == 4 {
                 let mut image = Image::<f32, Rgba>::new((self.spec.width(), self.spec.height()));
                 self.read_into(&mut image)?;
-                Ok(image.convert())
-            } else {
+                image.convert()
+            } else if nchannels >= 3 {
                 let mut image = Image::<f32, Rgb>::new((self.spec.width(), self.spec.height()));
                 self.read_into(&mut image)?;
-                Ok(image.convert())
+                image.convert()
+            } else {
+                return Err(Error::UnsupportedColor {
+                    requested: C::NAME.to_string(),
+                    found: format!("{nchannels} channels"),
+                });
             }
         } else {
             let mut image = Image::new((self.spec.width(), self.spec.height()));
             self.read_into(&mut image)?;
-            Ok(image)
+            match bgr_order::<C>() {
+                Some(order) => image.reorder_channels(&order),
+                None => image,
+            }
+        };
+        image.meta.georeference = self.spec.georeference();
+
+        // Only tag images that were read without conversion, converted images use the default
+        // colorspace of `C`
+        if C::CHANNELS == nchannels {
+            if let Some(colorspace) = self.spec.colorspace().and_then(Colorspace::from_name) {
+                image = image.with_colorspace(colorspace);
+            }
         }
+        Ok(image)
     }
 }
 
@@ -433,6 +816,24 @@ impl ImageSpec {
         }
     }
 
+    /// Get channel names
+    pub fn channel_names(&self) -> Vec<String> {
+        (0..self.nchannels())
+            .map(|i| {
+                let mut len = 0;
+                let len_ptr = &mut len;
+                unsafe {
+                    let s = cpp!([self as "const ImageSpec*", i as "size_t", len_ptr as "size_t*"] -> *const u8 as "const char*" {
+                        const std::string &name = self->channelnames[i];
+                        *len_ptr = name.size();
+                        return name.c_str();
+                    });
+                    String::from_utf8_lossy(std::slice::from_raw_parts(s, len)).into_owned()
+                }
+            })
+            .collect()
+    }
+
     /// Get image format
     pub fn format(&self) -> BaseType {
         unsafe {
@@ -491,6 +892,132 @@ impl ImageSpec {
         }
     }
 
+    /// Get a numeric array attribute, values are converted to `T`
+    pub fn get_attr_array<T: Type>(&self, key: impl AsRef<str>) -> Option<Vec<T>> {
+        let key_str = std::ffi::CString::new(key.as_ref().as_bytes().to_vec()).unwrap();
+        let key_ptr = key_str.as_ptr();
+        let mut len = 0;
+        let len_ptr = &mut len;
+        let found = unsafe {
+            cpp!([self as "const ImageSpec*",
+                  key_ptr as "const char*",
+                  len_ptr as "size_t*"
+            ] -> bool as "bool" {
+                ParamValue param;
+                auto p = self->find_attribute(key_ptr, param, TypeDesc::UNKNOWN, false);
+                if (!p || p->type().basetype == TypeDesc::STRING)
+                    return false;
+                *len_ptr = p->nvalues() * p->type().basevalues();
+                return true;
+            })
+        };
+
+        if !found {
+            return None;
+        }
+
+        let mut data = vec![0f64; len];
+        let data_ptr = data.as_mut_ptr();
+        unsafe {
+            cpp!([self as "const ImageSpec*",
+                  key_ptr as "const char*",
+                  len as "size_t",
+                  data_ptr as "double*"
+            ] {
+                ParamValue param;
+                auto p = self->find_attribute(key_ptr, param, TypeDesc::UNKNOWN, false);
+                convert_type(TypeDesc((TypeDesc::BASETYPE)p->type().basetype), p->data(),
+                             TypeDesc::DOUBLE, data_ptr, len);
+            })
+        };
+        Some(data.into_iter().map(T::from_f64).collect())
+    }
+
+    /// Set a numeric array attribute
+    pub fn set_attr_array<T: Type>(&mut self, key: impl AsRef<str>, value: &[T]) {
+        let key_str = std::ffi::CString::new(key.as_ref().as_bytes().to_vec()).unwrap();
+        let key_ptr = key_str.as_ptr();
+        let base_type = T::BASE;
+        let len = value.len();
+        let value_ptr = value.as_ptr();
+        unsafe {
+            cpp!([self as "ImageSpec*",
+                  key_ptr as "const char*",
+                  base_type as "TypeDesc::BASETYPE",
+                  len as "size_t",
+                  value_ptr as "const void*"
+            ] {
+                self->attribute(key_ptr, TypeDesc(base_type, (int)len), value_ptr);
+            });
+        }
+    }
+
+    /// Get GeoTIFF georeferencing tags
+    pub fn georeference(&self) -> Option<Georeference> {
+        let get = |key| self.get_attr_array::<f64>(key).unwrap_or_default();
+        let mut geo = Georeference::from_geotiff(
+            &get("geotiff:ModelPixelScale"),
+            &get("geotiff:ModelTiePoint"),
+            &get("geotiff:ModelTransformation"),
+        )?;
+        geo.keys = self
+            .get_attr_array("geotiff:GeoKeyDirectory")
+            .unwrap_or_default();
+        geo.double_params = get("geotiff:GeoDoubleParams");
+        if let Some(Attr::String(s)) = self.get_attr("geotiff:GeoAsciiParams") {
+            geo.ascii_params = s.to_string();
+        }
+        Some(geo)
+    }
+
+    /// Set GeoTIFF georeferencing tags
+    pub fn set_georeference(&mut self, geo: &Georeference) {
+        if geo.is_axis_aligned() {
+            self.set_attr_array("geotiff:ModelPixelScale", &geo.pixel_scale());
+            self.set_attr_array("geotiff:ModelTiePoint", &geo.tie_point());
+        } else {
+            self.set_attr_array("geotiff:ModelTransformation", &geo.transformation());
+        }
+        if !geo.keys.is_empty() {
+            self.set_attr_array("geotiff:GeoKeyDirectory", &geo.keys);
+        }
+        if !geo.double_params.is_empty() {
+            self.set_attr_array("geotiff:GeoDoubleParams", &geo.double_params);
+        }
+        if !geo.ascii_params.is_empty() {
+            self.set_attr("geotiff:GeoAsciiParams", geo.ascii_params.as_str());
+        }
+    }
+
+    /// Set encoder attributes for the format of `path`
+    pub fn set_encode_options(&mut self, path: &std::path::Path, options: &super::EncodeOptions) {
+        let ext = super::extension(path);
+        let compression = match ext.as_str() {
+            "avif" => "avif",
+            "heic" | "heif" => "heic",
+            "jxl" => "jpegxl",
+            "jpg" | "jpeg" => "jpeg",
+            "webp" => "webp",
+            _ => return,
+        };
+
+        if let Some(quality) = options.effective_quality() {
+            self.set_attr(
+                "Compression",
+                format!("{}:{}", compression, quality).as_str(),
+            );
+        }
+
+        if ext == "jxl" {
+            if let Some(effort) = options.jxl_effort() {
+                self.set_attr("jpegxl:effort", effort as i32);
+            }
+            if options.lossless {
+                self.set_attr("jpegxl:distance", 0.0f32);
+            }
+        }
+    }
+
     /// Get the oiio:Colorspace tag value
     pub fn colorspace(&self) -> Option<&str> {
         match self.get_attr("oiio:ColorSpace") {
@@ -679,6 +1206,35 @@ pub fn read<P: AsRef<std::path::Path>, T: Type, C: Color>(path: P) -> Result<Ima
     ImageInput::open(path, None)?.read()
 }
 
+/// Read image from disk, the header is checked against the limits in `options` before any pixel
+/// data is allocated
+pub fn read_with_options<P: AsRef<std::path::Path>, T: Type, C: Color>(
+    path: P,
+    options: &super::DecodeOptions,
+) -> Result<Image<T, C>, Error> {
+    let input = ImageInput::open(path, None)?;
+    options.check::<T, C>(&input.probe_info())?;
+    input.read()
+}
+
+/// List channel groups in an image file
+pub fn layers<P: AsRef<std::path::Path>>(path: P) -> Result<Vec<super::Layer>, Error> {
+    Ok(ImageInput::open(path, None)?.layers())
+}
+
+/// Read a single layer from a multi-layer image, such as an EXR render with AOVs
+pub fn open_layer<P: AsRef<std::path::Path>, T: Type, C: Color>(
+    path: P,
+    layer_name: impl AsRef<str>,
+) -> Result<Image<T, C>, Error> {
+    ImageInput::open(path, None)?.read_layer(layer_name)
+}
+
+/// Read deep image from disk
+pub fn read_deep<P: AsRef<std::path::Path>>(path: P) -> Result<DeepImage, Error> {
+    ImageInput::open(path, None)?.read_deep()
+}
+
 /// Write image to disk
 pub fn write<P: AsRef<std::path::Path>, T: Type, C: Color>(
     path: P,
@@ -686,3 +1242,147 @@ pub fn write<P: AsRef<std::path::Path>, T: Type, C: Color>(
 ) -> Result<(), Error> {
     ImageOutput::create(path)?.write(image)
 }
+
+/// Write a MIP chain to disk, see `ImageOutput::write_mipmaps`
+pub fn write_mipmaps<P: AsRef<std::path::Path>, T: Type, C: Color>(
+    path: P,
+    levels: &[Image<T, C>],
+) -> Result<(), Error> {
+    ImageOutput::create(path)?.write_mipmaps(levels)
+}
+
+/// Write image to disk using the provided encoder options, the encoding speed is only used by
+/// JPEG XL because the OpenImageIO HEIF writer doesn't expose it
+pub fn write_with_options<P: AsRef<std::path::Path>, T: Type, C: Color>(
+    path: P,
+    image: &Image<T, C>,
+    options: &super::EncodeOptions,
+) -> Result<(), Error> {
+    options.check(path.as_ref())?;
+    let mut output = ImageOutput::create(&path)?;
+    output.spec_mut().set_encode_options(path.as_ref(), options);
+    match super::dither_for_output(path.as_ref(), image, options) {
+        Some(image) => output.write(&image),
+        None => output.write(image),
+    }
+}
+
+/// Channel order used to swap red and blue for BGR colors, files are always stored as RGB
+fn bgr_order<C: Color>() -> Option<Vec<Channel>> {
+    if !C::BGR_ORDER {
+        return None;
+    }
+    let mut order: Vec<Channel> = (0..C::CHANNELS).collect();
+    order.swap(0, 2);
+    Some(order)
+}
+
+/// Channel names written for `C`, files are always stored as RGB so the first three channels are
+/// `R`, `G` and `B`, single channel images use `Y` and the alpha channel is `A`
+fn channel_names<C: Color>() -> Vec<std::ffi::CString> {
+    (0..C::CHANNELS)
+        .map(|c| {
+            let name = match c {
+                _ if C::CHANNELS == 1 => "Y".to_string(),
+                _ if C::ALPHA == Some(c) => "A".to_string(),
+                0..=2 => ["R", "G", "B"][c].to_string(),
+                _ => format!("channel{}", c),
+            };
+            std::ffi::CString::new(name).expect("channel names don't contain NUL")
+        })
+        .collect()
+}
+
+/// Copy and free a heap allocated `std::string`
+fn take_string(s: *mut u8) -> String {
+    let len = unsafe {
+        cpp!([s as "std::string*"] -> usize as "size_t" {
+            return s->size();
+        })
+    };
+
+    let mut data = vec![0u8; len];
+    let data_ptr = data.as_mut_ptr();
+    unsafe {
+        cpp!([s as "std::string*", data_ptr as "char*", len as "size_t"] {
+            memcpy(data_ptr, s->data(), len);
+            delete s;
+        })
+    };
+    String::from_utf8_lossy(&data).into_owned()
+}
+
+/// Take the last global OpenImageIO error message, this clears the pending error
+fn last_error() -> String {
+    take_string(unsafe {
+        cpp!([] -> *mut u8 as "std::string*" {
+            return new std::string(OIIO::geterror());
+        })
+    })
+}
+
+/// Get a global OpenImageIO string attribute
+fn global_attr(name: &str) -> String {
+    let name_str = std::ffi::CString::new(name.as_bytes().to_vec()).unwrap();
+    let name_ptr = name_str.as_ptr();
+    let len = unsafe {
+        cpp!([name_ptr as "const char*"] -> usize as "size_t" {
+            return OIIO::get_string_attribute(name_ptr).size();
+        })
+    };
+
+    let mut data = vec![0u8; len];
+    let data_ptr = data.as_mut_ptr();
+    unsafe {
+        cpp!([name_ptr as "const char*", data_ptr as "char*", len as "size_t"] {
+            std::string s = OIIO::get_string_attribute(name_ptr);
+            memcpy(data_ptr, s.data(), std::min(len, s.size()));
+        })
+    };
+    String::from_utf8_lossy(&data).into_owned()
+}
+
+/// List file extensions that can be written by the OpenImageIO plugins available at runtime
+pub fn supported_formats() -> Vec<String> {
+    let outputs = global_attr("output_format_list");
+    let outputs: Vec<&str> = outputs.split(',').collect();
+
+    // `extension_list` is formatted as `format:ext,ext;format:ext`
+    let mut formats: Vec<String> = global_attr("extension_list")
+        .split(';')
+        .filter_map(|entry| entry.split_once(':'))
+        .filter(|(format, _)| outputs.contains(format))
+        .flat_map(|(_, exts)| exts.split(','))
+        .map(|ext| ext.to_ascii_lowercase())
+        .collect();
+    formats.sort();
+    formats.dedup();
+    formats
+}
+
+/// List formats that can be read or written by the OpenImageIO plugins available at runtime
+pub fn formats() -> Vec<super::FormatInfo> {
+    let inputs = global_attr("input_format_list");
+    let inputs: Vec<&str> = inputs.split(',').collect();
+    let outputs = global_attr("output_format_list");
+    let outputs: Vec<&str> = outputs.split(',').collect();
+
+    global_attr("extension_list")
+        .split(';')
+        .filter_map(|entry| entry.split_once(':'))
+        .map(|(format, exts)| {
+            let extensions = exts.split(',').map(|e| e.to_ascii_lowercase()).collect();
+            super::FormatInfo::new(
+                format,
+                extensions,
+                inputs.contains(&format),
+                outputs.contains(&format),
+            )
+        })
+        .collect()
+}
+
+/// Read image properties from the file header without decoding pixels
+pub fn probe<P: AsRef<std::path::Path>>(path: P) -> Result<super::ProbeInfo, Error> {
+    Ok(ImageInput::open(path, None)?.probe_info())
+}
@@ -0,0 +1,666 @@
+use crate::*;
+
+use std::collections::VecDeque;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+/// End of image marker appended to truncated JPEG streams
+const JPEG_EOI: [u8; 2] = [0xff, 0xd9];
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+/// Adam7 passes as `(x, y, x step, y step, block width, block height)`, each pixel of a pass is
+/// drawn as a block covering the pixels filled in by later passes
+const ADAM7: [(usize, usize, usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8, 8, 8),
+    (4, 0, 8, 8, 4, 8),
+    (0, 4, 4, 8, 4, 4),
+    (2, 0, 4, 4, 2, 4),
+    (0, 2, 2, 4, 2, 2),
+    (1, 0, 2, 2, 1, 2),
+    (0, 1, 1, 2, 1, 1),
+];
+
+/// Incremental parser state for the supported container formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stream {
+    Unknown,
+    Jpeg { progressive: bool, in_scan: bool },
+    Png { interlaced: bool, image_data: bool },
+}
+
+/// Bytes that haven't been consumed by the PNG reader yet, reading past the end reports
+/// `UnexpectedEof` which the reader can resume from once more data arrives
+#[derive(Debug, Clone, Default)]
+struct Pending(Arc<Mutex<VecDeque<u8>>>);
+
+impl Pending {
+    fn extend(&self, bytes: &[u8]) {
+        let mut pending = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        pending.extend(bytes);
+    }
+}
+
+impl Read for Pending {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut pending = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        pending.read(buf)
+    }
+}
+
+/// Decodes an image as bytes arrive, producing successively refined previews
+///
+/// Previews are produced after each complete scan of a progressive JPEG and after each complete
+/// `IDAT` chunk of an interlaced PNG, other images are only decoded once all of the data has
+/// arrived. Everything is decoded in memory: PNG rows are decoded once as their data arrives,
+/// while JPEG previews decode the scans received so far again since the JPEG decoder can't resume
+/// a partially decoded image
+///
+/// Use `ProgressiveDecoder::with_options` to check the image size against `DecodeOptions` before
+/// any pixel data is allocated when decoding untrusted streams
+///
+/// ```rust,no_run
+/// use image2::io::ProgressiveDecoder;
+///
+/// let mut decoder = ProgressiveDecoder::new();
+/// # let chunks: Vec<Vec<u8>> = vec![];
+/// for chunk in chunks {
+///     if let Some(preview) = decoder.push(&chunk).unwrap() {
+///         println!("preview: {}x{}", preview.width(), preview.height());
+///     }
+/// }
+/// let image = decoder.finish().unwrap();
+/// ```
+pub struct ProgressiveDecoder {
+    data: Vec<u8>,
+    stream: Stream,
+    options: super::DecodeOptions,
+
+    /// Position of the parser in `data`
+    pos: usize,
+
+    /// End offsets of each refinement found so far
+    boundaries: Vec<usize>,
+    complete: bool,
+    decoded: usize,
+
+    /// Input for the PNG reader and the number of bytes of `data` passed to it
+    pending: Pending,
+    fed: usize,
+    png: Option<png::Reader<Pending>>,
+
+    /// Image that PNG rows are drawn into and the number of rows decoded so far
+    canvas: Option<Image<u8, Rgb>>,
+    rows: usize,
+}
+
+impl std::fmt::Debug for ProgressiveDecoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressiveDecoder")
+            .field("len", &self.data.len())
+            .field("stream", &self.stream)
+            .field("pos", &self.pos)
+            .field("boundaries", &self.boundaries)
+            .field("complete", &self.complete)
+            .field("decoded", &self.decoded)
+            .field("rows", &self.rows)
+            .finish()
+    }
+}
+
+impl Default for ProgressiveDecoder {
+    fn default() -> Self {
+        ProgressiveDecoder::new()
+    }
+}
+
+impl ProgressiveDecoder {
+    /// Create a new, empty decoder
+    pub fn new() -> ProgressiveDecoder {
+        ProgressiveDecoder::with_options(super::DecodeOptions::default())
+    }
+
+    /// Create a new, empty decoder that returns `Error::LimitExceeded` from `push` or `finish`
+    /// once the image header shows that `options` aren't met
+    pub fn with_options(options: super::DecodeOptions) -> ProgressiveDecoder {
+        ProgressiveDecoder {
+            data: Vec::new(),
+            stream: Stream::Unknown,
+            options,
+            pos: 0,
+            boundaries: Vec::new(),
+            complete: false,
+            decoded: 0,
+            pending: Pending::default(),
+            fed: 0,
+            png: None,
+            canvas: None,
+            rows: 0,
+        }
+    }
+
+    /// Data received so far
+    pub fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns true once the end of the image has been received
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Number of refinements that can be decoded from the data received so far
+    pub fn available_passes(&self) -> usize {
+        self.boundaries.len()
+    }
+
+    /// Add more data, returns a new preview when the data completes another refinement. Decode
+    /// errors for partial JPEG data other than `Error::LimitExceeded` are ignored since a scan
+    /// may depend on tables that haven't arrived yet
+    pub fn push(&mut self, bytes: &[u8]) -> Result<Option<Image<u8, Rgb>>, Error> {
+        self.data.extend_from_slice(bytes);
+        self.parse();
+        if let Stream::Png {
+            image_data: true, ..
+        } = self.stream
+        {
+            self.decode_png()?;
+        }
+
+        let end = match self.boundaries.last() {
+            Some(end) if self.boundaries.len() > self.decoded => *end,
+            _ => return Ok(None),
+        };
+        self.decoded = self.boundaries.len();
+
+        match self.stream {
+            Stream::Jpeg { .. } if self.complete && end == self.data.len() => {
+                decode_jpeg(self.data.as_slice(), &self.options).map(Some)
+            }
+            Stream::Jpeg { .. } => {
+                match decode_jpeg((&self.data[..end]).chain(&JPEG_EOI[..]), &self.options) {
+                    Ok(image) => Ok(Some(image)),
+                    Err(err @ Error::LimitExceeded { .. }) => Err(err),
+                    Err(_) => Ok(None),
+                }
+            }
+            Stream::Png { .. } => Ok(self.canvas.clone()),
+            Stream::Unknown => Ok(None),
+        }
+    }
+
+    /// Decode all of the data received, this can be called before the end of the image has
+    /// arrived to decode as much as possible
+    pub fn finish(mut self) -> Result<Image<u8, Rgb>, Error> {
+        match self.stream {
+            Stream::Jpeg { .. } => match self.boundaries.last() {
+                Some(&end) if !self.complete => {
+                    decode_jpeg((&self.data[..end]).chain(&JPEG_EOI[..]), &self.options)
+                }
+                _ => decode_jpeg(self.data.as_slice(), &self.options),
+            },
+            Stream::Png { .. } => {
+                self.decode_png()?;
+                self.canvas
+                    .take()
+                    .ok_or_else(|| Error::Message("png: missing image data".into()))
+            }
+            Stream::Unknown => Err(Error::Message(
+                "progressive decoding is only supported for JPEG and PNG images".into(),
+            )),
+        }
+    }
+
+    /// Decode the PNG rows that have arrived since the last call
+    fn decode_png(&mut self) -> Result<(), Error> {
+        self.pending.extend(&self.data[self.fed..]);
+        self.fed = self.data.len();
+
+        let reader = match &mut self.png {
+            Some(reader) => reader,
+            None => {
+                let mut decoder = png::Decoder::new(self.pending.clone());
+                decoder.set_transformations(png::Transformations::normalize_to_color8());
+                let reader = decoder.read_info().map_err(png_error)?;
+                let info = reader.info();
+                let size = Size::new(info.width as usize, info.height as usize);
+                check_size(&self.options, size)?;
+                self.canvas = Some(Image::try_new(size)?);
+                self.png.insert(reader)
+            }
+        };
+        let canvas = match &mut self.canvas {
+            Some(canvas) => canvas,
+            None => return Ok(()),
+        };
+        let (width, height) = (canvas.width(), canvas.height());
+        let passes = if reader.info().interlaced {
+            &ADAM7[..]
+        } else {
+            &[(0, 0, 1, 1, 1, 1)][..]
+        };
+        let channels = reader.output_color_type().0.samples();
+        let dest = canvas.data_mut();
+
+        loop {
+            let row = match reader.next_row() {
+                Ok(Some(row)) => row,
+                Ok(None) => return Ok(()),
+                Err(png::DecodingError::IoError(e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    return Ok(())
+                }
+                Err(e) => return Err(png_error(e)),
+            };
+
+            // Find the pass and line of the row, passes without any pixels are skipped
+            let mut line = self.rows;
+            self.rows += 1;
+            let mut pass = None;
+            for &(x0, y0, dx, dy, bw, bh) in passes {
+                let lines = if width > x0 && height > y0 {
+                    (height - y0).div_ceil(dy)
+                } else {
+                    0
+                };
+                if line < lines {
+                    pass = Some((x0, y0 + line * dy, dx, bw, bh));
+                    break;
+                }
+                line -= lines;
+            }
+            let (x0, y, dx, bw, bh) = match pass {
+                Some(pass) => pass,
+                None => return Ok(()),
+            };
+
+            for (i, px) in row.data().chunks_exact(channels).enumerate() {
+                let rgb = if channels < 3 {
+                    [px[0]; 3]
+                } else {
+                    [px[0], px[1], px[2]]
+                };
+                let x = x0 + i * dx;
+                for by in y..(y + bh).min(height) {
+                    for bx in x..(x + bw).min(width) {
+                        let index = (by * width + bx) * 3;
+                        dest[index..index + 3].copy_from_slice(&rgb);
+                    }
+                }
+            }
+        }
+    }
+
+    fn parse(&mut self) {
+        if self.stream == Stream::Unknown {
+            if self.data.starts_with(&[0xff, 0xd8]) {
+                self.stream = Stream::Jpeg {
+                    progressive: false,
+                    in_scan: false,
+                };
+                self.pos = 2;
+            } else if self.data.starts_with(&PNG_SIGNATURE) {
+                self.stream = Stream::Png {
+                    interlaced: false,
+                    image_data: false,
+                };
+                self.pos = PNG_SIGNATURE.len();
+            } else {
+                return;
+            }
+        }
+
+        while !self.complete {
+            let advanced = match self.stream {
+                Stream::Jpeg { .. } => self.parse_jpeg(),
+                Stream::Png { .. } => self.parse_png(),
+                Stream::Unknown => false,
+            };
+            if !advanced {
+                break;
+            }
+        }
+    }
+
+    /// Parse the next JPEG marker segment, returns false when more data is needed
+    fn parse_jpeg(&mut self) -> bool {
+        let (mut progressive, mut in_scan) = match self.stream {
+            Stream::Jpeg {
+                progressive,
+                in_scan,
+            } => (progressive, in_scan),
+            _ => return false,
+        };
+        let data = &self.data;
+
+        if in_scan {
+            // Skip entropy-coded data up to the next marker that isn't stuffing or a restart
+            let mut i = self.pos;
+            while i + 1 < data.len() {
+                if data[i] == 0xff && data[i + 1] != 0 && !(0xd0..=0xd7).contains(&data[i + 1]) {
+                    break;
+                }
+                i += 1;
+            }
+            if i + 1 >= data.len() {
+                self.pos = i;
+                return false;
+            }
+            self.pos = i;
+            in_scan = false;
+            if progressive {
+                self.boundaries.push(i);
+            }
+            self.stream = Stream::Jpeg {
+                progressive,
+                in_scan,
+            };
+        }
+
+        let data = &self.data;
+        let pos = self.pos;
+        if pos + 2 > data.len() {
+            return false;
+        }
+        let marker = data[pos + 1];
+        if data[pos] != 0xff || marker == 0xff {
+            // Fill bytes before a marker
+            self.pos += 1;
+        } else if marker == 0xd9 {
+            self.pos += 2;
+            self.complete = true;
+            self.boundaries.push(self.pos);
+        } else if (0xd0..=0xd7).contains(&marker) || marker == 0x01 {
+            self.pos += 2;
+        } else {
+            if pos + 4 > data.len() {
+                return false;
+            }
+            let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+            if pos + 2 + len > data.len() {
+                return false;
+            }
+            if marker == 0xc2 || marker == 0xc6 || marker == 0xca || marker == 0xce {
+                progressive = true;
+            }
+            in_scan = marker == 0xda;
+            self.pos += 2 + len;
+        }
+
+        self.stream = Stream::Jpeg {
+            progressive,
+            in_scan,
+        };
+        true
+    }
+
+    /// Parse the next PNG chunk, returns false when more data is needed
+    fn parse_png(&mut self) -> bool {
+        let (mut interlaced, mut image_data) = match self.stream {
+            Stream::Png {
+                interlaced,
+                image_data,
+            } => (interlaced, image_data),
+            _ => return false,
+        };
+        let data = &self.data;
+        let pos = self.pos;
+        if pos + 8 > data.len() {
+            return false;
+        }
+        let len = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
+        let end = pos + 12 + len as usize;
+        if end > data.len() {
+            return false;
+        }
+
+        match &data[pos + 4..pos + 8] {
+            b"IHDR" if len >= 13 => interlaced = data[pos + 8 + 12] == 1,
+            b"IDAT" => {
+                image_data = true;
+                if interlaced {
+                    self.boundaries.push(end);
+                }
+            }
+            b"IEND" => {
+                self.complete = true;
+                self.boundaries.push(end);
+            }
+            _ => (),
+        }
+        self.pos = end;
+        self.stream = Stream::Png {
+            interlaced,
+            image_data,
+        };
+        true
+    }
+}
+
+fn png_error(err: png::DecodingError) -> Error {
+    Error::Message(format!("png: {err}"))
+}
+
+fn jpeg_error(err: jpeg_decoder::Error) -> Error {
+    Error::Message(format!("jpeg: {err}"))
+}
+
+/// Check the size of the decoded `Image<u8, Rgb>` against `options`
+fn check_size(options: &super::DecodeOptions, size: Size) -> Result<(), Error> {
+    options.check::<u8, Rgb>(&super::ProbeInfo {
+        size,
+        base_type: super::BaseType::UInt8,
+        channels: Rgb::CHANNELS,
+        channel_names: Vec::new(),
+        frames: 1,
+        attributes: Default::default(),
+    })
+}
+
+/// Decode a JPEG image from memory, the frame header is checked against `options` before the
+/// scans are decoded
+fn decode_jpeg(data: impl Read, options: &super::DecodeOptions) -> Result<Image<u8, Rgb>, Error> {
+    let mut decoder = jpeg_decoder::Decoder::new(data);
+    if let Some(max) = options.max_bytes {
+        decoder.set_max_decoding_buffer_size(max);
+    }
+    decoder.read_info().map_err(jpeg_error)?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| Error::Message("jpeg: missing frame header".into()))?;
+    check_size(
+        options,
+        Size::new(info.width as usize, info.height as usize),
+    )?;
+    let pixels = decoder.decode().map_err(jpeg_error)?;
+    let rgb: Vec<u8> = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => pixels.iter().flat_map(|&l| [l; 3]).collect(),
+        jpeg_decoder::PixelFormat::L16 => pixels
+            .chunks_exact(2)
+            .flat_map(|l| [(u16::from_ne_bytes([l[0], l[1]]) >> 8) as u8; 3])
+            .collect(),
+        jpeg_decoder::PixelFormat::RGB24 => pixels,
+        jpeg_decoder::PixelFormat::CMYK32 => pixels
+            .chunks_exact(4)
+            .flat_map(|p| {
+                let k = 255 - p[3] as u16;
+                [0, 1, 2].map(|i| ((255 - p[i] as u16) * k / 255) as u8)
+            })
+            .collect(),
+    };
+    Image::new_with_data((info.width as usize, info.height as usize), rgb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(decoder: &mut ProgressiveDecoder, bytes: &[u8]) {
+        decoder.data.extend_from_slice(bytes);
+        decoder.parse();
+    }
+
+    /// PNG chunk with a valid CRC
+    fn chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut c = (data.len() as u32).to_be_bytes().to_vec();
+        c.extend_from_slice(kind);
+        c.extend_from_slice(data);
+        let mut crc = !0u32;
+        for &b in &c[4..] {
+            crc ^= b as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xedb88320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        c.extend_from_slice(&(!crc).to_be_bytes());
+        c
+    }
+
+    #[test]
+    fn test_progressive_jpeg_scans() {
+        let mut decoder = ProgressiveDecoder::new();
+        feed(&mut decoder, &[0xff, 0xd8, 0xff, 0xc2, 0x00, 0x04, 0x08]);
+        assert_eq!(decoder.available_passes(), 0);
+
+        // Finish the frame header and start the first scan, stuffed bytes and restart markers
+        // don't end the scan
+        feed(&mut decoder, &[0x00, 0xff, 0xda, 0x00, 0x03, 0x01]);
+        feed(
+            &mut decoder,
+            &[0x12, 0xff, 0x00, 0x34, 0xff, 0xd0, 0x56, 0xff],
+        );
+        assert_eq!(decoder.available_passes(), 0);
+
+        // The next marker completes the first scan
+        feed(&mut decoder, &[0xda, 0x00, 0x03, 0x02, 0x78, 0x9a]);
+        assert_eq!(decoder.available_passes(), 1);
+        assert_eq!(decoder.boundaries[0], 20);
+        assert!(!decoder.is_complete());
+
+        feed(&mut decoder, &[0xff, 0xd9]);
+        assert!(decoder.is_complete());
+        assert_eq!(decoder.boundaries.last(), Some(&decoder.bytes().len()));
+    }
+
+    #[test]
+    fn test_baseline_jpeg_and_png() {
+        // Baseline JPEG scans are only decoded once the image is complete
+        let mut decoder = ProgressiveDecoder::new();
+        feed(
+            &mut decoder,
+            &[
+                0xff, 0xd8, 0xff, 0xc0, 0x00, 0x02, 0xff, 0xda, 0x00, 0x02, 0x11, 0x22,
+            ],
+        );
+        feed(&mut decoder, &[0xff, 0xd9]);
+        assert_eq!(decoder.available_passes(), 1);
+        assert!(decoder.is_complete());
+
+        // Interlaced PNG, every IDAT chunk is a refinement
+        let chunk = |kind: &[u8], data: &[u8]| {
+            let mut c = (data.len() as u32).to_be_bytes().to_vec();
+            c.extend_from_slice(kind);
+            c.extend_from_slice(data);
+            c.extend_from_slice(&[0; 4]);
+            c
+        };
+        let mut ihdr = vec![0; 13];
+        ihdr[12] = 1;
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend(chunk(b"IHDR", &ihdr));
+        png.extend(chunk(b"IDAT", &[1, 2, 3]));
+        png.extend(chunk(b"IDAT", &[4, 5]));
+        let mut decoder = ProgressiveDecoder::new();
+        feed(&mut decoder, &png[..png.len() - 3]);
+        assert_eq!(decoder.available_passes(), 1);
+        feed(&mut decoder, &png[png.len() - 3..]);
+        assert_eq!(decoder.available_passes(), 2);
+        feed(&mut decoder, &chunk(b"IEND", &[]));
+        assert!(decoder.is_complete());
+
+        // Unknown data is never decoded early
+        let mut decoder = ProgressiveDecoder::new();
+        feed(&mut decoder, b"GIF89a");
+        assert_eq!(decoder.available_passes(), 0);
+    }
+
+    #[test]
+    fn test_interlaced_png_previews() {
+        // 2x2 RGB image with Adam7 interlacing, the rows of passes 1, 6 and 7 are stored
+        // uncompressed and split across three IDAT chunks
+        let rows: [&[u8]; 3] = [&[0, 10, 20, 30], &[0, 40, 50, 60], &[0, 1, 2, 3, 4, 5, 6]];
+        let raw = rows.concat();
+        let (mut a, mut b) = (1u32, 0u32);
+        for &x in &raw {
+            a = (a + x as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        let len = raw.len() as u16;
+        let mut first = vec![0x78, 0x01, 0x01];
+        first.extend_from_slice(&len.to_le_bytes());
+        first.extend_from_slice(&(!len).to_le_bytes());
+        first.extend_from_slice(rows[0]);
+        let mut last = rows[2].to_vec();
+        last.extend_from_slice(&((b << 16) | a).to_be_bytes());
+
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend(chunk(b"IHDR", &[0, 0, 0, 2, 0, 0, 0, 2, 8, 2, 0, 0, 1]));
+        let idat = [
+            chunk(b"IDAT", &first),
+            chunk(b"IDAT", rows[1]),
+            chunk(b"IDAT", &last),
+        ];
+
+        // The first pass covers the whole image
+        let mut decoder = ProgressiveDecoder::new();
+        assert!(decoder.push(&png).unwrap().is_none());
+        let preview = decoder.push(&idat[0]).unwrap().unwrap();
+        assert_eq!(preview.data(), [10, 20, 30].repeat(4).as_slice());
+
+        // Pass 6 fills in the right column
+        let preview = decoder.push(&idat[1]).unwrap().unwrap();
+        assert_eq!(
+            preview.data(),
+            [[10, 20, 30], [40, 50, 60]].concat().repeat(2)
+        );
+
+        decoder.push(&idat[2]).unwrap();
+        decoder.push(&chunk(b"IEND", &[])).unwrap();
+        assert!(decoder.is_complete());
+        let image = decoder.finish().unwrap();
+        assert_eq!(image.data(), &[10, 20, 30, 40, 50, 60, 1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_decode_options() {
+        let options = crate::io::DecodeOptions::new()
+            .with_max_width(4096)
+            .with_max_height(4096);
+
+        // PNG header declaring a 65536x65536 image is rejected before the canvas is allocated
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend(chunk(b"IHDR", &[0, 1, 0, 0, 0, 1, 0, 0, 8, 2, 0, 0, 0]));
+        png.extend(chunk(b"IDAT", &[]));
+        let mut decoder = ProgressiveDecoder::with_options(options);
+        assert!(matches!(
+            decoder.push(&png),
+            Err(Error::LimitExceeded { limit: "width", .. })
+        ));
+
+        // JPEG frame header declaring a 60000x60000 image
+        let jpeg = [
+            0xff, 0xd8, 0xff, 0xc0, 0x00, 0x0b, 0x08, 0xea, 0x60, 0xea, 0x60, 0x01, 0x01, 0x11,
+            0x00, 0xff, 0xd9,
+        ];
+        let mut decoder = ProgressiveDecoder::with_options(options);
+        assert!(matches!(
+            decoder.push(&jpeg),
+            Err(Error::LimitExceeded { limit: "width", .. })
+        ));
+    }
+}
@@ -2,12 +2,49 @@ use std::path::Path;
 
 use crate::*;
 
+/// Error returned by functions that need an I/O backend
+fn no_backend(path: &Path) -> crate::Error {
+    crate::Error::Message("no I/O backend enabled, use the `oiio` or `magick` feature".into())
+        .with_path(path)
+}
+
 /// Read image from disk this implementation is a stub, to enable I/O use the `oiio` trait to use the
 /// OpenImageIO backend, or `magick` to use the ImageMagick backend
 pub fn read<P: AsRef<Path>, T: Type, C: Color>(_path: P) -> Result<Image<T, C>, crate::Error> {
     unimplemented!()
 }
 
+/// Read image from disk after checking its header against `options`, this implementation is a
+/// stub, to enable I/O use the `oiio` feature to use the OpenImageIO backend, or `magick` to use
+/// the ImageMagick backend
+pub fn read_with_options<P: AsRef<Path>, T: Type, C: Color>(
+    path: P,
+    _options: &super::DecodeOptions,
+) -> Result<Image<T, C>, crate::Error> {
+    Err(no_backend(path.as_ref()))
+}
+
+/// List channel groups in an image file, this implementation is a stub, to enable I/O use the
+/// `oiio` feature
+pub fn layers<P: AsRef<Path>>(path: P) -> Result<Vec<super::Layer>, crate::Error> {
+    Err(no_backend(path.as_ref()))
+}
+
+/// Read a single layer from a multi-layer image, this implementation is a stub, to enable I/O use
+/// the `oiio` feature
+pub fn open_layer<P: AsRef<Path>, T: Type, C: Color>(
+    path: P,
+    _layer_name: impl AsRef<str>,
+) -> Result<Image<T, C>, crate::Error> {
+    Err(no_backend(path.as_ref()))
+}
+
+/// Read deep image from disk, this implementation is a stub, to read deep images enable the `oiio`
+/// feature
+pub fn read_deep<P: AsRef<Path>>(path: P) -> Result<DeepImage, crate::Error> {
+    Err(no_backend(path.as_ref()))
+}
+
 /// Write image to disk, this implementation is a stub, to enable I/O use the `oiio` trait to use the
 /// OpenImageIO backend, or `magick` to use the ImageMagick backend
 pub fn write<P: AsRef<Path>, T: Type, C: Color>(
@@ -16,3 +53,40 @@ pub fn write<P: AsRef<Path>, T: Type, C: Color>(
 ) -> Result<(), crate::Error> {
     unimplemented!()
 }
+
+/// Write a MIP chain to disk, this implementation is a stub, to enable I/O use the `oiio`
+/// feature
+pub fn write_mipmaps<P: AsRef<Path>, T: Type, C: Color>(
+    path: P,
+    _levels: &[Image<T, C>],
+) -> Result<(), crate::Error> {
+    Err(no_backend(path.as_ref()))
+}
+
+/// Write image to disk using the provided encoder options, this implementation is a stub, to enable
+/// I/O use the `oiio` feature to use the OpenImageIO backend, or `magick` to use the ImageMagick
+/// backend
+pub fn write_with_options<P: AsRef<Path>, T: Type, C: Color>(
+    path: P,
+    _image: &Image<T, C>,
+    _options: &super::EncodeOptions,
+) -> Result<(), crate::Error> {
+    Err(no_backend(path.as_ref()))
+}
+
+/// List file extensions that can be written, no formats are available without an I/O backend
+pub fn supported_formats() -> Vec<String> {
+    Vec::new()
+}
+
+/// List formats that can be read or written, no formats are available without an I/O backend
+pub fn formats() -> Vec<super::FormatInfo> {
+    Vec::new()
+}
+
+/// Read image properties without decoding pixels, this implementation is a stub, to enable I/O
+/// use the `oiio` feature to use the OpenImageIO backend, or `magick` to use the ImageMagick
+/// backend
+pub fn probe<P: AsRef<Path>>(path: P) -> Result<super::ProbeInfo, crate::Error> {
+    Err(no_backend(path.as_ref()))
+}
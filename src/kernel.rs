@@ -5,8 +5,9 @@ use crate::*;
 
 /// Used to determine the strategy when kernel processes edge of the image
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EdgeStrategy {
-    /// Constants
+    /// Pixels outside of the image are replaced by a constant value, see `Kernel::set_edge_constant`
     Constant,
     /// Extend
     Extend,
@@ -17,7 +18,16 @@ pub enum EdgeStrategy {
 }
 
 impl EdgeStrategy {
-    fn map_dimension(&self, value: isize, max: isize) -> usize {
+    /// Map a coordinate into the image, returns `None` if the coordinate is outside of the image
+    /// and should be replaced by a constant value
+    pub(crate) fn map_point(&self, value: isize, max: isize) -> Option<usize> {
+        match self {
+            EdgeStrategy::Constant if value < 0 || value > max => None,
+            _ => Some(self.map_dimension(value, max)),
+        }
+    }
+
+    pub(crate) fn map_dimension(&self, value: isize, max: isize) -> usize {
         fn no_action(value: isize, _: isize) -> usize {
             value as usize
         }
@@ -36,25 +46,20 @@ impl EdgeStrategy {
         }
 
         fn wrap(value: isize, max: isize) -> usize {
-            let ret = if value < 0 {
-                max + value + 1
-            } else {
-                value % (max + 1)
-            };
-
-            ret as usize
+            value.rem_euclid(max + 1) as usize
         }
 
         fn mirror(value: isize, max: isize) -> usize {
-            let ret = if value < 0 {
-                -value
-            } else if value > max {
-                max - (value % (max + 1)) - 1
-            } else {
-                value
-            };
+            if max == 0 {
+                return 0;
+            }
 
-            ret as usize
+            let ret = value.rem_euclid(2 * max);
+            if ret > max {
+                (2 * max - ret) as usize
+            } else {
+                ret as usize
+            }
         }
 
         match self {
@@ -66,6 +71,38 @@ impl EdgeStrategy {
     }
 }
 
+/// Parameters for `Kernel::gabor`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gabor {
+    /// Standard deviation of the gaussian envelope
+    pub sigma: f64,
+
+    /// Orientation in degrees
+    pub angle: f64,
+
+    /// Wavelength of the sinusoidal factor in pixels
+    pub wavelength: f64,
+
+    /// Spatial aspect ratio
+    pub aspect_ratio: f64,
+
+    /// Phase offset in radians
+    pub phase: f64,
+}
+
+impl Default for Gabor {
+    fn default() -> Self {
+        Gabor {
+            sigma: 2.0,
+            angle: 0.0,
+            wavelength: 4.0,
+            aspect_ratio: 0.5,
+            phase: 0.0,
+        }
+    }
+}
+
 /// 2-dimensional convolution kernel
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -74,6 +111,10 @@ pub struct Kernel {
     cols: usize,
     data: Vec<Vec<f64>>,
     edge_strategy: EdgeStrategy,
+    #[cfg_attr(feature = "serde", serde(default))]
+    edge_constant: Vec<f64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    input: usize,
 }
 
 impl From<Vec<Vec<f64>>> for Kernel {
@@ -85,6 +126,8 @@ impl From<Vec<Vec<f64>>> for Kernel {
             rows: rows,
             cols: cols,
             edge_strategy: EdgeStrategy::Constant,
+            edge_constant: Vec::new(),
+            input: 0,
         }
     }
 }
@@ -102,6 +145,8 @@ impl<'a> From<&'a [&'a [f64]]> for Kernel {
             rows: rows,
             cols: cols,
             edge_strategy: EdgeStrategy::Constant,
+            edge_constant: Vec::new(),
+            input: 0,
         }
     }
 }
@@ -114,6 +159,8 @@ impl<const N: usize> From<[[f64; N]; N]> for Kernel {
             rows: N,
             cols: N,
             edge_strategy: EdgeStrategy::Constant,
+            edge_constant: Vec::new(),
+            input: 0,
         }
     }
 }
@@ -124,39 +171,90 @@ impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Kernel {
     }
 
     fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
-        let input_width = input.images[0].width() as isize;
-        let input_height = input.images[0].height() as isize;
+        let mut f = input.new_pixel();
+        for c in 0..f.len() {
+            f[c] = self.convolve(pt, input, c);
+        }
+        f.copy_to_slice(dest);
+    }
+}
+
+/// Applies a separate kernel to each channel, channels without a kernel are copied unchanged
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiKernel<const N: usize>(pub [Kernel; N]);
+
+/// serde only implements arrays up to 32 elements, so kernels are serialized as a sequence
+#[cfg(feature = "serde")]
+impl<const N: usize> serde::Serialize for MultiKernel<N> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.as_slice().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const N: usize> serde::Deserialize<'de> for MultiKernel<N> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let kernels = Vec::<Kernel>::deserialize(deserializer)?;
+        let len = kernels.len();
+        let kernels = kernels.try_into().map_err(|_| {
+            serde::de::Error::invalid_length(len, &format!("{} kernels", N).as_str())
+        })?;
+        Ok(MultiKernel(kernels))
+    }
+}
+
+impl<const N: usize> From<[Kernel; N]> for MultiKernel<N> {
+    fn from(kernels: [Kernel; N]) -> MultiKernel<N> {
+        MultiKernel(kernels)
+    }
+}
+
+impl<const N: usize, T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for MultiKernel<N> {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut f = input.new_pixel();
+        for c in 0..f.len() {
+            f[c] = match self.0.get(c) {
+                Some(kernel) => kernel.convolve(pt, input, c),
+                None => input.get_f(pt, c, None),
+            };
+        }
+        f.copy_to_slice(dest);
+    }
+}
+
+impl Kernel {
+    /// Convolve a single channel at `pt`
+    fn convolve<T: Type, C: Color>(&self, pt: Point, input: &Input<T, C>, c: usize) -> f64 {
+        let input_width = input.images[self.input].width() as isize;
+        let input_height = input.images[self.input].height() as isize;
 
         let r2 = (self.rows / 2) as isize;
         let c2 = (self.cols / 2) as isize;
-        let mut f = input.new_pixel();
-        let mut x: f64;
+        let mut sum = 0.0;
         for ky in -r2..=r2 {
             let kr = &self.data[(ky + r2) as usize];
-            let pty = (pt.y as isize + ky) as usize;
+            let pty = self
+                .edge_strategy
+                .map_point(pt.y as isize + ky, input_height - 1);
             for kx in -c2..=c2 {
                 let krc = kr[(kx + c2) as usize];
-                for c in 0..f.len() {
-                    x = input.get_f(
-                        (
-                            self.edge_strategy
-                                .map_dimension(pt.x as isize + kx, input_width - 1),
-                            self.edge_strategy
-                                .map_dimension(pty as isize, input_height - 1),
-                        ),
-                        c,
-                        Some(0),
-                    );
-
-                    f[c] += x * krc;
-                }
+                let ptx = self
+                    .edge_strategy
+                    .map_point(pt.x as isize + kx, input_width - 1);
+                let x = ptx
+                    .zip(pty)
+                    .and_then(|pt| input.try_get_f(pt, c, Some(self.input)))
+                    .unwrap_or_else(|| self.edge_constant.get(c).copied().unwrap_or_default());
+                sum += x * krc;
             }
         }
-        f.copy_to_slice(dest);
+        sum
     }
-}
 
-impl Kernel {
     /// Create a new kernel with the given number of rows and columns
     pub fn new(rows: usize, cols: usize) -> Kernel {
         let data = vec![vec![0.0; cols]; rows];
@@ -165,9 +263,26 @@ impl Kernel {
             rows: rows,
             cols: cols,
             edge_strategy: EdgeStrategy::Constant,
+            edge_constant: Vec::new(),
+            input: 0,
         }
     }
 
+    /// Number of rows
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Number of columns
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Get the value at the given row and column
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        self.data[row][col]
+    }
+
     /// Create a new, square kernel
     pub fn square(x: usize) -> Kernel {
         Self::new(x, x)
@@ -199,14 +314,26 @@ impl Kernel {
         k
     }
 
-    /// Generate gaussian blur kernel
+    /// Generate an `n`x`n` gaussian blur kernel
     pub fn gaussian(n: usize, std: f64) -> Kernel {
         assert!(n % 2 != 0);
-        let std2 = std * std;
-        let a = 1.0 / (2.0 * f64::consts::PI * std2);
-        let mut k = Kernel::create(n, n, |i, j| {
-            let x = (i * i + j * j) as f64 / (2.0 * std2);
-            a * f64::consts::E.powf(-1.0 * x)
+        Kernel::gaussian_with_radius(n / 2, std, std, 0.0)
+    }
+
+    /// Generate a centered, anisotropic gaussian kernel with standard deviations `sigma_x` and
+    /// `sigma_y`, rotated by `angle` degrees. The kernel size covers 3 standard deviations
+    pub fn gaussian_2d(sigma_x: f64, sigma_y: f64, angle: f64) -> Kernel {
+        let radius = Kernel::sigma_radius(sigma_x.max(sigma_y));
+        Kernel::gaussian_with_radius(radius, sigma_x, sigma_y, angle)
+    }
+
+    fn gaussian_with_radius(radius: usize, sigma_x: f64, sigma_y: f64, angle: f64) -> Kernel {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (sx2, sy2) = (sigma_x * sigma_x, sigma_y * sigma_y);
+        let mut k = Kernel::centered(radius, |x, y| {
+            let xr = x * cos + y * sin;
+            let yr = -x * sin + y * cos;
+            (-(xr * xr / (2.0 * sx2) + yr * yr / (2.0 * sy2))).exp()
         });
         k.normalize();
         k
@@ -232,6 +359,136 @@ impl Kernel {
         Self::gaussian(9, 1.4)
     }
 
+    /// Create a new, odd-sized square kernel and fill it by executing `f` with each (x, y) offset
+    /// from the center of the kernel
+    fn centered<F: Fn(f64, f64) -> f64>(radius: usize, f: F) -> Kernel {
+        let n = radius * 2 + 1;
+        let r = radius as f64;
+        Kernel::create(n, n, |i, j| f(i as f64 - r, j as f64 - r))
+    }
+
+    /// Kernel radius needed to cover 3 standard deviations
+    fn sigma_radius(sigma: f64) -> usize {
+        (sigma * 3.0).ceil().max(1.0) as usize
+    }
+
+    /// Linear motion blur of `len` pixels in the direction of `angle` (in degrees)
+    pub fn motion_blur(len: usize, angle: f64) -> Kernel {
+        let radius = len / 2;
+        let n = radius * 2 + 1;
+        let mut k = Kernel::square(n);
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let half = len.max(1) as f64 / 2.0;
+        let steps = (len.max(1) * 8) as isize;
+
+        // Supersample the line and splat each sample using bilinear weights
+        for s in -steps..=steps {
+            let t = half * s as f64 / steps as f64;
+            let x = radius as f64 + t * cos;
+            let y = radius as f64 - t * sin;
+            let (x0, y0) = (x.floor(), y.floor());
+            let (fx, fy) = (x - x0, y - y0);
+            for (dx, dy, w) in [
+                (0, 0, (1.0 - fx) * (1.0 - fy)),
+                (1, 0, fx * (1.0 - fy)),
+                (0, 1, (1.0 - fx) * fy),
+                (1, 1, fx * fy),
+            ] {
+                let (i, j) = (x0 as isize + dx, y0 as isize + dy);
+                if i >= 0 && j >= 0 && (i as usize) < n && (j as usize) < n {
+                    k.data[j as usize][i as usize] += w;
+                }
+            }
+        }
+        k.normalize();
+        k
+    }
+
+    /// Flat, anti-aliased disk with the given radius
+    pub fn disk(radius: usize) -> Kernel {
+        const SAMPLES: usize = 4;
+        let r = radius as f64 + 0.5;
+        let mut k = Kernel::centered(radius, |x, y| {
+            let mut count = 0;
+            for sy in 0..SAMPLES {
+                for sx in 0..SAMPLES {
+                    let px = x - 0.5 + (sx as f64 + 0.5) / SAMPLES as f64;
+                    let py = y - 0.5 + (sy as f64 + 0.5) / SAMPLES as f64;
+                    if px * px + py * py <= r * r {
+                        count += 1;
+                    }
+                }
+            }
+            count as f64 / (SAMPLES * SAMPLES) as f64
+        });
+        k.normalize();
+        k
+    }
+
+    /// Laplacian of gaussian, the kernel sums to zero
+    pub fn log(sigma: f64) -> Kernel {
+        let s2 = sigma * sigma;
+        let mut k = Kernel::centered(Kernel::sigma_radius(sigma), |x, y| {
+            let r2 = (x * x + y * y) / (2.0 * s2);
+            -1.0 / (f64::consts::PI * s2 * s2) * (1.0 - r2) * (-r2).exp()
+        });
+        let mean = k.data.iter().flatten().sum::<f64>() / (k.rows * k.cols) as f64;
+        k.data.iter_mut().flatten().for_each(|x| *x -= mean);
+        k
+    }
+
+    /// Difference of gaussians, `gaussian(sigma1) - gaussian(sigma2)`
+    pub fn dog(sigma1: f64, sigma2: f64) -> Kernel {
+        let radius = Kernel::sigma_radius(sigma1.max(sigma2));
+        Kernel::gaussian_with_radius(radius, sigma1, sigma1, 0.0)
+            - Kernel::gaussian_with_radius(radius, sigma2, sigma2, 0.0)
+    }
+
+    /// Gabor filter
+    pub fn gabor(params: Gabor) -> Kernel {
+        let (sin, cos) = params.angle.to_radians().sin_cos();
+        let s2 = params.sigma * params.sigma;
+        let g2 = params.aspect_ratio * params.aspect_ratio;
+        Kernel::centered(Kernel::sigma_radius(params.sigma), |x, y| {
+            let xr = x * cos + y * sin;
+            let yr = -x * sin + y * cos;
+            (-(xr * xr + g2 * yr * yr) / (2.0 * s2)).exp()
+                * (2.0 * f64::consts::PI * xr / params.wavelength + params.phase).cos()
+        })
+    }
+
+    /// Rotate the kernel around its center by `angle` degrees using bilinear interpolation, the
+    /// size of the kernel is unchanged so values rotated past the edges are dropped
+    pub fn rotate(&self, angle: f64) -> Kernel {
+        let (sin, cos) = (-angle).to_radians().sin_cos();
+        let cx = (self.cols / 2) as f64;
+        let cy = (self.rows / 2) as f64;
+        let get = |i: isize, j: isize| {
+            if i < 0 || j < 0 || i as usize >= self.cols || j as usize >= self.rows {
+                0.0
+            } else {
+                self.data[j as usize][i as usize]
+            }
+        };
+
+        let mut k = Kernel::create(self.rows, self.cols, |i, j| {
+            let (x, y) = (i as f64 - cx, cy - j as f64);
+            let sx = cx + x * cos - y * sin;
+            let sy = cy - (x * sin + y * cos);
+            let (x0, y0) = (sx.floor(), sy.floor());
+            let (fx, fy) = (sx - x0, sy - y0);
+            let (x0, y0) = (x0 as isize, y0 as isize);
+            get(x0, y0) * (1.0 - fx) * (1.0 - fy)
+                + get(x0 + 1, y0) * fx * (1.0 - fy)
+                + get(x0, y0 + 1) * (1.0 - fx) * fy
+                + get(x0 + 1, y0 + 1) * fx * fy
+        });
+        k.edge_strategy = self.edge_strategy.clone();
+        k.edge_constant = self.edge_constant.clone();
+        k.input = self.input;
+        k
+    }
+
     /// Sobel X
     pub fn sobel_x() -> Kernel {
         Kernel {
@@ -243,6 +500,8 @@ impl Kernel {
                 vec![1.0, 0.0, -1.0],
             ],
             edge_strategy: EdgeStrategy::Constant,
+            edge_constant: Vec::new(),
+            input: 0,
         }
     }
 
@@ -257,6 +516,8 @@ impl Kernel {
                 vec![-1.0, -2.0, -1.0],
             ],
             edge_strategy: EdgeStrategy::Constant,
+            edge_constant: Vec::new(),
+            input: 0,
         }
     }
 
@@ -270,10 +531,121 @@ impl Kernel {
         Kernel::sobel_x() + Kernel::sobel_y()
     }
 
+    /// Emboss, lit from the bottom-right, the kernel sums to 1 so flat areas are preserved
+    pub fn emboss() -> Kernel {
+        Kernel::from([[-2., -1., 0.], [-1., 1., 1.], [0., 1., 2.]])
+    }
+
+    /// Ridge detection, responds to thin lines in every direction
+    pub fn ridge() -> Kernel {
+        Kernel::from([[-1., -1., -1.], [-1., 8., -1.], [-1., -1., -1.]])
+    }
+
+    /// Edge enhancement, adds half of the 8-neighbour Laplacian to the image
+    pub fn edge_enhance() -> Kernel {
+        Kernel::from([[-0.5, -0.5, -0.5], [-0.5, 5., -0.5], [-0.5, -0.5, -0.5]])
+    }
+
+    /// Sharpen using the 4-neighbour Laplacian
+    pub fn sharpen() -> Kernel {
+        Kernel::from([[0., -1., 0.], [-1., 5., -1.], [0., -1., 0.]])
+    }
+
+    /// Sharpen using the 8-neighbour Laplacian
+    pub fn sharpen_strong() -> Kernel {
+        Kernel::from([[-1., -1., -1.], [-1., 9., -1.], [-1., -1., -1.]])
+    }
+
     /// Changes how kernel processes images near edges
     pub fn set_edge_strategy(&mut self, edge_strategy: EdgeStrategy) {
         self.edge_strategy = edge_strategy
     }
+
+    /// Select which input image the kernel is applied to, by default the first input is used,
+    /// which is the intermediate image when the kernel is part of a pipeline
+    pub fn with_input(mut self, index: usize) -> Kernel {
+        self.input = index;
+        self
+    }
+
+    /// Use `EdgeStrategy::Constant` with the given normalized pixel value, missing channels are
+    /// filled with 0. By default the constant is black/transparent
+    pub fn set_edge_constant(&mut self, value: &[f64]) {
+        self.edge_strategy = EdgeStrategy::Constant;
+        self.edge_constant = value.to_vec();
+    }
+}
+
+/// Named kernels, used to refer to kernels by name from configuration files
+pub mod presets {
+    use std::collections::BTreeMap;
+    use std::sync::{OnceLock, RwLock};
+
+    use super::Kernel;
+
+    fn registry() -> &'static RwLock<BTreeMap<String, Kernel>> {
+        static REGISTRY: OnceLock<RwLock<BTreeMap<String, Kernel>>> = OnceLock::new();
+        REGISTRY.get_or_init(|| {
+            let builtin = [
+                ("box_3x3", Kernel::from([[1. / 9.; 3]; 3])),
+                ("gaussian_3x3", Kernel::gaussian_3x3()),
+                ("gaussian_5x5", Kernel::gaussian_5x5()),
+                ("gaussian_7x7", Kernel::gaussian_7x7()),
+                ("gaussian_9x9", Kernel::gaussian_9x9()),
+                ("sobel_x", Kernel::sobel_x()),
+                ("sobel_y", Kernel::sobel_y()),
+                ("sobel", Kernel::sobel()),
+                ("laplacian", Kernel::laplacian()),
+                ("emboss", Kernel::emboss()),
+                ("ridge", Kernel::ridge()),
+                ("edge_enhance", Kernel::edge_enhance()),
+                ("sharpen", Kernel::sharpen()),
+                ("sharpen_strong", Kernel::sharpen_strong()),
+            ];
+            RwLock::new(
+                builtin
+                    .into_iter()
+                    .map(|(name, kernel)| (name.to_string(), kernel))
+                    .collect(),
+            )
+        })
+    }
+
+    /// Get a copy of the kernel registered as `name`
+    pub fn get(name: &str) -> Option<Kernel> {
+        registry()
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .cloned()
+    }
+
+    /// Register a kernel as `name`, returning the kernel it replaced, if any. Built-in presets
+    /// may be overridden
+    pub fn register(name: impl Into<String>, kernel: Kernel) -> Option<Kernel> {
+        registry()
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name.into(), kernel)
+    }
+
+    /// Remove the kernel registered as `name`
+    pub fn unregister(name: &str) -> Option<Kernel> {
+        registry()
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(name)
+    }
+
+    /// Names of all registered kernels, in sorted order
+    pub fn names() -> Vec<String> {
+        registry()
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .cloned()
+            .collect()
+    }
 }
 
 impl ops::Add for Kernel {
@@ -330,7 +702,79 @@ impl ops::Div for Kernel {
 
 #[cfg(test)]
 mod tests {
-    use super::EdgeStrategy;
+    use super::{presets, EdgeStrategy, Gabor, Kernel, MultiKernel};
+
+    fn sum(k: &Kernel) -> f64 {
+        (0..k.rows())
+            .flat_map(|j| (0..k.cols()).map(move |i| (i, j)))
+            .map(|(i, j)| k.get(j, i))
+            .sum()
+    }
+
+    #[test]
+    fn test_gaussian_centered() {
+        let k = Kernel::gaussian(5, 1.4);
+        assert!((sum(&k) - 1.0).abs() < 1e-9);
+        for j in 0..5 {
+            for i in 0..5 {
+                assert!(k.get(j, i) <= k.get(2, 2));
+                assert!((k.get(j, i) - k.get(4 - j, 4 - i)).abs() < 1e-12);
+            }
+        }
+
+        let k = Kernel::gaussian_2d(3.0, 1.0, 0.0);
+        assert_eq!(k.rows(), 19);
+        assert!(k.get(9, 12) > k.get(12, 9));
+        let r = Kernel::gaussian_2d(3.0, 1.0, 90.0);
+        assert!((r.get(12, 9) - k.get(9, 12)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_kernel_builders() {
+        let k = Kernel::motion_blur(9, 0.0);
+        assert_eq!(k.rows(), 9);
+        assert!((sum(&k) - 1.0).abs() < 1e-9);
+        assert!(k.get(4, 0) > 0.0 && k.get(0, 4) == 0.0);
+
+        let r = Kernel::motion_blur(9, 90.0);
+        assert!(r.get(0, 4) > 0.0 && r.get(4, 0) == 0.0);
+        let rotated = k.rotate(90.0);
+        assert!((rotated.get(0, 4) - k.get(4, 0)).abs() < 1e-9);
+
+        let d = Kernel::disk(3);
+        assert_eq!(d.rows(), 7);
+        assert!((sum(&d) - 1.0).abs() < 1e-9);
+        assert_eq!(d.get(0, 0), 0.0);
+
+        assert!(sum(&Kernel::log(1.4)).abs() < 1e-9);
+        assert!(Kernel::log(1.4).get(4, 4) < 0.0);
+        assert!(sum(&Kernel::dog(1.0, 2.0)).abs() < 1e-9);
+
+        let g = Kernel::gabor(Gabor::default());
+        assert_eq!(g.rows(), 13);
+        assert!((g.get(6, 6) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_constant_edge_strategy() {
+        let strategy = EdgeStrategy::Constant;
+
+        assert_eq!(strategy.map_point(-1, 31), None);
+        assert_eq!(strategy.map_point(32, 31), None);
+        assert_eq!(strategy.map_point(5, 31), Some(5));
+
+        let mut image = crate::Image::<f32, crate::Gray>::new((4, 4));
+        image.for_each(|_, mut px| px[0] = 0.5);
+
+        let mut k = Kernel::from([[0., 0., 0.], [1., 0., 0.], [0., 0., 0.]]);
+        let dest: crate::Image<f32, crate::Gray> = image.run(k.clone(), None);
+        assert_eq!(dest.get_f((0, 0), 0), 0.0);
+        assert_eq!(dest.get_f((1, 0), 0), 0.5);
+
+        k.set_edge_constant(&[1.0]);
+        let dest: crate::Image<f32, crate::Gray> = image.run(k, None);
+        assert_eq!(dest.get_f((0, 0), 0), 1.0);
+    }
 
     #[test]
     fn test_extend_edge_strategy() {
@@ -351,6 +795,8 @@ mod tests {
         assert!(strategy.map_dimension(31, 31) == 31);
         assert!(strategy.map_dimension(32, 31) == 0);
         assert!(strategy.map_dimension(33, 31) == 1);
+        assert!(strategy.map_dimension(-33, 31) == 31);
+        assert!(strategy.map_dimension(64, 31) == 0);
     }
 
     #[test]
@@ -364,5 +810,52 @@ mod tests {
         assert!(strategy.map_dimension(31, 31) == 31);
         assert!(strategy.map_dimension(32, 31) == 30);
         assert!(strategy.map_dimension(33, 31) == 29);
+        assert!(strategy.map_dimension(62, 31) == 0);
+        assert!(strategy.map_dimension(-40, 31) == 22);
+    }
+
+    #[test]
+    fn test_presets() {
+        for name in ["emboss", "sharpen", "sharpen_strong", "edge_enhance"] {
+            let k = presets::get(name).unwrap();
+            assert!((sum(&k) - 1.0).abs() < 1e-9, "{}", name);
+        }
+        assert!(sum(&presets::get("ridge").unwrap()).abs() < 1e-9);
+        assert_eq!(presets::get("laplacian"), Some(Kernel::laplacian()));
+        assert!(presets::get("test_custom").is_none());
+
+        let custom = Kernel::from([[0., 0., 0.], [0., 2., 0.], [0., 0., 0.]]);
+        assert!(presets::register("test_custom", custom.clone()).is_none());
+        assert_eq!(presets::get("test_custom"), Some(custom.clone()));
+        assert!(presets::names().contains(&"test_custom".to_string()));
+        assert_eq!(presets::unregister("test_custom"), Some(custom));
+        assert!(presets::get("test_custom").is_none());
+    }
+
+    #[test]
+    fn test_multi_kernel() {
+        let mut image = crate::Image::<f32, crate::Rgb>::new((8, 8));
+        image.for_each(|pt, mut px| {
+            px[0] = (pt.x % 2) as f32;
+            px[1] = (pt.x % 2) as f32;
+            px[2] = (pt.x % 2) as f32;
+        });
+
+        let mut blur = Kernel::from([[0., 0., 0.], [0.5, 0., 0.5], [0., 0., 0.]]);
+        blur.set_edge_strategy(EdgeStrategy::Extend);
+        let k = MultiKernel([blur.clone(), Kernel::from([[1.0]])]);
+        let dest: crate::Image<f32, crate::Rgb> = image.run(k, None);
+        for x in 1..7 {
+            assert_eq!(dest.get_f((x, 4), 0), 1.0 - (x % 2) as f64);
+            assert_eq!(dest.get_f((x, 4), 1), (x % 2) as f64);
+            assert_eq!(dest.get_f((x, 4), 2), (x % 2) as f64);
+        }
+
+        let all: crate::Image<f32, crate::Rgb> = image.run(
+            MultiKernel([blur.clone(), blur.clone(), blur.clone()]),
+            None,
+        );
+        let single: crate::Image<f32, crate::Rgb> = image.run(blur, None);
+        assert!(all == single);
     }
 }
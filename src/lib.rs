@@ -34,17 +34,26 @@
 /// 16-bit float
 pub use half::f16;
 
+/// Safe reinterpretation of plain data, used by `Image::as_pixels`
+pub use bytemuck;
+
 mod color;
 mod data;
+mod deep;
 mod error;
+mod fft;
 mod filters;
 mod geom;
 mod hash;
 mod histogram;
 mod image;
 mod image_data;
+mod mask;
 mod meta;
+mod ops;
 mod pixel;
+mod stats;
+mod tile;
 mod r#type;
 
 /// OpenGL interop
@@ -68,21 +77,108 @@ pub mod kernel;
 /// Image transforms
 pub mod transform;
 
-pub use crate::meta::Meta;
-pub use color::{Channel, Cmyk, Color, Gray, Hsv, Rgb, Rgba, Srgb, Srgba, Xyz, Yuv};
-pub use data::{Data, DataMut};
-pub use error::Error;
+/// Reductions over image pixels
+pub mod reduce;
+
+/// Deconvolution
+pub mod deconv;
+
+/// Gradient-domain operations, such as seamless cloning
+pub mod poisson;
+
+/// Alpha matting
+pub mod matting;
+
+/// Segmentation into labeled regions
+pub mod segmentation;
+
+/// Stereo correspondence
+pub mod stereo;
+
+/// Robust model fitting
+pub mod fit;
+
+/// Image comparison metrics
+pub mod metrics;
+
+/// Filters over sequences of frames
+pub mod temporal;
+
+/// Layered documents with blend modes
+pub mod document;
+
+/// Procedural image generators
+pub mod generate;
+
+/// Planar and packed YUV frames
+pub mod yuv;
+
+/// Discrete wavelet transforms
+pub mod wavelet;
+
+/// Bias, dark frame and flat field calibration for scientific camera data
+pub mod calibrate;
+
+/// Visible and invisible watermarks
+pub mod watermark;
+
+/// Process many files in parallel using a serializable recipe
+pub mod batch;
+
+/// Local binary patterns and histograms of oriented gradients
+pub mod features;
+
+/// Pack images into texture atlas pages
+pub mod atlas;
+
+/// MIP chain generation
+pub mod mipmap;
+
+/// Image allocators and memory budget tracking
+pub mod memory;
+
+/// Helpers for testing image processing code
+pub mod testing;
+
+/// Filter benchmarking
+pub mod bench;
+
+/// Standard reference images for tests and benchmarks
+#[cfg(feature = "testdata")]
+pub mod testdata;
+
+/// QR code and barcode detection
+#[cfg(feature = "codes")]
+pub mod codes;
+
+pub use crate::meta::{Georeference, Meta};
+pub use color::{
+    hlg_to_linear, linear_to_hlg, linear_to_pq, linear_to_srgb, pq_to_linear, srgb_to_linear, Bgr,
+    Bgra, Channel, Cmyk, Color, Colorspace, DisplayP3, Gamut, GamutMapping, Gray, Hsv, LinearRgb,
+    OkLab, OkLch, Rec2020, Rec2100Hlg, Rec2100Pq, Rgb, Rgba, Srgb, Srgba, TransferFunction, Xyz,
+    Yuv, PQ_REFERENCE_WHITE,
+};
+pub use data::{Data, DataMut, Neighborhood};
+pub use deep::{DeepImage, DeepSample};
+pub use error::{ConversionLoss, Error};
 pub use filters::{
-    filter, AsyncFilter, AsyncMode, AsyncPipeline, Filter, FilterExt, Input, Pipeline, Schedule,
+    filter, AsyncFilter, AsyncMode, AsyncPipeline, ColorspaceMismatch, ExecutionOptions, Filter,
+    FilterExt, Fused, GeometryFilter, Input, Pipeline, PipelineCache, Plan, PointFilter, Schedule,
+    Stage,
 };
 pub use geom::{Point, Region, Size};
-pub use hash::Hash;
-pub use histogram::Histogram;
+pub use hash::{ContentHasher, Hash};
+pub use histogram::{Histogram, Histogram2d};
 pub use image::Image;
 pub use image_data::ImageData;
-pub use kernel::Kernel;
+pub use kernel::{Kernel, MultiKernel};
+pub use mask::Mask;
+pub use ops::Operand;
 pub use pixel::Pixel;
-pub use r#type::Type;
+pub use r#type::{Overflow, Type};
+pub use reduce::Reduce;
+pub use stats::{ChannelStats, Stats};
+pub use tile::Tile;
 pub use transform::Transform;
 
 #[cfg(feature = "mmap")]
@@ -0,0 +1,247 @@
+use crate::*;
+
+/// Selection mask, each pixel stores a coverage value between `0` (unselected) and `1` (selected)
+///
+/// Masks are backed by an `Image<u8, Gray>`, intermediate values are used for antialiased and
+/// feathered selections
+#[derive(Clone, PartialEq)]
+pub struct Mask {
+    image: Image<u8, Gray>,
+}
+
+impl std::fmt::Debug for Mask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mask").field("size", &self.size()).finish()
+    }
+}
+
+impl From<Image<u8, Gray>> for Mask {
+    fn from(image: Image<u8, Gray>) -> Mask {
+        Mask { image }
+    }
+}
+
+impl From<Mask> for Image<u8, Gray> {
+    fn from(mask: Mask) -> Image<u8, Gray> {
+        mask.image
+    }
+}
+
+impl Mask {
+    /// Create an empty mask
+    pub fn new(size: impl Into<Size>) -> Mask {
+        Mask {
+            image: Image::new(size),
+        }
+    }
+
+    /// Create a mask with every pixel selected
+    pub fn full(size: impl Into<Size>) -> Mask {
+        let mut mask = Mask::new(size);
+        mask.image.data_mut().fill(u8::MAX);
+        mask
+    }
+
+    /// Create a mask with only the pixels inside of `roi` selected
+    pub fn from_region(size: impl Into<Size>, roi: Region) -> Mask {
+        let mut mask = Mask::new(size);
+        mask.image
+            .for_each(|pt, mut px| px[0] = if roi.contains(pt) { u8::MAX } else { 0 });
+        mask
+    }
+
+    /// Create a mask from normalized values of a grayscale image
+    pub fn from_image<T: Type>(image: &Image<T, Gray>) -> Mask {
+        Mask {
+            image: image.convert(),
+        }
+    }
+
+    /// Convert to a grayscale image
+    pub fn to_image<T: Type>(&self) -> Image<T, Gray> {
+        self.image.convert()
+    }
+
+    /// Underlying image
+    pub fn as_image(&self) -> &Image<u8, Gray> {
+        &self.image
+    }
+
+    /// Mask size
+    pub fn size(&self) -> Size {
+        self.image.size()
+    }
+
+    /// Mask width
+    pub fn width(&self) -> usize {
+        self.image.width()
+    }
+
+    /// Mask height
+    pub fn height(&self) -> usize {
+        self.image.height()
+    }
+
+    /// Get coverage at the given point, points outside of the mask are unselected
+    pub fn get(&self, pt: impl Into<Point>) -> f64 {
+        let pt = pt.into();
+        if !self.image.in_bounds(pt) {
+            return 0.0;
+        }
+        self.image.get_f(pt, 0)
+    }
+
+    /// Set coverage at the given point
+    pub fn set(&mut self, pt: impl Into<Point>, value: f64) {
+        self.image.set_f(pt, 0, value.clamp(0.0, 1.0))
+    }
+
+    /// Returns true when the coverage at the given point is at least `0.5`
+    pub fn contains(&self, pt: impl Into<Point>) -> bool {
+        self.get(pt) >= 0.5
+    }
+
+    /// Returns true when no pixels are selected
+    pub fn is_empty(&self) -> bool {
+        self.image.data().iter().all(|x| *x == 0)
+    }
+
+    /// Smallest region containing all selected pixels
+    pub fn bounds(&self) -> Option<Region> {
+        let (mut x0, mut y0, mut x1, mut y1) = (usize::MAX, usize::MAX, 0, 0);
+        for (i, x) in self.image.data().iter().enumerate() {
+            if *x != 0 {
+                let (x, y) = (i % self.width(), i / self.width());
+                x0 = x0.min(x);
+                y0 = y0.min(y);
+                x1 = x1.max(x + 1);
+                y1 = y1.max(y + 1);
+            }
+        }
+        (x0 < x1).then(|| Region::new(Point::new(x0, y0), Size::new(x1 - x0, y1 - y0)))
+    }
+
+    fn zip(&self, other: &Mask, f: impl Fn(u8, u8) -> u8) -> Result<Mask, Error> {
+        if self.size() != other.size() {
+            return Err(Error::ShapeMismatch {
+                expected: self.image.shape(),
+                got: other.image.shape(),
+            });
+        }
+
+        let mut dest = self.clone();
+        dest.image
+            .data_mut()
+            .iter_mut()
+            .zip(other.image.data())
+            .for_each(|(a, b)| *a = f(*a, *b));
+        Ok(dest)
+    }
+
+    /// Pixels selected in either mask
+    pub fn union(&self, other: &Mask) -> Result<Mask, Error> {
+        self.zip(other, u8::max)
+    }
+
+    /// Pixels selected in both masks
+    pub fn intersect(&self, other: &Mask) -> Result<Mask, Error> {
+        self.zip(other, u8::min)
+    }
+
+    /// Pixels selected in `self` but not in `other`
+    pub fn subtract(&self, other: &Mask) -> Result<Mask, Error> {
+        self.zip(other, |a, b| a.min(u8::MAX - b))
+    }
+
+    /// Swap selected and unselected pixels
+    pub fn invert(&self) -> Mask {
+        let mut dest = self.clone();
+        dest.image
+            .data_mut()
+            .iter_mut()
+            .for_each(|x| *x = u8::MAX - *x);
+        dest
+    }
+
+    /// Soften the edges of the selection using a Gaussian blur that extends `radius` pixels
+    pub fn feather(&self, radius: f64) -> Mask {
+        let r = radius.max(0.0).ceil() as isize;
+        if r == 0 {
+            return self.clone();
+        }
+
+        let sigma = radius / 3.0;
+        let weights: Vec<f64> = (-r..=r)
+            .map(|i| (-((i * i) as f64) / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let (w, h) = (self.width() as isize, self.height() as isize);
+        let values: Vec<f64> = self.image.data().iter().map(Type::to_norm).collect();
+
+        // Separable blur, edge pixels are extended
+        let pass = |src: &[f64], horizontal: bool| -> Vec<f64> {
+            let mut dest = vec![0.0; src.len()];
+            for y in 0..h {
+                for x in 0..w {
+                    let mut sum = 0.0;
+                    let mut total = 0.0;
+                    for (k, weight) in (-r..=r).zip(&weights) {
+                        let (sx, sy) = if horizontal {
+                            ((x + k).clamp(0, w - 1), y)
+                        } else {
+                            (x, (y + k).clamp(0, h - 1))
+                        };
+                        sum += src[(sy * w + sx) as usize] * weight;
+                        total += weight;
+                    }
+                    dest[(y * w + x) as usize] = sum / total;
+                }
+            }
+            dest
+        };
+        let values = pass(&pass(&values, true), false);
+
+        let mut dest = self.clone();
+        dest.image
+            .data_mut()
+            .iter_mut()
+            .zip(values)
+            .for_each(|(x, v)| *x = u8::from_norm(v));
+        dest
+    }
+
+    /// Maximum or minimum coverage over a disk of `radius` pixels around each pixel
+    fn morphology(&self, radius: usize, grow: bool) -> Mask {
+        let r = radius as isize;
+        let offsets: Vec<(isize, isize)> = (-r..=r)
+            .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+            .filter(|(dx, dy)| dx * dx + dy * dy <= r * r)
+            .collect();
+        let (w, h) = (self.width() as isize, self.height() as isize);
+        let src = self.image.data();
+
+        let mut dest = self.clone();
+        for (i, x) in dest.image.data_mut().iter_mut().enumerate() {
+            let (px, py) = (i as isize % w, i as isize / w);
+            let values = offsets.iter().filter_map(|(dx, dy)| {
+                let (x, y) = (px + dx, py + dy);
+                (x >= 0 && y >= 0 && x < w && y < h).then(|| src[(y * w + x) as usize])
+            });
+            *x = if grow {
+                values.max().unwrap_or(0)
+            } else {
+                values.min().unwrap_or(0)
+            };
+        }
+        dest
+    }
+
+    /// Expand the selection by `radius` pixels
+    pub fn grow(&self, radius: usize) -> Mask {
+        self.morphology(radius, true)
+    }
+
+    /// Contract the selection by `radius` pixels
+    pub fn shrink(&self, radius: usize) -> Mask {
+        self.morphology(radius, false)
+    }
+}
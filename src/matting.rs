@@ -0,0 +1,323 @@
+use crate::*;
+
+/// Global sampling matting settings used by `global_sampling_with`
+#[derive(Debug, Clone, Copy)]
+pub struct Sampler {
+    /// Number of foreground and background samples tested for each unknown pixel before
+    /// refinement, `samples * samples` pairs are evaluated
+    pub samples: usize,
+
+    /// Number of propagation and random search passes
+    pub iterations: usize,
+
+    /// Weight of the distance between an unknown pixel and its samples relative to the color fit
+    pub spatial_weight: f64,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Sampler {
+            samples: 16,
+            iterations: 4,
+            spatial_weight: 0.1,
+        }
+    }
+}
+
+/// Trimap classification of a pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Label {
+    Background,
+    Foreground,
+    Unknown,
+}
+
+/// Known pixel on the boundary of the unknown region
+struct Sample {
+    pt: Point,
+    color: Vec<f64>,
+}
+
+/// Best foreground/background pair found for an unknown pixel
+#[derive(Debug, Clone, Copy)]
+struct Pair {
+    fg: usize,
+    bg: usize,
+    alpha: f64,
+    cost: f64,
+}
+
+struct Context<'a, T: Type, C: Color> {
+    image: &'a Image<T, C>,
+    fg: Vec<Sample>,
+    bg: Vec<Sample>,
+    diagonal: f64,
+    sampler: Sampler,
+}
+
+impl<'a, T: Type, C: Color> Context<'a, T, C> {
+    fn evaluate(&self, pt: Point, color: &[f64], fg: usize, bg: usize) -> Pair {
+        let (f, b) = (&self.fg[fg], &self.bg[bg]);
+
+        let mut dot = 0.0;
+        let mut norm = 0.0;
+        for ((x, f), b) in color.iter().zip(&f.color).zip(&b.color) {
+            dot += (x - b) * (f - b);
+            norm += (f - b) * (f - b);
+        }
+        let alpha = (dot / (norm + 1e-12)).clamp(0.0, 1.0);
+
+        let fit = color
+            .iter()
+            .zip(&f.color)
+            .zip(&b.color)
+            .map(|((x, f), b)| {
+                let e = x - (alpha * f + (1.0 - alpha) * b);
+                e * e
+            })
+            .sum::<f64>()
+            .sqrt();
+
+        let dist = |q: Point| {
+            let (dx, dy) = (q.x as f64 - pt.x as f64, q.y as f64 - pt.y as f64);
+            dx.hypot(dy)
+        };
+        let spatial = (dist(f.pt) + dist(b.pt)) / self.diagonal;
+
+        Pair {
+            fg,
+            bg,
+            alpha,
+            cost: fit + self.sampler.spatial_weight * spatial,
+        }
+    }
+
+    fn color(&self, pt: Point) -> Vec<f64> {
+        self.image.get_pixel(pt).to_vec()
+    }
+}
+
+fn classify<M: Type>(trimap: &Image<M, Gray>, pt: Point) -> Label {
+    let v = trimap.get_f(pt, 0);
+    if v <= 0.1 {
+        Label::Background
+    } else if v >= 0.9 {
+        Label::Foreground
+    } else {
+        Label::Unknown
+    }
+}
+
+/// Estimate a soft alpha matte for `image` from a trimap, where `trimap` is black for background,
+/// white for foreground and gray for unknown pixels
+pub fn global_sampling<T: Type, C: Color, M: Type>(
+    image: &Image<T, C>,
+    trimap: &Image<M, Gray>,
+) -> Result<Image<f32, Gray>, Error> {
+    global_sampling_with(image, trimap, Sampler::default())
+}
+
+/// Same as `global_sampling` using the provided sampler settings
+pub fn global_sampling_with<T: Type, C: Color, M: Type>(
+    image: &Image<T, C>,
+    trimap: &Image<M, Gray>,
+    sampler: Sampler,
+) -> Result<Image<f32, Gray>, Error> {
+    if trimap.size() != image.size() {
+        return Err(Error::InvalidDimensions(
+            trimap.width(),
+            trimap.height(),
+            trimap.channels(),
+        ));
+    }
+
+    let (width, height) = (image.width(), image.height());
+    let labels: Vec<Label> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| Point::new(x, y)))
+        .map(|pt| classify(trimap, pt))
+        .collect();
+
+    let mut dest = Image::<f32, Gray>::new(image.size());
+    let mut unknowns = Vec::new();
+    let mut fg = Vec::new();
+    let mut bg = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let label = labels[y * width + x];
+            if label == Label::Unknown {
+                unknowns.push(Point::new(x, y));
+                continue;
+            }
+
+            if label == Label::Foreground {
+                dest.set_f((x, y), 0, 1.0);
+            }
+
+            // Known pixels next to a different region are used as samples
+            let boundary = [(-1isize, 0isize), (1, 0), (0, -1), (0, 1)]
+                .iter()
+                .any(|(dx, dy)| {
+                    let (nx, ny) = (x as isize + dx, y as isize + dy);
+                    nx >= 0
+                        && ny >= 0
+                        && (nx as usize) < width
+                        && (ny as usize) < height
+                        && labels[ny as usize * width + nx as usize] != label
+                });
+            if boundary {
+                let pt = Point::new(x, y);
+                let sample = Sample {
+                    pt,
+                    color: image.get_pixel(pt).to_vec(),
+                };
+                match label {
+                    Label::Foreground => fg.push(sample),
+                    _ => bg.push(sample),
+                }
+            }
+        }
+    }
+
+    if unknowns.is_empty() {
+        return Ok(dest);
+    }
+
+    if fg.is_empty() || bg.is_empty() {
+        let alpha = if fg.is_empty() { 0.0 } else { 1.0 };
+        for pt in unknowns {
+            dest.set_f(pt, 0, alpha);
+        }
+        return Ok(dest);
+    }
+
+    // Sorting samples by intensity makes nearby indices similar in color, which is what the
+    // random search below relies on
+    let intensity = |s: &Sample| s.color.iter().sum::<f64>();
+    fg.sort_by(|a, b| intensity(a).total_cmp(&intensity(b)));
+    bg.sort_by(|a, b| intensity(a).total_cmp(&intensity(b)));
+
+    let ctx = Context {
+        image,
+        fg,
+        bg,
+        diagonal: (width as f64).hypot(height as f64),
+        sampler,
+    };
+
+    let colors: Vec<Vec<f64>> = unknowns.iter().map(|pt| ctx.color(*pt)).collect();
+    let mut index = vec![None; width * height];
+    for (i, pt) in unknowns.iter().enumerate() {
+        index[pt.y * width + pt.x] = Some(i);
+    }
+
+    // Initialize with an evenly spaced grid of sample pairs
+    let samples = sampler.samples.max(1);
+    let (nf, nb) = (ctx.fg.len(), ctx.bg.len());
+    let mut best: Vec<Pair> = unknowns
+        .iter()
+        .zip(colors.iter())
+        .map(|(pt, color)| {
+            let mut best: Option<Pair> = None;
+            for i in 0..samples.min(nf) {
+                for j in 0..samples.min(nb) {
+                    let f = i * nf / samples.min(nf);
+                    let b = j * nb / samples.min(nb);
+                    let pair = ctx.evaluate(*pt, color, f, b);
+                    if best.map(|x| pair.cost < x.cost).unwrap_or(true) {
+                        best = Some(pair);
+                    }
+                }
+            }
+            best.unwrap()
+        })
+        .collect();
+
+    // Refine with propagation from neighbors and random search in sample space
+    let mut rng = 0x2545f4914f6cdd1du64;
+    let mut random = move |n: usize| {
+        rng ^= rng << 13;
+        rng ^= rng >> 7;
+        rng ^= rng << 17;
+        (rng % n as u64) as usize
+    };
+
+    for iteration in 0..sampler.iterations {
+        let reverse = iteration % 2 == 1;
+        for k in 0..unknowns.len() {
+            let i = if reverse { unknowns.len() - 1 - k } else { k };
+            let pt = unknowns[i];
+            let color = &colors[i];
+
+            let neighbors = if reverse {
+                [(1isize, 0isize), (0, 1)]
+            } else {
+                [(-1, 0), (0, -1)]
+            };
+            for (dx, dy) in neighbors {
+                let (nx, ny) = (pt.x as isize + dx, pt.y as isize + dy);
+                if nx < 0 || ny < 0 || nx as usize >= width || ny as usize >= height {
+                    continue;
+                }
+                if let Some(j) = index[ny as usize * width + nx as usize] {
+                    let pair = ctx.evaluate(pt, color, best[j].fg, best[j].bg);
+                    if pair.cost < best[i].cost {
+                        best[i] = pair;
+                    }
+                }
+            }
+
+            let (mut rf, mut rb) = (nf, nb);
+            while rf > 1 || rb > 1 {
+                let f = (best[i].fg + nf + random(rf) - rf / 2) % nf;
+                let b = (best[i].bg + nb + random(rb) - rb / 2) % nb;
+                let pair = ctx.evaluate(pt, color, f, b);
+                if pair.cost < best[i].cost {
+                    best[i] = pair;
+                }
+                rf = (rf / 2).max(1);
+                rb = (rb / 2).max(1);
+            }
+        }
+    }
+
+    for (pt, pair) in unknowns.iter().zip(best) {
+        dest.set_f(*pt, 0, pair.alpha);
+    }
+
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_global_sampling() {
+        // Red foreground blended into a blue background across the unknown band
+        let mut image = Image::<f32, Rgb>::new((16, 8));
+        let mut trimap = Image::<u8, Gray>::new((16, 8));
+        let expected = |x: usize| ((10.0 - x as f64) / 6.0).clamp(0.0, 1.0);
+        image.for_each(|pt, mut px| {
+            let a = expected(pt.x) as f32;
+            px[0] = a;
+            px[2] = 1.0 - a;
+        });
+        trimap.for_each(|pt, mut px| {
+            px[0] = if pt.x < 5 {
+                255
+            } else if pt.x >= 10 {
+                0
+            } else {
+                128
+            }
+        });
+
+        let alpha = matting::global_sampling(&image, &trimap).unwrap();
+        alpha.each_pixel(|pt, px| {
+            assert!((px[0] - expected(pt.x)).abs() < 0.05);
+        });
+
+        let small = Image::<u8, Gray>::new((4, 4));
+        assert!(matting::global_sampling(&image, &small).is_err());
+    }
+}
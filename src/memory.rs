@@ -0,0 +1,415 @@
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "memory-budget")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::*;
+
+/// Byte counter with a limit, allocations are accounted against `Budget::global` unless an
+/// allocator using another budget is passed to `Image::new_in`
+#[cfg(feature = "memory-budget")]
+#[derive(Debug)]
+pub struct Budget {
+    used: AtomicUsize,
+    limit: AtomicUsize,
+}
+
+#[cfg(feature = "memory-budget")]
+static GLOBAL: Budget = Budget::new(None);
+
+#[cfg(feature = "memory-budget")]
+impl Budget {
+    /// Create a new budget limited to `limit` bytes, `None` means there is no limit
+    pub const fn new(limit: Option<usize>) -> Budget {
+        Budget {
+            used: AtomicUsize::new(0),
+            limit: AtomicUsize::new(match limit {
+                Some(limit) => limit,
+                None => usize::MAX,
+            }),
+        }
+    }
+
+    /// Budget used by `Image::new`, `Image::try_new` and `Pool`
+    pub fn global() -> &'static Budget {
+        &GLOBAL
+    }
+
+    /// Set the maximum number of bytes that can be reserved at once, `None` removes the limit.
+    /// Lowering the limit below the current usage doesn't free anything, it only makes new
+    /// reservations fail until enough memory is released
+    pub fn set_limit(&self, bytes: Option<usize>) {
+        self.limit
+            .store(bytes.unwrap_or(usize::MAX), Ordering::SeqCst);
+    }
+
+    /// Current limit in bytes, `None` when there is no limit
+    pub fn limit(&self) -> Option<usize> {
+        let limit = self.limit.load(Ordering::SeqCst);
+        (limit != usize::MAX).then_some(limit)
+    }
+
+    /// Number of bytes currently reserved
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::SeqCst)
+    }
+
+    /// Reserve `bytes`, returns `Error::OutOfMemory` when the reservation would exceed the limit
+    pub fn reserve(&'static self, bytes: usize) -> Result<Reservation, Error> {
+        let limit = self.limit.load(Ordering::SeqCst);
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                // Empty allocations always succeed, even when the limit is already exceeded
+                used.checked_add(bytes)
+                    .filter(|total| bytes == 0 || *total <= limit)
+            })
+            .map_err(|used| Error::OutOfMemory {
+                requested: bytes,
+                available: limit.saturating_sub(used),
+            })?;
+        Ok(Reservation {
+            bytes,
+            budget: self,
+        })
+    }
+
+    /// Reserve `bytes` even when that exceeds the limit, used by infallible constructors like
+    /// `Image::new` so their memory is still accounted for
+    fn force(&'static self, bytes: usize) -> Reservation {
+        self.used.fetch_add(bytes, Ordering::SeqCst);
+        Reservation {
+            bytes,
+            budget: self,
+        }
+    }
+}
+
+/// Set the limit of the global budget, see `Budget::set_limit`
+#[cfg(feature = "memory-budget")]
+pub fn set_budget(bytes: Option<usize>) {
+    GLOBAL.set_limit(bytes)
+}
+
+/// Limit of the global budget in bytes, `None` when there is no limit
+#[cfg(feature = "memory-budget")]
+pub fn budget() -> Option<usize> {
+    GLOBAL.limit()
+}
+
+/// Number of bytes of pixel data accounted against the global budget. This includes images
+/// created by `Image::new`, `Image::try_new` and clones of them, and buffers kept by a `Pool`.
+/// Buffers passed to `Image::new_with_data` and memory mapped images aren't counted
+#[cfg(feature = "memory-budget")]
+pub fn used() -> usize {
+    GLOBAL.used()
+}
+
+/// Bytes accounted against a `Budget`, the bytes are released when the reservation is dropped.
+/// Without the `memory-budget` feature reservations always succeed and nothing is tracked
+#[derive(Debug)]
+pub struct Reservation {
+    #[cfg_attr(not(feature = "memory-budget"), allow(dead_code))]
+    bytes: usize,
+
+    #[cfg(feature = "memory-budget")]
+    budget: &'static Budget,
+}
+
+impl Reservation {
+    /// Reserve `bytes` from the global budget, returns `Error::OutOfMemory` when the reservation
+    /// would exceed the limit
+    pub fn new(bytes: usize) -> Result<Reservation, Error> {
+        #[cfg(feature = "memory-budget")]
+        {
+            GLOBAL.reserve(bytes)
+        }
+
+        #[cfg(not(feature = "memory-budget"))]
+        Ok(Reservation { bytes })
+    }
+
+    /// Number of bytes reserved
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        #[cfg(feature = "memory-budget")]
+        self.budget.used.fetch_sub(self.bytes, Ordering::SeqCst);
+    }
+}
+
+/// Number of bytes needed to store `len` values of type `T`
+fn num_bytes<T: Type>(len: usize) -> Result<usize, Error> {
+    len.checked_mul(std::mem::size_of::<T>())
+        .ok_or(Error::OutOfMemory {
+            requested: usize::MAX,
+            available: 0,
+        })
+}
+
+/// Allocate `len` default values, returning an error instead of aborting when the system is out
+/// of memory
+fn zeroed<T: Type>(len: usize) -> Result<Vec<T>, Error> {
+    let mut data = Vec::new();
+    data.try_reserve_exact(len)
+        .map_err(|_| Error::OutOfMemory {
+            requested: len.saturating_mul(std::mem::size_of::<T>()),
+            available: 0,
+        })?;
+    data.resize(len, T::default());
+    Ok(data)
+}
+
+/// Pixel buffer that releases its reservation when dropped
+struct Buffer<T: Type> {
+    data: Box<[T]>,
+    _reservation: Reservation,
+}
+
+impl<T: Type> AsRef<[T]> for Buffer<T> {
+    fn as_ref(&self) -> &[T] {
+        &self.data
+    }
+}
+
+impl<T: Type> AsMut<[T]> for Buffer<T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+}
+
+impl<T: Type> ImageData<T> for Buffer<T> {
+    fn into_vec(self) -> Vec<T> {
+        self.data.into()
+    }
+}
+
+/// Wrap an already allocated buffer so it's accounted for, even when that exceeds the budget
+pub(crate) fn track<T: Type>(data: Box<[T]>) -> Box<dyn ImageData<T>> {
+    #[cfg(feature = "memory-budget")]
+    {
+        let bytes = std::mem::size_of_val(data.as_ref());
+        Box::new(Buffer {
+            data,
+            _reservation: GLOBAL.force(bytes),
+        })
+    }
+
+    #[cfg(not(feature = "memory-budget"))]
+    Box::new(data)
+}
+
+/// Source of pixel buffers for new images, see `Image::new_in`
+pub trait Allocator<T: Type>: Sync + Send {
+    /// Allocate a buffer of `len` values set to `T::default()`. Implementations should account for
+    /// the buffer using `Reservation` and return `Error::OutOfMemory` instead of panicking when
+    /// the memory isn't available
+    fn allocate(&self, len: usize) -> Result<Box<dyn ImageData<T>>, Error>;
+}
+
+/// Allocator using the global heap, this is what `Image::try_new` uses
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Global;
+
+impl<T: Type> Allocator<T> for Global {
+    fn allocate(&self, len: usize) -> Result<Box<dyn ImageData<T>>, Error> {
+        let reservation = Reservation::new(num_bytes::<T>(len)?)?;
+        Ok(Box::new(Buffer {
+            data: zeroed(len)?.into_boxed_slice(),
+            _reservation: reservation,
+        }))
+    }
+}
+
+/// Allocates from the global heap and accounts the buffers against this budget instead of the
+/// global one
+#[cfg(feature = "memory-budget")]
+impl<T: Type> Allocator<T> for &'static Budget {
+    fn allocate(&self, len: usize) -> Result<Box<dyn ImageData<T>>, Error> {
+        let reservation = self.reserve(num_bytes::<T>(len)?)?;
+        Ok(Box::new(Buffer {
+            data: zeroed(len)?.into_boxed_slice(),
+            _reservation: reservation,
+        }))
+    }
+}
+
+/// Idle buffers kept by a `Pool`
+type FreeList<T> = Mutex<Vec<(Vec<T>, Reservation)>>;
+
+/// Allocator that keeps the buffers of dropped images so they can be reused by images of the same
+/// size, this avoids repeatedly allocating large buffers when processing many images. Idle
+/// buffers still count towards the budget, use `Pool::clear` to release them
+pub struct Pool<T: Type> {
+    free: Arc<FreeList<T>>,
+    max_idle: usize,
+}
+
+impl<T: Type> Clone for Pool<T> {
+    fn clone(&self) -> Self {
+        Pool {
+            free: self.free.clone(),
+            max_idle: self.max_idle,
+        }
+    }
+}
+
+impl<T: Type> std::fmt::Debug for Pool<T> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("Pool")
+            .field("idle", &self.idle())
+            .field("max_idle", &self.max_idle)
+            .finish()
+    }
+}
+
+impl<T: Type> Pool<T> {
+    /// Create a new pool that keeps at most `max_idle` unused buffers
+    pub fn new(max_idle: usize) -> Pool<T> {
+        Pool {
+            free: Arc::new(Mutex::new(Vec::new())),
+            max_idle,
+        }
+    }
+
+    /// Number of unused buffers
+    pub fn idle(&self) -> usize {
+        self.free.lock().map(|free| free.len()).unwrap_or(0)
+    }
+
+    /// Release every unused buffer
+    pub fn clear(&self) {
+        if let Ok(mut free) = self.free.lock() {
+            free.clear();
+        }
+    }
+}
+
+/// Buffer allocated by a `Pool`, it's returned to the pool when dropped
+struct Pooled<T: Type> {
+    data: Vec<T>,
+    reservation: Option<Reservation>,
+    free: std::sync::Weak<FreeList<T>>,
+    max_idle: usize,
+}
+
+impl<T: Type> AsRef<[T]> for Pooled<T> {
+    fn as_ref(&self) -> &[T] {
+        &self.data
+    }
+}
+
+impl<T: Type> AsMut<[T]> for Pooled<T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+}
+
+impl<T: Type> ImageData<T> for Pooled<T> {
+    fn into_vec(mut self) -> Vec<T> {
+        // The buffer leaves the pool, its reservation is released when `self` is dropped
+        std::mem::take(&mut self.data)
+    }
+}
+
+impl<T: Type> Drop for Pooled<T> {
+    fn drop(&mut self) {
+        if let (Some(free), Some(reservation)) = (self.free.upgrade(), self.reservation.take()) {
+            if let Ok(mut free) = free.lock() {
+                if !self.data.is_empty() && free.len() < self.max_idle {
+                    free.push((std::mem::take(&mut self.data), reservation));
+                }
+            }
+        }
+    }
+}
+
+impl<T: Type> Allocator<T> for Pool<T> {
+    fn allocate(&self, len: usize) -> Result<Box<dyn ImageData<T>>, Error> {
+        let reused = self.free.lock().ok().and_then(|mut free| {
+            let index = free.iter().position(|(data, _)| data.len() == len)?;
+            Some(free.swap_remove(index))
+        });
+        let (data, reservation) = match reused {
+            Some((mut data, reservation)) => {
+                data.fill(T::default());
+                (data, reservation)
+            }
+            None => {
+                let reservation = Reservation::new(num_bytes::<T>(len)?)?;
+                (zeroed(len)?, reservation)
+            }
+        };
+        Ok(Box::new(Pooled {
+            data,
+            reservation: Some(reservation),
+            free: Arc::downgrade(&self.free),
+            max_idle: self.max_idle,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory() {
+        let pool = Pool::<f32>::new(2);
+        let mut image = Image::<f32, Rgb>::new_in((16, 16), &pool).unwrap();
+        image.set_f((3, 3), 0, 1.0);
+        let ptr = image.data().as_ptr();
+        drop(image);
+        assert_eq!(pool.idle(), 1);
+
+        // Buffers are only reused for images of the same size and are cleared first
+        let other = Image::<f32, Rgb>::new_in((8, 8), &pool).unwrap();
+        assert_eq!(pool.idle(), 1);
+        let image = Image::<f32, Rgb>::new_in((16, 16), &pool).unwrap();
+        assert_eq!(image.data().as_ptr(), ptr);
+        assert_eq!(image.get_f((3, 3), 0), 0.0);
+        assert_eq!(pool.idle(), 0);
+
+        drop((image, other));
+        assert_eq!(pool.idle(), 2);
+        pool.clear();
+        assert_eq!(pool.idle(), 0);
+
+        assert!(matches!(
+            Image::<f32, Rgb>::try_new((usize::MAX / 2, 4)),
+            Err(Error::OutOfMemory { .. })
+        ));
+
+        // Limits are tested using a local budget, the global one is shared with tests that
+        // allocate at the same time so only relative checks are made against it
+        #[cfg(feature = "memory-budget")]
+        {
+            let local: &'static Budget = Box::leak(Box::new(Budget::new(None)));
+            let image = Image::<u8, Rgb>::new_in((64, 64), &local).unwrap();
+            assert_eq!(local.used(), 64 * 64 * 3);
+
+            local.set_limit(Some(0));
+            assert_eq!(local.limit(), Some(0));
+            assert!(matches!(
+                Image::<u8, Rgb>::new_in((64, 64), &local),
+                Err(Error::OutOfMemory { requested, .. }) if requested == 64 * 64 * 3
+            ));
+            assert!(local.reserve(0).is_ok());
+
+            local.set_limit(None);
+            assert_eq!(local.limit(), None);
+            assert!(Image::<u8, Rgb>::new_in((64, 64), &local).is_ok());
+            drop(image);
+            assert_eq!(local.used(), 0);
+
+            // Infallible constructors are still accounted for
+            let image = Image::<u8, Rgb>::try_new((64, 64)).unwrap();
+            let copy = image.clone();
+            assert!(used() >= 2 * 64 * 64 * 3);
+            drop((image, copy));
+        }
+    }
+}
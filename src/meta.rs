@@ -11,6 +11,16 @@ use std::marker::PhantomData;
 pub struct Meta<T: Type, C: Color> {
     /// Image size
     pub size: Size,
+
+    /// Default display window stored by the source file, used by medical images
+    pub window_level: Option<filter::WindowLevel>,
+
+    /// Mapping from pixel to model coordinates, used by GeoTIFF images
+    pub georeference: Option<Georeference>,
+
+    /// Colorspace of the stored values when it differs from the default for the color type, see
+    /// `Image::colorspace`
+    pub colorspace: Option<Colorspace>,
     _type: PhantomData<T>,
     _color: PhantomData<C>,
 }
@@ -20,6 +30,9 @@ impl<T: Type, C: Color> Meta<T, C> {
     pub fn new(size: impl Into<Size>) -> Meta<T, C> {
         Meta {
             size: size.into(),
+            window_level: None,
+            georeference: None,
+            colorspace: None,
             _type: PhantomData,
             _color: PhantomData,
         }
@@ -140,3 +153,128 @@ impl<T: Type, C: Color> Meta<T, C> {
             .map(move |n| self.convert_index_to_point(n))
     }
 }
+
+/// Georeferencing information stored in GeoTIFF files
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Georeference {
+    /// Affine transform from pixel to model coordinates, using the same ordering as GDAL:
+    /// `x = t[0] + col * t[1] + row * t[2]` and `y = t[3] + col * t[4] + row * t[5]`
+    pub transform: [f64; 6],
+
+    /// Raw `GeoKeyDirectory` tag, this describes the coordinate reference system
+    pub keys: Vec<u16>,
+
+    /// Raw `GeoDoubleParams` tag
+    pub double_params: Vec<f64>,
+
+    /// Raw `GeoAsciiParams` tag
+    pub ascii_params: String,
+}
+
+/// Floating point values are compared bitwise so georeferences can be part of `Meta`, which
+/// implements `Eq`
+impl PartialEq for Georeference {
+    fn eq(&self, other: &Georeference) -> bool {
+        let bits = |a: &[f64], b: &[f64]| {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.to_bits() == b.to_bits())
+        };
+        bits(&self.transform, &other.transform)
+            && bits(&self.double_params, &other.double_params)
+            && self.keys == other.keys
+            && self.ascii_params == other.ascii_params
+    }
+}
+
+impl Eq for Georeference {}
+
+impl Georeference {
+    /// Create a new `Georeference` from an affine transform with no coordinate reference system
+    pub fn new(transform: [f64; 6]) -> Georeference {
+        Georeference {
+            transform,
+            keys: Vec::new(),
+            double_params: Vec::new(),
+            ascii_params: String::new(),
+        }
+    }
+
+    /// Build the transform from the GeoTIFF `ModelTransformation` tag, or `ModelPixelScale` and
+    /// `ModelTiePoint` when no transformation is available
+    pub fn from_geotiff(
+        pixel_scale: &[f64],
+        tie_point: &[f64],
+        transformation: &[f64],
+    ) -> Option<Georeference> {
+        if transformation.len() >= 16 {
+            let m = transformation;
+            return Some(Georeference::new([m[3], m[0], m[1], m[7], m[4], m[5]]));
+        }
+
+        if pixel_scale.len() < 2 || tie_point.len() < 6 {
+            return None;
+        }
+        let (i, j, x, y) = (tie_point[0], tie_point[1], tie_point[3], tie_point[4]);
+        let (sx, sy) = (pixel_scale[0], pixel_scale[1]);
+        Some(Georeference::new([
+            x - i * sx,
+            sx,
+            0.0,
+            y + j * sy,
+            0.0,
+            -sy,
+        ]))
+    }
+
+    /// Returns true when the transform has no rotation or shear and can be stored using
+    /// `ModelPixelScale` and `ModelTiePoint`
+    pub fn is_axis_aligned(&self) -> bool {
+        self.transform[2] == 0.0 && self.transform[4] == 0.0
+    }
+
+    /// GeoTIFF `ModelPixelScale` tag, only valid when `is_axis_aligned` is true
+    pub fn pixel_scale(&self) -> [f64; 3] {
+        [self.transform[1], -self.transform[5], 0.0]
+    }
+
+    /// GeoTIFF `ModelTiePoint` tag mapping pixel `(0, 0)` to model space
+    pub fn tie_point(&self) -> [f64; 6] {
+        [0.0, 0.0, 0.0, self.transform[0], self.transform[3], 0.0]
+    }
+
+    /// GeoTIFF `ModelTransformation` tag
+    pub fn transformation(&self) -> [f64; 16] {
+        let t = &self.transform;
+        [
+            t[1], t[2], 0.0, t[0], //
+            t[4], t[5], 0.0, t[3], //
+            0.0, 0.0, 0.0, 0.0, //
+            0.0, 0.0, 0.0, 1.0,
+        ]
+    }
+
+    /// Convert pixel coordinates to model coordinates
+    pub fn pixel_to_model(&self, x: f64, y: f64) -> (f64, f64) {
+        let t = &self.transform;
+        (t[0] + x * t[1] + y * t[2], t[3] + x * t[4] + y * t[5])
+    }
+
+    /// Update the transform for an image cropped to start at `origin`
+    pub fn crop(&self, origin: Point) -> Georeference {
+        let (x, y) = self.pixel_to_model(origin.x as f64, origin.y as f64);
+        let mut dest = self.clone();
+        dest.transform[0] = x;
+        dest.transform[3] = y;
+        dest
+    }
+
+    /// Update the transform for an image resized by `sx` horizontally and `sy` vertically
+    pub fn scale(&self, sx: f64, sy: f64) -> Georeference {
+        let mut dest = self.clone();
+        dest.transform[1] /= sx;
+        dest.transform[4] /= sx;
+        dest.transform[2] /= sy;
+        dest.transform[5] /= sy;
+        dest
+    }
+}
@@ -0,0 +1,287 @@
+use crate::fft::{fft2, Complex};
+use crate::*;
+
+/// Options for `diff`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffOptions {
+    /// Per-channel tolerance for normalized values, differences less than or equal to the
+    /// tolerance are not counted as mismatches. When there are fewer values than channels the
+    /// last value is used for the remaining channels
+    pub tolerance: Vec<f64>,
+
+    /// Multiplier applied to the error before it is rendered in the heat map
+    pub amplify: f64,
+}
+
+impl Default for DiffOptions {
+    fn default() -> DiffOptions {
+        DiffOptions {
+            tolerance: vec![0.0],
+            amplify: 1.0,
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Create new `DiffOptions` requiring an exact match
+    pub fn new() -> DiffOptions {
+        DiffOptions::default()
+    }
+
+    /// Use the same tolerance for all channels
+    pub fn with_tolerance(mut self, tolerance: f64) -> DiffOptions {
+        self.tolerance = vec![tolerance];
+        self
+    }
+
+    /// Set the tolerance of each channel
+    pub fn with_channel_tolerance(mut self, tolerance: &[f64]) -> DiffOptions {
+        self.tolerance = tolerance.to_vec();
+        self
+    }
+
+    /// Set heat map amplification, small errors are easier to see with larger values
+    pub fn with_amplify(mut self, amplify: f64) -> DiffOptions {
+        self.amplify = amplify;
+        self
+    }
+
+    /// Tolerance for channel `c`
+    pub fn tolerance(&self, c: Channel) -> f64 {
+        match self.tolerance.get(c) {
+            Some(t) => *t,
+            None => self.tolerance.last().copied().unwrap_or(0.0),
+        }
+    }
+}
+
+/// Result of `diff`, all errors are computed using normalized values
+pub struct Diff {
+    /// Mean squared error over all channels
+    pub mse: f64,
+
+    /// Peak signal-to-noise ratio in decibels, infinite when the images are identical
+    pub psnr: f64,
+
+    /// Mean absolute error over all channels
+    pub mean_error: f64,
+
+    /// Largest absolute error of each channel
+    pub max_error: Vec<f64>,
+
+    /// Number of pixels with at least one channel outside of the tolerance
+    pub mismatched_pixels: usize,
+
+    /// Heat map of the largest per-pixel error, black pixels are within tolerance and
+    /// larger errors go from red to yellow to white
+    pub heat_map: Image<f32, Rgb>,
+}
+
+impl Diff {
+    /// Returns true when all pixels are within tolerance
+    pub fn is_match(&self) -> bool {
+        self.mismatched_pixels == 0
+    }
+
+    /// Fraction of pixels outside of the tolerance
+    pub fn mismatched_fraction(&self) -> f64 {
+        let size = self.heat_map.size();
+        self.mismatched_pixels as f64 / (size.width * size.height).max(1) as f64
+    }
+}
+
+/// Maps an error to a black, red, yellow, white ramp
+fn heat(x: f64) -> [f64; 3] {
+    let x = x.clamp(0.0, 1.0) * 3.0;
+    [
+        x.clamp(0.0, 1.0),
+        (x - 1.0).clamp(0.0, 1.0),
+        (x - 2.0).clamp(0.0, 1.0),
+    ]
+}
+
+/// Compare two images, returning error statistics and a heat map of the differences
+pub fn diff<T: Type, U: Type, C: Color>(
+    a: &Image<T, C>,
+    b: &Image<U, C>,
+    options: &DiffOptions,
+) -> Result<Diff, Error> {
+    if a.size() != b.size() {
+        return Err(Error::ShapeMismatch {
+            expected: (a.width(), a.height(), C::CHANNELS),
+            got: (b.width(), b.height(), C::CHANNELS),
+        });
+    }
+
+    let mut sum_sq = 0.0;
+    let mut sum_abs = 0.0;
+    let mut max_error = vec![0.0f64; C::CHANNELS];
+    let mut mismatched_pixels = 0;
+    let mut errors = vec![0.0; a.width() * a.height()];
+
+    a.each_pixel(|pt, px| {
+        let other = b.get_pixel(pt);
+        let mut pixel_error = 0.0f64;
+        let mut mismatch = false;
+        for c in 0..C::CHANNELS {
+            let e = (px[c] - other[c]).abs();
+            sum_sq += e * e;
+            sum_abs += e;
+            max_error[c] = max_error[c].max(e);
+            if e > options.tolerance(c) {
+                mismatch = true;
+                pixel_error = pixel_error.max(e);
+            }
+        }
+
+        if mismatch {
+            mismatched_pixels += 1;
+            errors[pt.y * a.width() + pt.x] = pixel_error;
+        }
+    });
+
+    let n = (a.width() * a.height() * C::CHANNELS).max(1) as f64;
+    let mse = sum_sq / n;
+    let psnr = if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (1.0 / mse).log10()
+    };
+
+    let mut heat_map = Image::new(a.size());
+    heat_map.each_pixel_mut(|pt, mut px| {
+        let e = errors[pt.y * a.width() + pt.x];
+        if e > 0.0 {
+            // Any visible mismatch should show up, even when it is smaller than one display step
+            let [r, g, b] = heat((e * options.amplify).max(1.0 / 255.0));
+            px[0] = r;
+            px[1] = g;
+            px[2] = b;
+        }
+    });
+
+    Ok(Diff {
+        mse,
+        psnr,
+        mean_error: sum_abs / n,
+        max_error,
+        mismatched_pixels,
+        heat_map,
+    })
+}
+
+/// Estimate the translation of `b` relative to `a` using phase correlation, returns
+/// `(dx, dy, confidence)` where `b` is approximately `a` shifted right by `dx` and down by `dy`
+/// pixels. The shift is refined to subpixel precision by fitting a parabola around the
+/// correlation peak, confidence is the height of the peak between `0` (no match) and `1` (exact
+/// shift)
+///
+/// Both images are converted to grayscale and windowed to reduce the effect of the image
+/// borders, shifts are limited to half of the image size in each direction
+pub fn phase_correlation<T: Type, U: Type, C: Color>(
+    a: &Image<T, C>,
+    b: &Image<U, C>,
+) -> Result<(f64, f64, f64), Error> {
+    if a.size() != b.size() {
+        return Err(Error::ShapeMismatch {
+            expected: (a.width(), a.height(), C::CHANNELS),
+            got: (b.width(), b.height(), C::CHANNELS),
+        });
+    }
+
+    let (width, height) = (a.width(), a.height());
+    if width == 0 || height == 0 {
+        return Ok((0.0, 0.0, 0.0));
+    }
+    let (w, h) = (width.next_power_of_two(), height.next_power_of_two());
+
+    let hann = |i: usize, n: usize| {
+        if n < 2 {
+            1.0
+        } else {
+            0.5 - 0.5 * (std::f64::consts::TAU * i as f64 / (n - 1) as f64).cos()
+        }
+    };
+    let spectrum = |gray: Vec<f64>| {
+        let mean = gray.iter().sum::<f64>() / gray.len() as f64;
+        let mut data = vec![Complex::default(); w * h];
+        for y in 0..height {
+            for x in 0..width {
+                let v = (gray[y * width + x] - mean) * hann(x, width) * hann(y, height);
+                data[y * w + x] = Complex::new(v, 0.0);
+            }
+        }
+        fft2(&mut data, w, h, false);
+        data
+    };
+    let fa = spectrum(luminance(a));
+    let fb = spectrum(luminance(b));
+
+    // Normalized cross-power spectrum, frequencies with almost no energy are dropped since their
+    // phase is mostly noise
+    let mut cross: Vec<Complex> = fa.iter().zip(&fb).map(|(a, b)| *b * a.conj()).collect();
+    let max_norm = cross
+        .iter()
+        .map(|r| r.norm_sqr())
+        .fold(0.0, f64::max)
+        .sqrt();
+    let mut used = 0;
+    for r in cross.iter_mut() {
+        let norm = r.norm_sqr().sqrt();
+        if norm > max_norm * 1e-9 {
+            *r = r.scale(1.0 / norm);
+            used += 1;
+        } else {
+            *r = Complex::default();
+        }
+    }
+    fft2(&mut cross, w, h, true);
+
+    let (peak, value) = cross
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, c.re))
+        .fold((0, f64::MIN), |a, b| if b.1 > a.1 { b } else { a });
+    let (px, py) = (peak % w, peak / w);
+    let at = |x: usize, y: usize| cross[(y % h) * w + x % w].re;
+
+    // Parabolic fit through the peak and its neighbors
+    let refine = |before: f64, after: f64| {
+        let d = before - 2.0 * value + after;
+        if d.abs() > 1e-12 {
+            (0.5 * (before - after) / d).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        }
+    };
+    let fx = refine(at(px + w - 1, py), at(px + 1, py));
+    let fy = refine(at(px, py + h - 1), at(px, py + 1));
+
+    // Peaks past the midpoint correspond to negative shifts
+    let wrap = |p: usize, n: usize| {
+        if p > n / 2 {
+            p as f64 - n as f64
+        } else {
+            p as f64
+        }
+    };
+    // An exact shift puts all of the retained energy into the peak
+    let confidence = value * (w * h) as f64 / used.max(1) as f64;
+    Ok((
+        wrap(px, w) + fx,
+        wrap(py, h) + fy,
+        confidence.clamp(0.0, 1.0),
+    ))
+}
+
+/// Mean of the color channels of each pixel, alpha is ignored
+fn luminance<T: Type, C: Color>(image: &Image<T, C>) -> Vec<f64> {
+    let channels: Vec<Channel> = (0..C::CHANNELS).filter(|c| C::ALPHA != Some(*c)).collect();
+    let mut gray = vec![0.0; image.width() * image.height()];
+    image.each_pixel(|pt, px| {
+        gray[pt.y * image.width() + pt.x] =
+            channels.iter().map(|c| px[*c]).sum::<f64>() / channels.len().max(1) as f64;
+    });
+    gray
+}
@@ -0,0 +1,182 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::*;
+
+/// Filter used to compute each MIP level from the previous one
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MipFilter {
+    /// Average of the pixels covered by each output pixel, this is fast but can alias
+    Box,
+
+    /// Tent filter twice as wide as `Box`, this removes most aliasing with little blurring
+    #[default]
+    Triangle,
+
+    /// Lanczos filter with the given radius, this keeps the most detail but can ring near edges
+    Lanczos(usize),
+}
+
+impl MipFilter {
+    /// Filter radius in output pixels
+    fn radius(&self) -> f64 {
+        match self {
+            MipFilter::Box => 0.5,
+            MipFilter::Triangle => 1.0,
+            MipFilter::Lanczos(a) => (*a).max(1) as f64,
+        }
+    }
+
+    /// Filter weight at `x` output pixels from the center
+    fn weight(&self, x: f64) -> f64 {
+        let x = x.abs();
+        match self {
+            MipFilter::Box => (x <= 0.5) as u8 as f64,
+            MipFilter::Triangle => (1.0 - x).max(0.0),
+            MipFilter::Lanczos(_) => {
+                let a = self.radius();
+                if x == 0.0 {
+                    1.0
+                } else if x < a {
+                    let px = std::f64::consts::PI * x;
+                    a * px.sin() * (px / a).sin() / (px * px)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Source indices and normalized weights for each of the `dest` output pixels, indices
+    /// outside of the source are clamped to the edge
+    fn weights(&self, src: usize, dest: usize) -> Vec<Vec<(usize, f64)>> {
+        let scale = src as f64 / dest as f64;
+        let support = self.radius() * scale;
+        (0..dest)
+            .map(|i| {
+                let center = (i as f64 + 0.5) * scale;
+                let start = (center - support).floor() as isize;
+                let end = (center + support).ceil() as isize;
+                let mut taps: Vec<(usize, f64)> = (start..end)
+                    .map(|j| {
+                        let w = self.weight((j as f64 + 0.5 - center) / scale);
+                        (j.clamp(0, src as isize - 1) as usize, w)
+                    })
+                    .filter(|(_, w)| *w != 0.0)
+                    .collect();
+                let sum: f64 = taps.iter().map(|(_, w)| w).sum();
+                if sum == 0.0 {
+                    return vec![((center as usize).min(src - 1), 1.0)];
+                }
+                taps.iter_mut().for_each(|(_, w)| *w /= sum);
+                taps
+            })
+            .collect()
+    }
+}
+
+/// Number of levels in a full MIP chain for an image of the given size, down to 1x1
+pub fn mip_levels(size: impl Into<Size>) -> usize {
+    let size = size.into();
+    let n = size.width.max(size.height).max(1);
+    (usize::BITS - n.leading_zeros()) as usize
+}
+
+/// Compute `n` rows of `len` values each, in parallel when the `parallel` feature is enabled
+#[cfg(feature = "parallel")]
+fn map_rows(n: usize, len: usize, f: impl Sync + Send + Fn(usize, &mut [f64])) -> Vec<f64> {
+    let mut dest = vec![0.0; n * len];
+    dest.par_chunks_mut(len.max(1))
+        .enumerate()
+        .for_each(|(y, row)| f(y, row));
+    dest
+}
+
+/// Compute `n` rows of `len` values each, in parallel when the `parallel` feature is enabled
+#[cfg(not(feature = "parallel"))]
+fn map_rows(n: usize, len: usize, f: impl Sync + Send + Fn(usize, &mut [f64])) -> Vec<f64> {
+    let mut dest = vec![0.0; n * len];
+    dest.chunks_mut(len.max(1))
+        .enumerate()
+        .for_each(|(y, row)| f(y, row));
+    dest
+}
+
+/// Separable resample of interleaved `channels` values from `from` to `to`
+fn downsample(data: &[f64], channels: usize, from: Size, to: Size, filter: MipFilter) -> Vec<f64> {
+    let columns = filter.weights(from.width, to.width);
+    let rows = filter.weights(from.height, to.height);
+
+    let horizontal = map_rows(from.height, to.width * channels, |y, row| {
+        let src = &data[y * from.width * channels..(y + 1) * from.width * channels];
+        for (x, taps) in columns.iter().enumerate() {
+            for (i, w) in taps {
+                for c in 0..channels {
+                    row[x * channels + c] += src[i * channels + c] * w;
+                }
+            }
+        }
+    });
+
+    let len = to.width * channels;
+    map_rows(to.height, len, |y, row| {
+        for (i, w) in &rows[y] {
+            let src = &horizontal[i * len..(i + 1) * len];
+            row.iter_mut().zip(src).for_each(|(d, s)| *d += s * w);
+        }
+    })
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Generate a full MIP chain down to 1x1, the first level is a copy of the image and each
+    /// following level is half the size of the previous one, rounded down. Levels are filtered
+    /// in linear light using the transfer function of the image colorspace and color channels are
+    /// weighted by alpha so transparent pixels don't bleed into their neighbors
+    pub fn generate_mipmaps(&self, filter: MipFilter) -> Vec<Image<T, C>> {
+        let transfer = self
+            .colorspace()
+            .map(|c| c.transfer)
+            .unwrap_or(TransferFunction::Linear);
+        let channels = C::CHANNELS;
+
+        // Linear, premultiplied values are kept between levels so they're only quantized once
+        let mut data = vec![0.0; self.data().len()];
+        T::to_norm_slice(self.data(), &mut data);
+        for px in data.chunks_exact_mut(channels) {
+            let alpha = C::ALPHA.map(|a| px[a]).unwrap_or(1.0);
+            for c in (0..channels).filter(|c| C::ALPHA != Some(*c)) {
+                px[c] = transfer.to_linear(px[c]) * alpha;
+            }
+        }
+
+        let mut size = self.size();
+        let mut levels = vec![self.clone()];
+        while size.width > 1 || size.height > 1 {
+            let next = Size::new((size.width / 2).max(1), (size.height / 2).max(1));
+            data = downsample(&data, channels, size, next, filter);
+            size = next;
+
+            let mut encoded = data.clone();
+            for px in encoded.chunks_exact_mut(channels) {
+                let alpha = match C::ALPHA {
+                    Some(a) => {
+                        px[a] = px[a].clamp(0.0, 1.0);
+                        px[a]
+                    }
+                    None => 1.0,
+                };
+                for c in (0..channels).filter(|c| C::ALPHA != Some(*c)) {
+                    let x = if alpha > 0.0 { px[c] / alpha } else { 0.0 };
+                    px[c] = transfer.from_linear(x);
+                }
+            }
+
+            let mut level = Image::new(size);
+            level.meta.colorspace = self.meta.colorspace;
+            T::from_norm_slice(&encoded, level.data_mut());
+            levels.push(level);
+        }
+        levels
+    }
+}
@@ -0,0 +1,301 @@
+use crate::*;
+
+/// Right-hand side of image arithmetic, pixels and scalars are broadcast to every pixel of the
+/// image
+pub enum Operand<'a, T: Type, C: Color> {
+    /// Image with the same shape
+    Image(&'a Image<T, C>),
+
+    /// Pixel applied to every pixel
+    Pixel(&'a Pixel<C>),
+
+    /// Value applied to every channel, normalized when adding or subtracting and used as a
+    /// factor when multiplying or dividing
+    Scalar(f64),
+}
+
+impl<'a, T: Type, C: Color> From<&'a Image<T, C>> for Operand<'a, T, C> {
+    fn from(image: &'a Image<T, C>) -> Self {
+        Operand::Image(image)
+    }
+}
+
+impl<'a, T: Type, C: Color> From<&'a Pixel<C>> for Operand<'a, T, C> {
+    fn from(px: &'a Pixel<C>) -> Self {
+        Operand::Pixel(px)
+    }
+}
+
+impl<'a, T: Type, C: Color> From<f64> for Operand<'a, T, C> {
+    fn from(x: f64) -> Self {
+        Operand::Scalar(x)
+    }
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Apply `f` to the normalized values of each channel and the matching value from `rhs`,
+    /// results outside of the range of `T` are stored using the given overflow policy. Returns
+    /// an error when `rhs` is an image with a different shape
+    pub fn combine_with<'a>(
+        &mut self,
+        rhs: impl Into<Operand<'a, T, C>>,
+        overflow: Overflow,
+        f: impl Fn(f64, f64) -> f64,
+    ) -> Result<&mut Self, Error>
+    where
+        T: 'a,
+        C: 'a,
+    {
+        let rhs = rhs.into();
+        if let Operand::Image(other) = rhs {
+            if other.shape() != self.shape() {
+                return Err(Error::ShapeMismatch {
+                    expected: self.shape(),
+                    got: other.shape(),
+                });
+            }
+        }
+
+        if T::BASE == io::BaseType::Half {
+            self.combine_lanes(rhs, overflow, f);
+            return Ok(self);
+        }
+
+        match rhs {
+            Operand::Image(other) => {
+                self.data_mut()
+                    .iter_mut()
+                    .zip(other.data())
+                    .for_each(|(x, y)| *x = overflow.apply(f(x.to_norm(), y.to_norm())));
+            }
+            Operand::Pixel(px) => {
+                self.data_mut()
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, x)| *x = overflow.apply(f(x.to_norm(), px[i % C::CHANNELS])));
+            }
+            Operand::Scalar(y) => {
+                self.data_mut()
+                    .iter_mut()
+                    .for_each(|x| *x = overflow.apply(f(x.to_norm(), y)));
+            }
+        }
+        Ok(self)
+    }
+
+    /// `combine_with` implementation for half floats, values are converted to and from `f32`
+    /// lanes in bulk instead of one channel at a time
+    fn combine_lanes(
+        &mut self,
+        rhs: Operand<T, C>,
+        overflow: Overflow,
+        f: impl Fn(f64, f64) -> f64,
+    ) {
+        let chunk = 256 * C::CHANNELS;
+        let mut a = vec![0.0f32; chunk];
+        let mut b = vec![0.0f32; chunk];
+        for (i, data) in self.data_mut().chunks_mut(chunk).enumerate() {
+            let a = &mut a[..data.len()];
+            T::to_f32_slice(data, a);
+            match rhs {
+                Operand::Image(other) => {
+                    let b = &mut b[..data.len()];
+                    T::to_f32_slice(&other.data()[i * chunk..i * chunk + data.len()], b);
+                    a.iter_mut()
+                        .zip(b.iter())
+                        .for_each(|(x, y)| *x = overflow.apply(f(*x as f64, *y as f64)));
+                }
+                Operand::Pixel(px) => {
+                    // Chunks are a multiple of the channel count so channels line up
+                    a.iter_mut()
+                        .enumerate()
+                        .for_each(|(j, x)| *x = overflow.apply(f(*x as f64, px[j % C::CHANNELS])));
+                }
+                Operand::Scalar(y) => {
+                    a.iter_mut()
+                        .for_each(|x| *x = overflow.apply(f(*x as f64, y)));
+                }
+            }
+            T::from_f32_slice(a, data);
+        }
+    }
+
+    /// Add `rhs` using the given overflow policy
+    pub fn add_with<'a>(
+        &mut self,
+        rhs: impl Into<Operand<'a, T, C>>,
+        overflow: Overflow,
+    ) -> Result<&mut Self, Error>
+    where
+        T: 'a,
+        C: 'a,
+    {
+        self.combine_with(rhs, overflow, |a, b| a + b)
+    }
+
+    /// Subtract `rhs` using the given overflow policy
+    pub fn sub_with<'a>(
+        &mut self,
+        rhs: impl Into<Operand<'a, T, C>>,
+        overflow: Overflow,
+    ) -> Result<&mut Self, Error>
+    where
+        T: 'a,
+        C: 'a,
+    {
+        self.combine_with(rhs, overflow, |a, b| a - b)
+    }
+
+    /// Multiply by `rhs` using the given overflow policy
+    pub fn mul_with<'a>(
+        &mut self,
+        rhs: impl Into<Operand<'a, T, C>>,
+        overflow: Overflow,
+    ) -> Result<&mut Self, Error>
+    where
+        T: 'a,
+        C: 'a,
+    {
+        self.combine_with(rhs, overflow, |a, b| a * b)
+    }
+
+    /// Divide by `rhs` using the given overflow policy, division by zero produces the max value
+    /// for integer types
+    pub fn div_with<'a>(
+        &mut self,
+        rhs: impl Into<Operand<'a, T, C>>,
+        overflow: Overflow,
+    ) -> Result<&mut Self, Error>
+    where
+        T: 'a,
+        C: 'a,
+    {
+        self.combine_with(rhs, overflow, div)
+    }
+}
+
+fn div(a: f64, b: f64) -> f64 {
+    if b == 0.0 {
+        if a == 0.0 {
+            0.0
+        } else {
+            a.signum() * f64::INFINITY
+        }
+    } else {
+        a / b
+    }
+}
+
+// Operators use `T::OVERFLOW` and work on normalized values like `Pixel`, so scalars of type
+// `T` are normalized before they are added or subtracted. Multipliers and divisors are used as
+// is, `img * 2u8` doubles every value. Operators between images panic when the shapes don't
+// match, use `combine_with` and friends to handle the error instead
+macro_rules! image_op {
+    ($op:ident, $f:ident, $assign:ident, $assign_f:ident, $with:ident, $scalar:ident) => {
+        impl<'a, T: Type, C: Color> std::ops::$assign<&'a Image<T, C>> for Image<T, C> {
+            fn $assign_f(&mut self, other: &'a Image<T, C>) {
+                if let Err(e) = self.$with(other, T::OVERFLOW) {
+                    panic!("{}", e);
+                }
+            }
+        }
+
+        impl<T: Type, C: Color> std::ops::$assign<Image<T, C>> for Image<T, C> {
+            fn $assign_f(&mut self, other: Image<T, C>) {
+                std::ops::$assign::$assign_f(self, &other)
+            }
+        }
+
+        impl<'a, T: Type, C: Color> std::ops::$assign<&'a Pixel<C>> for Image<T, C> {
+            fn $assign_f(&mut self, other: &'a Pixel<C>) {
+                let _ = self.$with(other, T::OVERFLOW);
+            }
+        }
+
+        impl<T: Type, C: Color> std::ops::$assign<Pixel<C>> for Image<T, C> {
+            fn $assign_f(&mut self, other: Pixel<C>) {
+                std::ops::$assign::$assign_f(self, &other)
+            }
+        }
+
+        impl<T: Type, C: Color> std::ops::$assign<T> for Image<T, C> {
+            fn $assign_f(&mut self, other: T) {
+                let _ = self.$with(other.$scalar(), T::OVERFLOW);
+            }
+        }
+
+        impl<'a, T: Type, C: Color> std::ops::$op<&'a Image<T, C>> for Image<T, C> {
+            type Output = Image<T, C>;
+
+            fn $f(mut self, other: &'a Image<T, C>) -> Image<T, C> {
+                std::ops::$assign::$assign_f(&mut self, other);
+                self
+            }
+        }
+
+        impl<T: Type, C: Color> std::ops::$op<Image<T, C>> for Image<T, C> {
+            type Output = Image<T, C>;
+
+            fn $f(mut self, other: Image<T, C>) -> Image<T, C> {
+                std::ops::$assign::$assign_f(&mut self, &other);
+                self
+            }
+        }
+
+        impl<'a, T: Type, C: Color> std::ops::$op<&'a Image<T, C>> for &'a Image<T, C> {
+            type Output = Image<T, C>;
+
+            fn $f(self, other: &'a Image<T, C>) -> Image<T, C> {
+                std::ops::$op::$f(self.clone(), other)
+            }
+        }
+
+        impl<'a, T: Type, C: Color> std::ops::$op<&'a Pixel<C>> for Image<T, C> {
+            type Output = Image<T, C>;
+
+            fn $f(mut self, other: &'a Pixel<C>) -> Image<T, C> {
+                std::ops::$assign::$assign_f(&mut self, other);
+                self
+            }
+        }
+
+        impl<T: Type, C: Color> std::ops::$op<Pixel<C>> for Image<T, C> {
+            type Output = Image<T, C>;
+
+            fn $f(mut self, other: Pixel<C>) -> Image<T, C> {
+                std::ops::$assign::$assign_f(&mut self, &other);
+                self
+            }
+        }
+
+        impl<'a, T: Type, C: Color> std::ops::$op<&'a Pixel<C>> for &'a Image<T, C> {
+            type Output = Image<T, C>;
+
+            fn $f(self, other: &'a Pixel<C>) -> Image<T, C> {
+                std::ops::$op::$f(self.clone(), other)
+            }
+        }
+
+        impl<T: Type, C: Color> std::ops::$op<T> for Image<T, C> {
+            type Output = Image<T, C>;
+
+            fn $f(mut self, other: T) -> Image<T, C> {
+                std::ops::$assign::$assign_f(&mut self, other);
+                self
+            }
+        }
+
+        impl<'a, T: Type, C: Color> std::ops::$op<T> for &'a Image<T, C> {
+            type Output = Image<T, C>;
+
+            fn $f(self, other: T) -> Image<T, C> {
+                std::ops::$op::$f(self.clone(), other)
+            }
+        }
+    };
+}
+
+image_op!(Add, add, AddAssign, add_assign, add_with, to_norm);
+image_op!(Sub, sub, SubAssign, sub_assign, sub_with, to_norm);
+image_op!(Mul, mul, MulAssign, mul_assign, mul_with, to_f64);
+image_op!(Div, div, DivAssign, div_assign, div_with, to_f64);
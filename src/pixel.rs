@@ -144,13 +144,39 @@ impl<C: Color> Pixel<C> {
         dest
     }
 
+    /// Convert pixel color type, returns `Error::LossyConversion` when a value falls outside of
+    /// `[0, 1]` or a non-opaque alpha value has no destination channel
+    pub fn convert_checked<D: Color>(&self) -> Result<Pixel<D>, Error> {
+        let dest = self.convert::<D>();
+        let loss = ConversionLoss {
+            clipped: dest.iter().filter(|x| !(0.0..=1.0).contains(*x)).count(),
+            alpha_dropped: usize::from(D::ALPHA.is_none() && self.alpha().is_some_and(|a| a < 1.0)),
+            reduced_bit_depth: false,
+        };
+        if loss.is_lossless() {
+            Ok(dest)
+        } else {
+            Err(Error::LossyConversion(loss))
+        }
+    }
+
+    /// Convert pixel color type, colors that fall outside of `[0, 1]` in the destination color
+    /// are brought back into range using `mapping`
+    pub fn convert_mapped<D: Color>(&self, mapping: GamutMapping) -> Pixel<D> {
+        let mut rgb: Pixel<Rgb> = Pixel::new();
+        C::to_rgb(self, &mut rgb);
+        mapping.map_into(&rgb, |rgb| {
+            let mut dest = Pixel::new();
+            D::from_rgb(rgb, &mut dest);
+            dest
+        })
+    }
+
     /// Copy values from an existing slice
     #[inline]
     pub fn copy_from_slice<T: Type>(&mut self, data: impl AsRef<[T]>) -> &mut Self {
         let data = data.as_ref();
-        self.0.iter_mut().enumerate().for_each(|(i, x)| {
-            *x = data[i].to_norm();
-        });
+        T::to_norm_slice(&data[..self.0.len()], &mut self.0);
         self
     }
 
@@ -158,9 +184,7 @@ impl<C: Color> Pixel<C> {
     #[inline]
     pub fn copy_from_data<T: Type>(&mut self, data: &Data<T, C>) -> &mut Self {
         let data = data.as_ref();
-        self.0.iter_mut().enumerate().for_each(|(i, x)| {
-            *x = data[i].to_norm();
-        });
+        T::to_norm_slice(&data[..self.0.len()], &mut self.0);
         self
     }
 
@@ -178,9 +202,7 @@ impl<C: Color> Pixel<C> {
     /// Copy values to an existing slice
     pub fn copy_to_slice<T: Type>(&self, mut data: impl AsMut<[T]>) {
         let data = data.as_mut();
-        self.0.iter().enumerate().for_each(|(i, x)| {
-            data[i] = T::from_norm(*x);
-        });
+        T::from_norm_slice(&self.0, &mut data[..self.0.len()]);
     }
 
     /// Create from slice
@@ -266,6 +288,16 @@ impl<C: Color> Pixel<C> {
     pub fn gamma_lin(&mut self) -> &mut Self {
         self.gamma(2.2)
     }
+
+    /// Convert from sRGB to linear using the exact piecewise transfer function
+    pub fn srgb_to_linear(&mut self) -> &mut Self {
+        self.map(srgb_to_linear)
+    }
+
+    /// Convert from linear to sRGB using the exact piecewise transfer function
+    pub fn linear_to_srgb(&mut self) -> &mut Self {
+        self.map(linear_to_srgb)
+    }
 }
 
 impl<T: Type, C: Color> std::iter::FromIterator<T> for Pixel<C> {
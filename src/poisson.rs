@@ -0,0 +1,236 @@
+use crate::*;
+
+/// Conjugate gradient solver settings used by `blend_with`
+#[derive(Debug, Clone, Copy)]
+pub struct Solver {
+    /// Maximum number of iterations per channel
+    pub max_iterations: usize,
+
+    /// Stop once the residual norm drops below `tolerance` times the initial residual norm
+    pub tolerance: f64,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Solver {
+            max_iterations: 2000,
+            tolerance: 1e-6,
+        }
+    }
+}
+
+/// Neighbor of an unknown pixel
+enum Neighbor {
+    /// Another unknown pixel, by index
+    Unknown(usize),
+
+    /// Fixed pixel on the boundary of the masked region, in `dst` coordinates
+    Boundary(Point),
+}
+
+/// Seamlessly clone the masked region of `src` into `dst`, with the top-left corner of `src`
+/// placed at `offset`. Pixels where `mask` is greater than 0.5 are replaced by the solution of the
+/// Poisson equation that keeps the gradients of `src` while matching `dst` along the boundary
+pub fn blend<T: Type, C: Color, M: Type>(
+    dst: &mut Image<T, C>,
+    src: &Image<T, C>,
+    mask: &Image<M, Gray>,
+    offset: impl Into<Point>,
+) -> Result<(), Error> {
+    blend_with(dst, src, mask, offset, Solver::default())
+}
+
+/// Same as `blend` using the provided solver settings
+pub fn blend_with<T: Type, C: Color, M: Type>(
+    dst: &mut Image<T, C>,
+    src: &Image<T, C>,
+    mask: &Image<M, Gray>,
+    offset: impl Into<Point>,
+    solver: Solver,
+) -> Result<(), Error> {
+    if mask.size() != src.size() {
+        return Err(Error::InvalidDimensions(
+            mask.width(),
+            mask.height(),
+            mask.channels(),
+        ));
+    }
+
+    let offset = offset.into();
+    let (width, height) = (src.width(), src.height());
+
+    // Map source pixels to unknowns, pixels that fall outside of `dst` are skipped
+    let mut index = vec![None; width * height];
+    let mut unknowns = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let d = Point::new(x + offset.x, y + offset.y);
+            if mask.get_f((x, y), 0) > 0.5 && dst.in_bounds(d) {
+                index[y * width + x] = Some(unknowns.len());
+                unknowns.push(Point::new(x, y));
+            }
+        }
+    }
+
+    if unknowns.is_empty() {
+        return Ok(());
+    }
+
+    // Collect the 4-connected neighbors of each unknown that lie inside of `dst`
+    let neighbors: Vec<Vec<(Option<Point>, Neighbor)>> = unknowns
+        .iter()
+        .map(|p| {
+            let mut n = Vec::with_capacity(4);
+            let candidates = [
+                (p.x as isize - 1, p.y as isize),
+                (p.x as isize + 1, p.y as isize),
+                (p.x as isize, p.y as isize - 1),
+                (p.x as isize, p.y as isize + 1),
+            ];
+            for (x, y) in candidates {
+                let (dx, dy) = (x + offset.x as isize, y + offset.y as isize);
+                if dx < 0 || dy < 0 || !dst.in_bounds((dx as usize, dy as usize)) {
+                    continue;
+                }
+
+                let inside = x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height;
+                let q = inside.then(|| Point::new(x as usize, y as usize));
+                match q.and_then(|q| index[q.y * width + q.x]) {
+                    Some(i) => n.push((q, Neighbor::Unknown(i))),
+                    None => n.push((q, Neighbor::Boundary(Point::new(dx as usize, dy as usize)))),
+                }
+            }
+            n
+        })
+        .collect();
+
+    for c in 0..C::CHANNELS {
+        // Right-hand side: divergence of the source gradient plus boundary values
+        let b: Vec<f64> = unknowns
+            .iter()
+            .zip(neighbors.iter())
+            .map(|(p, n)| {
+                let g = src.get_f(*p, c);
+                n.iter()
+                    .map(|(q, kind)| {
+                        // Neighbors outside of `src` are treated as having a zero gradient
+                        let gq = q.map(|q| src.get_f(q, c)).unwrap_or(g);
+                        let v = g - gq;
+                        match kind {
+                            Neighbor::Unknown(_) => v,
+                            Neighbor::Boundary(d) => v + dst.get_f(*d, c),
+                        }
+                    })
+                    .sum()
+            })
+            .collect();
+
+        let initial: Vec<f64> = unknowns
+            .iter()
+            .map(|p| dst.get_f((p.x + offset.x, p.y + offset.y), c))
+            .collect();
+
+        let x = conjugate_gradient(&neighbors, &b, initial, solver);
+
+        for (p, value) in unknowns.iter().zip(x) {
+            dst.set_f((p.x + offset.x, p.y + offset.y), c, value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Multiply `x` by the discrete Laplacian restricted to the unknowns
+fn laplacian(neighbors: &[Vec<(Option<Point>, Neighbor)>], x: &[f64], dest: &mut [f64]) {
+    for (i, n) in neighbors.iter().enumerate() {
+        let mut sum = n.len() as f64 * x[i];
+        for (_, kind) in n {
+            if let Neighbor::Unknown(j) = kind {
+                sum -= x[*j];
+            }
+        }
+        dest[i] = sum;
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn conjugate_gradient(
+    neighbors: &[Vec<(Option<Point>, Neighbor)>],
+    b: &[f64],
+    mut x: Vec<f64>,
+    solver: Solver,
+) -> Vec<f64> {
+    let n = b.len();
+    let mut ax = vec![0.0; n];
+    laplacian(neighbors, &x, &mut ax);
+
+    let mut r: Vec<f64> = b.iter().zip(&ax).map(|(b, ax)| b - ax).collect();
+    let mut p = r.clone();
+    let mut rr = dot(&r, &r);
+    let threshold = rr.sqrt() * solver.tolerance;
+    let mut ap = ax;
+
+    for _ in 0..solver.max_iterations {
+        if rr.sqrt() <= threshold || rr == 0.0 {
+            break;
+        }
+
+        laplacian(neighbors, &p, &mut ap);
+        let pap = dot(&p, &ap);
+        if pap <= 0.0 {
+            break;
+        }
+
+        let alpha = rr / pap;
+        for i in 0..n {
+            x[i] += alpha * p[i];
+            r[i] -= alpha * ap[i];
+        }
+
+        let rr_next = dot(&r, &r);
+        let beta = rr_next / rr;
+        for i in 0..n {
+            p[i] = r[i] + beta * p[i];
+        }
+        rr = rr_next;
+    }
+
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_poisson_blend() {
+        // A constant source region takes on the surrounding destination color
+        let mut dst = Image::<f32, Gray>::new((16, 16));
+        dst.for_each(|_, mut px| px[0] = 0.25);
+        let mut src = Image::<f32, Gray>::new((8, 8));
+        src.for_each(|_, mut px| px[0] = 1.0);
+        let mut mask = Image::<u8, Gray>::new((8, 8));
+        mask.for_each(|pt, mut px| {
+            if pt.x > 0 && pt.y > 0 && pt.x < 7 && pt.y < 7 {
+                px[0] = 255
+            }
+        });
+
+        poisson::blend(&mut dst, &src, &mask, (4, 4)).unwrap();
+        dst.each_pixel(|_, px| assert!((px[0] - 0.25).abs() < 1e-4));
+
+        // Gradients from the source are preserved
+        let mut src = Image::<f32, Gray>::new((8, 8));
+        src.for_each(|pt, mut px| px[0] = (pt.x * pt.x + pt.y) as f32 * 0.01);
+        poisson::blend(&mut dst, &src, &mask, (4, 4)).unwrap();
+        let lap = |f: &dyn Fn(usize, usize) -> f64, x: usize, y: usize| {
+            4.0 * f(x, y) - f(x - 1, y) - f(x + 1, y) - f(x, y - 1) - f(x, y + 1)
+        };
+        let a = lap(&|x, y| dst.get_f((x + 4, y + 4), 0), 3, 3);
+        let b = lap(&|x, y| src.get_f((x, y), 0), 3, 3);
+        assert!((a - b).abs() < 1e-4);
+    }
+}
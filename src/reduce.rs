@@ -0,0 +1,405 @@
+use crate::*;
+
+/// Reductions compute a single value from every pixel in an image, rows are processed in
+/// parallel and the partial results are merged using `combine`
+pub trait Reduce<T: Type, C: Color>: Sync {
+    /// Intermediate state
+    type State: Clone + Send + Sync;
+
+    /// Final result
+    type Output;
+
+    /// Create an empty state
+    fn init(&self) -> Self::State;
+
+    /// Add a single pixel to `state`
+    fn fold(&self, state: &mut Self::State, pt: Point, px: &Pixel<C>);
+
+    /// Merge two states
+    fn combine(&self, a: Self::State, b: Self::State) -> Self::State;
+
+    /// Convert the final state into the output value
+    fn finish(&self, state: Self::State) -> Self::Output;
+
+    /// Evaluate the reduction over an entire image
+    fn eval(&self, image: &Image<T, C>) -> Self::Output {
+        self.eval_with(&ExecutionOptions::default(), image)
+    }
+
+    /// Evaluate the reduction over an entire image using the provided `ExecutionOptions`
+    fn eval_with(&self, options: &ExecutionOptions, image: &Image<T, C>) -> Self::Output {
+        let width = image.width();
+        let state = options.map_reduce(
+            image.height(),
+            self.init(),
+            |y| {
+                let mut state = self.init();
+                let mut px = Pixel::new();
+                for x in 0..width {
+                    image.pixel_at((x, y), &mut px);
+                    self.fold(&mut state, Point::new(x, y), &px);
+                }
+                state
+            },
+            |a, b| self.combine(a, b),
+        );
+        self.finish(state)
+    }
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Evaluate a reduction over the image
+    pub fn reduce<R: Reduce<T, C>>(&self, r: R) -> R::Output {
+        r.eval(self)
+    }
+}
+
+/// Per-channel minimum and maximum values
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MinMax;
+
+impl<T: Type, C: Color> Reduce<T, C> for MinMax {
+    type State = (Vec<f64>, Vec<f64>);
+    type Output = (Pixel<C>, Pixel<C>);
+
+    fn init(&self) -> Self::State {
+        (
+            vec![f64::INFINITY; C::CHANNELS],
+            vec![f64::NEG_INFINITY; C::CHANNELS],
+        )
+    }
+
+    fn fold(&self, state: &mut Self::State, _pt: Point, px: &Pixel<C>) {
+        for c in 0..C::CHANNELS {
+            state.0[c] = state.0[c].min(px[c]);
+            state.1[c] = state.1[c].max(px[c]);
+        }
+    }
+
+    fn combine(&self, mut a: Self::State, b: Self::State) -> Self::State {
+        for c in 0..C::CHANNELS {
+            a.0[c] = a.0[c].min(b.0[c]);
+            a.1[c] = a.1[c].max(b.1[c]);
+        }
+        a
+    }
+
+    fn finish(&self, state: Self::State) -> Self::Output {
+        (Pixel::from(state.0), Pixel::from(state.1))
+    }
+}
+
+/// Per-channel mean value
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mean;
+
+impl<T: Type, C: Color> Reduce<T, C> for Mean {
+    type State = (usize, Vec<f64>);
+    type Output = Pixel<C>;
+
+    fn init(&self) -> Self::State {
+        (0, vec![0.0; C::CHANNELS])
+    }
+
+    fn fold(&self, state: &mut Self::State, _pt: Point, px: &Pixel<C>) {
+        state.0 += 1;
+        for c in 0..C::CHANNELS {
+            state.1[c] += px[c];
+        }
+    }
+
+    fn combine(&self, mut a: Self::State, b: Self::State) -> Self::State {
+        a.0 += b.0;
+        for c in 0..C::CHANNELS {
+            a.1[c] += b.1[c];
+        }
+        a
+    }
+
+    fn finish(&self, (n, mut sum): Self::State) -> Self::Output {
+        let n = n.max(1) as f64;
+        sum.iter_mut().for_each(|x| *x /= n);
+        Pixel::from(sum)
+    }
+}
+
+/// Per-channel standard deviation, computed using Welford's algorithm
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdDev;
+
+impl<T: Type, C: Color> Reduce<T, C> for StdDev {
+    /// (count, mean, sum of squared differences)
+    type State = (usize, Vec<f64>, Vec<f64>);
+    type Output = Pixel<C>;
+
+    fn init(&self) -> Self::State {
+        (0, vec![0.0; C::CHANNELS], vec![0.0; C::CHANNELS])
+    }
+
+    fn fold(&self, state: &mut Self::State, _pt: Point, px: &Pixel<C>) {
+        state.0 += 1;
+        let n = state.0 as f64;
+        for c in 0..C::CHANNELS {
+            let delta = px[c] - state.1[c];
+            state.1[c] += delta / n;
+            state.2[c] += delta * (px[c] - state.1[c]);
+        }
+    }
+
+    fn combine(&self, a: Self::State, b: Self::State) -> Self::State {
+        if a.0 == 0 {
+            return b;
+        } else if b.0 == 0 {
+            return a;
+        }
+
+        let (na, nb) = (a.0 as f64, b.0 as f64);
+        let n = na + nb;
+        let mut dest = (a.0 + b.0, a.1, a.2);
+        for c in 0..C::CHANNELS {
+            let delta = b.1[c] - dest.1[c];
+            dest.1[c] += delta * nb / n;
+            dest.2[c] += b.2[c] + delta * delta * na * nb / n;
+        }
+        dest
+    }
+
+    fn finish(&self, (n, _, m2): Self::State) -> Self::Output {
+        let n = n.max(1) as f64;
+        m2.into_iter()
+            .map(|x| (x / n).sqrt())
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+/// Log-average luminance, `exp(mean(log(delta + L)))`, commonly used as the "key" of an HDR image
+#[derive(Debug, Clone, Copy)]
+pub struct LogAverageLuminance {
+    /// Small value used to avoid taking the log of zero
+    pub delta: f64,
+}
+
+impl Default for LogAverageLuminance {
+    fn default() -> Self {
+        LogAverageLuminance { delta: 1e-4 }
+    }
+}
+
+impl<T: Type, C: Color> Reduce<T, C> for LogAverageLuminance {
+    type State = (usize, f64);
+    type Output = f64;
+
+    fn init(&self) -> Self::State {
+        (0, 0.0)
+    }
+
+    fn fold(&self, state: &mut Self::State, _pt: Point, px: &Pixel<C>) {
+        let rgb: Pixel<Rgb> = px.convert();
+        let l = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+        state.0 += 1;
+        state.1 += (self.delta + l.max(0.0)).ln();
+    }
+
+    fn combine(&self, a: Self::State, b: Self::State) -> Self::State {
+        (a.0 + b.0, a.1 + b.1)
+    }
+
+    fn finish(&self, (n, sum): Self::State) -> Self::Output {
+        (sum / n.max(1) as f64).exp()
+    }
+}
+
+/// Per-channel histograms, values outside of `range` are counted in the first or last bin
+#[derive(Debug, Clone, Copy)]
+pub struct Histograms {
+    /// Number of bins
+    pub bins: usize,
+
+    /// Range of normalized values covered by the histograms
+    pub range: (f64, f64),
+}
+
+impl Default for Histograms {
+    fn default() -> Self {
+        Histograms::new(1024)
+    }
+}
+
+impl Histograms {
+    /// Histograms with the given number of bins covering `[0, 1]`
+    pub fn new(bins: usize) -> Histograms {
+        Histograms {
+            bins,
+            range: (0.0, 1.0),
+        }
+    }
+
+    /// Build histograms covering `[min, max]`
+    pub fn with_range(mut self, min: f64, max: f64) -> Histograms {
+        self.range = (min, max);
+        self
+    }
+
+    /// Build histograms covering the smallest and largest color value in `image`, this is used
+    /// for HDR images where values aren't limited to `[0, 1]`
+    pub fn with_range_of<T: Type, C: Color>(self, image: &Image<T, C>) -> Histograms {
+        let (min, max) = image.reduce(MinMax);
+        let channels = (0..C::CHANNELS).filter(|c| C::ALPHA != Some(*c));
+        let (lo, hi) = channels.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), c| {
+            (lo.min(min[c]), hi.max(max[c]))
+        });
+        if lo < hi {
+            self.with_range(lo, hi)
+        } else {
+            self
+        }
+    }
+}
+
+impl<T: Type, C: Color> Reduce<T, C> for Histograms {
+    type State = Vec<Histogram>;
+    type Output = Vec<Histogram>;
+
+    fn init(&self) -> Self::State {
+        let (min, max) = self.range;
+        vec![Histogram::new_with_range(self.bins.max(2), min, max); C::CHANNELS]
+    }
+
+    fn fold(&self, state: &mut Self::State, _pt: Point, px: &Pixel<C>) {
+        // Index every channel, `Pixel::iter` skips alpha
+        for (c, h) in state.iter_mut().enumerate() {
+            h.add(px[c]);
+        }
+    }
+
+    fn combine(&self, a: Self::State, b: Self::State) -> Self::State {
+        a.into_iter()
+            .zip(b)
+            .map(|(a, b)| Histogram::join([a, b]))
+            .collect()
+    }
+
+    fn finish(&self, state: Self::State) -> Self::Output {
+        state
+    }
+}
+
+/// Joint histogram of two channels, see `Histogram2d`
+#[derive(Debug, Clone, Copy)]
+pub struct JointHistogram {
+    /// Channels used for the first and second value
+    pub channels: (Channel, Channel),
+
+    /// Number of bins on each axis
+    pub bins: usize,
+
+    /// Range of normalized values covered by the histogram
+    pub range: (f64, f64),
+}
+
+impl JointHistogram {
+    /// Joint histogram of channels `a` and `b` with the given number of bins covering `[0, 1]`
+    pub fn new(a: Channel, b: Channel, bins: usize) -> JointHistogram {
+        JointHistogram {
+            channels: (a, b),
+            bins,
+            range: (0.0, 1.0),
+        }
+    }
+
+    /// Build histogram covering `[min, max]`
+    pub fn with_range(mut self, min: f64, max: f64) -> JointHistogram {
+        self.range = (min, max);
+        self
+    }
+}
+
+impl<T: Type, C: Color> Reduce<T, C> for JointHistogram {
+    type State = Histogram2d;
+    type Output = Histogram2d;
+
+    fn init(&self) -> Self::State {
+        Histogram2d::new_with_range(self.bins.max(2), self.range, self.range)
+    }
+
+    fn fold(&self, state: &mut Self::State, _pt: Point, px: &Pixel<C>) {
+        state.add(px[self.channels.0], px[self.channels.1]);
+    }
+
+    fn combine(&self, a: Self::State, b: Self::State) -> Self::State {
+        Histogram2d::join([a, b])
+    }
+
+    fn finish(&self, state: Self::State) -> Self::Output {
+        state
+    }
+}
+
+/// Smallest region containing all pixels with at least one non-zero color channel, the alpha
+/// channel is ignored. Returns `None` when every pixel is zero
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BoundingBox;
+
+impl<T: Type, C: Color> Reduce<T, C> for BoundingBox {
+    /// (min x, min y, max x, max y)
+    type State = Option<(usize, usize, usize, usize)>;
+    type Output = Option<Region>;
+
+    fn init(&self) -> Self::State {
+        None
+    }
+
+    fn fold(&self, state: &mut Self::State, pt: Point, px: &Pixel<C>) {
+        if px.iter().all(|x| *x == 0.0) {
+            return;
+        }
+
+        *state = <Self as Reduce<T, C>>::combine(self, *state, Some((pt.x, pt.y, pt.x, pt.y)));
+    }
+
+    fn combine(&self, a: Self::State, b: Self::State) -> Self::State {
+        match (a, b) {
+            (Some(a), Some(b)) => Some((a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3))),
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
+
+    fn finish(&self, state: Self::State) -> Self::Output {
+        state.map(|(x0, y0, x1, y1)| {
+            Region::new(Point::new(x0, y0), Size::new(x1 - x0 + 1, y1 - y0 + 1))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reduce::*;
+
+    #[test]
+    fn test_reduce_basic() {
+        let mut image = Image::<f32, Gray>::new((10, 10));
+        image.set_f((2, 3), 0, 1.0);
+        image.set_f((5, 7), 0, 0.5);
+
+        let (min, max) = image.reduce(MinMax);
+        assert_eq!(min[0], 0.0);
+        assert_eq!(max[0], 1.0);
+
+        let mean = image.reduce(Mean);
+        assert!((mean[0] - 0.015).abs() < 1e-9);
+
+        let std = image.reduce(StdDev);
+        let expected = ((1.0f64 + 0.25) / 100.0 - 0.015 * 0.015).sqrt();
+        assert!((std[0] - expected).abs() < 1e-9);
+
+        let bbox = image.reduce(BoundingBox).unwrap();
+        assert_eq!(bbox.origin, Point::new(2, 3));
+        assert_eq!(bbox.size, Size::new(4, 5));
+
+        assert!(Image::<f32, Gray>::new((4, 4))
+            .reduce(BoundingBox)
+            .is_none());
+    }
+}
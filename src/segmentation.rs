@@ -0,0 +1,859 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::*;
+
+/// Gradient magnitude of the luminance of `image`, computed using the Sobel kernels
+fn gradient<T: Type, C: Color>(image: &Image<T, C>) -> Vec<f64> {
+    let gray: Image<f32, Gray> = image.convert();
+    let mut sobel_x = Kernel::sobel_x();
+    let mut sobel_y = Kernel::sobel_y();
+    sobel_x.set_edge_strategy(kernel::EdgeStrategy::Extend);
+    sobel_y.set_edge_strategy(kernel::EdgeStrategy::Extend);
+    let gx: Image<f32, Gray> = gray.run(sobel_x, None);
+    let gy: Image<f32, Gray> = gray.run(sobel_y, None);
+    gx.data()
+        .iter()
+        .zip(gy.data())
+        .map(|(x, y)| (*x as f64).hypot(*y as f64))
+        .collect()
+}
+
+/// Label image from a flat list of labels
+fn labels_image(size: Size, labels: Vec<u32>) -> Image<u32, Gray> {
+    Image::new_with_data(size, labels).expect("label buffer matches image size")
+}
+
+/// Flooding queue entry, lower gradients are popped first and ties are resolved in insertion order
+#[derive(PartialEq)]
+struct Entry {
+    priority: f64,
+    seq: usize,
+    index: usize,
+}
+
+impl Eq for Entry {}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .priority
+            .total_cmp(&self.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn neighbors4(index: usize, width: usize, height: usize) -> impl Iterator<Item = usize> {
+    let (x, y) = (index % width, index / width);
+    let left = (x > 0).then(|| index - 1);
+    let right = (x + 1 < width).then(|| index + 1);
+    let up = (y > 0).then(|| index - width);
+    let down = (y + 1 < height).then(|| index + width);
+    [left, right, up, down].into_iter().flatten()
+}
+
+/// Marker-based watershed segmentation. `markers` contains a non-zero label for each seed pixel,
+/// labels are flooded outwards in order of increasing gradient magnitude of `image`. Pixels that
+/// can't be reached from any marker are left as `0`
+pub fn watershed<T: Type, C: Color>(
+    image: &Image<T, C>,
+    markers: &Image<u32, Gray>,
+) -> Result<Image<u32, Gray>, Error> {
+    if markers.size() != image.size() {
+        return Err(Error::InvalidDimensions(
+            markers.width(),
+            markers.height(),
+            markers.channels(),
+        ));
+    }
+
+    let (width, height) = (image.width(), image.height());
+    let gradient = gradient(image);
+    let mut labels = markers.data().to_vec();
+    let mut queued: Vec<bool> = labels.iter().map(|x| *x != 0).collect();
+    let mut heap = BinaryHeap::new();
+    let mut seq = 0;
+
+    for (index, label) in labels.iter().enumerate() {
+        if *label == 0 {
+            continue;
+        }
+
+        for n in neighbors4(index, width, height) {
+            if !queued[n] {
+                queued[n] = true;
+                heap.push(Entry {
+                    priority: gradient[n],
+                    seq,
+                    index: n,
+                });
+                seq += 1;
+            }
+        }
+    }
+
+    while let Some(Entry { index, .. }) = heap.pop() {
+        // Take the label of the labeled neighbor with the lowest gradient
+        let label = neighbors4(index, width, height)
+            .filter(|n| labels[*n] != 0)
+            .min_by(|a, b| gradient[*a].total_cmp(&gradient[*b]))
+            .map(|n| labels[n]);
+        let label = match label {
+            Some(label) => label,
+            None => continue,
+        };
+        labels[index] = label;
+
+        for n in neighbors4(index, width, height) {
+            if !queued[n] {
+                queued[n] = true;
+                heap.push(Entry {
+                    priority: gradient[n].max(gradient[index]),
+                    seq,
+                    index: n,
+                });
+                seq += 1;
+            }
+        }
+    }
+
+    Ok(labels_image(image.size(), labels))
+}
+
+/// SLIC superpixel settings
+#[derive(Debug, Clone, Copy)]
+pub struct Slic {
+    /// Approximate number of superpixels
+    pub segments: usize,
+
+    /// Balance between color similarity and spatial proximity, higher values produce more
+    /// compact superpixels
+    pub compactness: f64,
+
+    /// Number of clustering iterations
+    pub iterations: usize,
+}
+
+impl Default for Slic {
+    fn default() -> Self {
+        Slic {
+            segments: 100,
+            compactness: 10.0,
+            iterations: 10,
+        }
+    }
+}
+
+/// Superpixel cluster center
+#[derive(Debug, Clone, Copy)]
+struct Center {
+    lab: [f64; 3],
+    x: f64,
+    y: f64,
+}
+
+/// SLIC superpixel segmentation, returns a label image with labels starting at `0`. Clustering is
+/// done in OkLab scaled to the range of CIELAB so `compactness` behaves like in the original paper
+pub fn slic<T: Type, C: Color>(image: &Image<T, C>, params: Slic) -> Image<u32, Gray> {
+    let (width, height) = (image.width(), image.height());
+    let n = width * height;
+    let step = ((n as f64 / params.segments.max(1) as f64).sqrt().round() as usize).max(1);
+    if n == 0 {
+        return labels_image(image.size(), Vec::new());
+    }
+
+    let mut lab = Vec::with_capacity(n);
+    for y in 0..height {
+        for x in 0..width {
+            let px: Pixel<OkLab> = image.get_pixel((x, y)).convert();
+            lab.push([px[0] * 100.0, px[1] * 100.0, px[2] * 100.0]);
+        }
+    }
+
+    // Seed centers on a regular grid, moved to the lowest gradient position in a 3x3
+    // neighborhood so they don't start on an edge. The first row and column are clamped to the
+    // image so images thinner than the grid step still get a center
+    let gradient = gradient(image);
+    let mut centers = Vec::new();
+    let mut y = (step / 2).min(height - 1);
+    while y < height {
+        let mut x = (step / 2).min(width - 1);
+        while x < width {
+            let mut best = (x, y);
+            for j in y.saturating_sub(1)..(y + 2).min(height) {
+                for i in x.saturating_sub(1)..(x + 2).min(width) {
+                    if gradient[j * width + i] < gradient[best.1 * width + best.0] {
+                        best = (i, j);
+                    }
+                }
+            }
+            centers.push(Center {
+                lab: lab[best.1 * width + best.0],
+                x: best.0 as f64,
+                y: best.1 as f64,
+            });
+            x += step;
+        }
+        y += step;
+    }
+
+    let m2 = params.compactness * params.compactness;
+    let s2 = (step * step) as f64;
+    let mut labels = vec![0usize; n];
+    let mut distances = vec![f64::INFINITY; n];
+
+    for _ in 0..params.iterations.max(1) {
+        distances.iter_mut().for_each(|d| *d = f64::INFINITY);
+        for (k, center) in centers.iter().enumerate() {
+            let (cx, cy) = (center.x.round() as usize, center.y.round() as usize);
+            for y in cy.saturating_sub(step)..(cy + step + 1).min(height) {
+                for x in cx.saturating_sub(step)..(cx + step + 1).min(width) {
+                    let index = y * width + x;
+                    let c = lab[index];
+                    let dc = (c[0] - center.lab[0]).powi(2)
+                        + (c[1] - center.lab[1]).powi(2)
+                        + (c[2] - center.lab[2]).powi(2);
+                    let ds = (x as f64 - center.x).powi(2) + (y as f64 - center.y).powi(2);
+                    let d = dc + ds / s2 * m2;
+                    if d < distances[index] {
+                        distances[index] = d;
+                        labels[index] = k;
+                    }
+                }
+            }
+        }
+
+        let mut sums = vec![(0.0, [0.0; 3], 0.0, 0.0); centers.len()];
+        for (index, label) in labels.iter().enumerate() {
+            let s = &mut sums[*label];
+            s.0 += 1.0;
+            s.1.iter_mut().zip(lab[index]).for_each(|(a, b)| *a += b);
+            s.2 += (index % width) as f64;
+            s.3 += (index / width) as f64;
+        }
+        for (center, (count, l, x, y)) in centers.iter_mut().zip(sums) {
+            if count > 0.0 {
+                center.lab = [l[0] / count, l[1] / count, l[2] / count];
+                center.x = x / count;
+                center.y = y / count;
+            }
+        }
+    }
+
+    labels_image(
+        image.size(),
+        enforce_connectivity(&labels, width, height, step),
+    )
+}
+
+/// Relabel connected components of `labels` with consecutive labels, components smaller than a
+/// quarter of the expected superpixel size are merged into an adjacent component
+fn enforce_connectivity(labels: &[usize], width: usize, height: usize, step: usize) -> Vec<u32> {
+    let min_size = (step * step / 4).max(1);
+    let mut dest = vec![u32::MAX; labels.len()];
+    let mut next = 0u32;
+    let mut stack = Vec::new();
+    let mut component = Vec::new();
+
+    for start in 0..labels.len() {
+        if dest[start] != u32::MAX {
+            continue;
+        }
+
+        // Label of an already visited neighbor, used when the component is too small
+        let adjacent = neighbors4(start, width, height)
+            .find(|n| dest[*n] != u32::MAX)
+            .map(|n| dest[n]);
+
+        component.clear();
+        stack.push(start);
+        dest[start] = next;
+        while let Some(index) = stack.pop() {
+            component.push(index);
+            for n in neighbors4(index, width, height) {
+                if dest[n] == u32::MAX && labels[n] == labels[start] {
+                    dest[n] = next;
+                    stack.push(n);
+                }
+            }
+        }
+
+        match adjacent {
+            Some(label) if component.len() < min_size => {
+                component.iter().for_each(|i| dest[*i] = label);
+            }
+            _ => next += 1,
+        }
+    }
+
+    dest
+}
+
+/// Initial selection used by `grabcut`
+#[derive(Clone, Copy)]
+pub enum Selection<'a> {
+    /// Everything outside of the region is background, everything inside is probably foreground
+    Region(Region),
+
+    /// Mask where values below `0.1` are background, values above `0.9` are foreground and
+    /// anything in between is probably foreground when at least `0.5`, otherwise probably
+    /// background
+    Mask(&'a Image<u8, Gray>),
+}
+
+impl<'a> From<Region> for Selection<'a> {
+    fn from(r: Region) -> Self {
+        Selection::Region(r)
+    }
+}
+
+impl<'a> From<&'a Image<u8, Gray>> for Selection<'a> {
+    fn from(mask: &'a Image<u8, Gray>) -> Self {
+        Selection::Mask(mask)
+    }
+}
+
+/// GrabCut pixel classification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Trimap {
+    Background,
+    Foreground,
+    ProbableBackground,
+    ProbableForeground,
+}
+
+impl Trimap {
+    fn is_foreground(self) -> bool {
+        matches!(self, Trimap::Foreground | Trimap::ProbableForeground)
+    }
+}
+
+/// Single gaussian component of a `Gmm`
+#[derive(Debug, Clone, Copy, Default)]
+struct Gaussian {
+    weight: f64,
+    mean: [f64; 3],
+    inverse: [[f64; 3]; 3],
+    norm: f64,
+}
+
+impl Gaussian {
+    fn density(&self, x: &[f64; 3]) -> f64 {
+        let d = [
+            x[0] - self.mean[0],
+            x[1] - self.mean[1],
+            x[2] - self.mean[2],
+        ];
+        let mut m = 0.0;
+        for (i, row) in self.inverse.iter().enumerate() {
+            m += d[i] * (row[0] * d[0] + row[1] * d[1] + row[2] * d[2]);
+        }
+        self.norm * (-0.5 * m).exp()
+    }
+}
+
+/// Gaussian mixture color model
+#[derive(Debug, Clone)]
+struct Gmm {
+    components: Vec<Gaussian>,
+}
+
+impl Gmm {
+    const COMPONENTS: usize = 5;
+
+    /// Fit a new model, `assignment` gives the component index of each sample
+    fn fit(samples: &[[f64; 3]], assignment: &[usize]) -> Gmm {
+        let mut components = Vec::with_capacity(Self::COMPONENTS);
+        for k in 0..Self::COMPONENTS {
+            let members: Vec<&[f64; 3]> = samples
+                .iter()
+                .zip(assignment)
+                .filter(|(_, a)| **a == k)
+                .map(|(s, _)| s)
+                .collect();
+            if members.is_empty() {
+                components.push(Gaussian::default());
+                continue;
+            }
+
+            let n = members.len() as f64;
+            let mut mean = [0.0; 3];
+            for s in &members {
+                mean.iter_mut().zip(s.iter()).for_each(|(m, x)| *m += x / n);
+            }
+
+            // Regularize the covariance so single-color components stay invertible
+            let mut cov = [[0.0; 3]; 3];
+            for s in &members {
+                for i in 0..3 {
+                    for j in 0..3 {
+                        cov[i][j] += (s[i] - mean[i]) * (s[j] - mean[j]) / n;
+                    }
+                }
+            }
+            for (i, row) in cov.iter_mut().enumerate() {
+                row[i] += 0.01;
+            }
+
+            let det = cov[0][0] * (cov[1][1] * cov[2][2] - cov[1][2] * cov[2][1])
+                - cov[0][1] * (cov[1][0] * cov[2][2] - cov[1][2] * cov[2][0])
+                + cov[0][2] * (cov[1][0] * cov[2][1] - cov[1][1] * cov[2][0]);
+            let mut inverse = [[0.0; 3]; 3];
+            for (i, row) in inverse.iter_mut().enumerate() {
+                for (j, x) in row.iter_mut().enumerate() {
+                    let (a, b) = ((j + 1) % 3, (j + 2) % 3);
+                    let (c, d) = ((i + 1) % 3, (i + 2) % 3);
+                    *x = (cov[a][c] * cov[b][d] - cov[a][d] * cov[b][c]) / det;
+                }
+            }
+
+            components.push(Gaussian {
+                weight: n / samples.len() as f64,
+                mean,
+                inverse,
+                norm: 1.0 / ((2.0 * std::f64::consts::PI).powi(3) * det).sqrt(),
+            });
+        }
+        Gmm { components }
+    }
+
+    /// Split samples into components by repeatedly dividing the cluster with the largest spread
+    /// along its widest channel
+    fn initial_assignment(samples: &[[f64; 3]]) -> Vec<usize> {
+        let mut assignment = vec![0; samples.len()];
+        for k in 1..Self::COMPONENTS {
+            let mut best = None;
+            for c in 0..k {
+                for channel in 0..3 {
+                    let values = samples
+                        .iter()
+                        .zip(&assignment)
+                        .filter(|(_, a)| **a == c)
+                        .map(|(s, _)| s[channel]);
+                    let (min, max) = values
+                        .fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), x| {
+                            (a.min(x), b.max(x))
+                        });
+                    let spread = max - min;
+                    if spread.is_finite() && best.map(|(_, _, s, _)| spread > s).unwrap_or(true) {
+                        best = Some((c, channel, spread, (min + max) / 2.0));
+                    }
+                }
+            }
+
+            if let Some((c, channel, _, split)) = best {
+                for (s, a) in samples.iter().zip(assignment.iter_mut()) {
+                    if *a == c && s[channel] > split {
+                        *a = k;
+                    }
+                }
+            }
+        }
+        assignment
+    }
+
+    /// Index of the most likely component for `x`
+    fn component(&self, x: &[f64; 3]) -> usize {
+        let mut best = (0, f64::NEG_INFINITY);
+        for (k, c) in self.components.iter().enumerate() {
+            let p = c.weight * c.density(x);
+            if p > best.1 {
+                best = (k, p);
+            }
+        }
+        best.0
+    }
+
+    fn neg_log_likelihood(&self, x: &[f64; 3]) -> f64 {
+        let p: f64 = self
+            .components
+            .iter()
+            .map(|c| c.weight * c.density(x))
+            .sum();
+        -(p.max(1e-300)).ln()
+    }
+}
+
+/// Flow network used to compute the minimum cut, solved using Dinic's algorithm
+struct Graph {
+    head: Vec<usize>,
+    next: Vec<usize>,
+    to: Vec<usize>,
+    cap: Vec<f64>,
+}
+
+impl Graph {
+    const NONE: usize = usize::MAX;
+    const EPSILON: f64 = 1e-9;
+
+    fn new(nodes: usize) -> Graph {
+        Graph {
+            head: vec![Self::NONE; nodes],
+            next: Vec::new(),
+            to: Vec::new(),
+            cap: Vec::new(),
+        }
+    }
+
+    /// Add an edge from `a` to `b` and its reverse, edge `e` is always paired with `e ^ 1`
+    fn add_edge(&mut self, a: usize, b: usize, cap: f64, reverse: f64) {
+        for (from, to, cap) in [(a, b, cap), (b, a, reverse)] {
+            self.next.push(self.head[from]);
+            self.head[from] = self.to.len();
+            self.to.push(to);
+            self.cap.push(cap);
+        }
+    }
+
+    fn levels(&self, s: usize) -> Vec<usize> {
+        let mut level = vec![Self::NONE; self.head.len()];
+        let mut queue = std::collections::VecDeque::new();
+        level[s] = 0;
+        queue.push_back(s);
+        while let Some(u) = queue.pop_front() {
+            let mut e = self.head[u];
+            while e != Self::NONE {
+                let v = self.to[e];
+                if self.cap[e] > Self::EPSILON && level[v] == Self::NONE {
+                    level[v] = level[u] + 1;
+                    queue.push_back(v);
+                }
+                e = self.next[e];
+            }
+        }
+        level
+    }
+
+    /// Run max-flow from `s` to `t`, returns the nodes on the source side of the minimum cut
+    fn min_cut(&mut self, s: usize, t: usize) -> Vec<bool> {
+        loop {
+            let mut level = self.levels(s);
+            if level[t] == Self::NONE {
+                return level.iter().map(|l| *l != Self::NONE).collect();
+            }
+
+            // Blocking flow using an iterative depth-first search
+            let mut iter = self.head.clone();
+            let mut path: Vec<usize> = Vec::new();
+            let mut u = s;
+            loop {
+                if u == t {
+                    let flow = path
+                        .iter()
+                        .map(|e| self.cap[*e])
+                        .fold(f64::INFINITY, f64::min);
+                    for e in &path {
+                        self.cap[*e] -= flow;
+                        self.cap[*e ^ 1] += flow;
+                    }
+                    path.clear();
+                    u = s;
+                    continue;
+                }
+
+                let mut advanced = false;
+                while iter[u] != Self::NONE {
+                    let e = iter[u];
+                    let v = self.to[e];
+                    if self.cap[e] > Self::EPSILON
+                        && level[v] != Self::NONE
+                        && level[v] == level[u] + 1
+                    {
+                        path.push(e);
+                        u = v;
+                        advanced = true;
+                        break;
+                    }
+                    iter[u] = self.next[e];
+                }
+
+                if !advanced {
+                    if u == s {
+                        break;
+                    }
+
+                    // Dead end, remove `u` from the level graph and step back
+                    level[u] = Self::NONE;
+                    let e = path.pop().unwrap();
+                    u = self.to[e ^ 1];
+                }
+            }
+        }
+    }
+}
+
+/// GrabCut foreground extraction. Color models for foreground and background are learned from
+/// `selection` and refined for `iterations` rounds of graph cut segmentation. Returns a mask that
+/// is 255 for foreground and 0 for background pixels
+pub fn grabcut<'a, T: Type, C: Color>(
+    image: &Image<T, C>,
+    selection: impl Into<Selection<'a>>,
+    iterations: usize,
+) -> Result<Image<u8, Gray>, Error> {
+    let (width, height) = (image.width(), image.height());
+    let n = width * height;
+
+    let mut trimap = Vec::with_capacity(n);
+    match selection.into() {
+        Selection::Region(r) => {
+            for y in 0..height {
+                for x in 0..width {
+                    trimap.push(if r.contains(Point::new(x, y)) {
+                        Trimap::ProbableForeground
+                    } else {
+                        Trimap::Background
+                    });
+                }
+            }
+        }
+        Selection::Mask(mask) => {
+            if mask.size() != image.size() {
+                return Err(Error::InvalidDimensions(
+                    mask.width(),
+                    mask.height(),
+                    mask.channels(),
+                ));
+            }
+
+            for y in 0..height {
+                for x in 0..width {
+                    let v = mask.get_f((x, y), 0);
+                    trimap.push(if v <= 0.1 {
+                        Trimap::Background
+                    } else if v >= 0.9 {
+                        Trimap::Foreground
+                    } else if v >= 0.5 {
+                        Trimap::ProbableForeground
+                    } else {
+                        Trimap::ProbableBackground
+                    });
+                }
+            }
+        }
+    }
+
+    let mut colors = Vec::with_capacity(n);
+    for y in 0..height {
+        for x in 0..width {
+            let px: Pixel<Rgb> = image.get_pixel((x, y)).convert();
+            colors.push([px[0] * 255.0, px[1] * 255.0, px[2] * 255.0]);
+        }
+    }
+
+    // Pairwise smoothness weights between 8-connected neighbors
+    let offsets = [(1isize, 0isize), (0, 1), (1, 1), (-1, 1)];
+    let diff = |a: &[f64; 3], b: &[f64; 3]| {
+        (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+    };
+    let mut edges = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            for (dx, dy) in offsets {
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                if nx < 0 || nx as usize >= width || ny as usize >= height {
+                    continue;
+                }
+                let (a, b) = (y * width + x, ny as usize * width + nx as usize);
+                edges.push((a, b, diff(&colors[a], &colors[b]), dx != 0 && dy != 0));
+            }
+        }
+    }
+    let mean = edges.iter().map(|e| e.2).sum::<f64>() / edges.len().max(1) as f64;
+    let beta = if mean > 0.0 { 0.5 / mean } else { 0.0 };
+    let gamma = 50.0;
+    let smoothness: Vec<(usize, usize, f64)> = edges
+        .iter()
+        .map(|(a, b, d, diagonal)| {
+            let w = gamma * (-beta * d).exp();
+            (*a, *b, if *diagonal { w / 2f64.sqrt() } else { w })
+        })
+        .collect();
+    let hard = 1.0 + 8.0 * gamma;
+
+    let split = |fg: bool, trimap: &[Trimap]| -> Vec<[f64; 3]> {
+        colors
+            .iter()
+            .zip(trimap)
+            .filter(|(_, t)| t.is_foreground() == fg)
+            .map(|(c, _)| *c)
+            .collect()
+    };
+
+    let fg_samples = split(true, &trimap);
+    let bg_samples = split(false, &trimap);
+    if fg_samples.is_empty() || bg_samples.is_empty() {
+        let data = trimap
+            .iter()
+            .map(|t| if t.is_foreground() { 255 } else { 0 })
+            .collect::<Vec<u8>>();
+        return Image::new_with_data(image.size(), data);
+    }
+
+    let mut fg_gmm = Gmm::fit(&fg_samples, &Gmm::initial_assignment(&fg_samples));
+    let mut bg_gmm = Gmm::fit(&bg_samples, &Gmm::initial_assignment(&bg_samples));
+
+    for _ in 0..iterations.max(1) {
+        // Re-learn the color models from the current labeling
+        let fg_samples = split(true, &trimap);
+        let bg_samples = split(false, &trimap);
+        if !fg_samples.is_empty() {
+            let assignment: Vec<usize> = fg_samples.iter().map(|c| fg_gmm.component(c)).collect();
+            fg_gmm = Gmm::fit(&fg_samples, &assignment);
+        }
+        if !bg_samples.is_empty() {
+            let assignment: Vec<usize> = bg_samples.iter().map(|c| bg_gmm.component(c)).collect();
+            bg_gmm = Gmm::fit(&bg_samples, &assignment);
+        }
+
+        // Source is foreground, sink is background
+        let (s, t) = (n, n + 1);
+        let mut graph = Graph::new(n + 2);
+        for (i, (c, label)) in colors.iter().zip(&trimap).enumerate() {
+            let (to_fg, to_bg) = match label {
+                Trimap::Foreground => (hard, 0.0),
+                Trimap::Background => (0.0, hard),
+                _ => (bg_gmm.neg_log_likelihood(c), fg_gmm.neg_log_likelihood(c)),
+            };
+            graph.add_edge(s, i, to_fg, 0.0);
+            graph.add_edge(i, t, to_bg, 0.0);
+        }
+        for (a, b, w) in &smoothness {
+            graph.add_edge(*a, *b, *w, *w);
+        }
+
+        let source = graph.min_cut(s, t);
+        for (i, label) in trimap.iter_mut().enumerate() {
+            match label {
+                Trimap::ProbableForeground | Trimap::ProbableBackground => {
+                    *label = if source[i] {
+                        Trimap::ProbableForeground
+                    } else {
+                        Trimap::ProbableBackground
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    let data = trimap
+        .iter()
+        .map(|t| if t.is_foreground() { 255 } else { 0 })
+        .collect::<Vec<u8>>();
+    Image::new_with_data(image.size(), data)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_watershed() {
+        // Two flat regions separated by a vertical edge
+        let mut image = Image::<f32, Gray>::new((16, 8));
+        image.for_each(|pt, mut px| px[0] = if pt.x < 8 { 0.2 } else { 0.8 });
+        let mut markers = Image::<u32, Gray>::new((16, 8));
+        markers.data_mut()[4 * 16 + 1] = 1;
+        markers.data_mut()[4 * 16 + 14] = 2;
+
+        let labels = segmentation::watershed(&image, &markers).unwrap();
+        for y in 0..8 {
+            for x in 0..16 {
+                let expected = if x < 8 { 1 } else { 2 };
+                assert_eq!(labels.data()[y * 16 + x], expected);
+            }
+        }
+
+        let small = Image::<u32, Gray>::new((4, 4));
+        assert!(segmentation::watershed(&image, &small).is_err());
+    }
+
+    #[test]
+    fn test_slic() {
+        // Four colored quadrants should never share a superpixel
+        let mut image = Image::<f32, Rgb>::new((32, 32));
+        image.for_each(|pt, mut px| {
+            let q = (pt.x / 16) + 2 * (pt.y / 16);
+            px[q % 3] = 1.0;
+            if q == 3 {
+                px[1] = 1.0;
+            }
+        });
+
+        let params = segmentation::Slic {
+            segments: 16,
+            ..Default::default()
+        };
+        let labels = segmentation::slic(&image, params);
+        let data = labels.data();
+        let quadrant = |i: usize| (i % 32 / 16) + 2 * (i / 32 / 16);
+        for i in 0..data.len() {
+            for j in 0..data.len() {
+                if data[i] == data[j] {
+                    assert_eq!(quadrant(i), quadrant(j));
+                }
+            }
+        }
+
+        let count = data.iter().max().unwrap() + 1;
+        assert!((8..=32).contains(&count));
+
+        // Thin images and more segments than pixels still produce a labeling
+        let thin = Image::<f32, Rgb>::new((2, 64));
+        let params = segmentation::Slic {
+            segments: 1,
+            ..Default::default()
+        };
+        assert_eq!(segmentation::slic(&thin, params).size(), thin.size());
+        let tiny = Image::<f32, Rgb>::new((3, 3));
+        let params = segmentation::Slic {
+            segments: 100,
+            ..Default::default()
+        };
+        assert_eq!(segmentation::slic(&tiny, params).size(), tiny.size());
+        let empty = Image::<f32, Rgb>::new((0, 0));
+        assert!(segmentation::slic(&empty, params).data().is_empty());
+    }
+
+    #[test]
+    fn test_grabcut() {
+        // Noisy red disk on a noisy green background
+        let mut image = Image::<f32, Rgb>::new((32, 32));
+        image.for_each(|pt, mut px| {
+            let noise = ((pt.x * 7 + pt.y * 13) % 5) as f32 * 0.03;
+            let (dx, dy) = (pt.x as f32 - 16.0, pt.y as f32 - 16.0);
+            if dx * dx + dy * dy < 64.0 {
+                px[0] = 0.8 + noise;
+                px[1] = 0.1;
+            } else {
+                px[0] = 0.1;
+                px[1] = 0.6 + noise;
+            }
+            px[2] = 0.2;
+        });
+
+        let mask =
+            segmentation::grabcut(&image, Region::new(Point::new(4, 4), Size::new(24, 24)), 3)
+                .unwrap();
+        mask.each_pixel(|pt, px| {
+            let (dx, dy) = (pt.x as f64 - 16.0, pt.y as f64 - 16.0);
+            let inside = dx * dx + dy * dy < 64.0;
+            assert_eq!(px[0] > 0.5, inside, "{:?}", pt);
+        });
+
+        let mut selection = Image::<u8, Gray>::new((32, 32));
+        selection.for_each(|pt, mut px| {
+            px[0] = if pt.x > 2 && pt.x < 30 { 192 } else { 0 };
+        });
+        let mask = segmentation::grabcut(&image, &selection, 2).unwrap();
+        assert_eq!(mask.get_f((16, 16), 0), 1.0);
+        assert_eq!(mask.get_f((0, 16), 0), 0.0);
+        assert_eq!(mask.get_f((28, 28), 0), 0.0);
+    }
+}
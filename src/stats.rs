@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+
+use crate::*;
+
+/// Maps a value to a histogram key that preserves ordering, the key is made of the top 16 bits
+/// of the value's `f32` representation
+fn key(x: f64) -> u16 {
+    let bits = (x as f32).to_bits();
+    let bits = if bits & 0x8000_0000 != 0 {
+        !bits
+    } else {
+        bits | 0x8000_0000
+    };
+    (bits >> 16) as u16
+}
+
+/// Get the value in the middle of the range covered by a histogram key
+fn value(key: u16) -> f64 {
+    let bits = ((key as u32) << 16) | 0x8000;
+    let bits = if bits & 0x8000_0000 != 0 {
+        bits & 0x7fff_ffff
+    } else {
+        !bits
+    };
+    f32::from_bits(bits) as f64
+}
+
+/// Statistics for a single channel, all values are normalized
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelStats {
+    /// Minimum finite value
+    pub min: f64,
+
+    /// Maximum finite value
+    pub max: f64,
+
+    /// Mean of finite values
+    pub mean: f64,
+
+    /// Standard deviation of finite values
+    pub stddev: f64,
+
+    /// Number of NaN values
+    pub nan: usize,
+
+    /// Number of infinite values
+    pub inf: usize,
+
+    /// Number of finite values
+    pub count: usize,
+
+    m2: f64,
+    histogram: BTreeMap<u16, usize>,
+}
+
+impl Default for ChannelStats {
+    fn default() -> Self {
+        ChannelStats {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            mean: 0.0,
+            stddev: 0.0,
+            nan: 0,
+            inf: 0,
+            count: 0,
+            m2: 0.0,
+            histogram: BTreeMap::new(),
+        }
+    }
+}
+
+impl ChannelStats {
+    fn add(&mut self, x: f64) {
+        if x.is_nan() {
+            self.nan += 1;
+            return;
+        } else if x.is_infinite() {
+            self.inf += 1;
+            return;
+        }
+
+        self.count += 1;
+        self.min = self.min.min(x);
+        self.max = self.max.max(x);
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+        *self.histogram.entry(key(x)).or_default() += 1;
+    }
+
+    fn merge(mut self, other: ChannelStats) -> ChannelStats {
+        self.nan += other.nan;
+        self.inf += other.inf;
+
+        if other.count == 0 {
+            return self;
+        } else if self.count == 0 {
+            return ChannelStats {
+                nan: self.nan,
+                inf: self.inf,
+                ..other
+            };
+        }
+
+        let (na, nb) = (self.count as f64, other.count as f64);
+        let n = na + nb;
+        let delta = other.mean - self.mean;
+        self.mean += delta * nb / n;
+        self.m2 += other.m2 + delta * delta * na * nb / n;
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        for (k, v) in other.histogram {
+            *self.histogram.entry(k).or_default() += v;
+        }
+        self
+    }
+
+    /// Returns true if there are any NaN or infinite values
+    pub fn has_invalid(&self) -> bool {
+        self.nan > 0 || self.inf > 0
+    }
+
+    /// Get the value at the given percentile (0 to 100) of finite values. Percentiles are
+    /// approximate, the relative error is less than 0.4%
+    pub fn percentile(&self, p: f64) -> f64 {
+        if self.count == 0 {
+            return f64::NAN;
+        } else if p <= 0.0 {
+            return self.min;
+        } else if p >= 100.0 {
+            return self.max;
+        }
+
+        let rank = (p / 100.0 * (self.count - 1) as f64).round() as usize;
+        let mut total = 0;
+        for (k, n) in self.histogram.iter() {
+            total += n;
+            if total > rank {
+                return value(*k).clamp(self.min, self.max);
+            }
+        }
+
+        self.max
+    }
+
+    /// Get the median of finite values
+    pub fn median(&self) -> f64 {
+        self.percentile(50.0)
+    }
+}
+
+/// Per-channel image statistics, returned by `Image::stats`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    /// Statistics for each channel
+    pub channels: Vec<ChannelStats>,
+}
+
+impl std::ops::Index<Channel> for Stats {
+    type Output = ChannelStats;
+
+    fn index(&self, c: Channel) -> &ChannelStats {
+        &self.channels[c]
+    }
+}
+
+impl Stats {
+    /// Total number of NaN values across all channels
+    pub fn nan(&self) -> usize {
+        self.channels.iter().map(|c| c.nan).sum()
+    }
+
+    /// Total number of infinite values across all channels
+    pub fn inf(&self) -> usize {
+        self.channels.iter().map(|c| c.inf).sum()
+    }
+
+    /// Returns true if any channel contains NaN or infinite values
+    pub fn has_invalid(&self) -> bool {
+        self.channels.iter().any(ChannelStats::has_invalid)
+    }
+}
+
+struct StatsReduce;
+
+impl<T: Type, C: Color> Reduce<T, C> for StatsReduce {
+    type State = Vec<ChannelStats>;
+    type Output = Stats;
+
+    fn init(&self) -> Self::State {
+        vec![ChannelStats::default(); C::CHANNELS]
+    }
+
+    fn fold(&self, state: &mut Self::State, _pt: Point, px: &Pixel<C>) {
+        for (c, s) in state.iter_mut().enumerate() {
+            s.add(px[c]);
+        }
+    }
+
+    fn combine(&self, a: Self::State, b: Self::State) -> Self::State {
+        a.into_iter().zip(b).map(|(a, b)| a.merge(b)).collect()
+    }
+
+    fn finish(&self, mut channels: Self::State) -> Self::Output {
+        for c in channels.iter_mut() {
+            if c.count > 0 {
+                c.stddev = (c.m2 / c.count as f64).sqrt();
+            }
+        }
+        Stats { channels }
+    }
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Compute per-channel statistics in a single parallel pass
+    pub fn stats(&self) -> Stats {
+        self.reduce(StatsReduce)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_stats() {
+        let mut image = Image::<f32, Gray>::new((10, 10));
+        image.for_each(|pt, mut px| px[0] = (pt.y * 10 + pt.x) as f32 / 100.0);
+        image.set_f((0, 0), 0, f64::NAN);
+        image.set_f((1, 0), 0, f64::INFINITY);
+
+        let stats = image.stats();
+        assert_eq!(stats[0].nan, 1);
+        assert_eq!(stats[0].inf, 1);
+        assert_eq!(stats[0].count, 98);
+        assert!(stats.has_invalid());
+        assert_eq!(stats[0].min, 0.02f32 as f64);
+        assert_eq!(stats[0].max, 0.99f32 as f64);
+        assert!((stats[0].median() - 0.5).abs() < 0.02);
+        assert!((stats[0].percentile(90.0) - 0.9).abs() < 0.02);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::*;
+
+/// Matching cost used to compare blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Cost {
+    /// Sum of absolute differences of intensity
+    Sad,
+
+    /// Hamming distance between census transforms, robust to exposure differences between the
+    /// two cameras
+    Census,
+}
+
+/// Block matching stereo correspondence, the first input is the left image and the second input
+/// is the right image of a rectified pair. Output is the disparity of each left pixel divided by
+/// `max_disparity`, pixels that fail the left-right consistency check are set to `0`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockMatch {
+    /// Largest disparity searched, in pixels
+    pub max_disparity: usize,
+
+    /// Radius of the matching window, the window is `2 * radius + 1` pixels wide
+    pub radius: usize,
+
+    /// Matching cost
+    pub cost: Cost,
+
+    /// Maximum difference between left-to-right and right-to-left disparities, `None` disables
+    /// the consistency check
+    pub max_lr_difference: Option<usize>,
+}
+
+impl Default for BlockMatch {
+    fn default() -> Self {
+        BlockMatch {
+            max_disparity: 64,
+            radius: 3,
+            cost: Cost::Sad,
+            max_lr_difference: Some(1),
+        }
+    }
+}
+
+impl BlockMatch {
+    /// Create a new block matcher with the given disparity range
+    pub fn new(max_disparity: usize) -> BlockMatch {
+        BlockMatch {
+            max_disparity,
+            ..Default::default()
+        }
+    }
+
+    /// Set window radius
+    pub fn with_radius(mut self, radius: usize) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Set matching cost
+    pub fn with_cost(mut self, cost: Cost) -> Self {
+        self.cost = cost;
+        self
+    }
+
+    /// Set left-right consistency tolerance
+    pub fn with_lr_check(mut self, max_difference: Option<usize>) -> Self {
+        self.max_lr_difference = max_difference;
+        self
+    }
+
+    /// Mean of the color channels at `(x, y)`, coordinates are clamped to the image
+    fn intensity<T: Type, C: Color>(input: &Input<T, C>, x: isize, y: isize, image: usize) -> f64 {
+        let size = input.images()[image].size();
+        let x = x.clamp(0, size.width as isize - 1) as usize;
+        let y = y.clamp(0, size.height as isize - 1) as usize;
+        let channels = C::ALPHA.unwrap_or(C::CHANNELS);
+        (0..channels)
+            .map(|c| input.get_f((x, y), c, Some(image)))
+            .sum::<f64>()
+            / channels as f64
+    }
+
+    /// Cost of matching the block at `(x, y)` in image `a` with the block at `(x + offset, y)` in
+    /// image `b`
+    fn block_cost<T: Type, C: Color>(
+        &self,
+        input: &Input<T, C>,
+        (x, y): (isize, isize),
+        offset: isize,
+        (a, b): (usize, usize),
+    ) -> f64 {
+        let r = self.radius as isize;
+        let (ca, cb) = match self.cost {
+            Cost::Sad => (0.0, 0.0),
+            Cost::Census => (
+                Self::intensity(input, x, y, a),
+                Self::intensity(input, x + offset, y, b),
+            ),
+        };
+
+        let mut sum = 0.0;
+        for j in -r..=r {
+            for i in -r..=r {
+                let va = Self::intensity(input, x + i, y + j, a);
+                let vb = Self::intensity(input, x + offset + i, y + j, b);
+                sum += match self.cost {
+                    Cost::Sad => (va - vb).abs(),
+                    Cost::Census => ((va < ca) != (vb < cb)) as u8 as f64,
+                };
+            }
+        }
+        sum
+    }
+
+    /// Best disparity for `(x, y)` in image `a`, matches are searched in image `b` in the
+    /// direction given by `sign`
+    fn best<T: Type, C: Color>(
+        &self,
+        input: &Input<T, C>,
+        (x, y): (usize, usize),
+        (a, b): (usize, usize),
+        sign: isize,
+    ) -> usize {
+        let width = input.images()[b].width() as isize;
+        let mut best = (0, f64::INFINITY);
+        for d in 0..=self.max_disparity {
+            let offset = sign * d as isize;
+            let xb = x as isize + offset;
+            if xb < 0 || xb >= width {
+                break;
+            }
+
+            let cost = self.block_cost(input, (x as isize, y as isize), offset, (a, b));
+            if cost < best.1 {
+                best = (d, cost);
+            }
+        }
+        best.0
+    }
+
+    /// Disparity in pixels of the left pixel at `pt`, `None` if the left-right check fails
+    pub fn disparity_at<T: Type, C: Color>(&self, pt: Point, input: &Input<T, C>) -> Option<usize> {
+        let d = self.best(input, (pt.x, pt.y), (0, 1), -1);
+        match self.max_lr_difference {
+            Some(tolerance) => {
+                let back = self.best(input, (pt.x - d, pt.y), (1, 0), 1);
+                (back.abs_diff(d) <= tolerance).then_some(d)
+            }
+            None => Some(d),
+        }
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for BlockMatch {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let value = self
+            .disparity_at(pt, input)
+            .map(|d| d as f64 / self.max_disparity.max(1) as f64)
+            .unwrap_or(0.0);
+        let mut px = Pixel::<D>::new();
+        px.fill(value);
+        px.copy_to_slice(dest);
+    }
+}
+
+/// Compute the disparity map of a rectified stereo pair, values are in pixels and pixels that
+/// fail the left-right consistency check are `0`
+pub fn disparity<T: Type, C: Color>(
+    left: &Image<T, C>,
+    right: &Image<T, C>,
+    matcher: BlockMatch,
+) -> Result<Image<f32, Gray>, Error> {
+    if left.size() != right.size() {
+        return Err(Error::InvalidDimensions(
+            right.width(),
+            right.height(),
+            right.channels(),
+        ));
+    }
+
+    let max = matcher.max_disparity.max(1) as f32;
+    let mut dest = Image::<f32, Gray>::new(left.size());
+    dest.apply(matcher, &[left, right]);
+    dest.data_mut().iter_mut().for_each(|x| *x *= max);
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_stereo_disparity() {
+        // The right image is the left image shifted by 4 pixels
+        let pattern = |x: usize, y: usize| ((x * 7 + y * 3) % 11) as f32 / 10.0;
+        let mut left = Image::<f32, Gray>::new((48, 16));
+        let mut right = Image::<f32, Gray>::new((48, 16));
+        left.for_each(|pt, mut px| px[0] = pattern(pt.x, pt.y));
+        right.for_each(|pt, mut px| px[0] = pattern(pt.x + 4, pt.y));
+
+        for cost in [stereo::Cost::Sad, stereo::Cost::Census] {
+            let matcher = stereo::BlockMatch::new(8).with_radius(2).with_cost(cost);
+            let d = stereo::disparity(&left, &right, matcher).unwrap();
+            for y in 2..14 {
+                for x in 12..44 {
+                    assert_eq!(d.get_f((x, y), 0), 4.0);
+                }
+            }
+        }
+
+        let small = Image::<f32, Gray>::new((8, 8));
+        assert!(stereo::disparity(&left, &small, stereo::BlockMatch::default()).is_err());
+    }
+}
@@ -0,0 +1,330 @@
+use std::collections::VecDeque;
+
+use crate::*;
+
+/// Filters that process a sequence of frames, keeping only a bounded amount of state
+pub trait TemporalFilter<T: Type, C: Color> {
+    /// Output produced for each frame
+    type Output;
+
+    /// Add the next frame and compute the output for it
+    fn push(&mut self, frame: &Image<T, C>) -> Self::Output;
+
+    /// Apply the filter to each frame of an iterator
+    fn frames<I: IntoIterator<Item = Image<T, C>>>(self, frames: I) -> Frames<I::IntoIter, Self>
+    where
+        Self: Sized,
+    {
+        Frames {
+            frames: frames.into_iter(),
+            filter: self,
+        }
+    }
+}
+
+/// Iterator over the output of a `TemporalFilter`, see `TemporalFilter::frames`
+pub struct Frames<I, F> {
+    frames: I,
+    filter: F,
+}
+
+impl<T: Type, C: Color, I: Iterator<Item = Image<T, C>>, F: TemporalFilter<T, C>> Iterator
+    for Frames<I, F>
+{
+    type Item = F::Output;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.frames.next()?;
+        Some(self.filter.push(&frame))
+    }
+}
+
+/// Window over the most recent frames, the oldest frame is dropped once `capacity` frames are
+/// stored
+pub struct FrameWindow<T: Type, C: Color> {
+    frames: VecDeque<Image<T, C>>,
+    capacity: usize,
+}
+
+impl<T: Type, C: Color> FrameWindow<T, C> {
+    /// Create a new window holding up to `capacity` frames
+    pub fn new(capacity: usize) -> FrameWindow<T, C> {
+        FrameWindow {
+            frames: VecDeque::with_capacity(capacity.max(1)),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Add a frame, the buffer of the dropped frame is reused when the sizes match
+    pub fn push(&mut self, frame: &Image<T, C>) {
+        if self.frames.len() < self.capacity {
+            self.frames.push_back(frame.clone());
+            return;
+        }
+
+        let mut oldest = self.frames.pop_front().unwrap();
+        if oldest.size() == frame.size() {
+            oldest.data_mut().copy_from_slice(frame.data());
+            oldest.meta = frame.meta.clone();
+        } else {
+            oldest = frame.clone();
+        }
+        self.frames.push_back(oldest);
+    }
+
+    /// Maximum number of frames
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of stored frames
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns true when no frames are stored
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns true when `capacity` frames are stored
+    pub fn is_full(&self) -> bool {
+        self.frames.len() == self.capacity
+    }
+
+    /// Most recent frame
+    pub fn latest(&self) -> Option<&Image<T, C>> {
+        self.frames.back()
+    }
+
+    /// Iterate over frames from oldest to newest
+    pub fn iter(&self) -> impl Iterator<Item = &Image<T, C>> {
+        self.frames.iter()
+    }
+}
+
+/// Background subtraction using an exponential running average of previous frames, the output
+/// is a mask set to `1` where a frame differs from the background
+pub struct RunningAverage<C: Color> {
+    background: Option<Image<f32, C>>,
+
+    /// Weight of each new frame when updating the background
+    pub alpha: f64,
+
+    /// Minimum difference from the background, in normalized values, for a pixel to be
+    /// considered foreground
+    pub threshold: f64,
+}
+
+impl<C: Color> RunningAverage<C> {
+    /// Create a new `RunningAverage`, the first frame is used as the initial background
+    pub fn new(alpha: f64, threshold: f64) -> RunningAverage<C> {
+        RunningAverage {
+            background: None,
+            alpha,
+            threshold,
+        }
+    }
+
+    /// Current background estimate
+    pub fn background(&self) -> Option<&Image<f32, C>> {
+        self.background.as_ref()
+    }
+}
+
+impl<T: Type, C: Color> TemporalFilter<T, C> for RunningAverage<C> {
+    type Output = Image<f32, Gray>;
+
+    fn push(&mut self, frame: &Image<T, C>) -> Image<f32, Gray> {
+        let background = match &mut self.background {
+            Some(bg) if bg.size() == frame.size() => bg,
+            bg => bg.insert(frame.convert()),
+        };
+
+        let mut mask = Image::new(frame.size());
+        mask.each_pixel_mut(|pt, mut px| {
+            let (a, b) = (frame.get_pixel(pt), background.get_pixel(pt));
+            let diff = (0..C::CHANNELS)
+                .filter(|c| C::ALPHA != Some(*c))
+                .map(|c| (a[c] - b[c]).abs())
+                .fold(0.0, f64::max);
+            px[0] = if diff > self.threshold { 1.0 } else { 0.0 };
+        });
+
+        let alpha = self.alpha.clamp(0.0, 1.0);
+        background.each_pixel_mut(|pt, mut px| {
+            let src = frame.get_pixel(pt);
+            for c in 0..C::CHANNELS {
+                px[c] = px[c] * (1.0 - alpha) + src[c] * alpha;
+            }
+        });
+        mask
+    }
+}
+
+/// Median of each value over the most recent frames, this removes transient objects and
+/// impulse noise
+pub struct TemporalMedian<T: Type, C: Color> {
+    window: FrameWindow<T, C>,
+}
+
+impl<T: Type, C: Color> TemporalMedian<T, C> {
+    /// Create a new `TemporalMedian` over up to `frames` frames
+    pub fn new(frames: usize) -> TemporalMedian<T, C> {
+        TemporalMedian {
+            window: FrameWindow::new(frames),
+        }
+    }
+}
+
+impl<T: Type, C: Color> TemporalFilter<T, C> for TemporalMedian<T, C> {
+    type Output = Image<T, C>;
+
+    fn push(&mut self, frame: &Image<T, C>) -> Image<T, C> {
+        self.window.push(frame);
+
+        let frames: Vec<&Image<T, C>> = self
+            .window
+            .iter()
+            .filter(|f| f.size() == frame.size())
+            .collect();
+        let mut dest = frame.clone();
+        let mut values = Vec::with_capacity(frames.len());
+        for (i, x) in dest.data_mut().iter_mut().enumerate() {
+            values.clear();
+            values.extend(frames.iter().map(|f| f.data()[i].to_f64()));
+            values.sort_by(f64::total_cmp);
+            *x = T::from_f64(values[values.len() / 2]);
+        }
+        dest
+    }
+}
+
+/// Offset of each block, in row order
+type Motion = Vec<(isize, isize)>;
+
+/// Motion-compensated temporal denoising, previous frames are aligned to the newest frame using
+/// block matching and averaged. Pixels that don't match after alignment, such as occlusions, are
+/// given less weight to avoid ghosting
+pub struct TemporalDenoise<T: Type, C: Color> {
+    window: FrameWindow<T, C>,
+
+    /// Block size used for motion estimation
+    pub block: usize,
+
+    /// Maximum motion in pixels between two frames
+    pub search: usize,
+
+    /// Expected noise level in normalized values, larger values average more aggressively
+    pub strength: f64,
+}
+
+impl<T: Type, C: Color> TemporalDenoise<T, C> {
+    /// Create a new `TemporalDenoise` over up to `frames` frames using 8x8 blocks and a search
+    /// radius of 4 pixels
+    pub fn new(frames: usize, strength: f64) -> TemporalDenoise<T, C> {
+        TemporalDenoise {
+            window: FrameWindow::new(frames),
+            block: 8,
+            search: 4,
+            strength,
+        }
+    }
+
+    /// Build filter with the given block size and search radius
+    pub fn with_motion_search(mut self, block: usize, search: usize) -> TemporalDenoise<T, C> {
+        self.block = block.max(1);
+        self.search = search;
+        self
+    }
+
+    /// Offset of each block of `reference` in `frame`, minimizing the sum of absolute luminance
+    /// differences
+    fn motion(&self, reference: &[f64], frame: &[f64], size: Size) -> Motion {
+        let (w, h) = (size.width as isize, size.height as isize);
+        let block = self.block as isize;
+        let search = self.search as isize;
+        let (bw, bh) = (
+            size.width.div_ceil(self.block),
+            size.height.div_ceil(self.block),
+        );
+        let at = |img: &[f64], x: isize, y: isize| {
+            img[(y.clamp(0, h - 1) * w + x.clamp(0, w - 1)) as usize]
+        };
+
+        (0..bw * bh)
+            .map(|b| {
+                let (x0, y0) = ((b % bw) as isize * block, (b / bw) as isize * block);
+                let mut best = (f64::INFINITY, (0, 0));
+                for dy in -search..=search {
+                    for dx in -search..=search {
+                        let mut sad = 0.0;
+                        for y in y0..(y0 + block).min(h) {
+                            for x in x0..(x0 + block).min(w) {
+                                sad += (at(reference, x, y) - at(frame, x + dx, y + dy)).abs();
+                            }
+                        }
+
+                        // Prefer smaller motion when blocks match equally well
+                        let sad = sad + 1e-9 * (dx.abs() + dy.abs()) as f64;
+                        if sad < best.0 {
+                            best = (sad, (dx, dy));
+                        }
+                    }
+                }
+                best.1
+            })
+            .collect()
+    }
+}
+
+impl<T: Type, C: Color> TemporalFilter<T, C> for TemporalDenoise<T, C> {
+    type Output = Image<T, C>;
+
+    fn push(&mut self, frame: &Image<T, C>) -> Image<T, C> {
+        self.window.push(frame);
+
+        let size = frame.size();
+        let luminance = |image: &Image<T, C>| {
+            let mut values = Vec::with_capacity(size.width * size.height);
+            image.each_pixel(|_, px| values.push(px.convert::<Gray>()[0]));
+            values
+        };
+        let reference = luminance(frame);
+        let previous: Vec<(&Image<T, C>, Motion)> = self
+            .window
+            .iter()
+            .take(self.window.len() - 1)
+            .filter(|f| f.size() == size)
+            .map(|f| (f, self.motion(&reference, &luminance(f), size)))
+            .collect();
+
+        let bw = size.width.div_ceil(self.block);
+        let strength = self.strength.max(f64::EPSILON);
+        let mut dest = frame.clone();
+        dest.each_pixel_mut(|pt, mut px| {
+            let center = px.clone();
+            let mut sum = center.clone();
+            let mut total = 1.0;
+            for (f, motion) in &previous {
+                let (dx, dy) = motion[(pt.y / self.block) * bw + pt.x / self.block];
+                let x = (pt.x as isize + dx).clamp(0, size.width as isize - 1) as usize;
+                let y = (pt.y as isize + dy).clamp(0, size.height as isize - 1) as usize;
+                let other = f.get_pixel((x, y));
+                let d = (0..C::CHANNELS)
+                    .map(|c| (other[c] - center[c]).abs())
+                    .sum::<f64>()
+                    / C::CHANNELS as f64;
+                let w = (-(d * d) / (2.0 * strength * strength)).exp();
+                for c in 0..C::CHANNELS {
+                    sum[c] += other[c] * w;
+                }
+                total += w;
+            }
+            for c in 0..C::CHANNELS {
+                px[c] = sum[c] / total;
+            }
+        });
+        dest
+    }
+}
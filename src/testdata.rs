@@ -0,0 +1,286 @@
+use std::io::Read;
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// Environment variable used to set the directory where downloaded images are cached
+pub const CACHE_ENV: &str = "IMAGE2_TESTDATA_DIR";
+
+/// Environment variable that disables downloads when set, only cached images can be opened
+pub const OFFLINE_ENV: &str = "IMAGE2_TESTDATA_OFFLINE";
+
+/// Pinned SHA-256 checksums of downloadable images as `(name, hex digest)` pairs, see
+/// `TestImage::sha256`. Images without an entry are never downloaded
+pub const CHECKSUMS: &[(&str, &str)] = &[];
+
+/// Directory where downloaded images are cached, this is `IMAGE2_TESTDATA_DIR` when set,
+/// otherwise `image2/testdata` in the user's cache directory
+pub fn cache_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os(CACHE_ENV) {
+        return PathBuf::from(dir);
+    }
+
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir);
+    cache.join("image2").join("testdata")
+}
+
+/// HDR light probes from Paul Debevec's Light Probe Image Gallery
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Probe {
+    /// Grace Cathedral, San Francisco
+    Grace,
+
+    /// St. Peter's Basilica, Rome
+    StPeters,
+
+    /// Uffizi Gallery, Florence
+    Uffizi,
+
+    /// Galileo's Tomb, Florence
+    Galileo,
+
+    /// Eucalyptus Grove, UC Berkeley
+    Campus,
+
+    /// Building, UC Berkeley
+    Building,
+
+    /// Beach
+    Beach,
+
+    /// Campus at sunset
+    Rnl,
+}
+
+impl Probe {
+    /// Every available probe
+    pub const ALL: [Probe; 8] = [
+        Probe::Grace,
+        Probe::StPeters,
+        Probe::Uffizi,
+        Probe::Galileo,
+        Probe::Campus,
+        Probe::Building,
+        Probe::Beach,
+        Probe::Rnl,
+    ];
+
+    /// Probe name as used in the gallery
+    pub fn name(&self) -> &'static str {
+        match self {
+            Probe::Grace => "grace",
+            Probe::StPeters => "stpeters",
+            Probe::Uffizi => "uffizi",
+            Probe::Galileo => "galileo",
+            Probe::Campus => "campus",
+            Probe::Building => "building",
+            Probe::Beach => "beach",
+            Probe::Rnl => "rnl",
+        }
+    }
+}
+
+/// Standard reference images, downloaded images are cached in `cache_dir` so they're only
+/// fetched once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TestImage {
+    /// Image from the Kodak Lossless True Color Image Suite, numbered `1..=24`
+    Kodak(usize),
+
+    /// HDR light probe, stored in Radiance HDR format
+    Probe(Probe),
+
+    /// Circular zone plate with the given width and height, the frequency increases linearly from
+    /// the center to the Nyquist limit at the edges. This image is generated so no download is
+    /// needed
+    ZonePlate(usize),
+}
+
+impl TestImage {
+    /// The full Kodak suite
+    pub fn kodak_suite() -> Vec<TestImage> {
+        (1..=24).map(TestImage::Kodak).collect()
+    }
+
+    /// Every HDR light probe
+    pub fn probes() -> Vec<TestImage> {
+        Probe::ALL.into_iter().map(TestImage::Probe).collect()
+    }
+
+    /// Name used for the cached file and in benchmark reports
+    pub fn name(&self) -> String {
+        match self {
+            TestImage::Kodak(n) => format!("kodim{:02}", n),
+            TestImage::Probe(p) => format!("{}_probe", p.name()),
+            TestImage::ZonePlate(size) => format!("zone_plate_{}", size),
+        }
+    }
+
+    /// Download URL, `None` for generated images
+    pub fn url(&self) -> Option<String> {
+        match self {
+            TestImage::Kodak(n) => Some(format!(
+                "https://r0k.us/graphics/kodak/kodak/kodim{:02}.png",
+                n
+            )),
+            TestImage::Probe(p) => Some(format!(
+                "https://www.pauldebevec.com/Probes/{}_probe.hdr",
+                p.name()
+            )),
+            TestImage::ZonePlate(_) => None,
+        }
+    }
+
+    /// Path of the cached file, `None` for generated images
+    pub fn path(&self) -> Option<PathBuf> {
+        let ext = match self {
+            TestImage::Kodak(_) => "png",
+            TestImage::Probe(_) => "hdr",
+            TestImage::ZonePlate(_) => return None,
+        };
+        Some(cache_dir().join(format!("{}.{}", self.name(), ext)))
+    }
+
+    /// Returns true when the image is generated or already cached
+    pub fn is_available(&self) -> bool {
+        self.path().map(|p| p.exists()).unwrap_or(true)
+    }
+
+    /// Pinned SHA-256 checksum of the downloaded file as a lowercase hex string, `None` for
+    /// generated images and images without an entry in `CHECKSUMS`
+    pub fn sha256(&self) -> Option<&'static str> {
+        let name = self.name();
+        CHECKSUMS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, sum)| *sum)
+    }
+
+    /// Download the image into `cache_dir` over HTTPS if it isn't cached yet, the download is
+    /// rejected unless it matches the pinned checksum returned by `sha256`
+    pub fn fetch(&self) -> Result<(), Error> {
+        if let TestImage::Kodak(n) = self {
+            if !(1..=24).contains(n) {
+                return Err(Error::Message(format!("invalid Kodak image number: {}", n)));
+            }
+        }
+
+        let (path, url) = match (self.path(), self.url()) {
+            (Some(path), Some(url)) => (path, url),
+            _ => return Ok(()),
+        };
+        if path.exists() {
+            return Ok(());
+        }
+
+        if std::env::var_os(OFFLINE_ENV).is_some() {
+            return Err(Error::Message(format!(
+                "{} is not cached and {} is set",
+                self.name(),
+                OFFLINE_ENV
+            )));
+        }
+
+        let expected = self.sha256().ok_or_else(|| {
+            Error::Message(format!(
+                "{} has no pinned SHA-256 checksum, refusing to download {}",
+                self.name(),
+                url
+            ))
+        })?;
+
+        let mut data = Vec::new();
+        ureq::get(&url)
+            .call()
+            .map_err(|e| Error::Message(format!("unable to download {}: {}", url, e)))?
+            .into_reader()
+            .read_to_end(&mut data)?;
+
+        let found = Sha256::digest(&data)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        if found != expected {
+            return Err(Error::Message(format!(
+                "checksum mismatch for {}: expected {}, found {}",
+                url, expected, found
+            )));
+        }
+
+        let dir = cache_dir();
+        std::fs::create_dir_all(&dir).map_err(|source| Error::IO(source).with_path(&dir))?;
+
+        // Write to a temporary file first so an interrupted write is never cached
+        let tmp = path.with_extension(format!("download-{}", std::process::id()));
+        std::fs::write(&tmp, &data).map_err(|source| Error::IO(source).with_path(&tmp))?;
+        std::fs::rename(&tmp, &path).map_err(|source| Error::IO(source).with_path(path))
+    }
+
+    /// Open the image, downloading it first if needed
+    pub fn open<T: Type, C: Color>(&self) -> Result<Image<T, C>, Error> {
+        match self {
+            TestImage::ZonePlate(size) => Ok(zone_plate(*size)),
+            _ => {
+                self.fetch()?;
+                Image::open(self.path().expect("downloaded images have a path"))
+            }
+        }
+    }
+}
+
+/// Generate a circular zone plate, `cos(pi * r^2 / size)` mapped to `[0, 1]`, see
+/// `TestImage::ZonePlate`
+pub fn zone_plate<T: Type, C: Color>(size: usize) -> Image<T, C> {
+    let mut image = Image::new((size, size));
+    let center = size as f64 / 2.0;
+    let mut px = Pixel::<C>::new();
+    image.each_pixel_mut(|pt, data| {
+        let (x, y) = (pt.x as f64 + 0.5 - center, pt.y as f64 + 0.5 - center);
+        let value = 0.5 + 0.5 * (std::f64::consts::PI * (x * x + y * y) / size as f64).cos();
+        let mut gray = Pixel::<Gray>::new();
+        gray[0] = value;
+        gray.convert_to(&mut px);
+        px.copy_to_slice(data);
+    });
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_testdata_paths() {
+        assert_eq!(TestImage::kodak_suite().len(), 24);
+        assert_eq!(TestImage::Kodak(3).name(), "kodim03");
+        assert!(TestImage::Kodak(3).url().unwrap().ends_with("/kodim03.png"));
+        assert!(TestImage::Probe(Probe::StPeters)
+            .path()
+            .unwrap()
+            .ends_with("stpeters_probe.hdr"));
+        assert!(TestImage::ZonePlate(64).path().is_none());
+        assert!(TestImage::ZonePlate(64).is_available());
+        assert!(TestImage::Kodak(25).fetch().is_err());
+        assert!(TestImage::ZonePlate(64).sha256().is_none());
+        assert!(CHECKSUMS
+            .iter()
+            .all(|(_, sum)| sum.len() == 64 && sum.bytes().all(|b| b.is_ascii_hexdigit())));
+    }
+
+    #[test]
+    fn test_zone_plate() {
+        let image: Image<f32, Gray> = TestImage::ZonePlate(64).open().unwrap();
+        assert_eq!(image.size(), Size::new(64, 64));
+        assert!(image.get_f((32, 32), 0) > 0.99);
+
+        // The first dark ring is at a radius of sqrt(size) and the pattern is symmetric
+        assert!(image.get_f((39, 32), 0) < 0.1);
+        assert_eq!(image.get_f((24, 32), 0), image.get_f((39, 32), 0));
+        assert_eq!(image.get_f((32, 24), 0), image.get_f((39, 32), 0));
+    }
+}
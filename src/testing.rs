@@ -0,0 +1,227 @@
+use std::path::{Path, PathBuf};
+
+use crate::*;
+
+/// Environment variable used to set the directory where failure artifacts are saved
+pub const ARTIFACTS_ENV: &str = "IMAGE2_TEST_ARTIFACTS";
+
+/// Directory where failure artifacts are saved, this is only set when the
+/// `IMAGE2_TEST_ARTIFACTS` environment variable is defined
+pub fn artifacts_dir() -> Option<PathBuf> {
+    std::env::var_os(ARTIFACTS_ENV).map(PathBuf::from)
+}
+
+/// Save the expected, actual and diff images to `dir` as `{name}-expected.png`,
+/// `{name}-actual.png` and `{name}-diff.png`
+pub fn save_artifacts<T: Type, U: Type, C: Color>(
+    dir: impl AsRef<Path>,
+    name: &str,
+    expected: &Image<T, C>,
+    actual: &Image<U, C>,
+    diff: &metrics::Diff,
+) -> Result<(), Error> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+
+    // Test names usually come from `module_path!` which isn't a valid file name
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    expected.save(dir.join(format!("{}-expected.png", name)))?;
+    actual.save(dir.join(format!("{}-actual.png", name)))?;
+    diff.heat_map.save(dir.join(format!("{}-diff.png", name)))?;
+    Ok(())
+}
+
+/// Compare `actual` against `expected` using the same `tolerance` for every channel, returns a
+/// message describing the mismatch on failure. Failure artifacts are saved when
+/// `artifacts_dir` is set
+pub fn images_match<T: Type, U: Type, C: Color>(
+    expected: &Image<T, C>,
+    actual: &Image<U, C>,
+    tolerance: f64,
+    name: &str,
+) -> Result<(), String> {
+    let options = metrics::DiffOptions::new()
+        .with_tolerance(tolerance)
+        .with_amplify(10.0);
+    let diff = match metrics::diff(expected, actual, &options) {
+        Ok(diff) => diff,
+        Err(_) => {
+            return Err(format!(
+                "{}: image sizes differ, expected {}x{} got {}x{}",
+                name,
+                expected.width(),
+                expected.height(),
+                actual.width(),
+                actual.height()
+            ))
+        }
+    };
+
+    if diff.is_match() {
+        return Ok(());
+    }
+
+    let mut msg = format!(
+        "{}: {} of {} pixels differ by more than {} (max error {:?}, psnr {:.2}dB)",
+        name,
+        diff.mismatched_pixels,
+        expected.width() * expected.height(),
+        tolerance,
+        diff.max_error,
+        diff.psnr
+    );
+    if let Some(dir) = artifacts_dir() {
+        match save_artifacts(&dir, name, expected, actual, &diff) {
+            Ok(()) => msg += &format!(", artifacts saved to {}", dir.display()),
+            Err(e) => msg += &format!(", unable to save artifacts: {}", e),
+        }
+    }
+    Err(msg)
+}
+
+/// Small SplitMix64 generator, good enough for test data and independent of external crates
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn range(&mut self, range: &std::ops::Range<usize>) -> usize {
+        if range.end <= range.start + 1 {
+            return range.start;
+        }
+        range.start + (self.next() % (range.end - range.start) as u64) as usize
+    }
+}
+
+/// Generate an image with random dimensions in `size_range` filled with random normalized values,
+/// the same `seed` always produces the same image
+pub fn arbitrary_image_with_seed<T: Type, C: Color>(
+    size_range: std::ops::Range<usize>,
+    seed: u64,
+) -> Image<T, C> {
+    let mut rng = SplitMix(seed);
+    let size = (rng.range(&size_range).max(1), rng.range(&size_range).max(1));
+    random_image(size, rng.next())
+}
+
+/// Generate an image filled with random normalized values, the same `seed` always produces the
+/// same image
+pub fn random_image<T: Type, C: Color>(size: impl Into<Size>, seed: u64) -> Image<T, C> {
+    let mut rng = SplitMix(seed);
+    let mut image = Image::new(size);
+    image.each_pixel_mut(|_, mut px| {
+        for c in 0..C::CHANNELS {
+            px[c] = rng.next_f64();
+        }
+    });
+    image
+}
+
+/// Generate an image with random dimensions in `size_range` filled with random normalized
+/// values, a new seed is used for each call
+pub fn arbitrary_image<T: Type, C: Color>(size_range: std::ops::Range<usize>) -> Image<T, C> {
+    use std::hash::{BuildHasher, Hasher};
+    let seed = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    arbitrary_image_with_seed(size_range, seed)
+}
+
+/// Generate a horizontal ramp from `0` at the left edge to `1` at the right edge, the alpha
+/// channel is set to `1`
+pub fn gradient<T: Type, C: Color>(size: impl Into<Size>) -> Image<T, C> {
+    let mut image = Image::new(size);
+    let width = image.width().max(2) - 1;
+    image.each_pixel_mut(|pt, mut px| {
+        for c in 0..C::CHANNELS {
+            px[c] = if C::ALPHA == Some(c) {
+                1.0
+            } else {
+                pt.x as f64 / width as f64
+            };
+        }
+    });
+    image
+}
+
+/// Generate a checkerboard of `cell`x`cell` squares starting with a black square in the top-left
+/// corner, the alpha channel is set to `1`
+pub fn checkerboard<T: Type, C: Color>(size: impl Into<Size>, cell: usize) -> Image<T, C> {
+    let cell = cell.max(1);
+    let mut image = Image::new(size);
+    image.each_pixel_mut(|pt, mut px| {
+        let v = ((pt.x / cell + pt.y / cell) % 2) as f64;
+        for c in 0..C::CHANNELS {
+            px[c] = if C::ALPHA == Some(c) { 1.0 } else { v };
+        }
+    });
+    image
+}
+
+/// Generate a zone plate, concentric rings with a frequency increasing linearly from the center
+/// and reaching the Nyquist limit at the nearest edge. Resampling a zone plate makes aliasing
+/// visible as extra sets of rings, the alpha channel is set to `1`
+pub fn zone_plate<T: Type, C: Color>(size: impl Into<Size>) -> Image<T, C> {
+    let mut image = Image::new(size);
+    let (cx, cy) = (image.width() as f64 / 2.0, image.height() as f64 / 2.0);
+
+    // The local frequency of `cos(k * r^2)` is `k * r / pi` cycles per pixel
+    let k = std::f64::consts::PI / (2.0 * cx.min(cy).max(1.0));
+    image.each_pixel_mut(|pt, mut px| {
+        let (x, y) = (pt.x as f64 + 0.5 - cx, pt.y as f64 + 0.5 - cy);
+        let v = 0.5 + 0.5 * (k * (x * x + y * y)).cos();
+        for c in 0..C::CHANNELS {
+            px[c] = if C::ALPHA == Some(c) { 1.0 } else { v };
+        }
+    });
+    image
+}
+
+/// Assert that two images are equal within a tolerance for normalized values, the tolerance
+/// defaults to `0`
+///
+/// When the `IMAGE2_TEST_ARTIFACTS` environment variable is set the expected, actual and diff
+/// images are saved to that directory on failure
+///
+/// ```rust
+/// use image2::*;
+///
+/// let a = Image::<f32, Rgb>::new((8, 8));
+/// let b = Image::<u8, Rgb>::new((8, 8));
+/// assert_images_match!(a, b, 1.0 / 255.0);
+/// ```
+#[macro_export]
+macro_rules! assert_images_match {
+    ($expected:expr, $actual:expr $(,)?) => {
+        $crate::assert_images_match!($expected, $actual, 0.0)
+    };
+    ($expected:expr, $actual:expr, $tolerance:expr $(,)?) => {
+        if let Err(msg) = $crate::testing::images_match(
+            &$expected,
+            &$actual,
+            $tolerance,
+            concat!(module_path!(), "-", line!()),
+        ) {
+            panic!("{}", msg);
+        }
+    };
+}
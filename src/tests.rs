@@ -44,6 +44,69 @@ fn test_read_write_rgba() {
     assert!(b.save("images/test-read-write-rgba2.png").is_ok());
 }
 
+#[test]
+fn test_save_with_options() {
+    let formats = io::supported_formats();
+    assert!(formats.iter().any(|f| f == "jpg"));
+
+    let a: Image<u8, Rgb> = Image::open("images/A.exr").unwrap();
+    let low = io::EncodeOptions::new().with_quality(10);
+    let high = io::EncodeOptions::new().with_quality(95);
+    assert!(a
+        .save_with_options("images/test-options-low.jpg", &low)
+        .is_ok());
+    assert!(a
+        .save_with_options("images/test-options-high.jpg", &high)
+        .is_ok());
+    let size = |path| std::fs::metadata(path).unwrap().len();
+    assert!(size("images/test-options-low.jpg") < size("images/test-options-high.jpg"));
+
+    for ext in ["avif", "jxl"] {
+        if formats.iter().any(|f| f == ext) {
+            let path = format!("images/test-options.{}", ext);
+            let options = io::EncodeOptions::new().with_speed(8).with_quality(90);
+            assert!(a.save_with_options(&path, &options).is_ok());
+            let b: Image<u8, Rgb> = Image::open(&path).unwrap();
+            assert_eq!(a.size(), b.size());
+        }
+    }
+
+    let lossless = io::EncodeOptions::new().with_lossless(true);
+    assert!(a
+        .save_with_options("images/test-options-lossless.avif", &lossless)
+        .is_err());
+}
+
+#[test]
+fn test_error_context() {
+    use std::error::Error as _;
+
+    let err = Error::IO(std::io::ErrorKind::NotFound.into()).with_path("images/missing.exr");
+    assert!(matches!(err, Error::WithPath { .. }));
+    assert_eq!(err.path(), Some(std::path::Path::new("images/missing.exr")));
+    assert!(err.source().is_some());
+    assert!(err.to_string().contains("images/missing.exr"));
+
+    let err = Error::ShapeMismatch {
+        expected: (1, 2, 3),
+        got: (3, 2, 1),
+    }
+    .with_path("a.png");
+    assert_eq!(err.path(), Some(std::path::Path::new("a.png")));
+    let source = err.source().unwrap().to_string();
+    assert!(source.contains("(1, 2, 3)") && source.contains("(3, 2, 1)"));
+
+    // Errors that already have a path are unchanged
+    let err = err.with_path("b.png");
+    assert_eq!(err.path(), Some(std::path::Path::new("a.png")));
+
+    let res: Result<Image<f32, Rgb>, Error> = Image::open("images/does-not-exist.exr");
+    match res {
+        Err(err) => assert!(err.path().is_some()),
+        Ok(_) => panic!("expected an error"),
+    }
+}
+
 #[test]
 fn test_to_grayscale() {
     let image: Image<f32, Rgb> = Image::open("images/A.exr").unwrap();
@@ -216,6 +279,55 @@ fn test_xyz() {
     assert!(image.save("images/test-xyz1.exr").is_ok());
 }
 
+#[test]
+fn test_try_convert() {
+    let image: Image<u8, Rgb> = testing::gradient((16, 4));
+    let lossless: Image<f32, Rgb> = image.try_convert().unwrap();
+    assert_eq!(lossless.get_pixel((15, 0)), image.get_pixel((15, 0)));
+    assert!(image.try_convert::<u16, Rgba>().is_ok());
+
+    match lossless.try_convert::<u8, Rgb>() {
+        Err(Error::LossyConversion(loss)) => {
+            assert!(loss.reduced_bit_depth);
+            assert_eq!(loss.clipped, 0);
+        }
+        _ => panic!("expected reduced bit depth"),
+    }
+
+    let mut hdr = Image::<f32, Rgba>::new((2, 2));
+    hdr.each_pixel_mut(|_, mut px| {
+        for c in 0..4 {
+            px[c] = 0.5;
+        }
+    });
+    hdr.set_pixel((0, 0), &Pixel::from(vec![2.0, 0.5, 0.5, 1.0]));
+    match hdr.try_convert::<f32, Rgb>() {
+        Err(Error::LossyConversion(loss)) => {
+            assert_eq!(loss.alpha_dropped, 3);
+            assert_eq!(loss.clipped, 0);
+        }
+        _ => panic!("expected dropped alpha"),
+    }
+    match hdr.try_convert::<u32, Rgba>() {
+        Err(Error::LossyConversion(loss)) => {
+            assert_eq!(loss.clipped, 1);
+            assert!(!loss.reduced_bit_depth);
+        }
+        _ => panic!("expected clipped values"),
+    }
+
+    let px: Pixel<Rgba> = Pixel::from(vec![0.2, 0.4, 0.6, 1.0]);
+    assert!(px.convert_checked::<Rgb>().is_ok());
+    let px: Pixel<Rgba> = Pixel::from(vec![0.2, 0.4, 3.0, 0.5]);
+    match px.convert_checked::<Rgb>() {
+        Err(Error::LossyConversion(loss)) => {
+            assert_eq!(loss.clipped, 1);
+            assert_eq!(loss.alpha_dropped, 1);
+        }
+        _ => panic!("expected lossy conversion"),
+    }
+}
+
 #[cfg(feature = "oiio")]
 #[test]
 fn test_convert_colorspace() {
@@ -251,6 +363,20 @@ fn test_type_and_color_name() {
     assert!(u8::type_name() == u8::type_name());
 }
 
+#[test]
+fn test_gray_from_rgb() {
+    // Luma weights sum to one so white stays white
+    let mut white = Pixel::<Rgb>::new();
+    white.map(|_| 1.0);
+    let gray: Pixel<Gray> = white.convert();
+    assert!((gray[0] - 1.0).abs() < 1e-9);
+
+    let mut blue = Pixel::<Rgb>::new();
+    blue[2] = 1.0;
+    let gray: Pixel<Gray> = blue.convert();
+    assert!((gray[0] - 0.07).abs() < 1e-9);
+}
+
 #[cfg(feature = "text")]
 #[test]
 fn test_text() {
@@ -277,3 +403,2411 @@ fn test_mmap() {
     assert!(image == image1);
     image1.save("images/test-mmap.png").unwrap();
 }
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_open_mmap() {
+    let dir = std::env::temp_dir().join(format!("image2-open-mmap-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let image: Image<u8, Rgb> = testing::random_image((7, 5), 2);
+
+    // Binary PPM with a comment in the header
+    let mut ppm = b"P6\n# comment\n7 5\n255\n".to_vec();
+    ppm.extend_from_slice(image.data());
+    std::fs::write(dir.join("a.ppm"), &ppm).unwrap();
+    let mut mapped: Image<u8, Rgb> = Image::open_mmap(dir.join("a.ppm")).unwrap();
+    assert!(mapped == image);
+
+    // Copy-on-write, the file is unchanged
+    mapped.set_f((0, 0), 0, 1.0 - image.get_f((0, 0), 0));
+    drop(mapped);
+    assert_eq!(std::fs::read(dir.join("a.ppm")).unwrap(), ppm);
+
+    // Mismatched types are rejected
+    assert!(Image::<u8, Gray>::open_mmap(dir.join("a.ppm")).is_err());
+    assert!(Image::<u16, Rgb>::open_mmap(dir.join("a.ppm")).is_err());
+
+    // Single strip uncompressed TIFF, the pixel data comes before the IFD
+    let data_offset = 8u32;
+    let ifd = data_offset + image.data().len() as u32;
+    let ifd = ifd + ifd % 2;
+    let mut tiff = b"II*\0".to_vec();
+    tiff.extend_from_slice(&ifd.to_le_bytes());
+    tiff.extend_from_slice(image.data());
+    tiff.resize(ifd as usize, 0);
+    let entries: [(u16, u16, u32, u32); 7] = [
+        (256, 3, 1, 7),
+        (257, 3, 1, 5),
+        (258, 3, 1, 8),
+        (259, 3, 1, 1),
+        (273, 4, 1, data_offset),
+        (277, 3, 1, 3),
+        (279, 4, 1, image.data().len() as u32),
+    ];
+    tiff.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for (tag, kind, count, value) in entries {
+        tiff.extend_from_slice(&tag.to_le_bytes());
+        tiff.extend_from_slice(&kind.to_le_bytes());
+        tiff.extend_from_slice(&count.to_le_bytes());
+        tiff.extend_from_slice(&value.to_le_bytes());
+    }
+    tiff.extend_from_slice(&0u32.to_le_bytes());
+    std::fs::write(dir.join("a.tif"), &tiff).unwrap();
+    let mapped: Image<u8, Rgb> = Image::open_mmap(dir.join("a.tif")).unwrap();
+    assert!(mapped == image);
+
+    // Headerless data
+    let raw: Image<u8, Rgb> = Image::open_mmap_raw(dir.join("a.tif"), (7, 5), 8).unwrap();
+    assert!(raw == image);
+    assert!(Image::<u8, Rgb>::open_mmap_raw(dir.join("a.tif"), (700, 5), 8).is_err());
+
+    // Sizes that overflow are rejected instead of wrapping around
+    let huge = format!("P6\n{} 2\n255\n", usize::MAX / 2);
+    std::fs::write(dir.join("huge.ppm"), huge).unwrap();
+    match Image::<u8, Rgb>::open_mmap(dir.join("huge.ppm")) {
+        Err(Error::WithPath { source, .. }) => {
+            assert!(matches!(*source, Error::InvalidDimensions(..)))
+        }
+        _ => panic!("expected an error with the path attached"),
+    }
+    let huge = Image::<u8, Rgb>::open_mmap_raw(dir.join("a.tif"), (usize::MAX, 2), 8);
+    assert!(matches!(huge, Err(Error::InvalidDimensions(..))));
+    let huge = Mmap::<u8>::open_copy(dir.join("a.tif"), u64::MAX, 8);
+    assert!(matches!(huge, Err(Error::InvalidDimensions(..))));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_image_arithmetic() {
+    let mut a: Image<u8, Gray> = Image::new((4, 3));
+    a.data_mut()
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = i as u8 * 20);
+    let b = a.clone();
+
+    // Saturating by default for integer types
+    let sum = &a + &b;
+    assert_eq!(sum.data()[3], 120);
+    assert_eq!(sum.data()[11], 255);
+    let mut double = b.clone();
+    double.mul_with(2.0, Overflow::Saturate).unwrap();
+    let diff = &a - &double;
+    assert!(diff.data().iter().skip(1).all(|x| *x == 0));
+
+    // Wrapping
+    let mut wrapped = a.clone();
+    wrapped.add_with(&b, Overflow::Wrap).unwrap();
+    assert_eq!(wrapped.data()[11], (220u8).wrapping_add(220));
+
+    // Broadcasting pixels and scalars
+    let px = Pixel::from(vec![10.0 / 255.0]);
+    assert_eq!((&a + &px).data()[1], 30);
+    assert_eq!((&a + 5u8).data()[2], 45);
+    let mut c = a.clone();
+    c -= 30u8;
+    assert_eq!(c.data()[1..3], [0, 10]);
+
+    // Scalar multipliers and divisors aren't normalized
+    assert_eq!((&a * 2u8).data()[..4], [0, 40, 80, 120]);
+    assert_eq!((&a * 2u8).data()[11], 255);
+    assert_eq!((&a / 4u8).data()[..4], [0, 5, 10, 15]);
+    let mut d = a.clone();
+    d *= 3u8;
+    assert_eq!(d.data()[1], 60);
+
+    // Normalized multiplication and division, like `Pixel`
+    let half: Image<f32, Gray> = a.convert::<f32, Gray>() * 0.5f32;
+    assert!((half.data()[3] - 30.0 / 255.0).abs() < 1e-6);
+    let ratio = &half / &a.convert::<f32, Gray>();
+    assert!(ratio.data().iter().skip(1).all(|x| (x - 0.5).abs() < 1e-6));
+    assert_eq!(ratio.data()[0], 0.0);
+
+    // Floating point values are unbounded
+    let big = &half + 2.0f32;
+    assert!(big.data()[0] > 1.0);
+
+    // Shape mismatch
+    let other: Image<u8, Gray> = Image::new((3, 3));
+    assert!(a.clone().add_with(&other, Overflow::Saturate).is_err());
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| &a + &other)).is_err());
+}
+
+#[test]
+fn test_f16_fast_paths() {
+    let mut image: Image<f32, Rgb> = Image::new((40, 20));
+    image
+        .data_mut()
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = i as f32 / 1000.0 - 0.2);
+    let half: Image<f16, Rgb> = image.convert_type();
+    assert_eq!(half.colorspace(), image.colorspace());
+
+    // Bulk conversion matches converting one channel at a time, negative values are kept
+    for (a, b) in image.data().iter().zip(half.data()) {
+        assert_eq!(f16::from_f32(*a), *b);
+    }
+    assert!(half.convert_type::<f32>().data()[0] < 0.0);
+    let px = half.get_pixel((39, 19));
+    assert_eq!(px[2], half.data()[40 * 20 * 3 - 1].to_f64());
+
+    // Arithmetic matches f32 within half precision, including across chunk boundaries
+    let sum = &half + &half;
+    let expected = &image + &image;
+    for (a, b) in sum.data().iter().zip(expected.data()) {
+        assert!((a.to_f32() - b).abs() <= b.abs() * 1e-3 + 1e-6);
+    }
+    let mut scaled = half.clone();
+    scaled
+        .mul_with(&Pixel::from(vec![1.0, 2.0, 0.5]), Overflow::Saturate)
+        .unwrap();
+    assert!(scaled
+        .data()
+        .iter()
+        .all(|x| (0.0..=1.0).contains(&x.to_f32())));
+    assert_eq!(scaled.data()[3 * 300 + 1], f16::from_f32(1.0));
+}
+
+#[test]
+fn test_content_hash() {
+    let image: Image<f32, Rgb> = testing::gradient((64, 8));
+    let hash = image.content_hash();
+    assert_eq!(hash, image.clone().content_hash());
+
+    // Any change to the data, type, color or metadata produces a different hash
+    let mut changed = image.clone();
+    changed.set_f((10, 3), 1, 0.25);
+    assert_ne!(changed.content_hash(), hash);
+    assert_ne!(image.convert::<f64, Rgb>().content_hash(), hash);
+    assert_ne!(image.convert::<f32, Rgba>().content_hash(), hash);
+    assert_ne!(
+        image
+            .clone()
+            .with_colorspace(Colorspace::SRGB)
+            .content_hash(),
+        hash
+    );
+    assert_ne!(
+        Image::<f32, Rgb>::new((8, 64)).content_hash(),
+        Image::<f32, Rgb>::new((64, 8)).content_hash()
+    );
+
+    // Re-running an unchanged pipeline on unchanged inputs is skipped
+    let pipeline = Pipeline::new()
+        .then(filter::auto_levels(0.5))
+        .then(filter::invert());
+    let fingerprint = pipeline.fingerprint();
+    let mut cache = PipelineCache::new();
+    let mut dest = image.new_like();
+    assert!(pipeline.execute_cached(&mut cache, &[&image], &mut dest));
+    assert_eq!(pipeline.fingerprint(), fingerprint);
+    assert!(!pipeline.execute_cached(&mut cache, &[&image], &mut dest));
+    assert_eq!(cache.len(), 1);
+
+    // Changing the output, the inputs or the filter parameters executes the pipeline again
+    dest.set_f((0, 0), 0, 0.5);
+    assert!(pipeline.execute_cached(&mut cache, &[&image], &mut dest));
+    assert!(pipeline.execute_cached(&mut cache, &[&changed], &mut dest));
+    let other = Pipeline::new()
+        .then(filter::auto_levels(1.0))
+        .then(filter::invert());
+    assert_ne!(other.fingerprint(), fingerprint);
+    assert!(other.execute_cached(&mut cache, &[&image], &mut dest));
+
+    // Pipelines containing filters without a fingerprint always run and aren't cached
+    let unknown = Pipeline::new()
+        .then(filter::invert())
+        .then(filter::convert_mapped(GamutMapping::Clip));
+    assert_eq!(unknown.fingerprint(), None);
+    let entries = cache.len();
+    assert!(unknown.execute_cached(&mut cache, &[&image], &mut dest));
+    assert!(unknown.execute_cached(&mut cache, &[&image], &mut dest));
+    assert_eq!(cache.len(), entries);
+}
+
+#[test]
+fn test_atlas() {
+    use atlas::*;
+
+    // Solid images with a distinct value so they can be found on the page
+    let images: Vec<Image<u8, Gray>> = (0..40)
+        .map(|i| {
+            let mut image = Image::new((4 + i % 7 * 3, 3 + i % 5 * 4));
+            image.data_mut().iter_mut().for_each(|x| *x = i as u8 + 1);
+            image
+        })
+        .collect();
+    let refs: Vec<&Image<u8, Gray>> = images.iter().collect();
+
+    for packing in [Packing::Skyline, Packing::Guillotine] {
+        let builder = AtlasBuilder::new((64, 64))
+            .with_padding(1)
+            .with_bleed(2)
+            .with_packing(packing);
+        let atlas = builder.build(&refs).unwrap();
+        assert_eq!(atlas.placements.len(), images.len());
+        assert!(atlas.pages.len() > 1);
+
+        // Images and their bleed are inside the page and separated by the padding
+        let bleed = |r: &Region| (r.min_x() - 2, r.min_y() - 2, r.max_x() + 2, r.max_y() + 2);
+        for (i, a) in atlas.placements.iter().enumerate() {
+            assert_eq!(a.region.size, images[i].size());
+            let (ax0, ay0, ax1, ay1) = bleed(&a.region);
+            assert!(ax1 <= 64 && ay1 <= 64);
+            for b in &atlas.placements[i + 1..] {
+                let (bx0, by0, bx1, by1) = bleed(&b.region);
+                if a.page == b.page {
+                    assert!(ax1 < bx0 || bx1 < ax0 || ay1 < by0 || by1 < ay0);
+                }
+            }
+
+            // Content is copied and the edges are repeated into the bleed
+            let page = &atlas.pages[a.page];
+            let (x, y) = (a.region.min_x(), a.region.min_y());
+            assert_eq!(page.get((x, y)).as_slice()[0], i as u8 + 1);
+            assert_eq!(page.get((x - 2, y - 2)).as_slice()[0], i as u8 + 1);
+            assert_eq!(
+                page.get((a.region.max_x() + 1, a.region.max_y() - 1))
+                    .as_slice()[0],
+                i as u8 + 1
+            );
+        }
+
+        let uv = atlas.uv(0);
+        assert!(uv[0] < uv[2] && uv[1] < uv[3] && uv[2] <= 1.0);
+    }
+
+    // Images larger than a page are rejected
+    let big = Image::<u8, Gray>::new((70, 10));
+    assert!(AtlasBuilder::new((64, 64)).build(&[&big]).is_err());
+    assert!(AtlasBuilder::new((64, 64))
+        .with_bleed(4)
+        .pack(&[Size::new(60, 10)])
+        .is_err());
+    assert_eq!(
+        AtlasBuilder::new((64, 64))
+            .pack(&[Size::new(64, 64)])
+            .unwrap()[0]
+            .region,
+        Region::new(Point::new(0, 0), Size::new(64, 64))
+    );
+}
+
+#[test]
+fn test_mipmaps() {
+    use mipmap::*;
+
+    assert_eq!(mip_levels((1, 1)), 1);
+    assert_eq!(mip_levels((256, 64)), 9);
+    assert_eq!(mip_levels((5, 3)), 3);
+
+    // Full chain, odd sizes round down
+    let image: Image<f32, Rgb> = Image::new((5, 3));
+    let levels = image.generate_mipmaps(MipFilter::default());
+    let sizes: Vec<Size> = levels.iter().map(|l| l.size()).collect();
+    assert_eq!(sizes, [Size::new(5, 3), Size::new(2, 1), Size::new(1, 1)]);
+
+    // Constant images stay constant with every filter
+    let mut image: Image<f32, Rgb> = Image::new((16, 8));
+    image.data_mut().iter_mut().for_each(|x| *x = 0.25);
+    for filter in [MipFilter::Box, MipFilter::Triangle, MipFilter::Lanczos(3)] {
+        let levels = image.generate_mipmaps(filter);
+        assert_eq!(levels.len(), 5);
+        for level in &levels {
+            assert!(level.data().iter().all(|x| (x - 0.25).abs() < 1e-6));
+        }
+    }
+
+    // sRGB encoded checkerboards average in linear light
+    let mut checker: Image<f32, Srgb> = Image::new((8, 8));
+    checker.for_each(|pt, mut px| {
+        let v = ((pt.x + pt.y) % 2) as f32;
+        px.as_slice_mut().iter_mut().for_each(|x| *x = v);
+    });
+    let levels = checker.generate_mipmaps(MipFilter::Box);
+    let expected = linear_to_srgb(0.5);
+    assert!((levels[1].get_f((1, 1), 0) - expected).abs() < 1e-6);
+    assert!((levels[3].get_f((0, 0), 2) - expected).abs() < 1e-6);
+
+    // Transparent pixels don't darken their opaque neighbors
+    let mut sprite: Image<f32, Rgba> = Image::new((2, 2));
+    sprite.set_pixel((0, 0), &Pixel::from(vec![1.0, 0.5, 0.0, 1.0]));
+    sprite.set_pixel((1, 1), &Pixel::from(vec![1.0, 0.5, 0.0, 1.0]));
+    let levels = sprite.generate_mipmaps(MipFilter::Box);
+    let px = levels[1].get_pixel((0, 0));
+    assert!((px[0] - 1.0).abs() < 1e-6);
+    assert!((px[1] - 0.5).abs() < 1e-6);
+    assert!((px[3] - 0.5).abs() < 1e-6);
+
+    // Without an I/O backend writing returns an error instead of panicking
+    #[cfg(all(not(feature = "magick"), not(feature = "oiio")))]
+    assert!(io::write_mipmaps("images/mip.exr", &levels).is_err());
+}
+
+#[test]
+fn test_flatten_onto() {
+    let mut image: Image<f32, Rgba> = Image::new((4, 4));
+    image.set_pixel((0, 0), &Pixel::from(vec![1.0, 0.0, 0.0, 1.0]));
+    image.set_pixel((1, 0), &Pixel::from(vec![1.0, 0.0, 0.0, 0.5]));
+    image.set_pixel((2, 0), &Pixel::from(vec![0.2, 0.4, 0.6, 0.0]));
+
+    let mut blue = Pixel::<Rgb>::new();
+    blue.copy_from_slice([0.0, 0.0, 1.0]);
+    let flat = image.flatten_onto(&blue);
+    assert_eq!(flat.get_pixel((0, 0)).as_ref(), [1.0, 0.0, 0.0]);
+    assert_eq!(flat.get_pixel((1, 0)).as_ref(), [0.5, 0.0, 0.5]);
+    assert_eq!(flat.get_pixel((2, 0)).as_ref(), [0.0, 0.0, 1.0]);
+
+    // Premultiplied color isn't scaled by alpha again
+    let mut premultiplied = image.clone();
+    premultiplied.set_pixel((1, 0), &Pixel::from(vec![0.5, 0.0, 0.0, 0.5]));
+    let flat = premultiplied.flatten_premultiplied_onto(&blue);
+    assert_eq!(flat.get_pixel((1, 0)).as_ref(), [0.5, 0.0, 0.5]);
+
+    // Transparent pixels show the checkerboard
+    let preview = image.preview_checkerboard(2);
+    assert_eq!(preview.get_pixel((2, 2)).as_ref(), [1.0; 3]);
+    let dark = preview.get_pixel((2, 0));
+    assert!((dark[0] - srgb_to_linear(0.8)).abs() < 1e-6);
+    assert_eq!(preview.get_pixel((0, 0)).as_ref(), [1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn test_map_neighborhood() {
+    let image: Image<f32, Gray> = testing::random_image((9, 7), 3);
+
+    // 3x3 box blur matches the kernel
+    let mean = image.map_neighborhood(1, |window| {
+        let sum = window
+            .pixels()
+            .fold(Pixel::new(), |acc: Pixel<Gray>, (_, px)| acc + px);
+        sum / 9.0f32
+    });
+    let mut k = Kernel::create(3, 3, |_, _| 1.0 / 9.0);
+    k.set_edge_strategy(kernel::EdgeStrategy::Extend);
+    let mut expected = image.new_like();
+    k.eval(&[&image], &mut expected);
+    for (a, b) in mean.data().iter().zip(expected.data()) {
+        assert!((a - b).abs() < 1e-5);
+    }
+
+    // Offsets and edges
+    let shifted = image.map_neighborhood(0, |window| window.get_pixel(-1, 0));
+    assert_eq!(shifted.get_f((3, 2), 0), image.get_f((2, 2), 0));
+    assert_eq!(shifted.get_f((0, 2), 0), image.get_f((0, 2), 0));
+    let constant = image.map_neighborhood_with(1, kernel::EdgeStrategy::Constant, |window| {
+        let mut px = Pixel::new();
+        px[0] = window.get_f(0, -1, 0);
+        assert_eq!(window.offsets().count(), 9);
+        px
+    });
+    assert_eq!(constant.get_f((4, 0), 0), 0.0);
+    assert_eq!(constant.get_f((4, 1), 0), image.get_f((4, 0), 0));
+}
+
+#[test]
+fn test_tile() {
+    let image: Image<u8, Rgb> = testing::random_image((10, 6), 4);
+
+    // Copy out and back in, pixels outside of the image are zero
+    let tile: Tile<u8, Rgb, 4, 4> = Tile::from_image(&image, (8, 4));
+    assert_eq!(tile.get((1, 1)).as_slice(), image.get((9, 5)).as_slice());
+    assert_eq!(tile.get((2, 1)).as_slice(), [0; 3]);
+    let mut dest = image.new_like();
+    tile.copy_to_image(&mut dest, (8, 4));
+    assert_eq!(dest.get((9, 5)).as_slice(), image.get((9, 5)).as_slice());
+    assert!(tile.to_image().size() == Size::new(4, 4));
+
+    // Filters
+    let mut inverted: Tile<u8, Rgb, 4, 4> = Tile::new();
+    inverted.eval(&filter::invert(), &[&image], (2, 1));
+    let mut expected = image.new_like();
+    filter::invert().eval(&[&image], &mut expected);
+    assert!(inverted == Tile::from_image(&expected, (2, 1)));
+
+    let mut copy = Tile::<u8, Rgb, 4, 4>::from_image(&image, (2, 1));
+    copy.run_point_filter(&filter::Invert);
+    assert!(copy == inverted);
+
+    copy.set_f((0, 0), 1, 1.0);
+    assert_eq!(copy.get_pixel((0, 0))[1], 1.0);
+}
+
+#[test]
+fn test_format_info() {
+    let png = io::FormatInfo::new("png", vec!["png".into()], true, true);
+    assert!(png.matches("a/b.PNG"));
+    assert!(!png.matches("a/b.jpg"));
+    assert!(png.supports(io::BaseType::UInt16, 4));
+    assert!(!png.supports(io::BaseType::Float, 3));
+    assert!(!png.supports(io::BaseType::UInt8, 5));
+
+    let exr = io::FormatInfo::new("openexr", vec!["exr".into(), "sxr".into()], true, false);
+    assert!(exr.supports(io::BaseType::Half, 9));
+    assert!(!exr.supports(io::BaseType::UInt8, 3));
+
+    // Unknown formats don't restrict anything
+    let other = io::FormatInfo::new("other", vec!["xyz".into()], true, false);
+    assert!(other.types.is_empty() && other.channels.is_empty());
+    assert!(other.supports(io::BaseType::Int64, 7));
+
+    let info = io::ProbeInfo {
+        size: Size::new(4, 3),
+        base_type: io::BaseType::UInt16,
+        channels: 3,
+        channel_names: vec![],
+        frames: 1,
+        attributes: Default::default(),
+    };
+    assert_eq!(info.num_bytes::<f32>(), Some(4 * 3 * 3 * 4));
+    let info = io::ProbeInfo {
+        size: Size::new(usize::MAX / 2, 3),
+        ..info
+    };
+    assert_eq!(info.num_bytes::<f32>(), None);
+}
+
+#[test]
+fn test_decode_options() {
+    let mut info = io::ProbeInfo {
+        size: Size::new(4000, 3000),
+        base_type: io::BaseType::UInt8,
+        channels: 3,
+        channel_names: vec![],
+        frames: 1,
+        attributes: Default::default(),
+    };
+
+    let options = io::DecodeOptions::new();
+    assert!(options.check::<u8, Rgb>(&info).is_ok());
+
+    let options = io::DecodeOptions::new()
+        .with_max_width(4096)
+        .with_max_height(4096)
+        .with_max_bytes(64 << 20)
+        .with_max_frames(1);
+    assert!(options.check::<u8, Rgb>(&info).is_ok());
+    assert!(matches!(
+        options.check::<f32, Rgb>(&info),
+        Err(Error::LimitExceeded { limit: "bytes", value, .. }) if value == 4000 * 3000 * 12
+    ));
+
+    // Channel conversion needs an extra buffer
+    assert_eq!(
+        io::DecodeOptions::decoded_bytes::<u8, Rgba>(&info),
+        Some(4000 * 3000 * (4 + 12))
+    );
+
+    info.frames = 100;
+    assert!(matches!(
+        options.check::<u8, Rgb>(&info),
+        Err(Error::LimitExceeded {
+            limit: "frames",
+            value: 100,
+            max: 1
+        })
+    ));
+
+    // Absurd dimensions are rejected without overflowing
+    info.size = Size::new(1 << 40, 1 << 40);
+    info.frames = 1;
+    assert!(matches!(
+        options.check::<u8, Rgb>(&info),
+        Err(Error::LimitExceeded { limit: "width", .. })
+    ));
+    let options = io::DecodeOptions::new().with_max_bytes(1 << 30);
+    assert!(matches!(
+        options.check::<u8, Rgb>(&info),
+        Err(Error::LimitExceeded {
+            limit: "bytes",
+            value: usize::MAX,
+            ..
+        })
+    ));
+
+    // Without an I/O backend reading returns an error instead of panicking
+    #[cfg(all(not(feature = "magick"), not(feature = "oiio")))]
+    assert!(matches!(
+        io::read_with_options::<_, u8, Rgb>("images/A.exr", &options),
+        Err(Error::WithPath { .. })
+    ));
+}
+
+#[test]
+fn test_watermark() {
+    use watermark::Position;
+
+    // Visible
+    let mut image: Image<f32, Rgb> = Image::new((6, 6));
+    let mut logo: Image<u8, Rgba> = Image::new((2, 2));
+    logo.each_pixel_mut(|_, px| {
+        px.copy_from_slice([1.0, 0.0, 0.0, 1.0]);
+    });
+    logo.set_f((0, 0), 3, 0.0);
+    watermark::embed_visible(&mut image, &logo, Position::BottomRight(1), 0.5);
+    assert_eq!(image.get_pixel((4, 4)).as_ref(), [0.5, 0.0, 0.0]);
+    assert_eq!(image.get_pixel((3, 3)).as_ref(), [0.0, 0.0, 0.0]);
+    assert_eq!(image.get_pixel((5, 5)).as_ref(), [0.0, 0.0, 0.0]);
+    assert_eq!(
+        Position::Center.origin(Size::new(6, 6), Size::new(2, 2)),
+        (2, 2)
+    );
+
+    // Invisible, the mark survives conversion to 8 bits
+    let payload = b"image2";
+    let mut image: Image<u8, Rgb> = testing::random_image((128, 96), 5);
+    let original = image.clone();
+    assert_eq!(watermark::capacity(image.size()), 8);
+    watermark::embed_invisible(&mut image, payload, 42).unwrap();
+    assert_eq!(watermark::extract(&image, 42).unwrap(), payload);
+    assert!(watermark::extract(&image, 43).is_err());
+    assert!(watermark::extract(&original, 42).is_err());
+    let max_diff = image
+        .data()
+        .iter()
+        .zip(original.data())
+        .map(|(a, b)| (*a as i32 - *b as i32).abs())
+        .max()
+        .unwrap();
+    assert!(max_diff <= 3);
+
+    // Rgba conversion premultiplies alpha, keep the image opaque so only quantization applies
+    let mut image: Image<f32, Rgba> = testing::random_image((128, 128), 6);
+    image.for_each(|_, mut px| px[3] = 1.0);
+    watermark::embed_invisible(&mut image, payload, 7).unwrap();
+    assert!(image.iter().all(|(_, px)| px[3] == 1.0));
+    let converted: Image<u8, Rgba> = image.convert();
+    assert_eq!(watermark::extract(&converted, 7).unwrap(), payload);
+    assert!(watermark::embed_invisible(&mut image, &[0; 64], 7).is_err());
+
+    // Images without room for the header are rejected, even for an empty payload
+    let mut small: Image<u8, Rgb> = Image::new((64, 64));
+    assert!(watermark::embed_invisible(&mut small, &[], 7).is_err());
+}
+
+#[test]
+fn test_batch() {
+    use batch::{Batch, Recipe, Step};
+
+    let recipe: Recipe = "resize(8, 4) | gaussian_blur(1.5)|invert".parse().unwrap();
+    assert_eq!(
+        recipe.steps,
+        vec![Step::Resize(8, 4), Step::GaussianBlur(1.5), Step::Invert]
+    );
+    assert_eq!(recipe.to_string().parse::<Recipe>().unwrap(), recipe);
+    assert!("blur(1)".parse::<Recipe>().is_err());
+    assert!("invert(1)".parse::<Recipe>().is_err());
+    assert!("resize(8)".parse::<Recipe>().is_err());
+
+    let image: Image<f32, Rgb> = testing::random_image((16, 16), 1);
+    let out = recipe.apply(&image).unwrap();
+    assert_eq!(out.size(), Size::new(8, 4));
+
+    // Kernels come from the preset registry, steps between resizes run as one pipeline
+    let recipe: Recipe = "kernel(sharpen) | brightness(0.1) | resize(8, 8) | invert"
+        .parse()
+        .unwrap();
+    assert_eq!(recipe.steps[0], Step::Kernel("sharpen".into()));
+    assert_eq!(recipe.to_string().parse::<Recipe>().unwrap(), recipe);
+    let sharpened: Image<f32, Rgb> = image.run(Kernel::sharpen(), None);
+    let brightened: Image<f32, Rgb> = sharpened.run(filter::Brightness(0.1), None);
+    let resized: Image<f32, Rgb> =
+        brightened.run_geometry(filter::resize(brightened.size(), Size::new(8, 8)));
+    let expected: Image<f32, Rgb> = resized.run(filter::Invert, None);
+    assert!(recipe.apply(&image).unwrap() == expected);
+    assert!("kernel(missing)".parse::<Recipe>().is_err());
+
+    assert!(batch::glob_match("*.png", "a.png"));
+    assert!(batch::glob_match("img_??.*", "img_01.jpg"));
+    assert!(!batch::glob_match("img_??.*", "img_1.jpg"));
+    assert!(!batch::glob_match("*.png", "a.png.bak"));
+    assert_eq!(
+        batch::output_path("{dir}/out/{stem}_{index}.{ext}", "images/a.png", 3),
+        std::path::PathBuf::from("images/out/a_3.png")
+    );
+
+    let dir = std::env::temp_dir().join(format!("image2-batch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for name in ["a.txt", "b.txt", "bad.txt", "panic.txt", "skip.dat"] {
+        std::fs::write(dir.join(name), name).unwrap();
+    }
+
+    let b = Batch::new(
+        format!("{}/*.txt", dir.display()),
+        format!("{}/out/{{stem}}.out", dir.display()),
+        Recipe::new(),
+    )
+    .with_threads(2)
+    .with_skip_existing(true);
+    let process = |input: &std::path::Path, output: &std::path::Path| {
+        let data = std::fs::read_to_string(input)?;
+        if data == "bad.txt" {
+            return Err(Error::Message("bad input".into()));
+        }
+        assert!(data != "panic.txt", "unable to process");
+        std::fs::write(output, data.to_uppercase())?;
+        Ok(())
+    };
+
+    let report = b.run_with(process).unwrap();
+    assert_eq!(report.files.len(), 4);
+    assert_eq!(report.processed(), 2);
+    assert_eq!(report.failed(), 2);
+    assert!(!report.is_ok());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("out/a.out")).unwrap(),
+        "A.TXT"
+    );
+    let summary = report.to_string();
+    assert!(summary.starts_with("2 processed, 0 skipped, 2 failed"));
+    assert!(summary.contains("bad input"));
+    assert!(summary.contains("unable to process"));
+
+    let report = b.run_with(process).unwrap();
+    assert_eq!(report.skipped(), 2);
+    assert_eq!(report.failed(), 2);
+
+    // Inputs that map to the same output are rejected before anything is processed
+    let clash = Batch::new(
+        format!("{}/*.txt", dir.display()),
+        format!("{}/out/same.out", dir.display()),
+        Recipe::new(),
+    );
+    assert!(clash.files().is_err());
+    assert!(clash.run_with(process).is_err());
+
+    // Unreadable directories are reported instead of being skipped
+    assert!(batch::glob(dir.join("missing/*.txt")).unwrap().is_empty());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let locked = dir.join("locked");
+        std::fs::create_dir(&locked).unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+        let readable = std::fs::read_dir(&locked).is_ok();
+        assert!(readable || batch::glob(locked.join("*.txt")).is_err());
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_features() {
+    let mut image: Image<f32, Gray> = Image::new((5, 5));
+    let codes = features::lbp(&image, 1.0, 8);
+    assert!(codes.data().iter().all(|x| *x == 0xff));
+
+    image.for_each(|_, mut px| px[0] = 0.5);
+    image.set_f((2, 2), 0, 1.0);
+    let codes = features::lbp(&image, 1.0, 8);
+    assert_eq!(codes.get((2, 2))[0], 0);
+    assert_eq!(codes.get((0, 0))[0], 0xff);
+
+    image.set_f((2, 2), 0, 0.0);
+    let codes = features::lbp(&image, 1.0, 8);
+    assert_eq!(codes.get((2, 2))[0], 0xff);
+    // The dark pixel is the left neighbor, bit 4, of the pixel to its right and also darkens the
+    // interpolated diagonal neighbors on either side
+    assert_eq!(codes.get((3, 2))[0], 0xff & !(0b111 << 3));
+    let codes = features::lbp(&image, 2.0, 16);
+    assert_eq!(codes.get((4, 2))[0], 0xffff & !(0b111 << 7));
+
+    let mut edge: Image<f32, Gray> = Image::new((32, 32));
+    edge.for_each(|pt, mut px| px[0] = if pt.x >= 16 { 1.0 } else { 0.0 });
+    let hog = features::hog(&edge, 8, 2);
+    assert_eq!(hog.blocks, Size::new(3, 3));
+    assert_eq!(hog.block_len(), 2 * 2 * features::HOG_BINS);
+    assert_eq!(hog.descriptor.len(), 9 * hog.block_len());
+    assert!(hog.descriptor.iter().all(|x| (0.0..=1.0).contains(x)));
+
+    // A vertical edge only produces horizontal gradients, split between the bins around 0 degrees
+    let block = hog.block(1, 1);
+    let bins = |i: usize| block.chunks(features::HOG_BINS).map(|h| h[i]).sum::<f64>();
+    assert!(bins(0) > 0.0);
+    assert!((bins(0) - bins(features::HOG_BINS - 1)).abs() < 1e-9);
+    assert!((1..features::HOG_BINS - 1).all(|i| bins(i) == 0.0));
+    // The first cell is flat, only the second cell in the block touches the edge
+    assert!(hog.block(0, 0)[..features::HOG_BINS]
+        .iter()
+        .all(|x| *x == 0.0));
+
+    assert!(features::hog(&edge, 16, 4).descriptor.is_empty());
+}
+
+#[test]
+fn test_sanitize() {
+    let mut image: Image<f32, Rgb> = Image::new((4, 4));
+    image.set_f((0, 0), 0, f64::NAN);
+    image.set_f((1, 0), 1, f64::INFINITY);
+    image.set_f((1, 0), 2, f64::NEG_INFINITY);
+    image.set_f((2, 0), 0, 4.0);
+
+    let f = Sanitize::new().with_clamp(0.0, 2.0);
+    let mut dest = image.new_like();
+    f.eval(&[&image], &mut dest);
+    assert_eq!(f.fixed(), 3);
+    assert_eq!(dest.get_f((0, 0), 0), 0.0);
+    assert_eq!(dest.get_f((1, 0), 1), 1.0);
+    assert_eq!(dest.get_f((1, 0), 2), 0.0);
+    assert_eq!(dest.get_f((2, 0), 0), 2.0);
+    assert!(!dest.stats().has_invalid());
+
+    let f = Sanitize::new().with_clamp(2.0, 0.0);
+    f.eval(&[&image], &mut dest);
+    assert_eq!(dest.get_f((2, 0), 0), 2.0);
+
+    // Bounds set directly on the field are normalized the same way as `with_clamp`
+    let mut f = Sanitize::new();
+    f.clamp_range = Some((2.0, 0.0));
+    f.eval(&[&image], &mut dest);
+    assert_eq!(dest.get_f((2, 0), 0), 2.0);
+
+    f.clamp_range = Some((f64::NAN, 2.0));
+    f.eval(&[&image], &mut dest);
+    assert_eq!(dest.get_f((2, 0), 0), 2.0);
+    assert_eq!(dest.get_f((1, 0), 2), -1.0);
+}
+
+#[test]
+fn test_kernel_in_pipeline() {
+    let mut a = Image::<f32, Gray>::new((8, 8));
+    a.for_each(|pt, mut px| px[0] = pt.x as f32 / 8.0);
+    let mut b = a.new_like();
+    b.for_each(|_, mut px| px[0] = 0.25);
+
+    let identity = Kernel::from([[0., 0., 0.], [0., 1., 0.], [0., 0., 0.]]);
+
+    let pipeline = filter::invert().then(identity.clone());
+    let mut dest = a.new_like();
+    pipeline.execute(&[&a], &mut dest);
+    assert!((dest.get_f((3, 3), 0) - (1.0 - a.get_f((3, 3), 0))).abs() < 1e-6);
+
+    let mut dest = a.new_like();
+    identity.with_input(1).eval(&[&a, &b], &mut dest);
+    assert_eq!(dest.get_f((3, 3), 0), 0.25);
+}
+
+#[test]
+fn test_pipeline_plan() {
+    let mut a = Image::<f32, Gray>::new((8, 8));
+    a.for_each(|pt, mut px| px[0] = pt.x as f32 / 8.0);
+
+    let identity = Kernel::from([[0., 0., 0.], [0., 1., 0.], [0., 0., 0.]]);
+    let inner = filter::invert().then(identity.clone());
+    let pipeline = filter::brightness(0.5)
+        .then(inner)
+        .then(filter::invert())
+        .then(filter::invert());
+
+    let plan = pipeline.plan();
+    assert_eq!(pipeline.flatten().len(), 5);
+    assert_eq!(plan.scratch_buffers, 1);
+    assert_eq!(
+        plan.stages,
+        vec![
+            Stage {
+                filters: 0..2,
+                materialize: true
+            },
+            Stage {
+                filters: 2..3,
+                materialize: true
+            },
+            Stage {
+                filters: 3..5,
+                materialize: false
+            },
+        ]
+    );
+
+    let mut expected = a.new_like();
+    filter::brightness(0.5)
+        .then(filter::invert())
+        .execute(&[&a], &mut expected);
+
+    let mut dest = a.new_like();
+    pipeline.execute(&[&a], &mut dest);
+    for (x, y) in dest.data().iter().zip(expected.data()) {
+        assert!((x - y).abs() < 1e-6);
+    }
+
+    let mut dest = a.new_like();
+    smol::block_on(pipeline.to_async(&[&a], &mut dest).execute());
+    for (x, y) in dest.data().iter().zip(expected.data()) {
+        assert!((x - y).abs() < 1e-6);
+    }
+
+    // An empty pipeline copies the input in both the sync and async paths
+    let empty = Pipeline::<f32, Gray>::new();
+    let mut dest = a.new_like();
+    empty.execute(&[&a], &mut dest);
+    assert_eq!(dest.data(), a.data());
+
+    let mut dest = a.new_like();
+    smol::block_on(empty.to_async(&[&a], &mut dest).execute());
+    assert_eq!(dest.data(), a.data());
+}
+
+#[test]
+fn test_fuse() {
+    let mut a = Image::<f32, Rgb>::new((8, 8));
+    a.for_each(|pt, mut px| {
+        px[0] = pt.x as f32 / 8.0;
+        px[1] = pt.y as f32 / 8.0;
+        px[2] = 0.5;
+    });
+
+    let mut expected = a.new_like();
+    filter::brightness(1.2)
+        .then(filter::contrast(1.1))
+        .then(filter::clamp())
+        .then(filter::gamma_log(None))
+        .execute(&[&a], &mut expected);
+
+    let mut dest = a.new_like();
+    let f = fuse!(
+        filter::Brightness(1.2),
+        filter::Contrast(1.1),
+        filter::Clamp,
+        filter::GammaLog(2.2)
+    );
+    f.eval(&[&a], &mut dest);
+    for (x, y) in dest.data().iter().zip(expected.data()) {
+        assert!((x - y).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_lut1d() {
+    let mut a = Image::<f32, Rgb>::new((8, 8));
+    a.for_each(|pt, mut px| {
+        px[0] = pt.x as f32 / 7.0;
+        px[1] = pt.y as f32 / 7.0;
+        px[2] = 0.25;
+    });
+
+    // Per-channel tables with linear interpolation
+    let lut = filter::Lut1d::new(vec![vec![1.0, 0.0], vec![0.0, 0.5, 1.0], vec![0.5]]);
+    let mut dest = a.new_like();
+    filter::lut1d(lut.clone()).eval(&[&a], &mut dest);
+    let px = dest.get_pixel((7, 2));
+    assert!(px[0].abs() < 1e-6);
+    assert!((px[1] - 2.0 / 7.0).abs() < 1e-6);
+    assert_eq!(px[2], 0.5);
+    assert_eq!(lut.lookup(0, 2.0), 0.0);
+
+    // A single table applies to every channel
+    let gray = filter::Lut1d::new(vec![vec![0.0, 1.0, 0.0]]);
+    assert!((gray.lookup(2, 0.25) - 0.5).abs() < 1e-12);
+
+    // Baked filters match the original
+    let f = fuse!(filter::Contrast(1.1), filter::Clamp, filter::GammaLog(2.2));
+    let baked = filter::Lut1d::from_filter::<Rgb>(&f, 1024);
+    let mut expected = a.new_like();
+    f.eval(&[&a], &mut expected);
+    filter::lut1d(baked).eval(&[&a], &mut dest);
+    for (x, y) in dest.data().iter().zip(expected.data()) {
+        assert!((x - y).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn test_srgb_transfer() {
+    assert_eq!(srgb_to_linear(0.0), 0.0);
+    assert!((srgb_to_linear(0.02) - 0.02 / 12.92).abs() < 1e-12);
+    assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-12);
+    for i in 0..=100 {
+        let x = i as f64 / 100.0;
+        assert!((linear_to_srgb(srgb_to_linear(x)) - x).abs() < 1e-9);
+    }
+
+    let mut px = Pixel::<Srgb>::new();
+    px.copy_from_slice([0.5, 0.02, 1.0]);
+    let rgb: Pixel<Rgb> = px.convert();
+    assert!((rgb[0] - 0.214041).abs() < 1e-6);
+
+    let xyz: Pixel<Xyz> = rgb.convert();
+    let back: Pixel<Srgb> = xyz.convert();
+    for c in 0..3 {
+        assert!((back[c] - px[c]).abs() < 1e-6);
+    }
+
+    // D65 white maps to Y = 1
+    let mut white = Pixel::<LinearRgb>::new();
+    white.fill(1.0);
+    let xyz: Pixel<Xyz> = white.convert();
+    assert!((xyz[1] - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_wide_gamut() {
+    // PQ and HLG round-trip, reference white encodes to the expected signal levels
+    for i in 0..=100 {
+        let x = i as f64 / 100.0;
+        assert!((pq_to_linear(linear_to_pq(x)) - x).abs() < 1e-6);
+        assert!((hlg_to_linear(linear_to_hlg(x)) - x).abs() < 1e-9);
+    }
+    assert!((linear_to_pq(1.0) - 0.5806).abs() < 1e-3);
+    assert!((linear_to_hlg(1.0) - 1.0).abs() < 1e-6);
+
+    let mut red = Pixel::<Rgb>::new();
+    red.copy_from_slice([1.0, 0.0, 0.0]);
+    for c in [
+        red.convert::<DisplayP3>().convert::<Rgb>(),
+        red.convert::<Rec2020>().convert::<Rgb>(),
+        red.convert::<Rec2100Pq>().convert::<Rgb>(),
+        red.convert::<Rec2100Hlg>().convert::<Rgb>(),
+    ] {
+        for i in 0..3 {
+            assert!((c[i] - red[i]).abs() < 1e-4);
+        }
+    }
+
+    // Fully saturated P3 green is outside of sRGB
+    let mut green = Pixel::<DisplayP3>::new();
+    green.copy_from_slice([0.0, 1.0, 0.0]);
+    let raw: Pixel<Srgb> = green.convert();
+    assert!(raw[0] < 0.0);
+
+    let clipped: Pixel<Srgb> = green.convert_mapped(GamutMapping::Clip);
+    assert_eq!(clipped[0], 0.0);
+    assert_eq!(clipped[1], 1.0);
+
+    let compressed: Pixel<Srgb> = green.convert_mapped(GamutMapping::Compress);
+    assert!(compressed.iter().all(|x| (0.0..=1.0).contains(x)));
+    assert!(compressed[1] > compressed[0] && compressed[1] > compressed[2]);
+    let y = |px: &Pixel<Rgb>| px[0] * 0.2126 + px[1] * 0.7152 + px[2] * 0.0722;
+    assert!((y(&compressed.convert()) - y(&green.convert())).abs() < 1e-4);
+
+    let mut image = Image::<f32, DisplayP3>::new((2, 2));
+    image.for_each(|_, mut px| px[1] = 1.0);
+    let dest: Image<u8, Srgb> = image.convert_mapped(GamutMapping::Compress);
+    assert!(dest.get_f((0, 0), 1) > 0.9);
+}
+
+#[test]
+fn test_gamut_map() {
+    use filter::GamutMap;
+
+    let mut green = Pixel::<Rec2020>::new();
+    green.copy_from_slice([0.0, 1.0, 0.0]);
+    let green: Pixel<Rgb> = green.convert();
+    assert!(!Gamut::Rec709.contains(&green));
+    assert!(Gamut::Rec2020.contains(&green));
+
+    let map = |method| GamutMap {
+        target_space: Gamut::Rec709,
+        method,
+    };
+    let y = |px: &Pixel<Rgb>| px[0] * 0.2126 + px[1] * 0.7152 + px[2] * 0.0722;
+
+    let gray = Pixel::<Rgb>::from_slice([0.5, 0.5, 0.5]);
+    for method in [
+        GamutMapping::Clip,
+        GamutMapping::Compress,
+        GamutMapping::Rolloff(0.8),
+        GamutMapping::Perceptual,
+        GamutMapping::Highlight([1.0, 0.0, 1.0]),
+    ] {
+        assert_eq!(map(method).map(&gray), gray);
+        let mapped = map(method).map(&green);
+        assert!(Gamut::Rec709.contains(&mapped));
+    }
+
+    let clipped = map(GamutMapping::Clip).map(&green);
+    assert_eq!(clipped[0], 0.0);
+
+    let rolled = map(GamutMapping::Rolloff(0.8)).map(&green);
+    assert!(rolled[1] > rolled[0] && rolled[1] > rolled[2]);
+    let bright = map(GamutMapping::Rolloff(0.8)).map(&Pixel::from_slice([4.0, 2.0, 1.0]));
+    assert!(bright.iter().all(|x| *x < 1.0));
+    assert!((bright[0] / bright[1] - 2.0).abs() < 1e-9);
+
+    // Compress keeps luminance, Perceptual keeps Oklab lightness and hue
+    let compressed = map(GamutMapping::Compress).map(&green);
+    assert!((y(&compressed) - y(&green)).abs() < 1e-4);
+
+    let compressed = map(GamutMapping::Perceptual).map(&green);
+    let lab: Pixel<OkLab> = compressed.convert();
+    let orig: Pixel<OkLab> = green.convert();
+    assert!((lab[0] - orig[0]).abs() < 1e-3);
+    assert!((lab[2].atan2(lab[1]) - orig[2].atan2(orig[1])).abs() < 1e-3);
+    assert!(y(&compressed) > 0.0);
+
+    let mut image = Image::<f32, Rec2020>::new((2, 1));
+    image.set_pixel((0, 0), &Pixel::from_slice([0.0, 1.0, 0.0]));
+    image.set_pixel((1, 0), &Pixel::from_slice([0.2, 0.2, 0.2]));
+    let proof: Image<f32, Rgb> = image.run(
+        filter::gamut_map(Gamut::Rec709, GamutMapping::Highlight([1.0, 0.0, 1.0])),
+        None,
+    );
+    assert_eq!(proof.get_pixel((0, 0)), Pixel::from_slice([1.0, 0.0, 1.0]));
+    assert!((proof.get_f((1, 0), 0) - 0.2).abs() < 1e-6);
+}
+
+#[test]
+fn test_colorspace_tag() {
+    assert_eq!(Colorspace::from_name("sRGB"), Some(Colorspace::SRGB));
+    assert_eq!(
+        Colorspace::from_name("lin_rec2020"),
+        Some(Colorspace::REC2020)
+    );
+    assert_eq!(Colorspace::from_name("ACEScg"), None);
+
+    // sRGB encoded values stored in a linear `Rgb` image
+    let mut encoded = Image::<f32, Rgb>::new((2, 1));
+    encoded.for_each(|_, mut px| px.as_slice_mut().fill(0.5));
+    assert_eq!(encoded.colorspace(), Some(Colorspace::LINEAR_SRGB));
+    let encoded = encoded.with_colorspace(Colorspace::SRGB);
+    assert_eq!(encoded.colorspace(), Some(Colorspace::SRGB));
+
+    let linear = encoded.to_colorspace(Colorspace::LINEAR_SRGB);
+    assert_eq!(linear.meta.colorspace, None);
+    assert!((linear.get_f((0, 0), 0) - srgb_to_linear(0.5)).abs() < 1e-6);
+
+    // Conversions interpret the values using the tag
+    let srgb: Image<f32, Srgb> = encoded.convert();
+    assert!((srgb.get_f((0, 0), 0) - 0.5).abs() < 1e-6);
+    assert_eq!(srgb.meta.colorspace, None);
+
+    // Converting the type only keeps the tag
+    let bytes: Image<u8, Rgb> = encoded.convert();
+    assert_eq!(bytes.colorspace(), Some(Colorspace::SRGB));
+    assert_eq!(bytes.get((0, 0))[0], 128);
+
+    // Primaries are converted along with the transfer function
+    let mut px = Pixel::<Rgb>::from_slice([0.0, 1.0, 0.0]);
+    Colorspace::LINEAR_SRGB.convert_pixel(&Colorspace::REC2020, &mut px);
+    let expected: Pixel<Rec2020> = Pixel::<Rgb>::from_slice([0.0, 1.0, 0.0]).convert();
+    for c in 0..3 {
+        assert!((px[c] - expected[c]).abs() < 1e-9);
+    }
+
+    // Mismatched inputs are converted to the colorspace of the first input
+    let mut dest = Image::<f32, Rgb>::new((2, 1));
+    dest.apply(filter::blend(), &[&linear, &encoded]);
+    assert!((dest.get_f((0, 0), 0) - srgb_to_linear(0.5)).abs() < 1e-6);
+
+    let options = ExecutionOptions::new().with_colorspace_mismatch(ColorspaceMismatch::Ignore);
+    dest.apply_with(&options, filter::blend(), &[&linear, &encoded]);
+    assert!((dest.get_f((0, 0), 0) - (srgb_to_linear(0.5) + 0.5) / 2.0).abs() < 1e-6);
+
+    let options = ExecutionOptions::new().with_colorspace_mismatch(ColorspaceMismatch::Error);
+    match dest.try_apply_with(&options, filter::blend(), &[&linear, &encoded]) {
+        Err(Error::ColorspaceMismatch { expected, found }) => {
+            assert_eq!(expected, Colorspace::LINEAR_SRGB);
+            assert_eq!(found, Colorspace::SRGB);
+        }
+        _ => panic!("expected a colorspace mismatch"),
+    }
+    assert!(dest
+        .try_apply_with(&options, filter::blend(), &[&linear, &linear])
+        .is_ok());
+}
+
+#[test]
+fn test_oklab() {
+    // Reference values from the Oklab specification
+    let mut white = Pixel::<Rgb>::new();
+    white.fill(1.0);
+    let lab: Pixel<OkLab> = white.convert();
+    assert!((lab[0] - 1.0).abs() < 1e-4 && lab[1].abs() < 1e-4 && lab[2].abs() < 1e-4);
+
+    let mut rgb = Pixel::<Rgb>::new();
+    rgb.copy_from_slice([0.8, 0.3, 0.1]);
+    let lch: Pixel<OkLch> = rgb.convert();
+    assert!((0.0..1.0).contains(&lch[2]));
+    let back: Pixel<Rgb> = lch.convert::<OkLab>().convert();
+    for c in 0..3 {
+        assert!((back[c] - rgb[c]).abs() < 1e-6);
+    }
+
+    // Desaturating in OkLch keeps hue and lightness
+    let mut px: Pixel<Rgb> = rgb.clone();
+    filter::Saturation(0.5, filter::HueModel::OkLch).apply(&mut px);
+    let out: Pixel<OkLch> = px.convert();
+    assert!((out[0] - lch[0]).abs() < 1e-6);
+    assert!((out[1] - lch[1] * 0.5).abs() < 1e-6);
+    assert!((out[2] - lch[2]).abs() < 1e-6);
+
+    let mut image = Image::<f32, Rgb>::new((2, 2));
+    image.for_each(|_, mut px| px.copy_from_slice([0.8, 0.3, 0.1]));
+    let dest: Image<f32, Rgb> =
+        image.run(filter::saturation_with(0.0, filter::HueModel::OkLch), None);
+    let px = dest.get_pixel((1, 1));
+    assert!((px[0] - px[1]).abs() < 1e-4 && (px[1] - px[2]).abs() < 1e-4);
+}
+
+#[test]
+fn test_hue_rotate_selective_color() {
+    let mut red = Pixel::<Rgb>::new();
+    red.copy_from_slice([1.0, 0.0, 0.0]);
+    let mut px = red.clone();
+    filter::HueRotate(120.0, filter::HueModel::Hsv).apply(&mut px);
+    assert!(px[0].abs() < 1e-6 && (px[1] - 1.0).abs() < 1e-6 && px[2].abs() < 1e-6);
+    filter::HueRotate(240.0, filter::HueModel::Hsv).apply(&mut px);
+    for c in 0..3 {
+        assert!((px[c] - red[c]).abs() < 1e-6);
+    }
+
+    let mut px = red.clone();
+    filter::HueRotate(360.0, filter::HueModel::OkLch).apply(&mut px);
+    for c in 0..3 {
+        assert!((px[c] - red[c]).abs() < 1e-6);
+    }
+
+    // Desaturate reds, leave blues and grays untouched
+    let selective = filter::SelectiveColor::new(0.0, 20.0)
+        .with_saturation(0.0)
+        .with_model(filter::HueModel::Hsv);
+    assert_eq!(selective.weight(350.0), 1.0);
+    assert_eq!(selective.weight(180.0), 0.0);
+    assert!(selective.weight(28.0) > 0.0 && selective.weight(28.0) < 1.0);
+
+    let mut image = Image::<f32, Rgb>::new((3, 1));
+    image.set_pixel((0, 0), &red);
+    image.set_f((1, 0), 2, 1.0);
+    image.set_f((2, 0), 0, 0.5);
+    image.set_f((2, 0), 1, 0.5);
+    image.set_f((2, 0), 2, 0.5);
+    let dest: Image<f32, Rgb> = image.run(filter::selective_color(selective), None);
+    let px = dest.get_pixel((0, 0));
+    assert!((px[0] - px[1]).abs() < 1e-6);
+    assert_eq!(dest.get_pixel((1, 0)), image.get_pixel((1, 0)));
+    assert_eq!(dest.get_pixel((2, 0)), image.get_pixel((2, 0)));
+}
+
+#[test]
+fn test_gradient_map() {
+    let duotone = filter::GradientMap::duotone([0.0, 0.0, 1.0], [1.0, 1.0, 0.0]);
+    assert_eq!(duotone.color_at(-1.0), [0.0, 0.0, 1.0]);
+    assert_eq!(duotone.color_at(0.5), [0.5, 0.5, 0.5]);
+    assert_eq!(duotone.color_at(2.0), [1.0, 1.0, 0.0]);
+
+    let viridis = filter::GradientMap::viridis();
+    let mut image = Image::<f32, Gray>::new((2, 1));
+    image.set_f((1, 0), 0, 1.0);
+    let dest: Image<u8, Srgb> = image.run(filter::gradient_map(viridis), None);
+    for (pt, expected) in [((0, 0), [0x44, 0x01, 0x54]), ((1, 0), [0xfd, 0xe7, 0x25])] {
+        for (a, b) in dest.get(pt).as_ref().iter().zip(expected) {
+            assert!((*a as i32 - b).abs() <= 1);
+        }
+    }
+
+    let turbo = filter::GradientMap::turbo();
+    assert_eq!(turbo.stops.len(), 10);
+    assert!(filter::GradientMap::inferno().color_at(0.0)[0] < 0.01);
+}
+
+#[test]
+fn test_chroma_key() {
+    let key = filter::ChromaKey::green_screen();
+    let mut image = Image::<f32, Srgb>::new((4, 1));
+    image.set_pixel((0, 0), &Pixel::from_slice([0.0f32, 0.69, 0.25]));
+    image.set_pixel((1, 0), &Pixel::from_slice([0.0f32, 0.5, 0.18]));
+    image.set_pixel((2, 0), &Pixel::from_slice([0.8f32, 0.2, 0.3]));
+    image.set_pixel((3, 0), &Pixel::from_slice([0.6f32, 0.75, 0.55]));
+
+    let dest: Image<f32, Rgba> = image.run(filter::chroma_key(key), None);
+    assert!(dest.get_f((0, 0), 3) < 1e-6);
+    assert!(dest.get_f((1, 0), 3) < 1e-6);
+    assert!((dest.get_f((2, 0), 3) - 1.0).abs() < 1e-6);
+
+    // Green spill on a light foreground pixel is reduced
+    let px = dest.get_pixel((3, 0));
+    assert!(px[3] > 0.0);
+    let spilled: Pixel<Rgb> = image.get_pixel((3, 0)).convert();
+    assert!(px[1] - px[0] < spilled[1] - spilled[0]);
+
+    let mut px = Pixel::<Rgb>::new();
+    px.copy_from_slice([0.2, 0.2, 0.2]);
+    let out = filter::ChromaKey::blue_screen().key_pixel(&px);
+    assert_eq!(out[3], 1.0);
+    for c in 0..3 {
+        assert!((out[c] - px[c]).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_metrics_diff() {
+    let a = Image::<f32, Rgb>::new((4, 4));
+    let mut b = Image::<u8, Rgb>::new((4, 4));
+    assert!(metrics::diff(&a, &b, &metrics::DiffOptions::new())
+        .unwrap()
+        .is_match());
+
+    b.set_pixel((1, 2), &Pixel::from(vec![0.0, 0.02, 0.0]));
+    b.set_pixel((3, 3), &Pixel::from(vec![0.5, 0.0, 0.0]));
+
+    let options = metrics::DiffOptions::new().with_tolerance(0.05);
+    let d = metrics::diff(&a, &b, &options).unwrap();
+    assert_eq!(d.mismatched_pixels, 1);
+    assert!(!d.is_match());
+    assert!((d.max_error[0] - 0.5).abs() < 1.0 / 255.0);
+    assert!(d.psnr.is_finite());
+    assert_eq!(d.heat_map.get_pixel((1, 2)), Pixel::new());
+    assert!(d.heat_map.get_pixel((3, 3))[0] > 0.0);
+
+    // Only the red channel is allowed to differ
+    let options = metrics::DiffOptions::new()
+        .with_channel_tolerance(&[1.0, 0.0])
+        .with_amplify(10.0);
+    let d = metrics::diff(&a, &b, &options).unwrap();
+    assert_eq!(d.mismatched_pixels, 1);
+    assert!(d.heat_map.get_pixel((1, 2))[0] > 0.5);
+    assert_eq!(d.heat_map.get_pixel((3, 3)), Pixel::new());
+
+    assert!(metrics::diff(&a, &Image::<f32, Rgb>::new((2, 2)), &options).is_err());
+}
+
+#[test]
+fn test_assert_images_match() {
+    let a = Image::<f32, Gray>::new((4, 4));
+    let mut b = Image::<f32, Gray>::new((4, 4));
+    assert_images_match!(a, b);
+
+    b.set_f((2, 2), 0, 0.01);
+    assert_images_match!(a, b, 0.01);
+
+    let err = testing::images_match(&a, &b, 0.001, "mismatch").unwrap_err();
+    assert!(err.starts_with("mismatch: 1 of 16 pixels"));
+    assert!(testing::images_match(&a, &Image::<f32, Gray>::new((2, 2)), 1.0, "size").is_err());
+}
+
+#[test]
+fn test_generators() {
+    let a: Image<u8, Rgb> = testing::arbitrary_image_with_seed(8..16, 1);
+    let b: Image<u8, Rgb> = testing::arbitrary_image_with_seed(8..16, 1);
+    assert!(a == b);
+    assert!((8..16).contains(&a.width()) && (8..16).contains(&a.height()));
+    let c: Image<f32, Gray> = testing::arbitrary_image(1..4);
+    assert!(c.width() >= 1 && c.width() < 4);
+
+    let g: Image<f32, Rgba> = testing::gradient((5, 2));
+    assert_eq!(g.get_pixel((0, 1))[0], 0.0);
+    assert_eq!(g.get_pixel((2, 0))[1], 0.5);
+    assert_eq!(g.get_pixel((4, 0))[3], 1.0);
+
+    let cb: Image<u8, Gray> = testing::checkerboard((4, 4), 2);
+    assert_eq!(cb.get((0, 0))[0], 0);
+    assert_eq!(cb.get((2, 0))[0], 255);
+    assert_eq!(cb.get((2, 2))[0], 0);
+
+    // The center of a zone plate is white and the rings get closer together towards the edge
+    let zp: Image<f32, Gray> = testing::zone_plate((64, 64));
+    assert!(zp.get_pixel((32, 32))[0] > 0.99);
+    let crossings = |x0: usize, x1: usize| {
+        (x0..x1)
+            .filter(|&x| (zp.get_pixel((x, 32))[0] > 0.5) != (zp.get_pixel((x + 1, 32))[0] > 0.5))
+            .count()
+    };
+    assert!(crossings(48, 63) > crossings(32, 47));
+}
+
+#[test]
+fn test_bench_filter() {
+    let sizes = [Size::new(16, 16), Size::new(32, 8)];
+    let results = bench::bench_filter_for::<f32, Rgb, f32, Rgb>(
+        &filter::Invert,
+        &sizes,
+        std::time::Duration::ZERO,
+    );
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[1].size, sizes[1]);
+    assert!(results
+        .iter()
+        .all(|r| r.iterations >= 3 && r.mpix_per_sec > 0.0));
+}
+
+#[test]
+fn test_geometry_filter() {
+    let image: Image<f32, Gray> = testing::gradient((8, 4));
+
+    let cropped: Image<f32, Gray> =
+        image.run_geometry(filter::crop(Region::new(Point::new(2, 1), Size::new(3, 2))));
+    assert_eq!(cropped.size(), Size::new(3, 2));
+    assert_eq!(cropped.get_pixel((0, 0)), image.get_pixel((2, 1)));
+
+    let resized: Image<f32, Gray> =
+        image.run_geometry(filter::resize(image.size(), Size::new(7, 3)));
+    assert_eq!(resized.size(), Size::new(7, 3));
+
+    let scaled: Image<f32, Gray> = image.run_geometry(filter::scale(2.0, 0.5));
+    assert_eq!(scaled.size(), Size::new(16, 2));
+
+    let padded: Image<f32, Gray> = image.run_geometry(filter::pad(1, 2, 3, 4));
+    assert_eq!(padded.size(), Size::new(12, 10));
+    assert_eq!(padded.get_pixel((0, 0))[0], 0.0);
+    assert_eq!(padded.get_pixel((8, 5)), image.get_pixel((7, 3)));
+
+    let rotated: Image<f32, Gray> = image.run_geometry(filter::rotate_expand(90.0));
+    assert_eq!(rotated.size(), Size::new(4, 8));
+    assert_eq!(rotated.get_pixel((3, 0)), image.get_pixel((0, 0)));
+    assert_eq!(rotated.get_pixel((0, 7)), image.get_pixel((7, 3)));
+
+    let rotated = GeometryFilter::<f32, Gray>::output_size(
+        &filter::rotate_expand::<f32, Gray, f32, Gray>(45.0),
+        Size::new(10, 10),
+    );
+    assert_eq!(rotated, Size::new(15, 15));
+}
+
+#[test]
+fn test_georeference() {
+    let geo = Georeference::from_geotiff(
+        &[30.0, 30.0, 0.0],
+        &[0.0, 0.0, 0.0, 440720.0, 3751320.0, 0.0],
+        &[],
+    )
+    .unwrap();
+    assert_eq!(geo.transform, [440720.0, 30.0, 0.0, 3751320.0, 0.0, -30.0]);
+    assert_eq!(geo.pixel_to_model(2.0, 1.0), (440780.0, 3751290.0));
+    assert_eq!(
+        Georeference::from_geotiff(&[], &[], &geo.transformation()),
+        Some(geo.clone())
+    );
+
+    let mut image = Image::<f32, Gray>::new((100, 50));
+    image.meta.georeference = Some(geo.clone());
+
+    // Model coordinates of the same location must not change after cropping or resizing
+    let cropped = image.crop(Region::new(Point::new(10, 20), Size::new(40, 20)));
+    let cropped_geo = cropped.meta.georeference.as_ref().unwrap();
+    assert_eq!(
+        cropped_geo.pixel_to_model(0.0, 0.0),
+        geo.pixel_to_model(10.0, 20.0)
+    );
+    assert!(cropped_geo.is_axis_aligned());
+
+    let resized = cropped.resize((20, 5));
+    let resized_geo = resized.meta.georeference.unwrap();
+    assert_eq!(resized_geo.transform[1], 60.0);
+    assert_eq!(resized_geo.transform[5], -120.0);
+    assert_eq!(
+        resized_geo.pixel_to_model(20.0, 5.0),
+        geo.pixel_to_model(50.0, 40.0)
+    );
+    assert_eq!(resized_geo.tie_point()[3..5], cropped_geo.tie_point()[3..5]);
+}
+
+#[test]
+fn test_window_level() {
+    let mut image = Image::<u16, Gray>::new((3, 1));
+    image.data.data_mut().copy_from_slice(&[0, 1064, 4000]);
+
+    let window = filter::WindowLevel::new(40.0, 80.0).with_rescale(1.0, -1024.0);
+    let dest: Image<f32, Gray> = image.run(filter::window_level(window), None);
+    assert_eq!(dest.data.data(), &[0.0, 0.5, 1.0]);
+
+    // Float input is windowed in its own `0..=1` range
+    let dest: Image<f32, Gray> = dest.run(
+        filter::window_level(filter::WindowLevel::new(0.5, 0.5)),
+        None,
+    );
+    assert_eq!(dest.data.data()[1], 0.5);
+}
+
+#[test]
+fn test_halftone() {
+    let mut image = Image::<f32, Rgb>::new((128, 128));
+    image.for_each(|_, mut px| {
+        px[0] = 0.25;
+        px[1] = 0.5;
+        px[2] = 0.9;
+    });
+
+    for (screen, lpi) in [
+        (filter::Screen::Dot, 30.0),
+        (filter::Screen::Line, 30.0),
+        (filter::Screen::BlueNoise, 300.0),
+    ] {
+        // Axis-aligned screens alias with the pixel grid, so avoid the 0 degree yellow screen
+        let halftone = filter::Halftone::cmy(screen, 300.0, lpi).with_angles(&[15.0, 75.0, 45.0]);
+        let dest: Image<f32, Rgb> = image.run(filter::halftone(halftone), None);
+        for (c, expected) in [0.25, 0.5, 0.9].into_iter().enumerate() {
+            let mut mean = 0.0;
+            dest.each_pixel(|_, px| {
+                assert!(px[c] == 0.0 || px[c] == 1.0);
+                mean += px[c];
+            });
+            mean /= 128.0 * 128.0;
+            assert!(
+                (mean - expected).abs() < 0.03,
+                "{:?} {} {}",
+                screen,
+                c,
+                mean
+            );
+        }
+    }
+
+    // Every blue noise threshold is used exactly once per tile
+    let halftone = filter::Halftone::new(filter::Screen::BlueNoise, 1.0, 1.0).with_angles(&[0.0]);
+    let mut thresholds: Vec<f64> = (0..64 * 64)
+        .map(|i| halftone.threshold(Point::new(i % 64, i / 64), 0))
+        .collect();
+    thresholds.sort_by(f64::total_cmp);
+    thresholds.dedup();
+    assert_eq!(thresholds.len(), 64 * 64);
+}
+
+#[test]
+fn test_pixel_art() {
+    // 4x4 sprite with a diagonal edge
+    let mut sprite = Image::<u8, Rgb>::new((4, 4));
+    sprite.for_each(|pt, mut px| {
+        let v = if pt.x + pt.y < 4 { 255 } else { 0 };
+        px.copy_from_slice([v, v / 2, 0]);
+    });
+    assert_eq!(sprite.detect_pixel_scale(), 1);
+
+    let mut nearest = Image::<u8, Rgb>::new((12, 12));
+    nearest.for_each(|pt, mut px| px.copy_from_slice(sprite.get((pt.x / 3, pt.y / 3))));
+    assert_eq!(nearest.detect_pixel_scale(), 3);
+
+    let downscaled = nearest.downscale_integer(3).unwrap();
+    assert_eq!(downscaled.data(), sprite.data());
+    assert!(nearest.downscale_integer(5).is_err());
+
+    let xbr = sprite.upscale_xbr(2);
+    assert_eq!(xbr.size(), Size::new(8, 8));
+
+    // Flat areas and the inner corners of blocks are unchanged
+    assert_eq!(xbr.get((0, 0)), sprite.get((0, 0)));
+    assert_eq!(xbr.get((7, 7)), sprite.get((3, 3)));
+    assert_eq!(xbr.get((2, 2)), sprite.get((1, 1)));
+
+    // The staircase along the diagonal is smoothed
+    let corner = xbr.get((5, 3));
+    assert!(corner[0] > 0 && corner[0] < 255);
+    assert_eq!(xbr.get((4, 2)), sprite.get((2, 1)));
+
+    // Straight edges stay sharp
+    let mut bar = Image::<u8, Gray>::new((4, 4));
+    bar.for_each(|pt, mut px| px[0] = if pt.x < 2 { 255 } else { 0 });
+    let upscaled = bar.upscale_xbr(3);
+    assert_eq!(upscaled.detect_pixel_scale() % 3, 0);
+    assert_eq!(upscaled.downscale_integer(3).unwrap().data(), bar.data());
+}
+
+#[test]
+fn test_layers() {
+    let names: Vec<String> = [
+        "A",
+        "B",
+        "G",
+        "R",
+        "diffuse.B",
+        "diffuse.G",
+        "diffuse.R",
+        "N.x.Z",
+        "depth.Z",
+    ]
+    .iter()
+    .map(|x| x.to_string())
+    .collect();
+    let layers = io::group_layers(&names);
+    assert_eq!(layers.len(), 4);
+    assert_eq!(layers[0].name, "");
+    assert_eq!(layers[0].channels, ["R", "G", "B", "A"]);
+    assert_eq!(layers[1].name, "diffuse");
+    assert_eq!(layers[1].channels, ["diffuse.R", "diffuse.G", "diffuse.B"]);
+    assert_eq!(layers[2].name, "N.x");
+    assert_eq!(layers[3].channels, ["depth.Z"]);
+
+    // Without an I/O backend reading returns an error instead of panicking
+    #[cfg(all(not(feature = "magick"), not(feature = "oiio")))]
+    {
+        assert!(io::layers("images/A.exr").is_err());
+        assert!(Image::<f32, Rgb>::open_layer("images/A.exr", "diffuse").is_err());
+    }
+}
+
+#[test]
+fn test_guided() {
+    let mut image: Image<f32, Gray> = testing::random_image((32, 16), 7);
+    image.each_pixel_mut(|pt, mut px| {
+        let step = if pt.x < 16 { 0.2 } else { 0.8 };
+        px[0] = step + (px[0] - 0.5) * 0.05;
+    });
+
+    let filter = filter::guided(3, 0.01);
+    let smooth: Image<f32, Gray> = image.run(filter, None);
+
+    // Noise is removed from flat areas while the edge stays sharp
+    let var = |img: &Image<f32, Gray>| {
+        let values: Vec<f64> = (2..12).map(|x| img.get_f((x, 8), 0)).collect();
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64
+    };
+    assert!(var(&smooth) < var(&image) / 4.0);
+    assert!(smooth.get_f((15, 8), 0) < 0.3);
+    assert!(smooth.get_f((16, 8), 0) > 0.7);
+
+    // Per-pixel evaluation matches the whole image fast path
+    let mut px = Image::<f32, Gray>::new((1, 1));
+    let input = [&image];
+    Filter::<f32, Gray>::compute_at(
+        &filter::guided::<f32, Gray, f32, Gray>(3, 0.01),
+        Point::new(5, 5),
+        &Input::new(&input),
+        &mut px.get_mut((0, 0)),
+    );
+    assert!((px.get_f((0, 0), 0) - smooth.get_f((5, 5), 0)).abs() < 1e-5);
+}
+
+#[test]
+fn test_film_grain() {
+    let mut plate = Image::<f32, Rgb>::new((96, 96));
+    plate.each_pixel_mut(|_, mut px| {
+        for c in 0..3 {
+            px[c] = 0.5;
+        }
+    });
+
+    let grain = filter::FilmGrain::new(1.0, 0.05, 0.3).with_seed(3);
+    let grainy: Image<f32, Rgb> = plate.run(filter::film_grain(grain), None);
+    assert!(grainy != plate);
+
+    // Grain is deterministic and fades out in the highlights
+    let again: Image<f32, Rgb> = plate.run(filter::film_grain(grain), None);
+    assert!(grainy == again);
+    let white = Image::<f32, Rgb>::new((8, 8)).run::<f32, Rgb>(filter::invert(), None);
+    let white_grain: Image<f32, Rgb> = white.run(filter::film_grain(grain), None);
+    assert!(white_grain == white);
+
+    let estimate = filter::FilmGrain::estimate(&grainy);
+    assert!((estimate.size - grain.size).abs() <= 0.5, "{:?}", estimate);
+    assert!(
+        (estimate.intensity - grain.intensity).abs() < 0.015,
+        "{:?}",
+        estimate
+    );
+    assert!(
+        (estimate.chroma - grain.chroma).abs() < 0.15,
+        "{:?}",
+        estimate
+    );
+}
+
+#[test]
+fn test_temporal() {
+    use temporal::TemporalFilter;
+
+    // A bright square moving one pixel per frame over a static background
+    let frames: Vec<Image<f32, Gray>> = (0..6)
+        .map(|i| {
+            let mut frame = testing::random_image((24, 24), i);
+            frame.each_pixel_mut(|pt, mut px| {
+                px[0] = 0.25 + (px[0] - 0.5) * 0.1;
+                if (8 + i as usize..12 + i as usize).contains(&pt.x) && (8..12).contains(&pt.y) {
+                    px[0] = 1.0;
+                }
+            });
+            frame
+        })
+        .collect();
+
+    let masks: Vec<Image<f32, Gray>> = temporal::RunningAverage::new(0.1, 0.3)
+        .frames(frames.clone())
+        .collect();
+    assert_eq!(masks.len(), 6);
+    assert_eq!(masks[5].get_f((16, 10), 0), 1.0);
+    assert_eq!(masks[5].get_f((2, 2), 0), 0.0);
+
+    // The moving square is removed by the median
+    let median = temporal::TemporalMedian::new(5)
+        .frames(frames.clone())
+        .last()
+        .unwrap();
+    assert!(median.get_f((10, 10), 0) < 0.5);
+
+    // Denoising reduces noise in the background and keeps the moving square in place
+    let denoised = temporal::TemporalDenoise::new(4, 0.05)
+        .frames(frames.clone())
+        .last()
+        .unwrap();
+    assert!(denoised.get_f((14, 10), 0) > 0.9);
+    let noise = |img: &Image<f32, Gray>| {
+        (0..8)
+            .map(|x| (img.get_f((x, 20), 0) - 0.25).abs())
+            .sum::<f64>()
+    };
+    assert!(noise(&denoised) < noise(&frames[5]));
+
+    let mut window = temporal::FrameWindow::new(2);
+    for frame in &frames {
+        window.push(frame);
+    }
+    assert!(window.is_full());
+    assert!(window.latest().unwrap() == &frames[5]);
+}
+
+#[test]
+fn test_auto_levels() {
+    // Low contrast image with a blue cast
+    let mut image: Image<f32, Rgb> = testing::gradient((64, 8));
+    image.each_pixel_mut(|_, mut px| {
+        px[0] = 0.3 + px[0] * 0.3;
+        px[1] = 0.3 + px[1] * 0.3;
+        px[2] = 0.4 + px[2] * 0.4;
+    });
+
+    let levels: Image<f32, Rgb> = image.run(filter::auto_levels(0.5), None);
+    for c in 0..3 {
+        assert!(levels.get_f((0, 0), c) < 0.02);
+        assert!(levels.get_f((63, 0), c) > 0.98);
+    }
+
+    let contrast: Image<f32, Rgb> = image.run(filter::auto_contrast(0.0), None);
+    assert!(contrast.get_f((0, 0), 0) < 0.01);
+    assert!(contrast.get_f((63, 0), 2) > 0.99);
+    assert!(contrast.get_f((63, 0), 0) < contrast.get_f((63, 0), 2));
+
+    let mut cast = image.clone();
+    cast.each_pixel_mut(|_, mut px| px[2] = px[2].powf(0.5));
+    let color: Image<f32, Rgb> = cast.run(filter::auto_color(0.0), None);
+    let mean = color.reduce(reduce::Mean);
+    assert!((mean[0] - mean[2]).abs() < 0.05, "{:?}", mean);
+
+    let hist = image.reduce(reduce::Histograms::default());
+    assert!((hist[0].percentile(50.0) - 0.45).abs() < 0.01);
+}
+
+#[test]
+fn test_auto_exposure() {
+    // Dark HDR image with a few very bright highlights
+    let mut image = Image::<f32, Rgb>::new((32, 32));
+    image.each_pixel_mut(|pt, px| {
+        let v = if pt.x == 0 { 1000.0 } else { 0.01 };
+        px.iter_mut().for_each(|x| *x = v);
+    });
+
+    let filter = filter::AutoExposure::new(0.18, 5.0);
+    assert!((filter.gain(&image) - 18.0).abs() < 0.01);
+
+    let exposed: Image<f32, Rgb> = image.run(filter::auto_exposure(0.18, 5.0), None);
+    assert!((exposed.get_f((16, 16), 1) - 0.18).abs() < 1e-3);
+
+    // Without clipping the highlights raise the key and lower the gain
+    let unclipped = filter::AutoExposure::new(0.18, 0.0).gain(&image);
+    assert!(unclipped < 15.0);
+
+    // Chains before tone mapping
+    let mut rgba = Image::<f32, Rgba>::new((4, 4));
+    rgba.each_pixel_mut(|_, mut px| {
+        px[0] = 0.5;
+        px[1] = 0.5;
+        px[2] = 0.5;
+        px[3] = 1.0;
+    });
+    let out: Image<f32, Rgba> =
+        rgba.run(filter::auto_exposure(0.18, 0.0).then(filter::clamp()), None);
+    assert!((out.get_f((0, 0), 1) - 0.18).abs() < 1e-3);
+    assert_eq!(out.get_f((0, 0), 3), 1.0);
+}
+
+#[test]
+fn test_patch_match_fill() {
+    // Vertical stripes with a hole in the middle
+    let mut image = Image::<f32, Gray>::new((64, 64));
+    image.each_pixel_mut(|pt, mut px| px[0] = ((pt.x / 4) % 2) as f64);
+    let original = image.clone();
+
+    let mut mask = Image::<f32, Gray>::new((64, 64));
+    mask.each_pixel_mut(|pt, mut px| {
+        if (24..40).contains(&pt.x) && (24..40).contains(&pt.y) {
+            px[0] = 1.0;
+            image.set_f(pt, 0, 0.5);
+        }
+    });
+
+    let filled: Image<f32, Gray> = image.run(filter::patch_match_fill(mask.clone()), None);
+    assert_eq!(filled.get_f((2, 2), 0), image.get_f((2, 2), 0));
+
+    // The stripes are continued through the hole
+    let mut error = 0.0;
+    for y in 24..40 {
+        for x in 24..40 {
+            error += (filled.get_f((x, y), 0) - original.get_f((x, y), 0)).abs();
+        }
+    }
+    assert!(error / 256.0 < 0.2, "{}", error / 256.0);
+}
+
+#[test]
+fn test_clone_stamp() {
+    let mut image = Image::<f32, Rgb>::new((64, 64));
+    image.each_pixel_mut(|pt, mut px| {
+        px[0] = if pt.x < 32 { 0.2 } else { 0.8 };
+        px[1] = 0.5;
+        px[2] = ((pt.x + pt.y) % 2) as f64 * 0.1;
+    });
+
+    let mut cloned = image.clone();
+    cloned.clone_stamp((16, 32), (48, 32), 6.0, 2.0);
+    assert_eq!(cloned.get_pixel((48, 32)), image.get_pixel((16, 32)));
+    assert_eq!(cloned.get_pixel((48, 40)), image.get_pixel((48, 40)));
+
+    // Feathered edge is a blend of source and destination
+    let edge = cloned.get_f((53, 32), 0);
+    assert!(edge > 0.2 && edge < 0.8);
+
+    // Healing keeps the source texture but matches the surrounding brightness
+    let mut healed = image.clone();
+    healed.heal_stamp((16, 32), (48, 32), 6.0, 2.0);
+    assert!((healed.get_f((48, 32), 0) - 0.8).abs() < 1e-4);
+    assert!((healed.get_f((48, 32), 2) - image.get_f((16, 32), 2)).abs() < 1e-4);
+}
+
+#[test]
+fn test_mask() {
+    let a = Mask::from_region((32, 32), Region::new(Point::new(4, 4), Size::new(8, 8)));
+    let b = Mask::from_region((32, 32), Region::new(Point::new(8, 8), Size::new(8, 8)));
+    assert!(a.union(&b).unwrap().contains((14, 14)));
+    assert!(!a.intersect(&b).unwrap().contains((5, 5)));
+    assert!(a.intersect(&b).unwrap().contains((9, 9)));
+    assert!(a.subtract(&b).unwrap().contains((5, 5)));
+    assert!(!a.subtract(&b).unwrap().contains((9, 9)));
+    assert!(a.invert().contains((0, 0)));
+    assert!(a.union(&Mask::new((16, 16))).is_err());
+
+    assert_eq!(
+        a.grow(2).bounds(),
+        Some(Region::new(Point::new(2, 2), Size::new(12, 12)))
+    );
+    assert_eq!(
+        a.shrink(2).bounds(),
+        Some(Region::new(Point::new(6, 6), Size::new(4, 4)))
+    );
+    assert!(a.shrink(4).is_empty());
+
+    let feathered = a.feather(3.0);
+    assert!(feathered.get((4, 8)) > 0.2 && feathered.get((4, 8)) < 0.8);
+    assert_eq!(feathered.get((8, 8)), 1.0);
+
+    let image: Image<f32, Gray> = feathered.to_image();
+    assert_eq!(Mask::from_image(&image), feathered);
+
+    // Filter is only applied inside of the mask
+    let input = Image::<f32, Gray>::new((32, 32));
+    let mut output = input.clone();
+    filter::invert().eval_masked(&feathered, &[&input], &mut output);
+    assert_eq!(output.get_f((0, 0), 0), 0.0);
+    assert_eq!(output.get_f((8, 8), 0), 1.0);
+    assert!((output.get_f((4, 8), 0) - feathered.get((4, 8))).abs() < 1e-6);
+}
+
+#[test]
+fn test_document() {
+    use document::{BlendMode, Document, Group, Layer};
+
+    let mut red = Image::<f32, Rgba>::new((4, 4));
+    red.each_pixel_mut(|_, mut px| {
+        px[0] = 1.0;
+        px[3] = 1.0;
+    });
+    let mut gray = Image::<f32, Rgb>::new((2, 2));
+    gray.each_pixel_mut(|_, px| {
+        px.map(|_| 0.5);
+    });
+
+    let mut doc = Document::new((6, 6));
+    doc.push(Layer::new("background", &red));
+
+    let mut group = Group::new("group");
+    group.push(
+        Layer::new("multiply", &gray)
+            .with_offset(1, 1)
+            .with_blend_mode(BlendMode::Multiply),
+    );
+    group.push(
+        Layer::new("faded", &gray)
+            .with_offset(3, 3)
+            .with_opacity(0.5),
+    );
+    doc.push(group);
+
+    let mut mask = Mask::new((4, 4));
+    mask.set((0, 0), 1.0);
+    doc.push(
+        Layer::new("masked", &gray)
+            .with_offset(-1, 0)
+            .with_mask(mask),
+    );
+
+    let flat = doc.flatten();
+    assert_eq!(flat.get_pixel((0, 0)).as_ref(), &[1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(flat.get_pixel((5, 5)).as_ref(), &[0.0, 0.0, 0.0, 0.0]);
+
+    // Multiply within a group is isolated, it only sees the layers in the group
+    assert_eq!(flat.get_pixel((1, 1)).as_ref(), &[0.5, 0.5, 0.5, 1.0]);
+
+    // Half transparent layer over red
+    assert_eq!(flat.get_pixel((3, 3)).as_ref(), &[0.75, 0.25, 0.25, 1.0]);
+
+    // Partially outside of the document, partially masked
+    assert_eq!(flat.get_pixel((1, 0)).as_ref(), &[1.0, 0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn test_gradient_generate() {
+    use generate::{gradient, Gradient};
+
+    let black = Pixel::<Rgb>::from(vec![0.0, 0.0, 0.0]);
+    let white = Pixel::<Rgb>::from(vec![1.0, 1.0, 1.0]);
+    let red = Pixel::<Rgb>::from(vec![1.0, 0.0, 0.0]);
+
+    let linear = Gradient::linear((0.0, 0.0), (100.0, 0.0), black.clone(), white.clone())
+        .with_stop(0.5, red.clone());
+    let image: Image<f32, Rgb> = gradient(&linear, (100, 10));
+    assert!(image.get_f((0, 5), 0) < 0.02);
+    assert!((image.get_f((50, 5), 0) - 1.0).abs() < 0.02);
+    assert!(image.get_f((50, 5), 1) < 0.02);
+    assert!(image.get_f((99, 5), 1) > 0.98);
+
+    let radial = Gradient::radial((32.0, 32.0), 32.0, white.clone(), black.clone());
+    let image: Image<f32, Rgb> = gradient(&radial, (64, 64));
+    assert!(image.get_f((32, 32), 0) > 0.95);
+    assert_eq!(image.get_f((0, 0), 0), 0.0);
+
+    let conic = Gradient::conic((32.0, 32.0), 0.0, black.clone(), white.clone());
+    let image: Image<f32, Rgb> = gradient(&conic, (64, 64));
+    assert!(image.get_f((63, 32), 0) < 0.05);
+    assert!((image.get_f((32, 63), 0) - 0.25).abs() < 0.05);
+
+    // Dithered u8 output averages to the exact value
+    let gray = Pixel::<Rgb>::from(vec![0.3, 0.3, 0.3]);
+    let flat = Gradient::linear((0.0, 0.0), (1.0, 0.0), gray.clone(), gray);
+    let dithered: Image<u8, Rgb> = gradient(&flat, (64, 64));
+    let mean =
+        dithered.data().iter().map(|x| *x as f64).sum::<f64>() / dithered.data().len() as f64;
+    assert!((mean - 0.3 * 255.0).abs() < 0.1, "{}", mean);
+    assert!(dithered.data().iter().any(|x| *x != dithered.data()[0]));
+    let banded: Image<u8, Rgb> = gradient(&flat.with_dither(false), (64, 64));
+    assert!(banded.data().iter().all(|x| *x == banded.data()[0]));
+}
+
+#[test]
+fn test_rows_columns() {
+    let mut image = testing::gradient::<f32, Rgb>((8, 4));
+    assert_eq!(image.rows().count(), 4);
+    for (y, row) in image.rows() {
+        assert_eq!(row.len(), 8 * 3);
+        assert_eq!(row[3], image.get((1, y))[0]);
+    }
+
+    for (y, row) in image.rows_mut() {
+        row[0] = y as f32;
+    }
+    assert_eq!(image.get_f((0, 3), 0), 3.0);
+
+    #[cfg(feature = "parallel")]
+    {
+        image.par_rows_mut().for_each(|(y, row)| row[1] = y as f32);
+        assert_eq!(image.par_rows().count(), 4);
+        assert_eq!(image.get_f((0, 2), 1), 2.0);
+    }
+
+    let columns: Vec<(usize, Vec<&[f32]>)> =
+        image.columns().map(|(x, col)| (x, col.collect())).collect();
+    assert_eq!(columns.len(), 8);
+    assert_eq!(columns[5].1.len(), 4);
+    assert_eq!(columns[5].1[2], image.get((5, 2)).as_slice());
+}
+
+#[test]
+fn test_bytes() {
+    let mut image = Image::<u8, Rgba>::new((2, 2));
+    image.set((1, 0), [1, 2, 3, 4]);
+    assert_eq!(&image.as_bytes()[4..8], &[1, 2, 3, 4]);
+    assert_eq!(image.as_pixels::<[u8; 4]>().unwrap()[1], [1, 2, 3, 4]);
+    assert!(image.as_pixels::<[u8; 3]>().is_err());
+    image.as_pixels_mut::<u32>().unwrap()[3] = u32::from_ne_bytes([5, 6, 7, 8]);
+    assert_eq!(image.get((1, 1)).as_slice(), &[5, 6, 7, 8]);
+
+    let mut gray = Image::<u16, Gray>::new((3, 1));
+    gray.set((0, 0), [0x0102]);
+    assert_eq!(&gray.to_be_bytes()[..2], &[1, 2]);
+    assert_eq!(&gray.to_le_bytes()[..2], &[2, 1]);
+    assert_eq!(&gray.as_bytes()[..2], &0x0102u16.to_ne_bytes());
+
+    let be = Image::<u16, Gray>::from_be_bytes((3, 1), &gray.to_be_bytes()).unwrap();
+    assert!(be == gray);
+    let le = Image::<u16, Gray>::from_le_bytes((3, 1), &gray.to_le_bytes()).unwrap();
+    assert!(le == gray);
+
+    // Unaligned input is copied
+    let mut buf = vec![0u8; 7];
+    buf[1..].copy_from_slice(gray.as_bytes());
+    assert!(Image::<u16, Gray>::from_bytes((3, 1), &buf[1..]).unwrap() == gray);
+    assert!(Image::<u16, Gray>::from_bytes((3, 1), &buf).is_err());
+}
+
+#[test]
+fn test_bgr() {
+    let mut rgba = Image::<u8, Rgba>::new((4, 2));
+    rgba.set((1, 1), [10, 20, 30, 128]);
+
+    let bgra: Image<u8, Bgra> = rgba.reorder_channels(&[2, 1, 0, 3]);
+    assert_eq!(bgra.get((1, 1)).as_slice(), &[30, 20, 10, 128]);
+    assert!(bgra.clone().into_rgba() == rgba);
+    assert!(rgba.clone().into_bgra() == bgra);
+
+    // Color conversion matches the swizzle for opaque pixels
+    let rgb = testing::gradient::<f32, Rgb>((8, 1));
+    let bgr: Image<f32, Bgr> = rgb.convert();
+    assert!(bgr == rgb.clone().into_bgr());
+    assert!(bgr.convert::<f32, Rgb>() == rgb);
+
+    let gray: Image<u8, Gray> = rgba.reorder_channels(&[3]);
+    assert_eq!(gray.get((1, 1))[0], 128);
+}
+
+#[test]
+fn test_yuv_frames() {
+    use yuv::{Encoding, Format, Matrix, Range};
+
+    let mut image = Image::<u8, Rgb>::new((17, 9));
+    image.each_pixel_mut(|pt, mut px| {
+        px[0] = 0.2 + pt.x as f64 / 64.0;
+        px[1] = 0.5;
+        px[2] = 0.3 + pt.y as f64 / 32.0;
+    });
+
+    for format in [Format::I420, Format::Nv12, Format::Yuyv] {
+        for encoding in [
+            Encoding::default(),
+            Encoding::new(Matrix::Bt601, Range::Full),
+        ] {
+            let data = yuv::encode(&image, format, encoding);
+            assert_eq!(data.len(), format.frame_len(image.size()));
+            let decoded = yuv::decode(&data, image.size(), format, encoding).unwrap();
+            let max = decoded
+                .data()
+                .iter()
+                .zip(image.data())
+                .map(|(a, b)| (*a as i32 - *b as i32).abs())
+                .max()
+                .unwrap();
+            assert!(max <= 6, "{:?} {:?}: {}", format, encoding, max);
+        }
+    }
+
+    let mut white = Image::<u8, Rgb>::new((2, 2));
+    white.data_mut().fill(255);
+    let data = yuv::encode(&white, Format::I420, Encoding::default());
+    assert_eq!(data, vec![235, 235, 235, 235, 128, 128]);
+    assert!(yuv::decode(&data[..5], (2, 2), Format::I420, Encoding::default()).is_err());
+}
+
+#[test]
+fn test_gaussian_blur_sigma() {
+    let image: Image<f32, Gray> = testing::random_image((96, 64), 3);
+    let boxes: Image<f32, Gray> = testing::checkerboard((96, 64), 24);
+    let at = |image: &Image<f32, Gray>, sigma: f64, pt: Point| {
+        let mut px = Image::<f32, Gray>::new((1, 1));
+        let input = [image];
+        Filter::<f32, Gray>::compute_at(
+            &filter::GaussianBlur(sigma),
+            pt,
+            &Input::new(&input),
+            &mut px.get_mut((0, 0)),
+        );
+        px.get_f((0, 0), 0)
+    };
+
+    // Separable passes match the direct 2D kernel
+    let small: Image<f32, Gray> = image.run(filter::gaussian_blur(2.0), None);
+    for pt in [Point::new(0, 0), Point::new(40, 30), Point::new(95, 63)] {
+        assert!((small.get_f(pt, 0) - at(&image, 2.0, pt)).abs() < 1e-5);
+    }
+    assert_eq!(filter::GaussianBlur(2.0).radius(), 6);
+
+    // Downsampled approximation stays close to the exact blur
+    let large: Image<f32, Gray> = boxes.run(filter::gaussian_blur(12.0), None);
+    for pt in [Point::new(48, 32), Point::new(20, 50), Point::new(70, 10)] {
+        assert!((large.get_f(pt, 0) - at(&boxes, 12.0, pt)).abs() < 0.02);
+    }
+    assert!(large.get_f((36, 12), 0) > 0.6 && large.get_f((60, 12), 0) < 0.4);
+
+    let same: Image<f32, Gray> = image.run(filter::gaussian_blur(0.0), None);
+    assert!(same == image);
+}
+
+#[test]
+fn test_fast_blur() {
+    let image: Image<u8, Rgb> = testing::checkerboard((64, 48), 5);
+    let at = |filter: &dyn Filter<u8, Rgb>, pt: Point| {
+        let mut px = Image::<u8, Rgb>::new((1, 1));
+        let input = [&image];
+        filter.compute_at(pt, &Input::new(&input), &mut px.get_mut((0, 0)));
+        px.get_pixel((0, 0))
+    };
+
+    // Running sums match the direct kernel away from the edges
+    let stack: Image<u8, Rgb> = image.run(filter::stack_blur(6), None);
+    let boxed: Image<u8, Rgb> = image.run(filter::box_blur(3), None);
+    for pt in [Point::new(20, 20), Point::new(33, 17), Point::new(40, 30)] {
+        let a = at(&filter::StackBlur(6), pt);
+        let b = at(&filter::BoxBlur::new(3), pt);
+        for c in 0..3 {
+            assert!((stack.get_f(pt, c) - a[c]).abs() <= 1.0 / 255.0);
+            assert!((boxed.get_f(pt, c) - b[c]).abs() <= 1.0 / 255.0);
+        }
+    }
+
+    // Both flatten the checkerboard towards its mean
+    for blurred in [&stack, &boxed] {
+        let v = blurred.get_f((32, 24), 0);
+        assert!(v > 0.3 && v < 0.7, "{}", v);
+    }
+
+    let same: Image<u8, Rgb> = image.run(filter::stack_blur(0), None);
+    assert!(same == image);
+}
+
+#[test]
+fn test_lens_blur() {
+    use filter::{Bokeh, Focus, LensBlur};
+
+    // Single bright pixel in a dark background, the depth map puts the left half in focus
+    let mut image = Image::<f32, Gray>::new((48, 24));
+    image.set_f((36, 12), 0, 1.0);
+    image.set_f((12, 12), 0, 1.0);
+    let mut depth = Image::<f32, Gray>::new((48, 24));
+    depth.each_pixel_mut(|pt, mut px| px[0] = if pt.x < 24 { 0.0 } else { 1.0 });
+
+    let filter = LensBlur::new(
+        Focus::DepthMap {
+            input: 1,
+            focus: 0.0,
+            range: 1.0,
+        },
+        5.0,
+    );
+    let mut dest = image.new_like();
+    dest.apply(filter, &[&image, &depth]);
+
+    // In focus pixel is unchanged, the background one becomes a disk
+    assert!((dest.get_f((12, 12), 0) - 1.0).abs() < 1e-6);
+    assert_eq!(dest.get_f((14, 12), 0), 0.0);
+    let center = dest.get_f((36, 12), 0);
+    assert!(center > 0.0 && center < 0.1);
+    assert!((dest.get_f((39, 12), 0) - center).abs() < 1e-6);
+    assert_eq!(dest.get_f((36, 19), 0), 0.0);
+
+    // Highlights are boosted and polygon bokeh leaves the corners of the disk empty
+    let boosted = filter.with_highlights(0.5, 10.0);
+    let mut bright = image.new_like();
+    bright.apply(boosted, &[&image, &depth]);
+    assert!(bright.get_f((36, 12), 0) > center);
+
+    let square = filter.with_bokeh(Bokeh::Polygon(4));
+    let mut poly = image.new_like();
+    poly.apply(square, &[&image, &depth]);
+    assert!(poly.get_f((40, 12), 0) > 0.0);
+    assert_eq!(poly.get_f((39, 15), 0), 0.0);
+
+    // Tilt-shift keeps the center rows sharp
+    let image: Image<f32, Gray> = testing::checkerboard((32, 64), 2);
+    let tilted: Image<f32, Gray> = image.run(filter::tilt_shift(0.5, 0.2, 4.0), None);
+    assert_eq!(tilted.get_f((10, 32), 0), image.get_f((10, 32), 0));
+    let edge = tilted.get_f((10, 2), 0);
+    assert!(edge > 0.2 && edge < 0.8, "{}", edge);
+}
+
+#[test]
+fn test_wavelet() {
+    use wavelet::Wavelet;
+
+    // Perfect reconstruction, including odd sizes
+    let image: Image<f32, Rgb> = testing::random_image((37, 22), 5);
+    for w in [Wavelet::Haar, Wavelet::Daubechies2, Wavelet::Daubechies4] {
+        let channels = wavelet::forward(&image, w, 3);
+        assert_eq!(channels[0].details.len(), 3);
+        let restored: Image<f32, Rgb> = wavelet::inverse(image.size(), &channels).unwrap();
+        for (a, b) in image.data().iter().zip(restored.data()) {
+            assert!((a - b).abs() < 1e-5, "{:?}", w);
+        }
+    }
+
+    // Denoising a smooth gradient brings it closer to the clean image
+    let mut clean = Image::<f32, Gray>::new((64, 64));
+    clean.for_each(|pt, mut px| px[0] = (pt.x + pt.y) as f32 / 128.0);
+    let noise: Image<f32, Gray> = testing::random_image((64, 64), 9);
+    let mut noisy = clean.clone();
+    noisy
+        .data_mut()
+        .iter_mut()
+        .zip(noise.data())
+        .for_each(|(x, n)| *x += (n - 0.5) * 0.2);
+
+    let error = |img: &Image<f32, Gray>| {
+        img.data()
+            .iter()
+            .zip(clean.data())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f32>()
+    };
+    let auto = WaveletDenoise::new(None, 3).denoise(&noisy);
+    assert!(error(&auto) < error(&noisy) * 0.5);
+    let fixed: Image<f32, Gray> = noisy.run(wavelet_denoise(Some(0.05), 3), None);
+    assert!(error(&fixed) < error(&noisy));
+}
+
+#[test]
+fn test_phase_correlation() {
+    let noise: Image<f32, Gray> = testing::random_image((96, 80), 4);
+    let texture: Image<f32, Gray> = noise.run(Kernel::gaussian_3x3(), None);
+    let shifted = |dx: f64, dy: f64| {
+        let mut image = Image::<f32, Rgb>::new((64, 48));
+        image.for_each(|pt, mut px| {
+            let (x, y) = (pt.x as f64 + 16.0 - dx, pt.y as f64 + 16.0 - dy);
+            let v = texture.sample(x, y, transform::Interpolation::Bilinear)[0] as f32;
+            px[0] = v;
+            px[1] = v;
+            px[2] = v;
+        });
+        image
+    };
+    let a = shifted(0.0, 0.0);
+
+    let (dx, dy, confidence) = metrics::phase_correlation(&a, &a).unwrap();
+    assert!(dx.abs() < 1e-6 && dy.abs() < 1e-6);
+    assert!(confidence > 0.9, "{}", confidence);
+
+    let (dx, dy, _) = metrics::phase_correlation(&a, &shifted(5.0, -3.0)).unwrap();
+    assert!(
+        (dx - 5.0).abs() < 0.1 && (dy + 3.0).abs() < 0.1,
+        "{} {}",
+        dx,
+        dy
+    );
+
+    let (dx, dy, _) = metrics::phase_correlation(&a, &shifted(-2.5, 1.3)).unwrap();
+    assert!(
+        (dx + 2.5).abs() < 0.25 && (dy - 1.3).abs() < 0.25,
+        "{} {}",
+        dx,
+        dy
+    );
+
+    let noise: Image<f32, Rgb> = testing::random_image((64, 48), 1);
+    let (_, _, low) = metrics::phase_correlation(&a, &noise).unwrap();
+    assert!(low < 0.5);
+
+    assert!(metrics::phase_correlation(&a, &Image::<f32, Rgb>::new((8, 8))).is_err());
+}
+
+#[test]
+fn test_calibrate() {
+    // A u16 sensor with a bias of 100, dark current of 50 per second, a vignetted flat field
+    // and one hot pixel
+    let size = Size::new(16, 12);
+    let vignette = |pt: Point| 1.0 - 0.02 * pt.x as f64;
+    let frame = |signal: f64, exposure: f64, hot: bool| {
+        let mut image = Image::<u16, Gray>::new(size);
+        image.for_each(|pt, mut px| {
+            let hot_pixel = if hot && pt == Point::new(5, 6) {
+                20000.0
+            } else {
+                0.0
+            };
+            px[0] = (100.0 + 50.0 * exposure + signal * vignette(pt) + hot_pixel) as u16;
+        });
+        image
+    };
+
+    let bias =
+        calibrate::combine_median(&[frame(0.0, 0.0, false), frame(0.0, 0.0, false)]).unwrap();
+    let dark = frame(0.0, 10.0, true);
+    let flat = frame(30000.0, 0.0, false);
+    let hot = calibrate::detect_hot_pixels(&dark, 5.0);
+    assert_eq!(hot, vec![Point::new(5, 6)]);
+
+    let calibration = calibrate::Calibration::new()
+        .with_bias(&bias)
+        .with_dark(&dark, 10.0)
+        .with_flat(&flat)
+        .with_hot_pixels(hot);
+    let images = calibration
+        .apply_all(&[frame(1000.0, 20.0, true)], 20.0)
+        .unwrap();
+
+    // The result is flat, including where the hot pixel was
+    let expected = images[0].get_f((8, 6), 0);
+    for (i, x) in images[0].data().iter().enumerate() {
+        assert!((*x as f64 - expected).abs() * 65535.0 < 2.0, "{} {}", i, x);
+    }
+
+    // Subtracting a brighter frame goes negative instead of wrapping around
+    let under = calibrate::subtract_bias(&frame(0.0, 0.0, false), &flat).unwrap();
+    assert!(under.get_f((0, 0), 0) < 0.0);
+    assert!(calibrate::apply_flat_field(&flat, &Image::<u16, Gray>::new((3, 3))).is_err());
+
+    // The middle values are averaged for an even number of frames
+    let constant = |x: f32| {
+        let mut image = Image::<f32, Gray>::new((2, 2));
+        image.for_each(|_, mut px| px[0] = x);
+        image
+    };
+    let master = calibrate::combine_median(&[constant(0.0), constant(0.25), constant(0.75)]);
+    assert_eq!(master.unwrap().get_f((1, 1), 0), 0.25);
+    let frames = [constant(0.0), constant(0.25), constant(0.75), constant(1.0)];
+    let master = calibrate::combine_median(&frames).unwrap();
+    assert_eq!(master.get_f((1, 1), 0), 0.5);
+}
+
+#[test]
+fn test_integer_types() {
+    // Normalized values cover the full range of signed and 32-bit types
+    assert_eq!(i16::MIN.to_norm(), 0.0);
+    assert_eq!(i16::MAX.to_norm(), 1.0);
+    assert_eq!(i16::from_norm(0.0), i16::MIN);
+    assert_eq!(i32::from_norm(1.0), i32::MAX);
+    assert_eq!(u32::from_norm(1.0), u32::MAX);
+    assert_eq!(i16::MIN.convert::<u16>(), 0);
+    assert_eq!(0u8.convert::<i16>(), i16::MIN);
+
+    // Every value survives a roundtrip through normalized floats
+    for x in [i16::MIN, -12345, -1, 0, 1, 12345, i16::MAX] {
+        assert_eq!(i16::from_norm(x.to_norm()), x);
+    }
+    for x in [i32::MIN, -123456789, -1, 0, 1, 123456789, i32::MAX] {
+        assert_eq!(i32::from_norm(x.to_norm()), x);
+    }
+    for x in [0, 1, 7, 123456789, u32::MAX - 1, u32::MAX] {
+        assert_eq!(u32::from_norm(x.to_norm()), x);
+    }
+
+    // Depth maps and label images convert to f64 and back losslessly
+    let mut labels: Image<i32, Gray> = Image::new((16, 16));
+    labels
+        .data_mut()
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = (i as i32).wrapping_mul(16_777_259));
+    let roundtrip: Image<i32, Gray> = labels.convert::<f64, Gray>().convert();
+    assert_eq!(roundtrip.data(), labels.data());
+
+    let mut depth: Image<i16, Gray> = Image::new((8, 8));
+    depth
+        .data_mut()
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = (i as i32 * 1000 - 32000) as i16);
+    let roundtrip: Image<i16, Gray> = depth.convert::<f32, Gray>().convert();
+    assert_eq!(roundtrip.data(), depth.data());
+
+    // Dithering doesn't push signed types past their minimum
+    let gray: Image<f32, Gray> = Image::new((8, 8));
+    let dithered: Image<i16, Gray> = gray.convert_dithered();
+    assert!(dithered.data().iter().all(|x| *x <= i16::MIN + 1));
+}
+
+#[test]
+fn test_convert_dithered() {
+    // A shallow gradient covers only a few 8-bit steps, dithering mixes neighboring levels so
+    // that the average of each column follows the gradient
+    let mut image = Image::<f32, Rgba>::new((64, 32));
+    image.for_each(|pt, mut px| {
+        let v = 0.5 + pt.x as f32 / 64.0 * 4.0 / 255.0;
+        px[0] = v;
+        px[1] = v;
+        px[2] = v;
+        px[3] = 1.0;
+    });
+
+    let plain: Image<u8, Rgba> = image.convert();
+    let dithered: Image<u8, Rgba> = image.convert_dithered();
+    let column_error = |img: &Image<u8, Rgba>| {
+        (0..64)
+            .map(|x| {
+                let mean = (0..32).map(|y| img.get_f((x, y), 0)).sum::<f64>() / 32.0;
+                (mean - image.get_f((x, 0), 0)).abs()
+            })
+            .fold(0.0, f64::max)
+    };
+    assert!(column_error(&dithered) < column_error(&plain));
+    assert!(column_error(&dithered) < 0.5 / 255.0);
+    assert!(dithered.get_f((10, 10), 3) == 1.0);
+
+    // Noise of up to one step plus rounding keeps values within one and a half steps
+    for (a, b) in image.data().iter().zip(dithered.data()) {
+        assert!((*a as f64 * 255.0 - *b as f64).abs() <= 1.5);
+    }
+
+    // Only used for 8-bit formats when enabled
+    let options = io::EncodeOptions::new().with_dither(true);
+    let path = std::path::Path::new("out.jpg");
+    assert!(io::dither_for_output(path, &image, &options).is_some());
+    assert!(io::dither_for_output(std::path::Path::new("out.exr"), &image, &options).is_none());
+    assert!(io::dither_for_output(path, &plain, &options).is_none());
+    assert!(io::dither_for_output(path, &image, &io::EncodeOptions::new()).is_none());
+}
+
+#[test]
+fn test_dither_quantization() {
+    // Values are quantized before the integer conversion, a level only moves by the noise and
+    // the average stays on the level
+    let mut flat = Image::<f32, Gray>::new((32, 32));
+    flat.for_each(|_, mut px| px[0] = 100.0 / 255.0);
+    let dithered: Image<u8, Gray> = flat.convert_dithered();
+    assert!(dithered.data().iter().all(|x| (99..=101).contains(x)));
+    let mean = dithered.data().iter().map(|x| *x as f64).sum::<f64>() / 1024.0;
+    assert!((mean - 100.0).abs() < 0.25, "{}", mean);
+
+    // Black and white are never pushed out of range
+    let black: Image<u16, Gray> = Image::<f32, Gray>::new((8, 8)).convert_dithered();
+    assert!(black.data().iter().all(|x| *x <= 1));
+}
+
+#[test]
+fn test_ambient_color_regions() {
+    // Left half red, right half blue
+    let mut image = Image::<u8, Rgb>::new((1920, 1080));
+    image.for_each(|pt, mut px| {
+        px[0] = if pt.x < 960 { 255 } else { 0 };
+        px[2] = if pt.x < 960 { 0 } else { 255 };
+    });
+
+    let colors = image.ambient_color_regions((8, 4));
+    assert_eq!(colors.len(), 32);
+
+    // Cells away from the boundary keep their color, cells next to it are blended
+    let row = &colors[8..16];
+    assert!((row[0][0] - 1.0).abs() < 1e-9 && row[0][2] == 0.0);
+    assert!((row[7][2] - 1.0).abs() < 1e-9 && row[7][0] == 0.0);
+    assert!(row[3][0] > 0.5 && row[3][2] > 0.0);
+    assert!((row[3][0] - row[4][2]).abs() < 1e-9);
+
+    // Grids larger than the image still return one color per cell
+    let small = Image::<f32, Rgb>::new((3, 2));
+    assert_eq!(small.ambient_color_regions((5, 5)).len(), 25);
+    assert!(image.ambient_color_regions((0, 4)).is_empty());
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_run_with_thread_pool() {
+    #[derive(Debug)]
+    struct Threads;
+
+    impl Filter<f32, Gray> for Threads {
+        fn compute_at(&self, _pt: Point, _input: &Input<f32, Gray>, dest: &mut DataMut<f32, Gray>) {
+            dest[0] = rayon::current_num_threads() as f32;
+        }
+    }
+
+    let options = ExecutionOptions::new().with_threads(3).unwrap();
+    let mut image = Image::<f32, Gray>::new((16, 16));
+    let dest = image.run_with(&options, Threads, None);
+    assert!(dest.data().iter().all(|x| *x == 3.0));
+
+    image.run_in_place_with(&options, Threads);
+    assert!(image.data().iter().all(|x| *x == 3.0));
+}
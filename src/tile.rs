@@ -0,0 +1,188 @@
+use crate::*;
+
+/// Fixed-size image stored inline, a `Tile` doesn't allocate so it can be used as scratch space
+/// in tiled algorithms or kept on the stack. Each pixel reserves `Tile::MAX_CHANNELS` values, so
+/// colors with more channels aren't supported
+#[derive(Clone, Copy)]
+pub struct Tile<T: Type, C: Color, const W: usize, const H: usize> {
+    data: [[[T; 4]; W]; H],
+    _color: std::marker::PhantomData<C>,
+}
+
+impl<T: Type, C: Color, const W: usize, const H: usize> std::fmt::Debug for Tile<T, C, W, H> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tile")
+            .field("width", &W)
+            .field("height", &H)
+            .field("color", &C::NAME)
+            .field("type", &T::type_name())
+            .finish()
+    }
+}
+
+impl<T: Type, C: Color, const W: usize, const H: usize> Default for Tile<T, C, W, H> {
+    fn default() -> Self {
+        Tile::new()
+    }
+}
+
+impl<T: Type, C: Color, const W: usize, const H: usize> PartialEq for Tile<T, C, W, H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.data.iter().zip(other.data.iter()).all(|(a, b)| {
+            a.iter()
+                .zip(b.iter())
+                .all(|(a, b)| a[..C::CHANNELS] == b[..C::CHANNELS])
+        })
+    }
+}
+
+impl<T: Type, C: Color, const W: usize, const H: usize> Tile<T, C, W, H> {
+    /// Max number of channels
+    pub const MAX_CHANNELS: usize = 4;
+
+    /// Tile width
+    pub const WIDTH: usize = W;
+
+    /// Tile height
+    pub const HEIGHT: usize = H;
+
+    /// Create a new tile with every value set to zero
+    pub fn new() -> Self {
+        assert!(
+            C::CHANNELS <= Self::MAX_CHANNELS,
+            "Tile supports at most {} channels",
+            Self::MAX_CHANNELS
+        );
+        Tile {
+            data: [[[T::default(); 4]; W]; H],
+            _color: std::marker::PhantomData,
+        }
+    }
+
+    /// Copy a `W`x`H` region starting at `offs` from an image, pixels outside of the image are
+    /// set to zero
+    pub fn from_image(image: &Image<T, C>, offs: impl Into<Point>) -> Self {
+        let mut tile = Tile::new();
+        tile.copy_from_image(image, offs);
+        tile
+    }
+
+    /// Tile size
+    pub fn size(&self) -> Size {
+        Size::new(W, H)
+    }
+
+    /// Tile shape, `(width, height, channels)`
+    pub fn shape(&self) -> (usize, usize, Channel) {
+        (W, H, C::CHANNELS)
+    }
+
+    /// Returns true when the point is inside of the tile
+    pub fn in_bounds(&self, pt: impl Into<Point>) -> bool {
+        let pt = pt.into();
+        pt.x < W && pt.y < H
+    }
+
+    /// Get the data at a point
+    pub fn get(&self, pt: impl Into<Point>) -> Data<'_, T, C> {
+        let pt = pt.into();
+        Data::new(&self.data[pt.y][pt.x][..C::CHANNELS])
+    }
+
+    /// Get mutable data at a point
+    pub fn get_mut(&mut self, pt: impl Into<Point>) -> DataMut<'_, T, C> {
+        let pt = pt.into();
+        DataMut::new(&mut self.data[pt.y][pt.x][..C::CHANNELS])
+    }
+
+    /// Get a single normalized value
+    pub fn get_f(&self, pt: impl Into<Point>, c: Channel) -> f64 {
+        let pt = pt.into();
+        self.data[pt.y][pt.x][c].to_norm()
+    }
+
+    /// Set a single normalized value
+    pub fn set_f(&mut self, pt: impl Into<Point>, c: Channel, f: f64) {
+        let pt = pt.into();
+        self.data[pt.y][pt.x][c].set_from_norm(f);
+    }
+
+    /// Get a pixel
+    pub fn get_pixel(&self, pt: impl Into<Point>) -> Pixel<C> {
+        self.get(pt).to_pixel()
+    }
+
+    /// Set a pixel
+    pub fn set_pixel(&mut self, pt: impl Into<Point>, px: &Pixel<C>) {
+        px.convert_to_data(&mut self.get_mut(pt));
+    }
+
+    /// Iterate over each pixel applying `f` to every pixel
+    pub fn for_each(&mut self, mut f: impl FnMut(Point, DataMut<T, C>)) {
+        for (y, row) in self.data.iter_mut().enumerate() {
+            for (x, px) in row.iter_mut().enumerate() {
+                f(Point::new(x, y), DataMut::new(&mut px[..C::CHANNELS]));
+            }
+        }
+    }
+
+    /// Apply a point-wise filter to every pixel in-place
+    pub fn run_point_filter(&mut self, filter: &impl PointFilter<C>) {
+        let mut px = Pixel::new();
+        self.for_each(|_, mut data| {
+            px.copy_from_slice(&data);
+            filter.apply(&mut px);
+            px.convert_to_data(&mut data);
+        });
+    }
+
+    /// Evaluate `filter` for the `W`x`H` region of the output starting at `offs`, storing the
+    /// results in the tile. Filters that need a pre-pass over the whole input still compute it
+    /// from `input`
+    pub fn eval<A: Type, B: Color>(
+        &mut self,
+        filter: &impl Filter<A, B, T, C>,
+        input: &[&Image<A, B>],
+        offs: impl Into<Point>,
+    ) {
+        let offs = offs.into();
+        let input = Input::new(input);
+        self.for_each(|pt, mut data| {
+            filter.compute_at(Point::new(offs.x + pt.x, offs.y + pt.y), &input, &mut data);
+        });
+    }
+
+    /// Copy a `W`x`H` region starting at `offs` from an image, pixels outside of the image are
+    /// set to zero
+    pub fn copy_from_image(&mut self, image: &Image<T, C>, offs: impl Into<Point>) {
+        let offs = offs.into();
+        self.for_each(|pt, mut data| {
+            let src = Point::new(offs.x + pt.x, offs.y + pt.y);
+            if image.in_bounds(src) {
+                data.copy_from_slice(image.get(src));
+            } else {
+                data.as_slice_mut().fill(T::default());
+            }
+        });
+    }
+
+    /// Copy the tile into an image at `offs`, pixels outside of the image are skipped
+    pub fn copy_to_image(&self, image: &mut Image<T, C>, offs: impl Into<Point>) {
+        let offs = offs.into();
+        for y in 0..H {
+            for x in 0..W {
+                let dest = Point::new(offs.x + x, offs.y + y);
+                if image.in_bounds(dest) {
+                    image.set(dest, self.get((x, y)));
+                }
+            }
+        }
+    }
+
+    /// Convert to a heap-allocated image
+    pub fn to_image(&self) -> Image<T, C> {
+        let mut image = Image::new((W, H));
+        self.copy_to_image(&mut image, (0, 0));
+        image
+    }
+}
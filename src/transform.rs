@@ -1,3 +1,4 @@
+use crate::kernel::EdgeStrategy;
 use crate::*;
 
 type EPoint<T> = euclid::Point2D<T, f64>;
@@ -11,26 +12,969 @@ impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Transform {
     }
 
     fn output_size(&self, input: &Input<T, C>, _dest: &mut Image<U, D>) -> Size {
-        let rect = self.outer_transformed_rect(&euclid::Rect::new(
+        GeometryFilter::<T, C, U, D>::output_size(self, input.images()[0].size())
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, px: &mut DataMut<U, D>) {
+        sample(self, Transfer::Linear, pt, input).copy_to_slice(px);
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> GeometryFilter<T, C, U, D> for Transform {
+    /// The transform maps output points to input points, so the output size is the bounding box of
+    /// the input transformed by the inverse
+    fn output_size(&self, input_size: Size) -> Size {
+        let inverse = self.inverse().unwrap_or(*self);
+        let rect = inverse.outer_transformed_rect(&euclid::Rect::new(
             euclid::Point2D::new(0., 0.),
-            input.images()[0].size().to_f64(),
+            input_size.to_f64(),
         ));
-        rect.size.to_usize()
+        rect.size.round().to_usize()
+    }
+}
+
+/// Transfer function of the values being resampled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Transfer {
+    /// Values are resampled as stored
+    #[default]
+    Linear,
+
+    /// Values are sRGB encoded, they are decoded to linear light before being combined and
+    /// encoded again afterwards. Averaging encoded values darkens fine detail
+    Srgb,
+}
+
+impl Transfer {
+    /// Convert encoded values to linear light, the alpha channel is left unchanged
+    pub fn decode<C: Color>(&self, mut px: &mut Pixel<C>) {
+        if *self == Transfer::Srgb {
+            for c in (0..C::CHANNELS).filter(|c| C::ALPHA != Some(*c)) {
+                px[c] = srgb_to_linear(px[c]);
+            }
+        }
+    }
+
+    /// Convert linear light values back to the encoded representation, the alpha channel is
+    /// left unchanged
+    pub fn encode<C: Color>(&self, mut px: &mut Pixel<C>) {
+        if *self == Transfer::Srgb {
+            for c in (0..C::CHANNELS).filter(|c| C::ALPHA != Some(*c)) {
+                px[c] = linear_to_srgb(px[c]);
+            }
+        }
+    }
+}
+
+/// Sample `input` at `pt` mapped through `transform`, combining neighboring pixels using
+/// `transfer`
+pub(crate) fn sample<T: Type, C: Color>(
+    transform: &Transform,
+    transfer: Transfer,
+    pt: Point,
+    input: &Input<T, C>,
+) -> Pixel<C> {
+    let pt = EPoint::new(pt.x as f64, pt.y as f64);
+    let dest = transform.transform_point(pt);
+    let mut px1 = input.get_pixel((dest.x.floor() as usize, dest.y.floor() as usize), None);
+    let mut px2 = input.get_pixel((dest.x.ceil() as usize, dest.y.ceil() as usize), None);
+    transfer.decode(&mut px1);
+    transfer.decode(&mut px2);
+
+    let mut px = (px1 + &px2) / 2.;
+    transfer.encode(&mut px);
+    px
+}
+
+/// Applies a `Transform` using a configurable `Transfer`, use `Transfer::Srgb` to resample
+/// sRGB encoded images in linear light
+#[derive(Debug, Clone, Copy)]
+pub struct Resample {
+    /// Mapping from output to input coordinates
+    pub transform: Transform,
+
+    /// Transfer function of the input values
+    pub transfer: Transfer,
+}
+
+impl Resample {
+    /// Create a new `Resample` filter, values are resampled as stored
+    pub fn new(transform: Transform) -> Resample {
+        Resample {
+            transform,
+            transfer: Transfer::Linear,
+        }
+    }
+
+    /// Build filter with the given transfer function
+    pub fn with_transfer(mut self, transfer: Transfer) -> Resample {
+        self.transfer = transfer;
+        self
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Resample {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn output_size(&self, input: &Input<T, C>, _dest: &mut Image<U, D>) -> Size {
+        GeometryFilter::<T, C, U, D>::output_size(&self.transform, input.images()[0].size())
     }
 
     fn compute_at(&self, pt: Point, input: &Input<T, C>, px: &mut DataMut<U, D>) {
-        let pt = EPoint::new(pt.x as f64, pt.y as f64);
-        let dest = self.transform_point(pt);
-        let px1 = input.get_pixel((dest.x.floor() as usize, dest.y.floor() as usize), None);
-        let px2 = input.get_pixel((dest.x.ceil() as usize, dest.y.ceil() as usize), None);
+        sample(&self.transform, self.transfer, pt, input).convert_to_data(px);
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> GeometryFilter<T, C, U, D> for Resample {
+    fn output_size(&self, input_size: Size) -> Size {
+        GeometryFilter::<T, C, U, D>::output_size(&self.transform, input_size)
+    }
+}
+
+/// Upscaling algorithm used by `Upscale`, this can be implemented to plug in external
+/// upscalers, for example machine learning models
+pub trait Upscaler<T: Type, C: Color>: std::fmt::Debug + Sync {
+    /// Upscale `image` to `size`
+    fn upscale(&self, image: &Image<T, C>, size: Size) -> Image<T, C>;
+}
+
+/// Normalized, floating point pixel buffer used by the built-in upscalers
+struct Buffer {
+    width: usize,
+    height: usize,
+    channels: usize,
+    data: Vec<f64>,
+}
+
+impl Buffer {
+    fn from_image<T: Type, C: Color>(image: &Image<T, C>) -> Buffer {
+        Buffer {
+            width: image.width(),
+            height: image.height(),
+            channels: C::CHANNELS,
+            data: image.data().iter().map(Type::to_norm).collect(),
+        }
+    }
+
+    fn into_image<T: Type, C: Color>(self) -> Image<T, C> {
+        let mut image = Image::<T, C>::new((self.width, self.height));
+        image
+            .data_mut()
+            .iter_mut()
+            .zip(self.data)
+            .for_each(|(dest, x)| dest.set_from_norm(x));
+        image
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> &[f64] {
+        let i = (y * self.width + x) * self.channels;
+        &self.data[i..i + self.channels]
+    }
+
+    /// Separable Lanczos resampling, when downsampling the kernel is stretched to avoid aliasing
+    fn lanczos(&self, size: Size, a: usize) -> Buffer {
+        let tmp = self.resample_axis(size.width, a, true);
+        tmp.resample_axis(size.height, a, false)
+    }
+
+    fn resample_axis(&self, n: usize, a: usize, horizontal: bool) -> Buffer {
+        let (src_len, width, height) = if horizontal {
+            (self.width, n, self.height)
+        } else {
+            (self.height, self.width, n)
+        };
+        let channels = self.channels;
+        if src_len == 0 {
+            return Buffer {
+                width,
+                height,
+                channels,
+                data: vec![0.0; width * height * channels],
+            };
+        }
+
+        let scale = src_len as f64 / n as f64;
+        let stretch = scale.max(1.0);
+        let support = a as f64 * stretch;
+
+        // Precompute weights for each output coordinate
+        let weights: Vec<Vec<(usize, f64)>> = (0..n)
+            .map(|o| {
+                let center = (o as f64 + 0.5) * scale - 0.5;
+                let start = (center - support).floor() as isize;
+                let end = (center + support).ceil() as isize;
+                let mut w: Vec<(usize, f64)> = (start..=end)
+                    .map(|i| {
+                        let index = i.clamp(0, src_len as isize - 1) as usize;
+                        (index, lanczos(i as f64 - center, stretch, a as f64))
+                    })
+                    .filter(|(_, w)| *w != 0.0)
+                    .collect();
+                let total: f64 = w.iter().map(|(_, w)| w).sum();
+                if total != 0.0 {
+                    w.iter_mut().for_each(|(_, x)| *x /= total);
+                }
+                w
+            })
+            .collect();
+
+        let mut data = vec![0.0; width * height * channels];
+        for y in 0..height {
+            for x in 0..width {
+                let dest = &mut data[(y * width + x) * channels..][..channels];
+                let w = if horizontal { &weights[x] } else { &weights[y] };
+                for (i, w) in w {
+                    let src = if horizontal {
+                        self.pixel(*i, y)
+                    } else {
+                        self.pixel(x, *i)
+                    };
+                    dest.iter_mut().zip(src).for_each(|(d, s)| *d += s * w);
+                }
+            }
+        }
+
+        Buffer {
+            width,
+            height,
+            channels,
+            data,
+        }
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = x * std::f64::consts::PI;
+        x.sin() / x
+    }
+}
+
+fn lanczos(x: f64, stretch: f64, a: f64) -> f64 {
+    let x = x / stretch;
+    if x.abs() >= a {
+        0.0
+    } else {
+        sinc(x) * sinc(x / a)
+    }
+}
+
+/// Lanczos resampling
+#[derive(Debug, Clone, Copy)]
+pub struct Lanczos {
+    /// Kernel radius, typically 2 or 3
+    pub radius: usize,
+}
+
+impl Default for Lanczos {
+    fn default() -> Self {
+        Lanczos { radius: 3 }
+    }
+}
+
+impl<T: Type, C: Color> Upscaler<T, C> for Lanczos {
+    fn upscale(&self, image: &Image<T, C>, size: Size) -> Image<T, C> {
+        Buffer::from_image(image)
+            .lanczos(size, self.radius)
+            .into_image()
+    }
+}
+
+/// Lanczos upscaling refined using iterative back-projection: the upscaled image is repeatedly
+/// downscaled, compared against the input and corrected using the upscaled error
+#[derive(Debug, Clone, Copy)]
+pub struct BackProjection {
+    /// Lanczos kernel radius
+    pub radius: usize,
+
+    /// Number of refinement iterations
+    pub iterations: usize,
+}
+
+impl Default for BackProjection {
+    fn default() -> Self {
+        BackProjection {
+            radius: 3,
+            iterations: 10,
+        }
+    }
+}
+
+impl<T: Type, C: Color> Upscaler<T, C> for BackProjection {
+    fn upscale(&self, image: &Image<T, C>, size: Size) -> Image<T, C> {
+        let input = Buffer::from_image(image);
+        let mut output = input.lanczos(size, self.radius);
+        for _ in 0..self.iterations {
+            let mut error = output.lanczos(image.size(), self.radius);
+            error
+                .data
+                .iter_mut()
+                .zip(&input.data)
+                .for_each(|(e, x)| *e = x - *e);
+            let error = error.lanczos(size, self.radius);
+            output
+                .data
+                .iter_mut()
+                .zip(error.data)
+                .for_each(|(x, e)| *x += e);
+        }
+        output.into_image()
+    }
+}
+
+/// Edge-directed Scale2x (EPX) upscaling for pixel art, the image is doubled in size until it
+/// reaches the target size and the remainder is filled using nearest-neighbor sampling
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Scale2x;
+
+impl Scale2x {
+    fn scale2x(input: &Buffer) -> Buffer {
+        let (w, h, channels) = (input.width, input.height, input.channels);
+        let mut data = vec![0.0; w * h * 4 * channels];
+        let width = w * 2;
+        for y in 0..h {
+            for x in 0..w {
+                let p = input.pixel(x, y);
+                let a = input.pixel(x, y.saturating_sub(1));
+                let b = input.pixel((x + 1).min(w - 1), y);
+                let c = input.pixel(x.saturating_sub(1), y);
+                let d = input.pixel(x, (y + 1).min(h - 1));
+
+                let out = [
+                    if c == a && c != d && a != b { a } else { p },
+                    if a == b && a != c && b != d { b } else { p },
+                    if d == c && d != b && c != a { c } else { p },
+                    if b == d && b != a && d != c { d } else { p },
+                ];
+
+                for (i, px) in out.iter().enumerate() {
+                    let (ox, oy) = (x * 2 + i % 2, y * 2 + i / 2);
+                    data[(oy * width + ox) * channels..][..channels].copy_from_slice(px);
+                }
+            }
+        }
+
+        Buffer {
+            width,
+            height: h * 2,
+            channels,
+            data,
+        }
+    }
+
+    fn nearest(input: &Buffer, size: Size) -> Buffer {
+        let channels = input.channels;
+        if input.width == 0 || input.height == 0 {
+            return Buffer {
+                width: size.width,
+                height: size.height,
+                channels,
+                data: vec![0.0; size.width * size.height * channels],
+            };
+        }
+
+        let mut data = Vec::with_capacity(size.width * size.height * channels);
+        for y in 0..size.height {
+            let sy = y * input.height / size.height;
+            for x in 0..size.width {
+                let sx = x * input.width / size.width;
+                data.extend_from_slice(input.pixel(sx, sy));
+            }
+        }
+
+        Buffer {
+            width: size.width,
+            height: size.height,
+            channels,
+            data,
+        }
+    }
+}
+
+impl<T: Type, C: Color> Upscaler<T, C> for Scale2x {
+    fn upscale(&self, image: &Image<T, C>, size: Size) -> Image<T, C> {
+        let mut buffer = Buffer::from_image(image);
+        while buffer.width > 0
+            && buffer.height > 0
+            && buffer.width * 2 <= size.width
+            && buffer.height * 2 <= size.height
+        {
+            buffer = Self::scale2x(&buffer);
+        }
+
+        if buffer.width != size.width || buffer.height != size.height {
+            buffer = Self::nearest(&buffer, size);
+        }
+        buffer.into_image()
+    }
+}
+
+/// High-quality upscaling using a pluggable `Upscaler`
+#[derive(Debug)]
+pub struct Upscale<T: Type, C: Color> {
+    upscaler: Box<dyn Upscaler<T, C>>,
+}
+
+impl<T: Type, C: Color> Default for Upscale<T, C> {
+    fn default() -> Self {
+        Upscale::new(BackProjection::default())
+    }
+}
+
+impl<T: Type, C: Color> Upscale<T, C> {
+    /// Create a new `Upscale` using the given upscaler
+    pub fn new(upscaler: impl 'static + Upscaler<T, C>) -> Self {
+        Upscale {
+            upscaler: Box::new(upscaler),
+        }
+    }
+
+    /// Upscale `image` to `size`
+    pub fn to_size(&self, image: &Image<T, C>, size: impl Into<Size>) -> Image<T, C> {
+        self.upscaler.upscale(image, size.into())
+    }
+
+    /// Upscale `image` by a constant factor
+    pub fn by(&self, image: &Image<T, C>, factor: f64) -> Image<T, C> {
+        let size = Size::new(
+            (image.width() as f64 * factor).round() as usize,
+            (image.height() as f64 * factor).round() as usize,
+        );
+        self.to_size(image, size)
+    }
+}
+
+/// Interpolation method used by `Image::sample`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Interpolation {
+    /// Nearest neighbor
+    Nearest,
+
+    /// Linear interpolation between the four nearest pixels
+    #[default]
+    Bilinear,
+
+    /// Bicubic interpolation using the Catmull-Rom spline, this is sharp but can overshoot near
+    /// edges
+    CatmullRom,
+
+    /// Bicubic interpolation using the Mitchell-Netravali filter with `B = C = 1/3`, this is a
+    /// compromise between blurring and ringing
+    Mitchell,
+
+    /// Lanczos interpolation with the given radius, typically 2 or 3
+    Lanczos(usize),
 
-        ((px1 + &px2) / 2.).copy_to_slice(px);
+    /// Elliptical weighted average, the footprint of each output pixel is mapped into the input
+    /// using the derivatives of the transform and the pixels it covers are averaged using a
+    /// Gaussian. This avoids aliasing under strong minification and shear, see
+    /// `Image::sample_ewa`. When no derivatives are available the footprint is a single pixel
+    Ewa,
+}
+
+/// Largest EWA footprint radius in input pixels, larger footprints are shrunk to keep the cost of
+/// each sample bounded
+const EWA_MAX_RADIUS: f64 = 32.0;
+
+/// Cubic filter from Mitchell and Netravali, "Reconstruction Filters in Computer Graphics"
+fn cubic(x: f64, b: f64, c: f64) -> f64 {
+    let x = x.abs();
+    let y = if x < 1.0 {
+        (12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + 6.0
+            - 2.0 * b
+    } else if x < 2.0 {
+        (-b - 6.0 * c) * x * x * x
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + 8.0 * b
+            + 24.0 * c
+    } else {
+        0.0
+    };
+    y / 6.0
+}
+
+impl Interpolation {
+    /// Number of pixels on each side of the sample position that contribute to the result
+    pub fn radius(&self) -> usize {
+        match self {
+            Interpolation::Nearest | Interpolation::Bilinear => 1,
+            Interpolation::CatmullRom | Interpolation::Mitchell | Interpolation::Ewa => 2,
+            Interpolation::Lanczos(a) => (*a).max(1),
+        }
+    }
+
+    /// Filter weight at distance `x` from the sample position
+    pub fn weight(&self, x: f64) -> f64 {
+        match self {
+            Interpolation::Nearest => {
+                if (-0.5..0.5).contains(&x) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Interpolation::Bilinear => (1.0 - x.abs()).max(0.0),
+            Interpolation::CatmullRom => cubic(x, 0.0, 0.5),
+            Interpolation::Mitchell => cubic(x, 1.0 / 3.0, 1.0 / 3.0),
+            Interpolation::Lanczos(a) => lanczos(x, 1.0, (*a).max(1) as f64),
+            // Separable form of the EWA Gaussian for a single pixel footprint
+            Interpolation::Ewa => {
+                if x * x < 2.0 {
+                    (-x * x).exp()
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Source indices and normalized weights along one axis, indices are clamped to the edge
+    fn taps(&self, x: f64, len: usize) -> Vec<(usize, f64)> {
+        self.edge_taps(x, len, &EdgeStrategy::Extend)
+            .into_iter()
+            .map(|(i, w)| (i.unwrap_or_default(), w))
+            .collect()
+    }
+
+    /// Source indices and normalized weights along one axis, indices are mapped into the image
+    /// using `edge`, taps outside of the image are `None` when using `EdgeStrategy::Constant`
+    fn edge_taps(&self, x: f64, len: usize, edge: &EdgeStrategy) -> Vec<(Option<usize>, f64)> {
+        let radius = self.radius() as isize;
+        let base = x.floor() as isize;
+        let mut taps: Vec<(Option<usize>, f64)> = (base - radius + 1..=base + radius)
+            .map(|i| {
+                let w = self.weight(x - i as f64);
+                (edge.map_point(i, len as isize - 1), w)
+            })
+            .filter(|(_, w)| *w != 0.0)
+            .collect();
+        let total: f64 = taps.iter().map(|(_, w)| w).sum();
+        if total != 0.0 {
+            taps.iter_mut().for_each(|(_, w)| *w /= total);
+        }
+        taps
+    }
+}
+
+/// Derivatives of the input position with respect to the output position as
+/// `[[du/dx, du/dy], [dv/dx, dv/dy]]`, estimated using finite differences of `map`. Backward
+/// differences are used at the right and bottom edges of `bounds`
+fn jacobian(map: impl Fn(Point) -> (f64, f64), pt: Point, bounds: Size) -> [[f64; 2]; 2] {
+    let diff = |a: Point, b: Point| {
+        let (p, q) = (map(a), map(b));
+        (p.0 - q.0, p.1 - q.1)
+    };
+    let dx = if pt.x + 1 < bounds.width {
+        diff(Point::new(pt.x + 1, pt.y), pt)
+    } else if pt.x > 0 {
+        diff(pt, Point::new(pt.x - 1, pt.y))
+    } else {
+        (1.0, 0.0)
+    };
+    let dy = if pt.y + 1 < bounds.height {
+        diff(Point::new(pt.x, pt.y + 1), pt)
+    } else if pt.y > 0 {
+        diff(pt, Point::new(pt.x, pt.y - 1))
+    } else {
+        (0.0, 1.0)
+    };
+    [[dx.0, dy.0], [dx.1, dy.1]]
+}
+
+/// Filter that maps each output point to a position in the input image, the input is sampled
+/// using `Image::sample` so positions outside of the image are clamped to the nearest edge
+///
+/// ```rust
+/// use image2::{transform::Warp, *};
+///
+/// let image = Image::<f32, Rgb>::new((64, 64));
+/// let (cx, cy) = (32.0, 32.0);
+/// let swirl = Warp::new(move |pt: Point| {
+///     let (x, y) = (pt.x as f64 - cx, pt.y as f64 - cy);
+///     let angle = (x * x + y * y).sqrt() / 32.0;
+///     let (sin, cos) = angle.sin_cos();
+///     (cx + x * cos - y * sin, cy + x * sin + y * cos)
+/// });
+/// let dest: Image<f32, Rgb> = image.run(swirl, None);
+/// ```
+pub struct Warp<F> {
+    map: F,
+    interpolation: Interpolation,
+}
+
+impl<F: Sync + Fn(Point) -> (f64, f64)> Warp<F> {
+    /// Create a new `Warp` using bilinear interpolation, `map` returns the input position for
+    /// each output point
+    pub fn new(map: F) -> Warp<F> {
+        Warp {
+            map,
+            interpolation: Interpolation::default(),
+        }
+    }
+
+    /// Build filter using the given interpolation method
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Warp<F> {
+        self.interpolation = interpolation;
+        self
+    }
+}
+
+impl<F> std::fmt::Debug for Warp<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Warp")
+            .field("interpolation", &self.interpolation)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color, F: Sync + Fn(Point) -> (f64, f64)> Filter<T, C, U, D>
+    for Warp<F>
+{
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let (x, y) = (self.map)(pt);
+        let image = input.images()[0];
+        let px = if self.interpolation == Interpolation::Ewa {
+            let j = jacobian(&self.map, pt, Size::new(usize::MAX, usize::MAX));
+            image.sample_ewa(x, y, j)
+        } else {
+            image.sample(x, y, self.interpolation)
+        };
+        px.convert_to_data(dest);
+    }
+}
+
+/// Filter that offsets each output point by the first two channels of a displacement map, the
+/// input is sampled at `(x + dx * scale, y + dy * scale)`
+pub struct DisplacementMap<M: Color> {
+    map: Image<f32, M>,
+    scale: f64,
+    interpolation: Interpolation,
+}
+
+impl<M: Color> DisplacementMap<M> {
+    /// Create a new `DisplacementMap` from a map storing horizontal and vertical offsets in pixels
+    /// in its first two channels, the map must have at least two channels
+    pub fn new(map: Image<f32, M>) -> Result<DisplacementMap<M>, Error> {
+        if M::CHANNELS < 2 {
+            return Err(Error::UnsupportedColor {
+                requested: "2 channels".into(),
+                found: M::NAME.into(),
+            });
+        }
+
+        Ok(DisplacementMap {
+            map,
+            scale: 1.0,
+            interpolation: Interpolation::default(),
+        })
+    }
+
+    /// Build filter with offsets multiplied by `scale`
+    pub fn with_scale(mut self, scale: f64) -> DisplacementMap<M> {
+        self.scale = scale;
+        self
+    }
+
+    /// Build filter using the given interpolation method
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> DisplacementMap<M> {
+        self.interpolation = interpolation;
+        self
+    }
+}
+
+impl<M: Color> std::fmt::Debug for DisplacementMap<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DisplacementMap")
+            .field("size", &self.map.size())
+            .field("scale", &self.scale)
+            .field("interpolation", &self.interpolation)
+            .finish()
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color, M: Color> Filter<T, C, U, D> for DisplacementMap<M> {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let position = |pt: Point| {
+            let d = self.map.get_pixel(pt);
+            (
+                pt.x as f64 + d[0] * self.scale,
+                pt.y as f64 + d[1] * self.scale,
+            )
+        };
+        let (x, y) = position(pt);
+        let image = input.images()[0];
+        let px = if self.interpolation == Interpolation::Ewa {
+            image.sample_ewa(x, y, jacobian(position, pt, self.map.size()))
+        } else {
+            image.sample(x, y, self.interpolation)
+        };
+        px.convert_to_data(dest);
+    }
+}
+
+/// Filter that maps each output point through a homography to a position in the input, points
+/// mapped to infinity are transparent black. Use `Interpolation::Ewa` when parts of the input
+/// are strongly minified, like the far edge of a perspective corrected document scan
+#[derive(Debug, Clone, Copy)]
+pub struct Perspective {
+    /// Mapping from output to input coordinates
+    pub homography: fit::Homography,
+
+    /// Interpolation method
+    pub interpolation: Interpolation,
+}
+
+impl Perspective {
+    /// Create a new `Perspective` filter using bilinear interpolation, `homography` maps output
+    /// points to input points
+    pub fn new(homography: fit::Homography) -> Perspective {
+        Perspective {
+            homography,
+            interpolation: Interpolation::default(),
+        }
+    }
+
+    /// Build filter using the given interpolation method
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Perspective {
+        self.interpolation = interpolation;
+        self
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Perspective {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let h = &self.homography.0;
+        let (x, y) = (pt.x as f64, pt.y as f64);
+        let w = h[2][0] * x + h[2][1] * y + h[2][2];
+        let image = input.images()[0];
+        let px = match self.homography.transform_point([x, y]) {
+            None => Pixel::new(),
+            Some([u, v]) if self.interpolation == Interpolation::Ewa => {
+                // Derivatives of the projection `(u, v) = (h0 . p, h1 . p) / (h2 . p)`
+                let j = [
+                    [(h[0][0] - u * h[2][0]) / w, (h[0][1] - u * h[2][1]) / w],
+                    [(h[1][0] - v * h[2][0]) / w, (h[1][1] - v * h[2][1]) / w],
+                ];
+                image.sample_ewa(u, v, j)
+            }
+            Some([u, v]) => image.sample(u, v, self.interpolation),
+        };
+        px.convert_to_data(dest);
+    }
+}
+
+/// Filter that samples the input at normalized texture coordinates stored in the first two
+/// channels of a UV map, `(0, 0)` is the top-left corner of the input and `(1, 1)` is the
+/// bottom-right corner. Coordinates outside of that range are handled using `wrap_mode`, for
+/// example `EdgeStrategy::Wrap` tiles the input
+pub struct UvMap<M: Color> {
+    /// UV map, the output pixel at each point is sampled at the coordinates stored at the same
+    /// point in the map
+    pub map: Image<f32, M>,
+
+    /// Determines how coordinates outside of the input are handled
+    pub wrap_mode: EdgeStrategy,
+
+    /// Interpolation method
+    pub interpolation: Interpolation,
+}
+
+impl<M: Color> UvMap<M> {
+    /// Create a new `UvMap`, the map must have at least two channels. By default coordinates
+    /// outside of the input are clamped to the nearest edge
+    pub fn new(map: Image<f32, M>) -> Result<UvMap<M>, Error> {
+        if M::CHANNELS < 2 {
+            return Err(Error::UnsupportedColor {
+                requested: "2 channels".into(),
+                found: M::NAME.into(),
+            });
+        }
+
+        Ok(UvMap {
+            map,
+            wrap_mode: EdgeStrategy::Extend,
+            interpolation: Interpolation::default(),
+        })
+    }
+
+    /// Build filter using the given wrap mode, with `EdgeStrategy::Constant` coordinates outside
+    /// of the input are transparent black
+    pub fn with_wrap_mode(mut self, wrap_mode: EdgeStrategy) -> UvMap<M> {
+        self.wrap_mode = wrap_mode;
+        self
+    }
+
+    /// Build filter using the given interpolation method
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> UvMap<M> {
+        self.interpolation = interpolation;
+        self
+    }
+}
+
+impl<M: Color> std::fmt::Debug for UvMap<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UvMap")
+            .field("size", &self.map.size())
+            .field("wrap_mode", &self.wrap_mode)
+            .field("interpolation", &self.interpolation)
+            .finish()
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color, M: Color> Filter<T, C, U, D> for UvMap<M> {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let image = input.images()[0];
+        let uv = self.map.get_pixel(pt);
+        let mut px = Pixel::<C>::new();
+        if image.width() > 0 && image.height() > 0 {
+            // Pixel centers are at integer coordinates
+            let x = uv[0] * image.width() as f64 - 0.5;
+            let y = uv[1] * image.height() as f64 - 0.5;
+            let xs = self
+                .interpolation
+                .edge_taps(x, image.width(), &self.wrap_mode);
+            let ys = self
+                .interpolation
+                .edge_taps(y, image.height(), &self.wrap_mode);
+            for (j, wy) in &ys {
+                for (i, wx) in &xs {
+                    if let (Some(i), Some(j)) = (i, j) {
+                        let data = image.get((*i, *j));
+                        for (c, v) in data.as_ref().iter().enumerate() {
+                            px[c] += v.to_norm() * wx * wy;
+                        }
+                    }
+                }
+            }
+        }
+        px.convert_to_data(dest);
+    }
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Upscale an image using the provided `Upscaler`
+    pub fn upscale(&self, size: impl Into<Size>, upscaler: &impl Upscaler<T, C>) -> Image<T, C> {
+        upscaler.upscale(self, size.into())
+    }
+
+    /// Sample the image at a subpixel position using the given interpolation method, pixel
+    /// centers are at integer coordinates and positions outside of the image are clamped to the
+    /// nearest edge
+    pub fn sample(&self, x: f64, y: f64, method: Interpolation) -> Pixel<C> {
+        let mut px = Pixel::new();
+        if self.width() == 0 || self.height() == 0 {
+            return px;
+        }
+
+        if method == Interpolation::Ewa {
+            return self.sample_ewa(x, y, [[1.0, 0.0], [0.0, 1.0]]);
+        }
+
+        let xs = method.taps(x, self.width());
+        let ys = method.taps(y, self.height());
+        for (j, wy) in &ys {
+            for (i, wx) in &xs {
+                let data = self.get((*i, *j));
+                for (c, v) in data.as_ref().iter().enumerate() {
+                    px[c] += v.to_norm() * wx * wy;
+                }
+            }
+        }
+        px
+    }
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Sample the image using an elliptical weighted average, `jacobian` holds the derivatives of
+    /// the input position with respect to the output position as `[[du/dx, du/dy], [dv/dx,
+    /// dv/dy]]` and determines the footprint of the output pixel in the input. Pixel centers are
+    /// at integer coordinates and positions outside of the image are clamped to the nearest edge
+    pub fn sample_ewa(&self, x: f64, y: f64, jacobian: [[f64; 2]; 2]) -> Pixel<C> {
+        let mut px = Pixel::new();
+        if self.width() == 0 || self.height() == 0 {
+            return px;
+        }
+
+        // Heckbert's ellipse coefficients, adding one to each axis keeps the footprint at least
+        // one pixel wide so magnified areas are still interpolated
+        let [[ux, uy], [vx, vy]] = jacobian;
+        let mut a = vx * vx + vy * vy + 1.0;
+        let mut b = -2.0 * (ux * vx + uy * vy);
+        let mut c = ux * ux + uy * uy + 1.0;
+        let f = a * c - b * b / 4.0;
+        a /= f;
+        b /= f;
+        c /= f;
+
+        // Bounding box of `a * u^2 + b * u * v + c * v^2 < 1`
+        let det = 4.0 * a * c - b * b;
+        let mut ru = 2.0 * (c / det).sqrt();
+        let mut rv = 2.0 * (a / det).sqrt();
+        let largest = ru.max(rv);
+        if largest > EWA_MAX_RADIUS {
+            let s = (largest / EWA_MAX_RADIUS).powi(2);
+            a *= s;
+            b *= s;
+            c *= s;
+            ru /= largest / EWA_MAX_RADIUS;
+            rv /= largest / EWA_MAX_RADIUS;
+        }
+
+        let clamp = |i: isize, len: usize| i.clamp(0, len as isize - 1) as usize;
+        let mut total = 0.0;
+        for j in (y - rv).ceil() as isize..=(y + rv).floor() as isize {
+            let dv = j as f64 - y;
+            for i in (x - ru).ceil() as isize..=(x + ru).floor() as isize {
+                let du = i as f64 - x;
+                let q = a * du * du + b * du * dv + c * dv * dv;
+                if q >= 1.0 {
+                    continue;
+                }
+
+                let w = (-2.0 * q).exp();
+                let data = self.get((clamp(i, self.width()), clamp(j, self.height())));
+                for (ch, v) in data.as_ref().iter().enumerate() {
+                    px[ch] += v.to_norm() * w;
+                }
+                total += w;
+            }
+        }
+
+        if total > 0.0 {
+            px.map(|v| v / total);
+        } else {
+            px = self.sample(x, y, Interpolation::Bilinear);
+        }
+        px
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{filter::*, Filter, Image, Rgb};
+    use crate::{filter::*, kernel::EdgeStrategy, transform::*, Filter, Gray, Image, Point, Rgb};
 
     #[test]
     fn test_rotate90() {
@@ -73,4 +1017,197 @@ mod test {
         resize(a.size(), a.size() * 2).eval(&[&a], &mut dest1);
         assert!(dest0 == dest1);
     }
+
+    #[test]
+    fn test_resample_transfer() {
+        let mut a = Image::<f32, Gray>::new((2, 1));
+        a.set_f((1, 0), 0, 1.0);
+
+        let gamma: Image<f32, Gray> = a.run_geometry(Resample::new(Transform::scale(0.5, 1.0)));
+        let linear: Image<f32, Gray> =
+            a.run_geometry(Resample::new(Transform::scale(0.5, 1.0)).with_transfer(Transfer::Srgb));
+        assert_eq!(linear.size(), Size::new(4, 1));
+        assert!((gamma.get_f((1, 0), 0) - 0.5).abs() < 1e-6);
+        assert!((linear.get_f((1, 0), 0) - crate::linear_to_srgb(0.5)).abs() < 1e-6);
+
+        let resized = a.resize_with_transfer((4, 1), Transfer::Srgb);
+        assert_eq!(resized.get_f((1, 0), 0), linear.get_f((1, 0), 0));
+
+        // Resize and Resample convert between colors the same way
+        let rgb: Image<f32, Rgb> = a.convert();
+        let mut resized = Image::<f32, Gray>::new((4, 1));
+        let mut resampled = resized.new_like();
+        resize(rgb.size(), resized.size()).eval(&[&rgb], &mut resized);
+        Resample::new(Transform::scale(0.5, 1.0)).eval(&[&rgb], &mut resampled);
+        assert!(resized == resampled);
+    }
+
+    #[test]
+    fn test_sample() {
+        let mut a = Image::<f32, Gray>::new((4, 4));
+        a.for_each(|pt, mut px| px[0] = pt.x as f32 / 3.0);
+
+        for method in [
+            Interpolation::Nearest,
+            Interpolation::Bilinear,
+            Interpolation::CatmullRom,
+            Interpolation::Mitchell,
+            Interpolation::Lanczos(3),
+        ] {
+            let px = a.sample(2.0, 1.0, method);
+            assert!((px[0] - a.get_f((2, 1), 0)).abs() < 0.05, "{:?}", method);
+        }
+
+        // Interpolating methods reproduce pixels exactly and ramps between them
+        let px = a.sample(1.5, 2.25, Interpolation::Bilinear);
+        assert!((px[0] - 0.5).abs() < 1e-6);
+        let px = a.sample(1.5, 2.25, Interpolation::CatmullRom);
+        assert!((px[0] - 0.5).abs() < 1e-6);
+        let px = a.sample(1.4, 0.0, Interpolation::Nearest);
+        assert_eq!(px[0], a.get_f((1, 0), 0));
+
+        // Positions outside of the image clamp to the edge
+        let px = a.sample(-3.0, 10.0, Interpolation::Bilinear);
+        assert_eq!(px[0], a.get_f((0, 3), 0));
+    }
+
+    #[test]
+    fn test_warp() {
+        let mut a = Image::<f32, Gray>::new((8, 8));
+        a.for_each(|pt, mut px| px[0] = (pt.x + pt.y * 8) as f32 / 64.0);
+
+        let flip = Warp::new(|pt: Point| (7.0 - pt.x as f64, pt.y as f64))
+            .with_interpolation(Interpolation::Nearest);
+        let b: Image<f32, Gray> = a.run(flip, None);
+        assert_eq!(b.get_f((0, 3), 0), a.get_f((7, 3), 0));
+
+        let mut map = Image::<f32, Rgb>::new(a.size());
+        map.for_each(|_, mut px| {
+            px[0] = 1.0;
+            px[1] = -0.5;
+        });
+        let displace = DisplacementMap::new(map).unwrap().with_scale(2.0);
+        let c: Image<f32, Gray> = a.run(displace, None);
+        assert_eq!(c.get_f((2, 3), 0), a.get_f((4, 2), 0));
+
+        assert!(DisplacementMap::new(Image::<f32, Gray>::new((8, 8))).is_err());
+    }
+
+    #[test]
+    fn test_uv_map() {
+        let mut a = Image::<f32, Gray>::new((8, 8));
+        a.for_each(|pt, mut px| px[0] = (pt.x + pt.y * 8) as f32 / 64.0);
+
+        // Identity map samples every pixel at its own center
+        let mut map = Image::<f32, Rgb>::new((16, 8));
+        map.for_each(|pt, mut px| {
+            px[0] = (pt.x as f32 + 0.5) / 8.0;
+            px[1] = (pt.y as f32 + 0.5) / 8.0;
+        });
+        let uv = UvMap::new(map.clone()).unwrap();
+        let mut b = Image::<f32, Gray>::new((16, 8));
+        uv.eval(&[&a], &mut b);
+        assert_eq!(b.get_f((3, 5), 0), a.get_f((3, 5), 0));
+        assert_eq!(b.get_f((12, 5), 0), a.get_f((7, 5), 0));
+
+        let uv = uv.with_wrap_mode(EdgeStrategy::Wrap);
+        uv.eval(&[&a], &mut b);
+        assert_eq!(b.get_f((12, 5), 0), a.get_f((4, 5), 0));
+
+        let uv = uv.with_wrap_mode(EdgeStrategy::Mirror);
+        uv.eval(&[&a], &mut b);
+        assert_eq!(b.get_f((12, 5), 0), a.get_f((2, 5), 0));
+
+        let uv = uv.with_wrap_mode(EdgeStrategy::Constant);
+        uv.eval(&[&a], &mut b);
+        assert_eq!(b.get_f((12, 5), 0), 0.0);
+
+        assert!(UvMap::new(Image::<f32, Gray>::new((8, 8))).is_err());
+    }
+
+    #[test]
+    fn test_upscale() {
+        let mut a = Image::<f32, Gray>::new((8, 8));
+        a.for_each(|pt, mut px| px[0] = ((pt.x + pt.y) % 2) as f32);
+
+        let b = Upscale::new(Scale2x).by(&a, 2.0);
+        assert_eq!(b.size(), Size::new(16, 16));
+        assert_eq!(b.get_f((0, 0), 0), a.get_f((0, 0), 0));
+
+        let empty = Image::<f32, Gray>::new((0, 0));
+        let c: Image<f32, Gray> = Upscaler::upscale(&Scale2x, &empty, Size::new(4, 4));
+        assert_eq!(c.size(), Size::new(4, 4));
+
+        // Every upscaler handles inputs with an empty axis
+        let upscalers: [&dyn Upscaler<f32, Gray>; 3] =
+            [&Scale2x, &Lanczos::default(), &BackProjection::default()];
+        for upscaler in upscalers {
+            for size in [Size::new(0, 4), Size::new(4, 0)] {
+                let empty = Image::<f32, Gray>::new(size);
+                let c = upscaler.upscale(&empty, Size::new(8, 8));
+                assert_eq!(c.size(), Size::new(8, 8));
+                assert!(c.data().iter().all(|x| *x == 0.0));
+            }
+        }
+
+        let mut a = Image::<f32, Gray>::new((16, 16));
+        a.for_each(|pt, mut px| px[0] = pt.x as f32 / 16.0);
+        let lanczos = a.upscale((32, 32), &Lanczos::default());
+        let bp = Upscale::default().to_size(&a, (32, 32));
+        assert_eq!(bp.size(), Size::new(32, 32));
+
+        // Back-projection should reproduce the input more closely when downscaled
+        let err = |b: &Image<f32, Gray>| {
+            let d: Image<f32, Gray> = Upscaler::upscale(&Lanczos::default(), b, a.size());
+            a.data()
+                .iter()
+                .zip(d.data())
+                .map(|(x, y)| (x - y).abs())
+                .sum::<f32>()
+        };
+        assert!(err(&bp) <= err(&lanczos));
+    }
+
+    #[test]
+    fn test_ewa() {
+        let mut a = Image::<f32, Gray>::new((64, 64));
+        a.for_each(|pt, mut px| px[0] = ((pt.x + pt.y) % 2) as f32);
+
+        // Constant images stay constant
+        let mut c = Image::<f32, Gray>::new((8, 8));
+        c.for_each(|_, mut px| px[0] = 0.25);
+        let px = c.sample_ewa(3.3, 4.7, [[5.0, 1.0], [-2.0, 3.0]]);
+        assert!((px[0] - 0.25).abs() < 1e-6);
+
+        // A 4x minified 1px checkerboard averages out, bilinear sampling aliases. Edge pixels
+        // are skipped since their footprint is clamped
+        let interior = |b: &Image<f32, Gray>| {
+            (1..15)
+                .flat_map(|y| (1..15).map(move |x| (x, y)))
+                .all(|pt| (b.get_f(pt, 0) - 0.5).abs() < 0.05)
+        };
+        let h = crate::fit::Homography([[4.0, 0.0, 0.0], [0.0, 4.0, 0.0], [0.0, 0.0, 1.0]]);
+        let mut dest = Image::<f32, Gray>::new((16, 16));
+        Perspective::new(h)
+            .with_interpolation(Interpolation::Ewa)
+            .eval(&[&a], &mut dest);
+        assert!(interior(&dest));
+        Perspective::new(h).eval(&[&a], &mut dest);
+        assert!(dest.data().iter().all(|v| *v == 0.0));
+
+        let mut warped = Image::<f32, Gray>::new((16, 16));
+        Warp::new(|pt: Point| (pt.x as f64 * 4.0, pt.y as f64 * 4.0))
+            .with_interpolation(Interpolation::Ewa)
+            .eval(&[&a], &mut warped);
+        assert!(interior(&warped));
+
+        // Points mapped to infinity are black
+        let h = crate::fit::Homography([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 0.0]]);
+        let mut dest = Image::<f32, Gray>::new((4, 4));
+        dest.for_each(|_, mut px| px[0] = 1.0);
+        Perspective::new(h)
+            .with_interpolation(Interpolation::Ewa)
+            .eval(&[&a], &mut dest);
+        assert!(dest.data().iter().all(|v| *v == 0.0));
+    }
 }
@@ -1,8 +1,57 @@
+use half::slice::HalfFloatSliceExt;
+
 use crate::*;
 
+/// Determines how arithmetic results outside of the range of a `Type` are stored
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Overflow {
+    /// Clamp to the min/max value
+    #[default]
+    Saturate,
+
+    /// Wrap around like integer overflow
+    Wrap,
+
+    /// Store the value as-is, only meaningful for floating point types
+    Unbounded,
+}
+
+impl Overflow {
+    /// Convert a normalized value to `T` using the overflow policy, integer results are rounded
+    pub fn apply<T: Type>(self, f: f64) -> T {
+        if T::is_float() {
+            let f = match self {
+                Overflow::Saturate => f.clamp(0.0, 1.0),
+                Overflow::Wrap => f.rem_euclid(1.0),
+                Overflow::Unbounded => f,
+            };
+            return T::from_norm(f);
+        }
+
+        let span = T::MAX - T::MIN;
+        let steps = (f * span).round();
+        let steps = match self {
+            Overflow::Wrap => steps.rem_euclid(span + 1.0),
+            Overflow::Saturate | Overflow::Unbounded => steps.clamp(0.0, span),
+        };
+        T::from_f64(steps + T::MIN)
+    }
+}
+
 /// Type is used to represent supported image data types
 pub trait Type:
-    'static + Unpin + Default + Clone + Copy + Sync + Send + PartialEq + PartialOrd + std::fmt::Debug
+    'static
+    + Unpin
+    + Default
+    + Clone
+    + Copy
+    + Sync
+    + Send
+    + PartialEq
+    + PartialOrd
+    + std::fmt::Debug
+    + bytemuck::Pod
 {
     /// Min value
     const MIN: f64;
@@ -13,6 +62,9 @@ pub trait Type:
     /// I/O base type
     const BASE: io::BaseType;
 
+    /// Overflow policy used by image arithmetic operators
+    const OVERFLOW: Overflow = Overflow::Saturate;
+
     /// Convert to f64
     fn to_f64(&self) -> f64;
 
@@ -63,9 +115,15 @@ pub trait Type:
         Self::normalize(self.to_f64())
     }
 
-    /// Convert to `T` from normalized float
+    /// Convert to `T` from normalized float, integer results are rounded so normalized values
+    /// convert back losslessly
     fn from_norm(f: f64) -> Self {
-        Self::from_f64(Self::denormalize(f))
+        let f = Self::denormalize(f);
+        if Self::is_float() {
+            Self::from_f64(f)
+        } else {
+            Self::from_f64(f.round())
+        }
     }
 
     #[inline]
@@ -77,7 +135,7 @@ pub trait Type:
     #[inline]
     /// Scale an f64 value to fit the range supported by `T`
     fn denormalize(f: f64) -> f64 {
-        f * Self::MAX - Self::MIN
+        f * (Self::MAX - Self::MIN) + Self::MIN
     }
 
     /// Ensure the given value is less than the max allowed and greater than or equal to the
@@ -97,6 +155,43 @@ pub trait Type:
     fn bits() -> usize {
         std::mem::size_of::<Self>() * 8
     }
+
+    /// Number of significant bits, this is the mantissa size for floating point types
+    fn precision() -> usize {
+        match Self::BASE {
+            io::BaseType::Half => 11,
+            io::BaseType::Float => 24,
+            io::BaseType::Double => 53,
+            _ => Self::bits(),
+        }
+    }
+
+    /// Convert a slice of values to normalized floats, `src` and `dest` must have the same length
+    fn to_norm_slice(src: &[Self], dest: &mut [f64]) {
+        dest.iter_mut().zip(src).for_each(|(d, x)| *d = x.to_norm());
+    }
+
+    /// Convert a slice of normalized floats to `T`, `src` and `dest` must have the same length
+    fn from_norm_slice(src: &[f64], dest: &mut [Self]) {
+        dest.iter_mut()
+            .zip(src)
+            .for_each(|(d, x)| *d = Self::from_norm(*x));
+    }
+
+    /// Convert a slice of values to normalized `f32` lanes, `src` and `dest` must have the same
+    /// length. Types with more precision than `f32` should use `to_norm_slice` instead
+    fn to_f32_slice(src: &[Self], dest: &mut [f32]) {
+        dest.iter_mut()
+            .zip(src)
+            .for_each(|(d, x)| *d = x.to_norm() as f32);
+    }
+
+    /// Convert a slice of normalized `f32` lanes to `T`, `src` and `dest` must have the same length
+    fn from_f32_slice(src: &[f32], dest: &mut [Self]) {
+        dest.iter_mut()
+            .zip(src)
+            .for_each(|(d, x)| *d = Self::from_norm(*x as f64));
+    }
 }
 
 impl Type for u8 {
@@ -215,6 +310,7 @@ impl Type for f16 {
     const MIN: f64 = 0.0;
     const MAX: f64 = 1.0;
     const BASE: io::BaseType = io::BaseType::Half;
+    const OVERFLOW: Overflow = Overflow::Unbounded;
 
     fn to_f64(&self) -> f64 {
         f16::to_f64(*self)
@@ -223,12 +319,42 @@ impl Type for f16 {
     fn from_f64(f: f64) -> Self {
         f16::from_f64(f)
     }
+
+    // The range is already `[0, 1]` so values are converted directly, slices use the hardware
+    // conversion instructions where available instead of converting one channel at a time
+
+    #[inline]
+    fn to_norm(&self) -> f64 {
+        f16::to_f64(*self)
+    }
+
+    #[inline]
+    fn from_norm(f: f64) -> Self {
+        f16::from_f64(f)
+    }
+
+    fn to_norm_slice(src: &[Self], dest: &mut [f64]) {
+        src.convert_to_f64_slice(dest);
+    }
+
+    fn from_norm_slice(src: &[f64], dest: &mut [Self]) {
+        dest.convert_from_f64_slice(src);
+    }
+
+    fn to_f32_slice(src: &[Self], dest: &mut [f32]) {
+        src.convert_to_f32_slice(dest);
+    }
+
+    fn from_f32_slice(src: &[f32], dest: &mut [Self]) {
+        dest.convert_from_f32_slice(src);
+    }
 }
 
 impl Type for f32 {
     const MIN: f64 = 0.0;
     const MAX: f64 = 1.0;
     const BASE: io::BaseType = io::BaseType::Float;
+    const OVERFLOW: Overflow = Overflow::Unbounded;
 
     fn to_f64(&self) -> f64 {
         *self as f64
@@ -237,12 +363,21 @@ impl Type for f32 {
     fn from_f64(f: f64) -> Self {
         f as Self
     }
+
+    fn to_f32_slice(src: &[Self], dest: &mut [f32]) {
+        dest.copy_from_slice(src);
+    }
+
+    fn from_f32_slice(src: &[f32], dest: &mut [Self]) {
+        dest.copy_from_slice(src);
+    }
 }
 
 impl Type for f64 {
     const MIN: f64 = 0.0;
     const MAX: f64 = 1.0;
     const BASE: io::BaseType = io::BaseType::Double;
+    const OVERFLOW: Overflow = Overflow::Unbounded;
 
     fn to_f64(&self) -> f64 {
         *self
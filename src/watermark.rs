@@ -0,0 +1,263 @@
+use crate::*;
+
+/// Placement of a visible watermark, corner positions are inset by the given margin in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Position {
+    /// Top-left corner
+    TopLeft(usize),
+
+    /// Top-right corner
+    TopRight(usize),
+
+    /// Bottom-left corner
+    BottomLeft(usize),
+
+    /// Bottom-right corner
+    BottomRight(usize),
+
+    /// Centered
+    Center,
+
+    /// Top-left corner of the watermark at the given point
+    At(Point),
+}
+
+impl Position {
+    /// Top-left corner of an item with size `item` placed in an image with size `size`, the
+    /// result may be negative when the item is larger than the image
+    pub fn origin(&self, size: Size, item: Size) -> (isize, isize) {
+        let (w, h) = (size.width as isize, size.height as isize);
+        let (iw, ih) = (item.width as isize, item.height as isize);
+        match *self {
+            Position::TopLeft(m) => (m as isize, m as isize),
+            Position::TopRight(m) => (w - iw - m as isize, m as isize),
+            Position::BottomLeft(m) => (m as isize, h - ih - m as isize),
+            Position::BottomRight(m) => (w - iw - m as isize, h - ih - m as isize),
+            Position::Center => ((w - iw) / 2, (h - ih) / 2),
+            Position::At(pt) => (pt.x as isize, pt.y as isize),
+        }
+    }
+}
+
+/// Blend `logo` onto `image` using the logo's alpha channel scaled by `opacity`, parts of the
+/// logo outside of the image are skipped
+pub fn embed_visible<T: Type, C: Color, U: Type>(
+    image: &mut Image<T, C>,
+    logo: &Image<U, Rgba>,
+    position: Position,
+    opacity: f64,
+) {
+    let (x0, y0) = position.origin(image.size(), logo.size());
+    let opacity = opacity.clamp(0.0, 1.0);
+    let mut color = Pixel::<Rgb>::new();
+    logo.each_pixel(|pt, px| {
+        let (x, y) = (x0 + pt.x as isize, y0 + pt.y as isize);
+        if x < 0 || y < 0 || !image.in_bounds((x as usize, y as usize)) {
+            return;
+        }
+        let alpha = px[3].clamp(0.0, 1.0) * opacity;
+        if alpha <= 0.0 {
+            return;
+        }
+
+        color.copy_from_slice(&px.as_ref()[..3]);
+        let src: Pixel<C> = color.convert();
+        let dest = Point::new(x as usize, y as usize);
+        let mut out = image.get_pixel(dest);
+        for c in (0..C::CHANNELS).filter(|c| C::ALPHA != Some(*c)) {
+            out[c] = out[c] * (1.0 - alpha) + src[c] * alpha;
+        }
+        image.set_pixel(dest, &out);
+    });
+}
+
+const BLOCK: usize = 8;
+
+/// DCT coefficient used to carry each bit, a mid frequency survives mild compression and
+/// resizing without being visible
+const COEFFICIENT: (usize, usize) = (2, 3);
+
+/// Quantization step of the carrier coefficient
+const STEP: f64 = 0.04;
+
+/// Number of times each header bit is repeated
+const HEADER_REPEAT: usize = 4;
+
+/// Payload length and checksum
+const HEADER_BITS: usize = 32;
+
+/// Orthonormal DCT-II basis function
+fn basis(u: usize, x: usize) -> f64 {
+    let a = if u == 0 {
+        (1.0 / BLOCK as f64).sqrt()
+    } else {
+        (2.0 / BLOCK as f64).sqrt()
+    };
+    a * ((2 * x + 1) as f64 * u as f64 * std::f64::consts::PI / (2 * BLOCK) as f64).cos()
+}
+
+fn splitmix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Block origins in the order derived from `key`
+fn block_order(size: Size, key: u64) -> Vec<Point> {
+    let mut blocks: Vec<Point> = (0..size.height / BLOCK)
+        .flat_map(|by| (0..size.width / BLOCK).map(move |bx| Point::new(bx * BLOCK, by * BLOCK)))
+        .collect();
+    let mut state = key;
+    for i in (1..blocks.len()).rev() {
+        let j = (splitmix(&mut state) % (i as u64 + 1)) as usize;
+        blocks.swap(i, j);
+    }
+    blocks
+}
+
+fn checksum(payload: &[u8]) -> u16 {
+    let (a, b) = payload.iter().fold((0u16, 0u16), |(a, b), x| {
+        let a = (a + *x as u16) % 255;
+        (a, (b + a) % 255)
+    });
+    (b << 8) | a
+}
+
+/// Carrier coefficient of the mean of the color channels in a block
+fn coefficient<T: Type, C: Color>(image: &Image<T, C>, origin: Point) -> f64 {
+    let (u, v) = COEFFICIENT;
+    let channels = (0..C::CHANNELS)
+        .filter(|c| C::ALPHA != Some(*c))
+        .collect::<Vec<_>>();
+    let mut sum = 0.0;
+    for y in 0..BLOCK {
+        for x in 0..BLOCK {
+            let data = image.get((origin.x + x, origin.y + y));
+            let value =
+                channels.iter().map(|c| data[*c].to_norm()).sum::<f64>() / channels.len() as f64;
+            sum += value * basis(u, x) * basis(v, y);
+        }
+    }
+    sum
+}
+
+/// Quantize the carrier coefficient of a block onto the lattice for `bit`
+fn embed_bit<T: Type, C: Color>(image: &mut Image<T, C>, origin: Point, bit: bool) {
+    let (u, v) = COEFFICIENT;
+    let offset = if bit { STEP / 2.0 } else { 0.0 };
+    let current = coefficient(image, origin);
+    let target = ((current - offset) / STEP).round() * STEP + offset;
+    let delta = target - current;
+    for y in 0..BLOCK {
+        for x in 0..BLOCK {
+            let pt = Point::new(origin.x + x, origin.y + y);
+            let d = delta * basis(u, x) * basis(v, y);
+            for c in (0..C::CHANNELS).filter(|c| C::ALPHA != Some(*c)) {
+                image.set_f(pt, c, image.get_f(pt, c) + d);
+            }
+        }
+    }
+}
+
+fn extract_bit<T: Type, C: Color>(image: &Image<T, C>, origin: Point) -> bool {
+    let x = coefficient(image, origin) / STEP;
+    let d = x - x.floor();
+    (0.25..0.75).contains(&d)
+}
+
+fn to_bits(bytes: &[u8]) -> Vec<bool> {
+    bytes
+        .iter()
+        .flat_map(|b| (0..8).map(move |i| (b >> (7 - i)) & 1 == 1))
+        .collect()
+}
+
+fn from_bits(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|c| c.iter().fold(0u8, |acc, b| (acc << 1) | *b as u8))
+        .collect()
+}
+
+/// Majority vote of the blocks carrying each bit, block `i` carries bit `i % n`
+fn vote<T: Type, C: Color>(image: &Image<T, C>, blocks: &[Point], n: usize) -> Vec<bool> {
+    let mut votes = vec![0isize; n];
+    for (i, origin) in blocks.iter().enumerate() {
+        votes[i % n] += if extract_bit(image, *origin) { 1 } else { -1 };
+    }
+    votes.into_iter().map(|v| v > 0).collect()
+}
+
+/// Number of payload bytes that can be embedded in an image with the given size
+pub fn capacity(size: impl Into<Size>) -> usize {
+    let size = size.into();
+    let blocks = (size.width / BLOCK) * (size.height / BLOCK);
+    (blocks.saturating_sub(HEADER_BITS * HEADER_REPEAT) / 8).min(u16::MAX as usize)
+}
+
+/// Hide `payload` in the mid-frequency DCT coefficients of 8x8 blocks, the blocks are shuffled
+/// using `key` and each bit is repeated across as many blocks as possible so the mark survives
+/// 8-bit quantization and mild edits. The changes are spread evenly across the color channels,
+/// alpha is left unchanged
+pub fn embed_invisible<T: Type, C: Color>(
+    image: &mut Image<T, C>,
+    payload: &[u8],
+    key: u64,
+) -> Result<(), Error> {
+    let blocks = block_order(image.size(), key);
+    if blocks.len() < HEADER_BITS * HEADER_REPEAT + payload.len() * 8
+        || payload.len() > capacity(image.size())
+    {
+        return Err(Error::Message(format!(
+            "payload of {} bytes exceeds watermark capacity of {} bytes",
+            payload.len(),
+            capacity(image.size())
+        )));
+    }
+
+    let (header, data) = blocks.split_at(HEADER_BITS * HEADER_REPEAT);
+    let mut header_bytes = (payload.len() as u16).to_be_bytes().to_vec();
+    header_bytes.extend_from_slice(&checksum(payload).to_be_bytes());
+    let header_bits = to_bits(&header_bytes);
+    for (i, origin) in header.iter().enumerate() {
+        embed_bit(image, *origin, header_bits[i % HEADER_BITS]);
+    }
+
+    let bits = to_bits(payload);
+    if !bits.is_empty() {
+        for (i, origin) in data.iter().enumerate() {
+            embed_bit(image, *origin, bits[i % bits.len()]);
+        }
+    }
+    Ok(())
+}
+
+/// Recover a payload embedded using `embed_invisible` with the same `key`, returns an error when
+/// no valid watermark is found
+pub fn extract<T: Type, C: Color>(image: &Image<T, C>, key: u64) -> Result<Vec<u8>, Error> {
+    let not_found = || Error::Message("no watermark found".into());
+    let blocks = block_order(image.size(), key);
+    if blocks.len() < HEADER_BITS * HEADER_REPEAT {
+        return Err(not_found());
+    }
+
+    let (header, data) = blocks.split_at(HEADER_BITS * HEADER_REPEAT);
+    let header = from_bits(&vote(image, header, HEADER_BITS));
+    let len = u16::from_be_bytes([header[0], header[1]]) as usize;
+    let sum = u16::from_be_bytes([header[2], header[3]]);
+    if len > capacity(image.size()) {
+        return Err(not_found());
+    }
+
+    let payload = if len == 0 {
+        Vec::new()
+    } else {
+        from_bits(&vote(image, data, len * 8))
+    };
+    if checksum(&payload) != sum {
+        return Err(not_found());
+    }
+    Ok(payload)
+}
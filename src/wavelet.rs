@@ -0,0 +1,384 @@
+use crate::*;
+
+/// Orthogonal wavelet families
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Wavelet {
+    /// Haar wavelet, 2 taps
+    #[default]
+    Haar,
+
+    /// Daubechies wavelet with 2 vanishing moments, 4 taps
+    Daubechies2,
+
+    /// Daubechies wavelet with 4 vanishing moments, 8 taps
+    Daubechies4,
+}
+
+const HAAR: [f64; 2] = [
+    std::f64::consts::FRAC_1_SQRT_2,
+    std::f64::consts::FRAC_1_SQRT_2,
+];
+
+const DB2: [f64; 4] = [
+    0.4829629131445341,
+    0.8365163037378079,
+    0.2241438680420134,
+    -0.12940952255126037,
+];
+
+const DB4: [f64; 8] = [
+    0.23037781330885523,
+    0.7148465705525415,
+    0.6308807679295904,
+    -0.02798376941698385,
+    -0.18703481171888114,
+    0.030841381835986965,
+    0.032883011666982945,
+    -0.010597401784997278,
+];
+
+impl Wavelet {
+    /// Low-pass decomposition filter
+    pub fn low_pass(&self) -> &'static [f64] {
+        match self {
+            Wavelet::Haar => &HAAR,
+            Wavelet::Daubechies2 => &DB2,
+            Wavelet::Daubechies4 => &DB4,
+        }
+    }
+
+    /// High-pass decomposition filter, the quadrature mirror of `low_pass`
+    pub fn high_pass(&self) -> Vec<f64> {
+        let h = self.low_pass();
+        (0..h.len())
+            .map(|n| {
+                let x = h[h.len() - 1 - n];
+                if n % 2 == 0 {
+                    x
+                } else {
+                    -x
+                }
+            })
+            .collect()
+    }
+
+    /// Single level 1-D transform of `x` with periodic extension, `x` must have an even length
+    /// and `low` and `high` must each be half as long
+    fn analyze(&self, x: &[f64], low: &mut [f64], high: &mut [f64], g: &[f64]) {
+        let h = self.low_pass();
+        let n = x.len();
+        for k in 0..n / 2 {
+            let (mut a, mut d) = (0.0, 0.0);
+            for (i, (hi, gi)) in h.iter().zip(g).enumerate() {
+                let v = x[(2 * k + i) % n];
+                a += hi * v;
+                d += gi * v;
+            }
+            low[k] = a;
+            high[k] = d;
+        }
+    }
+
+    /// Inverse of `analyze`
+    fn synthesize(&self, low: &[f64], high: &[f64], x: &mut [f64], g: &[f64]) {
+        let h = self.low_pass();
+        let n = x.len();
+        x.fill(0.0);
+        for k in 0..n / 2 {
+            for (i, (hi, gi)) in h.iter().zip(g).enumerate() {
+                x[(2 * k + i) % n] += hi * low[k] + gi * high[k];
+            }
+        }
+    }
+}
+
+/// Detail coefficients of a single decomposition level
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Detail {
+    /// Width of the input to this level
+    pub width: usize,
+
+    /// Height of the input to this level
+    pub height: usize,
+
+    /// Low-pass horizontally, high-pass vertically, responds to horizontal edges
+    pub horizontal: Vec<f64>,
+
+    /// High-pass horizontally, low-pass vertically, responds to vertical edges
+    pub vertical: Vec<f64>,
+
+    /// High-pass in both directions
+    pub diagonal: Vec<f64>,
+}
+
+impl Detail {
+    /// Width of each subband
+    pub fn band_width(&self) -> usize {
+        self.width.div_ceil(2)
+    }
+
+    /// Height of each subband
+    pub fn band_height(&self) -> usize {
+        self.height.div_ceil(2)
+    }
+
+    /// Iterate over all detail coefficients
+    pub fn coefficients_mut(&mut self) -> impl Iterator<Item = &mut f64> {
+        self.horizontal
+            .iter_mut()
+            .chain(self.vertical.iter_mut())
+            .chain(self.diagonal.iter_mut())
+    }
+}
+
+/// Multi-level 2-D wavelet decomposition of a single channel, see `dwt2`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Decomposition {
+    /// Wavelet used
+    pub wavelet: Wavelet,
+
+    /// Approximation coefficients of the coarsest level
+    pub approximation: Vec<f64>,
+
+    /// Detail coefficients, from the finest level to the coarsest
+    pub details: Vec<Detail>,
+}
+
+impl Decomposition {
+    /// Estimate the standard deviation of additive Gaussian noise from the median absolute
+    /// deviation of the finest diagonal coefficients
+    pub fn estimate_noise(&self) -> f64 {
+        let mut d: Vec<f64> = match self.details.first() {
+            Some(detail) => detail.diagonal.iter().map(|x| x.abs()).collect(),
+            None => return 0.0,
+        };
+        if d.is_empty() {
+            return 0.0;
+        }
+        let mid = d.len() / 2;
+        let (_, median, _) = d.select_nth_unstable_by(mid, f64::total_cmp);
+        *median / 0.6745
+    }
+
+    /// Shrink every detail coefficient towards zero by `threshold`
+    pub fn soft_threshold(&mut self, threshold: f64) {
+        for detail in &mut self.details {
+            detail.coefficients_mut().for_each(|x| shrink(x, threshold));
+        }
+    }
+
+    /// Soft threshold each subband using the BayesShrink threshold for noise with standard
+    /// deviation `sigma`, which adapts to the amount of signal in each subband
+    pub fn bayes_shrink(&mut self, sigma: f64) {
+        let noise = sigma * sigma;
+        for detail in &mut self.details {
+            for band in [
+                &mut detail.horizontal,
+                &mut detail.vertical,
+                &mut detail.diagonal,
+            ] {
+                if band.is_empty() {
+                    continue;
+                }
+                let variance = band.iter().map(|x| x * x).sum::<f64>() / band.len() as f64;
+                let signal = (variance - noise).max(0.0).sqrt();
+                let threshold = if signal > 0.0 {
+                    noise / signal
+                } else {
+                    band.iter().fold(0.0f64, |a, x| a.max(x.abs()))
+                };
+                band.iter_mut().for_each(|x| shrink(x, threshold));
+            }
+        }
+    }
+}
+
+fn shrink(x: &mut f64, threshold: f64) {
+    *x = x.signum() * (x.abs() - threshold).max(0.0);
+}
+
+/// Single level 2-D transform, odd dimensions are padded by repeating the last row or column
+fn forward_level(
+    data: &[f64],
+    width: usize,
+    height: usize,
+    wavelet: Wavelet,
+) -> (Vec<f64>, Detail) {
+    let (bw, bh) = (width.div_ceil(2), height.div_ceil(2));
+    let (pw, ph) = (bw * 2, bh * 2);
+    let g = wavelet.high_pass();
+
+    // Rows, the low-pass half is stored on the left
+    let mut rows = vec![0.0; pw * ph];
+    let mut line = vec![0.0; pw];
+    for y in 0..ph {
+        let src = &data[y.min(height - 1) * width..][..width];
+        line[..width].copy_from_slice(src);
+        if pw > width {
+            line[width] = src[width - 1];
+        }
+        let (low, high) = rows[y * pw..][..pw].split_at_mut(bw);
+        wavelet.analyze(&line, low, high, &g);
+    }
+
+    // Columns, the low-pass half is stored on the top
+    let mut out = vec![0.0; pw * ph];
+    let mut column = vec![0.0; ph];
+    let (mut low, mut high) = (vec![0.0; bh], vec![0.0; bh]);
+    for x in 0..pw {
+        for y in 0..ph {
+            column[y] = rows[y * pw + x];
+        }
+        wavelet.analyze(&column, &mut low, &mut high, &g);
+        for y in 0..bh {
+            out[y * pw + x] = low[y];
+            out[(y + bh) * pw + x] = high[y];
+        }
+    }
+
+    let band = |x0: usize, y0: usize| -> Vec<f64> {
+        (0..bh)
+            .flat_map(|y| out[(y0 + y) * pw + x0..][..bw].iter().copied())
+            .collect()
+    };
+    let detail = Detail {
+        width,
+        height,
+        horizontal: band(0, bh),
+        vertical: band(bw, 0),
+        diagonal: band(bw, bh),
+    };
+    (band(0, 0), detail)
+}
+
+/// Inverse of `forward_level`
+fn inverse_level(approximation: &[f64], detail: &Detail, wavelet: Wavelet) -> Vec<f64> {
+    let (bw, bh) = (detail.band_width(), detail.band_height());
+    let (pw, ph) = (bw * 2, bh * 2);
+    let g = wavelet.high_pass();
+
+    // Columns
+    let mut rows = vec![0.0; pw * ph];
+    let mut column = vec![0.0; ph];
+    let (mut low, mut high) = (vec![0.0; bh], vec![0.0; bh]);
+    for x in 0..pw {
+        let (top, bottom) = if x < bw {
+            (approximation, &detail.horizontal[..])
+        } else {
+            (&detail.vertical[..], &detail.diagonal[..])
+        };
+        let bx = x % bw;
+        for y in 0..bh {
+            low[y] = top[y * bw + bx];
+            high[y] = bottom[y * bw + bx];
+        }
+        wavelet.synthesize(&low, &high, &mut column, &g);
+        for y in 0..ph {
+            rows[y * pw + x] = column[y];
+        }
+    }
+
+    // Rows, cropping the padding
+    let mut out = vec![0.0; detail.width * detail.height];
+    let mut line = vec![0.0; pw];
+    for y in 0..detail.height {
+        let (low, high) = rows[y * pw..][..pw].split_at(bw);
+        wavelet.synthesize(low, high, &mut line, &g);
+        out[y * detail.width..][..detail.width].copy_from_slice(&line[..detail.width]);
+    }
+    out
+}
+
+/// Multi-level 2-D discrete wavelet transform of a single channel stored in row-major order,
+/// decomposition stops early once a level would be smaller than a single pixel
+pub fn dwt2(
+    data: &[f64],
+    width: usize,
+    height: usize,
+    wavelet: Wavelet,
+    levels: usize,
+) -> Decomposition {
+    let mut approximation = data[..width * height].to_vec();
+    let mut details = Vec::with_capacity(levels);
+    let (mut w, mut h) = (width, height);
+    while details.len() < levels && w > 1 && h > 1 {
+        let (a, detail) = forward_level(&approximation, w, h, wavelet);
+        (w, h) = (detail.band_width(), detail.band_height());
+        approximation = a;
+        details.push(detail);
+    }
+
+    Decomposition {
+        wavelet,
+        approximation,
+        details,
+    }
+}
+
+/// Inverse of `dwt2`, returns the reconstructed channel in row-major order
+pub fn idwt2(decomposition: &Decomposition) -> Vec<f64> {
+    decomposition
+        .details
+        .iter()
+        .rev()
+        .fold(decomposition.approximation.clone(), |a, detail| {
+            inverse_level(&a, detail, decomposition.wavelet)
+        })
+}
+
+/// Decompose each channel of an image, values are normalized
+pub fn forward<T: Type, C: Color>(
+    image: &Image<T, C>,
+    wavelet: Wavelet,
+    levels: usize,
+) -> Vec<Decomposition> {
+    let (w, h) = (image.width(), image.height());
+    let mut channel = vec![0.0; w * h];
+    (0..C::CHANNELS)
+        .map(|c| {
+            channel
+                .iter_mut()
+                .zip(image.data().iter().skip(c).step_by(C::CHANNELS))
+                .for_each(|(d, x)| *d = x.to_norm());
+            dwt2(&channel, w, h, wavelet, levels)
+        })
+        .collect()
+}
+
+/// Reconstruct an image from per-channel decompositions created by `forward`
+pub fn inverse<T: Type, C: Color>(
+    size: impl Into<Size>,
+    channels: &[Decomposition],
+) -> Result<Image<T, C>, Error> {
+    let size = size.into();
+    if channels.len() != C::CHANNELS {
+        return Err(Error::InvalidDimensions(
+            size.width,
+            size.height,
+            channels.len(),
+        ));
+    }
+
+    let mut image = Image::<T, C>::new(size);
+    for (c, decomposition) in channels.iter().enumerate() {
+        let data = idwt2(decomposition);
+        if data.len() != size.width * size.height {
+            return Err(Error::InvalidDimensions(
+                size.width,
+                size.height,
+                C::CHANNELS,
+            ));
+        }
+        image
+            .data_mut()
+            .iter_mut()
+            .skip(c)
+            .step_by(C::CHANNELS)
+            .zip(data)
+            .for_each(|(d, x)| d.set_from_norm(x));
+    }
+    Ok(image)
+}
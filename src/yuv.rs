@@ -0,0 +1,243 @@
+use crate::*;
+
+/// Planar and packed YUV layouts, all samples are 8-bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Format {
+    /// 4:2:0, full Y plane followed by quarter resolution U and V planes
+    I420,
+
+    /// 4:2:0, full Y plane followed by a quarter resolution plane of interleaved U and V
+    Nv12,
+
+    /// 4:2:2, packed `Y0 U Y1 V` for every two pixels
+    Yuyv,
+}
+
+impl Format {
+    /// Number of bytes in a frame of the given size
+    pub fn frame_len(&self, size: impl Into<Size>) -> usize {
+        let size = size.into();
+        let (cw, ch) = (size.width.div_ceil(2), size.height.div_ceil(2));
+        match self {
+            Format::I420 | Format::Nv12 => size.width * size.height + 2 * cw * ch,
+            Format::Yuyv => 4 * cw * size.height,
+        }
+    }
+}
+
+/// RGB to YUV matrix
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Matrix {
+    /// ITU-R BT.601, used for standard definition video
+    Bt601,
+
+    /// ITU-R BT.709, used for high definition video
+    #[default]
+    Bt709,
+}
+
+impl Matrix {
+    /// Red and blue luma coefficients
+    fn coefficients(&self) -> (f64, f64) {
+        match self {
+            Matrix::Bt601 => (0.299, 0.114),
+            Matrix::Bt709 => (0.2126, 0.0722),
+        }
+    }
+}
+
+/// Range of encoded values
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Range {
+    /// Luma between 16 and 235, chroma between 16 and 240, this is the default for video
+    #[default]
+    Limited,
+
+    /// Luma and chroma between 0 and 255, used by JPEG and some cameras
+    Full,
+}
+
+/// Matrix and range used to encode a frame
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Encoding {
+    /// Color matrix
+    pub matrix: Matrix,
+
+    /// Value range
+    pub range: Range,
+}
+
+impl Encoding {
+    /// Create a new `Encoding`
+    pub fn new(matrix: Matrix, range: Range) -> Encoding {
+        Encoding { matrix, range }
+    }
+
+    /// Normalized RGB to encoded `(y, u, v)`
+    fn encode_pixel(&self, r: f64, g: f64, b: f64) -> (f64, f64, f64) {
+        let (kr, kb) = self.matrix.coefficients();
+        let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+        let u = (b - y) / (2.0 * (1.0 - kb));
+        let v = (r - y) / (2.0 * (1.0 - kr));
+        match self.range {
+            Range::Limited => (16.0 + 219.0 * y, 128.0 + 224.0 * u, 128.0 + 224.0 * v),
+            Range::Full => (255.0 * y, 128.0 + 255.0 * u, 128.0 + 255.0 * v),
+        }
+    }
+
+    /// Encoded `(y, u, v)` to normalized RGB
+    fn decode_pixel(&self, y: f64, u: f64, v: f64) -> (f64, f64, f64) {
+        let (y, u, v) = match self.range {
+            Range::Limited => ((y - 16.0) / 219.0, (u - 128.0) / 224.0, (v - 128.0) / 224.0),
+            Range::Full => (y / 255.0, (u - 128.0) / 255.0, (v - 128.0) / 255.0),
+        };
+        let (kr, kb) = self.matrix.coefficients();
+        let r = y + 2.0 * (1.0 - kr) * v;
+        let b = y + 2.0 * (1.0 - kb) * u;
+        let g = (y - kr * r - kb * b) / (1.0 - kr - kb);
+        (r, g, b)
+    }
+}
+
+fn to_u8(x: f64) -> u8 {
+    x.round().clamp(0.0, 255.0) as u8
+}
+
+/// Sample a subsampled chroma plane at full resolution using bilinear interpolation, chroma
+/// samples are centered between the luma samples they cover
+fn upsample(plane: &[f64], cw: usize, ch: usize, sx: usize, sy: usize, x: usize, y: usize) -> f64 {
+    let pos = |p: usize, s: usize, n: usize| {
+        let f = ((p as f64 + 0.5) / s as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+        let i = (f.floor() as usize).min(n.saturating_sub(2));
+        (i, (i + 1).min(n - 1), f - i as f64)
+    };
+    let (x0, x1, fx) = pos(x, sx, cw);
+    let (y0, y1, fy) = pos(y, sy, ch);
+    let at = |x: usize, y: usize| plane[y * cw + x];
+    let top = at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx;
+    let bottom = at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+/// Decode a YUV frame to RGB
+pub fn decode(
+    data: &[u8],
+    size: impl Into<Size>,
+    format: Format,
+    encoding: Encoding,
+) -> Result<Image<u8, Rgb>, Error> {
+    let size = size.into();
+    let (w, h) = (size.width, size.height);
+    if data.len() < format.frame_len(size) {
+        return Err(Error::InvalidDimensions(w, h, 3));
+    }
+
+    let (sy, cw, ch) = match format {
+        Format::I420 | Format::Nv12 => (2, w.div_ceil(2), h.div_ceil(2)),
+        Format::Yuyv => (1, w.div_ceil(2), h),
+    };
+    let mut luma = vec![0.0; w * h];
+    let mut u = vec![0.0; cw * ch];
+    let mut v = vec![0.0; cw * ch];
+    match format {
+        Format::I420 => {
+            let (y_plane, rest) = data.split_at(w * h);
+            luma.iter_mut()
+                .zip(y_plane)
+                .for_each(|(a, b)| *a = *b as f64);
+            u.iter_mut().zip(rest).for_each(|(a, b)| *a = *b as f64);
+            v.iter_mut()
+                .zip(&rest[cw * ch..])
+                .for_each(|(a, b)| *a = *b as f64);
+        }
+        Format::Nv12 => {
+            let (y_plane, rest) = data.split_at(w * h);
+            luma.iter_mut()
+                .zip(y_plane)
+                .for_each(|(a, b)| *a = *b as f64);
+            for (i, uv) in rest.chunks_exact(2).take(cw * ch).enumerate() {
+                u[i] = uv[0] as f64;
+                v[i] = uv[1] as f64;
+            }
+        }
+        Format::Yuyv => {
+            for y in 0..h {
+                for x in 0..cw {
+                    let px = &data[(y * cw + x) * 4..][..4];
+                    luma[y * w + 2 * x] = px[0] as f64;
+                    if 2 * x + 1 < w {
+                        luma[y * w + 2 * x + 1] = px[2] as f64;
+                    }
+                    u[y * cw + x] = px[1] as f64;
+                    v[y * cw + x] = px[3] as f64;
+                }
+            }
+        }
+    }
+
+    let mut image = Image::new(size);
+    image.for_each(|pt, mut px| {
+        let cu = upsample(&u, cw, ch, 2, sy, pt.x, pt.y);
+        let cv = upsample(&v, cw, ch, 2, sy, pt.x, pt.y);
+        let (r, g, b) = encoding.decode_pixel(luma[pt.y * w + pt.x], cu, cv);
+        px[0] = to_u8(r * 255.0);
+        px[1] = to_u8(g * 255.0);
+        px[2] = to_u8(b * 255.0);
+    });
+    Ok(image)
+}
+
+/// Encode an RGB image as a YUV frame, chroma is averaged over each block of subsampled pixels
+pub fn encode(image: &Image<u8, Rgb>, format: Format, encoding: Encoding) -> Vec<u8> {
+    let (w, h) = (image.width(), image.height());
+    let (sy, cw, ch) = match format {
+        Format::I420 | Format::Nv12 => (2, w.div_ceil(2), h.div_ceil(2)),
+        Format::Yuyv => (1, w.div_ceil(2), h),
+    };
+
+    let mut luma = vec![0u8; w * h];
+    let mut u = vec![0.0; cw * ch];
+    let mut v = vec![0.0; cw * ch];
+    let mut count = vec![0usize; cw * ch];
+    image.each_pixel(|pt, px| {
+        let (y, cu, cv) = encoding.encode_pixel(px[0], px[1], px[2]);
+        luma[pt.y * w + pt.x] = to_u8(y);
+        let i = (pt.y / sy) * cw + pt.x / 2;
+        u[i] += cu;
+        v[i] += cv;
+        count[i] += 1;
+    });
+    let chroma = |plane: &[f64], i: usize| to_u8(plane[i] / count[i].max(1) as f64);
+
+    let mut data = Vec::with_capacity(format.frame_len(image.size()));
+    match format {
+        Format::I420 => {
+            data.extend_from_slice(&luma);
+            data.extend((0..cw * ch).map(|i| chroma(&u, i)));
+            data.extend((0..cw * ch).map(|i| chroma(&v, i)));
+        }
+        Format::Nv12 => {
+            data.extend_from_slice(&luma);
+            data.extend((0..cw * ch).flat_map(|i| [chroma(&u, i), chroma(&v, i)]));
+        }
+        Format::Yuyv => {
+            for y in 0..h {
+                for x in 0..cw {
+                    let i = y * cw + x;
+                    let y0 = luma[y * w + 2 * x];
+                    let y1 = if 2 * x + 1 < w {
+                        luma[y * w + 2 * x + 1]
+                    } else {
+                        y0
+                    };
+                    data.extend([y0, chroma(&u, i), y1, chroma(&v, i)]);
+                }
+            }
+        }
+    }
+    data
+}